@@ -0,0 +1,56 @@
+// Benchmarks the allocation path of the buffer pool, comparing |new_page| with
+// |new_page_uninit| when every new page is overwritten right away, like a bulk
+// load does. Run with |cargo bench|.
+
+use db::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use db::common::config::PAGE_SIZE;
+use db::disk::disk_manager::BITMAP_FILE_SUFFIX;
+use db::page::page::Page;
+use db::page::table_page::TablePage;
+use std::time::Duration;
+use std::time::Instant;
+
+const POOL_SIZE: usize = 4096;
+const ROUNDS: usize = 10;
+
+fn bench<F>(name: &str, file_path: &str, new_page: F)
+where
+    F: Fn(&mut DefaultBufferPoolManager<TablePage>) -> &mut TablePage,
+{
+    let content = [7; PAGE_SIZE];
+    let mut total = Duration::default();
+    for _ in 0..ROUNDS {
+        let mut bpm = DefaultBufferPoolManager::<TablePage>::new(POOL_SIZE, file_path).unwrap();
+        // Cycles every frame once, so that frames hold stale bytes.
+        for _ in 0..POOL_SIZE {
+            let page_id = new_page(&mut bpm).page_id();
+            bpm.unpin_page(page_id, /*is_dirty=*/ false).unwrap();
+            bpm.delete_page(page_id).unwrap();
+        }
+
+        let start = Instant::now();
+        for _ in 0..POOL_SIZE {
+            let page = new_page(&mut bpm);
+            page.data_mut().copy_from_slice(&content);
+        }
+        total += start.elapsed();
+
+        drop(bpm);
+        std::fs::remove_file(file_path).unwrap();
+        std::fs::remove_file(file_path.to_string() + BITMAP_FILE_SUFFIX).unwrap();
+    }
+    println!(
+        "{}: {:?} per page",
+        name,
+        total / (ROUNDS * POOL_SIZE) as u32
+    );
+}
+
+fn main() {
+    bench("new_page", "/tmp/benchfile.new_page.1.db", |bpm| {
+        bpm.new_page().unwrap()
+    });
+    bench("new_page_uninit", "/tmp/benchfile.new_page.2.db", |bpm| {
+        bpm.new_page_uninit().unwrap()
+    });
+}
@@ -0,0 +1,34 @@
+// Prints the catalog, the allocation bitmap summary and the page summaries of
+// a database file, followed by the layout and hex dump of the given pages.
+//
+// Usage: rsdb-inspect <db_file> [page_id ...]
+
+use db::common::config::PageId;
+use db::tools::inspector::Inspector;
+use std::env;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <db_file> [page_id ...]", args[0]);
+        process::exit(2);
+    }
+    let mut page_ids = Vec::new();
+    for arg in args[2..].iter() {
+        match arg.parse::<PageId>() {
+            Ok(page_id) => page_ids.push(page_id),
+            Err(_) => {
+                eprintln!("Invalid page ID: {}", arg);
+                process::exit(2);
+            }
+        }
+    }
+    match Inspector::open(&args[1]).and_then(|mut inspector| inspector.report(&page_ids)) {
+        Ok(report) => print!("{}", report),
+        Err(e) => {
+            eprintln!("Cannot inspect {}: {}", args[1], e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+// Opens a database file, creating it if needed, and executes the statements
+// read from the standard input one line at a time; see |Shell| for the
+// command language. Type .exit or .quit, or send EOF, to close the database.
+//
+// Usage: rsdb <db_file>
+
+use db::tools::shell::Shell;
+use std::env;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        eprintln!("Usage: {} <db_file>", args[0]);
+        process::exit(2);
+    }
+    let mut shell = match Shell::open(&args[1]) {
+        Ok(shell) => shell,
+        Err(e) => {
+            eprintln!("Cannot open {}: {}", args[1], e);
+            process::exit(1);
+        }
+    };
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("rsdb> ");
+        io::stdout().flush().unwrap();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(e)) => {
+                eprintln!("Error: {}", e);
+                break;
+            }
+            None => {
+                println!();
+                break;
+            }
+        };
+        match line.trim() {
+            ".exit" | ".quit" => break,
+            line => match shell.execute(line) {
+                Ok(output) => print!("{}", output),
+                Err(e) => eprintln!("Error: {}", e),
+            },
+        }
+    }
+    if let Err(e) = shell.close() {
+        eprintln!("Cannot close {}: {}", args[1], e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,154 @@
+// Functionality: Per-page read/write counters for heat maps. To bound the
+// overhead, only one out of every |sample_rate| accesses is recorded, weighted
+// by |sample_rate|, so counts are estimates unless the rate is 1.
+
+use crate::common::config::PageId;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PageHeat {
+    pub page_id: PageId,
+    // The estimated number of fetches.
+    pub reads: u64,
+    // The estimated number of dirty unpins.
+    pub writes: u64,
+}
+
+impl PageHeat {
+    pub fn accesses(&self) -> u64 {
+        self.reads + self.writes
+    }
+}
+
+pub struct AccessStats {
+    // 0 disables tracking.
+    sample_rate: u64,
+    // Whether accesses go unrecorded for now, whatever the rate.
+    paused: bool,
+    tick: u64,
+    pages: HashMap<PageId, PageHeat>,
+}
+
+impl AccessStats {
+    pub fn new(sample_rate: u64) -> Self {
+        AccessStats {
+            sample_rate,
+            paused: false,
+            tick: 0,
+            pages: HashMap::new(),
+        }
+    }
+
+    // Changing the rate restarts tracking from scratch.
+    pub fn set_sample_rate(&mut self, sample_rate: u64) {
+        self.sample_rate = sample_rate;
+        self.tick = 0;
+        self.pages.clear();
+    }
+
+    // Pauses or resumes recording, keeping the counters. Returns whether it was
+    // paused.
+    pub fn set_paused(&mut self, paused: bool) -> bool {
+        std::mem::replace(&mut self.paused, paused)
+    }
+
+    pub fn record_read(&mut self, page_id: PageId) {
+        if let Some(weight) = self.sample() {
+            self.entry(page_id).reads += weight;
+        }
+    }
+
+    pub fn record_write(&mut self, page_id: PageId) {
+        if let Some(weight) = self.sample() {
+            self.entry(page_id).writes += weight;
+        }
+    }
+
+    pub fn remove(&mut self, page_id: PageId) {
+        self.pages.remove(&page_id);
+    }
+
+    // Returns up to |n| pages with the most accesses, hottest first.
+    pub fn hottest(&self, n: usize) -> Vec<PageHeat> {
+        let mut pages: Vec<PageHeat> = self.pages.values().cloned().collect();
+        pages.sort_by(|a, b| {
+            b.accesses()
+                .cmp(&a.accesses())
+                .then(a.page_id.cmp(&b.page_id))
+        });
+        pages.truncate(n);
+        pages
+    }
+
+    // Returns the weight of the current access if it is sampled.
+    fn sample(&mut self) -> Option<u64> {
+        if self.sample_rate == 0 || self.paused {
+            return None;
+        }
+        self.tick += 1;
+        match self.tick % self.sample_rate {
+            0 => Some(self.sample_rate),
+            _ => None,
+        }
+    }
+
+    fn entry(&mut self, page_id: PageId) -> &mut PageHeat {
+        self.pages.entry(page_id).or_insert_with(|| PageHeat {
+            page_id,
+            ..PageHeat::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampled_counts() {
+        let mut stats = AccessStats::new(0);
+        stats.record_read(1);
+        assert!(stats.hottest(10).is_empty());
+
+        stats.set_sample_rate(1);
+        for _ in 0..3 {
+            stats.record_read(1);
+        }
+        stats.record_write(2);
+        stats.record_read(3);
+        stats.record_write(3);
+        let hottest = stats.hottest(2);
+        assert_eq!(2, hottest.len());
+        assert_eq!(
+            (1, 3, 0),
+            (hottest[0].page_id, hottest[0].reads, hottest[0].writes)
+        );
+        assert_eq!(
+            (3, 1, 1),
+            (hottest[1].page_id, hottest[1].reads, hottest[1].writes)
+        );
+
+        // Every other access is recorded, with twice the weight.
+        stats.set_sample_rate(2);
+        for _ in 0..10 {
+            stats.record_read(4);
+        }
+        stats.record_read(5);
+        assert_eq!(
+            vec![4],
+            stats
+                .hottest(10)
+                .iter()
+                .map(|p| p.page_id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(10, stats.hottest(1)[0].reads);
+        assert!(!stats.set_paused(true));
+        stats.record_read(4);
+        stats.record_read(4);
+        assert!(stats.set_paused(false));
+        assert_eq!(10, stats.hottest(1)[0].reads);
+        stats.remove(4);
+        assert!(stats.hottest(10).is_empty());
+    }
+}
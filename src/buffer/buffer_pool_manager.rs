@@ -1,43 +1,144 @@
 // Functionality: The simplified Buffer Manager interface allows a client to
 // new/delete pages on disk, to read a disk page into the buffer pool and pin
 // it, also to unpin a page in the buffer pool.
+//
+// Pages are stored by a |StorageBackend|: the database file by default, or
+// memory for tests and ephemeral databases, see |MemoryBufferPoolManager|.
 
+use crate::buffer::access_stats::AccessStats;
+use crate::buffer::access_stats::PageHeat;
+use crate::buffer::cold_tier::ColdTier;
+use crate::buffer::lru_k_replacer::LRUKReplacer;
 use crate::buffer::lru_replacer::LRUReplacer;
+use crate::buffer::page_guard::ReadPageGuard;
+use crate::buffer::page_guard::ReadPageView;
+use crate::buffer::page_guard::WritePageGuard;
+use crate::buffer::page_guard::WritePageView;
+use crate::buffer::pin_quota::PinQuota;
 use crate::buffer::replacer::Replacer;
+use crate::buffer::scan_ring::ScanRing;
+use crate::buffer::scan_ring::SCAN_RING_SIZE;
+use crate::common::config::Lsn;
 use crate::common::config::PageId;
+use crate::common::config::PAGE_SIZE;
 use crate::common::config::HEADER_PAGE_ID;
+use crate::common::config::INVALID_LSN;
+use crate::common::config::INVALID_TRANSACTION_ID;
+use crate::common::config::TransactionId;
+use crate::common::compression::decompress;
 use crate::common::error::*;
+use crate::common::options::DbOptions;
 use crate::disk::disk_manager::DiskManager;
+use crate::disk::memory_storage::MemoryStorage;
+use crate::disk::storage_backend::StorageBackend;
+use crate::disk::tablespace::file_id;
+use crate::disk::tablespace::FileId;
+use crate::disk::tablespace::PRIMARY_FILE_ID;
 use crate::logging::error_logging::ErrorLogging;
 use crate::page::page::Page;
+use crate::page::page::PageFrame;
+use crate::recovery::log_manager::LogManager;
+use crate::recovery::log_record::LogRecord;
+use crate::recovery::log_record::LogRecordBody;
 use log::info;
 use std::clone::Clone;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::mem;
 use std::ops::Drop;
+use std::ops::Range;
+use std::sync::Arc;
 
 // Struct members are split into |data| and |actor|, because this makes it
 // possible to hold mutable borrow on |actor| while acquiring mutable/immutable
 // borrow on |data|.
-pub struct BufferPoolManager<T, R>
+pub struct BufferPoolManager<T, R, S = DiskManager>
 where
     T: Page + Clone,
     R: Replacer<usize>,
+    S: StorageBackend,
 {
     data: Data<T>,
-    actor: Actor<R>,
+    actor: Actor<R, S>,
 }
 
 // The default BufferPoolManager uses LRUReplacer.
-pub type DefaultBufferPoolManager<T> = BufferPoolManager<T, LRUReplacer<usize>>;
+pub type DefaultBufferPoolManager<T, S = DiskManager> = BufferPoolManager<T, LRUReplacer<usize>, S>;
 
-impl<T, R> Drop for BufferPoolManager<T, R>
+// Resists sequential scans evicting the working set; see |LRUKReplacer|.
+pub type LRUKBufferPoolManager<T> = BufferPoolManager<T, LRUKReplacer<usize>>;
+
+// Keeps the pages in memory rather than in a file; see |MemoryStorage|.
+pub type MemoryBufferPoolManager<T> = BufferPoolManager<T, LRUReplacer<usize>, MemoryStorage>;
+
+// At most this fraction of the pool may be marked keep-in-memory, so that the
+// keep set can never starve the replacer.
+pub const MAX_KEEP_FRACTION: f64 = 0.25;
+
+// At most this fraction of the pool may be read ahead on a miss, so that
+// read-ahead does not evict the pages it just loaded.
+pub const MAX_READ_AHEAD_FRACTION: f64 = 0.25;
+
+// A snapshot of the buffer pool counters, see |BufferPoolManager::stats|. The
+// counts are cumulative since the pool was created.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BufferPoolStats {
+    // Fetches of pages already in the pool.
+    pub hits: u64,
+    // Fetches that had to load the page, from disk or the cold tier.
+    pub misses: u64,
+    // Pages victimized by the replacer to make room for another page.
+    pub evictions: u64,
+    // Pages loaded by |prefetch_pages| or read-ahead.
+    pub prefetched: u64,
+    // Dirty pages written back to disk, on eviction or on flush.
+    pub dirty_writes: u64,
+    // Pages currently pinned.
+    pub pinned_pages: usize,
+    // Frames not holding any page.
+    pub free_frames: usize,
+}
+
+impl BufferPoolStats {
+    // The fraction of fetches served from the pool, or 0 before any fetch.
+    pub fn hit_ratio(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            fetches => self.hits as f64 / fetches as f64,
+        }
+    }
+}
+
+impl fmt::Display for BufferPoolStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "hits = {}, misses = {}, hit ratio = {:.3}, evictions = {}, prefetched = {}, \
+             dirty writes = {}, pinned pages = {}, free frames = {}",
+            self.hits,
+            self.misses,
+            self.hit_ratio(),
+            self.evictions,
+            self.prefetched,
+            self.dirty_writes,
+            self.pinned_pages,
+            self.free_frames
+        )
+    }
+}
+
+impl<T, R, S> Drop for BufferPoolManager<T, R, S>
 where
     T: Page + Clone,
     R: Replacer<usize>,
+    S: StorageBackend,
 {
     fn drop(&mut self) {
-        // Unable to handle I/O errors on destruction.
-        self.flush_all_pages().log();
+        if !self.is_read_only() {
+            // Unable to handle I/O errors on destruction.
+            self.flush_all_pages().log();
+        }
     }
 }
 
@@ -47,14 +148,83 @@ where
     R: Replacer<usize>,
 {
     pub fn new(size: usize, db_file: &str) -> std::io::Result<Self> {
-        Ok(BufferPoolManager {
+        Ok(Self::with_storage(size, DiskManager::new(db_file)?, R::default()))
+    }
+
+    // Same as |new|, with a configured replacer (e.g. |LRUKReplacer::new(3)|).
+    pub fn with_replacer(size: usize, db_file: &str, replacer: R) -> std::io::Result<Self> {
+        Ok(Self::with_storage(size, DiskManager::new(db_file)?, replacer))
+    }
+
+    // Same as |new|, with the database file opened for direct I/O; see
+    // |DiskManager::with_direct_io|.
+    pub fn with_direct_io(size: usize, db_file: &str) -> std::io::Result<Self> {
+        Ok(Self::with_storage(size, DiskManager::with_direct_io(db_file)?, R::default()))
+    }
+
+    // Opens an existing database without write access. |new_page|,
+    // |delete_page|, flushing and unpinning a page as dirty all return a
+    // |ReadOnlyFilesystem| error.
+    pub fn new_read_only(size: usize, db_file: &str) -> std::io::Result<Self> {
+        Ok(Self::with_storage(size, DiskManager::new_read_only(db_file)?, R::default()))
+    }
+
+    // Opens the database file and configures the pool as specified by
+    // |options|; see |DbOptions|.
+    pub fn with_options(db_file: &str, options: &DbOptions) -> std::io::Result<Self> {
+        let disk_mgr = DiskManager::with_options(db_file, options)?;
+        let mut bpm = Self::with_storage(options.pool_size, disk_mgr, R::default());
+        bpm.set_read_ahead(options.read_ahead)?;
+        bpm.set_scan_ring_threshold(options.scan_ring_threshold);
+        bpm.set_pin_quota(options.pin_quota);
+        bpm.set_cold_tier_capacity(options.cold_tier_capacity);
+        bpm.set_stats_log_interval(options.stats_log_interval);
+        bpm.set_flush_interval(options.flush_interval);
+        Ok(bpm)
+    }
+
+    // Adds a tablespace stored in the file at |path|; see
+    // |DiskManager::add_tablespace|.
+    pub fn add_tablespace(&mut self, path: &str) -> std::io::Result<FileId> {
+        self.actor.disk_mgr.add_tablespace(path)
+    }
+
+    // Compresses pages written back to disk when |enabled|; see
+    // |DiskManager::set_compression|.
+    pub fn set_compression(&mut self, enabled: bool) -> std::io::Result<()> {
+        self.actor.disk_mgr.set_compression(enabled)
+    }
+}
+
+impl<T, R> BufferPoolManager<T, R, MemoryStorage>
+where
+    T: Page + Clone,
+    R: Replacer<usize>,
+{
+    // Creates a pool over pages kept in memory; see |MemoryStorage|.
+    pub fn in_memory(size: usize) -> Self {
+        Self::with_storage(size, MemoryStorage::new(), R::default())
+    }
+}
+
+impl<T, R, S> BufferPoolManager<T, R, S>
+where
+    T: Page + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    // Creates a pool over the pages of |storage|, with a configured replacer.
+    pub fn with_storage(size: usize, storage: S, replacer: R) -> Self {
+        let mut buffer_pool_mgr = BufferPoolManager {
             data: Data::new(size),
-            actor: Actor::new(db_file)?,
-        })
-        .and_then(|mut buffer_pool_mgr| {
-            buffer_pool_mgr.init();
-            Ok(buffer_pool_mgr)
-        })
+            actor: Actor::new(storage, replacer),
+        };
+        buffer_pool_mgr.init();
+        buffer_pool_mgr
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.actor.disk_mgr.is_read_only()
     }
 
     fn init(&mut self) {
@@ -65,12 +235,22 @@ where
 
     // Fetches the page with specified |page_id|. Pins the page if it already
     // exists in |self.data.page_table|; otherwise, loads the page from disk.
+    // Returns |QuotaExceeded| if the transaction pinning it holds too many
+    // pins; see |set_pin_quota|.
     pub fn fetch_page(&mut self, page_id: PageId) -> std::io::Result<&mut T> {
         info!("Fetch page; page_id = {}", page_id);
         validate(page_id)?;
+        self.actor.pin_quota.check()?;
+        self.data.access_stats.record_read(page_id);
+        self.log_stats_periodically();
         match self.data.page_table.get(&page_id) {
             Some(&idx) => {
                 info!("Found page in table, will pin the page; idx = {}", idx);
+                self.actor.counters.hits += 1;
+                // A pinned page must not be victimized.
+                self.actor.replacer.erase(&idx);
+                self.actor.replacer.record_access(&idx);
+                self.actor.pin_quota.on_pin();
                 let page = &mut self.data.pages[idx];
                 page.pin();
                 return Ok(page);
@@ -78,28 +258,322 @@ where
             None => (),
         }
         info!("Page not found in table, need to load from disk");
+        self.actor.counters.misses += 1;
+        // If no frame can be prepared, the cold copy is simply dropped; the page
+        // on disk is up to date.
+        let cold = self.data.cold_tier.remove(page_id);
         let actor = &mut self.actor;
         let data = &mut self.data;
-        Self::prepare_page(Some(page_id), /*need_reset=*/ false, actor, data).and_then(|page| {
+        let loaded = Self::prepare_page(
+            Some(page_id),
+            file_id(page_id),
+            /*need_reset=*/ false,
+            actor,
+            data,
+        )
+        .and_then(|page| {
+            if let Some(compressed) = cold {
+                info!("Loading the page from cold tier");
+                if decompress(&compressed, page.data_mut()).log_and().is_ok() {
+                    return Ok(());
+                }
+            }
             info!("Loading the page from disk");
-            Self::load_page_inl(&mut actor.disk_mgr, page).map(|_| page)
-        })
+            Self::load_page_inl(&mut actor.disk_mgr, page)
+        });
+        match loaded {
+            Ok(()) => {
+                let idx = self.data.page_table[&page_id];
+                self.actor.pin_quota.on_pin();
+                self.read_ahead(page_id);
+                Ok(&mut self.data.pages[idx])
+            }
+            Err(e) => {
+                // Gives back the frame prepared for the page, if any.
+                if let Some(idx) = self.data.page_table.remove(&page_id) {
+                    self.data.pages[idx].unpin();
+                    self.actor.replacer.remove(&idx);
+                    self.data.free_list.push(idx);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    // Same as |fetch_page|, but returns a guard that unpins the page when it
+    // goes out of scope.
+    pub fn fetch_page_read(
+        &mut self,
+        page_id: PageId,
+    ) -> std::io::Result<ReadPageGuard<'_, T, R, S>> {
+        self.fetch_page(page_id)?;
+        let idx = self.data.page_table[&page_id];
+        Ok(ReadPageGuard::new(self, page_id, idx))
+    }
+
+    // Same as |fetch_page|, but returns a guard that unpins the page as dirty
+    // when it goes out of scope.
+    pub fn fetch_page_write(
+        &mut self,
+        page_id: PageId,
+    ) -> std::io::Result<WritePageGuard<'_, T, R, S>> {
+        self.check_writable()?;
+        self.fetch_page(page_id)?;
+        let idx = self.data.page_table[&page_id];
+        Ok(WritePageGuard::new(self, page_id, idx))
+    }
+
+    // Same as |fetch_page_read|, but returns a view of the page as a page of
+    // type |V|, e.g. a |HeaderPage| fetched from a pool of |TablePage|s. The
+    // view borrows the frame, so the caller needs to know the type of the
+    // page; the bytes are reinterpreted as is, see |view|.
+    pub fn fetch_page_as<V: PageFrame>(
+        &mut self,
+        page_id: PageId,
+    ) -> std::io::Result<ReadPageView<'_, V, T, R, S>>
+    where
+        T: PageFrame,
+    {
+        self.fetch_page(page_id)?;
+        let idx = self.data.page_table[&page_id];
+        Ok(ReadPageView::new(self, page_id, idx))
+    }
+
+    // Same as |fetch_page_write|, but returns a view of the page as a page of
+    // type |V|; see |fetch_page_as|.
+    pub fn fetch_page_as_mut<V: PageFrame>(
+        &mut self,
+        page_id: PageId,
+    ) -> std::io::Result<WritePageView<'_, V, T, R, S>>
+    where
+        T: PageFrame,
+    {
+        self.check_writable()?;
+        self.fetch_page(page_id)?;
+        let idx = self.data.page_table[&page_id];
+        Ok(WritePageView::new(self, page_id, idx))
+    }
+
+    // Same as |new_page|, but returns a view of the new page as an empty page
+    // of type |V|; see |fetch_page_as_mut|.
+    pub fn new_page_as<V: PageFrame>(&mut self) -> std::io::Result<WritePageView<'_, V, T, R, S>>
+    where
+        T: PageFrame,
+    {
+        let page_id = self.new_page_uninit()?.page_id();
+        let idx = self.data.page_table[&page_id];
+        let mut view = WritePageView::<V, T, R, S>::new(self, page_id, idx);
+        view.reset();
+        Ok(view)
+    }
+
+    // Returns the frame at |idx|. The caller needs to hold a pin on its page.
+    pub(crate) fn frame(&self, idx: usize) -> &T {
+        &self.data.pages[idx]
+    }
+
+    pub(crate) fn frame_mut(&mut self, idx: usize) -> &mut T {
+        &mut self.data.pages[idx]
+    }
+
+    // Loads the pages in |page_ids| that are allocated but neither in the pool
+    // nor in the cold tier, reading each run of consecutive pages with a single
+    // disk read, so that fetching them afterwards hits the pool. The pages are
+    // left unpinned. They take free frames first, then frames of victims, and
+    // loading stops once the frames available on entry are used up, so that
+    // prefetched pages never evict each other. Pages failing to read are
+    // skipped, leaving them for |fetch_page| to report. Returns the number of
+    // pages loaded.
+    pub fn prefetch_pages(&mut self, page_ids: Range<PageId>) -> std::io::Result<usize> {
+        info!("Prefetch pages; page_ids = {:?}", page_ids);
+        validate(page_ids.start)?;
+        // Decided up front, so that pages evicted to make room are not loaded
+        // back.
+        let budget = self.data.free_list.len() + self.actor.replacer.size();
+        let wanted: Vec<PageId> = page_ids
+            .filter(|&page_id| self.needs_load(page_id))
+            .take(budget)
+            .collect();
+        let mut loaded = 0;
+        for run in wanted.chunk_by(|&prev, &next| prev + 1 == next) {
+            let mut buffer = vec![0; run.len() * PAGE_SIZE];
+            let count = self.actor.disk_mgr.read_pages(run[0], &mut buffer)?;
+            for (&page_id, data) in run.iter().zip(buffer.chunks(PAGE_SIZE)).take(count) {
+                if !self.load_prefetched(page_id, data)? {
+                    return Ok(loaded);
+                }
+                loaded += 1;
+            }
+        }
+        Ok(loaded)
+    }
+
+    // Prefetches up to |pages| pages following every page loaded by
+    // |fetch_page|, which speeds up sequential scans over pages allocated in a
+    // row (e.g. the pages of a table heap). 0 disables read-ahead, which is
+    // the default. Returns |InvalidInput| if |pages| exceeds
+    // |MAX_READ_AHEAD_FRACTION| of the pool.
+    pub fn set_read_ahead(&mut self, pages: usize) -> std::io::Result<()> {
+        if pages > (self.data.pool_size as f64 * MAX_READ_AHEAD_FRACTION) as usize {
+            return Err(invalid_input("Read-ahead exceeds its share of the pool"));
+        }
+        self.data.read_ahead = pages;
+        Ok(())
+    }
+
+    // Read-ahead is best effort, errors are only logged.
+    fn read_ahead(&mut self, page_id: PageId) {
+        if self.data.read_ahead > 0 {
+            let end = page_id + 1 + self.data.read_ahead as PageId;
+            self.prefetch_pages((page_id + 1)..end).log_and().ok();
+        }
+    }
+
+    // Sequential scans that have read more than |pages| pages read the rest
+    // through |read_page_for_scan|, so that scanning a large table does not
+    // evict the working set. 0 disables the scan ring, which is the default.
+    pub fn set_scan_ring_threshold(&mut self, pages: usize) {
+        self.data.scan_ring_threshold = pages;
+    }
+
+    pub fn scan_ring_threshold(&self) -> usize {
+        self.data.scan_ring_threshold
+    }
+
+    // Reads the page with specified |page_id| for a large one-off scan: from
+    // its frame if the pool holds it, otherwise into the scan ring, along with
+    // the following pages up to |SCAN_RING_SIZE| in one read, without evicting
+    // any page of the pool. The page is not pinned; the copy in the ring stays
+    // valid until the pool loads or writes the page.
+    pub fn read_page_for_scan(&mut self, page_id: PageId) -> std::io::Result<&T> {
+        info!("Read page for scan; page_id = {}", page_id);
+        validate(page_id)?;
+        self.data.access_stats.record_read(page_id);
+        if let Some(&idx) = self.data.page_table.get(&page_id) {
+            return Ok(&self.data.pages[idx]);
+        }
+        if self.data.scan_ring.get(page_id).is_none() {
+            let run = (page_id..)
+                .take(SCAN_RING_SIZE)
+                .take_while(|&next_id| {
+                    next_id == page_id
+                        || (self.actor.disk_mgr.is_allocated(next_id)
+                            && !self.data.page_table.contains_key(&next_id))
+                })
+                .count();
+            let mut buffer = vec![0; run * PAGE_SIZE];
+            let mut count = self.actor.disk_mgr.read_pages(page_id, &mut buffer)?;
+            if count == 0 {
+                // Reports why the page cannot be read.
+                self.actor
+                    .disk_mgr
+                    .read_page(page_id, &mut buffer[..PAGE_SIZE])?;
+                count = 1;
+            }
+            for (next_id, data) in (page_id..).zip(buffer.chunks(PAGE_SIZE)).take(count) {
+                self.data.scan_ring.insert(next_id, data);
+            }
+        }
+        self.data
+            .scan_ring
+            .get(page_id)
+            .ok_or_else(|| not_found("Page is not in the scan ring"))
+    }
+
+    fn needs_load(&self, page_id: PageId) -> bool {
+        self.actor.disk_mgr.is_allocated(page_id)
+            && !self.data.page_table.contains_key(&page_id)
+            && !self.data.cold_tier.contains(page_id)
+    }
+
+    // Places the prefetched |data| of the page with specified |page_id| into a
+    // frame and makes it evictable. Returns false if no frame is available.
+    fn load_prefetched(&mut self, page_id: PageId, data: &[u8]) -> std::io::Result<bool> {
+        let actor = &mut self.actor;
+        let file_id = file_id(page_id);
+        let page = match Self::prepare_page(Some(page_id), file_id, false, actor, &mut self.data) {
+            Ok(page) => page,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        page.data_mut().copy_from_slice(data);
+        page.unpin();
+        let idx = self.data.page_table[&page_id];
+        if !self.data.keep.contains(&page_id) {
+            self.actor.replacer.insert(idx);
+        }
+        self.actor.counters.prefetched += 1;
+        Ok(true)
+    }
+
+    // Keeps up to |capacity| evicted pages compressed in memory, so that
+    // fetching them again does not need to go to disk. 0 disables the tier,
+    // which is the default.
+    pub fn set_cold_tier_capacity(&mut self, capacity: usize) {
+        self.data.cold_tier.set_capacity(capacity);
+    }
+
+    // Whether an evicted copy of the page is held in the cold tier.
+    pub fn is_in_cold_tier(&self, page_id: PageId) -> bool {
+        self.data.cold_tier.contains(page_id)
+    }
+
+    // Caps the number of pages a transaction may pin at once, counting a page
+    // once per pin; fetching or creating a page beyond it returns
+    // |QuotaExceeded|. 0 disables the cap, which is the default. See
+    // |PinQuota|.
+    pub fn set_pin_quota(&mut self, pins: usize) {
+        self.actor.pin_quota.set_cap(pins);
+    }
+
+    pub fn pin_quota(&self) -> usize {
+        self.actor.pin_quota.cap()
+    }
+
+    // Charges the pins taken from now on to |txn_id|, until |release_pins|.
+    pub fn set_pin_owner(&mut self, txn_id: TransactionId) {
+        self.actor.pin_quota.set_owner(txn_id);
+    }
+
+    // Stops charging pins to |txn_id|, which ended, and forgets its count.
+    pub fn release_pins(&mut self, txn_id: TransactionId) {
+        self.actor.pin_quota.release(txn_id);
+    }
+
+    // The number of pins |txn_id| currently holds.
+    pub fn pins_of(&self, txn_id: TransactionId) -> usize {
+        self.actor.pin_quota.pins(txn_id)
     }
 
-    // Unpins the page with specified |page_id|. |is_dirty| sets the dirty flag
-    // of this page. Returns |InvalidData| if the page pin count <= 0.
+    // Unpins the page with specified |page_id|. |is_dirty| marks the page as
+    // dirty; a clean unpin never clears the flag set by an earlier writer.
+    // Returns |InvalidData| if the page pin count <= 0.
     pub fn unpin_page(&mut self, page_id: PageId, is_dirty: bool) -> std::io::Result<()> {
         info!("Unpin page; page_id = {}", page_id);
+        if is_dirty {
+            self.check_writable()?;
+        }
         match self.data.page_table.get(&page_id) {
             Some(&idx) => {
                 info!("Found page in table; idx = {}", idx);
+                if is_dirty {
+                    // The page was modified, its snapshot (if any) is stale, and
+                    // optimistic readers need to retry.
+                    self.data.snapshots.remove(&page_id);
+                    self.data.bump_version(idx);
+                    self.data.access_stats.record_write(page_id);
+                }
                 let page = &mut self.data.pages[idx];
-                page.set_is_dirty(is_dirty);
+                page.set_is_dirty(page.is_dirty() || is_dirty);
                 if page.unpin() {
-                    if page.pin_count() == 0 {
+                    self.actor.pin_quota.on_unpin();
+                    if page.pin_count() == 0 && !self.data.keep.contains(&page_id) {
                         info!("Insert page to replacer; idx = {}", idx);
                         self.actor.replacer.insert(idx);
                     }
+                    if is_dirty {
+                        self.flush_periodically();
+                    }
                     Ok(())
                 } else {
                     Err(invalid_data("Pin count <= 0, cannot be unpinned"))
@@ -113,30 +587,152 @@ where
     // no such page exists in |self.data.page_table|.
     pub fn flush_page(&mut self, page_id: PageId) -> std::io::Result<()> {
         info!("Flush page; page_id = {}", page_id);
+        self.check_writable()?;
         validate(page_id)?;
         match self.data.page_table.get(&page_id) {
-            Some(&idx) => Self::flush_page_inl(&mut self.actor.disk_mgr, &mut self.data.pages[idx]),
+            Some(&idx) => Self::flush_page_inl(&mut self.actor, &mut self.data.pages[idx]),
             None => Err(not_found("Page not found in table")),
         }
     }
 
-    // Flushes if dirty all pages (i.e. |self.data.pages|) to disk. Finishes
-    // flushing all pages regardless of I/O errors. Returns the first error
+    // Flushes if dirty all pages (i.e. |self.data.pages|) to disk. Each run of
+    // consecutive dirty pages is written at once, and the file is synced once
+    // at the end. Finishes flushing all pages regardless of I/O errors; the
+    // pages of a run that fails stay dirty. Returns the first error
     // encountered.
     pub fn flush_all_pages(&mut self) -> std::io::Result<()> {
+        self.check_writable()?;
+        self.flush_pages_inl(|_| true)
+    }
+
+    // Flushes the dirty pages accepted by |filter| in runs of consecutive page
+    // IDs, then syncs the database file.
+    fn flush_pages_inl(&mut self, filter: impl Fn(&T) -> bool) -> std::io::Result<()> {
+        let mut dirty: Vec<&mut T> = self
+            .data
+            .pages
+            .iter_mut()
+            .filter(|page| page.is_dirty() && filter(page))
+            .collect();
+        if dirty.is_empty() {
+            return Ok(());
+        }
+        dirty.sort_by_key(|page| page.page_id());
         let mut result = Ok(());
-        for (page_id, &idx) in self.data.page_table.iter() {
-            info!("Flush page; page_id = {}", page_id);
-            let res = Self::flush_page_inl(&mut self.actor.disk_mgr, &mut self.data.pages[idx]);
-            result = result.and(res);
+        for run in dirty.chunk_by_mut(|prev, next| prev.page_id() + 1 == next.page_id()) {
+            info!("Flush pages; page_id = {}, count = {}", run[0].page_id(), run.len());
+            result = result.and(Self::flush_run_inl(&mut self.actor, run));
         }
-        result
+        result.and(self.actor.disk_mgr.sync_data())
+    }
+
+    // Flushes all pages, persists the allocation bitmap and syncs the database
+    // file, then truncates the log, if any, and releases the lock on the
+    // database file. Unlike dropping, this reports I/O errors to the caller. A
+    // read-only pool only releases the lock.
+    pub fn close(mut self) -> std::io::Result<()> {
+        if self.is_read_only() {
+            return Ok(());
+        }
+        self.flush_all_pages()?;
+        self.actor.disk_mgr.sync()?;
+        // Every change logged is on disk now, so the log is not needed.
+        if let Some(log_mgr) = self.actor.log_mgr.as_mut() {
+            log_mgr.truncate()?;
+        }
+        Ok(())
+    }
+
+    // Marks |page_id| as allocated on disk; see |DiskManager::reserve_page|.
+    pub fn reserve_page(&mut self, page_id: PageId) -> std::io::Result<()> {
+        self.check_writable()?;
+        validate(page_id)?;
+        self.actor.disk_mgr.reserve_page(page_id)
+    }
+
+    // Attaches a write-ahead log. From then on, a dirty page is only written
+    // to disk once the log is durable up to the page's LSN.
+    pub fn set_log_manager(&mut self, log_mgr: LogManager) {
+        self.actor.log_mgr = Some(log_mgr);
+    }
+
+    pub fn log_manager(&mut self) -> Option<&mut LogManager> {
+        self.actor.log_mgr.as_mut()
+    }
+
+    // Keeps the changes of every transaction in memory while no log manager
+    // is attached, so that they can be rolled back; see |take_undo_log|.
+    pub fn enable_undo_log(&mut self) {
+        self.actor.undo_log = Some(HashMap::new());
+    }
+
+    // Removes and returns the changes kept for |txn_id|, oldest first, once
+    // the transaction ends.
+    pub fn take_undo_log(&mut self, txn_id: TransactionId) -> Vec<LogRecordBody> {
+        match self.actor.undo_log.as_mut() {
+            Some(undo_log) => undo_log.remove(&txn_id).unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    // Appends |record| describing a change to the pinned page |page_id|, and
+    // stamps the page with its LSN. Returns the LSN, or |INVALID_LSN| if no
+    // log manager is attached, in which case the change of a transaction is
+    // kept in the undo log, if enabled, or if the change is not to be logged;
+    // see |set_unlogged|.
+    pub fn log_page(&mut self, page_id: PageId, record: LogRecord) -> std::io::Result<Lsn> {
+        let log_mgr = match (self.actor.log_mgr.as_mut(), self.actor.undo_log.as_mut()) {
+            (Some(log_mgr), _) => log_mgr,
+            (None, Some(undo_log)) if record.txn_id() != INVALID_TRANSACTION_ID => {
+                let txn_id = record.txn_id();
+                undo_log.entry(txn_id).or_default().push(record.body().clone());
+                return Ok(INVALID_LSN);
+            }
+            (None, _) => return Ok(INVALID_LSN),
+        };
+        let idx = match self.data.page_table.get(&page_id) {
+            Some(&idx) => idx,
+            None => return Err(not_found("Page not found in table")),
+        };
+        let txn_id = record.txn_id();
+        if txn_id != INVALID_TRANSACTION_ID && txn_id == self.actor.unlogged_txn_id {
+            // Stamped with the latest LSN, the page is newer than every change
+            // logged for it, so that redo leaves it as written.
+            let lsn = log_mgr.next_lsn() - 1;
+            self.data.pages[idx].set_lsn(lsn);
+            self.actor.unlogged_pages.insert(page_id);
+            return Ok(lsn);
+        }
+        let lsn = log_mgr.append(record)?;
+        self.data.pages[idx].set_lsn(lsn);
+        Ok(lsn)
+    }
+
+    // Stops logging the changes of |txn_id|, e.g. of a bulk load, from now
+    // on: |log_page| stamps the pages they change with the latest LSN and
+    // records them instead, for |flush_unlogged| to write. Since such changes
+    // cannot be rolled back nor recovered, the caller needs to tell whether
+    // they made it to disk. With |INVALID_TRANSACTION_ID|, the changes of
+    // every transaction are logged.
+    pub fn set_unlogged(&mut self, txn_id: TransactionId) {
+        self.actor.unlogged_txn_id = txn_id;
+    }
+
+    // Flushes the pages changed without being logged since the last call,
+    // then persists the allocation bitmap and syncs the database file, so
+    // that the changes are durable without the log.
+    pub fn flush_unlogged(&mut self) -> std::io::Result<()> {
+        self.check_writable()?;
+        let page_ids = mem::take(&mut self.actor.unlogged_pages);
+        self.flush_pages_inl(|page| page_ids.contains(&page.page_id()))?;
+        self.actor.disk_mgr.sync()
     }
 
     // Deletes a page. User should call this method for deleting a page. This
     // routine will call |self.actor.disk_mgr| to deallocate the page.
     pub fn delete_page(&mut self, page_id: PageId) -> std::io::Result<()> {
         info!("Delete page; page_id = {}", page_id);
+        self.check_writable()?;
         validate(page_id)?;
         match self.data.page_table.get(&page_id) {
             Some(&idx) => {
@@ -146,37 +742,308 @@ where
                     return Err(invalid_data("Cannot delete pinned page"));
                 }
                 page.set_is_dirty(false);
+                // The frame must not be handed out by both the free list and
+                // the replacer.
+                self.actor.replacer.remove(&idx);
                 self.data.free_list.push(idx);
                 self.data.page_table.remove(&page_id);
+                self.data.bump_version(idx);
             }
             None => (),
         }
-        self.actor.disk_mgr.deallocate_page(page_id);
+        self.data.keep.remove(&page_id);
+        self.data.cold_tier.remove(page_id);
+        self.data.scan_ring.remove(page_id);
+        self.data.snapshots.remove(&page_id);
+        self.data.access_stats.remove(page_id);
+        self.actor.disk_mgr.deallocate_page(page_id)
+    }
+
+    // Returns an immutable snapshot of the page with specified |page_id|, which
+    // can be shared and read without pinning or latching (e.g. catalog pages
+    // after load). The snapshot is taken on first use and reused until the page
+    // is unpinned as dirty, deleted, or |invalidate_snapshot| is called.
+    pub fn snapshot_page(&mut self, page_id: PageId) -> std::io::Result<Arc<[u8; PAGE_SIZE]>> {
+        info!("Snapshot page; page_id = {}", page_id);
+        if let Some(snapshot) = self.data.snapshots.get(&page_id) {
+            return Ok(Arc::clone(snapshot));
+        }
+        let snapshot = Arc::new(*self.fetch_page(page_id)?.data());
+        self.unpin_page(page_id, /*is_dirty=*/ false)?;
+        self.data.snapshots.insert(page_id, Arc::clone(&snapshot));
+        Ok(snapshot)
+    }
+
+    // Drops the snapshot of the page with specified |page_id|, so that the next
+    // |snapshot_page| call reflects its latest content. Holders of the old
+    // snapshot keep reading the old content.
+    pub fn invalidate_snapshot(&mut self, page_id: PageId) {
+        self.data.snapshots.remove(&page_id);
+    }
+
+    // Records one out of every |sample_rate| page accesses for |hottest_pages|;
+    // 0 disables tracking, which is the default. Resets the counters.
+    pub fn set_access_sampling(&mut self, sample_rate: u64) {
+        self.data.access_stats.set_sample_rate(sample_rate);
+    }
+
+    // Returns up to |n| pages with the most (estimated) fetches and writes
+    // since sampling was enabled, hottest first. See |Catalog::hottest_pages|
+    // for the tables and indexes owning them.
+    pub fn hottest_pages(&self, n: usize) -> Vec<PageHeat> {
+        self.data.access_stats.hottest(n)
+    }
+
+    // Runs |f| without sampling its page accesses, e.g. for inspection that
+    // fetches many pages and would skew the counters.
+    pub fn without_access_sampling<V>(&mut self, f: impl FnOnce(&mut Self) -> V) -> V {
+        let paused = self.data.access_stats.set_paused(true);
+        let result = f(self);
+        self.data.access_stats.set_paused(paused);
+        result
+    }
+
+    // Returns the current counters of the pool.
+    pub fn stats(&self) -> BufferPoolStats {
+        let pinned_pages = self
+            .data
+            .page_table
+            .values()
+            .filter(|&&idx| self.data.pages[idx].pin_count() > 0)
+            .count();
+        BufferPoolStats {
+            pinned_pages,
+            free_frames: self.data.free_list.len(),
+            ..self.actor.counters.clone()
+        }
+    }
+
+    // Logs |stats| once every |interval| page fetches; 0 disables logging,
+    // which is the default.
+    pub fn set_stats_log_interval(&mut self, interval: u64) {
+        self.data.stats_log_interval = interval;
+        self.data.fetches_since_log = 0;
+    }
+
+    // Flushes the dirty pages that are not pinned once every |interval| pages
+    // unpinned as dirty, so that fewer dirty pages wait for eviction, closing
+    // or recovery; 0 disables it, which is the default. Pinned pages may be
+    // half-modified and wait for the next round.
+    pub fn set_flush_interval(&mut self, interval: u64) {
+        self.data.flush_interval = interval;
+        self.data.dirty_since_flush = 0;
+    }
+
+    // Errors are logged rather than returned, since the unpin itself worked;
+    // the pages stay dirty and are retried later.
+    fn flush_periodically(&mut self) {
+        if self.data.flush_interval == 0 {
+            return;
+        }
+        self.data.dirty_since_flush += 1;
+        if self.data.dirty_since_flush >= self.data.flush_interval {
+            self.data.dirty_since_flush = 0;
+            info!("Flush unpinned pages periodically");
+            self.flush_pages_inl(|page| page.pin_count() == 0).log();
+        }
+    }
+
+    fn log_stats_periodically(&mut self) {
+        if self.data.stats_log_interval == 0 {
+            return;
+        }
+        self.data.fetches_since_log += 1;
+        if self.data.fetches_since_log >= self.data.stats_log_interval {
+            self.data.fetches_since_log = 0;
+            info!("Buffer pool stats; {}", self.stats());
+        }
+    }
+
+    // Returns the version of the frame holding the page with specified
+    // |page_id|, or None if the page is not in the pool. The version changes
+    // whenever the page is unpinned as dirty, deleted or evicted, and is never
+    // reused, so a reader (e.g. a B+ tree descent) can read the page
+    // optimistically and retry if |validate_version| fails afterwards.
+    pub fn page_version(&self, page_id: PageId) -> Option<u64> {
+        self.data
+            .page_table
+            .get(&page_id)
+            .map(|&idx| self.data.versions[idx])
+    }
+
+    // Whether the page with specified |page_id| is still at |version|, i.e. an
+    // optimistic read started at |version| saw consistent content.
+    pub fn validate_version(&self, page_id: PageId, version: u64) -> bool {
+        self.page_version(page_id) == Some(version)
+    }
+
+    // Marks the page with specified |page_id| as keep-in-memory (e.g. the
+    // catalog header or index roots), so that the replacer never victimizes it
+    // once loaded. Returns |InvalidInput| if the keep set would exceed
+    // |MAX_KEEP_FRACTION| of the pool.
+    pub fn keep_page(&mut self, page_id: PageId) -> std::io::Result<()> {
+        info!("Keep page; page_id = {}", page_id);
+        validate(page_id)?;
+        if self.data.keep.contains(&page_id) {
+            return Ok(());
+        }
+        if self.data.keep.len() + 1 > self.max_keep_count() {
+            return Err(invalid_input("Keep set exceeds its share of the pool"));
+        }
+        self.data.keep.insert(page_id);
+        if let Some(&idx) = self.data.page_table.get(&page_id) {
+            self.actor.replacer.erase(&idx);
+        }
         Ok(())
     }
 
+    // Clears the keep-in-memory mark of the page with specified |page_id|,
+    // making it evictable again once unpinned.
+    pub fn unkeep_page(&mut self, page_id: PageId) -> std::io::Result<()> {
+        info!("Unkeep page; page_id = {}", page_id);
+        if !self.data.keep.remove(&page_id) {
+            return Err(not_found("Page is not kept"));
+        }
+        if let Some(&idx) = self.data.page_table.get(&page_id) {
+            if self.data.pages[idx].pin_count() == 0 {
+                self.actor.replacer.insert(idx);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_kept(&self, page_id: PageId) -> bool {
+        self.data.keep.contains(&page_id)
+    }
+
+    fn max_keep_count(&self) -> usize {
+        (self.data.pool_size as f64 * MAX_KEEP_FRACTION) as usize
+    }
+
     // Creates a new page. User should call this method if one needs to create a
     // new page. This routine will call |self.actor.disk_mgr| to allocate a page.
     //
     // TODO: Update new page's metadata?
     pub fn new_page(&mut self) -> std::io::Result<&mut T> {
-        info!("New page");
-        Self::prepare_page(
+        self.new_page_in(PRIMARY_FILE_ID)
+    }
+
+    // Same as |new_page|, but allocates the page in the tablespace |file_id|;
+    // see |add_tablespace|.
+    pub fn new_page_in(&mut self, file_id: FileId) -> std::io::Result<&mut T> {
+        info!("New page; file_id = {}", file_id);
+        self.check_writable()?;
+        self.actor.pin_quota.check()?;
+        let page = Self::prepare_page(
             /*maybe_id=*/ None,
+            file_id,
             /*need_reset=*/ true,
             &mut self.actor,
             &mut self.data,
+        )?;
+        self.actor.pin_quota.on_pin();
+        Ok(page)
+    }
+
+    // Same as |new_page|, for the page |page_id| allocated on disk but never
+    // written, e.g. a page of an extent; see |allocate_pages_in|. Returns
+    // |InvalidInput| if the page is not allocated, and |AlreadyExists| if it
+    // is in the pool.
+    pub fn new_page_at(&mut self, page_id: PageId) -> std::io::Result<&mut T> {
+        info!("New page; page_id = {}", page_id);
+        self.check_writable()?;
+        validate(page_id)?;
+        if !self.actor.disk_mgr.is_allocated(page_id) {
+            return Err(invalid_input("Page is not allocated"));
+        }
+        if self.data.page_table.contains_key(&page_id) || self.data.cold_tier.contains(page_id) {
+            return Err(already_exists("Page is in the buffer pool"));
+        }
+        self.actor.pin_quota.check()?;
+        let page = Self::prepare_page(
+            Some(page_id),
+            file_id(page_id),
+            /*need_reset=*/ true,
+            &mut self.actor,
+            &mut self.data,
+        )?;
+        self.actor.pin_quota.on_pin();
+        Ok(page)
+    }
+
+    // Allocates |count| contiguous pages in the tablespace |file_id| without
+    // bringing them into the pool; see |DiskManager::allocate_pages_in|.
+    pub fn allocate_pages_in(
+        &mut self,
+        file_id: FileId,
+        count: usize,
+    ) -> std::io::Result<Range<PageId>> {
+        self.check_writable()?;
+        self.actor.disk_mgr.allocate_pages_in(file_id, count)
+    }
+
+    // Writes |pages| straight to disk, one page per element, to the pages
+    // starting at |first_page_id|, bypassing the pool, and syncs them; see
+    // |DiskManager::write_pages|. Meant for bulk loads of pages allocated by
+    // |allocate_pages_in|. Returns |InvalidInput| if one of the pages is not
+    // allocated, and |AlreadyExists| if one is in the pool, whose frame would
+    // go stale.
+    pub fn write_pages(
+        &mut self,
+        first_page_id: PageId,
+        pages: &mut [&mut [u8]],
+    ) -> std::io::Result<()> {
+        info!(
+            "Write pages; first_page_id = {}, count = {}",
+            first_page_id,
+            pages.len()
+        );
+        self.check_writable()?;
+        for page_id in (first_page_id..).take(pages.len()) {
+            validate(page_id)?;
+            if !self.actor.disk_mgr.is_allocated(page_id) {
+                return Err(invalid_input("Page is not allocated"));
+            }
+            if self.data.page_table.contains_key(&page_id) || self.data.cold_tier.contains(page_id)
+            {
+                return Err(already_exists("Page is in the buffer pool"));
+            }
+            self.data.scan_ring.remove(page_id);
+        }
+        self.actor.disk_mgr.write_pages(first_page_id, pages)?;
+        self.actor.disk_mgr.sync_data()
+    }
+
+    // Whether the page is allocated on disk.
+    pub fn is_allocated(&self, page_id: PageId) -> bool {
+        self.actor.disk_mgr.is_allocated(page_id)
+    }
+
+    // Same as |new_page|, but skips |Page::reset|, so the frame keeps the bytes
+    // of the page it held before. Meant for callers that overwrite the whole
+    // page anyway (e.g. bulk loads).
+    pub fn new_page_uninit(&mut self) -> std::io::Result<&mut T> {
+        info!("New page, uninitialized");
+        self.check_writable()?;
+        Self::prepare_page(
+            /*maybe_id=*/ None,
+            PRIMARY_FILE_ID,
+            /*need_reset=*/ false,
+            &mut self.actor,
+            &mut self.data,
         )
     }
 
     // Prepares and pins a new page and returns a (PageId, Page) pair.
-    // If |maybe_id| is None, asks |actor.disk_mgr| to allocate a new page ID.
-    // If |need_reset| is |true|, resets the page with 0's. Returns error if the
-    // old page fails to be flushed to disk.
+    // If |maybe_id| is None, asks |actor.disk_mgr| to allocate a new page ID in
+    // the tablespace |file_id|. If |need_reset| is |true|, resets the page with
+    // 0's. Returns error if the old page fails to be flushed to disk, or the
+    // page fails to be allocated.
     fn prepare_page<'a>(
         maybe_id: Option<PageId>,
+        file_id: FileId,
         need_reset: bool,
-        actor: &mut Actor<R>,
+        actor: &mut Actor<R, S>,
         data: &'a mut Data<T>,
     ) -> std::io::Result<&'a mut T> {
         let either = match data.free_list.last().map(|x| *x) {
@@ -191,7 +1058,7 @@ where
         }?;
         let idx = *either.borrow();
         let page = &mut data.pages[idx];
-        match Self::flush_page_inl(&mut actor.disk_mgr, page) {
+        match Self::flush_page_inl(actor, page) {
             Ok(()) => {
                 // On flush success.
                 match either {
@@ -199,16 +1066,32 @@ where
                         data.free_list.pop();
                     }
                     Either::FromReplacer(_) => {
+                        actor.counters.evictions += 1;
+                        // The victim is clean now, so it can be kept compressed.
+                        data.cold_tier.insert(page.page_id(), page.data());
                         data.page_table.remove(&page.page_id());
                     }
                 }
-                let allocate = || {
-                    info!("Allocate page ID");
-                    actor.disk_mgr.allocate_page()
+                let page_id = match maybe_id {
+                    Some(page_id) => page_id,
+                    None => {
+                        info!("Allocate page ID");
+                        match actor.disk_mgr.allocate_page_in(file_id) {
+                            Ok(page_id) => page_id,
+                            Err(e) => {
+                                // The frame is empty now.
+                                data.free_list.push(idx);
+                                return Err(e);
+                            }
+                        }
+                    }
                 };
-                page.set_page_id(maybe_id.unwrap_or_else(allocate));
+                page.set_page_id(page_id);
                 data.page_table.insert(page.page_id(), idx);
-                Ok(page)
+                data.scan_ring.remove(page_id);
+                data.bump_version(idx);
+                actor.replacer.record_access(&idx);
+                Ok(&mut data.pages[idx])
             }
             Err(e) => {
                 // On flush failure.
@@ -231,15 +1114,28 @@ where
         })
     }
 
+    fn check_writable(&self) -> std::io::Result<()> {
+        if self.is_read_only() {
+            Err(read_only("Buffer pool is opened read-only"))
+        } else {
+            Ok(())
+        }
+    }
+
     // Flushes the specified page to disk manager iff the page is dirty, resets
     // the dirty flag. |page.data()| stores the data being written to disk.
     //
     // Note: If the page is not dirty, calling this is a no-op.
-    fn flush_page_inl(disk_mgr: &mut DiskManager, page: &mut T) -> std::io::Result<()> {
+    fn flush_page_inl(actor: &mut Actor<R, S>, page: &mut T) -> std::io::Result<()> {
         match page.is_dirty() {
             true => {
                 info!("Page is dirty, flushiung to disk");
-                disk_mgr.write_page(page.page_id(), page.data_mut())?;
+                // Write-ahead rule: the log describing the page goes first.
+                if let Some(log_mgr) = actor.log_mgr.as_mut() {
+                    log_mgr.flush_until(page.lsn())?;
+                }
+                actor.disk_mgr.write_page(page.page_id(), page.data_mut())?;
+                actor.counters.dirty_writes += 1;
                 page.set_is_dirty(false);
             }
             false => {
@@ -249,11 +1145,32 @@ where
         Ok(())
     }
 
+    // Writes the dirty pages |run| with consecutive page IDs to disk manager
+    // without syncing, and resets their dirty flags.
+    fn flush_run_inl(actor: &mut Actor<R, S>, run: &mut [&mut T]) -> std::io::Result<()> {
+        // Write-ahead rule: the log describing the pages goes first.
+        if let Some(log_mgr) = actor.log_mgr.as_mut() {
+            let lsn = run.iter().map(|page| page.lsn()).max().unwrap_or(INVALID_LSN);
+            log_mgr.flush_until(lsn)?;
+        }
+        let first_page_id = run[0].page_id();
+        let mut data: Vec<&mut [u8]> = run
+            .iter_mut()
+            .map(|page| &mut page.data_mut()[..])
+            .collect();
+        actor.disk_mgr.write_pages(first_page_id, &mut data)?;
+        for page in run.iter_mut() {
+            page.set_is_dirty(false);
+        }
+        actor.counters.dirty_writes += run.len() as u64;
+        Ok(())
+    }
+
     // Loads the specified page from disk manager. |page.data_mut()| is the place
     // where the data being read will be stored.
     //
     // Note: It is not allowed to load page when the current page is dirty.
-    fn load_page_inl(disk_mgr: &mut DiskManager, page: &mut T) -> std::io::Result<()> {
+    fn load_page_inl(disk_mgr: &mut S, page: &mut T) -> std::io::Result<()> {
         match page.is_dirty() {
             true => Err(invalid_data("Cannot load while current page is dirty")),
             false => {
@@ -287,6 +1204,31 @@ where
     pages: Vec<T>,
     page_table: HashMap<PageId, usize>,
     free_list: Vec<usize>,
+    // Pages that the replacer must never victimize.
+    keep: HashSet<PageId>,
+    // Compressed copies of evicted pages.
+    cold_tier: ColdTier,
+    // Immutable snapshots served by |snapshot_page|.
+    snapshots: HashMap<PageId, Arc<[u8; PAGE_SIZE]>>,
+    // Per-frame versions for optimistic reads, drawn from |next_version|.
+    versions: Vec<u64>,
+    next_version: u64,
+    // Sampled access counters served by |hottest_pages|.
+    access_stats: AccessStats,
+    // Fetches between two lines logged by |log_stats_periodically|; 0 disables
+    // logging.
+    stats_log_interval: u64,
+    fetches_since_log: u64,
+    // Pages unpinned as dirty between two rounds of |flush_periodically|; 0
+    // disables them.
+    flush_interval: u64,
+    dirty_since_flush: u64,
+    // The number of pages prefetched after a miss, see |set_read_ahead|.
+    read_ahead: usize,
+    // Private frames of large sequential scans, see |read_page_for_scan|.
+    scan_ring: ScanRing<T>,
+    // See |set_scan_ring_threshold|.
+    scan_ring_threshold: usize,
 }
 
 impl<T> Data<T>
@@ -299,28 +1241,67 @@ where
             pages: vec![T::default(); size],
             page_table: HashMap::new(),
             free_list: Vec::new(),
+            keep: HashSet::new(),
+            cold_tier: ColdTier::new(0),
+            snapshots: HashMap::new(),
+            versions: vec![0; size],
+            next_version: 1,
+            access_stats: AccessStats::new(0),
+            stats_log_interval: 0,
+            fetches_since_log: 0,
+            flush_interval: 0,
+            dirty_since_flush: 0,
+            read_ahead: 0,
+            scan_ring: ScanRing::new(),
+            scan_ring_threshold: 0,
         }
     }
+
+    fn bump_version(&mut self, idx: usize) {
+        self.versions[idx] = self.next_version;
+        self.next_version += 1;
+    }
 }
 
-struct Actor<R>
+struct Actor<R, S>
 where
     R: Replacer<usize>,
+    S: StorageBackend,
 {
     replacer: R,
-    disk_mgr: DiskManager,
+    disk_mgr: S,
+    log_mgr: Option<LogManager>,
+    // The changes of every running transaction by transaction ID, if enabled;
+    // see |enable_undo_log|.
+    undo_log: Option<HashMap<TransactionId, Vec<LogRecordBody>>>,
+    // Cumulative counters served by |stats|. They live here rather than in
+    // |Data|, because flushing a page only borrows the actor.
+    counters: BufferPoolStats,
+    // Pins of every transaction, see |set_pin_quota|. Like |counters|, they
+    // are updated while a frame of |Data| is borrowed.
+    pin_quota: PinQuota,
+    // The transaction whose changes are not logged, if any, and the pages it
+    // changed; see |set_unlogged|.
+    unlogged_txn_id: TransactionId,
+    unlogged_pages: HashSet<PageId>,
 }
 
-impl<R> Actor<R>
+impl<R, S> Actor<R, S>
 where
     R: Replacer<usize>,
+    S: StorageBackend,
 {
-    pub fn new(db_file: &str) -> std::io::Result<Self> {
-        let actor = Actor {
-            replacer: R::default(),
-            disk_mgr: DiskManager::new(db_file)?,
-        };
-        Ok(actor)
+    pub fn new(disk_mgr: S, replacer: R) -> Self {
+        Actor {
+            replacer,
+            disk_mgr,
+            log_mgr: None,
+            undo_log: None,
+            counters: BufferPoolStats::default(),
+            pin_quota: PinQuota::new(0),
+            unlogged_txn_id: INVALID_TRANSACTION_ID,
+            unlogged_pages: HashSet::new(),
+        }
     }
 }
 
@@ -334,9 +1315,11 @@ fn validate(page_id: PageId) -> std::io::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::config::INVALID_PAGE_ID;
     use crate::common::reinterpret;
     use crate::disk::disk_manager::BITMAP_FILE_SUFFIX;
     use crate::page::table_page::TablePage;
+    use crate::recovery::log_record::LogRecordBody;
     use crate::testing::file_deleter::FileDeleter;
 
     const SAFE_OFFSET: usize = 128;
@@ -488,4 +1471,634 @@ mod tests {
             }
         } // Drops bpm.
     }
+
+    #[test]
+    fn read_only() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.4.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        {
+            let mut bpm = TestingBufferPoolManager::new(10, file_path).unwrap();
+            let page = bpm.new_page().unwrap();
+            reinterpret::write_str(&mut page.data_mut()[SAFE_OFFSET..], "Hello");
+            assert!(bpm.unpin_page(HEADER_PAGE_ID, /*is_dirty=*/ true).is_ok());
+        } // Drops bpm.
+
+        {
+            let mut bpm = TestingBufferPoolManager::new_read_only(10, file_path).unwrap();
+            assert!(bpm.is_read_only());
+
+            let page = bpm.fetch_page(HEADER_PAGE_ID).unwrap();
+            assert_eq!("Hello", reinterpret::read_str(&page.data()[SAFE_OFFSET..]));
+
+            let kind = std::io::ErrorKind::ReadOnlyFilesystem;
+            assert_eq!(kind, bpm.new_page().err().unwrap().kind());
+            assert_eq!(kind, bpm.delete_page(HEADER_PAGE_ID).unwrap_err().kind());
+            assert_eq!(kind, bpm.flush_page(HEADER_PAGE_ID).unwrap_err().kind());
+            assert_eq!(kind, bpm.flush_all_pages().unwrap_err().kind());
+            assert_eq!(
+                kind,
+                bpm.unpin_page(HEADER_PAGE_ID, /*is_dirty=*/ true)
+                    .unwrap_err()
+                    .kind()
+            );
+            assert!(bpm.unpin_page(HEADER_PAGE_ID, /*is_dirty=*/ false).is_ok());
+        } // Drops bpm.
+    }
+
+    #[test]
+    fn keep_pages() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.5.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut bpm = TestingBufferPoolManager::new(10, file_path).unwrap();
+        for i in 0..10 {
+            assert_eq!(i + HEADER_PAGE_ID, bpm.new_page().unwrap().page_id());
+        }
+
+        // At most 10 * MAX_KEEP_FRACTION = 2 pages can be kept.
+        assert!(bpm.keep_page(HEADER_PAGE_ID).is_ok());
+        assert!(bpm.keep_page(HEADER_PAGE_ID).is_ok());
+        assert!(bpm.keep_page(1 + HEADER_PAGE_ID).is_ok());
+        assert!(bpm.keep_page(2 + HEADER_PAGE_ID).is_err());
+        assert!(bpm.is_kept(HEADER_PAGE_ID));
+        assert!(!bpm.is_kept(2 + HEADER_PAGE_ID));
+
+        // Kept pages are never chosen as victims.
+        for i in 0..10 {
+            assert!(bpm.unpin_page(i + HEADER_PAGE_ID, /*is_dirty=*/ true).is_ok());
+        }
+        for i in 10..18 {
+            assert_eq!(i + HEADER_PAGE_ID, bpm.new_page().unwrap().page_id());
+        }
+        assert!(bpm.new_page().is_err());
+
+        // Unkept pages become evictable again.
+        assert!(bpm.unkeep_page(HEADER_PAGE_ID).is_ok());
+        assert!(bpm.unkeep_page(HEADER_PAGE_ID).is_err());
+        assert_eq!(18 + HEADER_PAGE_ID, bpm.new_page().unwrap().page_id());
+        assert!(bpm.new_page().is_err());
+    }
+
+    #[test]
+    fn cold_tier() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.6.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut bpm = TestingBufferPoolManager::new(2, file_path).unwrap();
+        bpm.set_cold_tier_capacity(2);
+        for id in 0..4 {
+            let page = bpm.new_page().unwrap();
+            reinterpret::write_i32(&mut page.data_mut()[SAFE_OFFSET..], id);
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ true).is_ok());
+        }
+
+        // Pages 0 and 1 were evicted into the cold tier.
+        assert!(bpm.is_in_cold_tier(0));
+        assert!(bpm.is_in_cold_tier(1));
+        assert!(!bpm.is_in_cold_tier(2));
+
+        // Fetching a cold page moves it back into the pool and evicts page 2.
+        let page = bpm.fetch_page(1).unwrap();
+        assert_eq!(1, reinterpret::read_i32(&page.data()[SAFE_OFFSET..]));
+        assert!(!bpm.is_in_cold_tier(1));
+        assert!(bpm.is_in_cold_tier(2));
+        assert!(bpm.unpin_page(1, /*is_dirty=*/ false).is_ok());
+
+        // Deleting a page drops its cold copy.
+        assert!(bpm.delete_page(0).is_ok());
+        assert!(!bpm.is_in_cold_tier(0));
+
+        // Disabling the tier still serves pages from disk.
+        bpm.set_cold_tier_capacity(0);
+        let page = bpm.fetch_page(2).unwrap();
+        assert_eq!(2, reinterpret::read_i32(&page.data()[SAFE_OFFSET..]));
+    }
+
+    #[test]
+    fn snapshot_pages() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.7.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut bpm = TestingBufferPoolManager::new(2, file_path).unwrap();
+        let page = bpm.new_page().unwrap();
+        reinterpret::write_str(&mut page.data_mut()[SAFE_OFFSET..], "Hello");
+        assert!(bpm.unpin_page(HEADER_PAGE_ID, /*is_dirty=*/ true).is_ok());
+
+        let snapshot = bpm.snapshot_page(HEADER_PAGE_ID).unwrap();
+        assert_eq!("Hello", reinterpret::read_str(&snapshot[SAFE_OFFSET..]));
+        // The snapshot does not hold a pin, and is shared on later calls.
+        assert!(bpm.unpin_page(HEADER_PAGE_ID, /*is_dirty=*/ false).is_err());
+        assert!(Arc::ptr_eq(&snapshot, &bpm.snapshot_page(HEADER_PAGE_ID).unwrap()));
+
+        // Modifying the page refreshes the snapshot; old holders are unaffected.
+        let page = bpm.fetch_page(HEADER_PAGE_ID).unwrap();
+        reinterpret::write_str(&mut page.data_mut()[SAFE_OFFSET..], "World");
+        assert!(bpm.unpin_page(HEADER_PAGE_ID, /*is_dirty=*/ true).is_ok());
+        let refreshed = bpm.snapshot_page(HEADER_PAGE_ID).unwrap();
+        assert_eq!("World", reinterpret::read_str(&refreshed[SAFE_OFFSET..]));
+        assert_eq!("Hello", reinterpret::read_str(&snapshot[SAFE_OFFSET..]));
+
+        bpm.invalidate_snapshot(HEADER_PAGE_ID);
+        assert!(!Arc::ptr_eq(&refreshed, &bpm.snapshot_page(HEADER_PAGE_ID).unwrap()));
+    }
+
+    #[test]
+    fn optimistic_versions() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.8.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut bpm = TestingBufferPoolManager::new(1, file_path).unwrap();
+        assert_eq!(None, bpm.page_version(HEADER_PAGE_ID));
+        bpm.new_page().unwrap();
+        let version = bpm.page_version(HEADER_PAGE_ID).unwrap();
+
+        // Clean unpins keep the version.
+        assert!(bpm.unpin_page(HEADER_PAGE_ID, /*is_dirty=*/ false).is_ok());
+        bpm.fetch_page(HEADER_PAGE_ID).unwrap();
+        assert!(bpm.validate_version(HEADER_PAGE_ID, version));
+
+        // Writes change it.
+        assert!(bpm.unpin_page(HEADER_PAGE_ID, /*is_dirty=*/ true).is_ok());
+        assert!(!bpm.validate_version(HEADER_PAGE_ID, version));
+        let version = bpm.page_version(HEADER_PAGE_ID).unwrap();
+
+        // Evicting and reloading the page does not reuse the old version.
+        bpm.new_page().unwrap();
+        assert!(!bpm.validate_version(HEADER_PAGE_ID, version));
+        assert!(bpm.unpin_page(1, /*is_dirty=*/ false).is_ok());
+        bpm.fetch_page(HEADER_PAGE_ID).unwrap();
+        assert!(!bpm.validate_version(HEADER_PAGE_ID, version));
+    }
+
+    #[test]
+    fn hottest_pages() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.9.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut bpm = TestingBufferPoolManager::new(2, file_path).unwrap();
+        for id in 0..2 {
+            bpm.new_page().unwrap();
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ true).is_ok());
+        }
+        // Tracking is disabled by default.
+        assert!(bpm.hottest_pages(10).is_empty());
+
+        bpm.set_access_sampling(1);
+        for _ in 0..3 {
+            bpm.fetch_page(1).unwrap();
+            assert!(bpm.unpin_page(1, /*is_dirty=*/ false).is_ok());
+        }
+        bpm.fetch_page(0).unwrap();
+        assert!(bpm.unpin_page(0, /*is_dirty=*/ true).is_ok());
+        let hottest = bpm.hottest_pages(10);
+        assert_eq!(2, hottest.len());
+        assert_eq!((1, 3, 0), (hottest[0].page_id, hottest[0].reads, hottest[0].writes));
+        assert_eq!((0, 1, 1), (hottest[1].page_id, hottest[1].reads, hottest[1].writes));
+
+        bpm.without_access_sampling(|bpm| {
+            bpm.fetch_page(0).unwrap();
+            assert!(bpm.unpin_page(0, /*is_dirty=*/ false).is_ok());
+        });
+        assert_eq!(1, bpm.hottest_pages(10)[1].reads);
+
+        assert!(bpm.delete_page(1).is_ok());
+        assert_eq!(1, bpm.hottest_pages(10).len());
+    }
+
+    #[test]
+    fn close_releases_lock() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.10.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut bpm = TestingBufferPoolManager::new(10, file_path).unwrap();
+        let page = bpm.new_page().unwrap();
+        let id = page.page_id();
+        reinterpret::write_i32(&mut page.data_mut()[SAFE_OFFSET..], 42);
+        assert!(bpm.unpin_page(id, /*is_dirty=*/ true).is_ok());
+
+        // The database file is locked until the pool is closed.
+        let err = TestingBufferPoolManager::new_read_only(10, file_path).err().unwrap();
+        assert_eq!(std::io::ErrorKind::WouldBlock, err.kind());
+        assert!(bpm.close().is_ok());
+
+        let mut bpm = TestingBufferPoolManager::new_read_only(10, file_path).unwrap();
+        let page = bpm.fetch_page(id).unwrap();
+        assert_eq!(42, reinterpret::read_i32(&page.data()[SAFE_OFFSET..]));
+        assert!(bpm.unpin_page(id, /*is_dirty=*/ false).is_ok());
+        assert!(bpm.close().is_ok());
+    }
+
+    #[test]
+    fn new_page_uninit() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.11.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut bpm = TestingBufferPoolManager::new(1, file_path).unwrap();
+        let page = bpm.new_page().unwrap();
+        let id = page.page_id();
+        page.set_next_page_id(5);
+        reinterpret::write_i32(&mut page.data_mut()[SAFE_OFFSET..], 7);
+        assert!(bpm.unpin_page(id, /*is_dirty=*/ false).is_ok());
+        assert!(bpm.delete_page(id).is_ok());
+
+        // The frame keeps the bytes of the deleted page.
+        let page = bpm.new_page_uninit().unwrap();
+        let id = page.page_id();
+        assert_eq!(5, page.next_page_id());
+        assert_eq!(7, reinterpret::read_i32(&page.data()[SAFE_OFFSET..]));
+        assert!(bpm.unpin_page(id, /*is_dirty=*/ false).is_ok());
+        assert!(bpm.delete_page(id).is_ok());
+
+        // |new_page| resets the header, leaving the rest as is.
+        let page = bpm.new_page().unwrap();
+        assert_eq!(INVALID_PAGE_ID, page.next_page_id());
+        assert_eq!(0, page.tuple_count());
+        assert_eq!(7, reinterpret::read_i32(&page.data()[SAFE_OFFSET..]));
+    }
+
+    // Creates 6 pages in a pool of 3 frames, uses the first one repeatedly,
+    // then scans the others once. Returns whether the first page survived.
+    fn survives_scan<R: Replacer<usize>>(bpm: &mut BufferPoolManager<TablePage, R>) -> bool {
+        let mut page_ids = vec![];
+        for _ in 0..6 {
+            let page_id = bpm.new_page().unwrap().page_id();
+            assert!(bpm.unpin_page(page_id, /*is_dirty=*/ false).is_ok());
+            page_ids.push(page_id);
+        }
+        let hot_id = page_ids[0];
+        for _ in 0..2 {
+            assert!(bpm.fetch_page(hot_id).is_ok());
+            assert!(bpm.unpin_page(hot_id, /*is_dirty=*/ false).is_ok());
+        }
+        for &page_id in page_ids[1..].iter() {
+            assert!(bpm.fetch_page(page_id).is_ok());
+            assert!(bpm.unpin_page(page_id, /*is_dirty=*/ false).is_ok());
+        }
+        bpm.page_version(hot_id).is_some()
+    }
+
+    #[test]
+    fn lru_k_resists_scans() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.12.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let lru_file_path = "/tmp/testfile.buffer_pool_manager.13.db";
+        let lru_bitmap_path = lru_file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(lru_file_path);
+        file_deleter.push(&lru_bitmap_path);
+
+        let replacer = LRUKReplacer::new(2);
+        let mut bpm =
+            LRUKBufferPoolManager::<TablePage>::with_replacer(3, file_path, replacer).unwrap();
+        assert!(survives_scan(&mut bpm));
+
+        let mut bpm = TestingBufferPoolManager::new(3, lru_file_path).unwrap();
+        assert!(!survives_scan(&mut bpm));
+    }
+
+    #[test]
+    fn write_ahead_log() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.14.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let log_path = "/tmp/testfile.buffer_pool_manager.14.log";
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(log_path);
+
+        let record = || LogRecord::new(1, LogRecordBody::Begin);
+        let mut bpm = TestingBufferPoolManager::new(1, file_path).unwrap();
+        let id = bpm.new_page().unwrap().page_id();
+        assert_eq!(INVALID_LSN, bpm.log_page(id, record()).unwrap());
+
+        bpm.set_log_manager(LogManager::new(log_path).unwrap());
+        assert_eq!(1, bpm.log_page(id, record()).unwrap());
+        assert_eq!(2, bpm.log_page(id, record()).unwrap());
+        assert_eq!(2, bpm.fetch_page(id).unwrap().lsn());
+        assert!(bpm.unpin_page(id, /*is_dirty=*/ true).is_ok());
+        assert!(bpm.unpin_page(id, /*is_dirty=*/ true).is_ok());
+        assert_eq!(INVALID_LSN, bpm.log_manager().unwrap().persistent_lsn());
+
+        // Evicting the page first makes the log durable up to the page's LSN.
+        let other_id = bpm.new_page().unwrap().page_id();
+        assert_eq!(2, bpm.log_manager().unwrap().persistent_lsn());
+        assert_eq!(
+            std::io::ErrorKind::NotFound,
+            bpm.log_page(id, record()).err().unwrap().kind()
+        );
+        assert!(bpm.unpin_page(other_id, /*is_dirty=*/ false).is_ok());
+        assert_eq!(2, bpm.fetch_page(id).unwrap().lsn());
+
+        // The changes of an unlogged transaction are not appended, and the
+        // pages they change are written by |flush_unlogged|.
+        bpm.set_unlogged(1);
+        assert_eq!(2, bpm.log_page(id, record()).unwrap());
+        assert_eq!(3, bpm.log_manager().unwrap().next_lsn());
+        assert!(bpm.unpin_page(id, /*is_dirty=*/ true).is_ok());
+        assert!(bpm.flush_unlogged().is_ok());
+        assert!(!bpm.fetch_page(id).unwrap().is_dirty());
+        bpm.set_unlogged(INVALID_TRANSACTION_ID);
+        assert_eq!(3, bpm.log_page(id, record()).unwrap());
+        assert!(bpm.unpin_page(id, /*is_dirty=*/ false).is_ok());
+    }
+
+    #[test]
+    fn stats() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.15.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut bpm = TestingBufferPoolManager::new(2, file_path).unwrap();
+        bpm.set_stats_log_interval(1);
+        assert_eq!(2, bpm.stats().free_frames);
+        assert_eq!(0.0, bpm.stats().hit_ratio());
+        for id in 0..2 {
+            bpm.new_page().unwrap();
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ true).is_ok());
+        }
+        bpm.fetch_page(0).unwrap();
+        let stats = bpm.stats();
+        assert_eq!((1, 0, 0, 0), (stats.hits, stats.misses, stats.evictions, stats.dirty_writes));
+        assert_eq!((1, 0), (stats.pinned_pages, stats.free_frames));
+
+        // Page 1 is evicted and written back, then page 0 in turn.
+        bpm.new_page().unwrap();
+        assert!(bpm.unpin_page(0, /*is_dirty=*/ false).is_ok());
+        bpm.fetch_page(1).unwrap();
+        let stats = bpm.stats();
+        assert_eq!((1, 1, 2, 2), (stats.hits, stats.misses, stats.evictions, stats.dirty_writes));
+        assert_eq!((2, 0), (stats.pinned_pages, stats.free_frames));
+        assert_eq!(0.5, stats.hit_ratio());
+        assert!(stats.to_string().starts_with("hits = 1, misses = 1, hit ratio = 0.500"));
+    }
+
+    #[test]
+    fn prefetch_pages() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.16.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut bpm = TestingBufferPoolManager::new(8, file_path).unwrap();
+        for id in 0..8 {
+            let page = bpm.new_page().unwrap();
+            reinterpret::write_i32(&mut page.data_mut()[SAFE_OFFSET..], id * 10);
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ true).is_ok());
+        }
+        assert!(bpm.flush_all_pages().is_ok());
+        // Evicts pages 0 to 2, frees the frame of page 5, and keeps page 7
+        // pinned.
+        for _ in 0..3 {
+            bpm.new_page().unwrap();
+        }
+        assert!(bpm.delete_page(5).is_ok());
+        bpm.fetch_page(7).unwrap();
+
+        // Pages 3, 4 and 6 to 8 are in the pool, and page 5 is not allocated.
+        let before = bpm.stats();
+        assert_eq!(3, bpm.prefetch_pages(0..9).unwrap());
+        assert_eq!(before.evictions + 2, bpm.stats().evictions);
+        assert_eq!(3, bpm.stats().prefetched);
+        for id in 0..3 {
+            let page = bpm.fetch_page(id).unwrap();
+            assert_eq!(id * 10, reinterpret::read_i32(&page.data()[SAFE_OFFSET..]));
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ false).is_ok());
+        }
+        assert_eq!(before.misses, bpm.stats().misses);
+        assert!(bpm.prefetch_pages(-1..2).is_err());
+
+        // Prefetching evicted pages 3 and 4. A miss on page 3 reads ahead page
+        // 4, skipping page 5.
+        assert!(bpm.set_read_ahead(3).is_err());
+        assert!(bpm.set_read_ahead(2).is_ok());
+        for id in 3..5 {
+            let page = bpm.fetch_page(id).unwrap();
+            assert_eq!(id * 10, reinterpret::read_i32(&page.data()[SAFE_OFFSET..]));
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ false).is_ok());
+        }
+        assert_eq!(before.misses + 1, bpm.stats().misses);
+        assert_eq!(4, bpm.stats().prefetched);
+    }
+
+    #[test]
+    fn with_options() {
+        let file_path = "/tmp/testfile.buffer_pool_manager.17.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut options = DbOptions::new();
+        options.create_if_missing(false);
+        let error = TestingBufferPoolManager::with_options(file_path, &options).err().unwrap();
+        assert_eq!(std::io::ErrorKind::NotFound, error.kind());
+        assert!(!std::path::Path::new(file_path).exists());
+        options.pool_size(0);
+        assert!(TestingBufferPoolManager::with_options(file_path, &options).is_err());
+
+        options.create_if_missing(true).pool_size(8).compression(true);
+        let bpm = TestingBufferPoolManager::with_options(file_path, &options).unwrap();
+        assert!(bpm.actor.disk_mgr.is_compression_enabled());
+        drop(bpm);
+
+        options.read_ahead(2).compression(false).flush_interval(4).verify_checksums(false);
+        {
+            let mut bpm = TestingBufferPoolManager::with_options(file_path, &options).unwrap();
+            assert_eq!(8, bpm.data.pool_size);
+            assert_eq!(2, bpm.data.read_ahead);
+            assert_eq!(4, bpm.data.flush_interval);
+            assert!(!bpm.actor.disk_mgr.is_verify_checksums_enabled());
+            bpm.new_page().unwrap();
+            assert!(bpm.unpin_page(0, /*is_dirty=*/ true).is_ok());
+        } // Drops bpm.
+
+        options.read_only(true).flush_interval(0);
+        let mut bpm = TestingBufferPoolManager::with_options(file_path, &options).unwrap();
+        assert!(bpm.is_read_only());
+        assert!(bpm.fetch_page(0).is_ok());
+        assert!(bpm.new_page().is_err());
+    }
+
+    #[test]
+    fn in_memory() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(4);
+        for id in 0..4 {
+            let page = bpm.new_page().unwrap();
+            assert_eq!(id, page.page_id());
+            reinterpret::write_i32(&mut page.data_mut()[SAFE_OFFSET..], id * 10);
+        }
+        assert!(bpm.new_page().is_err());
+        for id in 0..4 {
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ true).is_ok());
+        }
+
+        // Evicts pages 0 to 3, writing them back to memory.
+        for id in 4..8 {
+            assert_eq!(id, bpm.new_page().unwrap().page_id());
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ false).is_ok());
+        }
+        assert_eq!(4, bpm.stats().dirty_writes);
+        for id in 0..4 {
+            let page = bpm.fetch_page(id).unwrap();
+            assert_eq!(id * 10, reinterpret::read_i32(&page.data()[SAFE_OFFSET..]));
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ false).is_ok());
+        }
+
+        // Deleted pages are reused, and the pool reads ahead in memory too.
+        assert!(bpm.delete_page(2).is_ok());
+        assert!(!bpm.is_allocated(2));
+        assert_eq!(2, bpm.new_page().unwrap().page_id());
+        assert!(bpm.unpin_page(2, /*is_dirty=*/ false).is_ok());
+        assert_eq!(4, bpm.prefetch_pages(4..9).unwrap());
+        assert!(bpm.flush_all_pages().is_ok());
+        assert!(!bpm.is_read_only());
+    }
+
+    #[test]
+    fn pinned_pages_are_not_evicted() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(2);
+        for id in 0..2 {
+            assert_eq!(id, bpm.new_page().unwrap().page_id());
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ false).is_ok());
+        }
+
+        // Page 0 is the least recently unpinned, but pinning it again takes it
+        // out of the replacer, so page 1 is evicted instead.
+        assert!(bpm.fetch_page(0).is_ok());
+        assert_eq!(2, bpm.new_page().unwrap().page_id());
+        assert!(bpm.new_page().is_err());
+        assert_eq!(0, bpm.fetch_page(0).unwrap().page_id());
+        assert!(bpm.unpin_page(0, /*is_dirty=*/ false).is_ok());
+        assert!(bpm.unpin_page(0, /*is_dirty=*/ false).is_ok());
+    }
+
+    #[test]
+    fn pin_quota() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(8);
+        bpm.set_pin_quota(2);
+        assert_eq!(2, bpm.pin_quota());
+        // Pins without an owner are not capped.
+        for id in 0..3 {
+            assert_eq!(id, bpm.new_page().unwrap().page_id());
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ false).is_ok());
+        }
+
+        bpm.set_pin_owner(1);
+        assert!(bpm.fetch_page(0).is_ok());
+        assert!(bpm.fetch_page(0).is_ok());
+        assert_eq!(2, bpm.pins_of(1));
+        let error = bpm.fetch_page(1).err().unwrap();
+        assert_eq!(std::io::ErrorKind::QuotaExceeded, error.kind());
+        assert!(bpm.new_page().is_err());
+        assert!(bpm.unpin_page(0, /*is_dirty=*/ false).is_ok());
+        assert_eq!(1, bpm.fetch_page(1).unwrap().page_id());
+
+        // Once released, the transaction is no longer charged.
+        bpm.release_pins(1);
+        assert_eq!(0, bpm.pins_of(1));
+        assert!(bpm.fetch_page(2).is_ok());
+        assert_eq!(0, bpm.pins_of(1));
+    }
+
+    #[test]
+    fn clean_unpin_keeps_dirty_flag() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(1);
+        let page = bpm.new_page().unwrap();
+        reinterpret::write_str(&mut page.data_mut()[SAFE_OFFSET..], "Hello");
+        assert!(bpm.unpin_page(0, /*is_dirty=*/ true).is_ok());
+
+        // A reader unpinning the page clean does not discard the write.
+        assert!(bpm.fetch_page(0).unwrap().is_dirty());
+        assert!(bpm.unpin_page(0, /*is_dirty=*/ false).is_ok());
+        assert_eq!(1, bpm.new_page().unwrap().page_id());
+        assert_eq!(1, bpm.stats().dirty_writes);
+        assert!(bpm.unpin_page(1, /*is_dirty=*/ false).is_ok());
+        let page = bpm.fetch_page(0).unwrap();
+        assert_eq!("Hello", reinterpret::read_str(&page.data()[SAFE_OFFSET..]));
+    }
+
+    #[test]
+    fn flush_interval() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(4);
+        bpm.set_flush_interval(3);
+        for id in 0..3 {
+            assert_eq!(id, bpm.new_page().unwrap().page_id());
+        }
+        assert!(bpm.fetch_page(2).is_ok());
+        assert!(bpm.unpin_page(2, /*is_dirty=*/ true).is_ok());
+        assert!(bpm.unpin_page(0, /*is_dirty=*/ true).is_ok());
+        assert_eq!(0, bpm.stats().dirty_writes);
+
+        // The third dirty unpin flushes the unpinned pages, not page 2.
+        assert!(bpm.unpin_page(1, /*is_dirty=*/ true).is_ok());
+        assert_eq!(2, bpm.stats().dirty_writes);
+        assert!(!bpm.fetch_page(0).unwrap().is_dirty());
+        assert!(bpm.fetch_page(2).unwrap().is_dirty());
+
+        // Clean unpins do not count.
+        for id in [0, 2, 2] {
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ false).is_ok());
+        }
+        for id in [0, 1, 0] {
+            assert!(bpm.fetch_page(id).is_ok());
+            assert!(bpm.unpin_page(id, /*is_dirty=*/ true).is_ok());
+        }
+        assert_eq!(5, bpm.stats().dirty_writes);
+    }
 }
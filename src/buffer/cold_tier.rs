@@ -0,0 +1,96 @@
+// Functionality: An optional second-tier in-memory cache for pages evicted from
+// the buffer pool. Pages are stored compressed, so a pool miss that hits this
+// tier costs a decompression instead of a disk read. Only clean pages are
+// stored, therefore an entry may be dropped at any time without losing data.
+
+use crate::buffer::lru_replacer::LRUReplacer;
+use crate::buffer::replacer::Replacer;
+use crate::common::compression::compress;
+use crate::common::config::PageId;
+use std::collections::HashMap;
+
+pub struct ColdTier {
+    // The maximum number of compressed pages; 0 disables the tier.
+    capacity: usize,
+    pages: HashMap<PageId, Vec<u8>>,
+    replacer: LRUReplacer<PageId>,
+}
+
+impl ColdTier {
+    pub fn new(capacity: usize) -> Self {
+        ColdTier {
+            capacity,
+            pages: HashMap::new(),
+            replacer: LRUReplacer::default(),
+        }
+    }
+
+    // Shrinks or grows the tier, dropping the least recently inserted pages if
+    // needed.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.pages.len() > capacity {
+            self.evict();
+        }
+    }
+
+    pub fn contains(&self, page_id: PageId) -> bool {
+        self.pages.contains_key(&page_id)
+    }
+
+    // Compresses and stores a clean copy of the page |data|.
+    pub fn insert(&mut self, page_id: PageId, data: &[u8]) {
+        if self.capacity == 0 {
+            return;
+        }
+        if !self.contains(page_id) && self.pages.len() >= self.capacity {
+            self.evict();
+        }
+        self.pages.insert(page_id, compress(data));
+        self.replacer.insert(page_id);
+    }
+
+    // Removes the page from the tier and returns its compressed data.
+    pub fn remove(&mut self, page_id: PageId) -> Option<Vec<u8>> {
+        self.replacer.erase(&page_id);
+        self.pages.remove(&page_id)
+    }
+
+    fn evict(&mut self) {
+        if let Some(page_id) = self.replacer.victim() {
+            self.pages.remove(&page_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::compression::decompress;
+    use crate::common::config::PAGE_SIZE;
+
+    #[test]
+    fn insert_and_remove() {
+        let mut tier = ColdTier::new(2);
+        let mut data = [0; PAGE_SIZE];
+        for i in 0..3 {
+            data[100] = i as u8;
+            tier.insert(i, &data);
+        }
+        // Page 0 is the least recently inserted and got evicted.
+        assert!(!tier.contains(0));
+        assert!(tier.contains(1));
+
+        let compressed = tier.remove(2).unwrap();
+        assert!(compressed.len() < PAGE_SIZE);
+        let mut buffer = [0; PAGE_SIZE];
+        assert!(decompress(&compressed, &mut buffer).is_ok());
+        assert_eq!(2, buffer[100]);
+        assert!(tier.remove(2).is_none());
+
+        tier.set_capacity(0);
+        assert!(!tier.contains(1));
+        tier.insert(3, &data);
+        assert!(!tier.contains(3));
+    }
+}
@@ -0,0 +1,162 @@
+// Functionality: The LRU-K replacer evicts the value whose K-th most recent
+// access is the oldest, i.e. whose backward K-distance is the largest. Values
+// accessed fewer than K times have an infinite distance and are evicted
+// first, oldest access first. Unlike plain LRU, a sequential scan touching
+// many pages once cannot evict pages accessed repeatedly.
+//
+// Only values marked evictable (see |set_evictable|) are candidates; the
+// access history is kept until the value is evicted or removed.
+
+use crate::buffer::replacer::Replacer;
+use std::clone::Clone;
+use std::cmp::Eq;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::default::Default;
+use std::hash::Hash;
+
+pub const DEFAULT_K: usize = 2;
+
+pub struct LRUKReplacer<T>
+where
+    T: Clone + Eq + Hash,
+{
+    k: usize,
+    // The timestamps of the last |k| accesses of each value, oldest first.
+    history: HashMap<T, VecDeque<u64>>,
+    evictable: HashSet<T>,
+    clock: u64,
+}
+
+impl<T> Default for LRUKReplacer<T>
+where
+    T: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new(DEFAULT_K)
+    }
+}
+
+impl<T> LRUKReplacer<T>
+where
+    T: Clone + Eq + Hash,
+{
+    // |k| is at least 1; K = 1 is plain LRU.
+    pub fn new(k: usize) -> Self {
+        LRUKReplacer {
+            k: k.max(1),
+            history: HashMap::new(),
+            evictable: HashSet::new(),
+            clock: 0,
+        }
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    // Marks |val| as a candidate for eviction or not.
+    pub fn set_evictable(&mut self, val: &T, evictable: bool) {
+        if evictable {
+            self.history.entry(val.clone()).or_default();
+            self.evictable.insert(val.clone());
+        } else {
+            self.evictable.remove(val);
+        }
+    }
+}
+
+impl<T> Replacer<T> for LRUKReplacer<T>
+where
+    T: Clone + Eq + Hash,
+{
+    fn insert(&mut self, val: T) {
+        self.set_evictable(&val, true);
+    }
+
+    fn erase(&mut self, val: &T) -> bool {
+        self.evictable.remove(val)
+    }
+
+    fn victim(&mut self) -> Option<T> {
+        // Values with fewer than |k| accesses sort first, then the oldest
+        // tracked access goes first.
+        let val = self
+            .evictable
+            .iter()
+            .min_by_key(|val| {
+                let history = &self.history[*val];
+                (
+                    history.len() >= self.k,
+                    history.front().copied().unwrap_or(0),
+                )
+            })?
+            .clone();
+        self.remove(&val);
+        Some(val)
+    }
+
+    fn size(&self) -> usize {
+        self.evictable.len()
+    }
+
+    fn record_access(&mut self, val: &T) {
+        self.clock += 1;
+        let history = self.history.entry(val.clone()).or_default();
+        history.push_back(self.clock);
+        if history.len() > self.k {
+            history.pop_front();
+        }
+    }
+
+    fn remove(&mut self, val: &T) {
+        self.evictable.remove(val);
+        self.history.remove(val);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lru_k_replacer() {
+        let mut lru_k = LRUKReplacer::default();
+        assert_eq!(2, lru_k.k());
+
+        // Accesses 1 and 2 twice, 3 and 4 once.
+        for val in [1, 2, 3, 4, 1, 2] {
+            lru_k.record_access(&val);
+        }
+        for val in 1..=4 {
+            lru_k.insert(val);
+        }
+        assert_eq!(4, lru_k.size());
+
+        // 3 and 4 have an infinite backward distance.
+        lru_k.set_evictable(&3, false);
+        assert_eq!(Some(4), lru_k.victim());
+        assert_eq!(Some(1), lru_k.victim());
+        lru_k.set_evictable(&3, true);
+        assert_eq!(2, lru_k.size());
+
+        // An access of 3 makes it the most recently used at distance 2.
+        lru_k.record_access(&3);
+        assert_eq!(Some(2), lru_k.victim());
+        assert_eq!(Some(3), lru_k.victim());
+        assert_eq!(None, lru_k.victim());
+        assert_eq!(0, lru_k.size());
+
+        // Evicted values start over with no history.
+        lru_k.record_access(&1);
+        lru_k.record_access(&5);
+        lru_k.record_access(&5);
+        lru_k.insert(5);
+        lru_k.insert(1);
+        assert_eq!(Some(1), lru_k.victim());
+        assert!(!lru_k.erase(&1));
+        lru_k.remove(&5);
+        assert_eq!(None, lru_k.victim());
+    }
+}
@@ -1,4 +1,10 @@
+pub mod access_stats;
 pub mod buffer_pool_manager;
+pub mod lru_k_replacer;
+pub mod page_guard;
 
+mod cold_tier;
 mod lru_replacer;
+mod pin_quota;
 mod replacer;
+mod scan_ring;
@@ -0,0 +1,385 @@
+// Functionality: RAII guards over pinned pages, returned by
+// |BufferPoolManager::fetch_page_read| and |fetch_page_write|. A guard derefs
+// to the page and unpins it when dropped, so that a page can no longer be
+// leaked pinned by a forgotten |unpin_page|. A write guard also marks the page
+// as dirty.
+//
+// A guard mutably borrows the buffer pool manager, so at most one guard is
+// alive at a time; use |fetch_page| directly to pin several pages at once. A
+// read guard also holds the latch of the page in shared mode, and a write
+// guard in exclusive mode, see |Page::rlatch|; a thread holding the latch of a
+// page must not take a guard on it.
+//
+// Page views, returned by |BufferPoolManager::fetch_page_as| and
+// |fetch_page_as_mut|, are guards deref'ing to a page of another type than the
+// frames of the pool, e.g. to a |HeaderPage| in a pool of |TablePage|s. A view
+// borrows the frame and reinterprets it in place; see |PageFrame|.
+
+use crate::buffer::buffer_pool_manager::BufferPoolManager;
+use crate::buffer::replacer::Replacer;
+use crate::common::config::PageId;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::logging::error_logging::ErrorLogging;
+use crate::page::page::view;
+use crate::page::page::view_mut;
+use crate::page::page::Page;
+use crate::page::page::PageFrame;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::ops::Drop;
+
+pub struct ReadPageGuard<'a, T, R, S = DiskManager>
+where
+    T: Page + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    bpm: &'a mut BufferPoolManager<T, R, S>,
+    page_id: PageId,
+    idx: usize,
+}
+
+impl<'a, T, R, S> ReadPageGuard<'a, T, R, S>
+where
+    T: Page + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    // The page at frame |idx| needs to be pinned once on behalf of the guard.
+    pub(crate) fn new(
+        bpm: &'a mut BufferPoolManager<T, R, S>,
+        page_id: PageId,
+        idx: usize,
+    ) -> Self {
+        bpm.frame(idx).rlatch();
+        ReadPageGuard { bpm, page_id, idx }
+    }
+
+    pub fn page_id(&self) -> PageId {
+        self.page_id
+    }
+}
+
+impl<'a, T, R, S> Deref for ReadPageGuard<'a, T, R, S>
+where
+    T: Page + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.bpm.frame(self.idx)
+    }
+}
+
+impl<'a, T, R, S> Drop for ReadPageGuard<'a, T, R, S>
+where
+    T: Page + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    fn drop(&mut self) {
+        self.bpm.frame(self.idx).runlatch();
+        // Unable to handle errors on destruction.
+        self.bpm.unpin_page(self.page_id, /*is_dirty=*/ false).log();
+    }
+}
+
+pub struct WritePageGuard<'a, T, R, S = DiskManager>
+where
+    T: Page + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    bpm: &'a mut BufferPoolManager<T, R, S>,
+    page_id: PageId,
+    idx: usize,
+}
+
+impl<'a, T, R, S> WritePageGuard<'a, T, R, S>
+where
+    T: Page + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    // The page at frame |idx| needs to be pinned once on behalf of the guard.
+    pub(crate) fn new(
+        bpm: &'a mut BufferPoolManager<T, R, S>,
+        page_id: PageId,
+        idx: usize,
+    ) -> Self {
+        bpm.frame(idx).wlatch();
+        WritePageGuard { bpm, page_id, idx }
+    }
+
+    pub fn page_id(&self) -> PageId {
+        self.page_id
+    }
+}
+
+impl<'a, T, R, S> Deref for WritePageGuard<'a, T, R, S>
+where
+    T: Page + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.bpm.frame(self.idx)
+    }
+}
+
+impl<'a, T, R, S> DerefMut for WritePageGuard<'a, T, R, S>
+where
+    T: Page + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        self.bpm.frame_mut(self.idx)
+    }
+}
+
+impl<'a, T, R, S> Drop for WritePageGuard<'a, T, R, S>
+where
+    T: Page + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    fn drop(&mut self) {
+        self.bpm.frame(self.idx).wunlatch();
+        // Unable to handle errors on destruction.
+        self.bpm.unpin_page(self.page_id, /*is_dirty=*/ true).log();
+    }
+}
+
+pub struct ReadPageView<'a, V, T, R, S = DiskManager>
+where
+    V: PageFrame,
+    T: PageFrame + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    guard: ReadPageGuard<'a, T, R, S>,
+    view: PhantomData<V>,
+}
+
+impl<'a, V, T, R, S> ReadPageView<'a, V, T, R, S>
+where
+    V: PageFrame,
+    T: PageFrame + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    // The page at frame |idx| needs to be pinned once on behalf of the view.
+    pub(crate) fn new(
+        bpm: &'a mut BufferPoolManager<T, R, S>,
+        page_id: PageId,
+        idx: usize,
+    ) -> Self {
+        ReadPageView {
+            guard: ReadPageGuard::new(bpm, page_id, idx),
+            view: PhantomData,
+        }
+    }
+
+    pub fn page_id(&self) -> PageId {
+        self.guard.page_id()
+    }
+}
+
+impl<'a, V, T, R, S> Deref for ReadPageView<'a, V, T, R, S>
+where
+    V: PageFrame,
+    T: PageFrame + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        view(&*self.guard)
+    }
+}
+
+pub struct WritePageView<'a, V, T, R, S = DiskManager>
+where
+    V: PageFrame,
+    T: PageFrame + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    guard: WritePageGuard<'a, T, R, S>,
+    view: PhantomData<V>,
+}
+
+impl<'a, V, T, R, S> WritePageView<'a, V, T, R, S>
+where
+    V: PageFrame,
+    T: PageFrame + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    // The page at frame |idx| needs to be pinned once on behalf of the view.
+    pub(crate) fn new(
+        bpm: &'a mut BufferPoolManager<T, R, S>,
+        page_id: PageId,
+        idx: usize,
+    ) -> Self {
+        WritePageView {
+            guard: WritePageGuard::new(bpm, page_id, idx),
+            view: PhantomData,
+        }
+    }
+
+    pub fn page_id(&self) -> PageId {
+        self.guard.page_id()
+    }
+}
+
+impl<'a, V, T, R, S> Deref for WritePageView<'a, V, T, R, S>
+where
+    V: PageFrame,
+    T: PageFrame + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        view(&*self.guard)
+    }
+}
+
+impl<'a, V, T, R, S> DerefMut for WritePageView<'a, V, T, R, S>
+where
+    V: PageFrame,
+    T: PageFrame + Clone,
+    R: Replacer<usize>,
+    S: StorageBackend,
+{
+    fn deref_mut(&mut self) -> &mut V {
+        view_mut(&mut *self.guard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+    use crate::common::config::INVALID_PAGE_ID;
+    use crate::common::reinterpret;
+    use crate::disk::disk_manager::BITMAP_FILE_SUFFIX;
+    use crate::page::extent_page::ExtentPage;
+    use crate::page::header_page::HeaderPage;
+    use crate::page::page::Page;
+    use crate::page::raw_page::RawPage;
+    use crate::page::table_page::TablePage;
+    use crate::testing::file_deleter::FileDeleter;
+
+    const SAFE_OFFSET: usize = 128;
+
+    #[test]
+    fn guards_unpin_on_drop() {
+        let file_path = "/tmp/testfile.page_guard.1.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        // With a single frame, every fetch below fails if a guard leaks a pin.
+        let mut bpm = DefaultBufferPoolManager::<TablePage>::new(1, file_path).unwrap();
+        let first_id = bpm.new_page().unwrap().page_id();
+        assert!(bpm.unpin_page(first_id, /*is_dirty=*/ false).is_ok());
+
+        {
+            let mut page = bpm.fetch_page_write(first_id).unwrap();
+            assert_eq!(first_id, page.page_id());
+            assert_eq!(1, page.pin_count());
+            assert!(!page.latch().try_rlatch());
+            reinterpret::write_i32(&mut page.data_mut()[SAFE_OFFSET..], 42);
+        } // Drops the guard, which unpins the page as dirty.
+
+        // Evicts the first page, which needs to be flushed.
+        let second_id = bpm.new_page().unwrap().page_id();
+        assert!(bpm.unpin_page(second_id, /*is_dirty=*/ false).is_ok());
+
+        let page = bpm.fetch_page_read(first_id).unwrap();
+        assert_eq!(42, reinterpret::read_i32(&page.data()[SAFE_OFFSET..]));
+        assert!(!page.is_dirty());
+        // Readers share the latch.
+        assert!(!page.latch().try_wlatch());
+        assert!(page.latch().try_rlatch());
+        page.runlatch();
+        drop(page);
+
+        let page = bpm.fetch_page_read(second_id).unwrap();
+        assert_eq!(0, reinterpret::read_i32(&page.data()[SAFE_OFFSET..]));
+        drop(page);
+        assert!(bpm.fetch_page_write(first_id).is_ok());
+
+        // Dropping the guards released the latch.
+        let page = bpm.fetch_page(first_id).unwrap();
+        assert!(page.latch().try_wlatch());
+        page.wunlatch();
+    }
+
+    #[test]
+    fn views_of_raw_pages() {
+        let file_path = "/tmp/testfile.page_guard.2.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        // A single pool holds pages of every type; with two frames, the pages
+        // are evicted and read back in between.
+        let mut bpm = DefaultBufferPoolManager::<RawPage>::new(2, file_path).unwrap();
+        let header_id = {
+            let mut header = bpm.new_page_as::<HeaderPage>().unwrap();
+            header.init();
+            header.page_id()
+        };
+        let table_id = {
+            let mut table = bpm.new_page_as::<TablePage>().unwrap();
+            assert_eq!(INVALID_PAGE_ID, table.next_page_id());
+            table.set_next_page_id(42);
+            table.page_id()
+        };
+        let extent_id = {
+            let mut extent = bpm.new_page_as::<ExtentPage>().unwrap();
+            extent.init(table_id, /*first_page_id=*/ 10, /*used=*/ 1);
+            extent.page_id()
+        };
+        {
+            let mut header = bpm.fetch_page_as_mut::<HeaderPage>(header_id).unwrap();
+            assert_eq!(header_id, header.page_id());
+            assert!(header.insert_record("table", table_id).is_ok());
+        } // The view changed the frame in place.
+
+        let header = bpm.fetch_page_as::<HeaderPage>(header_id).unwrap();
+        assert_eq!(table_id, header.root_id("table").unwrap());
+        drop(header);
+        let table = bpm.fetch_page_as::<TablePage>(table_id).unwrap();
+        assert_eq!(table_id, table.page_id());
+        assert_eq!(42, table.next_page_id());
+        drop(table);
+        let mut extent = bpm.fetch_page_as_mut::<ExtentPage>(extent_id).unwrap();
+        assert!(extent.is_owned_by(table_id));
+        assert_eq!(Some(11), extent.next_page_id());
+        drop(extent);
+
+        // Views latch and unpin the frame like guards.
+        let page = bpm.fetch_page(extent_id).unwrap();
+        assert_eq!(1, page.pin_count());
+        assert!(page.latch().try_wlatch());
+        page.wunlatch();
+    }
+}
@@ -0,0 +1,114 @@
+// Functionality: Counts the pins held by every transaction, and caps them, so
+// that a runaway executor (e.g. one leaking the pins of an iterator) fails
+// with |QuotaExceeded| instead of pinning the whole pool and starving every
+// other transaction of frames. Pins are charged to the current owner, the
+// transaction the pool works for, which |BufferPoolManager::set_pin_owner|
+// sets when the transaction begins; pins taken without an owner, e.g. by the
+// catalog on open, are not counted.
+
+use crate::common::config::TransactionId;
+use crate::common::config::INVALID_TRANSACTION_ID;
+use crate::common::error::*;
+use std::collections::HashMap;
+
+pub struct PinQuota {
+    // The maximum number of pins of a transaction; 0 means no limit.
+    cap: usize,
+    owner: TransactionId,
+    pins: HashMap<TransactionId, usize>,
+}
+
+impl PinQuota {
+    pub fn new(cap: usize) -> Self {
+        PinQuota {
+            cap,
+            owner: INVALID_TRANSACTION_ID,
+            pins: HashMap::new(),
+        }
+    }
+
+    pub fn cap(&self) -> usize {
+        self.cap
+    }
+
+    pub fn set_cap(&mut self, cap: usize) {
+        self.cap = cap;
+    }
+
+    pub fn set_owner(&mut self, owner: TransactionId) {
+        self.owner = owner;
+    }
+
+    // Forgets the pins of |owner|, which no longer pins pages, and stops
+    // charging pins to it.
+    pub fn release(&mut self, owner: TransactionId) {
+        self.pins.remove(&owner);
+        if self.owner == owner {
+            self.owner = INVALID_TRANSACTION_ID;
+        }
+    }
+
+    pub fn pins(&self, owner: TransactionId) -> usize {
+        self.pins.get(&owner).copied().unwrap_or(0)
+    }
+
+    // Returns |QuotaExceeded| if the owner may not take another pin.
+    pub fn check(&self) -> std::io::Result<()> {
+        let pins = self.pins(self.owner);
+        if self.owner == INVALID_TRANSACTION_ID || self.cap == 0 || pins < self.cap {
+            return Ok(());
+        }
+        Err(quota_exceeded(&format!(
+            "Transaction pins too many pages; txn_id = {}, pins = {}",
+            self.owner, pins
+        )))
+    }
+
+    pub fn on_pin(&mut self) {
+        if self.owner != INVALID_TRANSACTION_ID {
+            *self.pins.entry(self.owner).or_default() += 1;
+        }
+    }
+
+    pub fn on_unpin(&mut self) {
+        if let Some(pins) = self.pins.get_mut(&self.owner) {
+            *pins = pins.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn check_and_release() {
+        let mut quota = PinQuota::new(2);
+        // Pins without an owner are not counted.
+        quota.on_pin();
+        assert_eq!(0, quota.pins(INVALID_TRANSACTION_ID));
+
+        quota.set_owner(1);
+        for _ in 0..2 {
+            assert!(quota.check().is_ok());
+            quota.on_pin();
+        }
+        assert_eq!(ErrorKind::QuotaExceeded, quota.check().unwrap_err().kind());
+        quota.on_unpin();
+        assert!(quota.check().is_ok());
+
+        // Other owners have quotas of their own.
+        quota.set_owner(2);
+        assert!(quota.check().is_ok());
+        quota.set_owner(1);
+        quota.on_pin();
+        quota.set_cap(0);
+        assert!(quota.check().is_ok());
+
+        quota.release(1);
+        assert_eq!(0, quota.pins(1));
+        quota.on_pin();
+        assert_eq!(0, quota.pins(1));
+    }
+}
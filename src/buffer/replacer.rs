@@ -16,4 +16,12 @@ where
     fn erase(&mut self, val: &T) -> bool;
     fn victim(&mut self) -> Option<T>;
     fn size(&self) -> usize;
+
+    // Records an access to |val|, for replacers that track access history.
+    fn record_access(&mut self, _val: &T) {}
+
+    // Forgets |val| altogether, including its access history.
+    fn remove(&mut self, val: &T) {
+        self.erase(val);
+    }
 }
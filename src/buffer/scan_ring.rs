@@ -0,0 +1,81 @@
+// Functionality: A few private frames that large sequential scans read pages
+// into instead of the buffer pool, reusing them in turn, so that a one-off
+// scan of a table larger than the pool does not evict the working set; see
+// |BufferPoolManager::read_page_for_scan|. Only copies of pages the pool does
+// not hold are stored, and they are dropped as soon as the pool loads or
+// writes the page, so they are never stale.
+
+use crate::common::config::PageId;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::page::page::Page;
+
+// The number of frames of the ring.
+pub const SCAN_RING_SIZE: usize = 8;
+
+pub struct ScanRing<T: Page> {
+    frames: Vec<T>,
+    // The frame to reuse next.
+    next: usize,
+}
+
+impl<T: Page> ScanRing<T> {
+    pub fn new() -> Self {
+        let frames = (0..SCAN_RING_SIZE)
+            .map(|_| {
+                let mut frame = T::default();
+                frame.set_page_id(INVALID_PAGE_ID);
+                frame
+            })
+            .collect();
+        ScanRing { frames, next: 0 }
+    }
+
+    pub fn get(&self, page_id: PageId) -> Option<&T> {
+        self.frames
+            .iter()
+            .find(|frame| page_id != INVALID_PAGE_ID && frame.page_id() == page_id)
+    }
+
+    // Stores a copy of the page |data|, in place of the oldest one.
+    pub fn insert(&mut self, page_id: PageId, data: &[u8]) {
+        self.remove(page_id);
+        let frame = &mut self.frames[self.next];
+        frame.set_page_id(page_id);
+        frame.data_mut().copy_from_slice(data);
+        self.next = (self.next + 1) % SCAN_RING_SIZE;
+    }
+
+    pub fn remove(&mut self, page_id: PageId) {
+        for frame in self.frames.iter_mut() {
+            if frame.page_id() == page_id {
+                frame.set_page_id(INVALID_PAGE_ID);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::config::PAGE_SIZE;
+    use crate::page::table_page::TablePage;
+
+    #[test]
+    fn insert_and_remove() {
+        let mut ring = ScanRing::<TablePage>::new();
+        assert!(ring.get(INVALID_PAGE_ID).is_none());
+        let mut data = [0; PAGE_SIZE];
+        for page_id in 0..(SCAN_RING_SIZE as PageId + 1) {
+            data[100] = page_id as u8;
+            ring.insert(page_id, &data);
+        }
+        // Page 0 was stored first, and its frame reused.
+        assert!(ring.get(0).is_none());
+        assert_eq!(1, ring.get(1).unwrap().data()[100]);
+        assert_eq!(8, ring.get(8).unwrap().data()[100]);
+
+        ring.remove(8);
+        assert!(ring.get(8).is_none());
+        assert!(ring.get(7).is_some());
+    }
+}
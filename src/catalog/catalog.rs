@@ -0,0 +1,1855 @@
+// Functionality: The catalog of a database, i.e. its tables and indexes with
+// their schemas, so that a database can be reopened and its tables known.
+//
+// Every table and index is described by an entry, stored as a tuple in the
+// catalog table heap, so entries are logged like any other tuple, and are
+// reloaded by scanning the heap on startup. The catalog table heap starts at
+// |CATALOG_PAGE_ID|, the page after the header page of the database file; both
+// are allocated when the catalog is created.
+//
+// Tables and indexes share one space of object IDs (OIDs), assigned in
+// increasing order. OIDs are not persisted on their own, so the largest ones
+// may be reused after their objects are dropped and the catalog reopened.
+// Index pages live in the database file next to the table pages, and the root
+// of every index is tracked in the header page under its name.
+//
+// Entry format (size in byte):
+//  ---------------------------------------------------------------------
+// | Kind (1) | Oid (4) | NameLen (4) | Name (NameLen) | Body (variable) |
+//  ---------------------------------------------------------------------
+// Table body:
+//  -------------------------------------------------------------------------
+// | FirstPageId (4) | Schema (variable) | Version (2) | HistoryCount (4) | ...
+//  -------------------------------------------------------------------------
+//  ----------------------------------------------------------------------
+// | History_1 (variable) | ... | DefaultCount (4) | Default_1 (variable) | ...
+//  ----------------------------------------------------------------------
+//  ---------------------------------------------------------------------
+// | Loading (1) | CheckCount (4) | Check_1 (variable) | ... | Check_N ... |
+//  ---------------------------------------------------------------------
+// See |Schema::serialize_to| for the schema format. The history holds the
+// schemas of the previous versions of the table, each as Version (2) and
+// Schema (variable). Every column added after the table was created has a
+// default, as NameLen (4), Name (NameLen), Version (2) it was added in,
+// ValueLen (4) and Value (ValueLen); a null default has no value. Loading is
+// 1 while an unlogged load of the table runs; see |Catalog::begin_load|.
+// Every check constraint is NameLen (4), Name (NameLen) and its expression;
+// see |Expression::serialize_to|. Entries of tables created before schema
+// versions end after the schema, those created before unlogged loads after
+// the defaults, and those created before check constraints after Loading.
+// Index body:
+//  -------------------------------
+// | TableOid (4) | KeyColumn (4) |
+//  -------------------------------
+// Statistics entries have the OID of their table, an empty name, and the
+// serialized statistics as body; see |TableStatistics::serialize_to|. Sketches
+// entries are alike, with the serialized sketches of the table as body; see
+// |TableSketches::serialize_to|. They are rewritten at checkpoints only, so
+// the sketches miss the writes since the last one after a crash.
+
+use crate::buffer::access_stats::PageHeat;
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::catalog::column::Column;
+use crate::catalog::schema::Schema;
+use crate::catalog::table_sketches::TableSketches;
+use crate::catalog::table_statistics::TableStatistics;
+use crate::common::config::PageId;
+use crate::common::config::TransactionId;
+use crate::common::config::CATALOG_PAGE_ID;
+use crate::common::config::HEADER_PAGE_ID;
+use crate::common::error::*;
+use crate::common::reinterpret;
+use crate::common::rid::Rid;
+use crate::disk::storage_backend::StorageBackend;
+use crate::index::bplus_tree::BPlusTree;
+use crate::index::index_util::clear_header;
+use crate::index::index_util::create_header;
+use crate::index::index_util::delete_root;
+use crate::page::table_page::TablePage;
+use crate::plan::expression::Expression;
+use crate::table::table_heap::TableHeap;
+use crate::table::table_heap::VacuumStats;
+use crate::table::tuple::Tuple;
+use crate::types::types::Operation;
+use crate::types::types::Types;
+use crate::types::value::Value;
+use std::cell::Cell;
+use std::cell::Ref;
+use std::cell::RefCell;
+use std::cell::RefMut;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::mem;
+
+pub type Oid = u32;
+
+const TABLE_ENTRY: u8 = 1;
+const INDEX_ENTRY: u8 = 2;
+const STATISTICS_ENTRY: u8 = 3;
+const SKETCHES_ENTRY: u8 = 4;
+
+#[derive(Debug)]
+pub struct TableInfo {
+    oid: Oid,
+    name: String,
+    schema: Schema<'static>,
+    first_page_id: PageId,
+    // The free-space hint of the table heap, carried over from one handle to
+    // the next; see |heap| and |TableHeap::free_space_hint|.
+    free_space_hint: Cell<PageId>,
+    // The version of |schema|, bumped whenever a column is added or dropped.
+    version: u16,
+    // The schemas of the previous versions, for the tuples written with them.
+    history: BTreeMap<u16, Schema<'static>>,
+    // The columns added after the table was created, with the versions they
+    // were added in and the values that older tuples read for them.
+    defaults: Vec<(String, u16, Value<'static>)>,
+    // The streaming statistics of the table, updated by the transactions
+    // writing it as they commit.
+    sketches: RefCell<TableSketches>,
+    // Whether an unlogged load of the table has begun and not ended; see
+    // |Catalog::begin_load|.
+    is_loading: bool,
+    // Whether the tuples of the table can be trusted, i.e. it was not left
+    // loading by a crash or a failed load.
+    is_valid: bool,
+    // The check constraints by name, each holding for a tuple unless its
+    // expression evaluates to false.
+    checks: Vec<(String, Expression)>,
+    // The location of the entry in the catalog table heap.
+    rid: Rid,
+}
+
+impl TableInfo {
+    pub fn oid(&self) -> Oid {
+        self.oid
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn schema(&self) -> &Schema<'static> {
+        &self.schema
+    }
+
+    pub fn first_page_id(&self) -> PageId {
+        self.first_page_id
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.is_valid
+    }
+
+    pub fn checks(&self) -> &[(String, Expression)] {
+        &self.checks
+    }
+
+    // Returns |InvalidInput| naming the first check constraint |tuple|, of
+    // the current schema, violates.
+    pub fn check_constraints(&self, tuple: &Tuple) -> std::io::Result<()> {
+        for (name, expression) in self.checks.iter() {
+            // Unknown, i.e. null, satisfies a check.
+            let violated = Expression::Not(Box::new(expression.clone()));
+            if violated.evaluate_predicate(tuple, &self.schema)? {
+                return Err(invalid_input(&format!(
+                    "Check constraint is violated; constraint = {}",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Returns |InvalidData| if the table is not valid, in which case it can
+    // only be dropped.
+    pub fn check_valid(&self) -> std::io::Result<()> {
+        match self.is_valid {
+            true => Ok(()),
+            false => Err(invalid_data(&format!(
+                "Table was left invalid by an unlogged load; name = {}",
+                self.name
+            ))),
+        }
+    }
+
+    // Opens the table heap holding the tuples of the table, with the
+    // free-space hint recorded by |set_free_space_hint|.
+    pub fn heap(&self) -> TableHeap {
+        let mut heap = TableHeap::open(self.first_page_id);
+        heap.set_free_space_hint(self.free_space_hint.get());
+        heap
+    }
+
+    // Records the free-space hint of |heap|, a handle returned by |heap|, for
+    // the handles opened later.
+    pub fn set_free_space_hint(&self, heap: &TableHeap) {
+        self.free_space_hint.set(heap.free_space_hint());
+    }
+
+    // The version of the schema, which new tuples need to be written with.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn sketches(&self) -> Ref<'_, TableSketches> {
+        self.sketches.borrow()
+    }
+
+    pub(crate) fn sketches_mut(&self) -> RefMut<'_, TableSketches> {
+        self.sketches.borrow_mut()
+    }
+
+    // Converts |tuple|, read from the table heap, to the current schema if it
+    // was written with a previous version: dropped columns are left out, and
+    // added columns read their defaults. Returns |InvalidData| if the version
+    // is unknown.
+    pub fn upgrade(&self, tuple: Tuple) -> std::io::Result<Tuple> {
+        let version = tuple.version();
+        if version == self.version {
+            return Ok(tuple);
+        }
+        let unknown = || invalid_data(&format!("Unknown schema version; version = {}", version));
+        let schema = self.history.get(&version).ok_or_else(unknown)?;
+        let mut values = Vec::new();
+        for column in self.schema.columns().iter() {
+            let default = self
+                .defaults
+                .iter()
+                .find(|(name, added, _)| name == column.name() && *added > version);
+            let value = match (default, schema.column_idx(column.name())) {
+                (Some((_, _, default)), _) => default.clone(),
+                (None, Some(idx)) => tuple.nth_value(schema, idx).to_static(),
+                (None, None) => return Err(unknown()),
+            };
+            values.push(value);
+        }
+        let mut upgraded = Tuple::new(&values, &self.schema);
+        upgraded.set_version(self.version);
+        Ok(upgraded)
+    }
+
+    // Moves to a new version with |schema|, keeping the current one in the
+    // history. Returns |InvalidInput| if there are too many versions.
+    fn evolve(&mut self, schema: Schema<'static>) -> std::io::Result<()> {
+        let version = self
+            .version
+            .checked_add(1)
+            .ok_or_else(|| invalid_input("Too many schema versions"))?;
+        self.sketches.replace(TableSketches::new(&schema));
+        let previous = std::mem::replace(&mut self.schema, schema);
+        self.history.insert(self.version, previous);
+        self.version = version;
+        Ok(())
+    }
+}
+
+// The object owning a page of the database file; see |Catalog::page_owners|.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PageOwner {
+    // The header page and the pages of the catalog table heap.
+    Catalog,
+    // The pages of the table heap of the table so named.
+    Table(String),
+    // The pages of the B+ tree of the index so named.
+    Index(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexInfo {
+    oid: Oid,
+    name: String,
+    table_oid: Oid,
+    // The index of the key column in the table schema.
+    key_column: usize,
+    // The location of the entry in the catalog table heap.
+    rid: Rid,
+}
+
+impl IndexInfo {
+    pub fn oid(&self) -> Oid {
+        self.oid
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn table_oid(&self) -> Oid {
+        self.table_oid
+    }
+
+    pub fn key_column(&self) -> usize {
+        self.key_column
+    }
+
+    // Opens the B+ tree of the index.
+    pub fn open<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<BPlusTree> {
+        BPlusTree::new(&self.name, bpm)
+    }
+}
+
+pub struct Catalog {
+    heap: TableHeap,
+    next_oid: Oid,
+    tables: BTreeMap<String, TableInfo>,
+    indexes: BTreeMap<String, IndexInfo>,
+    // The statistics of analyzed tables, with the locations of their entries.
+    statistics: BTreeMap<Oid, (TableStatistics, Rid)>,
+    // The locations of the sketches entries of the tables checkpointed.
+    sketches: BTreeMap<Oid, Rid>,
+}
+
+impl Catalog {
+    // Creates the catalog of a new database, allocating its header page and
+    // the first page of its heap. Returns |AlreadyExists| if the database
+    // already has pages.
+    pub fn create<S: StorageBackend>(
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<Self> {
+        create_header(bpm)?;
+        let heap = TableHeap::new(bpm)?;
+        if heap.first_page_id() != CATALOG_PAGE_ID {
+            bpm.delete_page(heap.first_page_id())?;
+            return Err(already_exists("Database is not empty"));
+        }
+        Ok(Catalog {
+            heap,
+            next_oid: 1,
+            tables: BTreeMap::new(),
+            indexes: BTreeMap::new(),
+            statistics: BTreeMap::new(),
+            sketches: BTreeMap::new(),
+        })
+    }
+
+    // Reloads the catalog of an existing database. Returns |NotFound| if the
+    // database has no catalog, and |InvalidData| if an entry is corrupted.
+    pub fn open<S: StorageBackend>(
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<Self> {
+        match bpm.fetch_page(CATALOG_PAGE_ID) {
+            Ok(_) => bpm.unpin_page(CATALOG_PAGE_ID, /*is_dirty=*/ false)?,
+            Err(_) => return Err(not_found("Database has no catalog")),
+        }
+        let mut catalog = Catalog {
+            heap: TableHeap::open(CATALOG_PAGE_ID),
+            next_oid: 1,
+            tables: BTreeMap::new(),
+            indexes: BTreeMap::new(),
+            statistics: BTreeMap::new(),
+            sketches: BTreeMap::new(),
+        };
+        let entries = catalog
+            .heap
+            .iter(bpm)
+            .collect::<std::io::Result<Vec<(Rid, Tuple)>>>()?;
+        // Statistics and sketches are decoded once all tables are known.
+        let mut statistics = Vec::new();
+        let mut sketches = Vec::new();
+        for (rid, tuple) in entries {
+            let mut data = vec![0; mem::size_of::<u64>() + tuple.len()];
+            tuple.serialize_to(&mut data);
+            match decode_entry(&data[mem::size_of::<u64>()..], rid)? {
+                Entry::Table(table) => {
+                    catalog.next_oid = catalog.next_oid.max(table.oid + 1);
+                    catalog.tables.insert(table.name.clone(), table);
+                }
+                Entry::Index(index) => {
+                    catalog.next_oid = catalog.next_oid.max(index.oid + 1);
+                    catalog.indexes.insert(index.name.clone(), index);
+                }
+                Entry::Statistics(table_oid, body, rid) => statistics.push((table_oid, body, rid)),
+                Entry::Sketches(table_oid, body, rid) => sketches.push((table_oid, body, rid)),
+            }
+        }
+        for (table_oid, body, rid) in statistics {
+            let table = catalog
+                .table_by_oid(table_oid)
+                .ok_or_else(|| invalid_data("Statistics of an unknown table"))?;
+            let decoded = TableStatistics::deserialize_from(&body, table.schema())?;
+            catalog.statistics.insert(table_oid, (decoded, rid));
+        }
+        for (table_oid, body, rid) in sketches {
+            let table = catalog
+                .table_by_oid(table_oid)
+                .ok_or_else(|| invalid_data("Sketches of an unknown table"))?;
+            let decoded = TableSketches::deserialize_from(&body)?;
+            if decoded.column_count() != table.schema.columns().len() {
+                return Err(invalid_data("Sketches do not match the table schema"));
+            }
+            table.sketches.replace(decoded);
+            catalog.sketches.insert(table_oid, rid);
+        }
+        Ok(catalog)
+    }
+
+    pub fn table(&self, name: &str) -> Option<&TableInfo> {
+        self.tables.get(name)
+    }
+
+    pub fn table_by_oid(&self, oid: Oid) -> Option<&TableInfo> {
+        self.tables.values().find(|table| table.oid == oid)
+    }
+
+    // Returns all tables, ordered by name.
+    pub fn tables(&self) -> Vec<&TableInfo> {
+        self.tables.values().collect()
+    }
+
+    pub fn index(&self, name: &str) -> Option<&IndexInfo> {
+        self.indexes.get(name)
+    }
+
+    // Returns the indexes of the table |table_oid|, ordered by name.
+    pub fn table_indexes(&self, table_oid: Oid) -> Vec<&IndexInfo> {
+        self.indexes
+            .values()
+            .filter(|index| index.table_oid == table_oid)
+            .collect()
+    }
+
+    // Returns the statistics of the table |table_oid|, if it was analyzed.
+    pub fn statistics(&self, table_oid: Oid) -> Option<&TableStatistics> {
+        self.statistics
+            .get(&table_oid)
+            .map(|(statistics, _)| statistics)
+    }
+
+    // Scans the table |name| to compute its statistics, and persists them in
+    // place of the previous ones. The sketches of the table are computed anew
+    // from the scan too, e.g. for a table written before it had sketches.
+    // Returns |NotFound| if there is no such table.
+    pub fn analyze<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        name: &str,
+    ) -> std::io::Result<&TableStatistics> {
+        let table = self
+            .tables
+            .get(name)
+            .ok_or_else(|| not_found(&format!("No such table; name = {}", name)))?;
+        table.check_valid()?;
+        let table_oid = table.oid;
+        let statistics = TableStatistics::analyze(bpm, table)?;
+        let mut sketches = TableSketches::new(&table.schema);
+        for item in table.heap().iter(bpm) {
+            let tuple = table.upgrade(item?.1)?;
+            sketches.on_insert(&table.schema, &tuple);
+        }
+        let body = sketches.checkpoint();
+        table.sketches.replace(sketches);
+        self.write_sketches(bpm, txn_id, table_oid, &body)?;
+        let entry = encode_statistics(table_oid, &statistics);
+        if let Some((_, rid)) = self.statistics.remove(&table_oid) {
+            self.delete_entry(bpm, txn_id, &rid)?;
+        }
+        let rid = self.insert_entry(bpm, txn_id, &entry)?;
+        self.statistics.insert(table_oid, (statistics, rid));
+        Ok(&self.statistics[&table_oid].0)
+    }
+
+    // Persists the sketches of the tables changed since the last checkpoint,
+    // in place of their previous ones.
+    pub fn checkpoint_sketches<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+    ) -> std::io::Result<()> {
+        let changed: Vec<(Oid, Vec<u8>)> = self
+            .tables
+            .values()
+            .filter(|table| table.sketches().changes_since_checkpoint() > 0)
+            .map(|table| (table.oid, table.sketches_mut().checkpoint()))
+            .collect();
+        for (table_oid, body) in changed {
+            self.write_sketches(bpm, txn_id, table_oid, &body)?;
+        }
+        Ok(())
+    }
+
+    // Returns the number of writes to the tables since the last checkpoint of
+    // their sketches.
+    pub fn sketch_changes(&self) -> u64 {
+        self.tables
+            .values()
+            .map(|table| table.sketches().changes_since_checkpoint())
+            .sum()
+    }
+
+    // Reclaims the space of the dead tuples of the table |name|; see
+    // |TableHeap::vacuum|, including when it may run. Returns |NotFound| if
+    // there is no such table.
+    pub fn vacuum<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        name: &str,
+    ) -> std::io::Result<VacuumStats> {
+        let table = self
+            .tables
+            .get(name)
+            .ok_or_else(|| not_found(&format!("No such table; name = {}", name)))?;
+        table.check_valid()?;
+        let mut heap = table.heap();
+        let stats = heap.vacuum(bpm)?;
+        table.set_free_space_hint(&heap);
+        Ok(stats)
+    }
+
+    // Marks the table |name| as loading, before its tuples are inserted
+    // without being logged. The mark is logged like any entry, so a table
+    // whose load a crash interrupted is known to be invalid once the catalog
+    // is reopened. Returns |NotFound| if there is no such table, and
+    // |InvalidData| if it is invalid or loading already.
+    pub fn begin_load<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        name: &str,
+    ) -> std::io::Result<()> {
+        let table = self
+            .tables
+            .get_mut(name)
+            .ok_or_else(|| not_found(&format!("No such table; name = {}", name)))?;
+        table.check_valid()?;
+        if table.is_loading {
+            return Err(invalid_data(&format!(
+                "Table is loading already; name = {}",
+                name
+            )));
+        }
+        table.is_loading = true;
+        self.rewrite_table_entry(bpm, txn_id, name)
+    }
+
+    // Removes the mark of |begin_load| from the table |name|, once the tuples
+    // loaded are on disk. Returns |NotFound| if there is no such table.
+    pub fn end_load<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        name: &str,
+    ) -> std::io::Result<()> {
+        let table = self
+            .tables
+            .get_mut(name)
+            .ok_or_else(|| not_found(&format!("No such table; name = {}", name)))?;
+        table.is_loading = false;
+        self.rewrite_table_entry(bpm, txn_id, name)
+    }
+
+    // Adds the check constraint |name| to the table |table_name|, holding for
+    // the tuples for which |expression|, over the columns of the table, does
+    // not evaluate to false. Returns |NotFound| if there is no such table,
+    // |AlreadyExists| if it has a check named alike, and |InvalidInput| if
+    // |expression| is not a boolean one, or a tuple of the table violates it.
+    pub fn add_check<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        table_name: &str,
+        name: &str,
+        expression: Expression,
+    ) -> std::io::Result<()> {
+        let table = self
+            .tables
+            .get_mut(table_name)
+            .ok_or_else(|| not_found(&format!("No such table; name = {}", table_name)))?;
+        table.check_valid()?;
+        validate_check(&table.schema, &table.checks, name, &expression)?;
+        table.checks.push((name.to_string(), expression));
+        let table = &self.tables[table_name];
+        let mut result = Ok(());
+        for item in table.heap().iter(bpm) {
+            result = item
+                .and_then(|(_, tuple)| table.upgrade(tuple))
+                .and_then(|tuple| table.check_constraints(&tuple));
+            if result.is_err() {
+                break;
+            }
+        }
+        if let Err(e) = result {
+            self.tables.get_mut(table_name).unwrap().checks.pop();
+            return Err(e);
+        }
+        self.rewrite_table_entry(bpm, txn_id, table_name)
+    }
+
+    // Marks the table |name| as invalid until the catalog is closed, e.g.
+    // after its load failed; it stays invalid once reopened since it is still
+    // marked as loading.
+    pub fn invalidate(&mut self, name: &str) {
+        if let Some(table) = self.tables.get_mut(name) {
+            table.is_valid = false;
+        }
+    }
+
+    // Maps the pages of the database file to the objects owning them; see
+    // |TableHeap::page_ids| and |BPlusTree::page_ids|. Pages owned by no object
+    // (e.g. the overflow chains of deleted tuples) are left out. Every page of
+    // the objects is fetched, without sampling the accesses.
+    pub fn page_owners<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<HashMap<PageId, PageOwner>> {
+        bpm.without_access_sampling(|bpm| {
+            let mut owners = HashMap::new();
+            owners.insert(HEADER_PAGE_ID, PageOwner::Catalog);
+            for page_id in self.heap.page_ids(bpm)? {
+                owners.insert(page_id, PageOwner::Catalog);
+            }
+            for table in self.tables.values() {
+                for page_id in table.heap().page_ids(bpm)? {
+                    owners.insert(page_id, PageOwner::Table(table.name.clone()));
+                }
+            }
+            for index in self.indexes.values() {
+                for page_id in index.open(bpm)?.page_ids(bpm)? {
+                    owners.insert(page_id, PageOwner::Index(index.name.clone()));
+                }
+            }
+            Ok(owners)
+        })
+    }
+
+    // Returns up to |n| pages with the most sampled accesses, hottest first,
+    // with the objects owning them; see |BufferPoolManager::hottest_pages|.
+    pub fn hottest_pages<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        n: usize,
+    ) -> std::io::Result<Vec<(PageHeat, Option<PageOwner>)>> {
+        let hottest = bpm.hottest_pages(n);
+        let mut owners = self.page_owners(bpm)?;
+        Ok(hottest
+            .into_iter()
+            .map(|heat| {
+                let owner = owners.remove(&heat.page_id);
+                (heat, owner)
+            })
+            .collect())
+    }
+
+    // Creates an empty table named |name|, and returns its OID. Returns
+    // |AlreadyExists| if there is such a table already.
+    pub fn create_table<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        name: &str,
+        schema: Schema<'static>,
+    ) -> std::io::Result<Oid> {
+        self.create_table_with_checks(bpm, txn_id, name, schema, vec![])
+    }
+
+    // Creates an empty table like |create_table|, with the check constraints
+    // |checks| by name; see |add_check|. Returns |AlreadyExists| if two checks
+    // are named alike, and |InvalidInput| if a check is not a boolean
+    // expression.
+    pub fn create_table_with_checks<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        name: &str,
+        schema: Schema<'static>,
+        checks: Vec<(String, Expression)>,
+    ) -> std::io::Result<Oid> {
+        if self.tables.contains_key(name) {
+            return Err(already_exists(&format!("Table exists; name = {}", name)));
+        }
+        if schema.columns().is_empty() {
+            return Err(invalid_input("Table has no columns"));
+        }
+        for (idx, (check, expression)) in checks.iter().enumerate() {
+            validate_check(&schema, &checks[..idx], check, expression)?;
+        }
+        let first_page_id = TableHeap::new(bpm)?.first_page_id();
+        let mut table = TableInfo {
+            oid: self.next_oid,
+            name: name.to_string(),
+            sketches: RefCell::new(TableSketches::new(&schema)),
+            schema,
+            first_page_id,
+            free_space_hint: Cell::new(first_page_id),
+            version: 0,
+            history: BTreeMap::new(),
+            defaults: Vec::new(),
+            is_loading: false,
+            is_valid: true,
+            checks,
+            rid: Rid::default(),
+        };
+        table.rid = self.insert_entry(bpm, txn_id, &encode_table(&table))?;
+        self.next_oid += 1;
+        self.tables.insert(table.name.clone(), table);
+        Ok(self.next_oid - 1)
+    }
+
+    // Drops the table named |name| together with its indexes. Returns
+    // |NotFound| if there is no such table.
+    // Note: The pages of dropped tables and indexes are not reclaimed.
+    pub fn drop_table<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        name: &str,
+    ) -> std::io::Result<()> {
+        let table = self
+            .tables
+            .get(name)
+            .ok_or_else(|| not_found(&format!("No such table; name = {}", name)))?;
+        let index_names: Vec<String> = self
+            .table_indexes(table.oid)
+            .iter()
+            .map(|index| index.name.clone())
+            .collect();
+        for index_name in index_names.iter() {
+            self.drop_index(bpm, txn_id, index_name)?;
+        }
+        let table_oid = self.tables[name].oid;
+        if let Some((_, rid)) = self.statistics.remove(&table_oid) {
+            self.delete_entry(bpm, txn_id, &rid)?;
+        }
+        if let Some(rid) = self.sketches.remove(&table_oid) {
+            self.delete_entry(bpm, txn_id, &rid)?;
+        }
+        let rid = self.tables[name].rid.clone();
+        self.delete_entry(bpm, txn_id, &rid)?;
+        self.tables.remove(name);
+        Ok(())
+    }
+
+    // Renames the table |name| to |new_name|. Its indexes, statistics and
+    // sketches refer to it by OID, so only its entry is rewritten. Returns
+    // |NotFound| if there is no such table, and |AlreadyExists| if a table is
+    // named |new_name| already.
+    pub fn rename_table<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        name: &str,
+        new_name: &str,
+    ) -> std::io::Result<()> {
+        if self.tables.contains_key(new_name) {
+            return Err(already_exists(&format!(
+                "Table exists; name = {}",
+                new_name
+            )));
+        }
+        let mut table = self
+            .tables
+            .remove(name)
+            .ok_or_else(|| not_found(&format!("No such table; name = {}", name)))?;
+        table.name = new_name.to_string();
+        self.tables.insert(new_name.to_string(), table);
+        self.rewrite_table_entry(bpm, txn_id, new_name)
+    }
+
+    // Adds |column| as the last column of the table |table_name|. The tuples
+    // of the table are not rewritten; those written before read |default| for
+    // the column. Returns |NotFound| if there is no such table,
+    // |AlreadyExists| if it has a column named alike, and |InvalidInput| if
+    // |default| is not of the column type.
+    // Note: The statistics of the table are dropped, until it is analyzed.
+    pub fn add_column<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        table_name: &str,
+        column: Column<'static>,
+        default: Value<'static>,
+    ) -> std::io::Result<()> {
+        let table = self
+            .tables
+            .get_mut(table_name)
+            .ok_or_else(|| not_found(&format!("No such table; name = {}", table_name)))?;
+        if table.schema.column_idx(column.name()).is_some() {
+            return Err(already_exists(&format!(
+                "Column exists; name = {}",
+                column.name()
+            )));
+        }
+        if default.borrow().id() != column.types().id() {
+            return Err(invalid_input("Default does not match column type"));
+        }
+        let mut columns = static_columns(&table.schema, |_| true);
+        let name = column.name().to_string();
+        columns.push(column);
+        table.evolve(Schema::new(columns))?;
+        let version = table.version;
+        table.defaults.retain(|(other, _, _)| *other != name);
+        table.defaults.push((name, version, default));
+        self.replace_table_entry(bpm, txn_id, table_name)
+    }
+
+    // Drops the column |column_name| of the table |table_name|. The tuples of
+    // the table are not rewritten; the values of the column are ignored when
+    // they are read. Returns |NotFound| if there is no such table or column,
+    // and |InvalidInput| if the column is the last one, or is indexed.
+    // Note: The statistics of the table are dropped, until it is analyzed.
+    pub fn drop_column<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        table_name: &str,
+        column_name: &str,
+    ) -> std::io::Result<()> {
+        let table = self
+            .tables
+            .get(table_name)
+            .ok_or_else(|| not_found(&format!("No such table; name = {}", table_name)))?;
+        let column_idx = table
+            .schema
+            .column_idx(column_name)
+            .ok_or_else(|| not_found(&format!("No such column; name = {}", column_name)))?;
+        if table.schema.columns().len() == 1 {
+            return Err(invalid_input("Cannot drop the last column"));
+        }
+        if let Some((name, _)) = table
+            .checks
+            .iter()
+            .find(|(_, expression)| expression.column_indices().contains(&column_idx))
+        {
+            return Err(invalid_input(&format!(
+                "Column is checked; name = {}, constraint = {}",
+                column_name, name
+            )));
+        }
+        let table_oid = table.oid;
+        let indexes = self.table_indexes(table_oid);
+        if indexes.iter().any(|index| index.key_column == column_idx) {
+            return Err(invalid_input(&format!(
+                "Column is indexed; name = {}",
+                column_name
+            )));
+        }
+
+        // The key columns of the indexes after the dropped column move.
+        let moved: Vec<String> = indexes
+            .iter()
+            .filter(|index| index.key_column > column_idx)
+            .map(|index| index.name.clone())
+            .collect();
+        for name in moved.iter() {
+            let index = self.indexes.get_mut(name).unwrap();
+            index.key_column -= 1;
+            let (rid, entry) = (index.rid.clone(), encode_index(index));
+            self.delete_entry(bpm, txn_id, &rid)?;
+            let rid = self.insert_entry(bpm, txn_id, &entry)?;
+            self.indexes.get_mut(name).unwrap().rid = rid;
+        }
+
+        let table = self.tables.get_mut(table_name).unwrap();
+        let columns = static_columns(&table.schema, |column| column.name() != column_name);
+        table.evolve(Schema::new(columns))?;
+        table.defaults.retain(|(name, _, _)| name != column_name);
+        // So do the columns of the checks.
+        for (_, expression) in table.checks.iter_mut() {
+            expression.remap_columns(&|idx| match idx > column_idx {
+                true => idx - 1,
+                false => idx,
+            });
+        }
+        self.replace_table_entry(bpm, txn_id, table_name)
+    }
+
+    // Renames the column |column_name| of the table |table_name| to
+    // |new_name|, in the schemas of all versions, so that the tuples written
+    // with previous versions still read it. Indexes, statistics and sketches
+    // refer to columns by position, so only the entry of the table is
+    // rewritten. Returns |NotFound| if there is no such table or column,
+    // |AlreadyExists| if the table has a column named |new_name|, and
+    // |InvalidInput| if a dropped column was named |new_name|.
+    pub fn rename_column<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        table_name: &str,
+        column_name: &str,
+        new_name: &str,
+    ) -> std::io::Result<()> {
+        let table = self
+            .tables
+            .get_mut(table_name)
+            .ok_or_else(|| not_found(&format!("No such table; name = {}", table_name)))?;
+        if table.schema.column_idx(new_name).is_none()
+            && table
+                .history
+                .values()
+                .any(|schema| schema.column_idx(new_name).is_some())
+        {
+            return Err(invalid_input(&format!(
+                "A dropped column had the name; name = {}",
+                new_name
+            )));
+        }
+        table.schema.rename_column(column_name, new_name)?;
+        for schema in table.history.values_mut() {
+            if schema.column_idx(column_name).is_some() {
+                schema.rename_column(column_name, new_name)?;
+            }
+        }
+        for (name, _, _) in table.defaults.iter_mut() {
+            if name == column_name {
+                *name = new_name.to_string();
+            }
+        }
+        self.rewrite_table_entry(bpm, txn_id, table_name)
+    }
+
+    // Creates a B+ tree index named |name| on the integer column |column_name|
+    // of the table |table_name|, fills it with the tuples of the table, and
+    // returns its OID. Null keys are not indexed. Returns |InvalidInput| if the
+    // column is not an integer column, or holds duplicate keys.
+    pub fn create_index<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        name: &str,
+        table_name: &str,
+        column_name: &str,
+    ) -> std::io::Result<Oid> {
+        if self.indexes.contains_key(name) {
+            return Err(already_exists(&format!("Index exists; name = {}", name)));
+        }
+        let table = self
+            .tables
+            .get(table_name)
+            .ok_or_else(|| not_found(&format!("No such table; name = {}", table_name)))?;
+        table.check_valid()?;
+        let key_column = table
+            .schema
+            .column_idx(column_name)
+            .ok_or_else(|| not_found(&format!("No such column; name = {}", column_name)))?;
+        if !(2..=5).contains(&table.schema.nth_types(key_column).unwrap().id()) {
+            return Err(invalid_input("Index key needs to be an integer column"));
+        }
+
+        let mut tree = BPlusTree::new(name, bpm)?;
+        if !tree.is_empty() {
+            return Err(already_exists(&format!("Index exists; name = {}", name)));
+        }
+        let filled = fill_index(bpm, &mut tree, table, key_column);
+        let mut index = IndexInfo {
+            oid: self.next_oid,
+            name: name.to_string(),
+            table_oid: table.oid,
+            key_column,
+            rid: Rid::default(),
+        };
+        let inserted = filled.and_then(|_| self.insert_entry(bpm, txn_id, &encode_index(&index)));
+        match inserted {
+            Ok(rid) => index.rid = rid,
+            Err(e) => {
+                delete_root(bpm, name)?;
+                return Err(e);
+            }
+        }
+        self.next_oid += 1;
+        self.indexes.insert(index.name.clone(), index);
+        Ok(self.next_oid - 1)
+    }
+
+    // Fills every index anew from its table, after clearing the roots in the
+    // header page, e.g. after a crash lost index pages whose tuples the log
+    // kept. Like those of dropped indexes, the pages of the old trees are not
+    // reclaimed.
+    pub fn rebuild_indexes<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<()> {
+        clear_header(bpm)?;
+        for index in self.indexes.values() {
+            let table = self.table_by_oid(index.table_oid).ok_or_else(|| {
+                invalid_data(&format!("No table of index; name = {}", index.name))
+            })?;
+            let mut tree = BPlusTree::new(&index.name, bpm)?;
+            // The tuples of an invalid table cannot be read safely, so its
+            // indexes are left empty.
+            if table.is_valid {
+                fill_index(bpm, &mut tree, table, index.key_column)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Rebuilds the index |name| from its table with the bulk loader, e.g. to
+    // compact a tree left sparse by removes; see |BPlusTree::rebuild|. The old
+    // tree serves reads until the header page records the new root, which is
+    // flushed before the pages of the old tree are freed, so that the header
+    // page on disk never refers to freed pages. Returns |NotFound| if there is
+    // no such index, and |InvalidInput| if the table holds duplicate keys.
+    pub fn reindex<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        name: &str,
+    ) -> std::io::Result<()> {
+        let index = self
+            .indexes
+            .get(name)
+            .ok_or_else(|| not_found(&format!("No such index; name = {}", name)))?;
+        let table = self
+            .table_by_oid(index.table_oid)
+            .ok_or_else(|| invalid_data(&format!("No table of index; name = {}", name)))?;
+        table.check_valid()?;
+        let entries = index_entries(bpm, table, index.key_column)?;
+        let old_page_ids = index.open(bpm)?.rebuild(bpm, &entries)?;
+        bpm.flush_page(HEADER_PAGE_ID)?;
+        for page_id in old_page_ids {
+            bpm.delete_page(page_id)?;
+        }
+        Ok(())
+    }
+
+    // Drops the index named |name|. Returns |NotFound| if there is no such
+    // index.
+    pub fn drop_index<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        name: &str,
+    ) -> std::io::Result<()> {
+        let rid = match self.indexes.get(name) {
+            Some(index) => index.rid.clone(),
+            None => return Err(not_found(&format!("No such index; name = {}", name))),
+        };
+        self.delete_entry(bpm, txn_id, &rid)?;
+        self.indexes.remove(name);
+        delete_root(bpm, name)
+    }
+
+    // Renames the index |name| to |new_name|: the record of its root in the
+    // header page, then its entry. The header page is not logged, so the
+    // record is renamed back if rewriting the entry fails. Returns |NotFound|
+    // if there is no such index, and |AlreadyExists| if an index is named
+    // |new_name| already.
+    pub fn rename_index<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        name: &str,
+        new_name: &str,
+    ) -> std::io::Result<()> {
+        if self.indexes.contains_key(new_name) {
+            return Err(already_exists(&format!(
+                "Index exists; name = {}",
+                new_name
+            )));
+        }
+        let index = self
+            .indexes
+            .get(name)
+            .ok_or_else(|| not_found(&format!("No such index; name = {}", name)))?;
+        let mut tree = index.open(bpm)?;
+        tree.rename(bpm, new_name)?;
+        let mut renamed = index.clone();
+        renamed.name = new_name.to_string();
+        let rewritten = self.delete_entry(bpm, txn_id, &renamed.rid).and_then(|_| {
+            let entry = encode_index(&renamed);
+            self.insert_entry(bpm, txn_id, &entry)
+        });
+        match rewritten {
+            Ok(rid) => renamed.rid = rid,
+            Err(e) => {
+                tree.rename(bpm, name)?;
+                return Err(e);
+            }
+        }
+        self.indexes.remove(name);
+        self.indexes.insert(new_name.to_string(), renamed);
+        Ok(())
+    }
+
+    // Rewrites the entry of the table |name| after its schema changed, and
+    // drops its statistics and sketches entries, which no longer match the
+    // schema.
+    fn replace_table_entry<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        name: &str,
+    ) -> std::io::Result<()> {
+        let table_oid = self.tables[name].oid;
+        if let Some((_, rid)) = self.statistics.remove(&table_oid) {
+            self.delete_entry(bpm, txn_id, &rid)?;
+        }
+        if let Some(rid) = self.sketches.remove(&table_oid) {
+            self.delete_entry(bpm, txn_id, &rid)?;
+        }
+        self.rewrite_table_entry(bpm, txn_id, name)
+    }
+
+    // Rewrites the entry of the table |name| after it changed.
+    fn rewrite_table_entry<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        name: &str,
+    ) -> std::io::Result<()> {
+        let rid = self.tables[name].rid.clone();
+        self.delete_entry(bpm, txn_id, &rid)?;
+        let entry = encode_table(&self.tables[name]);
+        let rid = self.insert_entry(bpm, txn_id, &entry)?;
+        self.tables.get_mut(name).unwrap().rid = rid;
+        Ok(())
+    }
+
+    // Writes |body|, the serialized sketches of the table |table_oid|, in
+    // place of its previous sketches entry.
+    fn write_sketches<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        table_oid: Oid,
+        body: &[u8],
+    ) -> std::io::Result<()> {
+        if let Some(rid) = self.sketches.remove(&table_oid) {
+            self.delete_entry(bpm, txn_id, &rid)?;
+        }
+        let mut entry = encode_header(SKETCHES_ENTRY, table_oid, "");
+        entry.extend_from_slice(body);
+        let rid = self.insert_entry(bpm, txn_id, &entry)?;
+        self.sketches.insert(table_oid, rid);
+        Ok(())
+    }
+
+    fn insert_entry<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        entry: &[u8],
+    ) -> std::io::Result<Rid> {
+        let mut data = vec![0; mem::size_of::<u64>() + entry.len()];
+        reinterpret::write_u64(&mut data, entry.len() as u64);
+        data[mem::size_of::<u64>()..].copy_from_slice(entry);
+        let mut tuple = Tuple::default();
+        tuple.deserialize_from(&data);
+        self.heap.insert_tuple(bpm, txn_id, tuple)
+    }
+
+    fn delete_entry<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        rid: &Rid,
+    ) -> std::io::Result<()> {
+        self.heap.mark_delete(bpm, txn_id, rid)?;
+        self.heap.apply_delete(bpm, txn_id, rid)
+    }
+}
+
+enum Entry {
+    Table(TableInfo),
+    Index(IndexInfo),
+    // The OID of the table, and the serialized statistics.
+    Statistics(Oid, Vec<u8>, Rid),
+    // The OID of the table, and the serialized sketches.
+    Sketches(Oid, Vec<u8>, Rid),
+}
+
+// Inserts the key of every tuple of |table| into |tree|. The keys are
+// collected first, as the heap iterator borrows the buffer pool.
+fn fill_index<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    tree: &mut BPlusTree,
+    table: &TableInfo,
+    key_column: usize,
+) -> std::io::Result<()> {
+    for (key, rid) in index_entries(bpm, table, key_column)? {
+        tree.insert(bpm, key, rid)?;
+    }
+    Ok(())
+}
+
+// Returns the keys of the column |key_column| of the tuples of |table| with
+// their RIDs, in key order. Null keys are left out. Returns |InvalidInput| if
+// the column holds duplicate keys.
+fn index_entries<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    table: &TableInfo,
+    key_column: usize,
+) -> std::io::Result<Vec<(i64, Rid)>> {
+    let mut keys = Vec::new();
+    for item in table.heap().iter(bpm) {
+        let (rid, tuple) = item?;
+        let tuple = table.upgrade(tuple)?;
+        let value = tuple.nth_value(&table.schema, key_column);
+        if value.is_null() {
+            continue;
+        }
+        let key = value
+            .borrow()
+            .get_as_i64()
+            .map_err(|_| invalid_data("Index key is not an integer"))?;
+        keys.push((key, rid));
+    }
+    keys.sort_by_key(|(key, _)| *key);
+    if let Some(pair) = keys.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Err(invalid_input(&format!(
+            "Duplicate key; key = {}",
+            pair[0].0
+        )));
+    }
+    Ok(keys)
+}
+
+// Returns copies of the columns of |schema| that satisfy |predicate|.
+fn static_columns<F>(schema: &Schema, predicate: F) -> Vec<Column<'static>>
+where
+    F: Fn(&Column) -> bool,
+{
+    schema
+        .columns()
+        .iter()
+        .filter(|column| predicate(column))
+        .map(|column| {
+            Column::new(
+                column.name().to_string(),
+                column.types().to_static(),
+                column.len(),
+            )
+        })
+        .collect()
+}
+
+// Returns |AlreadyExists| if one of |checks| is named |name|, and
+// |InvalidInput| if |expression| is not a boolean one over |schema|.
+fn validate_check(
+    schema: &Schema,
+    checks: &[(String, Expression)],
+    name: &str,
+    expression: &Expression,
+) -> std::io::Result<()> {
+    if checks.iter().any(|(other, _)| other == name) {
+        return Err(already_exists(&format!(
+            "Check constraint exists; name = {}",
+            name
+        )));
+    }
+    let column = expression.output_column(schema, name)?;
+    if column.types().id() != Types::boolean().id() {
+        return Err(invalid_input(&format!(
+            "Check constraint is not a boolean; name = {}",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn encode_header(kind: u8, oid: Oid, name: &str) -> Vec<u8> {
+    let mut data = vec![kind];
+    data.extend_from_slice(&oid.to_le_bytes());
+    encode_str(&mut data, name);
+    data
+}
+
+fn encode_str(data: &mut Vec<u8>, s: &str) {
+    data.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    data.extend_from_slice(s.as_bytes());
+}
+
+fn encode_table(table: &TableInfo) -> Vec<u8> {
+    let mut data = encode_header(TABLE_ENTRY, table.oid, &table.name);
+    data.extend_from_slice(&table.first_page_id.to_le_bytes());
+    encode_schema(&mut data, &table.schema);
+    data.extend_from_slice(&table.version.to_le_bytes());
+    data.extend_from_slice(&(table.history.len() as u32).to_le_bytes());
+    for (version, schema) in table.history.iter() {
+        data.extend_from_slice(&version.to_le_bytes());
+        encode_schema(&mut data, schema);
+    }
+    data.extend_from_slice(&(table.defaults.len() as u32).to_le_bytes());
+    for (name, version, default) in table.defaults.iter() {
+        encode_str(&mut data, name);
+        data.extend_from_slice(&version.to_le_bytes());
+        // Room for the value, as in a tuple.
+        let len = match default.is_null() {
+            true => 0,
+            false => default.len() + mem::size_of::<u64>(),
+        };
+        data.extend_from_slice(&(len as u32).to_le_bytes());
+        let offset = data.len();
+        data.resize(offset + len, 0);
+        default.serialize_to(&mut data[offset..]);
+    }
+    data.push(table.is_loading as u8);
+    data.extend_from_slice(&(table.checks.len() as u32).to_le_bytes());
+    for (name, expression) in table.checks.iter() {
+        encode_str(&mut data, name);
+        let offset = data.len();
+        data.resize(offset + expression.serialized_len(), 0);
+        expression.serialize_to(&mut data[offset..]);
+    }
+    data
+}
+
+fn encode_schema(data: &mut Vec<u8>, schema: &Schema) {
+    let offset = data.len();
+    data.resize(offset + schema.serialized_len(), 0);
+    schema.serialize_to(&mut data[offset..]);
+}
+
+fn encode_index(index: &IndexInfo) -> Vec<u8> {
+    let mut data = encode_header(INDEX_ENTRY, index.oid, &index.name);
+    data.extend_from_slice(&index.table_oid.to_le_bytes());
+    data.extend_from_slice(&(index.key_column as u32).to_le_bytes());
+    data
+}
+
+fn encode_statistics(table_oid: Oid, statistics: &TableStatistics) -> Vec<u8> {
+    let mut data = encode_header(STATISTICS_ENTRY, table_oid, "");
+    let offset = data.len();
+    data.resize(offset + statistics.serialized_len(), 0);
+    statistics.serialize_to(&mut data[offset..]);
+    data
+}
+
+// Reads the serialized entry |src| field by field.
+struct Decoder<'a> {
+    src: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn bytes(&mut self, len: usize) -> std::io::Result<&'a [u8]> {
+        if self.src.len() < self.offset + len {
+            return Err(invalid_data("Catalog entry is truncated"));
+        }
+        self.offset += len;
+        Ok(&self.src[(self.offset - len)..self.offset])
+    }
+
+    fn u8(&mut self) -> std::io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> std::io::Result<u16> {
+        Ok(reinterpret::read_i16(self.bytes(2)?) as u16)
+    }
+
+    fn u32(&mut self) -> std::io::Result<u32> {
+        Ok(reinterpret::read_u32(self.bytes(4)?))
+    }
+
+    fn string(&mut self) -> std::io::Result<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec())
+            .map_err(|_| invalid_data("Catalog entry has an invalid name"))
+    }
+
+    fn schema(&mut self) -> std::io::Result<Schema<'static>> {
+        let schema = Schema::deserialize_from(&self.src[self.offset..])?;
+        self.offset += schema.serialized_len();
+        Ok(schema)
+    }
+
+    fn expression(&mut self) -> std::io::Result<Expression> {
+        let expression = Expression::deserialize_from(&self.src[self.offset..])?;
+        self.offset += expression.serialized_len();
+        Ok(expression)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset == self.src.len()
+    }
+}
+
+fn decode_entry(src: &[u8], rid: Rid) -> std::io::Result<Entry> {
+    let mut decoder = Decoder { src, offset: 0 };
+    let kind = decoder.u8()?;
+    let oid = decoder.u32()?;
+    let name = decoder.string()?;
+    match kind {
+        TABLE_ENTRY => {
+            let first_page_id = decoder.u32()? as PageId;
+            let schema = decoder.schema()?;
+            let mut table = TableInfo {
+                oid,
+                name,
+                sketches: RefCell::new(TableSketches::new(&schema)),
+                schema,
+                first_page_id,
+                free_space_hint: Cell::new(first_page_id),
+                version: 0,
+                history: BTreeMap::new(),
+                defaults: Vec::new(),
+                is_loading: false,
+                is_valid: true,
+                checks: Vec::new(),
+                rid,
+            };
+            if decoder.is_empty() {
+                return Ok(Entry::Table(table));
+            }
+            table.version = decoder.u16()?;
+            for _ in 0..decoder.u32()? {
+                let version = decoder.u16()?;
+                table.history.insert(version, decoder.schema()?);
+            }
+            for _ in 0..decoder.u32()? {
+                let name = decoder.string()?;
+                let version = decoder.u16()?;
+                let len = decoder.u32()? as usize;
+                let types = match table.schema.column_idx(&name) {
+                    Some(idx) => table.schema.nth_types(idx).unwrap().to_static(),
+                    None => return Err(invalid_data("Default of an unknown column")),
+                };
+                let default = match len {
+                    0 => Value::null(types),
+                    _ => {
+                        let mut default = Value::new(types);
+                        default.deserialize_from(decoder.bytes(len)?);
+                        default
+                    }
+                };
+                table.defaults.push((name, version, default));
+            }
+            // A table still loading when the catalog is opened was left so by
+            // a crash.
+            if !decoder.is_empty() {
+                table.is_loading = decoder.u8()? != 0;
+                table.is_valid = !table.is_loading;
+            }
+            if !decoder.is_empty() {
+                for _ in 0..decoder.u32()? {
+                    let name = decoder.string()?;
+                    table.checks.push((name, decoder.expression()?));
+                }
+            }
+            Ok(Entry::Table(table))
+        }
+        INDEX_ENTRY => Ok(Entry::Index(IndexInfo {
+            oid,
+            name,
+            table_oid: decoder.u32()?,
+            key_column: decoder.u32()? as usize,
+            rid,
+        })),
+        STATISTICS_ENTRY => Ok(Entry::Statistics(oid, src[decoder.offset..].to_vec(), rid)),
+        SKETCHES_ENTRY => Ok(Entry::Sketches(oid, src[decoder.offset..].to_vec(), rid)),
+        _ => Err(invalid_data("Catalog entry has an invalid kind")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::catalog::column::Column;
+    use crate::plan::expression::ComparisonOp;
+    use crate::types::types::Str;
+    use crate::types::types::Types;
+    use crate::types::types::Varlen;
+    use crate::types::value::Value;
+
+    fn create_schema() -> Schema<'static> {
+        Schema::new(vec![
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("Name".to_string(), Types::owned(), 20),
+        ])
+    }
+
+    fn create_tuple(schema: &Schema, id: i64, name: &str) -> Tuple {
+        let values = vec![
+            Value::new(Types::BigInt(id)),
+            Value::new(Types::Varchar(Varlen::Owned(Str::Val(name.to_string())))),
+        ];
+        Tuple::new(&values, schema)
+    }
+
+    #[test]
+    fn create_and_reopen() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(4);
+
+        {
+            let mut catalog = Catalog::create(&mut bpm).unwrap();
+            assert_eq!(
+                1,
+                catalog
+                    .create_table(&mut bpm, 1, "users", create_schema())
+                    .unwrap()
+            );
+            assert_eq!(
+                2,
+                catalog
+                    .create_table(&mut bpm, 1, "items", create_schema())
+                    .unwrap()
+            );
+            assert!(catalog
+                .create_table(&mut bpm, 1, "users", create_schema())
+                .is_err());
+
+            let users = catalog.table("users").unwrap();
+            let mut heap = users.heap();
+            for id in 0..10 {
+                let tuple = create_tuple(users.schema(), id, "user");
+                heap.insert_tuple(&mut bpm, 1, tuple).unwrap();
+            }
+            let oid = catalog
+                .create_index(&mut bpm, 1, "users_id", "users", "Id")
+                .unwrap();
+            assert_eq!(3, oid);
+            let result = catalog.create_index(&mut bpm, 1, "x", "users", "Name");
+            assert!(result.is_err());
+            let index = catalog.index("users_id").unwrap();
+            let tree = index.open(&mut bpm).unwrap();
+            assert_eq!(10, tree.iter(&mut bpm).unwrap().count());
+        }
+        {
+            let mut catalog = Catalog::open(&mut bpm).unwrap();
+            assert!(Catalog::create(&mut bpm).is_err());
+            let names: Vec<&str> = catalog.tables().iter().map(|t| t.name()).collect();
+            assert_eq!(vec!["items", "users"], names);
+            let users = catalog.table("users").unwrap();
+            assert_eq!(1, users.oid());
+            assert_eq!(create_schema(), *users.schema());
+            assert_eq!("Name", users.schema().nth_column(1).unwrap().name());
+            assert_eq!(10, users.heap().iter(&mut bpm).count());
+            assert_eq!(1, catalog.table_indexes(1).len());
+            assert_eq!(Some("users"), catalog.table_by_oid(1).map(|t| t.name()));
+
+            catalog.drop_table(&mut bpm, 1, "users").unwrap();
+            assert!(catalog.table("users").is_none());
+            assert!(catalog.index("users_id").is_none());
+            assert!(catalog.drop_index(&mut bpm, 1, "users_id").is_err());
+            assert_eq!(
+                4,
+                catalog
+                    .create_table(&mut bpm, 1, "orders", create_schema())
+                    .unwrap()
+            );
+        }
+        {
+            let catalog = Catalog::open(&mut bpm).unwrap();
+            let names: Vec<&str> = catalog.tables().iter().map(|t| t.name()).collect();
+            assert_eq!(vec!["items", "orders"], names);
+            assert!(catalog.index("users_id").is_none());
+            // The index record was removed from the header page too.
+            let tree = BPlusTree::new("users_id", &mut bpm).unwrap();
+            assert!(tree.is_empty());
+        }
+    }
+
+    #[test]
+    fn add_and_drop_columns() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(4);
+
+        let name = |s: &str| Value::new(Types::Varchar(Varlen::Owned(Str::Val(s.to_string()))));
+        // Reads the tuples of |users| as (Id, Score, Name), or "-" for missing columns.
+        let read = |bpm: &mut MemoryBufferPoolManager<TablePage>, catalog: &Catalog| {
+            let users = catalog.table("users").unwrap();
+            let schema = users.schema();
+            let mut rows: Vec<String> = users
+                .heap()
+                .iter(bpm)
+                .map(|item| {
+                    let tuple = users.upgrade(item.unwrap().1).unwrap();
+                    let nth = |column: &str| match schema.column_idx(column) {
+                        Some(idx) => tuple.nth_value(schema, idx).to_string(),
+                        None => "-".to_string(),
+                    };
+                    format!("{} {} {}", nth("Id"), nth("Score"), nth("Name"))
+                })
+                .collect();
+            rows.sort();
+            rows
+        };
+
+        {
+            let mut catalog = Catalog::create(&mut bpm).unwrap();
+            // Users (Name Varchar, Id BigInt), so that dropping Name moves Id.
+            let schema = Schema::new(vec![
+                Column::new("Name".to_string(), Types::owned(), 20),
+                Column::new("Id".to_string(), Types::bigint(), 8),
+            ]);
+            catalog.create_table(&mut bpm, 1, "users", schema).unwrap();
+            let users = catalog.table("users").unwrap();
+            for id in 0..2 {
+                let values = vec![name("old"), Value::new(Types::BigInt(id))];
+                let tuple = Tuple::new(&values, users.schema());
+                users.heap().insert_tuple(&mut bpm, 1, tuple).unwrap();
+            }
+            catalog
+                .create_index(&mut bpm, 1, "users_id", "users", "Id")
+                .unwrap();
+            catalog.analyze(&mut bpm, 1, "users").unwrap();
+
+            let score = Column::new("Score".to_string(), Types::integer(), 4);
+            let seven = Value::new(Types::Integer(7));
+            catalog
+                .add_column(&mut bpm, 1, "users", score.clone(), seven.clone())
+                .unwrap();
+            assert!(catalog.statistics(1).is_none());
+            let result = catalog.add_column(&mut bpm, 1, "users", score, seven.clone());
+            assert_eq!(
+                std::io::ErrorKind::AlreadyExists,
+                result.unwrap_err().kind()
+            );
+            let other = Column::new("Other".to_string(), Types::bigint(), 8);
+            assert!(catalog
+                .add_column(&mut bpm, 1, "users", other, seven)
+                .is_err());
+
+            let users = catalog.table("users").unwrap();
+            assert_eq!(1, users.version());
+            let values = vec![
+                name("new"),
+                Value::new(Types::BigInt(2)),
+                Value::new(Types::Integer(9)),
+            ];
+            let mut tuple = Tuple::new(&values, users.schema());
+            tuple.set_version(users.version());
+            users.heap().insert_tuple(&mut bpm, 1, tuple).unwrap();
+
+            // Checks on Score; one the tuples violate is not added.
+            let score_above = |val: i32| {
+                Expression::Comparison(
+                    ComparisonOp::Gt,
+                    Box::new(Expression::Column(2)),
+                    Box::new(Expression::Constant(Value::new(Types::Integer(val)))),
+                )
+            };
+            catalog
+                .add_check(&mut bpm, 1, "users", "positive", score_above(0))
+                .unwrap();
+            let result = catalog.add_check(&mut bpm, 1, "users", "positive", score_above(1));
+            assert_eq!(
+                std::io::ErrorKind::AlreadyExists,
+                result.unwrap_err().kind()
+            );
+            let result = catalog.add_check(&mut bpm, 1, "users", "big", score_above(8));
+            assert!(result
+                .unwrap_err()
+                .to_string()
+                .ends_with("constraint = big"));
+            assert_eq!(1, catalog.table("users").unwrap().checks().len());
+            assert!(catalog.drop_column(&mut bpm, 1, "users", "Score").is_err());
+
+            assert!(catalog.drop_column(&mut bpm, 1, "users", "Id").is_err());
+            catalog.drop_column(&mut bpm, 1, "users", "Name").unwrap();
+            assert!(catalog.drop_column(&mut bpm, 1, "users", "Name").is_err());
+            assert_eq!(0, catalog.index("users_id").unwrap().key_column());
+            assert_eq!(vec!["0 7 -", "1 7 -", "2 9 -"], read(&mut bpm, &catalog));
+        }
+        {
+            let mut catalog = Catalog::open(&mut bpm).unwrap();
+            assert_eq!(2, catalog.table("users").unwrap().version());
+            assert_eq!(0, catalog.index("users_id").unwrap().key_column());
+            assert_eq!(vec!["0 7 -", "1 7 -", "2 9 -"], read(&mut bpm, &catalog));
+            // The check follows Score, which moved with Name dropped.
+            let users = catalog.table("users").unwrap();
+            assert_eq!("positive", users.checks()[0].0);
+            let values = vec![Value::new(Types::BigInt(3)), Value::new(Types::Integer(0))];
+            let tuple = Tuple::new(&values, users.schema());
+            assert!(users.check_constraints(&tuple).is_err());
+
+            // A column added again does not read the values it had before.
+            let column = Column::new("Name".to_string(), Types::owned(), 20);
+            catalog
+                .add_column(&mut bpm, 1, "users", column, name("anon"))
+                .unwrap();
+            let expected = vec!["0 7 anon", "1 7 anon", "2 9 anon"];
+            assert_eq!(expected, read(&mut bpm, &catalog));
+            let catalog = Catalog::open(&mut bpm).unwrap();
+            assert_eq!(expected, read(&mut bpm, &catalog));
+        }
+    }
+
+    #[test]
+    fn rename() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(8);
+        let mut catalog = Catalog::create(&mut bpm).unwrap();
+        catalog
+            .create_table(&mut bpm, 1, "users", create_schema())
+            .unwrap();
+        catalog
+            .create_table(&mut bpm, 1, "items", create_schema())
+            .unwrap();
+        let users = catalog.table("users").unwrap();
+        for id in 0..10 {
+            let tuple = create_tuple(users.schema(), id, "user");
+            users.heap().insert_tuple(&mut bpm, 1, tuple).unwrap();
+        }
+        catalog
+            .create_index(&mut bpm, 1, "users_id", "users", "Id")
+            .unwrap();
+        let score = Column::new("Score".to_string(), Types::integer(), 4);
+        let seven = Value::new(Types::Integer(7));
+        catalog
+            .add_column(&mut bpm, 1, "users", score, seven)
+            .unwrap();
+        let kind = |result: std::io::Result<()>| result.unwrap_err().kind();
+
+        // Columns are renamed in the schemas of all versions and defaults.
+        catalog
+            .rename_column(&mut bpm, 1, "users", "Name", "FullName")
+            .unwrap();
+        catalog
+            .rename_column(&mut bpm, 1, "users", "Score", "Points")
+            .unwrap();
+        let result = catalog.rename_column(&mut bpm, 1, "users", "Id", "Points");
+        assert_eq!(std::io::ErrorKind::AlreadyExists, kind(result));
+        let result = catalog.rename_column(&mut bpm, 1, "users", "Name", "Other");
+        assert_eq!(std::io::ErrorKind::NotFound, kind(result));
+        catalog.drop_column(&mut bpm, 1, "users", "Points").unwrap();
+        let result = catalog.rename_column(&mut bpm, 1, "users", "FullName", "Points");
+        assert_eq!(std::io::ErrorKind::InvalidInput, kind(result));
+
+        catalog
+            .rename_table(&mut bpm, 1, "users", "customers")
+            .unwrap();
+        let result = catalog.rename_table(&mut bpm, 1, "customers", "items");
+        assert_eq!(std::io::ErrorKind::AlreadyExists, kind(result));
+        assert_eq!(
+            std::io::ErrorKind::NotFound,
+            kind(catalog.rename_table(&mut bpm, 1, "users", "x"))
+        );
+        catalog
+            .rename_index(&mut bpm, 1, "users_id", "customers_id")
+            .unwrap();
+        let result = catalog.rename_index(&mut bpm, 1, "customers_id", &"x".repeat(40));
+        assert!(result.is_err());
+
+        let catalog = Catalog::open(&mut bpm).unwrap();
+        assert!(catalog.table("users").is_none());
+        let customers = catalog.table("customers").unwrap();
+        let names: Vec<&str> = customers
+            .schema()
+            .columns()
+            .iter()
+            .map(|column| column.name())
+            .collect();
+        assert_eq!(vec!["Id", "FullName"], names);
+        let tuples: Vec<Tuple> = customers
+            .heap()
+            .iter(&mut bpm)
+            .map(|item| customers.upgrade(item.unwrap().1).unwrap())
+            .collect();
+        assert_eq!(10, tuples.len());
+        assert_eq!(
+            "user",
+            tuples[0].nth_value(customers.schema(), 1).to_string()
+        );
+        assert!(catalog.index("users_id").is_none());
+        let index = catalog.index("customers_id").unwrap();
+        assert_eq!(vec![index], catalog.table_indexes(customers.oid()));
+        let tree = index.open(&mut bpm).unwrap();
+        assert_eq!(10, tree.iter(&mut bpm).unwrap().count());
+    }
+
+    #[test]
+    fn reindex() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(8);
+        let mut catalog = Catalog::create(&mut bpm).unwrap();
+        catalog
+            .create_table(&mut bpm, 1, "users", create_schema())
+            .unwrap();
+        let users = catalog.table("users").unwrap();
+        let mut heap = users.heap();
+        let mut rids = Vec::new();
+        for id in 0..1000 {
+            let tuple = create_tuple(users.schema(), id, "user");
+            rids.push(heap.insert_tuple(&mut bpm, 1, tuple).unwrap());
+        }
+        catalog
+            .create_index(&mut bpm, 1, "users_id", "users", "Id")
+            .unwrap();
+        assert!(catalog.reindex(&mut bpm, "nothing").is_err());
+
+        // The rebuilt tree holds the keys left, in new pages, and the pages of
+        // the old tree are freed.
+        for rid in rids[100..].iter() {
+            assert!(heap.apply_delete(&mut bpm, 1, rid).is_ok());
+        }
+        let index = catalog.index("users_id").unwrap();
+        let mut tree = index.open(&mut bpm).unwrap();
+        for id in 100..1000 {
+            assert!(tree.remove(&mut bpm, id).unwrap());
+        }
+        let old_page_ids = tree.page_ids(&mut bpm).unwrap();
+        catalog.reindex(&mut bpm, "users_id").unwrap();
+        let tree = index.open(&mut bpm).unwrap();
+        let page_ids = tree.page_ids(&mut bpm).unwrap();
+        assert!(page_ids
+            .iter()
+            .all(|page_id| !old_page_ids.contains(page_id)));
+        assert!(old_page_ids
+            .iter()
+            .all(|page_id| !bpm.is_allocated(*page_id)));
+        let keys: Vec<i64> = tree.iter(&mut bpm).unwrap().map(|e| e.unwrap().0).collect();
+        assert_eq!((0..100).collect::<Vec<i64>>(), keys);
+
+        // Duplicate keys leave the index as it was.
+        let users = catalog.table("users").unwrap();
+        let tuple = create_tuple(users.schema(), 7, "again");
+        heap.insert_tuple(&mut bpm, 1, tuple).unwrap();
+        let result = catalog.reindex(&mut bpm, "users_id");
+        assert_eq!("Duplicate key; key = 7", result.unwrap_err().to_string());
+        assert_eq!(page_ids, tree.page_ids(&mut bpm).unwrap());
+    }
+
+    #[test]
+    fn page_owners() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(8);
+        let mut catalog = Catalog::create(&mut bpm).unwrap();
+        catalog
+            .create_table(&mut bpm, 1, "users", create_schema())
+            .unwrap();
+        let users = catalog.table("users").unwrap();
+        let mut heap = users.heap();
+        let mut rids = Vec::new();
+        for id in 0..200 {
+            let tuple = create_tuple(users.schema(), id, "user");
+            rids.push(heap.insert_tuple(&mut bpm, 1, tuple).unwrap());
+        }
+        // A tuple spanning overflow pages.
+        let tuple = create_tuple(users.schema(), 200, &"x".repeat(10000));
+        heap.insert_tuple(&mut bpm, 1, tuple).unwrap();
+        catalog
+            .create_index(&mut bpm, 1, "users_id", "users", "Id")
+            .unwrap();
+
+        let owners = catalog.page_owners(&mut bpm).unwrap();
+        assert_eq!(Some(&PageOwner::Catalog), owners.get(&HEADER_PAGE_ID));
+        assert_eq!(Some(&PageOwner::Catalog), owners.get(&CATALOG_PAGE_ID));
+        let table_pages = owners
+            .values()
+            .filter(|owner| **owner == PageOwner::Table("users".to_string()))
+            .count();
+        // At least two table pages and two overflow pages.
+        assert!(table_pages >= 4);
+        let index = catalog.index("users_id").unwrap();
+        let root_page_id = index.open(&mut bpm).unwrap().root_page_id();
+        assert_eq!(
+            Some(&PageOwner::Index("users_id".to_string())),
+            owners.get(&root_page_id)
+        );
+
+        bpm.set_access_sampling(1);
+        for _ in 0..3 {
+            catalog
+                .table("users")
+                .unwrap()
+                .heap()
+                .get_tuple(&mut bpm, &rids[199])
+                .unwrap();
+        }
+        let hottest = catalog.hottest_pages(&mut bpm, 1).unwrap();
+        assert_eq!(rids[199].page_id(), hottest[0].0.page_id);
+        assert_eq!(3, hottest[0].0.reads);
+        assert_eq!(Some(PageOwner::Table("users".to_string())), hottest[0].1);
+        // Looking up the owners is not sampled.
+        assert_eq!(3, bpm.hottest_pages(1)[0].reads);
+    }
+
+    #[test]
+    fn open_without_catalog() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(4);
+        let result = Catalog::open(&mut bpm);
+        assert_eq!(std::io::ErrorKind::NotFound, result.err().unwrap().kind());
+    }
+}
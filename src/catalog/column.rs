@@ -1,10 +1,12 @@
+use crate::common::error::*;
+use crate::common::reinterpret;
 use crate::types::types::Types;
 use std::cmp::Eq;
 use std::cmp::PartialEq;
 use std::fmt::Debug;
 use std::mem;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Column<'a> {
     // The name of the column.
     name: String,
@@ -68,6 +70,10 @@ impl<'a> Column<'a> {
         self.variable_len
     }
 
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
     pub fn set_offset(&mut self, offset: usize) {
         self.offset = offset;
     }
@@ -87,6 +93,55 @@ impl<'a> Column<'a> {
         )
     }
 
+    pub fn serialized_len(&self) -> usize {
+        17 + self.name.len()
+    }
+
+    // Serialized format (size in byte):
+    //  ----------------------------------------------------------------------
+    // | NameLen (4) | Name (NameLen) | TypeId (1) | Offset (4) | FixedLen (4) |
+    //  ----------------------------------------------------------------------
+    //  -------------------
+    // | VariableLen (4) |
+    //  -------------------
+    // The caller needs to ensure that |dst| has |self.serialized_len()| bytes.
+    pub fn serialize_to(&self, dst: &mut [u8]) {
+        let name_len = self.name.len();
+        reinterpret::write_u32(dst, name_len as u32);
+        dst[4..(4 + name_len)].copy_from_slice(self.name.as_bytes());
+        let dst = &mut dst[(4 + name_len)..];
+        dst[0] = self.types.id();
+        reinterpret::write_u32(&mut dst[1..], self.offset as u32);
+        reinterpret::write_u32(&mut dst[5..], self.fixed_len as u32);
+        reinterpret::write_u32(&mut dst[9..], self.variable_len as u32);
+    }
+
+    // Returns |InvalidData| if |src| does not hold a serialized column.
+    pub fn deserialize_from(src: &[u8]) -> std::io::Result<Self> {
+        let truncated = || invalid_data("Column is truncated");
+        if src.len() < 4 {
+            return Err(truncated());
+        }
+        let name_len = reinterpret::read_u32(src) as usize;
+        if src.len() < 17 + name_len {
+            return Err(truncated());
+        }
+        let name = String::from_utf8(src[4..(4 + name_len)].to_vec())
+            .map_err(|_| invalid_data("Column name is not UTF-8"))?;
+        let src = &src[(4 + name_len)..];
+        let types =
+            Types::from_id(src[0]).ok_or_else(|| invalid_data("Column type is unknown"))?;
+        let fixed_len = reinterpret::read_u32(&src[5..]) as usize;
+        let variable_len = reinterpret::read_u32(&src[9..]) as usize;
+        let mut column = match types.is_inlined() {
+            true if variable_len == 0 => Column::new(name, types, fixed_len),
+            false if fixed_len == mem::size_of::<u64>() => Column::new(name, types, variable_len),
+            _ => return Err(invalid_data("Column lengths are inconsistent")),
+        };
+        column.set_offset(reinterpret::read_u32(&src[1..]) as usize);
+        Ok(column)
+    }
+
     fn init(mut self, length: usize) -> Self {
         self.set_inlined();
         self.set_len(length);
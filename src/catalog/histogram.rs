@@ -0,0 +1,198 @@
+// Functionality: An equi-depth histogram of the values of a column, built by
+// ANALYZE from a sample of its non-null values. Its bounds split the sorted
+// values into buckets holding the same number of values each, so that the
+// fraction of the values in a range is estimated by the buckets the range
+// covers, interpolating linearly inside the buckets it cuts if the values are
+// numbers, and counting half of them otherwise.
+//
+// Serialized format (size in byte):
+//  ----------------------------------------------------------------
+// | BoundCount (4) | BoundLen_1 (4) | Bound_1 (BoundLen_1) | ...
+//  ----------------------------------------------------------------
+
+use crate::common::error::*;
+use crate::common::reinterpret;
+use crate::index::key::compare_values;
+use crate::types::types::Operation;
+use crate::types::types::Types;
+use crate::types::value::Value;
+use std::mem;
+
+// The number of buckets of the histograms built by ANALYZE.
+pub const HISTOGRAM_BUCKETS: usize = 32;
+
+#[derive(Clone, Debug, Default)]
+pub struct Histogram {
+    // The smallest value, the upper bound of every bucket, in order.
+    bounds: Vec<Value<'static>>,
+}
+
+impl Histogram {
+    // Builds a histogram of up to |buckets| buckets of |values|, which need
+    // to be non-null and ordered, i.e. not points.
+    pub fn build(mut values: Vec<Value<'static>>, buckets: usize) -> Self {
+        if values.is_empty() {
+            return Histogram::default();
+        }
+        values.sort_by(|lhs, rhs| compare_values(lhs, rhs));
+        let buckets = buckets.clamp(1, values.len());
+        let last = values.len() - 1;
+        let bounds = (0..=buckets)
+            .map(|idx| values[idx * last / buckets].clone())
+            .collect();
+        Histogram { bounds }
+    }
+
+    pub fn bounds(&self) -> &[Value<'static>] {
+        &self.bounds
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    // Estimates the fraction of the values less than |value|, or less than or
+    // equal to it if |inclusive|. Returns None if the histogram is empty, or
+    // |value| cannot be compared with its values.
+    pub fn fraction_below(&self, value: &Value, inclusive: bool) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        let below = |bound: &Value| match inclusive {
+            true => bound.le(value),
+            false => bound.lt(value),
+        };
+        // The bounds are sorted, so the ones below |value| come first.
+        let mut count = 0;
+        for bound in self.bounds.iter() {
+            match below(bound)? {
+                true => count += 1,
+                false => break,
+            }
+        }
+        if count == 0 {
+            return Some(0.0);
+        }
+        if count == self.bounds.len() {
+            return Some(1.0);
+        }
+        let idx = count - 1;
+        let inside = interpolate(&self.bounds[idx], &self.bounds[idx + 1], value).unwrap_or(0.5);
+        Some((idx as f64 + inside) / (self.bounds.len() - 1) as f64)
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        4 + self
+            .bounds
+            .iter()
+            .map(|bound| 4 + serialized_value_len(bound))
+            .sum::<usize>()
+    }
+
+    // The caller needs to ensure that |dst| has |self.serialized_len()| bytes.
+    pub fn serialize_to(&self, dst: &mut [u8]) {
+        reinterpret::write_u32(dst, self.bounds.len() as u32);
+        let mut offset = 4;
+        for bound in self.bounds.iter() {
+            let len = serialized_value_len(bound);
+            reinterpret::write_u32(&mut dst[offset..], len as u32);
+            bound.serialize_to(&mut dst[(offset + 4)..(offset + 4 + len)]);
+            offset += 4 + len;
+        }
+    }
+
+    // Reads a histogram of values of |types|. Returns |InvalidData| if |src|
+    // does not hold one.
+    pub fn deserialize_from(src: &[u8], types: &Types) -> std::io::Result<Self> {
+        let truncated = || invalid_data("Histogram is truncated");
+        if src.len() < 4 {
+            return Err(truncated());
+        }
+        let count = reinterpret::read_u32(src) as usize;
+        let mut bounds = Vec::new();
+        let mut offset = 4;
+        for _ in 0..count {
+            if src.len() < offset + 4 {
+                return Err(truncated());
+            }
+            let len = reinterpret::read_u32(&src[offset..]) as usize;
+            if src.len() < offset + 4 + len {
+                return Err(truncated());
+            }
+            let mut bound = Value::new(types.to_static());
+            bound.deserialize_from(&src[(offset + 4)..(offset + 4 + len)]);
+            bounds.push(bound);
+            offset += 4 + len;
+        }
+        Ok(Histogram { bounds })
+    }
+}
+
+// Room for a value, as in a tuple.
+fn serialized_value_len(value: &Value) -> usize {
+    value.len() + mem::size_of::<u64>()
+}
+
+// Returns where |value| lies between |low| and |high|, from 0 to 1, if they
+// are numbers.
+fn interpolate(low: &Value, high: &Value, value: &Value) -> Option<f64> {
+    let low = low.borrow().get_as_f64().ok()?;
+    let high = high.borrow().get_as_f64().ok()?;
+    let value = value.borrow().get_as_f64().ok()?;
+    match high > low {
+        true => Some(((value - low) / (high - low)).clamp(0.0, 1.0)),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::types::Str;
+    use crate::types::types::Varlen;
+
+    fn bigint(val: i64) -> Value<'static> {
+        Value::new(Types::BigInt(val))
+    }
+
+    #[test]
+    fn build_and_estimate() {
+        assert!(Histogram::build(vec![], 4).is_empty());
+        assert_eq!(None, Histogram::default().fraction_below(&bigint(1), true));
+
+        // 0, 1, ..., 99, shuffled.
+        let values = (0..100).map(|val| bigint(val * 37 % 100)).collect();
+        let histogram = Histogram::build(values, 4);
+        let bounds: Vec<String> = histogram.bounds().iter().map(|b| b.to_string()).collect();
+        assert_eq!(vec!["0", "24", "49", "74", "99"], bounds);
+        assert_eq!(Some(0.0), histogram.fraction_below(&bigint(-5), true));
+        assert_eq!(Some(0.0), histogram.fraction_below(&bigint(0), false));
+        assert_eq!(Some(1.0), histogram.fraction_below(&bigint(99), true));
+        assert_eq!(Some(1.0), histogram.fraction_below(&bigint(500), false));
+        let half = histogram.fraction_below(&bigint(49), true).unwrap();
+        assert!((half - 0.5).abs() < 0.01, "half = {}", half);
+        let tenth = histogram.fraction_below(&bigint(10), false).unwrap();
+        assert!((tenth - 0.1).abs() < 0.01, "tenth = {}", tenth);
+
+        // Skewed values get narrow buckets where they are dense.
+        let mut values: Vec<Value> = (0..90).map(|_| bigint(1)).collect();
+        values.extend((0..10).map(|val| bigint(1000 + val)));
+        let histogram = Histogram::build(values, 10);
+        let below = histogram.fraction_below(&bigint(1), true).unwrap();
+        assert!(below >= 0.9, "below = {}", below);
+        assert_eq!(Some(0.0), histogram.fraction_below(&bigint(1), false));
+
+        // Strings are compared, but not interpolated.
+        let string = |val: &str| Value::new(Types::Varchar(Varlen::Owned(Str::Val(val.into()))));
+        let values = ["a", "c", "e"].iter().map(|val| string(val)).collect();
+        let histogram = Histogram::build(values, 2);
+        assert_eq!(Some(0.25), histogram.fraction_below(&string("b"), true));
+
+        let mut data = vec![0; histogram.serialized_len()];
+        histogram.serialize_to(&mut data);
+        let decoded = Histogram::deserialize_from(&data, &Types::owned()).unwrap();
+        assert_eq!(3, decoded.bounds().len());
+        assert_eq!("e", decoded.bounds()[2].to_string());
+        assert!(Histogram::deserialize_from(&data[..10], &Types::owned()).is_err());
+    }
+}
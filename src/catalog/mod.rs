@@ -1,2 +1,6 @@
+pub mod catalog;
 pub mod column;
+pub mod histogram;
 pub mod schema;
+pub mod table_sketches;
+pub mod table_statistics;
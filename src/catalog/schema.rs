@@ -1,10 +1,12 @@
 use crate::catalog::column::Column;
+use crate::common::error::*;
+use crate::common::reinterpret;
 use crate::types::types::Types;
 use std::cmp::Eq;
 use std::cmp::PartialEq;
 use std::fmt::Debug;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Schema<'a> {
     len: usize,
     columns: Vec<Column<'a>>,
@@ -75,6 +77,21 @@ impl<'a> Schema<'a> {
         None
     }
 
+    // Renames the column |name|. The layout of tuples does not depend on
+    // column names, so existing tuples stay valid.
+    pub fn rename_column(&mut self, name: &str, new_name: &str) -> std::io::Result<()> {
+        if self.column_idx(new_name).is_some() {
+            return Err(already_exists(&format!("Column exists; name = {}", new_name)));
+        }
+        match self.column_idx(name) {
+            Some(idx) => {
+                self.columns[idx].set_name(new_name.to_string());
+                Ok(())
+            }
+            None => Err(not_found(&format!("Column not found; name = {}", name))),
+        }
+    }
+
     pub fn to_string(&self) -> String {
         format!(
             "Schema[NumColumns:{}, IsInlined:{}, Length:{}]",
@@ -84,6 +101,58 @@ impl<'a> Schema<'a> {
         )
     }
 
+    pub fn serialized_len(&self) -> usize {
+        8 + self
+            .columns
+            .iter()
+            .map(|column| column.serialized_len())
+            .sum::<usize>()
+    }
+
+    // Serialized format (size in byte):
+    //  ------------------------------------------------------------
+    // | Len (4) | ColumnCount (4) | Column_1 (variable) | ...
+    //  ------------------------------------------------------------
+    // The caller needs to ensure that |dst| has |self.serialized_len()| bytes.
+    pub fn serialize_to(&self, dst: &mut [u8]) {
+        reinterpret::write_u32(dst, self.len as u32);
+        reinterpret::write_u32(&mut dst[4..], self.columns.len() as u32);
+        let mut offset = 8;
+        for column in self.columns.iter() {
+            column.serialize_to(&mut dst[offset..]);
+            offset += column.serialized_len();
+        }
+    }
+
+    // Returns |InvalidData| if |src| does not hold a serialized schema, e.g.
+    // the offsets of its columns do not match their lengths.
+    pub fn deserialize_from(src: &[u8]) -> std::io::Result<Self> {
+        if src.len() < 8 {
+            return Err(invalid_data("Schema is truncated"));
+        }
+        let column_count = reinterpret::read_u32(&src[4..]) as usize;
+        let mut columns = Vec::new();
+        let mut offsets = Vec::new();
+        let mut offset = 8;
+        for _ in 0..column_count {
+            let column = Column::deserialize_from(&src[offset..])?;
+            offset += column.serialized_len();
+            offsets.push(column.offset());
+            columns.push(column);
+        }
+        let schema = Schema::new(columns);
+        let consistent = schema.len == reinterpret::read_u32(src) as usize
+            && schema
+                .columns
+                .iter()
+                .zip(offsets.iter())
+                .all(|(column, &offset)| column.offset() == offset);
+        match consistent {
+            true => Ok(schema),
+            false => Err(invalid_data("Schema offsets are inconsistent")),
+        }
+    }
+
     fn init(mut self) -> Self {
         let mut offset = 0;
         for (idx, column) in self.columns.iter_mut().enumerate() {
@@ -113,3 +182,32 @@ impl<'a> PartialEq for Schema<'a> {
 }
 
 impl<'a> Eq for Schema<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_and_deserialize() {
+        let schema = Schema::new(vec![
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("Name".to_string(), Types::owned(), 20),
+            Column::new("Location".to_string(), Types::point(), 16),
+        ]);
+        let mut buffer = vec![0; schema.serialized_len()];
+        schema.serialize_to(&mut buffer);
+        let other = Schema::deserialize_from(&buffer).unwrap();
+        assert_eq!(schema.to_string(), other.to_string());
+        for (lhs, rhs) in schema.columns().iter().zip(other.columns().iter()) {
+            assert_eq!(lhs.to_string(), rhs.to_string());
+        }
+
+        assert!(Schema::deserialize_from(&buffer[..(buffer.len() - 1)]).is_err());
+        // The offset of "Id" is not 0.
+        buffer[8 + 4 + 2 + 1] = 9;
+        assert!(Schema::deserialize_from(&buffer).is_err());
+        // Unknown type ID of "Id".
+        buffer[8 + 4 + 2] = 0;
+        assert!(Schema::deserialize_from(&buffer).is_err());
+    }
+}
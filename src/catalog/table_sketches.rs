@@ -0,0 +1,294 @@
+// Functionality: Streaming statistics of a table, maintained on every insert
+// and delete rather than by a full scan, so that estimates stay reasonably
+// fresh between ANALYZE runs. Every column keeps its null count, a count-min
+// sketch (value frequencies and heavy hitters) and a reservoir sample.
+//
+// The sketches live in memory, in the |TableInfo| of their table. The
+// execution context updates them as the transactions writing the table
+// commit. At a checkpoint, they are serialized into the catalog, see
+// |Catalog::checkpoint_sketches|, and loaded back with |deserialize_from| on
+// startup.
+//
+// Serialized format (size in byte):
+//  ---------------------------------------------------------------
+// | RowCount (8) | ColumnCount (4) | Column_1 (variable) | ...
+//  ---------------------------------------------------------------
+// Column format:
+//  -----------------------------------------------------------------
+// | NullCount (8) | SketchLen (4) | Sketch (SketchLen) | Sample (variable)
+//  -----------------------------------------------------------------
+
+use crate::catalog::schema::Schema;
+use crate::common::error::*;
+use crate::common::reinterpret;
+use crate::table::tuple::Tuple;
+use crate::types::count_min_sketch::CountMinSketch;
+use crate::types::reservoir_sample::ReservoirSample;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnSketch {
+    null_count: u64,
+    frequencies: CountMinSketch,
+    sample: ReservoirSample,
+}
+
+impl ColumnSketch {
+    pub fn null_count(&self) -> u64 {
+        self.null_count
+    }
+
+    pub fn frequencies(&self) -> &CountMinSketch {
+        &self.frequencies
+    }
+
+    pub fn sample(&self) -> &ReservoirSample {
+        &self.sample
+    }
+
+    fn serialized_len(&self) -> usize {
+        12 + self.frequencies.serialized_len() + self.sample.serialized_len()
+    }
+
+    fn serialize_to(&self, dst: &mut [u8]) {
+        let sketch_len = self.frequencies.serialized_len();
+        reinterpret::write_u64(dst, self.null_count);
+        reinterpret::write_u32(&mut dst[8..], sketch_len as u32);
+        self.frequencies.serialize_to(&mut dst[12..]);
+        self.sample.serialize_to(&mut dst[(12 + sketch_len)..]);
+    }
+
+    fn deserialize_from(src: &[u8]) -> std::io::Result<Self> {
+        if src.len() < 12 {
+            return Err(invalid_data("Column sketch is truncated"));
+        }
+        let sketch_len = reinterpret::read_u32(&src[8..]) as usize;
+        if src.len() < 12 + sketch_len {
+            return Err(invalid_data("Column sketch is truncated"));
+        }
+        Ok(ColumnSketch {
+            null_count: reinterpret::read_u64(src),
+            frequencies: CountMinSketch::deserialize_from(&src[12..(12 + sketch_len)])?,
+            sample: ReservoirSample::deserialize_from(&src[(12 + sketch_len)..])?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TableSketches {
+    row_count: u64,
+    columns: Vec<ColumnSketch>,
+    // The number of inserts and deletes since the last checkpoint.
+    changes: u64,
+}
+
+impl TableSketches {
+    pub fn new(schema: &Schema) -> Self {
+        TableSketches {
+            row_count: 0,
+            columns: vec![ColumnSketch::default(); schema.columns().len()],
+            changes: 0,
+        }
+    }
+
+    pub fn row_count(&self) -> u64 {
+        self.row_count
+    }
+
+    pub fn column(&self, idx: usize) -> Option<&ColumnSketch> {
+        self.columns.get(idx)
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn changes_since_checkpoint(&self) -> u64 {
+        self.changes
+    }
+
+    // Accounts for |tuple| inserted into the table. |schema| needs to be the
+    // one the sketches were created for.
+    pub fn on_insert(&mut self, schema: &Schema, tuple: &Tuple) {
+        for (idx, column) in self.columns.iter_mut().enumerate() {
+            let value = tuple.nth_value(schema, idx);
+            match value.is_null() {
+                true => column.null_count += 1,
+                false => {
+                    column.frequencies.add_value(&value);
+                    column.sample.add_value(&value);
+                }
+            }
+        }
+        self.row_count += 1;
+        self.changes += 1;
+    }
+
+    // Accounts for |tuple| deleted from the table. An update is a delete of
+    // the old tuple followed by an insert of the new one.
+    pub fn on_delete(&mut self, schema: &Schema, tuple: &Tuple) {
+        for (idx, column) in self.columns.iter_mut().enumerate() {
+            let value = tuple.nth_value(schema, idx);
+            match value.is_null() {
+                true => column.null_count = column.null_count.saturating_sub(1),
+                false => {
+                    column.frequencies.remove_value(&value);
+                    column.sample.remove_value(&value);
+                }
+            }
+        }
+        self.row_count = self.row_count.saturating_sub(1);
+        self.changes += 1;
+    }
+
+    // Folds the sketches of the rows tracked by |other|, which needs to cover
+    // other rows of the same table, e.g. those loaded by another worker.
+    // Returns |InvalidInput| if the columns differ.
+    pub fn merge(&mut self, other: &Self) -> std::io::Result<()> {
+        if self.columns.len() != other.columns.len() {
+            return Err(invalid_input("Cannot merge sketches of different columns"));
+        }
+        for (column, other) in self.columns.iter_mut().zip(other.columns.iter()) {
+            column.frequencies.merge(&other.frequencies)?;
+            column.sample.merge(&other.sample)?;
+            column.null_count += other.null_count;
+        }
+        self.row_count += other.row_count;
+        self.changes += other.changes;
+        Ok(())
+    }
+
+    // Serializes the sketches into the persisted statistics, and starts
+    // counting changes afresh.
+    pub fn checkpoint(&mut self) -> Vec<u8> {
+        let mut buffer = vec![0; self.serialized_len()];
+        self.serialize_to(&mut buffer);
+        self.changes = 0;
+        buffer
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        12 + self
+            .columns
+            .iter()
+            .map(|column| column.serialized_len())
+            .sum::<usize>()
+    }
+
+    // The caller needs to ensure that |dst| has |self.serialized_len()| bytes.
+    pub fn serialize_to(&self, dst: &mut [u8]) {
+        reinterpret::write_u64(dst, self.row_count);
+        reinterpret::write_u32(&mut dst[8..], self.columns.len() as u32);
+        let mut offset = 12;
+        for column in self.columns.iter() {
+            column.serialize_to(&mut dst[offset..]);
+            offset += column.serialized_len();
+        }
+    }
+
+    // Returns |InvalidData| if |src| does not hold serialized sketches.
+    pub fn deserialize_from(src: &[u8]) -> std::io::Result<Self> {
+        if src.len() < 12 {
+            return Err(invalid_data("Table sketches are truncated"));
+        }
+        let column_count = reinterpret::read_u32(&src[8..]) as usize;
+        let mut sketches = TableSketches {
+            row_count: reinterpret::read_u64(src),
+            columns: Vec::new(),
+            changes: 0,
+        };
+        let mut offset = 12;
+        for _ in 0..column_count {
+            let column = ColumnSketch::deserialize_from(&src[offset..])?;
+            offset += column.serialized_len();
+            sketches.columns.push(column);
+        }
+        Ok(sketches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::column::Column;
+    use crate::types::types::Str;
+    use crate::types::types::Types;
+    use crate::types::types::Varlen;
+    use crate::types::value::Value;
+
+    fn create_schema() -> Schema<'static> {
+        Schema::new(vec![
+            Column::new("Name".to_string(), Types::owned(), 10),
+            Column::new("Count".to_string(), Types::integer(), 4),
+        ])
+    }
+
+    fn create_tuple(schema: &Schema, name: &str, count: Option<i32>) -> Tuple {
+        let values = vec![
+            Value::new(Types::Varchar(Varlen::Owned(Str::Val(name.to_string())))),
+            match count {
+                Some(count) => Value::new(Types::Integer(count)),
+                None => Value::null(Types::integer()),
+            },
+        ];
+        Tuple::new(&values, schema)
+    }
+
+    fn tuple_value<'a>(schema: &'a Schema, name: &str) -> Value<'a> {
+        create_tuple(schema, name, Some(0)).nth_value(schema, 0)
+    }
+
+    #[test]
+    fn insert_and_delete() {
+        let schema = create_schema();
+        let mut sketches = TableSketches::new(&schema);
+        for i in 0..100 {
+            let name = if i % 2 == 0 { "even" } else { "odd" };
+            sketches.on_insert(&schema, &create_tuple(&schema, name, Some(i)));
+        }
+        sketches.on_insert(&schema, &create_tuple(&schema, "none", None));
+        assert_eq!(101, sketches.row_count());
+        assert_eq!(101, sketches.changes_since_checkpoint());
+
+        let count = sketches.column(1).unwrap();
+        assert_eq!(1, count.null_count());
+        assert_eq!(100, count.frequencies().total());
+        assert_eq!(100, count.sample().population());
+        let name = sketches.column(0).unwrap();
+        assert_eq!(3, name.frequencies().heavy_hitters().len());
+        let even = tuple_value(&schema, "even");
+        assert!(name.frequencies().estimate_value(&even) >= 50);
+
+        let tuple = create_tuple(&schema, "none", None);
+        sketches.on_delete(&schema, &tuple);
+        let tuple = create_tuple(&schema, "even", Some(0));
+        sketches.on_delete(&schema, &tuple);
+        assert_eq!(99, sketches.row_count());
+        assert_eq!(0, sketches.column(1).unwrap().null_count());
+        assert_eq!(99, sketches.column(1).unwrap().sample().population());
+        assert!(sketches.column(2).is_none());
+    }
+
+    #[test]
+    fn merge_and_checkpoint() {
+        let schema = create_schema();
+        let mut sketches = TableSketches::new(&schema);
+        let mut other = TableSketches::new(&schema);
+        for i in 0..10 {
+            sketches.on_insert(&schema, &create_tuple(&schema, "a", Some(i)));
+            other.on_insert(&schema, &create_tuple(&schema, "b", None));
+        }
+        assert!(sketches.merge(&other).is_ok());
+        assert_eq!(20, sketches.row_count());
+        assert_eq!(10, sketches.column(1).unwrap().null_count());
+        let one_column = Schema::new(vec![Column::new("Count".to_string(), Types::integer(), 4)]);
+        assert!(sketches.merge(&TableSketches::new(&one_column)).is_err());
+
+        let persisted = sketches.checkpoint();
+        assert_eq!(0, sketches.changes_since_checkpoint());
+        assert_eq!(
+            sketches,
+            TableSketches::deserialize_from(&persisted).unwrap()
+        );
+        assert!(TableSketches::deserialize_from(&persisted[..100]).is_err());
+    }
+}
@@ -0,0 +1,435 @@
+// Functionality: Statistics of a table computed by ANALYZE, i.e. a full scan
+// of its heap: the number of rows, and for every column its minimum and
+// maximum values, the number of nulls and an estimate of the number of
+// distinct values (counted by a HyperLogLog sketch), and an equi-depth
+// histogram of its values, built from a reservoir sample of them. They are
+// persisted in the catalog, for the optimizer to estimate the cost of plans;
+// see |range_selectivity| and |equality_selectivity|.
+//
+// Unlike |TableSketches|, they are not maintained on writes, and go stale
+// until the table is analyzed again.
+//
+// Serialized format (size in byte):
+//  ---------------------------------------------------------------
+// | RowCount (8) | ColumnCount (4) | Column_1 (variable) | ...
+//  ---------------------------------------------------------------
+// Column format:
+//  -----------------------------------------------------------------------------
+// | NullCount (8) | DistinctCount (8) | MinLen (4) | Min | MaxLen (4) | Max |
+//  -----------------------------------------------------------------------------
+// A length of 0 means there is no such value, i.e. all values are null, or
+// the values cannot be ordered, e.g. points.
+//
+// The columns are followed by the histogram of every column, in the format of
+// |Histogram|. Statistics persisted before histograms existed end after the
+// columns, and have empty histograms.
+
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::catalog::catalog::TableInfo;
+use crate::catalog::histogram::Histogram;
+use crate::catalog::histogram::HISTOGRAM_BUCKETS;
+use crate::catalog::schema::Schema;
+use crate::common::error::*;
+use crate::common::reinterpret;
+use crate::disk::storage_backend::StorageBackend;
+use crate::logging::error_logging::ErrorLogging;
+use crate::page::table_page::TablePage;
+use crate::types::hyperloglog::HyperLogLog;
+use crate::types::reservoir_sample::ReservoirSample;
+use crate::types::types::Operation;
+use crate::types::types::Types;
+use crate::types::value::Value;
+use std::mem;
+use std::ops::Bound;
+
+// The number of values of every column ANALYZE samples to build histograms.
+const HISTOGRAM_SAMPLE_SIZE: usize = 1024;
+
+#[derive(Clone, Debug, Default)]
+pub struct ColumnStatistics {
+    min: Option<Value<'static>>,
+    max: Option<Value<'static>>,
+    null_count: u64,
+    distinct_count: u64,
+    histogram: Histogram,
+}
+
+impl ColumnStatistics {
+    pub fn min(&self) -> Option<&Value<'static>> {
+        self.min.as_ref()
+    }
+
+    pub fn max(&self) -> Option<&Value<'static>> {
+        self.max.as_ref()
+    }
+
+    pub fn null_count(&self) -> u64 {
+        self.null_count
+    }
+
+    pub fn distinct_count(&self) -> u64 {
+        self.distinct_count
+    }
+
+    pub fn histogram(&self) -> &Histogram {
+        &self.histogram
+    }
+
+    fn serialized_len(&self) -> usize {
+        let value_len = |value: &Option<Value>| value.as_ref().map_or(0, serialized_value_len);
+        24 + value_len(&self.min) + value_len(&self.max)
+    }
+
+    fn serialize_to(&self, dst: &mut [u8]) {
+        reinterpret::write_u64(dst, self.null_count);
+        reinterpret::write_u64(&mut dst[8..], self.distinct_count);
+        let mut offset = 16;
+        for value in [&self.min, &self.max].iter() {
+            let len = value.as_ref().map_or(0, serialized_value_len);
+            reinterpret::write_u32(&mut dst[offset..], len as u32);
+            if let Some(value) = value {
+                value.serialize_to(&mut dst[(offset + 4)..(offset + 4 + len)]);
+            }
+            offset += 4 + len;
+        }
+    }
+
+    fn deserialize_from(src: &[u8], types: &Types) -> std::io::Result<Self> {
+        let truncated = || invalid_data("Column statistics are truncated");
+        if src.len() < 16 {
+            return Err(truncated());
+        }
+        let mut column = ColumnStatistics {
+            null_count: reinterpret::read_u64(src),
+            distinct_count: reinterpret::read_u64(&src[8..]),
+            ..ColumnStatistics::default()
+        };
+        let mut offset = 16;
+        for value in [&mut column.min, &mut column.max].iter_mut() {
+            if src.len() < offset + 4 {
+                return Err(truncated());
+            }
+            let len = reinterpret::read_u32(&src[offset..]) as usize;
+            offset += 4;
+            if len == 0 {
+                continue;
+            }
+            if src.len() < offset + len {
+                return Err(truncated());
+            }
+            let mut decoded = Value::new(types.to_static());
+            decoded.deserialize_from(&src[offset..(offset + len)]);
+            **value = Some(decoded);
+            offset += len;
+        }
+        Ok(column)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TableStatistics {
+    row_count: u64,
+    columns: Vec<ColumnStatistics>,
+}
+
+impl TableStatistics {
+    // Scans the heap of |table|, and computes its statistics.
+    pub fn analyze<S: StorageBackend>(
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        table: &TableInfo,
+    ) -> std::io::Result<Self> {
+        let schema = table.schema();
+        let columns = schema.columns().len();
+        let mut statistics = TableStatistics {
+            row_count: 0,
+            columns: vec![ColumnStatistics::default(); columns],
+        };
+        let mut sketches = vec![HyperLogLog::default(); columns];
+        let mut samples = vec![ReservoirSample::new(HISTOGRAM_SAMPLE_SIZE); columns];
+        for item in table.heap().iter(bpm) {
+            let (_, tuple) = item?;
+            // Tuples of unknown schema versions are logged and skipped.
+            let tuple = match table.upgrade(tuple).log_and() {
+                Ok(tuple) => tuple,
+                Err(_) => continue,
+            };
+            statistics.row_count += 1;
+            for (idx, column) in statistics.columns.iter_mut().enumerate() {
+                let value = tuple.nth_value(schema, idx);
+                if value.is_null() {
+                    column.null_count += 1;
+                    continue;
+                }
+                sketches[idx].add_value(&value);
+                // Points have no order.
+                if let Types::Point(..) = value.borrow() {
+                    continue;
+                }
+                samples[idx].add_value(&value);
+                let value = value.to_static();
+                column.min = Some(extreme(column.min.take(), &value, /*smaller=*/ true));
+                column.max = Some(extreme(column.max.take(), &value, /*smaller=*/ false));
+            }
+        }
+        for (idx, column) in statistics.columns.iter_mut().enumerate() {
+            // The estimate may exceed the number of values on small tables.
+            column.distinct_count = sketches[idx]
+                .estimate()
+                .min(statistics.row_count - column.null_count);
+            let types = schema.nth_types(idx).unwrap();
+            let values = samples[idx]
+                .samples()
+                .iter()
+                .map(|bytes| {
+                    let mut value = Value::new(types.to_static());
+                    value.deserialize_from(bytes);
+                    value
+                })
+                .collect();
+            column.histogram = Histogram::build(values, HISTOGRAM_BUCKETS);
+        }
+        Ok(statistics)
+    }
+
+    pub fn row_count(&self) -> u64 {
+        self.row_count
+    }
+
+    pub fn column(&self, idx: usize) -> Option<&ColumnStatistics> {
+        self.columns.get(idx)
+    }
+
+    // Returns the fraction of the rows whose column |idx| is null, which is 0
+    // for an empty table.
+    pub fn null_fraction(&self, idx: usize) -> Option<f64> {
+        let column = self.columns.get(idx)?;
+        match self.row_count {
+            0 => Some(0.0),
+            rows => Some(column.null_count as f64 / rows as f64),
+        }
+    }
+
+    // Estimates the fraction of the rows whose column |idx| lies between
+    // |low| and |high|. Returns None if there is no histogram of the column,
+    // or the bounds cannot be compared with its values.
+    pub fn range_selectivity(
+        &self,
+        idx: usize,
+        low: Bound<&Value>,
+        high: Bound<&Value>,
+    ) -> Option<f64> {
+        let histogram = &self.columns.get(idx)?.histogram;
+        let below = match low {
+            Bound::Included(value) => histogram.fraction_below(value, /*inclusive=*/ false)?,
+            Bound::Excluded(value) => histogram.fraction_below(value, /*inclusive=*/ true)?,
+            Bound::Unbounded => 0.0,
+        };
+        let up_to = match high {
+            Bound::Included(value) => histogram.fraction_below(value, /*inclusive=*/ true)?,
+            Bound::Excluded(value) => histogram.fraction_below(value, /*inclusive=*/ false)?,
+            Bound::Unbounded => histogram.fraction_below(histogram.bounds().last()?, true)?,
+        };
+        let non_null = 1.0 - self.null_fraction(idx)?;
+        Some((up_to - below).max(0.0) * non_null)
+    }
+
+    // Estimates the fraction of the rows whose column |idx| equals a value,
+    // assuming the values are spread evenly. Returns None if the column has
+    // no values.
+    pub fn equality_selectivity(&self, idx: usize) -> Option<f64> {
+        let column = self.columns.get(idx)?;
+        match column.distinct_count {
+            0 => None,
+            distinct => Some((1.0 - self.null_fraction(idx)?) / distinct as f64),
+        }
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        12 + self
+            .columns
+            .iter()
+            .map(|column| column.serialized_len() + column.histogram.serialized_len())
+            .sum::<usize>()
+    }
+
+    // The caller needs to ensure that |dst| has |self.serialized_len()| bytes.
+    pub fn serialize_to(&self, dst: &mut [u8]) {
+        reinterpret::write_u64(dst, self.row_count);
+        reinterpret::write_u32(&mut dst[8..], self.columns.len() as u32);
+        let mut offset = 12;
+        for column in self.columns.iter() {
+            column.serialize_to(&mut dst[offset..]);
+            offset += column.serialized_len();
+        }
+        for column in self.columns.iter() {
+            column.histogram.serialize_to(&mut dst[offset..]);
+            offset += column.histogram.serialized_len();
+        }
+    }
+
+    // Returns |InvalidData| if |src| does not hold the serialized statistics
+    // of a table with |schema|.
+    pub fn deserialize_from(src: &[u8], schema: &Schema) -> std::io::Result<Self> {
+        if src.len() < 12 {
+            return Err(invalid_data("Table statistics are truncated"));
+        }
+        let column_count = reinterpret::read_u32(&src[8..]) as usize;
+        if column_count != schema.columns().len() {
+            return Err(invalid_data("Table statistics do not match the schema"));
+        }
+        let mut statistics = TableStatistics {
+            row_count: reinterpret::read_u64(src),
+            columns: Vec::new(),
+        };
+        let mut offset = 12;
+        for idx in 0..column_count {
+            let types = schema.nth_types(idx).unwrap();
+            let column = ColumnStatistics::deserialize_from(&src[offset..], types)?;
+            offset += column.serialized_len();
+            statistics.columns.push(column);
+        }
+        if offset == src.len() {
+            return Ok(statistics);
+        }
+        for (idx, column) in statistics.columns.iter_mut().enumerate() {
+            let types = schema.nth_types(idx).unwrap();
+            column.histogram = Histogram::deserialize_from(&src[offset..], types)?;
+            offset += column.histogram.serialized_len();
+        }
+        Ok(statistics)
+    }
+}
+
+// Room for a value, as in a tuple.
+fn serialized_value_len(value: &Value) -> usize {
+    value.len() + mem::size_of::<u64>()
+}
+
+// Returns the smaller (or larger) one of |current| and |value|.
+fn extreme(
+    current: Option<Value<'static>>,
+    value: &Value<'static>,
+    smaller: bool,
+) -> Value<'static> {
+    let current = match current {
+        Some(current) => current,
+        None => return value.clone(),
+    };
+    let result = match smaller {
+        true => current.min(value),
+        false => current.max(value),
+    };
+    result.unwrap_or(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::catalog::catalog::Catalog;
+    use crate::catalog::column::Column;
+    use crate::table::tuple::Tuple;
+    use crate::types::types::Str;
+    use crate::types::types::Varlen;
+
+    fn create_schema() -> Schema<'static> {
+        Schema::new(vec![
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("Name".to_string(), Types::owned(), 10),
+            Column::new("Score".to_string(), Types::decimal(), 8),
+        ])
+    }
+
+    fn create_tuple(schema: &Schema, id: i64, name: &str, score: Option<f64>) -> Tuple {
+        let values = vec![
+            Value::new(Types::BigInt(id)),
+            Value::new(Types::Varchar(Varlen::Owned(Str::Val(name.to_string())))),
+            match score {
+                Some(score) => Value::new(Types::Decimal(score)),
+                None => Value::null(Types::decimal()),
+            },
+        ];
+        Tuple::new(&values, schema)
+    }
+
+    fn check(statistics: &TableStatistics) {
+        assert_eq!(100, statistics.row_count());
+        assert!(statistics.column(3).is_none());
+
+        let id = statistics.column(0).unwrap();
+        assert_eq!("0", id.min().unwrap().to_string());
+        assert_eq!("99", id.max().unwrap().to_string());
+        assert_eq!(0, id.null_count());
+        assert!((90..=100).contains(&id.distinct_count()));
+
+        let name = statistics.column(1).unwrap();
+        assert_eq!("even", name.min().unwrap().to_string());
+        assert_eq!("odd", name.max().unwrap().to_string());
+        assert_eq!(2, name.distinct_count());
+        let bounds = name.histogram().bounds();
+        assert_eq!("even", bounds[0].to_string());
+        assert_eq!("odd", bounds[bounds.len() - 1].to_string());
+
+        let score = statistics.column(2).unwrap();
+        assert_eq!(75, score.null_count());
+        assert_eq!(Some(0.75), statistics.null_fraction(2));
+        assert_eq!(Some(0.0), statistics.null_fraction(0));
+        assert_eq!(None, statistics.null_fraction(3));
+        assert_eq!("0", score.min().unwrap().to_string());
+        assert_eq!("96", score.max().unwrap().to_string());
+    }
+
+    #[test]
+    fn analyze_and_reopen() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(4);
+
+        {
+            let mut catalog = Catalog::create(&mut bpm).unwrap();
+            let oid = catalog
+                .create_table(&mut bpm, 1, "scores", create_schema())
+                .unwrap();
+            assert!(catalog.statistics(oid).is_none());
+            assert!(catalog.analyze(&mut bpm, 1, "missing").is_err());
+
+            // Statistics of an empty table.
+            let statistics = catalog.analyze(&mut bpm, 1, "scores").unwrap();
+            assert_eq!(0, statistics.row_count());
+            assert!(statistics.column(0).unwrap().min().is_none());
+            assert_eq!(Some(0.0), statistics.null_fraction(2));
+
+            let scores = catalog.table("scores").unwrap();
+            let mut heap = scores.heap();
+            for id in 0..100 {
+                let name = if id % 2 == 0 { "even" } else { "odd" };
+                let score = if id % 4 == 0 { Some(id as f64) } else { None };
+                let tuple = create_tuple(scores.schema(), id, name, score);
+                heap.insert_tuple(&mut bpm, 1, tuple).unwrap();
+            }
+            // Statistics go stale until the table is analyzed again.
+            assert_eq!(0, catalog.statistics(oid).unwrap().row_count());
+            check(catalog.analyze(&mut bpm, 1, "scores").unwrap());
+
+            // Round trip through the serialized format.
+            let statistics = catalog.statistics(oid).unwrap();
+            let mut data = vec![0; statistics.serialized_len()];
+            statistics.serialize_to(&mut data);
+            check(&TableStatistics::deserialize_from(&data, &create_schema()).unwrap());
+            let other = Schema::new(vec![Column::new("Id".to_string(), Types::bigint(), 8)]);
+            assert!(TableStatistics::deserialize_from(&data, &other).is_err());
+            assert!(TableStatistics::deserialize_from(&data[..40], &create_schema()).is_err());
+        }
+        {
+            let mut catalog = Catalog::open(&mut bpm).unwrap();
+            let oid = catalog.table("scores").unwrap().oid();
+            check(catalog.statistics(oid).unwrap());
+
+            catalog.drop_table(&mut bpm, 1, "scores").unwrap();
+            assert!(catalog.statistics(oid).is_none());
+        }
+        {
+            let catalog = Catalog::open(&mut bpm).unwrap();
+            assert!(catalog.tables().is_empty());
+            assert!(catalog.statistics(1).is_none());
+        }
+    }
+}
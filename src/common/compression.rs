@@ -0,0 +1,183 @@
+// A small LZ77-style block compressor, used to keep pages compressed without
+// pulling in an external dependency.
+//
+// Compressed format: a sequence of (token, literals, match) triples.
+//  -----------------------------------------------------------------------
+// | Token (1) | LiteralLen* | Literals | Offset (2) | MatchLen* | ... |
+//  -----------------------------------------------------------------------
+// The high 4 bits of the token hold the literal length and the low 4 bits the
+// match length minus |MIN_MATCH|; a nibble of 15 is followed by extra length
+// bytes (each 255 means "keep adding"). The last triple has no match part.
+
+use crate::common::error::*;
+
+const MIN_MATCH: usize = 4;
+const MAX_OFFSET: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 12;
+const NIBBLE_MAX: usize = 15;
+
+// Compresses |src| into a new buffer.
+pub fn compress(src: &[u8]) -> Vec<u8> {
+    let mut dst = Vec::with_capacity(src.len() / 2);
+    let mut table = [usize::MAX; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut pos = 0;
+    while pos + MIN_MATCH <= src.len() {
+        let hash = hash(&src[pos..]);
+        let candidate = table[hash];
+        table[hash] = pos;
+        if candidate != usize::MAX
+            && pos - candidate <= MAX_OFFSET
+            && src[candidate..(candidate + MIN_MATCH)] == src[pos..(pos + MIN_MATCH)]
+        {
+            let mut len = MIN_MATCH;
+            while pos + len < src.len() && src[candidate + len] == src[pos + len] {
+                len += 1;
+            }
+            write_sequence(&mut dst, &src[anchor..pos], Some((pos - candidate, len)));
+            pos += len;
+            anchor = pos;
+        } else {
+            pos += 1;
+        }
+    }
+    write_sequence(&mut dst, &src[anchor..], None);
+    dst
+}
+
+// Decompresses |src| into |dst| and returns the number of bytes written.
+// Returns |InvalidData| if |src| is malformed or does not fit into |dst|.
+pub fn decompress(src: &[u8], dst: &mut [u8]) -> std::io::Result<usize> {
+    let mut input = 0;
+    let mut output = 0;
+    loop {
+        let token = *src
+            .get(input)
+            .ok_or_else(|| invalid_data("Compressed data is truncated"))? as usize;
+        input += 1;
+
+        let mut literal_len = token >> 4;
+        if literal_len == NIBBLE_MAX {
+            literal_len += read_len(src, &mut input)?;
+        }
+        if input + literal_len > src.len() || output + literal_len > dst.len() {
+            return Err(invalid_data("Compressed literals out of bounds"));
+        }
+        dst[output..(output + literal_len)].copy_from_slice(&src[input..(input + literal_len)]);
+        input += literal_len;
+        output += literal_len;
+        if input == src.len() {
+            return Ok(output);
+        }
+
+        if input + 2 > src.len() {
+            return Err(invalid_data("Compressed data is truncated"));
+        }
+        let offset = src[input] as usize | (src[input + 1] as usize) << 8;
+        input += 2;
+        let mut match_len = (token & NIBBLE_MAX) + MIN_MATCH;
+        if token & NIBBLE_MAX == NIBBLE_MAX {
+            match_len += read_len(src, &mut input)?;
+        }
+        if offset == 0 || offset > output || output + match_len > dst.len() {
+            return Err(invalid_data("Compressed match out of bounds"));
+        }
+        // Copy byte by byte, since the match may overlap with itself.
+        for i in output..(output + match_len) {
+            dst[i] = dst[i - offset];
+        }
+        output += match_len;
+    }
+}
+
+fn hash(data: &[u8]) -> usize {
+    let word = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+    (word.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+fn write_sequence(dst: &mut Vec<u8>, literals: &[u8], matched: Option<(usize, usize)>) {
+    let literal_nibble = literals.len().min(NIBBLE_MAX);
+    let match_nibble = matched.map_or(0, |(_, len)| (len - MIN_MATCH).min(NIBBLE_MAX));
+    dst.push((literal_nibble << 4 | match_nibble) as u8);
+    if literal_nibble == NIBBLE_MAX {
+        write_len(dst, literals.len() - NIBBLE_MAX);
+    }
+    dst.extend_from_slice(literals);
+    if let Some((offset, len)) = matched {
+        dst.push(offset as u8);
+        dst.push((offset >> 8) as u8);
+        if match_nibble == NIBBLE_MAX {
+            write_len(dst, len - MIN_MATCH - NIBBLE_MAX);
+        }
+    }
+}
+
+fn write_len(dst: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        dst.push(255);
+        len -= 255;
+    }
+    dst.push(len as u8);
+}
+
+fn read_len(src: &[u8], input: &mut usize) -> std::io::Result<usize> {
+    let mut len = 0;
+    loop {
+        let byte = *src
+            .get(*input)
+            .ok_or_else(|| invalid_data("Compressed length is truncated"))?;
+        *input += 1;
+        len += byte as usize;
+        if byte != 255 {
+            return Ok(len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::config::PAGE_SIZE;
+
+    fn round_trip(src: &[u8]) -> Vec<u8> {
+        let compressed = compress(src);
+        let mut dst = vec![0; src.len()];
+        assert_eq!(src.len(), decompress(&compressed, &mut dst).unwrap());
+        assert_eq!(src, &dst[..]);
+        compressed
+    }
+
+    #[test]
+    fn compress_and_decompress() {
+        round_trip(b"");
+        round_trip(b"abc");
+        round_trip(b"hello hello hello hello world");
+
+        // A mostly empty page compresses very well.
+        let mut page = [0; PAGE_SIZE];
+        page[100..111].copy_from_slice(b"hello world");
+        page[PAGE_SIZE - 8..].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(round_trip(&page).len() < 64);
+
+        // Incompressible data still round trips.
+        let mut state: u32 = 19260817;
+        let noise: Vec<u8> = (0..PAGE_SIZE)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        round_trip(&noise);
+    }
+
+    #[test]
+    fn malformed_input() {
+        let compressed = compress(b"hello hello hello hello world");
+        let mut dst = [0; 64];
+        assert!(decompress(&compressed[..compressed.len() - 3], &mut dst).is_err());
+        assert!(decompress(&compressed, &mut dst[..8]).is_err());
+        assert!(decompress(&[], &mut dst).is_err());
+        // A match pointing before the start of the output.
+        assert!(decompress(&[0x10, b'a', 0x02, 0x00], &mut dst).is_err());
+    }
+}
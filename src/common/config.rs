@@ -2,9 +2,13 @@
 
 pub const INVALID_PAGE_ID: i32 = -1; // Represents an invalid page ID.
 pub const INVALID_TRANSACTION_ID: i32 = -1; // Represents an invalid tansaction ID.
+pub const INVALID_LSN: i32 = -1; // Represents an invalid log sequence number.
 pub const HEADER_PAGE_ID: i32 = 0; // The header page ID.
+pub const CATALOG_PAGE_ID: i32 = 1; // The first page of the catalog table heap.
 pub const PAGE_SIZE: usize = 4096; // Size of a data page in bytes.
 pub const CHECKSUM_SIZE: usize = 8; // Size of the checksum overhead.
+pub const LOG_BUFFER_SIZE: usize = 32 * PAGE_SIZE; // Size of the in-memory log buffer.
 
 pub type PageId = i32;
 pub type TransactionId = i32;
+pub type Lsn = i32;
@@ -0,0 +1,60 @@
+// CRC-32C (Castagnoli) checksum. Unlike `DefaultHasher`, its output is stable
+// across Rust versions and platforms, so it is safe to persist on disk.
+
+const POLYNOMIAL: u32 = 0x82f6_3b78; // Reversed Castagnoli polynomial.
+
+const TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+// Computes the CRC-32C of |data|.
+pub fn crc32c(data: &[u8]) -> u32 {
+    extend(0, data)
+}
+
+// Continues the CRC-32C |crc| computed so far with more |data|.
+pub fn extend(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data.iter() {
+        crc = TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_values() {
+        assert_eq!(0, crc32c(b""));
+        assert_eq!(0xe306_9283, crc32c(b"123456789"));
+        assert_eq!(0x8a91_36aa, crc32c(&[0; 32]));
+        assert_eq!(0x62a8_ab43, crc32c(&[0xff; 32]));
+    }
+
+    #[test]
+    fn extend_matches_whole() {
+        let data = b"oranges are not the only fruit";
+        let partial = crc32c(&data[..10]);
+        assert_eq!(crc32c(data), extend(partial, &data[10..]));
+    }
+}
@@ -16,3 +16,11 @@ pub fn invalid_input(message: &str) -> Error {
 pub fn not_found(message: &str) -> Error {
     Error::new(ErrorKind::NotFound, message)
 }
+
+pub fn read_only(message: &str) -> Error {
+    Error::new(ErrorKind::ReadOnlyFilesystem, message)
+}
+
+pub fn quota_exceeded(message: &str) -> Error {
+    Error::new(ErrorKind::QuotaExceeded, message)
+}
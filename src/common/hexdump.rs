@@ -0,0 +1,50 @@
+// Renders bytes in the classic `hexdump -C` layout: offset, 16 bytes in hex,
+// then the printable ASCII characters.
+//
+// 00000000  68 65 6c 6c 6f 00 00 00  00 00 00 00 00 00 00 00  |hello...........|
+
+const BYTES_PER_LINE: usize = 16;
+
+pub fn hex_dump(data: &[u8]) -> String {
+    let mut s = String::new();
+    for (line, chunk) in data.chunks(BYTES_PER_LINE).enumerate() {
+        s.push_str(&format!("{:08x} ", line * BYTES_PER_LINE));
+        for i in 0..BYTES_PER_LINE {
+            if i % 8 == 0 {
+                s.push(' ');
+            }
+            match chunk.get(i) {
+                Some(byte) => s.push_str(&format!("{:02x} ", byte)),
+                None => s.push_str("   "),
+            }
+        }
+        s.push_str(" |");
+        for &byte in chunk.iter() {
+            s.push(if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            });
+        }
+        s.push_str("|\n");
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_dump_lines() {
+        assert_eq!("", hex_dump(&[]));
+
+        let mut data = [0; 20];
+        data[..5].copy_from_slice(b"hello");
+        assert_eq!(
+            "00000000  68 65 6c 6c 6f 00 00 00  00 00 00 00 00 00 00 00  |hello...........|\n\
+             00000010  00 00 00 00                                       |....|\n",
+            hex_dump(&data)
+        );
+    }
+}
@@ -1,4 +1,8 @@
+pub mod compression;
 pub mod config;
+pub mod crc32c;
 pub mod error;
+pub mod hexdump;
+pub mod options;
 pub mod reinterpret;
 pub mod rid;
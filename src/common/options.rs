@@ -0,0 +1,206 @@
+// Functionality: Options for opening a database, gathered in one place
+// instead of being passed to the constructors and setters of every layer:
+//
+// let options = DbOptions::new().pool_size(256).read_ahead(16).clone();
+// let mut db = Database::open("/path/to/db", &options)?;
+//
+// Setters chain like |std::fs::OpenOptions|. |DiskManager::with_options| and
+// |BufferPoolManager::with_options| apply the options of their layer, after
+// |validate| accepts them.
+
+use crate::buffer::buffer_pool_manager::MAX_READ_AHEAD_FRACTION;
+use crate::common::error::*;
+
+#[derive(Clone, Debug)]
+pub struct DbOptions {
+    pub(crate) pool_size: usize,
+    pub(crate) create_if_missing: bool,
+    pub(crate) read_only: bool,
+    pub(crate) direct_io: bool,
+    pub(crate) compression: bool,
+    pub(crate) read_ahead: usize,
+    pub(crate) scan_ring_threshold: usize,
+    pub(crate) pin_quota: usize,
+    pub(crate) cold_tier_capacity: usize,
+    pub(crate) stats_log_interval: u64,
+    pub(crate) flush_interval: u64,
+    pub(crate) verify_checksums: bool,
+    pub(crate) quarantine_dir: Option<String>,
+}
+
+impl Default for DbOptions {
+    fn default() -> Self {
+        DbOptions {
+            pool_size: 64,
+            create_if_missing: true,
+            read_only: false,
+            direct_io: false,
+            compression: false,
+            read_ahead: 0,
+            scan_ring_threshold: 0,
+            pin_quota: 0,
+            cold_tier_capacity: 0,
+            stats_log_interval: 0,
+            flush_interval: 0,
+            verify_checksums: true,
+            quarantine_dir: None,
+        }
+    }
+}
+
+impl DbOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The number of frames of every buffer pool.
+    pub fn pool_size(&mut self, pool_size: usize) -> &mut Self {
+        self.pool_size = pool_size;
+        self
+    }
+
+    // Whether to create the database if it does not exist; otherwise opening
+    // it returns |NotFound|. Read-only databases are never created.
+    pub fn create_if_missing(&mut self, create_if_missing: bool) -> &mut Self {
+        self.create_if_missing = create_if_missing;
+        self
+    }
+
+    // See |DiskManager::new_read_only|.
+    pub fn read_only(&mut self, read_only: bool) -> &mut Self {
+        self.read_only = read_only;
+        self
+    }
+
+    // See |DiskManager::with_direct_io|.
+    pub fn direct_io(&mut self, direct_io: bool) -> &mut Self {
+        self.direct_io = direct_io;
+        self
+    }
+
+    // See |DiskManager::set_compression|.
+    pub fn compression(&mut self, compression: bool) -> &mut Self {
+        self.compression = compression;
+        self
+    }
+
+    // See |BufferPoolManager::set_read_ahead|.
+    pub fn read_ahead(&mut self, pages: usize) -> &mut Self {
+        self.read_ahead = pages;
+        self
+    }
+
+    // See |BufferPoolManager::set_scan_ring_threshold|.
+    pub fn scan_ring_threshold(&mut self, pages: usize) -> &mut Self {
+        self.scan_ring_threshold = pages;
+        self
+    }
+
+    // See |BufferPoolManager::set_pin_quota|.
+    pub fn pin_quota(&mut self, pins: usize) -> &mut Self {
+        self.pin_quota = pins;
+        self
+    }
+
+    // See |BufferPoolManager::set_cold_tier_capacity|.
+    pub fn cold_tier_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.cold_tier_capacity = capacity;
+        self
+    }
+
+    // See |BufferPoolManager::set_stats_log_interval|.
+    pub fn stats_log_interval(&mut self, interval: u64) -> &mut Self {
+        self.stats_log_interval = interval;
+        self
+    }
+
+    // See |BufferPoolManager::set_flush_interval|.
+    pub fn flush_interval(&mut self, interval: u64) -> &mut Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    // See |DiskManager::set_verify_checksums|.
+    pub fn verify_checksums(&mut self, enabled: bool) -> &mut Self {
+        self.verify_checksums = enabled;
+        self
+    }
+
+    // See |DiskManager::set_quarantine_dir|.
+    pub fn quarantine_dir(&mut self, dir: &str) -> &mut Self {
+        self.quarantine_dir = Some(dir.to_string());
+        self
+    }
+
+    // Returns |InvalidInput| if the options contradict each other or are out
+    // of range.
+    pub fn validate(&self) -> std::io::Result<()> {
+        if self.pool_size == 0 {
+            return Err(invalid_input("Pool size must be positive"));
+        }
+        if self.read_only && self.compression {
+            return Err(invalid_input("Cannot compress a read-only database"));
+        }
+        if self.read_only && self.flush_interval > 0 {
+            return Err(invalid_input("Cannot flush a read-only database"));
+        }
+        if self.quarantine_dir.is_some() && !self.verify_checksums {
+            return Err(invalid_input(
+                "Cannot quarantine pages without verifying checksums",
+            ));
+        }
+        if self.read_ahead > (self.pool_size as f64 * MAX_READ_AHEAD_FRACTION) as usize {
+            return Err(invalid_input("Read-ahead exceeds its share of the pool"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        assert!(DbOptions::new().validate().is_ok());
+        let options = DbOptions::new()
+            .pool_size(16)
+            .read_ahead(4)
+            .compression(true)
+            .flush_interval(32)
+            .verify_checksums(false)
+            .pin_quota(8)
+            .clone();
+        assert_eq!(16, options.pool_size);
+        assert_eq!(32, options.flush_interval);
+        assert_eq!(8, options.pin_quota);
+        assert!(!options.verify_checksums);
+        assert!(options.validate().is_ok());
+
+        let error = |options: &DbOptions| options.validate().unwrap_err().to_string();
+        assert_eq!(
+            "Pool size must be positive",
+            error(DbOptions::new().pool_size(0))
+        );
+        assert_eq!(
+            "Cannot compress a read-only database",
+            error(DbOptions::new().read_only(true).compression(true))
+        );
+        assert_eq!(
+            "Cannot flush a read-only database",
+            error(DbOptions::new().read_only(true).flush_interval(1))
+        );
+        assert_eq!(
+            "Cannot quarantine pages without verifying checksums",
+            error(
+                DbOptions::new()
+                    .quarantine_dir("/tmp")
+                    .verify_checksums(false)
+            )
+        );
+        assert_eq!(
+            "Read-ahead exceeds its share of the pool",
+            error(DbOptions::new().pool_size(16).read_ahead(5))
+        );
+    }
+}
@@ -4,6 +4,8 @@ use std::clone::Clone;
 use std::cmp::Eq;
 use std::cmp::PartialEq;
 use std::fmt::Debug;
+use std::hash::Hash;
+use std::hash::Hasher;
 
 #[derive(Clone, Debug)]
 pub struct Rid {
@@ -51,3 +53,10 @@ impl PartialEq for Rid {
 }
 
 impl Eq for Rid {}
+
+impl Hash for Rid {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.page_id.hash(state);
+        self.slot_num.hash(state);
+    }
+}
@@ -0,0 +1,567 @@
+// Functionality: The lock manager grants shared and exclusive tuple locks,
+// keyed by |Rid|, under strict two-phase locking: a transaction acquires locks
+// as it goes and releases all of them at once when it commits or aborts.
+//
+// Every tuple has a FIFO queue of lock requests. A request is granted once it
+// is compatible with every request ahead of it, so that a stream of shared
+// locks cannot starve an exclusive one. A lock upgrade jumps ahead of the
+// waiting requests, and only one transaction may upgrade a tuple at a time.
+//
+// Deadlocks are broken by a background thread that periodically builds the
+// waits-for graph, i.e. an edge from every waiting transaction to every
+// transaction holding a conflicting lock, and aborts the youngest (largest
+// ID) transaction of every cycle. The victim's pending request fails with a
+// |TransactionAborted| error; its caller then rolls the transaction back and
+// calls |LockManager::abort| to release its locks.
+
+use crate::common::config::TransactionId;
+use crate::common::rid::Rid;
+use log::info;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Error;
+use std::ops::Drop;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+// The payload of the error returned when a transaction was chosen as a
+// deadlock victim, or tried to upgrade a lock another transaction is already
+// upgrading. Retrieve it with |err.get_ref()| and |downcast_ref|.
+#[derive(Debug)]
+pub struct TransactionAborted {
+    pub txn_id: TransactionId,
+}
+
+impl fmt::Display for TransactionAborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Transaction aborted; txn_id = {}", self.txn_id)
+    }
+}
+
+impl std::error::Error for TransactionAborted {}
+
+struct Request {
+    txn_id: TransactionId,
+    mode: LockMode,
+    granted: bool,
+}
+
+#[derive(Default)]
+struct RequestQueue {
+    requests: Vec<Request>,
+    // The transaction waiting to upgrade its shared lock, if any.
+    upgrading: Option<TransactionId>,
+}
+
+#[derive(Default)]
+struct TxnLocks {
+    aborted: bool,
+    shared: HashSet<Rid>,
+    exclusive: HashSet<Rid>,
+}
+
+#[derive(Default)]
+struct LockTable {
+    queues: HashMap<Rid, RequestQueue>,
+    txns: HashMap<TransactionId, TxnLocks>,
+    // Set when the lock manager is dropped, to stop the detector.
+    shutdown: bool,
+}
+
+#[derive(Default)]
+struct Inner {
+    table: Mutex<LockTable>,
+    // Notified whenever a lock is released or a transaction is aborted.
+    released: Condvar,
+}
+
+pub struct LockManager {
+    inner: Arc<Inner>,
+    detector: Option<JoinHandle<()>>,
+}
+
+impl Drop for LockManager {
+    fn drop(&mut self) {
+        self.inner.lock_table().shutdown = true;
+        self.inner.released.notify_all();
+        if let Some(detector) = self.detector.take() {
+            // The detector only panics if the lock table is poisoned.
+            let _ = detector.join();
+        }
+    }
+}
+
+impl LockManager {
+    // Creates a lock manager without deadlock detection; see
+    // |detect_deadlocks|.
+    pub fn new() -> Self {
+        LockManager {
+            inner: Arc::new(Inner::default()),
+            detector: None,
+        }
+    }
+
+    // Creates a lock manager whose background thread looks for deadlocks
+    // every |interval|.
+    pub fn with_deadlock_detection(interval: Duration) -> Self {
+        let inner = Arc::new(Inner::default());
+        let detector_inner = inner.clone();
+        let detector = thread::spawn(move || loop {
+            let table = detector_inner.lock_table();
+            let (mut table, _) = detector_inner
+                .released
+                .wait_timeout(table, interval)
+                .unwrap();
+            if table.shutdown {
+                return;
+            }
+            if !table.break_deadlocks().is_empty() {
+                detector_inner.released.notify_all();
+            }
+        });
+        LockManager {
+            inner,
+            detector: Some(detector),
+        }
+    }
+
+    // Blocks until |txn_id| holds a shared lock on |rid|. Holding an
+    // exclusive lock is enough.
+    pub fn lock_shared(&self, txn_id: TransactionId, rid: &Rid) -> std::io::Result<()> {
+        self.lock(txn_id, rid, LockMode::Shared)
+    }
+
+    // Blocks until |txn_id| holds an exclusive lock on |rid|. A shared lock
+    // held by |txn_id| is upgraded.
+    pub fn lock_exclusive(&self, txn_id: TransactionId, rid: &Rid) -> std::io::Result<()> {
+        let holds_shared = self
+            .inner
+            .lock_table()
+            .txns
+            .get(&txn_id)
+            .is_some_and(|locks| locks.shared.contains(rid));
+        match holds_shared {
+            true => self.upgrade(txn_id, rid),
+            false => self.lock(txn_id, rid, LockMode::Exclusive),
+        }
+    }
+
+    // Returns the lock |txn_id| holds on |rid|, if any.
+    pub fn lock_mode(&self, txn_id: TransactionId, rid: &Rid) -> Option<LockMode> {
+        let table = self.inner.lock_table();
+        let locks = table.txns.get(&txn_id)?;
+        if locks.exclusive.contains(rid) {
+            Some(LockMode::Exclusive)
+        } else if locks.shared.contains(rid) {
+            Some(LockMode::Shared)
+        } else {
+            None
+        }
+    }
+
+    // Whether |txn_id| was chosen as a deadlock victim and not yet aborted.
+    pub fn is_aborted(&self, txn_id: TransactionId) -> bool {
+        let table = self.inner.lock_table();
+        table.txns.get(&txn_id).is_some_and(|locks| locks.aborted)
+    }
+
+    // Releases all locks of a committed transaction.
+    pub fn commit(&self, txn_id: TransactionId) {
+        self.release_all(txn_id);
+    }
+
+    // Releases all locks of an aborted transaction. The caller needs to roll
+    // back its changes first.
+    pub fn abort(&self, txn_id: TransactionId) {
+        self.release_all(txn_id);
+    }
+
+    // Aborts the youngest transaction of every cycle in the waits-for graph.
+    // Returns the victims in ascending order.
+    pub fn detect_deadlocks(&self) -> Vec<TransactionId> {
+        let victims = self.inner.lock_table().break_deadlocks();
+        if !victims.is_empty() {
+            self.inner.released.notify_all();
+        }
+        victims
+    }
+
+    fn lock(&self, txn_id: TransactionId, rid: &Rid, mode: LockMode) -> std::io::Result<()> {
+        let mut guard = self.inner.lock_table();
+        let table = &mut *guard;
+        let locks = table.txns.entry(txn_id).or_default();
+        if locks.aborted {
+            return Err(aborted(txn_id));
+        }
+        let held = match mode {
+            LockMode::Shared => locks.shared.contains(rid) || locks.exclusive.contains(rid),
+            LockMode::Exclusive => locks.exclusive.contains(rid),
+        };
+        if held {
+            return Ok(());
+        }
+        let queue = table.queues.entry(rid.clone()).or_default();
+        queue.requests.push(Request {
+            txn_id,
+            mode,
+            granted: false,
+        });
+        self.wait(guard, txn_id, rid, mode)
+    }
+
+    fn upgrade(&self, txn_id: TransactionId, rid: &Rid) -> std::io::Result<()> {
+        let mut table = self.inner.lock_table();
+        if table.txns[&txn_id].aborted {
+            return Err(aborted(txn_id));
+        }
+        let queue = table.queues.get_mut(rid).unwrap();
+        if queue.upgrading.is_some() {
+            // Both upgraders would wait for each other.
+            table.txns.get_mut(&txn_id).unwrap().aborted = true;
+            return Err(aborted(txn_id));
+        }
+        queue.upgrading = Some(txn_id);
+        // Turn the shared lock into an exclusive request ahead of all waiting
+        // requests.
+        queue.requests.retain(|request| request.txn_id != txn_id);
+        let idx = queue.requests.iter().take_while(|r| r.granted).count();
+        queue.requests.insert(
+            idx,
+            Request {
+                txn_id,
+                mode: LockMode::Exclusive,
+                granted: false,
+            },
+        );
+        table.txns.get_mut(&txn_id).unwrap().shared.remove(rid);
+        let result = self.wait(table, txn_id, rid, LockMode::Exclusive);
+        let mut table = self.inner.lock_table();
+        if let Some(queue) = table.queues.get_mut(rid) {
+            queue.upgrading = None;
+            if queue.requests.is_empty() {
+                table.queues.remove(rid);
+            }
+        }
+        result
+    }
+
+    // Waits until the request of |txn_id| on |rid| is granted, or |txn_id| is
+    // aborted, in which case the request is withdrawn.
+    fn wait(
+        &self,
+        mut table: MutexGuard<'_, LockTable>,
+        txn_id: TransactionId,
+        rid: &Rid,
+        mode: LockMode,
+    ) -> std::io::Result<()> {
+        loop {
+            if table.txns[&txn_id].aborted {
+                table.withdraw(txn_id, rid);
+                self.inner.released.notify_all();
+                return Err(aborted(txn_id));
+            }
+            let queue = table.queues.get_mut(rid).unwrap();
+            if let Some(idx) = queue.grantable(txn_id) {
+                queue.requests[idx].granted = true;
+                let locks = table.txns.get_mut(&txn_id).unwrap();
+                match mode {
+                    LockMode::Shared => locks.shared.insert(rid.clone()),
+                    LockMode::Exclusive => locks.exclusive.insert(rid.clone()),
+                };
+                return Ok(());
+            }
+            table = self.inner.released.wait(table).unwrap();
+        }
+    }
+
+    fn release_all(&self, txn_id: TransactionId) {
+        let mut table = self.inner.lock_table();
+        let locks = match table.txns.remove(&txn_id) {
+            Some(locks) => locks,
+            None => return,
+        };
+        for rid in locks.shared.iter().chain(locks.exclusive.iter()) {
+            table.withdraw(txn_id, rid);
+        }
+        self.inner.released.notify_all();
+    }
+}
+
+impl Default for LockManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inner {
+    fn lock_table(&self) -> MutexGuard<'_, LockTable> {
+        self.table.lock().unwrap()
+    }
+}
+
+impl LockTable {
+    // Removes the request of |txn_id| on |rid|, granted or not.
+    fn withdraw(&mut self, txn_id: TransactionId, rid: &Rid) {
+        if let Some(queue) = self.queues.get_mut(rid) {
+            queue.requests.retain(|request| request.txn_id != txn_id);
+            if queue.requests.is_empty() && queue.upgrading.is_none() {
+                self.queues.remove(rid);
+            }
+        }
+    }
+
+    fn break_deadlocks(&mut self) -> Vec<TransactionId> {
+        let mut victims = Vec::new();
+        let mut graph = self.waits_for();
+        while let Some(victim) = find_cycle(&graph).and_then(|cycle| cycle.into_iter().max()) {
+            info!("Aborting deadlock victim; txn_id = {}", victim);
+            self.txns.get_mut(&victim).unwrap().aborted = true;
+            graph.remove(&victim);
+            for targets in graph.values_mut() {
+                targets.remove(&victim);
+            }
+            victims.push(victim);
+        }
+        victims.sort_unstable();
+        victims
+    }
+
+    // Builds the waits-for graph among the transactions not yet aborted. The
+    // ordered maps keep the victim choice deterministic.
+    fn waits_for(&self) -> BTreeMap<TransactionId, BTreeSet<TransactionId>> {
+        let mut graph: BTreeMap<TransactionId, BTreeSet<TransactionId>> = BTreeMap::new();
+        let is_live = |txn_id: &TransactionId| !self.txns[txn_id].aborted;
+        for queue in self.queues.values() {
+            for waiter in queue
+                .requests
+                .iter()
+                .filter(|r| !r.granted && is_live(&r.txn_id))
+            {
+                let holders = queue.requests.iter().filter(|holder| {
+                    holder.granted
+                        && holder.txn_id != waiter.txn_id
+                        && is_live(&holder.txn_id)
+                        && !compatible(holder.mode, waiter.mode)
+                });
+                for holder in holders {
+                    graph
+                        .entry(waiter.txn_id)
+                        .or_default()
+                        .insert(holder.txn_id);
+                }
+            }
+        }
+        graph
+    }
+}
+
+impl RequestQueue {
+    // Returns the index of the request of |txn_id| if it can be granted, i.e.
+    // it is compatible with every request ahead of it.
+    fn grantable(&self, txn_id: TransactionId) -> Option<usize> {
+        let idx = self.requests.iter().position(|r| r.txn_id == txn_id)?;
+        let mode = self.requests[idx].mode;
+        let blocked = self.requests[..idx]
+            .iter()
+            .any(|ahead| !compatible(ahead.mode, mode));
+        match blocked {
+            true => None,
+            false => Some(idx),
+        }
+    }
+}
+
+fn compatible(a: LockMode, b: LockMode) -> bool {
+    a == LockMode::Shared && b == LockMode::Shared
+}
+
+// Returns the transactions on a cycle of |graph|, if any, searching from the
+// smallest transaction ID.
+fn find_cycle(
+    graph: &BTreeMap<TransactionId, BTreeSet<TransactionId>>,
+) -> Option<Vec<TransactionId>> {
+    let mut visited = HashSet::new();
+    for &start in graph.keys() {
+        let mut path = Vec::new();
+        if let Some(cycle) = visit(graph, start, &mut visited, &mut path) {
+            return Some(cycle);
+        }
+    }
+    None
+}
+
+// Depth-first search from |txn_id|. |path| holds the transactions on the
+// current search path.
+fn visit(
+    graph: &BTreeMap<TransactionId, BTreeSet<TransactionId>>,
+    txn_id: TransactionId,
+    visited: &mut HashSet<TransactionId>,
+    path: &mut Vec<TransactionId>,
+) -> Option<Vec<TransactionId>> {
+    if let Some(idx) = path.iter().position(|&t| t == txn_id) {
+        return Some(path[idx..].to_vec());
+    }
+    if !visited.insert(txn_id) {
+        return None;
+    }
+    path.push(txn_id);
+    for &next in graph.get(&txn_id).into_iter().flatten() {
+        if let Some(cycle) = visit(graph, next, visited, path) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    None
+}
+
+fn aborted(txn_id: TransactionId) -> Error {
+    Error::other(TransactionAborted { txn_id })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    #[test]
+    fn shared_and_exclusive() {
+        let lock_mgr = Arc::new(LockManager::new());
+        let rid = Rid::new(1, 0);
+        lock_mgr.lock_shared(1, &rid).unwrap();
+        lock_mgr.lock_shared(2, &rid).unwrap();
+        assert_eq!(Some(LockMode::Shared), lock_mgr.lock_mode(2, &rid));
+
+        // The exclusive lock waits for both shared locks.
+        let (sender, receiver) = mpsc::channel();
+        let waiter = {
+            let lock_mgr = lock_mgr.clone();
+            let rid = rid.clone();
+            thread::spawn(move || {
+                lock_mgr.lock_exclusive(3, &rid).unwrap();
+                sender.send(()).unwrap();
+            })
+        };
+        assert!(receiver.recv_timeout(TIMEOUT).is_err());
+        lock_mgr.commit(1);
+        assert!(receiver.recv_timeout(TIMEOUT).is_err());
+        lock_mgr.abort(2);
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        waiter.join().unwrap();
+        assert_eq!(Some(LockMode::Exclusive), lock_mgr.lock_mode(3, &rid));
+        assert_eq!(None, lock_mgr.lock_mode(1, &rid));
+
+        // Holding an exclusive lock covers a shared one.
+        lock_mgr.lock_shared(3, &rid).unwrap();
+        assert_eq!(Some(LockMode::Exclusive), lock_mgr.lock_mode(3, &rid));
+    }
+
+    #[test]
+    fn upgrade() {
+        let lock_mgr = Arc::new(LockManager::new());
+        let rid = Rid::new(1, 0);
+        lock_mgr.lock_shared(1, &rid).unwrap();
+        lock_mgr.lock_shared(2, &rid).unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let upgrader = {
+            let lock_mgr = lock_mgr.clone();
+            let rid = rid.clone();
+            thread::spawn(move || {
+                lock_mgr.lock_exclusive(1, &rid).unwrap();
+                sender.send(()).unwrap();
+            })
+        };
+        assert!(receiver.recv_timeout(TIMEOUT).is_err());
+
+        // A second upgrade of the same tuple would deadlock.
+        let err = lock_mgr.lock_exclusive(2, &rid).unwrap_err();
+        let payload = err.get_ref().unwrap().downcast_ref::<TransactionAborted>();
+        assert_eq!(2, payload.unwrap().txn_id);
+        assert!(lock_mgr.is_aborted(2));
+        lock_mgr.abort(2);
+
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        upgrader.join().unwrap();
+        assert_eq!(Some(LockMode::Exclusive), lock_mgr.lock_mode(1, &rid));
+    }
+
+    #[test]
+    fn deadlock_detection() {
+        let lock_mgr = Arc::new(LockManager::with_deadlock_detection(Duration::from_millis(
+            10,
+        )));
+        let (a, b) = (Rid::new(1, 0), Rid::new(1, 1));
+        lock_mgr.lock_exclusive(1, &a).unwrap();
+        lock_mgr.lock_exclusive(2, &b).unwrap();
+
+        let spawn = |txn_id: TransactionId, rid: &Rid| {
+            let lock_mgr = lock_mgr.clone();
+            let rid = rid.clone();
+            thread::spawn(move || {
+                let result = lock_mgr.lock_exclusive(txn_id, &rid);
+                if result.is_err() {
+                    lock_mgr.abort(txn_id);
+                }
+                result
+            })
+        };
+        let older = spawn(1, &b);
+        let younger = spawn(2, &a);
+
+        // The younger transaction is the victim, which lets the older one
+        // proceed.
+        assert!(younger.join().unwrap().is_err());
+        assert!(older.join().unwrap().is_ok());
+        assert_eq!(Some(LockMode::Exclusive), lock_mgr.lock_mode(1, &b));
+        assert_eq!(None, lock_mgr.lock_mode(2, &b));
+    }
+
+    #[test]
+    fn detect_deadlocks_manually() {
+        let lock_mgr = Arc::new(LockManager::new());
+        let (a, b) = (Rid::new(1, 0), Rid::new(1, 1));
+        lock_mgr.lock_shared(1, &a).unwrap();
+        lock_mgr.lock_shared(2, &b).unwrap();
+        assert!(lock_mgr.detect_deadlocks().is_empty());
+
+        let waiter = {
+            let lock_mgr = lock_mgr.clone();
+            let b = b.clone();
+            thread::spawn(move || lock_mgr.lock_exclusive(1, &b))
+        };
+        thread::sleep(TIMEOUT);
+        assert!(lock_mgr.detect_deadlocks().is_empty());
+
+        let (sender, receiver) = mpsc::channel();
+        let victim = {
+            let lock_mgr = lock_mgr.clone();
+            let a = a.clone();
+            thread::spawn(move || {
+                let result = lock_mgr.lock_exclusive(2, &a);
+                sender.send(()).unwrap();
+                result
+            })
+        };
+        assert!(receiver.recv_timeout(TIMEOUT).is_err());
+        assert_eq!(vec![2], lock_mgr.detect_deadlocks());
+        assert!(victim.join().unwrap().is_err());
+        lock_mgr.abort(2);
+        assert!(waiter.join().unwrap().is_ok());
+    }
+}
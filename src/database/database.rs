@@ -0,0 +1,1197 @@
+// Functionality: The entry point for embedding the crate as a database. A
+// |Database| owns the buffer pool of its file together with the catalog, and
+// executes statements or plans over them:
+//
+// let mut db = Database::open("/path/to/db", &DbOptions::default())?;
+// db.execute("create table users (Id BIGINT, Name VARCHAR(20))")?;
+// db.execute("insert into users values (1, 'apple'), (2, 'banana')")?;
+// let rows = db.execute("scan users where Id = 1")?;
+// let schema = rows.schema().clone();
+// for tuple in rows {
+//     let name: String = Row::new(&tuple?, &schema).get("Name")?;
+// }
+// let users: Vec<User> = db.query_as("scan users")?;
+// db.close()?;
+//
+// See |Statement| for the statements |execute| accepts, and |FromRow| for
+// reading rows as structs like |User| above. Tables and indexes share the
+// pages of the database file.
+//
+// Every statement runs in a transaction of its own. Insert, update and delete
+// run to completion before |execute| returns, and commit unless they fail;
+// their rows only hold the number of tuples changed. Queries commit once their
+// rows are read to the end or dropped, and abort on the first error. Changes
+// of tables are logged to a write-ahead log next to the database file, see
+// |LOG_FILE_SUFFIX|, so committed statements survive a crash: opening the
+// database recovers the tables from the log, see |RecoveryManager|. Indexes
+// are not logged, but rebuilt from the tables after a crash. |vacuum| frees
+// the pages emptied by deletes. The sketches of the tables are checkpointed
+// into the catalog every |SKETCH_CHECKPOINT_CHANGES| writes and on |close|,
+// which flushes everything and truncates the log. |dump| writes the tables
+// as statements, which |restore| replays, e.g. into a database of another
+// version of the page format. |bulk_load| inserts many tuples at once without
+// logging them.
+//
+// |Database::in_memory| keeps the pages in memory instead, e.g.
+// for tests. It has no log; the changes of running statements are kept in
+// memory to roll back a failing one instead.
+
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::catalog::catalog::Catalog;
+use crate::catalog::column::Column;
+use crate::catalog::schema::Schema;
+use crate::catalog::table_statistics::TableStatistics;
+use crate::common::config::TransactionId;
+use crate::common::config::INVALID_TRANSACTION_ID;
+use crate::common::error::*;
+use crate::common::options::DbOptions;
+use crate::concurrency::lock_manager::LockManager;
+use crate::database::dump::create_index_statement;
+use crate::database::dump::create_table_statement;
+use crate::database::dump::insert_statement;
+use crate::database::dump::StatementReader;
+use crate::database::dump::DUMP_INSERT_ROWS;
+use crate::database::statement::Statement;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::memory_storage::MemoryStorage;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::abort;
+use crate::execution::execution_context::begin;
+use crate::execution::execution_context::commit;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::create_executor;
+use crate::execution::executor::drain;
+use crate::execution::executor::Executor;
+use crate::logging::error_logging::ErrorLogging;
+use crate::page::table_page::TablePage;
+use crate::plan::explain::explain;
+use crate::plan::expression::Expression;
+use crate::plan::optimizer::Optimizer;
+use crate::plan::plan_node::PlanNode;
+use crate::recovery::log_manager::LogManager;
+use crate::recovery::log_manager::LogReader;
+use crate::recovery::recovery_manager::RecoveryManager;
+use crate::table::from_row::FromRow;
+use crate::table::from_row::Row;
+use crate::table::table_heap::VacuumStats;
+use crate::table::tuple::Tuple;
+use crate::types::types::Str;
+use crate::types::types::Types;
+use crate::types::types::Varlen;
+use crate::types::value::Value;
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::path::Path;
+
+// Appended to the path of a database to get the path of its log.
+pub const LOG_FILE_SUFFIX: &str = ".log";
+
+// The number of writes to the tables after which the next statement first
+// checkpoints their sketches; see |Catalog::checkpoint_sketches|.
+pub const SKETCH_CHECKPOINT_CHANGES: u64 = 1000;
+
+pub struct Database<S: StorageBackend = DiskManager> {
+    bpm: DefaultBufferPoolManager<TablePage, S>,
+    catalog: Catalog,
+    lock_mgr: LockManager,
+    next_txn_id: TransactionId,
+}
+
+// The tuples produced by a statement or a plan, computed as they are
+// iterated, except for those of insert, update and delete, which are computed
+// upfront. Statements producing no tuples have no columns.
+pub struct Rows<'a, S: StorageBackend = DiskManager> {
+    ctx: ExecutionContext<'a, S>,
+    executor: Box<dyn Executor<S>>,
+    // The tuples computed upfront, not read yet.
+    computed: VecDeque<Tuple>,
+    // Whether the transaction of the statement has ended.
+    ended: bool,
+}
+
+impl Database {
+    // Opens the database at |path|, as configured by |options|, and recovers
+    // it if it was not closed. Returns |NotFound| if it does not exist and is
+    // not to be created, and |InvalidData| if it is to be opened read-only but
+    // needs recovery.
+    pub fn open(path: &str, options: &DbOptions) -> std::io::Result<Self> {
+        let mut bpm = DefaultBufferPoolManager::with_options(path, options)?;
+        let log_path = log_path(path);
+        let is_clean = match options.read_only {
+            true => !Path::new(&log_path).exists() || LogReader::open(&log_path)?.is_clean()?,
+            false => {
+                let log_mgr = LogManager::new(&log_path)?;
+                let is_clean = log_mgr.reader()?.is_clean()?;
+                bpm.set_log_manager(log_mgr);
+                is_clean
+            }
+        };
+        if !is_clean && options.read_only {
+            return Err(invalid_data(&format!(
+                "Database needs recovery; open it for writing first; path = {}",
+                path
+            )));
+        }
+        if !is_clean {
+            RecoveryManager::new(&mut bpm)?.recover(&mut bpm)?;
+        }
+        let catalog = match Catalog::open(&mut bpm) {
+            Err(e) if e.kind() == ErrorKind::NotFound && !options.read_only => {
+                Catalog::create(&mut bpm)?
+            }
+            catalog => catalog?,
+        };
+        // Index pages are not logged, so the indexes are rebuilt from the
+        // recovered tables.
+        if !is_clean {
+            catalog.rebuild_indexes(&mut bpm)?;
+        }
+        Ok(Self::new(bpm, catalog))
+    }
+}
+
+impl Database<MemoryStorage> {
+    // Creates an empty database kept in memory, with a buffer pool of |size|
+    // frames. Its content is lost once it is dropped.
+    pub fn in_memory(size: usize) -> std::io::Result<Self> {
+        let mut bpm = DefaultBufferPoolManager::in_memory(size);
+        let catalog = Catalog::create(&mut bpm)?;
+        bpm.enable_undo_log();
+        Ok(Self::new(bpm, catalog))
+    }
+}
+
+impl<S: StorageBackend + 'static> Database<S> {
+    fn new(bpm: DefaultBufferPoolManager<TablePage, S>, catalog: Catalog) -> Self {
+        Database {
+            bpm,
+            catalog,
+            lock_mgr: LockManager::new(),
+            next_txn_id: 1,
+        }
+    }
+
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    // Parses and executes |statement|. Explaining a query produces the lines
+    // of its logical and physical plans, see |explain|, in a column named
+    // "Plan".
+    pub fn execute(&mut self, statement: &str) -> std::io::Result<Rows<'_, S>> {
+        match Statement::parse(statement, &self.catalog)? {
+            Statement::CreateTable {
+                name,
+                schema,
+                checks,
+            } => {
+                let txn_id = self.begin()?;
+                let result = self.catalog.create_table_with_checks(
+                    &mut self.bpm,
+                    txn_id,
+                    &name,
+                    schema,
+                    checks,
+                );
+                end(&mut self.bpm, txn_id, result)?;
+                self.query(PlanNode::values(Schema::new(vec![]), vec![])?)
+            }
+            Statement::CreateIndex {
+                name,
+                table,
+                column,
+            } => {
+                let txn_id = self.begin()?;
+                let result =
+                    self.catalog
+                        .create_index(&mut self.bpm, txn_id, &name, &table, &column);
+                end(&mut self.bpm, txn_id, result)?;
+                self.query(PlanNode::values(Schema::new(vec![]), vec![])?)
+            }
+            Statement::RenameTable { name, new_name } => {
+                let txn_id = self.begin()?;
+                let result = self
+                    .catalog
+                    .rename_table(&mut self.bpm, txn_id, &name, &new_name);
+                end(&mut self.bpm, txn_id, result)?;
+                self.query(PlanNode::values(Schema::new(vec![]), vec![])?)
+            }
+            Statement::RenameColumn {
+                table,
+                column,
+                new_name,
+            } => {
+                let txn_id = self.begin()?;
+                let result =
+                    self.catalog
+                        .rename_column(&mut self.bpm, txn_id, &table, &column, &new_name);
+                end(&mut self.bpm, txn_id, result)?;
+                self.query(PlanNode::values(Schema::new(vec![]), vec![])?)
+            }
+            Statement::RenameIndex { name, new_name } => {
+                let txn_id = self.begin()?;
+                let result = self
+                    .catalog
+                    .rename_index(&mut self.bpm, txn_id, &name, &new_name);
+                end(&mut self.bpm, txn_id, result)?;
+                self.query(PlanNode::values(Schema::new(vec![]), vec![])?)
+            }
+            Statement::Reindex(name) => {
+                self.catalog.reindex(&mut self.bpm, &name)?;
+                self.query(PlanNode::values(Schema::new(vec![]), vec![])?)
+            }
+            Statement::Explain(plan) => {
+                let text = explain(&self.catalog, &plan);
+                let lines: Vec<&str> = text.lines().collect();
+                let len = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+                let schema =
+                    Schema::new(vec![Column::new("Plan".to_string(), Types::owned(), len)]);
+                let rows = lines
+                    .iter()
+                    .map(|line| {
+                        let types = Types::Varchar(Varlen::Owned(Str::Val(line.to_string())));
+                        vec![Expression::Constant(Value::new(types))]
+                    })
+                    .collect();
+                self.query(PlanNode::values(schema, rows)?)
+            }
+            Statement::Query(plan) => self.query(plan),
+        }
+    }
+
+    // Executes |statement| like |execute|, and reads each of its rows as |T|;
+    // see |FromRow|.
+    pub fn query_as<T: FromRow>(&mut self, statement: &str) -> std::io::Result<Vec<T>> {
+        let rows = self.execute(statement)?;
+        let schema = rows.schema().clone();
+        rows.map(|tuple| T::from_row(&Row::new(&tuple?, &schema)))
+            .collect()
+    }
+
+    // Optimizes and executes |plan|. A plan changing a table is executed to
+    // the end, and its transaction ended, before returning.
+    pub fn query(&mut self, plan: PlanNode) -> std::io::Result<Rows<'_, S>> {
+        if self.catalog.sketch_changes() >= SKETCH_CHECKPOINT_CHANGES {
+            self.checkpoint_sketches()?;
+        }
+        let txn_id = self.txn_id();
+        self.query_in(txn_id, plan)
+    }
+
+    // Executes |plan| like |query|, in the transaction |txn_id|.
+    fn query_in(&mut self, txn_id: TransactionId, plan: PlanNode) -> std::io::Result<Rows<'_, S>> {
+        let plan = Optimizer::new(&self.catalog).optimize(plan);
+        let mut ctx = ExecutionContext::new(&mut self.bpm, &self.catalog, txn_id);
+        ctx.set_lock_manager(&self.lock_mgr);
+        ctx.begin()?;
+        let mut executor = create_executor(&plan);
+        if let Err(e) = executor.init(&mut ctx) {
+            ctx.abort().log();
+            return Err(e);
+        }
+        let mut rows = Rows {
+            ctx,
+            executor,
+            computed: VecDeque::new(),
+            ended: false,
+        };
+        if let PlanNode::Insert { .. } | PlanNode::Delete { .. } | PlanNode::Update { .. } = plan {
+            rows.compute()?;
+        }
+        Ok(rows)
+    }
+
+    // Inserts |rows| into the table |name| without logging them, which is
+    // much faster for loading many tuples at once, and returns the number of
+    // tuples inserted. The table is marked as loading in the catalog first;
+    // the pages changed are flushed and the database file synced at the end,
+    // and only then is the mark removed. A crash in between leaves the table
+    // marked, and so invalid once the database is reopened: its tuples cannot
+    // be trusted, and it can only be dropped. A load that fails leaves the
+    // table invalid too, since its inserts cannot be rolled back.
+    pub fn bulk_load(
+        &mut self,
+        name: &str,
+        rows: Vec<Vec<Value<'static>>>,
+    ) -> std::io::Result<usize> {
+        let schema = match self.catalog.table(name) {
+            Some(table) => table.schema().clone(),
+            None => return Err(not_found(&format!("No such table; name = {}", name))),
+        };
+        let rows = rows
+            .into_iter()
+            .map(|row| row.into_iter().map(Expression::Constant).collect())
+            .collect();
+        let plan = PlanNode::insert(&self.catalog, name, PlanNode::values(schema, rows)?)?;
+        let txn_id = self.begin()?;
+        let result = self.catalog.begin_load(&mut self.bpm, txn_id, name);
+        end(&mut self.bpm, txn_id, result)?;
+        let txn_id = self.txn_id();
+        self.bpm.set_unlogged(txn_id);
+        let result = self.load(txn_id, plan);
+        self.bpm.set_unlogged(INVALID_TRANSACTION_ID);
+        let count = match result.and_then(|count| self.bpm.flush_unlogged().map(|_| count)) {
+            Ok(count) => count,
+            Err(e) => {
+                self.catalog.invalidate(name);
+                return Err(e);
+            }
+        };
+        let txn_id = self.begin()?;
+        let result = self.catalog.end_load(&mut self.bpm, txn_id, name);
+        end(&mut self.bpm, txn_id, result)?;
+        Ok(count)
+    }
+
+    // Executes the insert |plan| in the transaction |txn_id|, and returns the
+    // number of tuples inserted.
+    fn load(&mut self, txn_id: TransactionId, plan: PlanNode) -> std::io::Result<usize> {
+        let mut rows = self.query_in(txn_id, plan)?;
+        let schema = rows.schema().clone();
+        match rows.next() {
+            Some(tuple) => Ok(Row::new(&tuple?, &schema).get::<i64>("Count")? as usize),
+            None => Ok(0),
+        }
+    }
+
+    // Drops the table |name| with its indexes, e.g. one left invalid by
+    // |bulk_load|; see |Catalog::drop_table|.
+    pub fn drop_table(&mut self, name: &str) -> std::io::Result<()> {
+        let txn_id = self.begin()?;
+        let result = self.catalog.drop_table(&mut self.bpm, txn_id, name);
+        end(&mut self.bpm, txn_id, result)
+    }
+
+    // Computes the statistics of the table |name|; see |Catalog::analyze|.
+    pub fn analyze(&mut self, name: &str) -> std::io::Result<&TableStatistics> {
+        let txn_id = self.begin()?;
+        let result = self.catalog.analyze(&mut self.bpm, txn_id, name);
+        end(&mut self.bpm, txn_id, result)
+    }
+
+    // Reclaims the space of the dead tuples of the table |name|; see
+    // |Catalog::vacuum|. No statement is running while the database is
+    // borrowed mutably, so none sees the tuples removed.
+    pub fn vacuum(&mut self, name: &str) -> std::io::Result<VacuumStats> {
+        self.catalog.vacuum(&mut self.bpm, name)
+    }
+
+    // Writes a logical dump of the tables, their tuples and their indexes to
+    // |writer|; see |dump|. Every table is read in a transaction of its own.
+    pub fn dump<W: Write>(&mut self, writer: &mut W) -> std::io::Result<()> {
+        let names: Vec<String> = self
+            .catalog
+            .tables()
+            .iter()
+            .map(|table| table.name().to_string())
+            .collect();
+        for name in names.iter() {
+            let table = self.catalog.table(name).unwrap();
+            writeln!(writer, "{}", create_table_statement(table))?;
+            let rows = self.execute(&format!("scan {}", name))?;
+            let schema = rows.schema().clone();
+            let mut tuples = Vec::new();
+            for tuple in rows {
+                tuples.push(tuple?);
+                if tuples.len() == DUMP_INSERT_ROWS {
+                    writeln!(writer, "{}", insert_statement(name, &tuples, &schema))?;
+                    tuples.clear();
+                }
+            }
+            if !tuples.is_empty() {
+                writeln!(writer, "{}", insert_statement(name, &tuples, &schema))?;
+            }
+            let table = self.catalog.table(name).unwrap();
+            for index in self.catalog.table_indexes(table.oid()) {
+                writeln!(writer, "{}", create_index_statement(index, table))?;
+            }
+        }
+        writer.flush()
+    }
+
+    // Replays the dump read from |reader| into this database, statement by
+    // statement, stopping at the first one that fails. Tables and indexes in
+    // the dump need to be new to the database.
+    pub fn restore<R: BufRead>(&mut self, reader: R) -> std::io::Result<()> {
+        for statement in StatementReader::new(reader) {
+            for tuple in self.execute(&statement?)? {
+                tuple?;
+            }
+        }
+        Ok(())
+    }
+
+    // Checkpoints the sketches of the tables, then flushes the database to
+    // disk, and truncates the log.
+    pub fn close(mut self) -> std::io::Result<()> {
+        if self.catalog.sketch_changes() > 0 {
+            self.checkpoint_sketches()?;
+        }
+        self.bpm.close()
+    }
+
+    fn checkpoint_sketches(&mut self) -> std::io::Result<()> {
+        let txn_id = self.begin()?;
+        let result = self.catalog.checkpoint_sketches(&mut self.bpm, txn_id);
+        end(&mut self.bpm, txn_id, result)
+    }
+
+    fn txn_id(&mut self) -> TransactionId {
+        self.next_txn_id += 1;
+        self.next_txn_id - 1
+    }
+
+    // Starts a transaction, and returns its ID.
+    fn begin(&mut self) -> std::io::Result<TransactionId> {
+        let txn_id = self.txn_id();
+        begin(&mut self.bpm, txn_id)?;
+        Ok(txn_id)
+    }
+}
+
+impl<'a, S: StorageBackend> Rows<'a, S> {
+    pub fn schema(&self) -> &Schema<'static> {
+        self.executor.output_schema()
+    }
+
+    // Computes all tuples, then commits the transaction of the statement, or
+    // aborts it on the first error.
+    fn compute(&mut self) -> std::io::Result<()> {
+        self.ended = true;
+        match drain(self.executor.as_mut(), &mut self.ctx) {
+            Ok(items) => {
+                self.computed = items.into_iter().map(|(_, tuple)| tuple).collect();
+                self.ctx.commit()
+            }
+            Err(e) => {
+                self.ctx.abort().log();
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<'a, S: StorageBackend> Iterator for Rows<'a, S> {
+    type Item = std::io::Result<Tuple>;
+
+    // Commits the transaction of the statement after the last row, and aborts
+    // it on the first error, which ends the rows.
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(tuple) = self.computed.pop_front() {
+            return Some(Ok(tuple));
+        }
+        if self.ended {
+            return None;
+        }
+        match self.executor.next(&mut self.ctx) {
+            Ok(Some((_, tuple))) => Some(Ok(tuple)),
+            Ok(None) => {
+                self.ended = true;
+                self.ctx.commit().err().map(Err)
+            }
+            Err(e) => {
+                self.ended = true;
+                self.ctx.abort().log();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<'a, S: StorageBackend> Drop for Rows<'a, S> {
+    // Commits the changes of the statement made so far, if its rows were not
+    // read to the end.
+    fn drop(&mut self) {
+        if !self.ended {
+            // Unable to handle I/O errors on destruction.
+            self.ctx.commit().log();
+        }
+    }
+}
+
+pub fn log_path(path: &str) -> String {
+    path.to_string() + LOG_FILE_SUFFIX
+}
+
+// Commits |txn_id| if |result| is Ok, and aborts it otherwise.
+fn end<V, S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    txn_id: TransactionId,
+    result: std::io::Result<V>,
+) -> std::io::Result<V> {
+    match result {
+        Ok(value) => commit(bpm, txn_id).map(|_| value),
+        Err(e) => {
+            abort(bpm, txn_id).log();
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::disk_manager::BITMAP_FILE_SUFFIX;
+    use crate::plan::expression::ComparisonOp;
+    use crate::recovery::log_record::LogRecordBody;
+    use crate::testing::file_deleter::FileDeleter;
+    use std::fs;
+
+    #[test]
+    fn execute_and_query() {
+        let mut db = Database::in_memory(8).unwrap();
+        let rows = db
+            .execute("create table users (Id INTEGER, Name VARCHAR(20))")
+            .unwrap();
+        assert!(rows.schema().columns().is_empty());
+        assert_eq!(0, rows.count());
+        assert!(db.execute("create index users_id on users (Id)").is_ok());
+
+        let mut rows = db
+            .execute("insert into users values (1, 'apple'), (2, 'banana'), (3, 'cherry')")
+            .unwrap();
+        let tuple = rows.next().unwrap().unwrap();
+        assert_eq!(
+            3,
+            Row::new(&tuple, rows.schema()).get::<i64>("Count").unwrap()
+        );
+        assert!(rows.next().is_none());
+        drop(rows);
+
+        // Changes are made even if their rows are not read.
+        db.execute("insert into users values (4, 'date')").unwrap();
+        assert_eq!(1, db.execute("scan users where Id = 4").unwrap().count());
+        let scan = PlanNode::seq_scan(db.catalog(), "users").unwrap();
+        let predicate = Expression::Comparison(
+            ComparisonOp::Eq,
+            Box::new(Expression::column(&scan.output_schema(), "Id").unwrap()),
+            Box::new(Expression::constant(Types::Integer(4))),
+        );
+        let plan = PlanNode::delete(db.catalog(), "users", scan.filter(predicate)).unwrap();
+        db.query(plan).unwrap();
+        assert_eq!(0, db.execute("scan users where Id = 4").unwrap().count());
+        assert_eq!(0, db.execute("reindex users_id").unwrap().count());
+        assert_eq!(1, db.execute("scan users where Id = 3").unwrap().count());
+
+        let rows = db.execute("scan users where Id >= 2").unwrap();
+        let schema = rows.schema().clone();
+        let names: Vec<String> = rows
+            .map(|tuple| Row::new(&tuple.unwrap(), &schema).get("Name").unwrap())
+            .collect();
+        assert_eq!(vec!["banana", "cherry"], names);
+
+        let rows = db.execute("explain scan users where Id = 1").unwrap();
+        let schema = rows.schema().clone();
+        let lines: Vec<String> = rows
+            .map(|tuple| Row::new(&tuple.unwrap(), &schema).get("Plan").unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                "Logical plan:",
+                "Filter: (Id = 1)",
+                "  SeqScan: users",
+                "Physical plan:",
+                "IndexScan: users using users_id, key = 1",
+            ],
+            lines
+        );
+
+        let plan = PlanNode::seq_scan(db.catalog(), "users").unwrap().limit(1);
+        assert_eq!(1, db.query(plan).unwrap().count());
+        assert_eq!(3, db.analyze("users").unwrap().row_count());
+
+        assert_eq!(
+            ErrorKind::AlreadyExists,
+            db.execute("create table users (Id INTEGER)")
+                .err()
+                .unwrap()
+                .kind()
+        );
+        assert!(db.execute("scan nothing").is_err());
+        assert!(db.close().is_ok());
+    }
+
+    struct User {
+        id: i32,
+        name: Option<String>,
+    }
+
+    impl FromRow for User {
+        fn from_row(row: &Row) -> std::io::Result<Self> {
+            Ok(User {
+                id: row.get("Id")?,
+                name: row.get("Name")?,
+            })
+        }
+    }
+
+    #[test]
+    fn query_as() {
+        let mut db = Database::in_memory(8).unwrap();
+        db.execute("create table users (Id INTEGER, Name VARCHAR(20))")
+            .unwrap();
+        db.execute("insert into users values (1, 'apple'), (2, 'banana'), (3, 'cherry')")
+            .unwrap();
+        let users: Vec<User> = db.query_as("scan users where Id >= 2").unwrap();
+        assert_eq!(
+            vec![(2, Some("banana")), (3, Some("cherry"))],
+            users
+                .iter()
+                .map(|user| (user.id, user.name.as_deref()))
+                .collect::<Vec<_>>()
+        );
+
+        // A row that cannot be read as |User| fails the query.
+        let error = db.query_as::<User>("explain scan users").err().unwrap();
+        assert_eq!(ErrorKind::NotFound, error.kind());
+        assert!(db.query_as::<User>("scan nothing").is_err());
+    }
+
+    #[test]
+    fn check_constraints() {
+        let mut db = Database::in_memory(8).unwrap();
+        db.execute("create table users (Id INTEGER check (Id > 0), Name VARCHAR(20))")
+            .unwrap();
+        db.execute("insert into users values (1, 'apple')").unwrap();
+        // A violating row fails the whole statement.
+        let error = db
+            .execute("insert into users values (2, 'banana'), (0, 'cherry')")
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert!(error.to_string().ends_with("constraint = users_Id_check"));
+        assert_eq!(1, db.execute("scan users").unwrap().count());
+
+        let scan = PlanNode::seq_scan(db.catalog(), "users").unwrap();
+        let assignments = vec![("Id", Expression::constant(Types::Integer(-1)))];
+        let plan = PlanNode::update(db.catalog(), "users", assignments, scan).unwrap();
+        let error = db.query(plan).err().unwrap();
+        assert!(error.to_string().ends_with("constraint = users_Id_check"));
+        assert_eq!(1, db.execute("scan users where Id = 1").unwrap().count());
+    }
+
+    #[test]
+    fn upsert() {
+        let mut db = Database::in_memory(8).unwrap();
+        db.execute("create table users (Id INTEGER, Name VARCHAR(20) check (Name <> 'none'))")
+            .unwrap();
+        let upsert = "insert into users values (1, 'apple') \
+                      on conflict (Id) do update set Name = excluded.Name";
+        assert!(db.execute(upsert).is_err());
+        db.execute("create index users_id on users (Id)").unwrap();
+        db.execute(upsert).unwrap();
+
+        // The second and the last rows update the ones before them.
+        let rows = db
+            .execute(
+                "insert into users values (1, 'banana'), (2, 'cherry'), (2, 'date') \
+                 on conflict (Id) do update set Name = excluded.Name",
+            )
+            .unwrap();
+        let schema = rows.schema().clone();
+        let counts: Vec<i64> = rows
+            .map(|tuple| Row::new(&tuple.unwrap(), &schema).get("Count").unwrap())
+            .collect();
+        assert_eq!(vec![3], counts);
+        let users: Vec<User> = db.query_as("scan users").unwrap();
+        let mut names: Vec<(i32, Option<&str>)> = users
+            .iter()
+            .map(|user| (user.id, user.name.as_deref()))
+            .collect();
+        names.sort();
+        assert_eq!(vec![(1, Some("banana")), (2, Some("date"))], names);
+
+        // The update keeps the existing values it does not assign.
+        db.execute("insert into users values (2, 'egg') on conflict (Id) do update set Id = 3")
+            .unwrap();
+        let users: Vec<User> = db.query_as("scan users where Id = 3").unwrap();
+        assert_eq!(Some("date"), users[0].name.as_deref());
+        // The updated tuple is checked, and a failed upsert changes nothing.
+        let error = db
+            .execute(
+                "insert into users values (4, 'fig'), (1, 'x') \
+                 on conflict (Id) do update set Name = 'none'",
+            )
+            .err()
+            .unwrap();
+        assert!(error.to_string().ends_with("constraint = users_Name_check"));
+        assert_eq!(2, db.execute("scan users").unwrap().count());
+
+        let rows = db.execute(&format!("explain {}", upsert)).unwrap();
+        let schema = rows.schema().clone();
+        let lines: Vec<String> = rows
+            .map(|tuple| Row::new(&tuple.unwrap(), &schema).get("Plan").unwrap())
+            .collect();
+        assert_eq!(
+            "Insert: users on conflict users_id set Name = excluded.Name",
+            lines[1]
+        );
+    }
+
+    // Returns the rows of the table |name|, formatted.
+    fn scan_strings<S: StorageBackend + 'static>(db: &mut Database<S>, name: &str) -> Vec<String> {
+        let rows = db.execute(&format!("scan {}", name)).unwrap();
+        let schema = rows.schema().clone();
+        rows.map(|tuple| tuple.unwrap().to_string(&schema))
+            .collect()
+    }
+
+    #[test]
+    fn dump_and_restore() {
+        let mut db = Database::in_memory(8).unwrap();
+        db.execute(
+            "create table things (Id BIGINT, Name VARCHAR(20), Score DECIMAL, Ok BOOLEAN, \
+             At TIMESTAMP, Place POINT, Data VARBINARY(4))",
+        )
+        .unwrap();
+        db.execute(
+            "create table empty (Id INTEGER check (Id > 0), Name VARCHAR(5), constraint named \
+             check (Id < 9), constraint named check (Name is not null))",
+        )
+        .err()
+        .unwrap();
+        db.execute(
+            "create table empty (Id INTEGER check (Id > 0), Name VARCHAR(5), constraint named \
+             check (not Name is null and (3 <> Id or Name = 'it''s')))",
+        )
+        .unwrap();
+        for id in 0..250 {
+            let statement = format!(
+                "insert into things values ({}, 'it''s;\n{}', {}, {}, \
+                 '2024-01-02 03:04:05.000006+00', '(1.5, -{})', x'00ff{:02x}')",
+                id,
+                id,
+                match id % 3 {
+                    0 => "null".to_string(),
+                    _ => format!("{}.25", id),
+                },
+                id % 2 == 0,
+                id,
+                id % 256
+            );
+            db.execute(&statement).unwrap();
+        }
+        db.execute("create index things_id on things (Id)").unwrap();
+        let mut dump = Vec::new();
+        db.dump(&mut dump).unwrap();
+        // Two tables, three inserts and one index.
+        assert_eq!(6, StatementReader::new(dump.as_slice()).count());
+
+        let mut restored = Database::in_memory(8).unwrap();
+        restored.restore(dump.as_slice()).unwrap();
+        assert_eq!(
+            scan_strings(&mut db, "things"),
+            scan_strings(&mut restored, "things")
+        );
+        assert!(scan_strings(&mut restored, "empty").is_empty());
+        // The check constraints are restored.
+        let error = restored
+            .execute("insert into empty values (0, 'a')")
+            .err()
+            .unwrap();
+        assert!(error.to_string().ends_with("constraint = empty_Id_check"));
+        let error = restored
+            .execute("insert into empty values (3, 'a')")
+            .err()
+            .unwrap();
+        assert!(error.to_string().ends_with("constraint = named"));
+        assert!(restored.catalog().index("things_id").is_some());
+        let mut again = Vec::new();
+        restored.dump(&mut again).unwrap();
+        assert_eq!(dump, again);
+
+        // Restoring twice fails on the existing tables.
+        assert_eq!(
+            ErrorKind::AlreadyExists,
+            restored.restore(dump.as_slice()).unwrap_err().kind()
+        );
+        assert!(restored
+            .restore("scan empty; insert into".as_bytes())
+            .is_err());
+    }
+
+    #[test]
+    fn roll_back_in_memory() {
+        let mut db = Database::in_memory(8).unwrap();
+        db.execute("create table users (Id INTEGER, Name VARCHAR(20))")
+            .unwrap();
+        db.execute("create index users_id on users (Id)").unwrap();
+        db.execute("insert into users values (1, 'apple'), (2, 'banana'), (3, 'cherry')")
+            .unwrap();
+
+        // A failing statement is rolled back, in the table and in its index.
+        let error = db
+            .execute("insert into users values (4, 'kiwi'), (2, 'lime')")
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert_eq!(3, db.execute("scan users").unwrap().count());
+        assert_eq!(0, db.execute("scan users where Id = 4").unwrap().count());
+
+        db.execute("insert into users values (4, 'kiwi')").unwrap();
+        assert_eq!(4, db.execute("scan users").unwrap().count());
+        let error = db
+            .execute("insert into users values (4, 'lime')")
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert_eq!(4, db.execute("scan users").unwrap().count());
+        assert!(db.bpm.take_undo_log(db.next_txn_id - 1).is_empty());
+    }
+
+    #[test]
+    fn open_and_reopen() {
+        let file_path = "/tmp/testfile.database.1.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let log_path = log_path(file_path);
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(&log_path);
+
+        let options = DbOptions::new().create_if_missing(false).clone();
+        let error = Database::open(file_path, &options).err().unwrap();
+        assert_eq!(ErrorKind::NotFound, error.kind());
+
+        let mut db = Database::open(file_path, &DbOptions::default()).unwrap();
+        db.execute("create table users (Id INTEGER, Name VARCHAR(20))")
+            .unwrap();
+        db.execute("create index users_id on users (Id)").unwrap();
+        db.execute("insert into users values (1, 'apple'), (2, 'banana'), (3, 'cherry')")
+            .unwrap()
+            .count();
+        assert!(db.close().is_ok());
+
+        // The tables and indexes are persisted.
+        let mut db = Database::open(file_path, &options).unwrap();
+        let table_oid = db.catalog().table("users").unwrap().oid();
+        assert_eq!(1, db.catalog().table_indexes(table_oid).len());
+        assert_eq!(1, db.execute("scan users where Id = 3").unwrap().count());
+        assert!(db.close().is_ok());
+
+        let mut db = Database::open(file_path, DbOptions::new().read_only(true)).unwrap();
+        assert_eq!(3, db.execute("scan users").unwrap().count());
+        let error = db.execute("create table items (Id INTEGER)").err().unwrap();
+        assert_eq!(ErrorKind::ReadOnlyFilesystem, error.kind());
+        assert!(db.close().is_ok());
+    }
+
+    #[test]
+    fn pin_quota() {
+        let file_path = "/tmp/testfile.database.7.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let log_path = log_path(file_path);
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(&log_path);
+
+        // The executors pin one page at a time, so statements run under the
+        // tightest quota.
+        let options = DbOptions::new().pin_quota(1).clone();
+        let mut db = Database::open(file_path, &options).unwrap();
+        db.execute("create table users (Id INTEGER, Name VARCHAR(20))")
+            .unwrap();
+        db.execute("create index users_id on users (Id)").unwrap();
+        let rows: Vec<String> = (0..500)
+            .map(|id| format!("({}, 'user{}')", id, id))
+            .collect();
+        let statement = format!("insert into users values {}", rows.join(", "));
+        db.execute(&statement).unwrap().count();
+        assert_eq!(500, db.execute("scan users").unwrap().count());
+        assert_eq!(1, db.execute("scan users where Id = 321").unwrap().count());
+        db.execute("reindex users_id").unwrap();
+        assert!(db.close().is_ok());
+    }
+
+    #[test]
+    fn vacuum() {
+        let file_path = "/tmp/testfile.database.5.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let log_path = log_path(file_path);
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(&log_path);
+
+        let mut db = Database::open(file_path, &DbOptions::default()).unwrap();
+        db.execute("create table users (Id INTEGER, Name VARCHAR(200))")
+            .unwrap();
+        let name = "x".repeat(190);
+        for id in 0..100 {
+            let statement = format!("insert into users values ({}, '{}')", id, name);
+            db.execute(&statement).unwrap();
+        }
+        let scan = PlanNode::seq_scan(db.catalog(), "users").unwrap();
+        let predicate = Expression::Comparison(
+            ComparisonOp::Ge,
+            Box::new(Expression::column(&scan.output_schema(), "Id").unwrap()),
+            Box::new(Expression::constant(Types::Integer(20))),
+        );
+        let plan = PlanNode::delete(db.catalog(), "users", scan.filter(predicate)).unwrap();
+        db.query(plan).unwrap();
+
+        // The pages emptied are freed, and the frees are logged, so that they
+        // survive a crash.
+        let stats = db.vacuum("users").unwrap();
+        assert!(stats.pages_freed > 0);
+        assert_eq!(VacuumStats::default(), db.vacuum("users").unwrap());
+        assert_eq!(
+            ErrorKind::NotFound,
+            db.vacuum("nothing").err().unwrap().kind()
+        );
+        drop(db);
+
+        let mut db = Database::open(file_path, &DbOptions::default()).unwrap();
+        assert_eq!(20, db.execute("scan users").unwrap().count());
+        db.execute(&format!("insert into users values (100, '{}')", name))
+            .unwrap();
+        assert_eq!(21, db.execute("scan users").unwrap().count());
+        assert!(db.close().is_ok());
+    }
+
+    #[test]
+    fn sketches() {
+        let file_path = "/tmp/testfile.database.6.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let log_path = log_path(file_path);
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(&log_path);
+
+        let mut db = Database::open(file_path, &DbOptions::default()).unwrap();
+        db.execute("create table users (Id INTEGER, Name VARCHAR(20))")
+            .unwrap();
+        db.execute("create index users_id on users (Id)").unwrap();
+        db.execute("insert into users values (1, 'apple'), (null, 'banana'), (3, 'cherry')")
+            .unwrap();
+        // A failing statement leaves the sketches as they were.
+        assert!(db
+            .execute("insert into users values (4, 'kiwi'), (3, 'lime')")
+            .is_err());
+        let scan = PlanNode::seq_scan(db.catalog(), "users").unwrap();
+        let predicate = Expression::Comparison(
+            ComparisonOp::Eq,
+            Box::new(Expression::column(&scan.output_schema(), "Id").unwrap()),
+            Box::new(Expression::constant(Types::Integer(1))),
+        );
+        let plan = PlanNode::delete(db.catalog(), "users", scan.filter(predicate)).unwrap();
+        db.query(plan).unwrap();
+
+        let check = |db: &Database| {
+            let sketches = db.catalog().table("users").unwrap().sketches();
+            assert_eq!(2, sketches.row_count());
+            assert_eq!(1, sketches.column(0).unwrap().null_count());
+            let three = Value::new(Types::Integer(3));
+            let frequencies = sketches.column(0).unwrap().frequencies();
+            assert!(frequencies.estimate_value(&three) >= 1);
+        };
+        check(&db);
+        assert_eq!(4, db.catalog().sketch_changes());
+        assert!(db.close().is_ok());
+
+        // The sketches are persisted with the catalog.
+        let db = Database::open(file_path, DbOptions::new().read_only(true)).unwrap();
+        check(&db);
+        assert_eq!(0, db.catalog().sketch_changes());
+        assert!(db.close().is_ok());
+    }
+
+    #[test]
+    fn bulk_load() {
+        let file_path = "/tmp/testfile.database.8.db";
+        let crash_file_path = "/tmp/testfile.database.9.db";
+        let suffixes = [
+            "".to_string(),
+            BITMAP_FILE_SUFFIX.to_string(),
+            LOG_FILE_SUFFIX.to_string(),
+        ];
+        let paths: Vec<String> = suffixes
+            .iter()
+            .flat_map(|suffix| {
+                vec![
+                    file_path.to_string() + suffix,
+                    crash_file_path.to_string() + suffix,
+                ]
+            })
+            .collect();
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        for path in paths.iter() {
+            file_deleter.push(path);
+        }
+        // Simulates a crash by copying the files while the database is alive.
+        let crash = || {
+            for suffix in suffixes.iter() {
+                fs::copy(
+                    file_path.to_string() + suffix,
+                    crash_file_path.to_string() + suffix,
+                )
+                .unwrap();
+            }
+        };
+        let rows = |ids: std::ops::Range<i32>| -> Vec<Vec<Value<'static>>> {
+            ids.map(|id| {
+                let name = Types::Varchar(Varlen::Owned(Str::Val(format!("user{}", id))));
+                vec![Value::new(Types::Integer(id)), Value::new(name)]
+            })
+            .collect()
+        };
+
+        let mut db = Database::open(file_path, &DbOptions::default()).unwrap();
+        db.execute("create table users (Id INTEGER, Name VARCHAR(20))")
+            .unwrap();
+        db.execute("create index users_id on users (Id)").unwrap();
+        let inserts = |db: &mut Database| {
+            let records = db.bpm.log_manager().unwrap().records().unwrap();
+            records
+                .iter()
+                .filter(|r| matches!(r.body(), LogRecordBody::Insert { .. }))
+                .count()
+        };
+        let logged = inserts(&mut db);
+        assert_eq!(300, db.bulk_load("users", rows(0..300)).unwrap());
+        // Only the two rewrites of the catalog entry are logged.
+        assert_eq!(logged + 2, inserts(&mut db));
+        assert_eq!(300, db.execute("scan users").unwrap().count());
+        assert_eq!(1, db.execute("scan users where Id = 150").unwrap().count());
+        assert!(db.catalog().table("users").unwrap().is_valid());
+
+        // The tuples loaded are durable without the log.
+        crash();
+        let mut recovered = Database::open(crash_file_path, &DbOptions::default()).unwrap();
+        assert_eq!(300, recovered.execute("scan users").unwrap().count());
+        let rows_of = |db: &mut Database, id: i32| {
+            db.execute(&format!("scan users where Id = {}", id))
+                .unwrap()
+                .count()
+        };
+        assert_eq!(1, rows_of(&mut recovered, 299));
+        assert!(recovered.close().is_ok());
+
+        // A load not matching the table leaves it as it was.
+        let error = db.bulk_load("users", vec![vec![]]).err().unwrap();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert!(db.catalog().table("users").unwrap().is_valid());
+
+        // A failing load leaves the table invalid, also after a crash.
+        let error = db.bulk_load("users", rows(290..310)).err().unwrap();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        let error = db.bulk_load("users", rows(0..1)).err().unwrap();
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+        crash();
+        drop(db);
+        let mut db = Database::open(crash_file_path, &DbOptions::default()).unwrap();
+        assert!(!db.catalog().table("users").unwrap().is_valid());
+        let error = db.execute("scan users").err().unwrap();
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+        assert!(db.analyze("users").is_err());
+        db.drop_table("users").unwrap();
+        db.execute("create table users (Id INTEGER, Name VARCHAR(20))")
+            .unwrap();
+        assert_eq!(10, db.bulk_load("users", rows(0..10)).unwrap());
+        assert!(db.close().is_ok());
+    }
+
+    #[test]
+    fn transactions_and_recovery() {
+        let file_path = "/tmp/testfile.database.3.db";
+        let crash_file_path = "/tmp/testfile.database.4.db";
+        let suffixes = [
+            "".to_string(),
+            BITMAP_FILE_SUFFIX.to_string(),
+            LOG_FILE_SUFFIX.to_string(),
+        ];
+
+        let paths: Vec<String> = suffixes
+            .iter()
+            .flat_map(|suffix| {
+                vec![
+                    file_path.to_string() + suffix,
+                    crash_file_path.to_string() + suffix,
+                ]
+            })
+            .collect();
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        for path in paths.iter() {
+            file_deleter.push(path);
+        }
+
+        let mut db = Database::open(file_path, &DbOptions::default()).unwrap();
+        db.execute("create table users (Id INTEGER, Name VARCHAR(20))")
+            .unwrap();
+        db.execute("create index users_id on users (Id)").unwrap();
+        let inserted = db
+            .execute("insert into users values (1, 'apple'), (2, 'banana'), (3, 'cherry')")
+            .unwrap()
+            .count();
+        assert_eq!(1, inserted);
+
+        // A failing statement is rolled back, in the table and in its index.
+        let error = db
+            .execute("insert into users values (4, 'kiwi'), (2, 'lime')")
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert_eq!(3, db.execute("scan users").unwrap().count());
+        assert_eq!(0, db.execute("scan users where Id = 4").unwrap().count());
+        let records = db.bpm.log_manager().unwrap().records().unwrap();
+        let bodies: Vec<&LogRecordBody> = records.iter().map(|r| r.body()).collect();
+        let count = |expected: LogRecordBody| bodies.iter().filter(|&&b| b == &expected).count();
+        assert_eq!(
+            count(LogRecordBody::Begin),
+            count(LogRecordBody::Commit) + 1
+        );
+        assert_eq!(1, count(LogRecordBody::Abort));
+        assert!(db
+            .execute("insert into users values (4, 'kiwi')")
+            .unwrap()
+            .next()
+            .unwrap()
+            .is_ok());
+
+        // Simulates a crash by copying the files while |db| is alive. The
+        // database file misses the changes still in the pool.
+        for suffix in suffixes.iter() {
+            let path = file_path.to_string() + suffix;
+            if Path::new(&path).exists() {
+                fs::copy(&path, crash_file_path.to_string() + suffix).unwrap();
+            }
+        }
+        drop(db);
+
+        let options = DbOptions::new().read_only(true).clone();
+        let error = Database::open(crash_file_path, &options).err().unwrap();
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+
+        // The committed statements are recovered, and the index is rebuilt.
+        let mut db = Database::open(crash_file_path, &DbOptions::default()).unwrap();
+        assert_eq!(4, db.execute("scan users").unwrap().count());
+        for id in 1..=4 {
+            let rows = db
+                .execute(&format!("scan users where Id = {}", id))
+                .unwrap();
+            assert_eq!(1, rows.count());
+        }
+        assert!(db.close().is_ok());
+
+        // Closing truncates the log.
+        let mut reader = LogReader::open(&log_path(crash_file_path)).unwrap();
+        assert!(reader.is_clean().unwrap());
+        let mut db = Database::open(crash_file_path, &options).unwrap();
+        assert_eq!(4, db.execute("scan users").unwrap().count());
+        assert!(db.close().is_ok());
+    }
+}
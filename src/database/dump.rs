@@ -0,0 +1,214 @@
+// Functionality: The logical dump of a database, i.e. the statements that
+// recreate its tables, their tuples and their indexes, written by
+// |Database::dump| and replayed by |Database::restore|. Being statements of
+// the command language rather than pages, a dump is independent of the page
+// format, and can be restored into a database of another version of it.
+//
+// A dump holds, for every table in order of their names:
+//
+//   create table users (Id BIGINT, Name VARCHAR(20),
+//                       constraint users_Id_check check (Id > 0));
+//   insert into users values (1, 'apple'), (2, null);
+//   create index users_id on users (Id);
+//
+// with up to |DUMP_INSERT_ROWS| tuples per insert. Indexes are created after
+// the tuples are inserted, so that they are bulk loaded. Every statement ends
+// with a semicolon and a line break; strings may span lines. Check
+// constraints are written as table constraints, keeping their names; those
+// with arithmetic, which the command language lacks, cannot be restored.
+
+use crate::catalog::catalog::IndexInfo;
+use crate::catalog::catalog::TableInfo;
+use crate::catalog::schema::Schema;
+use crate::common::error::*;
+use crate::plan::expression::ArithmeticOp;
+use crate::plan::expression::ComparisonOp;
+use crate::plan::expression::Expression;
+use crate::table::tuple::Tuple;
+use crate::types::types::bytes_to_hex;
+use crate::types::types::Operation;
+use crate::types::types::Types;
+use crate::types::value::Value;
+use std::io::BufRead;
+
+// The number of tuples of every insert statement of a dump.
+pub const DUMP_INSERT_ROWS: usize = 100;
+
+pub fn create_table_statement(table: &TableInfo) -> String {
+    let mut items: Vec<String> = table
+        .schema()
+        .columns()
+        .iter()
+        .map(|column| match column.is_inlined() {
+            true => format!("{} {}", column.name(), column.types().name()),
+            false => format!(
+                "{} {}({})",
+                column.name(),
+                column.types().name(),
+                column.variable_len()
+            ),
+        })
+        .collect();
+    items.extend(table.checks().iter().map(|(name, expression)| {
+        format!(
+            "constraint {} check ({})",
+            name,
+            check_text(expression, table.schema())
+        )
+    }));
+    format!("create table {} ({});", table.name(), items.join(", "))
+}
+
+// Returns the text |Statement::parse| reads back as the check |expression|
+// over |schema|, if it has no arithmetic.
+fn check_text(expression: &Expression, schema: &Schema) -> String {
+    let text = |expression: &Expression| check_text(expression, schema);
+    match expression {
+        Expression::Constant(value) => literal(value),
+        Expression::Column(idx) => match schema.nth_column(*idx) {
+            Some(column) => column.name().to_string(),
+            None => format!("#{}", idx),
+        },
+        Expression::Comparison(op, lhs, rhs) => {
+            let op = match op {
+                ComparisonOp::Eq => "=",
+                ComparisonOp::Ne => "<>",
+                ComparisonOp::Lt => "<",
+                ComparisonOp::Le => "<=",
+                ComparisonOp::Gt => ">",
+                ComparisonOp::Ge => ">=",
+            };
+            format!("{} {} {}", text(lhs), op, text(rhs))
+        }
+        Expression::Arithmetic(op, lhs, rhs) => {
+            let op = match op {
+                ArithmeticOp::Add => "+",
+                ArithmeticOp::Subtract => "-",
+                ArithmeticOp::Multiply => "*",
+                ArithmeticOp::Divide => "/",
+                ArithmeticOp::Modulo => "%",
+            };
+            format!("({} {} {})", text(lhs), op, text(rhs))
+        }
+        Expression::And(lhs, rhs) => format!("({} and {})", text(lhs), text(rhs)),
+        Expression::Or(lhs, rhs) => format!("({} or {})", text(lhs), text(rhs)),
+        Expression::Not(child) => format!("not {}", text(child)),
+        Expression::IsNull(child) => format!("{} is null", text(child)),
+    }
+}
+
+pub fn create_index_statement(index: &IndexInfo, table: &TableInfo) -> String {
+    let column = table.schema().nth_column(index.key_column()).unwrap();
+    format!(
+        "create index {} on {} ({});",
+        index.name(),
+        table.name(),
+        column.name()
+    )
+}
+
+// Returns the statement inserting |tuples| of |schema| into the table |name|.
+pub fn insert_statement(name: &str, tuples: &[Tuple], schema: &Schema) -> String {
+    let rows: Vec<String> = tuples
+        .iter()
+        .map(|tuple| {
+            let values: Vec<String> = (0..schema.columns().len())
+                .map(|idx| literal(&tuple.nth_value(schema, idx)))
+                .collect();
+            format!("({})", values.join(", "))
+        })
+        .collect();
+    format!("insert into {} values {};", name, rows.join(", "))
+}
+
+// Returns the literal |Statement::parse| reads back as |value|.
+fn literal(value: &Value) -> String {
+    if value.is_null() {
+        return "null".to_string();
+    }
+    match value.borrow() {
+        Types::Boolean(_)
+        | Types::TinyInt(_)
+        | Types::SmallInt(_)
+        | Types::Integer(_)
+        | Types::BigInt(_)
+        | Types::Decimal(_) => value.to_string(),
+        Types::Varbinary(bytes) => format!("x'{}'", &bytes_to_hex(bytes)[2..]),
+        // Strings, timestamps and points are quoted.
+        _ => format!("'{}'", value.to_string().replace('\'', "''")),
+    }
+}
+
+// Reads the statements of a dump one by one. A statement ends with a
+// semicolon outside of quotes, and may share a line with others.
+pub struct StatementReader<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> StatementReader<R> {
+    pub fn new(reader: R) -> Self {
+        StatementReader { reader }
+    }
+}
+
+impl<R: BufRead> Iterator for StatementReader<R> {
+    type Item = std::io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut statement = Vec::new();
+        loop {
+            match self.reader.read_until(b';', &mut statement) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(e) => return Some(Err(e)),
+            }
+            // Quotes inside strings are doubled, so an even number of them
+            // means the semicolon is outside of quotes.
+            let quotes = statement.iter().filter(|byte| **byte == b'\'').count();
+            if statement.ends_with(b";") && quotes % 2 == 0 {
+                return Some(match String::from_utf8(statement) {
+                    Ok(statement) => Ok(statement.trim().to_string()),
+                    Err(_) => Err(invalid_data("Dump is not valid UTF-8")),
+                });
+            }
+        }
+        match statement.iter().all(u8::is_ascii_whitespace) {
+            true => None,
+            false => Some(Err(invalid_data("Dump ends within a statement"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::types::Str;
+    use crate::types::types::Varlen;
+
+    #[test]
+    fn literals_and_statements() {
+        assert_eq!("null", literal(&Value::null(Types::bigint())));
+        assert_eq!("true", literal(&Value::new(Types::Boolean(1))));
+        assert_eq!("-1.5", literal(&Value::new(Types::Decimal(-1.5))));
+        let string = Types::Varchar(Varlen::Owned(Str::Val("it's".to_string())));
+        assert_eq!("'it''s'", literal(&Value::new(string)));
+        assert_eq!(
+            "x'00ff'",
+            literal(&Value::new(Types::Varbinary(vec![0, 255])))
+        );
+
+        let dump = "create table t (A VARCHAR(5)); insert into t values ('a;\n''b');\n\n";
+        let statements: Vec<String> = StatementReader::new(dump.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            vec![
+                "create table t (A VARCHAR(5));",
+                "insert into t values ('a;\n''b');"
+            ],
+            statements
+        );
+        let mut truncated = StatementReader::new("insert into t".as_bytes());
+        assert!(truncated.next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod database;
+pub mod dump;
+pub mod statement;
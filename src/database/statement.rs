@@ -0,0 +1,817 @@
+// Functionality: Parses the statements |Database::execute| accepts, in a small
+// command language rather than SQL:
+//
+//   create table users (Id BIGINT, Name VARCHAR(20))
+//   create table items (Id BIGINT check (Id > 0), Price DECIMAL,
+//                       constraint priced check (Price >= 0 or Price is null))
+//   create index users_id on users (Id)
+//   alter table users rename to customers
+//   alter table users rename column Name to FullName
+//   alter index users_id rename to customers_id
+//   reindex users_id
+//   insert into users values (1, 'apple'), (2, null)
+//   insert into users values (1, 'pear') on conflict (Id)
+//       do update set Name = excluded.Name
+//   scan users [where Id = 1] [limit 10]
+//   explain scan users where Id = 1
+//
+// Keywords and type names are case-insensitive; strings are quoted with
+// single quotes, doubled inside, bytes are written in hex as x'00ff', and a
+// trailing semicolon is optional. The assignments of an upsert take a
+// literal, a column of the existing tuple, or one of the proposed tuple,
+// prefixed with "excluded.".
+// Inserts and scans are planned against the catalog as they are parsed, with
+// literals converted to the types of the columns they are compared with or
+// inserted into.
+
+use crate::catalog::catalog::Catalog;
+use crate::catalog::column::Column;
+use crate::catalog::schema::Schema;
+use crate::common::error::*;
+use crate::execution::execution_context::conform_value;
+use crate::plan::expression::ComparisonOp;
+use crate::plan::expression::Expression;
+use crate::plan::plan_node::conflict_schema;
+use crate::plan::plan_node::PlanNode;
+use crate::types::types::bytes_to_hex;
+use crate::types::types::Str;
+use crate::types::types::Types;
+use crate::types::types::Varlen;
+use crate::types::value::Value;
+
+// The length of varchar and varbinary columns declared without one.
+const DEFAULT_VARLEN: usize = 255;
+
+#[derive(Debug)]
+pub enum Statement {
+    CreateTable {
+        name: String,
+        schema: Schema<'static>,
+        // The check constraints by name.
+        checks: Vec<(String, Expression)>,
+    },
+    CreateIndex {
+        name: String,
+        table: String,
+        column: String,
+    },
+    RenameTable {
+        name: String,
+        new_name: String,
+    },
+    RenameColumn {
+        table: String,
+        column: String,
+        new_name: String,
+    },
+    RenameIndex {
+        name: String,
+        new_name: String,
+    },
+    // The name of the index to rebuild.
+    Reindex(String),
+    // The plan of the query to explain.
+    Explain(PlanNode),
+    Query(PlanNode),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Number(String),
+    Str(String),
+    Bytes(Vec<u8>),
+    Symbol(&'static str),
+}
+
+enum Operand {
+    // The index of the column in the schema.
+    Column(usize),
+    Literal(Literal),
+}
+
+enum Literal {
+    Null,
+    Bool(bool),
+    Bytes(Vec<u8>),
+    // Numbers and strings are converted from their text.
+    Text(String),
+}
+
+impl Statement {
+    // Parses |text|, planning inserts and scans of the tables in |catalog|.
+    pub fn parse(text: &str, catalog: &Catalog) -> std::io::Result<Self> {
+        let mut tokens = Tokens::new(tokenize(text)?);
+        if tokens.is_done() {
+            return Err(invalid_input("Empty statement"));
+        }
+        let statement = match tokens.keyword()?.as_str() {
+            "create" => match tokens.keyword()?.as_str() {
+                "table" => parse_create_table(&mut tokens)?,
+                "index" => parse_create_index(&mut tokens)?,
+                other => return Err(unexpected(other)),
+            },
+            "alter" => parse_alter(&mut tokens)?,
+            "reindex" => Statement::Reindex(tokens.word()?),
+            "explain" => Statement::Explain(parse_query(&mut tokens, catalog)?),
+            _ => {
+                tokens.back();
+                Statement::Query(parse_query(&mut tokens, catalog)?)
+            }
+        };
+        tokens.finish()?;
+        Ok(statement)
+    }
+}
+
+// create table <table> (<column> <type>[(<length>)] [check (<check>)], ...,
+//                       [constraint <name>] check (<check>), ...)
+// Only varchar and varbinary columns take a length; the other types have a
+// fixed one. A check refers to the columns declared before it, and is named
+// after the table, and its column if it follows one, unless it is declared by
+// a constraint; see |parse_check|.
+fn parse_create_table(tokens: &mut Tokens) -> std::io::Result<Statement> {
+    let name = tokens.word()?;
+    tokens.symbol("(")?;
+    let mut columns = Vec::new();
+    let mut checks = Vec::new();
+    loop {
+        if tokens.keyword_if("constraint") {
+            let check_name = tokens.word()?;
+            tokens.expect_keyword("check")?;
+            let expression = parse_check(tokens, &Schema::new(columns.clone()))?;
+            checks.push((check_name, expression));
+        } else if tokens.keyword_if("check") {
+            let check_name = unique_name(&format!("{}_check", name), &checks);
+            let expression = parse_check(tokens, &Schema::new(columns.clone()))?;
+            checks.push((check_name, expression));
+        } else {
+            let column = parse_column(tokens)?;
+            let column_name = column.name().to_string();
+            columns.push(column);
+            if tokens.keyword_if("check") {
+                let check_name = unique_name(&format!("{}_{}_check", name, column_name), &checks);
+                let expression = parse_check(tokens, &Schema::new(columns.clone()))?;
+                checks.push((check_name, expression));
+            }
+        }
+        if !tokens.symbol_if(",") {
+            break;
+        }
+    }
+    tokens.symbol(")")?;
+    Ok(Statement::CreateTable {
+        name,
+        schema: Schema::new(columns),
+        checks,
+    })
+}
+
+// <column> <type>[(<length>)]
+fn parse_column(tokens: &mut Tokens) -> std::io::Result<Column<'static>> {
+    let column_name = tokens.word()?;
+    let type_name = tokens.word()?;
+    let types = parse_type(&type_name)?;
+    let mut length = match types.is_inlined() {
+        true => types.size(),
+        false => DEFAULT_VARLEN,
+    };
+    if tokens.symbol_if("(") {
+        if types.is_inlined() {
+            return Err(invalid_input(&format!(
+                "Type has a fixed length; column = {}",
+                column_name
+            )));
+        }
+        length = tokens
+            .number()?
+            .parse()
+            .map_err(|_| invalid_input("Invalid column length"))?;
+        tokens.symbol(")")?;
+    }
+    Ok(Column::new(column_name, types, length))
+}
+
+// Returns |base|, or |base| followed by the first number making it unique,
+// if a check is named |base| already.
+fn unique_name(base: &str, checks: &[(String, Expression)]) -> String {
+    let taken = |name: &str| checks.iter().any(|(other, _)| other == name);
+    match taken(base) {
+        false => base.to_string(),
+        true => (1..)
+            .map(|n| format!("{}{}", base, n))
+            .find(|name| !taken(name))
+            .unwrap(),
+    }
+}
+
+// (<check>), where
+//   <check> := <conjunction> [or <conjunction> ...]
+//   <conjunction> := <negation> [and <negation> ...]
+//   <negation> := not <negation> | (<check>) | <column> is [not] null
+//               | <operand> <op> <operand>
+// and an operand is a column of |schema| or a literal, converted to the type
+// of the column it is compared with.
+fn parse_check(tokens: &mut Tokens, schema: &Schema) -> std::io::Result<Expression> {
+    tokens.symbol("(")?;
+    let expression = parse_disjunction(tokens, schema)?;
+    tokens.symbol(")")?;
+    Ok(expression)
+}
+
+fn parse_disjunction(tokens: &mut Tokens, schema: &Schema) -> std::io::Result<Expression> {
+    let mut expression = parse_conjunction(tokens, schema)?;
+    while tokens.keyword_if("or") {
+        let rhs = parse_conjunction(tokens, schema)?;
+        expression = Expression::Or(Box::new(expression), Box::new(rhs));
+    }
+    Ok(expression)
+}
+
+fn parse_conjunction(tokens: &mut Tokens, schema: &Schema) -> std::io::Result<Expression> {
+    let mut expression = parse_negation(tokens, schema)?;
+    while tokens.keyword_if("and") {
+        let rhs = parse_negation(tokens, schema)?;
+        expression = Expression::And(Box::new(expression), Box::new(rhs));
+    }
+    Ok(expression)
+}
+
+fn parse_negation(tokens: &mut Tokens, schema: &Schema) -> std::io::Result<Expression> {
+    if tokens.keyword_if("not") {
+        return Ok(Expression::Not(Box::new(parse_negation(tokens, schema)?)));
+    }
+    if tokens.symbol_if("(") {
+        let expression = parse_disjunction(tokens, schema)?;
+        tokens.symbol(")")?;
+        return Ok(expression);
+    }
+    let lhs = tokens.operand(schema)?;
+    if let Operand::Column(idx) = lhs {
+        if tokens.keyword_if("is") {
+            let negated = tokens.keyword_if("not");
+            tokens.expect_keyword("null")?;
+            let is_null = Expression::IsNull(Box::new(Expression::Column(idx)));
+            return Ok(match negated {
+                true => Expression::Not(Box::new(is_null)),
+                false => is_null,
+            });
+        }
+    }
+    let op = tokens.comparison()?;
+    let rhs = tokens.operand(schema)?;
+    let constant = |literal, idx: usize| {
+        literal_value(literal, schema.nth_column(idx).unwrap()).map(Expression::Constant)
+    };
+    let (lhs, rhs) = match (lhs, rhs) {
+        (Operand::Column(lhs), Operand::Column(rhs)) => {
+            (Expression::Column(lhs), Expression::Column(rhs))
+        }
+        (Operand::Column(idx), Operand::Literal(literal)) => {
+            (Expression::Column(idx), constant(literal, idx)?)
+        }
+        (Operand::Literal(literal), Operand::Column(idx)) => {
+            (constant(literal, idx)?, Expression::Column(idx))
+        }
+        (Operand::Literal(_), Operand::Literal(_)) => {
+            return Err(invalid_input("Comparison needs a column"))
+        }
+    };
+    Ok(Expression::Comparison(op, Box::new(lhs), Box::new(rhs)))
+}
+
+// create index <index> on <table> (<column>)
+fn parse_create_index(tokens: &mut Tokens) -> std::io::Result<Statement> {
+    let name = tokens.word()?;
+    tokens.expect_keyword("on")?;
+    let table = tokens.word()?;
+    tokens.symbol("(")?;
+    let column = tokens.word()?;
+    tokens.symbol(")")?;
+    Ok(Statement::CreateIndex {
+        name,
+        table,
+        column,
+    })
+}
+
+// alter table <table> rename to <name>
+// alter table <table> rename column <column> to <name>
+// alter index <index> rename to <name>
+fn parse_alter(tokens: &mut Tokens) -> std::io::Result<Statement> {
+    let kind = tokens.keyword()?;
+    if kind != "table" && kind != "index" {
+        return Err(unexpected(&kind));
+    }
+    let name = tokens.word()?;
+    tokens.expect_keyword("rename")?;
+    if kind == "table" && tokens.keyword_if("column") {
+        let column = tokens.word()?;
+        tokens.expect_keyword("to")?;
+        return Ok(Statement::RenameColumn {
+            table: name,
+            column,
+            new_name: tokens.word()?,
+        });
+    }
+    tokens.expect_keyword("to")?;
+    let new_name = tokens.word()?;
+    Ok(match kind.as_str() {
+        "table" => Statement::RenameTable { name, new_name },
+        _ => Statement::RenameIndex { name, new_name },
+    })
+}
+
+// Parses and plans an insert or a scan.
+fn parse_query(tokens: &mut Tokens, catalog: &Catalog) -> std::io::Result<PlanNode> {
+    match tokens.keyword()?.as_str() {
+        "insert" => parse_insert(tokens, catalog),
+        "scan" => parse_scan(tokens, catalog),
+        other => Err(unexpected(other)),
+    }
+}
+
+// insert into <table> values (<value>, ...), ...
+//     [on conflict (<column>) do update set <column> = <value>, ...]
+fn parse_insert(tokens: &mut Tokens, catalog: &Catalog) -> std::io::Result<PlanNode> {
+    tokens.expect_keyword("into")?;
+    let name = tokens.word()?;
+    tokens.expect_keyword("values")?;
+    let schema = match catalog.table(&name) {
+        Some(table) => table.schema().clone(),
+        None => return Err(not_found(&format!("No such table; name = {}", name))),
+    };
+    let mut rows = Vec::new();
+    loop {
+        tokens.symbol("(")?;
+        let mut row = Vec::new();
+        for (idx, column) in schema.columns().iter().enumerate() {
+            if idx > 0 {
+                tokens.symbol(",")?;
+            }
+            let value = literal_value(tokens.literal()?, column)?;
+            row.push(Expression::Constant(value));
+        }
+        tokens.symbol(")")?;
+        rows.push(row);
+        if !tokens.symbol_if(",") {
+            break;
+        }
+    }
+    let values = PlanNode::values(schema.clone(), rows)?;
+    if !tokens.keyword_if("on") {
+        return PlanNode::insert(catalog, &name, values);
+    }
+    tokens.expect_keyword("conflict")?;
+    tokens.symbol("(")?;
+    let key = tokens.word()?;
+    tokens.symbol(")")?;
+    tokens.expect_keyword("do")?;
+    tokens.expect_keyword("update")?;
+    tokens.expect_keyword("set")?;
+    let input = conflict_schema(&schema);
+    let mut assignments = Vec::new();
+    loop {
+        let column_name = tokens.word()?;
+        let column = schema
+            .column_idx(&column_name)
+            .ok_or_else(|| invalid_input(&format!("Column not found; name = {}", column_name)))?;
+        tokens.symbol("=")?;
+        let expression = match tokens.keyword_if("excluded") {
+            true => {
+                tokens.symbol(".")?;
+                Expression::column(&input, &format!("excluded.{}", tokens.word()?))?
+            }
+            false => match tokens.operand(&schema)? {
+                Operand::Column(idx) => Expression::Column(idx),
+                Operand::Literal(literal) => {
+                    let column = schema.nth_column(column).unwrap();
+                    Expression::Constant(literal_value(literal, column)?)
+                }
+            },
+        };
+        assignments.push((schema.nth_column(column).unwrap().name(), expression));
+        if !tokens.symbol_if(",") {
+            break;
+        }
+    }
+    PlanNode::upsert(catalog, &name, &key, assignments, values)
+}
+
+// scan <table> [where <column> <op> <value>] [limit <count>]
+fn parse_scan(tokens: &mut Tokens, catalog: &Catalog) -> std::io::Result<PlanNode> {
+    let name = tokens.word()?;
+    let mut plan = PlanNode::seq_scan(catalog, &name)?;
+    if tokens.keyword_if("where") {
+        let schema = plan.output_schema();
+        let column_name = tokens.word()?;
+        let column = Expression::column(&schema, &column_name)?;
+        let op = tokens.comparison()?;
+        let idx = schema.column_idx(&column_name).unwrap();
+        let value = literal_value(tokens.literal()?, schema.nth_column(idx).unwrap())?;
+        let predicate =
+            Expression::Comparison(op, Box::new(column), Box::new(Expression::Constant(value)));
+        plan = plan.filter(predicate);
+    }
+    if tokens.keyword_if("limit") {
+        let limit = tokens
+            .number()?
+            .parse()
+            .map_err(|_| invalid_input("Invalid limit"))?;
+        plan = plan.limit(limit);
+    }
+    Ok(plan)
+}
+
+// Parses a type name such as |Types::name| returns, ignoring case.
+fn parse_type(name: &str) -> std::io::Result<Types<'static>> {
+    let name = name.to_uppercase();
+    (1..)
+        .map(Types::from_id)
+        .take_while(Option::is_some)
+        .flatten()
+        .find(|types| types.name() == name)
+        .ok_or_else(|| invalid_input(&format!("Unknown type; name = {}", name)))
+}
+
+// Converts |literal| to the type of |column|.
+fn literal_value(literal: Literal, column: &Column) -> std::io::Result<Value<'static>> {
+    let text = match literal {
+        Literal::Null => {
+            let types = Types::from_id(column.types().id()).unwrap();
+            return match types.clone().null_val() {
+                Ok(_) => Ok(Value::null(types)),
+                Err(_) => Err(invalid_input(&format!(
+                    "Column cannot be null; column = {}",
+                    column.name()
+                ))),
+            };
+        }
+        Literal::Bool(val) => val.to_string(),
+        Literal::Bytes(bytes) => return conform_value(Value::new(Types::Varbinary(bytes)), column),
+        Literal::Text(text) => text,
+    };
+    conform_value(
+        Value::new(Types::Varchar(Varlen::Owned(Str::Val(text)))),
+        column,
+    )
+}
+
+fn tokenize(line: &str) -> std::io::Result<Vec<Token>> {
+    const SYMBOLS: [&str; 12] = [
+        "<=", ">=", "!=", "<>", "(", ")", ",", ";", "=", "<", ">", ".",
+    ];
+    let mut tokens = Vec::new();
+    let mut rest = line;
+    loop {
+        rest = rest.trim_start();
+        let c = match rest.chars().next() {
+            Some(c) => c,
+            None => return Ok(tokens),
+        };
+        let negative = c == '-' && rest[1..].starts_with(|c: char| c.is_ascii_digit());
+        let len = if c.is_ascii_digit() || negative {
+            let len = rest[1..]
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .map_or(rest.len(), |len| len + 1);
+            tokens.push(Token::Number(rest[..len].to_string()));
+            len
+        } else if (c == 'x' || c == 'X') && rest[1..].starts_with('\'') {
+            let (s, len) = scan_string(&rest[1..])?;
+            tokens.push(Token::Bytes(parse_hex(&s)?));
+            len + 1
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Word(rest[..len].to_string()));
+            len
+        } else if c == '\'' {
+            let (s, len) = scan_string(rest)?;
+            tokens.push(Token::Str(s));
+            len
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(*symbol))
+                .ok_or_else(|| {
+                    invalid_input(&format!("Unexpected character; character = {}", c))
+                })?;
+            tokens.push(Token::Symbol(symbol));
+            symbol.len()
+        };
+        rest = &rest[len..];
+    }
+}
+
+// Scans the quoted string at the start of |s|. Returns the string and the
+// length of its quoted form.
+fn scan_string(s: &str) -> std::io::Result<(String, usize)> {
+    let mut string = String::new();
+    let mut chars = s.char_indices().skip(1).peekable();
+    while let Some((idx, c)) = chars.next() {
+        if c != '\'' {
+            string.push(c);
+        } else if let Some((_, '\'')) = chars.peek() {
+            chars.next();
+            string.push('\'');
+        } else {
+            return Ok((string, idx + 1));
+        }
+    }
+    Err(invalid_input("Unterminated string"))
+}
+
+// Parses the digits of a hex literal, two per byte.
+fn parse_hex(digits: &str) -> std::io::Result<Vec<u8>> {
+    let invalid = || invalid_input(&format!("Invalid hex literal; literal = x'{}'", digits));
+    if !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+        return Err(invalid());
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&digits[idx..(idx + 2)], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+fn unexpected(token: &str) -> std::io::Error {
+    invalid_input(&format!("Unexpected token; token = {}", token))
+}
+
+struct Tokens {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Tokens {
+    fn new(tokens: Vec<Token>) -> Self {
+        Tokens { tokens, pos: 0 }
+    }
+
+    fn next(&mut self) -> std::io::Result<&Token> {
+        self.pos += 1;
+        self.tokens
+            .get(self.pos - 1)
+            .ok_or_else(|| invalid_input("Unexpected end of statement"))
+    }
+
+    fn back(&mut self) {
+        self.pos -= 1;
+    }
+
+    fn is_done(&self) -> bool {
+        self.tokens[self.pos..]
+            .iter()
+            .all(|token| *token == Token::Symbol(";"))
+    }
+
+    // Checks that nothing but a semicolon follows.
+    fn finish(&mut self) -> std::io::Result<()> {
+        match self.is_done() {
+            true => Ok(()),
+            false => Err(unexpected(&describe(&self.tokens[self.pos]))),
+        }
+    }
+
+    fn word(&mut self) -> std::io::Result<String> {
+        match self.next()? {
+            Token::Word(word) => Ok(word.clone()),
+            token => Err(unexpected(&describe(token))),
+        }
+    }
+
+    // A word, lowercased.
+    fn keyword(&mut self) -> std::io::Result<String> {
+        self.word().map(|word| word.to_lowercase())
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> std::io::Result<()> {
+        match self.keyword()? {
+            word if word == keyword => Ok(()),
+            word => Err(unexpected(&word)),
+        }
+    }
+
+    // Consumes the keyword if it comes next.
+    fn keyword_if(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.pos) {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn symbol(&mut self, symbol: &str) -> std::io::Result<()> {
+        match self.next()? {
+            Token::Symbol(s) if *s == symbol => Ok(()),
+            token => Err(unexpected(&describe(token))),
+        }
+    }
+
+    // Consumes the symbol if it comes next.
+    fn symbol_if(&mut self, symbol: &str) -> bool {
+        match self.tokens.get(self.pos) {
+            Some(Token::Symbol(s)) if *s == symbol => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn number(&mut self) -> std::io::Result<String> {
+        match self.next()? {
+            Token::Number(number) => Ok(number.clone()),
+            token => Err(unexpected(&describe(token))),
+        }
+    }
+
+    fn comparison(&mut self) -> std::io::Result<ComparisonOp> {
+        match self.next()? {
+            Token::Symbol("=") => Ok(ComparisonOp::Eq),
+            Token::Symbol("!=") | Token::Symbol("<>") => Ok(ComparisonOp::Ne),
+            Token::Symbol("<") => Ok(ComparisonOp::Lt),
+            Token::Symbol("<=") => Ok(ComparisonOp::Le),
+            Token::Symbol(">") => Ok(ComparisonOp::Gt),
+            Token::Symbol(">=") => Ok(ComparisonOp::Ge),
+            token => Err(unexpected(&describe(token))),
+        }
+    }
+
+    // A column of |schema| if its name comes next, and a literal otherwise.
+    fn operand(&mut self, schema: &Schema) -> std::io::Result<Operand> {
+        let idx = match self.next()? {
+            Token::Word(word) => schema.column_idx(word),
+            _ => None,
+        };
+        match idx {
+            Some(idx) => Ok(Operand::Column(idx)),
+            None => {
+                self.back();
+                Ok(Operand::Literal(self.literal()?))
+            }
+        }
+    }
+
+    fn literal(&mut self) -> std::io::Result<Literal> {
+        match self.next()? {
+            Token::Number(number) => Ok(Literal::Text(number.clone())),
+            Token::Str(s) => Ok(Literal::Text(s.clone())),
+            Token::Bytes(bytes) => Ok(Literal::Bytes(bytes.clone())),
+            Token::Word(word) => match word.to_lowercase().as_str() {
+                "null" => Ok(Literal::Null),
+                "true" => Ok(Literal::Bool(true)),
+                "false" => Ok(Literal::Bool(false)),
+                _ => Err(unexpected(word)),
+            },
+            token => Err(unexpected(&describe(token))),
+        }
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(s) | Token::Number(s) => s.clone(),
+        Token::Str(s) => format!("'{}'", s),
+        Token::Bytes(bytes) => format!("x'{}'", &bytes_to_hex(bytes)[2..]),
+        Token::Symbol(s) => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::page::table_page::TablePage;
+
+    #[test]
+    fn parse() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(8);
+        let mut catalog = Catalog::create(&mut bpm).unwrap();
+
+        let statement = "CREATE TABLE users (Id bigint, Name varchar(20), Bio VARCHAR);";
+        let schema = match Statement::parse(statement, &catalog).unwrap() {
+            Statement::CreateTable {
+                name,
+                schema,
+                checks,
+            } => {
+                assert_eq!("users", name);
+                assert!(checks.is_empty());
+                schema
+            }
+            statement => panic!("Unexpected statement: {:?}", statement),
+        };
+        let lengths: Vec<usize> = schema.columns().iter().map(|c| c.len()).collect();
+        assert_eq!(vec![8, 20, DEFAULT_VARLEN], lengths);
+        assert!(catalog.create_table(&mut bpm, 1, "users", schema).is_ok());
+
+        let statement = "create table t (A integer check (A > 0), B integer, \
+                         check (A <> B or B is null), constraint positive check (not (0 >= B)), \
+                         check (A < 10 and B < 'x'))";
+        assert!(Statement::parse(statement, &catalog).is_err());
+        let statement = "create table t (A integer check (A > 0), B integer, \
+                         check (A <> B or B is null), constraint positive check (not (0 >= B)), \
+                         check (A < 10 and B < 20))";
+        match Statement::parse(statement, &catalog).unwrap() {
+            Statement::CreateTable { checks, .. } => {
+                let names: Vec<&str> = checks.iter().map(|(name, _)| name.as_str()).collect();
+                assert_eq!(vec!["t_A_check", "t_check", "positive", "t_check1"], names);
+                assert_eq!(vec![0, 1, 1], checks[1].1.column_indices());
+                assert!(matches!(checks[2].1, Expression::Not(_)));
+            }
+            statement => panic!("Unexpected statement: {:?}", statement),
+        }
+        assert!(Statement::parse("create table t (A integer check (1 < 2))", &catalog).is_err());
+
+        match Statement::parse("create index users_id on users (Id)", &catalog).unwrap() {
+            Statement::CreateIndex {
+                name,
+                table,
+                column,
+            } => assert_eq!(("users_id", "users", "Id"), (&*name, &*table, &*column)),
+            statement => panic!("Unexpected statement: {:?}", statement),
+        }
+        let statement = "alter table users rename column Name to FullName";
+        match Statement::parse(statement, &catalog).unwrap() {
+            Statement::RenameColumn {
+                table,
+                column,
+                new_name,
+            } => assert_eq!(
+                ("users", "Name", "FullName"),
+                (&*table, &*column, &*new_name)
+            ),
+            statement => panic!("Unexpected statement: {:?}", statement),
+        }
+        let statement = Statement::parse("alter index users_id rename to id", &catalog).unwrap();
+        assert!(matches!(statement, Statement::RenameIndex { .. }));
+        match Statement::parse("REINDEX users_id", &catalog).unwrap() {
+            Statement::Reindex(name) => assert_eq!("users_id", name),
+            statement => panic!("Unexpected statement: {:?}", statement),
+        }
+        assert!(Statement::parse("alter view users rename to x", &catalog).is_err());
+        match Statement::parse("explain scan users where Id <> -1 limit 2", &catalog).unwrap() {
+            Statement::Explain(PlanNode::Limit { limit, child }) => {
+                assert_eq!(2, limit);
+                assert!(matches!(*child, PlanNode::Filter { .. }));
+            }
+            statement => panic!("Unexpected statement: {:?}", statement),
+        }
+        let statement = "insert into users values (null, 'it''s', ''), (2, X'6869', 'y')";
+        match Statement::parse(statement, &catalog).unwrap() {
+            Statement::Query(PlanNode::Insert { child, .. }) => match *child {
+                PlanNode::Values { rows, .. } => assert_eq!(2, rows.len()),
+                plan => panic!("Unexpected plan: {:?}", plan),
+            },
+            statement => panic!("Unexpected statement: {:?}", statement),
+        }
+
+        let error = |text: &str| Statement::parse(text, &catalog).unwrap_err().to_string();
+        assert_eq!("Empty statement", error(" ; "));
+        assert_eq!("Unexpected token; token = users", error("scan users users"));
+        assert_eq!("Unexpected end of statement", error("scan"));
+        assert_eq!("Unexpected token; token = drop", error("drop table users"));
+        assert_eq!("Unexpected character; character = ?", error("scan users?"));
+        assert_eq!("Unterminated string", error("scan users where Name = 'a"));
+        assert_eq!(
+            "Unknown type; name = UUID",
+            error("create table t (Id uuid)")
+        );
+        assert_eq!(
+            "Type has a fixed length; column = Id",
+            error("create table t (Id INTEGER(1))")
+        );
+        assert_eq!(
+            "Column cannot be null; column = Name",
+            error("insert into users values (5, null, '')")
+        );
+        assert_eq!(
+            "Cannot convert value; column = Id",
+            error("insert into users values ('five', 'x', '')")
+        );
+        assert_eq!(
+            "No such table; name = nothing",
+            error("insert into nothing values (1)")
+        );
+        let upsert = "insert into users values (5, 'x', '') on conflict (Id) do update set";
+        assert_eq!(
+            "Unexpected token; token = nothing",
+            error("insert into users values (5, 'x', '') on conflict (Id) do nothing")
+        );
+        assert_eq!(
+            "Column not found; name = Age",
+            error(&format!("{} Age = 1", upsert))
+        );
+        assert_eq!(
+            "Column not found; name = excluded.Age",
+            error(&format!("{} Name = excluded.Age", upsert))
+        );
+        assert_eq!(
+            "Invalid hex literal; literal = x'0g'",
+            error("insert into users values (5, x'0g', '')")
+        );
+    }
+}
@@ -0,0 +1,51 @@
+// Functionality: Page buffers aligned for direct I/O, which needs the memory,
+// the file offset and the length of every request to be multiples of the
+// logical block size of the device. Pages are aligned to 4096 bytes, which
+// covers the common block sizes.
+
+use crate::common::config::PAGE_SIZE;
+
+#[repr(C, align(4096))]
+#[derive(Clone, Copy)]
+struct AlignedPage([u8; PAGE_SIZE]);
+
+// A zero-initialized buffer of whole pages, contiguous in memory.
+pub struct AlignedBuffer {
+    pages: Vec<AlignedPage>,
+}
+
+impl AlignedBuffer {
+    pub fn new(page_count: usize) -> Self {
+        AlignedBuffer {
+            pages: vec![AlignedPage([0; PAGE_SIZE]); page_count],
+        }
+    }
+
+    // Copies the first |PAGE_SIZE| bytes of every element of |pages|.
+    pub fn from_pages(pages: &[&mut [u8]]) -> Self {
+        let mut buffer = Self::new(pages.len());
+        for (dst, src) in buffer.pages.iter_mut().zip(pages.iter()) {
+            dst.0.copy_from_slice(&src[..PAGE_SIZE]);
+        }
+        buffer
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        // |AlignedPage| has no padding, so the pages are contiguous bytes.
+        unsafe {
+            std::slice::from_raw_parts(
+                self.pages.as_ptr() as *const u8,
+                self.pages.len() * PAGE_SIZE,
+            )
+        }
+    }
+
+    pub fn bytes_mut(&mut self) -> &mut [u8] {
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self.pages.as_mut_ptr() as *mut u8,
+                self.pages.len() * PAGE_SIZE,
+            )
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::common::config::CHECKSUM_SIZE;
+use crate::common::error::*;
 use crate::disk::disk_manager::read;
 use crate::disk::disk_manager::write;
 use crate::logging::error_logging::ErrorLogging;
@@ -15,24 +16,38 @@ pub const FULL_WORD: u8 = 255;
 pub struct Bitmap {
     file: File,
     cache: Vec<u8>,
+    read_only: bool,
 }
 
 impl Drop for Bitmap {
     fn drop(&mut self) {
-        // Unable to handle errors on destruction.
-        self.sync().log();
+        if !self.read_only {
+            // Unable to handle errors on destruction.
+            self.sync().log();
+        }
     }
 }
 
 impl Bitmap {
     pub fn new(path: &str) -> std::io::Result<Self> {
+        Self::open(path, /*read_only=*/ false)
+    }
+
+    // Opens an existing bitmap file without write access. Changes to the bits
+    // stay in memory and are never persisted.
+    pub fn new_read_only(path: &str) -> std::io::Result<Self> {
+        Self::open(path, /*read_only=*/ true)
+    }
+
+    fn open(path: &str, read_only: bool) -> std::io::Result<Self> {
         Ok(Bitmap {
             file: OpenOptions::new()
                 .read(true)
-                .write(true)
-                .create(true)
+                .write(!read_only)
+                .create(!read_only)
                 .open(path)?,
             cache: Vec::new(),
+            read_only,
         })
         .and_then(|mut bitmap| {
             bitmap.init()?;
@@ -79,6 +94,9 @@ impl Bitmap {
 
     // Compacts and persists to disk.
     pub fn sync(&mut self) -> std::io::Result<()> {
+        if self.read_only {
+            return Err(read_only("Bitmap is opened read-only"));
+        }
         self.compact();
         let size = self.cache.len();
         self.file.set_len(size as u64)?;
@@ -5,92 +5,746 @@
 
 use crate::common::config::PageId;
 use crate::common::config::PAGE_SIZE;
+use crate::common::crc32c::crc32c;
 use crate::common::error::*;
+use crate::common::hexdump::hex_dump;
+use crate::common::options::DbOptions;
 use crate::common::reinterpret;
+use crate::disk::aligned_buffer::AlignedBuffer;
+use crate::disk::page_compression::*;
 use crate::disk::selector::Selector;
-use std::collections::hash_map::DefaultHasher;
+use crate::disk::superblock::*;
+use crate::disk::tablespace::*;
+use crate::logging::error_logging::ErrorLogging;
+use log::warn;
+use std::fmt;
+use std::fs;
 use std::fs::File;
 use std::fs::OpenOptions;
-use std::hash::Hash;
-use std::hash::Hasher;
+use std::fs::TryLockError;
 use std::io::Error;
 use std::io::ErrorKind;
+use std::io::IoSlice;
 use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
 use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
 
 pub const BITMAP_FILE_SUFFIX: &'static str = ".bm";
 
+// The version of the page format, stored with the checksum of every page:
+//  ------------------------------------------------
+// | CRC-32C (4) | Format version (1) | Reserved (3) |
+//  ------------------------------------------------
+// A page written in another format fails checksum validation, whatever its
+// content, rather than being misread.
+pub const PAGE_FORMAT_VERSION: u8 = 1;
+
 // TODO: Right now, DiskManager does not support creating directories, i.e.
 // the |db_file| being passed to |DiskManager::new| has to be under an existing
 // directory. However, it might not be the DiskManager's responsibility to
 // create directories.
 
 pub struct DiskManager {
-    db_io: File,
+    db_file: String,
+    // The tablespaces, indexed by file ID; the first one is |db_file|.
+    spaces: Vec<Space>,
+    read_only: bool,
+    // Where hex dumps of pages failing checksum validation are written.
+    quarantine_dir: Option<PathBuf>,
+    // Whether pages are compressed on write, see |set_compression|.
+    compression: bool,
+    // Whether reads validate page checksums, see |set_verify_checksums|.
+    verify_checksums: bool,
+}
+
+// A file of pages, with its allocation bitmap.
+struct Space {
+    path: String,
+    file: File,
     selector: Selector,
+    // Whether |file| bypasses the OS page cache, see |with_direct_io|.
+    direct_io: bool,
+    superblock: Superblock,
+}
+
+impl Space {
+    fn open(path: &str, read_only: bool, direct_io: bool) -> std::io::Result<Self> {
+        let bitmap_file = path.to_string() + BITMAP_FILE_SUFFIX;
+        let (mut file, direct_io) = match read_only {
+            true => (OpenOptions::new().read(true).open(path)?, false),
+            false => {
+                let mut options = OpenOptions::new();
+                options.read(true).write(true).create(true);
+                match direct_io {
+                    true => open_direct(&options, path)?,
+                    false => (options.open(path)?, false),
+                }
+            }
+        };
+        lock(&file, path, /*exclusive=*/ !read_only)?;
+        // Checked before opening the bitmap, so that a file which is not a
+        // database file does not get one.
+        let superblock = match file.metadata()?.len() {
+            0 if !read_only => {
+                let superblock = Superblock::default();
+                write_superblock(&mut file, &superblock)?;
+                superblock
+            }
+            _ => read_superblock(&mut file, path)?,
+        };
+        let selector = match read_only {
+            true => Selector::new_read_only(&bitmap_file)?,
+            false => Selector::new(&bitmap_file)?,
+        };
+        Ok(Space {
+            path: path.to_string(),
+            file,
+            selector,
+            direct_io,
+            superblock,
+        })
+    }
+
+    // The number of page slots of the file, after the superblock.
+    fn page_count(&self) -> std::io::Result<usize> {
+        let slots = self.file.metadata()?.len() / PAGE_SIZE as u64;
+        Ok(slots.saturating_sub(1) as usize)
+    }
+
+    // Records |features| in the superblock, if not already there.
+    fn add_features(&mut self, features: u32) -> std::io::Result<()> {
+        if self.superblock.features & features == features {
+            return Ok(());
+        }
+        self.superblock.features |= features;
+        write_superblock(&mut self.file, &self.superblock)
+    }
+
+    // Writes the page |data| at |page_id|, compressed if |compression| and
+    // the page compresses. Direct I/O writes from an aligned copy.
+    fn write_page(
+        &mut self,
+        page_id: PageId,
+        data: &mut [u8],
+        compression: bool,
+    ) -> std::io::Result<()> {
+        update_checksum(data)?;
+        self.file.seek(SeekFrom::Start(offset(page_id)))?;
+        let compressed = match compression {
+            true => compress_page(data),
+            false => None,
+        };
+        let (image, size) = match &compressed {
+            Some((image, size)) => (&image[..], *size),
+            None => (&data[..PAGE_SIZE], PAGE_SIZE),
+        };
+        match self.direct_io {
+            true => {
+                let buffer = AlignedBuffer::from_pages(&[&mut image.to_vec()[..]]);
+                self.file.write_all(&buffer.bytes()[..size])
+            }
+            false => self.file.write_all(&image[..size]),
+        }
+    }
+
+    // Fills |data|, whose length is a multiple of |PAGE_SIZE|, from the
+    // current file offset. Direct I/O reads into an aligned buffer first.
+    fn read_into(&mut self, data: &mut [u8]) -> std::io::Result<()> {
+        let size = data.len();
+        match self.direct_io {
+            true => {
+                let mut buffer = AlignedBuffer::new(size / PAGE_SIZE);
+                read_all(&mut self.file, buffer.bytes_mut(), size)?;
+                data.copy_from_slice(buffer.bytes());
+                Ok(())
+            }
+            false => read_all(&mut self.file, data, size),
+        }
+    }
+}
+
+// The payload of the |InvalidData| error returned when a page fails checksum
+// validation. Retrieve it with |err.get_ref()| and |downcast_ref|.
+#[derive(Debug)]
+pub struct CorruptedPage {
+    pub page_id: PageId,
+    // The checksum stored in the page.
+    pub expected: u64,
+    // The checksum computed over the page content.
+    pub actual: u64,
+    // The hex dump written to the quarantine directory, if any.
+    pub dump_path: Option<PathBuf>,
+}
+
+impl fmt::Display for CorruptedPage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Page corrupted; page_id = {}, expected checksum = {:#018x}, actual checksum = {:#018x}",
+            self.page_id, self.expected, self.actual
+        )?;
+        let version = format_version(self.expected);
+        if version != PAGE_FORMAT_VERSION {
+            write!(f, ", unsupported format version = {}", version)?;
+        }
+        if let Some(path) = &self.dump_path {
+            write!(f, ", dumped to {}", path.display())?;
+        }
+        Ok(())
+    }
 }
 
+impl std::error::Error for CorruptedPage {}
+
+// The payload of the |WouldBlock| error returned when the database file is
+// locked by another process. A read-write open takes an exclusive lock, and a
+// read-only open a shared one; the lock is released when the |DiskManager| is
+// dropped.
+#[derive(Debug)]
+pub struct DatabaseLocked {
+    pub path: PathBuf,
+}
+
+impl fmt::Display for DatabaseLocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Database is locked; path = {}", self.path.display())
+    }
+}
+
+impl std::error::Error for DatabaseLocked {}
+
 impl DiskManager {
     pub fn new(db_file: &str) -> std::io::Result<Self> {
-        let bitmap_file = db_file.to_string() + BITMAP_FILE_SUFFIX;
+        Self::open(db_file, /*read_only=*/ false, /*direct_io=*/ false)
+    }
+
+    // Same as |new|, but reads and writes pages bypassing the OS page cache
+    // (i.e. O_DIRECT on Linux, F_NOCACHE on macOS), e.g. to benchmark the
+    // buffer pool. Falls back to cached I/O, with a warning, on platforms or
+    // filesystems without direct I/O (e.g. tmpfs); see |is_direct_io|.
+    pub fn with_direct_io(db_file: &str) -> std::io::Result<Self> {
+        Self::open(db_file, /*read_only=*/ false, /*direct_io=*/ true)
+    }
+
+    // Opens an existing database without write access. Every mutation returns
+    // a |ReadOnlyFilesystem| error, which makes it safe to inspect production
+    // files or serve reads from a snapshot.
+    pub fn new_read_only(db_file: &str) -> std::io::Result<Self> {
+        Self::open(db_file, /*read_only=*/ true, /*direct_io=*/ false)
+    }
+
+    // Opens the database file as configured by |options|; see |DbOptions|.
+    // Returns |NotFound| if the file does not exist and is not to be created.
+    pub fn with_options(db_file: &str, options: &DbOptions) -> std::io::Result<Self> {
+        options.validate()?;
+        if (options.read_only || !options.create_if_missing) && !Path::new(db_file).exists() {
+            return Err(not_found(&format!("No such database; path = {}", db_file)));
+        }
+        let mut disk_mgr = Self::open(db_file, options.read_only, options.direct_io)?;
+        if options.compression {
+            disk_mgr.set_compression(true)?;
+        }
+        if let Some(dir) = &options.quarantine_dir {
+            disk_mgr.set_quarantine_dir(dir);
+        }
+        disk_mgr.set_verify_checksums(options.verify_checksums);
+        Ok(disk_mgr)
+    }
+
+    // Opens the database file, then its other tablespaces.
+    fn open(db_file: &str, read_only: bool, direct_io: bool) -> std::io::Result<Self> {
+        let mut spaces = vec![Space::open(db_file, read_only, direct_io)?];
+        for path in read_registry(db_file)? {
+            spaces.push(Space::open(&path, read_only, direct_io)?);
+        }
         Ok(DiskManager {
-            db_io: OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create(true)
-                .open(db_file)?,
-            selector: Selector::new(&bitmap_file)?,
+            db_file: db_file.to_string(),
+            spaces,
+            read_only,
+            quarantine_dir: None,
+            compression: false,
+            verify_checksums: true,
         })
     }
 
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    // Whether the database file bypasses the OS page cache.
+    pub fn is_direct_io(&self) -> bool {
+        self.spaces[0].direct_io
+    }
+
+    // Compresses pages on write when |enabled|, trading CPU for less data
+    // written; see |page_compression|. Pages that do not compress are written
+    // as is. Compressed pages are read whatever the setting. Enabling it
+    // records |FEATURE_COMPRESSION| in the superblocks, so that versions
+    // without compression refuse to open the database.
+    pub fn set_compression(&mut self, enabled: bool) -> std::io::Result<()> {
+        if enabled {
+            self.check_writable()?;
+            for space in self.spaces.iter_mut() {
+                space.add_features(FEATURE_COMPRESSION)?;
+            }
+        }
+        self.compression = enabled;
+        Ok(())
+    }
+
+    // The superblock of the database file.
+    pub fn superblock(&self) -> &Superblock {
+        &self.spaces[0].superblock
+    }
+
+    pub fn is_compression_enabled(&self) -> bool {
+        self.compression
+    }
+
+    // Adds a tablespace stored in the file at |path|, which is created if it
+    // does not exist, and returns its file ID; see |allocate_page_in|. The
+    // tablespace is recorded next to the database file, and reopened with it.
+    // Returns |AlreadyExists| if |path| is already a tablespace, and
+    // |InvalidInput| if there are |MAX_FILE_ID| tablespaces already.
+    pub fn add_tablespace(&mut self, path: &str) -> std::io::Result<FileId> {
+        self.check_writable()?;
+        if self.spaces.iter().any(|space| space.path == path) {
+            return Err(already_exists("Tablespace already exists"));
+        }
+        if self.spaces.len() > MAX_FILE_ID as usize {
+            return Err(invalid_input("Too many tablespaces"));
+        }
+        let mut space = Space::open(path, /*read_only=*/ false, self.is_direct_io())?;
+        if self.compression {
+            space.add_features(FEATURE_COMPRESSION)?;
+        }
+        append_registry(&self.db_file, path)?;
+        self.spaces.push(space);
+        Ok((self.spaces.len() - 1) as FileId)
+    }
+
+    // The number of tablespaces, including the database file.
+    pub fn tablespace_count(&self) -> usize {
+        self.spaces.len()
+    }
+
+    // Makes |read_page| write a hex dump of every page failing checksum
+    // validation into |dir|, which has to exist.
+    pub fn set_quarantine_dir(&mut self, dir: &str) {
+        self.quarantine_dir = Some(PathBuf::from(dir));
+    }
+
+    // Makes |read_page| and |read_pages| skip checksum validation unless
+    // |enabled|, which is the default. Checksums are still written, so that
+    // turning validation back on works on pages written meanwhile.
+    pub fn set_verify_checksums(&mut self, enabled: bool) {
+        self.verify_checksums = enabled;
+    }
+
+    pub fn is_verify_checksums_enabled(&self) -> bool {
+        self.verify_checksums
+    }
+
+    // Duplicates the handles of the tablespace files, in file ID order, e.g.
+    // for the workers of |AsyncDiskScheduler|. The duplicates share the locks
+    // and the file offsets.
+    pub(crate) fn try_clone_files(&self) -> std::io::Result<Vec<File>> {
+        self.spaces.iter().map(|space| space.file.try_clone()).collect()
+    }
+
     // Writes data to page with the specified page ID on disk.
     // The caller needs to ensure that page_id >= 1 and is valid.
     pub fn write_page(&mut self, page_id: PageId, data: &mut [u8]) -> std::io::Result<()> {
-        let offset = (page_id as u64) * (PAGE_SIZE as u64);
-        self.db_io.seek(SeekFrom::Start(offset))?;
-        write(&mut self.db_io, data, PAGE_SIZE)?;
-        self.db_io.sync_data()?;
+        self.check_writable()?;
+        let compression = self.compression;
+        let space = self.space_mut(page_id)?;
+        space.write_page(page_id, data, compression)?;
+        space.file.sync_data()?;
+        Ok(())
+    }
+
+    // Writes |pages| to the pages starting at |first_page_id|, one page per
+    // element, with vectored writes. Unlike |write_page|, the file is not
+    // synced, so that a batch of writes can be followed by one |sync_data|.
+    // The caller needs to ensure that the pages are valid. Returns
+    // |InvalidInput| if the pages span several tablespaces.
+    pub fn write_pages(
+        &mut self,
+        first_page_id: PageId,
+        pages: &mut [&mut [u8]],
+    ) -> std::io::Result<()> {
+        self.check_writable()?;
+        if page_no(first_page_id) as usize + pages.len() > MAX_PAGE_NO as usize + 1 {
+            return Err(invalid_input("Pages span several tablespaces"));
+        }
+        let compression = self.compression;
+        let space = self.space_mut(first_page_id)?;
+        // Compressed pages have different sizes, so they are written one by
+        // one.
+        if compression {
+            for (page_id, page) in (first_page_id..).zip(pages.iter_mut()) {
+                space.write_page(page_id, page, compression)?;
+            }
+            return Ok(());
+        }
+        for page in pages.iter_mut() {
+            update_checksum(page)?;
+        }
+        space.file.seek(SeekFrom::Start(offset(first_page_id)))?;
+        // Direct I/O writes the pages at once from an aligned copy.
+        let buffer;
+        let mut slices: Vec<IoSlice> = match space.direct_io {
+            true => {
+                buffer = AlignedBuffer::from_pages(pages);
+                vec![IoSlice::new(buffer.bytes())]
+            }
+            false => pages
+                .iter()
+                .map(|page| IoSlice::new(&page[..PAGE_SIZE]))
+                .collect(),
+        };
+        let mut slices = &mut slices[..];
+        while !slices.is_empty() {
+            let bytes_written = space.file.write_vectored(slices)?;
+            if bytes_written == 0 {
+                return Err(Error::new(ErrorKind::WriteZero, "I/O error: wrote 0 byte"));
+            }
+            IoSlice::advance_slices(&mut slices, bytes_written);
+        }
+        Ok(())
+    }
+
+    // Syncs the content of the tablespace files, see |write_pages|.
+    pub fn sync_data(&mut self) -> std::io::Result<()> {
+        self.check_writable()?;
+        for space in self.spaces.iter_mut() {
+            space.file.sync_data()?;
+        }
         Ok(())
     }
 
     // Reads data from page with the specified page ID on disk.
-    // The caller needs to ensure that page_id >= 1 and is valid.
+    // The caller needs to ensure that page_id >= 1 and is valid. Returns a
+    // |CorruptedPage| error if the checksum does not match, unless validation
+    // is off; see |set_verify_checksums|.
     pub fn read_page(&mut self, page_id: PageId, data: &mut [u8]) -> std::io::Result<()> {
-        if !self.selector.is_used(page_id as usize) {
+        let verify = self.verify_checksums;
+        self.read_page_inl(page_id, data, verify)
+    }
+
+    // Same as |read_page|, but skips checksum verification. Meant for salvage
+    // tooling recovering what it can from corrupted pages.
+    pub fn read_page_unchecked(&mut self, page_id: PageId, data: &mut [u8]) -> std::io::Result<()> {
+        self.read_page_inl(page_id, data, /*verify=*/ false)
+    }
+
+    // Reads the pages starting at |first_page_id| into |data|, one page per
+    // |PAGE_SIZE| bytes, with a single disk read. Stops before the first page
+    // that is not allocated or lies past the end of its file, and keeps only
+    // the pages before the first one failing checksum validation, leaving it
+    // for |read_page| to report. Returns the number of pages read.
+    pub fn read_pages(&mut self, first_page_id: PageId, data: &mut [u8]) -> std::io::Result<usize> {
+        let file_pages = match self.space(first_page_id) {
+            Some(space) => space.page_count()? as u32,
+            None => return Ok(0),
+        };
+        let first_page_no = page_no(first_page_id);
+        let count = (first_page_no..=MAX_PAGE_NO)
+            .take(data.len() / PAGE_SIZE)
+            .take_while(|&page_no| {
+                let page_id = make_page_id(file_id(first_page_id), page_no);
+                page_no < file_pages && self.is_allocated(page_id)
+            })
+            .count();
+        if count == 0 {
+            return Ok(0);
+        }
+        let space = self.space_mut(first_page_id)?;
+        space.file.seek(SeekFrom::Start(offset(first_page_id)))?;
+        space.read_into(&mut data[..(count * PAGE_SIZE)])?;
+        let verify = self.verify_checksums;
+        let mut valid = 0;
+        for page in data.chunks_mut(PAGE_SIZE).take(count) {
+            if expand_page(page).is_err() || (verify && checksum_mismatch(page)?.is_some()) {
+                break;
+            }
+            valid += 1;
+        }
+        Ok(valid)
+    }
+
+    fn read_page_inl(&mut self, page_id: PageId, data: &mut [u8], verify: bool) -> std::io::Result<()> {
+        if !self.is_allocated(page_id) {
             return Err(invalid_input(&format!(
                 "The page is not allocated; page_id = {}",
                 page_id
             )));
         }
 
-        // Extend the file length when the page is at the tail.
-        let offset = (page_id as u64) * (PAGE_SIZE as u64);
-        if offset == self.db_io.metadata()?.len() {
-            self.db_io.set_len(offset + PAGE_SIZE as u64)?;
+        // Extend the file length when the page is at or past the tail, e.g. it
+        // was allocated but never written before a crash. A read-only file
+        // cannot be extended; the page was never written, so it reads as zeros.
+        let read_only = self.read_only;
+        let space = self.space_mut(page_id)?;
+        let offset = offset(page_id);
+        if offset >= space.file.metadata()?.len() {
+            if read_only {
+                for byte in data.iter_mut().take(PAGE_SIZE) {
+                    *byte = 0;
+                }
+                return Ok(());
+            }
+            space.file.set_len(offset + PAGE_SIZE as u64)?;
         }
 
-        self.db_io.seek(SeekFrom::Start(offset))?;
-        read(&mut self.db_io, data, PAGE_SIZE)?;
+        space.file.seek(SeekFrom::Start(offset))?;
+        space.read_into(&mut data[..PAGE_SIZE])?;
+        let expanded = expand_page(data);
+        if verify {
+            expanded?;
+            if let Some((expected, actual)) = checksum_mismatch(data)? {
+                let dump_path = self.quarantine(page_id, &data[..PAGE_SIZE]);
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    CorruptedPage {
+                        page_id,
+                        expected,
+                        actual,
+                        dump_path,
+                    },
+                ));
+            }
+        }
         Ok(())
     }
 
-    pub fn allocate_page(&mut self) -> PageId {
-        let idx = self.selector.vacant();
-        self.selector.set_used(idx);
-        idx as PageId
+    // Allocates a page in the database file.
+    pub fn allocate_page(&mut self) -> std::io::Result<PageId> {
+        self.allocate_page_in(PRIMARY_FILE_ID)
+    }
+
+    // Allocates a page in the tablespace |file_id|. Returns |NotFound| if
+    // there is no such tablespace, and |InvalidInput| if it is full.
+    pub fn allocate_page_in(&mut self, file_id: FileId) -> std::io::Result<PageId> {
+        self.allocate_pages_in(file_id, 1).map(|page_ids| page_ids.start)
+    }
+
+    // Allocates |count| contiguous pages in the database file, e.g. to grow a
+    // table by an extent, so that scanning them reads a contiguous region.
+    pub fn allocate_pages(&mut self, count: usize) -> std::io::Result<Range<PageId>> {
+        self.allocate_pages_in(PRIMARY_FILE_ID, count)
+    }
+
+    // Same as |allocate_pages|, in the tablespace |file_id|. Returns
+    // |NotFound| if there is no such tablespace, and |InvalidInput| if it has
+    // no room for |count| contiguous pages.
+    pub fn allocate_pages_in(
+        &mut self,
+        file_id: FileId,
+        count: usize,
+    ) -> std::io::Result<Range<PageId>> {
+        self.check_writable()?;
+        let space = match self.spaces.get_mut(file_id as usize) {
+            Some(space) => space,
+            None => return Err(not_found("Tablespace not found")),
+        };
+        let start = space.selector.vacant_run(count);
+        if start + count > MAX_PAGE_NO as usize + 1 {
+            return Err(invalid_input("Tablespace is full"));
+        }
+        for idx in start..(start + count) {
+            space.selector.set_used(idx);
+        }
+        let first_page_id = make_page_id(file_id, start as u32);
+        Ok(first_page_id..(first_page_id + count as PageId))
+    }
+
+    // Marks |page_id| as allocated. Recovery uses this to replay allocations
+    // that the bitmap lost in a crash.
+    pub fn reserve_page(&mut self, page_id: PageId) -> std::io::Result<()> {
+        self.check_writable()?;
+        let space = self.space_mut(page_id)?;
+        space.selector.set_used(page_no(page_id) as usize);
+        Ok(())
     }
 
     // |HEADER_PAGE_ID| is the smallest possible page ID. Therefore, the caller
     // needs to ensure that |page_id| >= |HEADER_PAGE_ID|.
-    pub fn deallocate_page(&mut self, page_id: PageId) {
-        self.selector.set_free(page_id as usize);
+    pub fn deallocate_page(&mut self, page_id: PageId) -> std::io::Result<()> {
+        self.check_writable()?;
+        let space = self.space_mut(page_id)?;
+        space.selector.set_free(page_no(page_id) as usize);
+        Ok(())
+    }
+
+    pub fn is_allocated(&self, page_id: PageId) -> bool {
+        self.space(page_id)
+            .is_some_and(|space| space.selector.is_used(page_no(page_id) as usize))
+    }
+
+    // The number of pages tracked by the allocation bitmap of the database
+    // file, allocated or not.
+    pub fn bitmap_page_count(&self) -> usize {
+        self.spaces[0].selector.capacity()
+    }
+
+    // The number of pages the database file spans.
+    pub fn file_page_count(&self) -> std::io::Result<usize> {
+        self.spaces[0].page_count()
+    }
+
+    // Persists the allocation bitmaps and syncs the tablespace files.
+    pub fn sync(&mut self) -> std::io::Result<()> {
+        self.check_writable()?;
+        for space in self.spaces.iter_mut() {
+            space.selector.sync()?;
+            space.file.sync_all()?;
+        }
+        Ok(())
     }
 
     // TODO: Think about whether it is needed and how to compact.
     pub fn compact(&mut self) {
-        self.selector.compact();
+        for space in self.spaces.iter_mut() {
+            space.selector.compact();
+        }
+    }
+
+    fn check_writable(&self) -> std::io::Result<()> {
+        if self.read_only {
+            Err(read_only("Database is opened read-only"))
+        } else {
+            Ok(())
+        }
+    }
+
+    // Returns the tablespace of |page_id|, or None if there is no such
+    // tablespace.
+    fn space(&self, page_id: PageId) -> Option<&Space> {
+        match page_id < 0 {
+            true => None,
+            false => self.spaces.get(file_id(page_id) as usize),
+        }
+    }
+
+    // Same as |space|, but returns |InvalidInput| if there is no such
+    // tablespace.
+    fn space_mut(&mut self, page_id: PageId) -> std::io::Result<&mut Space> {
+        if page_id < 0 || file_id(page_id) as usize >= self.spaces.len() {
+            return Err(invalid_input(&format!(
+                "Tablespace not found; page_id = {}",
+                page_id
+            )));
+        }
+        Ok(&mut self.spaces[file_id(page_id) as usize])
+    }
+
+    // Writes a hex dump of the corrupted page into the quarantine directory.
+    // Returns the path of the dump, or None if there is no quarantine directory
+    // or the dump fails.
+    fn quarantine(&self, page_id: PageId, data: &[u8]) -> Option<PathBuf> {
+        let dir = self.quarantine_dir.as_ref()?;
+        let path = Path::new(dir).join(format!("page_{}.hexdump", page_id));
+        let content = format!(
+            "page_id = {}, stored checksum = {:#018x}, computed checksum = {:#018x}\n{}",
+            page_id,
+            reinterpret::read_u64(data),
+            compute_checksum(&data[8..]),
+            hex_dump(data)
+        );
+        fs::write(&path, content).log_and().ok().map(|_| path)
+    }
+}
+
+// The value of O_DIRECT differs between architectures.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "riscv64")
+))]
+const O_DIRECT: i32 = 0o40000;
+#[cfg(all(target_os = "linux", any(target_arch = "arm", target_arch = "aarch64")))]
+const O_DIRECT: i32 = 0o200000;
+
+// Opens |db_file| bypassing the OS page cache. Returns the file, and whether
+// direct I/O is in effect.
+#[cfg(all(
+    target_os = "linux",
+    any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "riscv64",
+        target_arch = "arm",
+        target_arch = "aarch64"
+    )
+))]
+fn open_direct(options: &OpenOptions, db_file: &str) -> std::io::Result<(File, bool)> {
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut direct_options = options.clone();
+    direct_options.custom_flags(O_DIRECT);
+    match direct_options.open(db_file) {
+        Ok(file) => Ok((file, true)),
+        // The filesystem does not support O_DIRECT.
+        Err(e) if e.kind() == ErrorKind::InvalidInput => {
+            warn!("Direct I/O unsupported, falling back; db_file = {}", db_file);
+            Ok((options.open(db_file)?, false))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn open_direct(options: &OpenOptions, db_file: &str) -> std::io::Result<(File, bool)> {
+    use std::os::raw::c_int;
+    use std::os::unix::io::AsRawFd;
+    extern "C" {
+        fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+    }
+    const F_NOCACHE: c_int = 48;
+    let file = options.open(db_file)?;
+    let direct_io = unsafe { fcntl(file.as_raw_fd(), F_NOCACHE, 1) } != -1;
+    if !direct_io {
+        warn!("Direct I/O unsupported, falling back; db_file = {}", db_file);
+    }
+    Ok((file, direct_io))
+}
+
+#[cfg(not(any(
+    target_os = "macos",
+    all(
+        target_os = "linux",
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "riscv64",
+            target_arch = "arm",
+            target_arch = "aarch64"
+        )
+    )
+)))]
+fn open_direct(options: &OpenOptions, db_file: &str) -> std::io::Result<(File, bool)> {
+    warn!("Direct I/O unsupported, falling back; db_file = {}", db_file);
+    Ok((options.open(db_file)?, false))
+}
+
+// Takes an advisory lock on the database file without blocking.
+fn lock(file: &File, path: &str, exclusive: bool) -> std::io::Result<()> {
+    let result = match exclusive {
+        true => file.try_lock(),
+        false => file.try_lock_shared(),
+    };
+    match result {
+        Ok(()) => Ok(()),
+        Err(TryLockError::WouldBlock) => Err(Error::new(
+            ErrorKind::WouldBlock,
+            DatabaseLocked {
+                path: PathBuf::from(path),
+            },
+        )),
+        Err(TryLockError::Error(e)) => Err(e),
     }
 }
 
@@ -108,6 +762,38 @@ pub fn write(file: &mut File, data: &mut [u8], size: usize) -> std::io::Result<(
 }
 
 pub fn read(file: &mut File, data: &mut [u8], size: usize) -> std::io::Result<()> {
+    read_all(file, data, size)?;
+    validate_checksum(data)?;
+    Ok(())
+}
+
+// Reads and validates the superblock of |file|. A file too short to hold one
+// is not a database file. Reads through an aligned buffer, in case of direct
+// I/O.
+fn read_superblock(file: &mut File, path: &str) -> std::io::Result<Superblock> {
+    let mut buffer = AlignedBuffer::new(1);
+    if file.metadata()?.len() >= PAGE_SIZE as u64 {
+        file.seek(SeekFrom::Start(0))?;
+        read_all(file, buffer.bytes_mut(), PAGE_SIZE)?;
+    }
+    Superblock::deserialize_from(buffer.bytes(), path)
+}
+
+fn write_superblock(file: &mut File, superblock: &Superblock) -> std::io::Result<()> {
+    let mut buffer = AlignedBuffer::new(1);
+    superblock.serialize_to(buffer.bytes_mut());
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(buffer.bytes())?;
+    file.sync_data()
+}
+
+// The offset of |page_id| in the file of its tablespace, after the
+// superblock.
+pub(crate) fn offset(page_id: PageId) -> u64 {
+    (page_no(page_id) as u64 + 1) * (PAGE_SIZE as u64)
+}
+
+fn read_all(file: &mut File, data: &mut [u8], size: usize) -> std::io::Result<()> {
     let mut pos = 0;
     while pos < size {
         let bytes_read = file.read(&mut data[pos..])?;
@@ -119,11 +805,10 @@ pub fn read(file: &mut File, data: &mut [u8], size: usize) -> std::io::Result<()
         }
         pos += bytes_read;
     }
-    validate_checksum(data)?;
     Ok(())
 }
 
-fn update_checksum(data: &mut [u8]) -> std::io::Result<()> {
+pub(crate) fn update_checksum(data: &mut [u8]) -> std::io::Result<()> {
     if data.len() < 8 {
         return Err(invalid_input("Data length should >= 8"));
     }
@@ -132,23 +817,40 @@ fn update_checksum(data: &mut [u8]) -> std::io::Result<()> {
 }
 
 fn validate_checksum(data: &[u8]) -> std::io::Result<()> {
+    match checksum_mismatch(data)? {
+        None => Ok(()),
+        Some((expected, actual)) => Err(invalid_data(&format!(
+            "Data corrupted; expected checksum = {:#018x}, actual checksum = {:#018x}",
+            expected, actual
+        ))),
+    }
+}
+
+// Returns the stored and the computed checksum if they do not match.
+pub fn checksum_mismatch(data: &[u8]) -> std::io::Result<Option<(u64, u64)>> {
     if data.len() < 8 {
         return Err(invalid_input("Data length should >= 8"));
     }
     let checksum = reinterpret::read_u64(data);
     if checksum == 0 {
-        return Ok(()); // The page is empty, it is a success.
+        return Ok(None); // The page is empty, it is a success.
     }
-    match checksum == compute_checksum(&data[8..]) {
-        true => Ok(()),
-        false => Err(invalid_data("Data corrupted")),
+    let computed = compute_checksum(&data[8..]);
+    match checksum == computed {
+        true => Ok(None),
+        false => Ok(Some((checksum, computed))),
     }
 }
 
-fn compute_checksum(data: &[u8]) -> u64 {
-    let mut hasher = DefaultHasher::new();
-    data.hash(&mut hasher);
-    hasher.finish()
+// The format version of a page with the stored |checksum|.
+pub fn format_version(checksum: u64) -> u8 {
+    (checksum >> 32) as u8
+}
+
+// The CRC-32C of |data|, tagged with |PAGE_FORMAT_VERSION|. It is never 0, so
+// it tells written pages from empty ones.
+pub(crate) fn compute_checksum(data: &[u8]) -> u64 {
+    crc32c(data) as u64 | (PAGE_FORMAT_VERSION as u64) << 32
 }
 
 #[cfg(test)]
@@ -170,7 +872,7 @@ mod tests {
         assert!(result.is_ok(), "Failed to create DiskManager");
 
         let mut disk_mgr = result.unwrap();
-        let page_id = disk_mgr.allocate_page();
+        let page_id = disk_mgr.allocate_page().unwrap();
         assert_eq!(0, page_id, "Page should start from 0");
 
         let mut data = String::with_capacity(PAGE_SIZE);
@@ -233,7 +935,7 @@ mod tests {
             assert!(result.is_ok(), "Failed to create DiskManager");
 
             let mut disk_mgr = result.unwrap();
-            page_id = disk_mgr.allocate_page();
+            page_id = disk_mgr.allocate_page().unwrap();
             unsafe {
                 // Write the data to page on disk with specified page ID.
                 let data_write: &mut [u8] = data.as_bytes_mut();
@@ -281,30 +983,30 @@ mod tests {
             assert!(result.is_ok(), "Failed to create DiskManager");
 
             let mut disk_mgr = result.unwrap();
-            assert_eq!(0, disk_mgr.allocate_page());
-            assert_eq!(1, disk_mgr.allocate_page());
-            assert_eq!(2, disk_mgr.allocate_page());
-            assert_eq!(3, disk_mgr.allocate_page());
-            assert_eq!(4, disk_mgr.allocate_page());
-
-            disk_mgr.deallocate_page(2);
-            assert_eq!(2, disk_mgr.allocate_page());
-            disk_mgr.deallocate_page(3);
-            disk_mgr.deallocate_page(3);
-            assert_eq!(3, disk_mgr.allocate_page());
-            assert_eq!(5, disk_mgr.allocate_page());
-
-            disk_mgr.deallocate_page(0);
-            disk_mgr.deallocate_page(4);
-            assert_eq!(0, disk_mgr.allocate_page());
-            assert_eq!(4, disk_mgr.allocate_page());
-            disk_mgr.deallocate_page(1);
-            disk_mgr.deallocate_page(2);
-            disk_mgr.deallocate_page(3);
-            disk_mgr.deallocate_page(5);
-            assert_eq!(1, disk_mgr.allocate_page());
-            assert_eq!(2, disk_mgr.allocate_page());
-            assert_eq!(3, disk_mgr.allocate_page());
+            assert_eq!(0, disk_mgr.allocate_page().unwrap());
+            assert_eq!(1, disk_mgr.allocate_page().unwrap());
+            assert_eq!(2, disk_mgr.allocate_page().unwrap());
+            assert_eq!(3, disk_mgr.allocate_page().unwrap());
+            assert_eq!(4, disk_mgr.allocate_page().unwrap());
+
+            assert!(disk_mgr.deallocate_page(2).is_ok());
+            assert_eq!(2, disk_mgr.allocate_page().unwrap());
+            assert!(disk_mgr.deallocate_page(3).is_ok());
+            assert!(disk_mgr.deallocate_page(3).is_ok());
+            assert_eq!(3, disk_mgr.allocate_page().unwrap());
+            assert_eq!(5, disk_mgr.allocate_page().unwrap());
+
+            assert!(disk_mgr.deallocate_page(0).is_ok());
+            assert!(disk_mgr.deallocate_page(4).is_ok());
+            assert_eq!(0, disk_mgr.allocate_page().unwrap());
+            assert_eq!(4, disk_mgr.allocate_page().unwrap());
+            assert!(disk_mgr.deallocate_page(1).is_ok());
+            assert!(disk_mgr.deallocate_page(2).is_ok());
+            assert!(disk_mgr.deallocate_page(3).is_ok());
+            assert!(disk_mgr.deallocate_page(5).is_ok());
+            assert_eq!(1, disk_mgr.allocate_page().unwrap());
+            assert_eq!(2, disk_mgr.allocate_page().unwrap());
+            assert_eq!(3, disk_mgr.allocate_page().unwrap());
         } // Drops disk_mgr.
 
         {
@@ -312,14 +1014,14 @@ mod tests {
             assert!(result.is_ok(), "Failed to create DiskManager");
 
             let mut disk_mgr = result.unwrap();
-            assert_eq!(5, disk_mgr.allocate_page());
-            assert_eq!(6, disk_mgr.allocate_page());
-            assert_eq!(7, disk_mgr.allocate_page());
-
-            disk_mgr.deallocate_page(0);
-            disk_mgr.deallocate_page(7);
-            disk_mgr.deallocate_page(6);
-            disk_mgr.deallocate_page(5);
+            assert_eq!(5, disk_mgr.allocate_page().unwrap());
+            assert_eq!(6, disk_mgr.allocate_page().unwrap());
+            assert_eq!(7, disk_mgr.allocate_page().unwrap());
+
+            assert!(disk_mgr.deallocate_page(0).is_ok());
+            assert!(disk_mgr.deallocate_page(7).is_ok());
+            assert!(disk_mgr.deallocate_page(6).is_ok());
+            assert!(disk_mgr.deallocate_page(5).is_ok());
         } // Drops disk_mgr.
 
         {
@@ -327,11 +1029,420 @@ mod tests {
             assert!(result.is_ok(), "Failed to create DiskManager");
 
             let mut disk_mgr = result.unwrap();
-            assert_eq!(0, disk_mgr.allocate_page());
-            assert_eq!(5, disk_mgr.allocate_page());
-            assert_eq!(6, disk_mgr.allocate_page());
-            assert_eq!(7, disk_mgr.allocate_page());
-            assert_eq!(8, disk_mgr.allocate_page());
+            assert_eq!(0, disk_mgr.allocate_page().unwrap());
+            assert_eq!(5, disk_mgr.allocate_page().unwrap());
+            assert_eq!(6, disk_mgr.allocate_page().unwrap());
+            assert_eq!(7, disk_mgr.allocate_page().unwrap());
+            assert_eq!(8, disk_mgr.allocate_page().unwrap());
+        } // Drops disk_mgr.
+    }
+
+    #[test]
+    fn read_only() {
+        let file_path = "/tmp/testfile.disk_manager.4.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        // Opening a database that does not exist fails.
+        assert!(DiskManager::new_read_only(file_path).is_err());
+
+        let mut data = [7; PAGE_SIZE];
+        {
+            let mut disk_mgr = DiskManager::new(file_path).unwrap();
+            assert!(!disk_mgr.is_read_only());
+            assert_eq!(0, disk_mgr.allocate_page().unwrap());
+            assert!(disk_mgr.write_page(0, &mut data).is_ok());
+            assert_eq!(1, disk_mgr.allocate_page().unwrap());
         } // Drops disk_mgr.
+
+        {
+            let mut disk_mgr = DiskManager::new_read_only(file_path).unwrap();
+            assert!(disk_mgr.is_read_only());
+
+            let mut buffer = [0; PAGE_SIZE];
+            assert!(disk_mgr.read_page(0, &mut buffer).is_ok());
+            assert_eq!(data[8..], buffer[8..]);
+            // Page 1 is allocated but was never written.
+            assert!(disk_mgr.read_page(1, &mut buffer).is_ok());
+            assert_eq!([0; PAGE_SIZE][..], buffer[..]);
+
+            let kind = std::io::ErrorKind::ReadOnlyFilesystem;
+            assert_eq!(kind, disk_mgr.write_page(0, &mut data).unwrap_err().kind());
+            assert_eq!(kind, disk_mgr.allocate_page().unwrap_err().kind());
+            assert_eq!(kind, disk_mgr.deallocate_page(0).unwrap_err().kind());
+        } // Drops disk_mgr.
+
+        {
+            // Nothing was changed by the read-only manager.
+            let mut disk_mgr = DiskManager::new(file_path).unwrap();
+            assert_eq!(2, disk_mgr.allocate_page().unwrap());
+        } // Drops disk_mgr.
+    }
+
+    #[test]
+    fn corrupted_page() {
+        let file_path = "/tmp/testfile.disk_manager.5.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let dump_path = "/tmp/page_1.hexdump";
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(dump_path);
+
+        let mut data = [7; PAGE_SIZE];
+        let mut disk_mgr = DiskManager::new(file_path).unwrap();
+        disk_mgr.set_quarantine_dir("/tmp");
+        assert_eq!(0, disk_mgr.allocate_page().unwrap());
+        assert_eq!(1, disk_mgr.allocate_page().unwrap());
+        assert!(disk_mgr.write_page(1, &mut data).is_ok());
+
+        // Flip a byte of page 1 behind the manager's back.
+        {
+            let mut file = OpenOptions::new().write(true).open(file_path).unwrap();
+            file.seek(SeekFrom::Start(offset(1) + 100)).unwrap();
+            file.write_all(&[8]).unwrap();
+        }
+
+        let mut buffer = [0; PAGE_SIZE];
+        let err = disk_mgr.read_page(1, &mut buffer).unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        let corrupted = err
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<CorruptedPage>())
+            .unwrap();
+        assert_eq!(1, corrupted.page_id);
+        assert_eq!(reinterpret::read_u64(&data), corrupted.expected);
+        assert_ne!(corrupted.expected, corrupted.actual);
+        assert_eq!(Some(PathBuf::from(dump_path)), corrupted.dump_path);
+        let dump = fs::read_to_string(dump_path).unwrap();
+        assert!(dump.contains("00000060  07 07 07 07 08 07"));
+
+        // Salvage tooling can still read the page.
+        assert!(disk_mgr.read_page_unchecked(1, &mut buffer).is_ok());
+        assert_eq!(8, buffer[100]);
+        assert_eq!(7, buffer[101]);
+
+        // So can any reader once validation is turned off.
+        disk_mgr.set_verify_checksums(false);
+        buffer = [0; PAGE_SIZE];
+        assert!(disk_mgr.read_page(1, &mut buffer).is_ok());
+        assert_eq!(8, buffer[100]);
+        assert_eq!(1, disk_mgr.read_pages(1, &mut [0; 2 * PAGE_SIZE]).unwrap());
+    }
+
+    #[test]
+    fn checksum_format() {
+        let mut data = [7; PAGE_SIZE];
+        update_checksum(&mut data).unwrap();
+        let checksum = reinterpret::read_u64(&data);
+        assert_eq!(crc32c(&data[8..]), checksum as u32);
+        assert_eq!(PAGE_FORMAT_VERSION, format_version(checksum));
+        assert_eq!(None, checksum_mismatch(&data).unwrap());
+
+        // A page of another format fails validation, even with the same CRC.
+        reinterpret::write_u64(&mut data, checksum as u32 as u64);
+        let (expected, actual) = checksum_mismatch(&data).unwrap().unwrap();
+        assert_eq!(checksum, actual);
+        let corrupted = CorruptedPage {
+            page_id: 1,
+            expected,
+            actual,
+            dump_path: None,
+        };
+        assert!(corrupted
+            .to_string()
+            .ends_with(", unsupported format version = 0"));
+    }
+
+    #[test]
+    fn file_lock() {
+        let file_path = "/tmp/testfile.disk_manager.6.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        {
+            let mut disk_mgr = DiskManager::new(file_path).unwrap();
+            assert_eq!(0, disk_mgr.allocate_page().unwrap());
+            assert!(disk_mgr.sync().is_ok());
+
+            // The database is locked while opened read-write.
+            for result in [DiskManager::new(file_path), DiskManager::new_read_only(file_path)] {
+                let err = result.err().unwrap();
+                assert_eq!(ErrorKind::WouldBlock, err.kind());
+                let locked = err.get_ref().unwrap().downcast_ref::<DatabaseLocked>();
+                assert_eq!(Path::new(file_path), locked.unwrap().path);
+            }
+        } // Drops disk_mgr, which releases the lock.
+
+        {
+            // Read-only managers share the lock.
+            let first = DiskManager::new_read_only(file_path).unwrap();
+            let second = DiskManager::new_read_only(file_path).unwrap();
+            assert!(second.spaces[0].selector.is_used(0));
+            assert!(DiskManager::new(file_path).is_err());
+            drop(first);
+        } // Drops the read-only managers.
+
+        assert!(DiskManager::new(file_path).is_ok());
+    }
+
+    #[test]
+    fn batched_io() {
+        let file_path = "/tmp/testfile.disk_manager.7.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut disk_mgr = DiskManager::new(file_path).unwrap();
+        for _ in 0..4 {
+            disk_mgr.allocate_page().unwrap();
+        }
+        let mut pages: Vec<Vec<u8>> = (0..3).map(|i| vec![i as u8 + 1; PAGE_SIZE]).collect();
+        let mut slices: Vec<&mut [u8]> = pages.iter_mut().map(|page| &mut page[..]).collect();
+        assert!(disk_mgr.write_pages(1, &mut slices).is_ok());
+        assert!(disk_mgr.sync_data().is_ok());
+
+        // Page 0 was never written and reads as zeros, and no page lies past
+        // page 3.
+        let mut buffer = vec![0xff; 5 * PAGE_SIZE];
+        assert_eq!(4, disk_mgr.read_pages(0, &mut buffer).unwrap());
+        assert!(buffer[..PAGE_SIZE].iter().all(|&byte| byte == 0));
+        assert_eq!(3, disk_mgr.read_pages(1, &mut buffer).unwrap());
+        for (i, page) in buffer.chunks(PAGE_SIZE).take(3).enumerate() {
+            assert_eq!(pages[i][8..], page[8..]);
+        }
+
+        // Page 3 is not allocated anymore, and page 2 is corrupted.
+        assert!(disk_mgr.deallocate_page(3).is_ok());
+        assert_eq!(2, disk_mgr.read_pages(1, &mut buffer).unwrap());
+        let mut corrupted = buffer[PAGE_SIZE..(2 * PAGE_SIZE)].to_vec();
+        corrupted[100] ^= 0xff;
+        let file = &mut disk_mgr.spaces[0].file;
+        file.seek(SeekFrom::Start(offset(2))).unwrap();
+        file.write_all(&corrupted).unwrap();
+        assert_eq!(1, disk_mgr.read_pages(1, &mut buffer).unwrap());
+    }
+
+    #[test]
+    fn direct_io() {
+        let file_path = "/tmp/testfile.disk_manager.8.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        // Unaligned page buffers work whether or not direct I/O is in effect.
+        let mut pages: Vec<Vec<u8>> = (0..3).map(|i| vec![i as u8 + 1; PAGE_SIZE + 1]).collect();
+        {
+            let mut disk_mgr = DiskManager::with_direct_io(file_path).unwrap();
+            for _ in 0..4 {
+                disk_mgr.allocate_page().unwrap();
+            }
+            let mut slices: Vec<&mut [u8]> = pages.iter_mut().map(|page| &mut page[1..]).collect();
+            assert!(disk_mgr.write_pages(0, &mut slices).is_ok());
+            let mut page = vec![4; PAGE_SIZE + 1];
+            assert!(disk_mgr.write_page(3, &mut page[1..]).is_ok());
+            assert!(disk_mgr.sync().is_ok());
+
+            let mut buffer = vec![0; 4 * PAGE_SIZE + 1];
+            assert_eq!(4, disk_mgr.read_pages(0, &mut buffer[1..]).unwrap());
+            assert!(disk_mgr.read_page(3, &mut buffer[1..=PAGE_SIZE]).is_ok());
+            assert_eq!(page[9..], buffer[9..(PAGE_SIZE + 1)]);
+        }
+
+        let mut disk_mgr = DiskManager::new(file_path).unwrap();
+        assert!(!disk_mgr.is_direct_io());
+        let mut buffer = vec![0; PAGE_SIZE];
+        assert!(disk_mgr.read_page(1, &mut buffer).is_ok());
+        assert_eq!(pages[1][9..], buffer[8..]);
+    }
+
+    #[test]
+    fn compression() {
+        let file_path = "/tmp/testfile.disk_manager.10.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut pages: Vec<Vec<u8>> = (0..3).map(|i| vec![i as u8 + 1; PAGE_SIZE]).collect();
+        // An incompressible page is written as is.
+        let mut state = 1u64;
+        for byte in pages[2].iter_mut() {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            *byte = (state >> 33) as u8;
+        }
+        {
+            let mut disk_mgr = DiskManager::new(file_path).unwrap();
+            assert!(!disk_mgr.is_compression_enabled());
+            disk_mgr.set_compression(true).unwrap();
+            for _ in 0..4 {
+                disk_mgr.allocate_page().unwrap();
+            }
+            let mut slices: Vec<&mut [u8]> = pages.iter_mut().map(|page| &mut page[..]).collect();
+            assert!(disk_mgr.write_pages(1, &mut slices[..2]).is_ok());
+            assert!(disk_mgr.write_page(3, slices[2]).is_ok());
+            assert!(disk_mgr.sync().is_ok());
+
+            // Compressed pages only take their first sector.
+            let mut raw = vec![0; PAGE_SIZE];
+            assert!(disk_mgr.read_page_inl(1, &mut raw, false).is_ok());
+            assert_eq!(pages[0], raw);
+            let space = &mut disk_mgr.spaces[0];
+            space.file.seek(SeekFrom::Start(offset(1))).unwrap();
+            read_all(&mut space.file, &mut raw, PAGE_SIZE).unwrap();
+            assert_ne!(pages[0], raw);
+            assert_eq!(vec![0; PAGE_SIZE - SECTOR_SIZE], raw[SECTOR_SIZE..]);
+        }
+
+        // Compressed pages are read without compression enabled.
+        let mut disk_mgr = DiskManager::new(file_path).unwrap();
+        let mut buffer = vec![0; 4 * PAGE_SIZE];
+        assert_eq!(4, disk_mgr.read_pages(0, &mut buffer).unwrap());
+        for (idx, page) in pages.iter().enumerate() {
+            let start = (idx + 1) * PAGE_SIZE;
+            assert_eq!(page[..], buffer[start..(start + PAGE_SIZE)]);
+            let mut data = vec![0; PAGE_SIZE];
+            assert!(disk_mgr.read_page(idx as PageId + 1, &mut data).is_ok());
+            assert_eq!(page[..], data[..]);
+        }
+
+        // Rewriting a page uncompressed overwrites the whole slot.
+        pages[0][100] = 0;
+        assert!(disk_mgr.write_page(1, &mut pages[0]).is_ok());
+        let mut data = vec![0; PAGE_SIZE];
+        assert!(disk_mgr.read_page(1, &mut data).is_ok());
+        assert_eq!(pages[0], data);
+    }
+
+    #[test]
+    fn superblock() {
+        let file_path = "/tmp/testfile.disk_manager.11.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        // Opening a file which is not a database fails, without creating a
+        // bitmap file.
+        fs::write(file_path, "name,score\nalice,3\n").unwrap();
+        let err = DiskManager::new(file_path).err().unwrap();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        assert_eq!(
+            format!("Not a database file; path = {}", file_path),
+            err.to_string()
+        );
+        assert!(!Path::new(&bitmap_path).exists());
+        fs::remove_file(file_path).unwrap();
+
+        {
+            let mut disk_mgr = DiskManager::new(file_path).unwrap();
+            assert_eq!(Superblock::default(), *disk_mgr.superblock());
+            assert!(disk_mgr.set_compression(true).is_ok());
+        }
+
+        // The superblock persists the features in use.
+        let disk_mgr = DiskManager::new_read_only(file_path).unwrap();
+        assert_eq!(FEATURE_COMPRESSION, disk_mgr.superblock().features);
+        assert_eq!(0, disk_mgr.file_page_count().unwrap());
+    }
+
+    #[test]
+    fn allocate_pages() {
+        let file_path = "/tmp/testfile.disk_manager.12.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        {
+            let mut disk_mgr = DiskManager::new(file_path).unwrap();
+            assert_eq!(0..3, disk_mgr.allocate_pages(3).unwrap());
+            assert_eq!(3..5, disk_mgr.allocate_pages(2).unwrap());
+            assert!(disk_mgr.deallocate_page(1).is_ok());
+            assert!(disk_mgr.deallocate_page(3).is_ok());
+
+            // Single pages fill holes, runs go where they fit.
+            assert_eq!(5..7, disk_mgr.allocate_pages(2).unwrap());
+            assert_eq!(1..2, disk_mgr.allocate_pages(1).unwrap());
+            assert!(disk_mgr.allocate_pages(MAX_PAGE_NO as usize + 2).is_err());
+            assert!(disk_mgr.sync().is_ok());
+        } // Drops disk_mgr.
+
+        // The allocation persists through the bitmap file.
+        let mut disk_mgr = DiskManager::new(file_path).unwrap();
+        assert!(!disk_mgr.is_allocated(3));
+        assert!(disk_mgr.is_allocated(6));
+        assert_eq!(7..71, disk_mgr.allocate_pages(64).unwrap());
+        assert_eq!(3, disk_mgr.allocate_page().unwrap());
+    }
+
+    #[test]
+    fn tablespaces() {
+        let file_path = "/tmp/testfile.disk_manager.9.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let registry_path = file_path.to_string() + TABLESPACES_FILE_SUFFIX;
+        let space_path = "/tmp/testfile.disk_manager.9.space.db";
+        let space_bitmap_path = space_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(&registry_path);
+        file_deleter.push(space_path);
+        file_deleter.push(&space_bitmap_path);
+
+        let page_id;
+        {
+            let mut disk_mgr = DiskManager::new(file_path).unwrap();
+            assert_eq!(1, disk_mgr.tablespace_count());
+            let file_id = disk_mgr.add_tablespace(space_path).unwrap();
+            assert_eq!(1, file_id);
+            let err = disk_mgr.add_tablespace(space_path).err().unwrap();
+            assert_eq!(ErrorKind::AlreadyExists, err.kind());
+            assert_eq!(ErrorKind::NotFound, disk_mgr.allocate_page_in(2).err().unwrap().kind());
+
+            // Page numbers are allocated per tablespace.
+            assert_eq!(0, disk_mgr.allocate_page().unwrap());
+            page_id = disk_mgr.allocate_page_in(file_id).unwrap();
+            assert_eq!(make_page_id(file_id, 0), page_id);
+            assert!(disk_mgr.is_allocated(page_id));
+            assert!(!disk_mgr.is_allocated(make_page_id(2, 0)));
+            let mut data = vec![7; PAGE_SIZE];
+            assert!(disk_mgr.write_page(page_id, &mut data).is_ok());
+            assert!(disk_mgr.write_page(make_page_id(2, 0), &mut data).is_err());
+            assert!(disk_mgr.sync().is_ok());
+            assert_eq!(0, disk_mgr.file_page_count().unwrap());
+        }
+
+        // Tablespaces are reopened with the database.
+        let mut disk_mgr = DiskManager::new_read_only(file_path).unwrap();
+        assert_eq!(2, disk_mgr.tablespace_count());
+        let mut buffer = vec![0; 2 * PAGE_SIZE];
+        assert_eq!(1, disk_mgr.read_pages(page_id, &mut buffer).unwrap());
+        assert_eq!(7, buffer[PAGE_SIZE - 1]);
+        assert!(disk_mgr.read_page(make_page_id(1, 1), &mut buffer).is_err());
     }
 }
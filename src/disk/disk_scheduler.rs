@@ -0,0 +1,289 @@
+// Functionality: Asynchronous disk I/O. |AsyncDiskScheduler| accepts page read
+// and write requests on a channel and executes them on a pool of I/O threads,
+// so that the caller can keep computing while the disk works. Every request
+// returns a |DiskFuture|, which completes once the request is done.
+//
+// Workers use positional I/O on duplicates of the tablespace file handles, so
+// that requests to different pages run in parallel. Tablespaces added after
+// the scheduler started are not served. Requests to the same page
+// are not ordered; the caller needs to wait for a write before reading or
+// writing the page again. Like |DiskManager::read_page|, reads verify the
+// page checksum; unlike |DiskManager|, the scheduler does not check that the
+// page is allocated. Reads decompress compressed pages, but writes are never
+// compressed. Pages go through aligned buffers, in case the files were opened
+// for direct I/O.
+
+use crate::common::config::PageId;
+use crate::common::config::PAGE_SIZE;
+use crate::common::error::*;
+use crate::disk::aligned_buffer::AlignedBuffer;
+use crate::disk::disk_manager::checksum_mismatch;
+use crate::disk::disk_manager::offset;
+use crate::disk::disk_manager::update_checksum;
+use crate::disk::disk_manager::CorruptedPage;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::page_compression::expand_page;
+use crate::disk::tablespace::file_id;
+use log::info;
+use std::fs::File;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::ops::Drop;
+use std::os::unix::fs::FileExt;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::sync::mpsc::TryRecvError;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::thread::JoinHandle;
+
+pub type PageData = Box<[u8; PAGE_SIZE]>;
+
+enum Request {
+    Read(PageId, Sender<std::io::Result<PageData>>),
+    Write(PageId, PageData, Sender<std::io::Result<()>>),
+}
+
+// The result of a scheduled request.
+pub struct DiskFuture<T> {
+    receiver: Receiver<std::io::Result<T>>,
+}
+
+impl<T> DiskFuture<T> {
+    // Blocks until the request is done.
+    pub fn wait(self) -> std::io::Result<T> {
+        self.receiver
+            .recv()
+            .unwrap_or_else(|_| Err(Error::other("Disk scheduler stopped")))
+    }
+
+    // Returns the result if the request is done, or gives the future back.
+    pub fn try_wait(self) -> Result<std::io::Result<T>, Self> {
+        match self.receiver.try_recv() {
+            Ok(result) => Ok(result),
+            Err(TryRecvError::Empty) => Err(self),
+            Err(TryRecvError::Disconnected) => Ok(Err(Error::other("Disk scheduler stopped"))),
+        }
+    }
+}
+
+pub struct AsyncDiskScheduler {
+    // Dropped first on destruction, which stops the workers once the pending
+    // requests are done.
+    sender: Option<Sender<Request>>,
+    workers: Vec<JoinHandle<()>>,
+    read_only: bool,
+}
+
+impl Drop for AsyncDiskScheduler {
+    fn drop(&mut self) {
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            // Workers never panic.
+            let _ = worker.join();
+        }
+    }
+}
+
+impl AsyncDiskScheduler {
+    // Starts |num_threads| I/O threads serving the tablespace files of
+    // |disk_mgr|. Returns |InvalidInput| if |num_threads| is 0.
+    pub fn new(disk_mgr: &DiskManager, num_threads: usize) -> std::io::Result<Self> {
+        if num_threads == 0 {
+            return Err(invalid_input("Disk scheduler needs at least one thread"));
+        }
+        let files = Arc::new(disk_mgr.try_clone_files()?);
+        let (sender, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..num_threads)
+            .map(|_| {
+                let files = files.clone();
+                let receiver = receiver.clone();
+                thread::spawn(move || loop {
+                    // The lock is released before the request is executed.
+                    let request = receiver.lock().unwrap().recv();
+                    match request {
+                        Ok(request) => execute(&files, request),
+                        Err(_) => return,
+                    }
+                })
+            })
+            .collect();
+        Ok(AsyncDiskScheduler {
+            sender: Some(sender),
+            workers,
+            read_only: disk_mgr.is_read_only(),
+        })
+    }
+
+    // Reads the page with specified |page_id|. Returns a |CorruptedPage|
+    // error if the checksum does not match. A page past the end of the file
+    // reads as zeros.
+    pub fn schedule_read(&self, page_id: PageId) -> DiskFuture<PageData> {
+        info!("Schedule read; page_id = {}", page_id);
+        let (sender, receiver) = mpsc::channel();
+        self.send(Request::Read(page_id, sender.clone()), sender);
+        DiskFuture { receiver }
+    }
+
+    // Writes |data| to the page with specified |page_id|, and syncs the file.
+    // Returns a |ReadOnlyFilesystem| error for a read-only database.
+    pub fn schedule_write(&self, page_id: PageId, data: PageData) -> DiskFuture<()> {
+        info!("Schedule write; page_id = {}", page_id);
+        let (sender, receiver) = mpsc::channel();
+        if self.read_only {
+            let _ = sender.send(Err(read_only("Database is opened read-only")));
+        } else {
+            self.send(Request::Write(page_id, data, sender.clone()), sender);
+        }
+        DiskFuture { receiver }
+    }
+
+    // Completes the request with an error if the workers are gone.
+    fn send<T>(&self, request: Request, sender: Sender<std::io::Result<T>>) {
+        let sent = self.sender.as_ref().map(|s| s.send(request));
+        if !matches!(sent, Some(Ok(()))) {
+            let _ = sender.send(Err(Error::other("Disk scheduler stopped")));
+        }
+    }
+}
+
+// A dropped future means nobody waits for the result, so sending errors are
+// ignored.
+fn execute(files: &[File], request: Request) {
+    match request {
+        Request::Read(page_id, sender) => {
+            let _ = sender.send(file(files, page_id).and_then(|file| read(file, page_id)));
+        }
+        Request::Write(page_id, data, sender) => {
+            let _ = sender.send(file(files, page_id).and_then(|file| write(file, page_id, data)));
+        }
+    }
+}
+
+// Returns the file of the tablespace of |page_id|.
+fn file(files: &[File], page_id: PageId) -> std::io::Result<&File> {
+    match page_id < 0 {
+        true => None,
+        false => files.get(file_id(page_id) as usize),
+    }
+    .ok_or_else(|| invalid_input(&format!("Tablespace not found; page_id = {}", page_id)))
+}
+
+fn read(file: &File, page_id: PageId) -> std::io::Result<PageData> {
+    let mut buffer = AlignedBuffer::new(1);
+    let offset = offset(page_id);
+    let mut pos = 0;
+    while pos < PAGE_SIZE {
+        let bytes_read = file.read_at(&mut buffer.bytes_mut()[pos..], offset + pos as u64)?;
+        if bytes_read == 0 {
+            // Past the end of the file, the page was never written.
+            return Ok(Box::new([0; PAGE_SIZE]));
+        }
+        pos += bytes_read;
+    }
+    let mut data = Box::new([0; PAGE_SIZE]);
+    data.copy_from_slice(buffer.bytes());
+    expand_page(&mut data[..])?;
+    if let Some((expected, actual)) = checksum_mismatch(&data[..])? {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            CorruptedPage {
+                page_id,
+                expected,
+                actual,
+                dump_path: None,
+            },
+        ));
+    }
+    Ok(data)
+}
+
+fn write(file: &File, page_id: PageId, mut data: PageData) -> std::io::Result<()> {
+    update_checksum(&mut data[..])?;
+    let buffer = AlignedBuffer::from_pages(&[&mut data[..]]);
+    let offset = offset(page_id);
+    file.write_all_at(buffer.bytes(), offset)?;
+    file.sync_data()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::disk_manager::BITMAP_FILE_SUFFIX;
+    use crate::testing::file_deleter::FileDeleter;
+
+    #[test]
+    fn read_and_write() {
+        let file_path = "/tmp/testfile.disk_scheduler.1.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut disk_mgr = DiskManager::new(file_path).unwrap();
+        assert!(AsyncDiskScheduler::new(&disk_mgr, 0).is_err());
+        let scheduler = AsyncDiskScheduler::new(&disk_mgr, 4).unwrap();
+
+        // Writes run in parallel, and are visible to the disk manager.
+        let writes: Vec<DiskFuture<()>> = (0..16)
+            .map(|id| scheduler.schedule_write(id, Box::new([id as u8 + 1; PAGE_SIZE])))
+            .collect();
+        for write in writes {
+            assert!(write.wait().is_ok());
+        }
+        let mut data = [0; PAGE_SIZE];
+        disk_mgr.allocate_page().unwrap();
+        assert!(disk_mgr.read_page(0, &mut data).is_ok());
+        assert_eq!(1, data[PAGE_SIZE - 1]);
+
+        let reads: Vec<DiskFuture<PageData>> =
+            (0..17).map(|id| scheduler.schedule_read(id)).collect();
+        for (id, read) in reads.into_iter().enumerate() {
+            let expected = if id < 16 { id as u8 + 1 } else { 0 };
+            assert_eq!(expected, read.wait().unwrap()[PAGE_SIZE - 1]);
+        }
+
+        // A corrupted page fails checksum validation.
+        data[100] ^= 0xff;
+        disk_mgr.try_clone_files().unwrap()[0]
+            .write_all_at(&data, offset(0))
+            .unwrap();
+        let mut read = scheduler.schedule_read(0);
+        let result = loop {
+            match read.try_wait() {
+                Ok(result) => break result,
+                Err(future) => read = future,
+            }
+        };
+        let err = result.err().unwrap();
+        let corrupted = err
+            .get_ref()
+            .unwrap()
+            .downcast_ref::<CorruptedPage>()
+            .unwrap();
+        assert_eq!(0, corrupted.page_id);
+    }
+
+    #[test]
+    fn read_only() {
+        let file_path = "/tmp/testfile.disk_scheduler.2.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        drop(DiskManager::new(file_path).unwrap());
+        let disk_mgr = DiskManager::new_read_only(file_path).unwrap();
+        let scheduler = AsyncDiskScheduler::new(&disk_mgr, 1).unwrap();
+        let result = scheduler.schedule_write(0, Box::new([0; PAGE_SIZE])).wait();
+        assert_eq!(ErrorKind::ReadOnlyFilesystem, result.err().unwrap().kind());
+        assert!(scheduler.schedule_read(0).wait().is_ok());
+    }
+}
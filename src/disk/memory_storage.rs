@@ -0,0 +1,209 @@
+// Functionality: A |StorageBackend| keeping pages in memory, so that tests and
+// ephemeral databases do not touch the filesystem. The pages are lost when it
+// is dropped.
+//
+// It has a single tablespace, |PRIMARY_FILE_ID|, and stores the pages as is,
+// without checksums or compression. Allocation takes the first vacant run of
+// pages, like |DiskManager|.
+
+use crate::common::config::PageId;
+use crate::common::config::PAGE_SIZE;
+use crate::common::error::*;
+use crate::disk::storage_backend::StorageBackend;
+use crate::disk::tablespace::*;
+use std::ops::Range;
+
+#[derive(Default)]
+pub struct MemoryStorage {
+    // The pages, indexed by page number; None for pages not allocated.
+    pages: Vec<Option<Box<[u8]>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The number of allocated pages.
+    pub fn page_count(&self) -> usize {
+        self.pages.iter().filter(|page| page.is_some()).count()
+    }
+
+    fn page(&self, page_id: PageId) -> std::io::Result<&[u8]> {
+        match self
+            .slot(page_id)
+            .and_then(|idx| self.pages[idx].as_deref())
+        {
+            Some(page) => Ok(page),
+            None => Err(not_allocated(page_id)),
+        }
+    }
+
+    fn page_mut(&mut self, page_id: PageId) -> std::io::Result<&mut [u8]> {
+        match self.slot(page_id) {
+            Some(idx) if self.pages[idx].is_some() => Ok(self.pages[idx].as_deref_mut().unwrap()),
+            _ => Err(not_allocated(page_id)),
+        }
+    }
+
+    // The index of the page |page_id| in |pages|, if it is in range.
+    fn slot(&self, page_id: PageId) -> Option<usize> {
+        match page_id >= 0 && file_id(page_id) == PRIMARY_FILE_ID {
+            true => Some(page_no(page_id) as usize).filter(|&idx| idx < self.pages.len()),
+            false => None,
+        }
+    }
+}
+
+impl StorageBackend for MemoryStorage {
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    fn write_page(&mut self, page_id: PageId, data: &mut [u8]) -> std::io::Result<()> {
+        self.page_mut(page_id)?.copy_from_slice(&data[..PAGE_SIZE]);
+        Ok(())
+    }
+
+    fn write_pages(
+        &mut self,
+        first_page_id: PageId,
+        pages: &mut [&mut [u8]],
+    ) -> std::io::Result<()> {
+        for (page_id, page) in (first_page_id..).zip(pages.iter_mut()) {
+            self.write_page(page_id, page)?;
+        }
+        Ok(())
+    }
+
+    fn sync_data(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn read_page(&mut self, page_id: PageId, data: &mut [u8]) -> std::io::Result<()> {
+        data[..PAGE_SIZE].copy_from_slice(self.page(page_id)?);
+        Ok(())
+    }
+
+    fn read_pages(&mut self, first_page_id: PageId, data: &mut [u8]) -> std::io::Result<usize> {
+        let mut count = 0;
+        for (page_id, dst) in (first_page_id..).zip(data.chunks_exact_mut(PAGE_SIZE)) {
+            match self.page(page_id) {
+                Ok(page) => dst.copy_from_slice(page),
+                Err(_) => break,
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn allocate_pages_in(
+        &mut self,
+        file_id: FileId,
+        count: usize,
+    ) -> std::io::Result<Range<PageId>> {
+        if file_id != PRIMARY_FILE_ID {
+            return Err(not_found("Tablespace not found"));
+        }
+        // The first run of |count| vacant pages, possibly past the end.
+        let mut start = 0;
+        for (idx, page) in self.pages.iter().enumerate() {
+            if idx - start == count {
+                break;
+            }
+            if page.is_some() {
+                start = idx + 1;
+            }
+        }
+        if start + count > MAX_PAGE_NO as usize + 1 {
+            return Err(invalid_input("Tablespace is full"));
+        }
+        if self.pages.len() < start + count {
+            self.pages.resize(start + count, None);
+        }
+        for page in self.pages[start..(start + count)].iter_mut() {
+            *page = Some(vec![0; PAGE_SIZE].into_boxed_slice());
+        }
+        let first_page_id = make_page_id(file_id, start as u32);
+        Ok(first_page_id..(first_page_id + count as PageId))
+    }
+
+    fn reserve_page(&mut self, page_id: PageId) -> std::io::Result<()> {
+        if page_id < 0 || file_id(page_id) != PRIMARY_FILE_ID {
+            return Err(not_found("Tablespace not found"));
+        }
+        let idx = page_no(page_id) as usize;
+        if self.pages.len() <= idx {
+            self.pages.resize(idx + 1, None);
+        }
+        if self.pages[idx].is_none() {
+            self.pages[idx] = Some(vec![0; PAGE_SIZE].into_boxed_slice());
+        }
+        Ok(())
+    }
+
+    fn deallocate_page(&mut self, page_id: PageId) -> std::io::Result<()> {
+        if let Some(idx) = self.slot(page_id) {
+            self.pages[idx] = None;
+        }
+        Ok(())
+    }
+
+    fn is_allocated(&self, page_id: PageId) -> bool {
+        self.page(page_id).is_ok()
+    }
+
+    fn sync(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn not_allocated(page_id: PageId) -> std::io::Error {
+    invalid_input(&format!("The page is not allocated; page_id = {}", page_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocate_read_and_write() {
+        let mut storage = MemoryStorage::new();
+        assert_eq!(0..3, storage.allocate_pages_in(PRIMARY_FILE_ID, 3).unwrap());
+        assert_eq!(3, storage.allocate_page_in(PRIMARY_FILE_ID).unwrap());
+        assert!(storage.allocate_page_in(1).is_err());
+        assert!(storage.deallocate_page(1).is_ok());
+        assert!(!storage.is_allocated(1));
+        assert_eq!(3, storage.page_count());
+
+        // Single pages fill holes, runs go where they fit.
+        assert_eq!(4..6, storage.allocate_pages_in(PRIMARY_FILE_ID, 2).unwrap());
+        assert_eq!(1, storage.allocate_page_in(PRIMARY_FILE_ID).unwrap());
+        assert!(storage.reserve_page(8).is_ok());
+        assert!(storage.is_allocated(8));
+        assert!(!storage.is_allocated(7));
+        assert_eq!(6..7, storage.allocate_pages_in(PRIMARY_FILE_ID, 1).unwrap());
+
+        let mut data = vec![0; PAGE_SIZE * 4];
+        data[PAGE_SIZE - 1] = 7;
+        assert!(storage.write_page(2, &mut data[..PAGE_SIZE]).is_ok());
+        let (first, rest) = data.split_at_mut(PAGE_SIZE);
+        first[0] = 1;
+        rest[0] = 2;
+        assert!(storage
+            .write_pages(4, &mut [first, &mut rest[..PAGE_SIZE]])
+            .is_ok());
+        assert!(storage.write_page(7, &mut data).is_err());
+
+        let mut page = vec![0; PAGE_SIZE];
+        assert!(storage.read_page(2, &mut page).is_ok());
+        assert_eq!((0, 7), (page[0], page[PAGE_SIZE - 1]));
+        assert!(storage.read_page(7, &mut page).is_err());
+        assert!(storage.read_page(-1, &mut page).is_err());
+
+        // Reading stops before page 7, which is not allocated.
+        assert_eq!(3, storage.read_pages(4, &mut data).unwrap());
+        assert_eq!((1, 2, 0), (data[0], data[PAGE_SIZE], data[2 * PAGE_SIZE]));
+        assert_eq!(0, storage.read_pages(7, &mut data).unwrap());
+    }
+}
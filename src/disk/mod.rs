@@ -1,4 +1,11 @@
 pub mod disk_manager;
+pub mod disk_scheduler;
+pub mod memory_storage;
+pub mod storage_backend;
+pub mod superblock;
+pub mod tablespace;
 
+mod aligned_buffer;
 mod bitmap;
+mod page_compression;
 mod selector;
@@ -0,0 +1,112 @@
+// Functionality: Transparent page compression, see
+// |DiskManager::set_compression|. A compressed page is stored in the slot of
+// the page, with a compression header after the checksum:
+//  ---------------------------------------------------------------
+// | Checksum (8) | Magic (4) | Length (4) | Compressed bytes | ...
+//  ---------------------------------------------------------------
+// The compressed bytes are the page content after the checksum, compressed by
+// |compression::compress|, and the checksum covers the header and the
+// compressed bytes. Only the image rounded up to |SECTOR_SIZE| is written, so
+// the tail of the slot holds stale bytes, or a hole of a sparse file.
+//
+// Reads detect compressed pages whatever the setting, so that compression can
+// be turned on and off for an existing database. A page whose bytes happen to
+// start with the magic is still read as is, since the checksum of the
+// compressed image does not match.
+
+use crate::common::compression;
+use crate::common::config::CHECKSUM_SIZE;
+use crate::common::config::PAGE_SIZE;
+use crate::common::error::*;
+use crate::common::reinterpret;
+use crate::disk::disk_manager::compute_checksum;
+use crate::disk::disk_manager::update_checksum;
+
+// The granularity of compressed writes.
+pub const SECTOR_SIZE: usize = 512;
+
+const MAGIC: u32 = 0x7a70_6763;
+const MAGIC_OFFSET: usize = CHECKSUM_SIZE;
+const LENGTH_OFFSET: usize = CHECKSUM_SIZE + 4;
+const HEADER_SIZE: usize = CHECKSUM_SIZE + 8;
+
+// Returns the compressed image of the page |data| padded to |PAGE_SIZE|, and
+// the number of bytes to write, or None if compression does not save a
+// sector.
+pub fn compress_page(data: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let compressed = compression::compress(&data[CHECKSUM_SIZE..PAGE_SIZE]);
+    let len = HEADER_SIZE + compressed.len();
+    let size = len.div_ceil(SECTOR_SIZE) * SECTOR_SIZE;
+    if size >= PAGE_SIZE {
+        return None;
+    }
+    let mut image = vec![0; PAGE_SIZE];
+    reinterpret::write_u32(&mut image[MAGIC_OFFSET..], MAGIC);
+    reinterpret::write_u32(&mut image[LENGTH_OFFSET..], compressed.len() as u32);
+    image[HEADER_SIZE..len].copy_from_slice(&compressed);
+    let checksum = compute_checksum(&image[CHECKSUM_SIZE..len]);
+    reinterpret::write_u64(&mut image, checksum);
+    Some((image, size))
+}
+
+// Decompresses the page |data| in place if it holds a compressed image, and
+// updates its checksum to that of the decompressed page, so that it validates
+// as a page written uncompressed. Returns |InvalidData| if the compressed
+// bytes are corrupted though their checksum matches.
+pub fn expand_page(data: &mut [u8]) -> std::io::Result<()> {
+    if reinterpret::read_u32(&data[MAGIC_OFFSET..]) != MAGIC {
+        return Ok(());
+    }
+    let len = HEADER_SIZE + reinterpret::read_u32(&data[LENGTH_OFFSET..]) as usize;
+    if len > PAGE_SIZE {
+        return Ok(());
+    }
+    if reinterpret::read_u64(data) != compute_checksum(&data[CHECKSUM_SIZE..len]) {
+        return Ok(());
+    }
+    let mut page = vec![0; PAGE_SIZE - CHECKSUM_SIZE];
+    let size = compression::decompress(&data[HEADER_SIZE..len], &mut page)?;
+    if size != page.len() {
+        return Err(invalid_data("Compressed page has a wrong length"));
+    }
+    data[CHECKSUM_SIZE..PAGE_SIZE].copy_from_slice(&page);
+    update_checksum(&mut data[..PAGE_SIZE])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::disk_manager::checksum_mismatch;
+
+    #[test]
+    fn compress_and_expand() {
+        let mut page = vec![0; PAGE_SIZE];
+        for (idx, byte) in page.iter_mut().enumerate().skip(CHECKSUM_SIZE) {
+            *byte = (idx % 7) as u8;
+        }
+        update_checksum(&mut page).unwrap();
+
+        let (mut image, size) = compress_page(&page).unwrap();
+        assert_eq!(0, size % SECTOR_SIZE);
+        assert!(size < PAGE_SIZE);
+        assert_eq!(MAGIC, reinterpret::read_u32(&image[MAGIC_OFFSET..]));
+        expand_page(&mut image).unwrap();
+        assert_eq!(page, image);
+        assert_eq!(None, checksum_mismatch(&image).unwrap());
+
+        // Uncompressed pages are left as is, even if they start with the magic.
+        let mut copy = page.clone();
+        reinterpret::write_u32(&mut copy[MAGIC_OFFSET..], MAGIC);
+        let expected = copy.clone();
+        expand_page(&mut copy).unwrap();
+        assert_eq!(expected, copy);
+
+        // Incompressible pages are not compressed.
+        let mut state = 1u64;
+        for byte in page.iter_mut() {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            *byte = (state >> 33) as u8;
+        }
+        assert!(compress_page(&page).is_none());
+    }
+}
@@ -10,8 +10,16 @@ pub struct Selector {
 
 impl Selector {
     pub fn new(path: &str) -> std::io::Result<Self> {
+        Self::from_bitmap(Bitmap::new(path)?)
+    }
+
+    pub fn new_read_only(path: &str) -> std::io::Result<Self> {
+        Self::from_bitmap(Bitmap::new_read_only(path)?)
+    }
+
+    fn from_bitmap(bitmap: Bitmap) -> std::io::Result<Self> {
         Ok(Selector {
-            bitmap: Bitmap::new(path)?,
+            bitmap,
             free: BTreeSet::new(),
         })
         .and_then(|mut selector| {
@@ -38,6 +46,23 @@ impl Selector {
         }
     }
 
+    // The first index of |len| consecutive unused indices. The run may extend
+    // past the capacity of the bitmap.
+    pub fn vacant_run(&self, len: usize) -> usize {
+        let mut start = self.vacant();
+        let mut idx = start;
+        while idx < start + len && idx < self.capacity() {
+            match self.is_used(idx) {
+                true => {
+                    start = self.vacant_from(idx + 1);
+                    idx = start;
+                }
+                false => idx += 1,
+            }
+        }
+        start
+    }
+
     pub fn set_used(&mut self, idx: usize) {
         let prev = self.bitmap.len();
         let word_idx = idx / BITS_PER_WORD;
@@ -66,6 +91,16 @@ impl Selector {
         self.bitmap.get_bit(idx)
     }
 
+    // The number of indices tracked by the bitmap, used or not.
+    pub fn capacity(&self) -> usize {
+        self.bitmap.len() * BITS_PER_WORD
+    }
+
+    // Persists the bitmap to disk.
+    pub fn sync(&mut self) -> std::io::Result<()> {
+        self.bitmap.sync()
+    }
+
     pub fn compact(&mut self) {
         self.bitmap.compact();
         while let Some(&word_idx) = self.free.iter().last() {
@@ -75,6 +110,18 @@ impl Selector {
         }
     }
 
+    // The first unused index at or after |from|.
+    fn vacant_from(&self, from: usize) -> usize {
+        for &word_idx in self.free.range((from / BITS_PER_WORD)..) {
+            let begin = from.max(word_idx * BITS_PER_WORD);
+            let end = (word_idx + 1) * BITS_PER_WORD;
+            if let Some(idx) = (begin..end).find(|&idx| !self.is_used(idx)) {
+                return idx;
+            }
+        }
+        from.max(self.capacity())
+    }
+
     fn init(&mut self) {
         for word_idx in 0..self.bitmap.len() {
             if self.bitmap.get_word(word_idx) < FULL_WORD {
@@ -174,4 +221,31 @@ mod tests {
             assert_eq!(129, selector.vacant());
         } // Drops selector.
     }
+
+    #[test]
+    fn vacant_run() {
+        let path = "/tmp/testfile.selector.3.db";
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(path);
+
+        let mut selector = Selector::new(path).unwrap();
+        assert_eq!(0, selector.vacant_run(4));
+        for i in [0, 3, 8, 20].iter() {
+            selector.set_used(*i);
+        }
+        assert_eq!(1, selector.vacant_run(2));
+        assert_eq!(4, selector.vacant_run(4));
+        assert_eq!(9, selector.vacant_run(11));
+        assert_eq!(21, selector.vacant_run(12));
+
+        // Full words are skipped.
+        for i in 0..40 {
+            selector.set_used(i);
+        }
+        selector.set_free(17);
+        assert_eq!(17, selector.vacant_run(1));
+        assert_eq!(40, selector.vacant_run(2));
+    }
 }
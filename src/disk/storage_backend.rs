@@ -0,0 +1,118 @@
+// Functionality: The page storage a |BufferPoolManager| reads and writes
+// pages through. |DiskManager| stores pages in the database file and its
+// tablespaces; |MemoryStorage| keeps them in memory, for tests and ephemeral
+// databases.
+//
+// Page IDs are allocated by the backend, and pages are |PAGE_SIZE| bytes.
+// Reading a page that was allocated but never written yields zeros.
+
+use crate::common::config::PageId;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::tablespace::FileId;
+use std::ops::Range;
+
+pub trait StorageBackend {
+    // Whether every mutation returns a |ReadOnlyFilesystem| error.
+    fn is_read_only(&self) -> bool;
+
+    // Writes |data| to the page |page_id|, which needs to be allocated.
+    fn write_page(&mut self, page_id: PageId, data: &mut [u8]) -> std::io::Result<()>;
+
+    // Writes |pages| to the pages starting at |first_page_id|, one page per
+    // element, without syncing; see |sync_data|.
+    fn write_pages(
+        &mut self,
+        first_page_id: PageId,
+        pages: &mut [&mut [u8]],
+    ) -> std::io::Result<()>;
+
+    // Syncs the content of the pages written by |write_pages|.
+    fn sync_data(&mut self) -> std::io::Result<()>;
+
+    // Reads the page |page_id| into |data|. Returns |InvalidInput| if the page
+    // is not allocated.
+    fn read_page(&mut self, page_id: PageId, data: &mut [u8]) -> std::io::Result<()>;
+
+    // Reads the pages starting at |first_page_id| into |data|, one page per
+    // |PAGE_SIZE| bytes, stopping before the first page it cannot read.
+    // Returns the number of pages read.
+    fn read_pages(&mut self, first_page_id: PageId, data: &mut [u8]) -> std::io::Result<usize>;
+
+    // Allocates |count| contiguous pages in the tablespace |file_id|. Returns
+    // |NotFound| if there is no such tablespace.
+    fn allocate_pages_in(
+        &mut self,
+        file_id: FileId,
+        count: usize,
+    ) -> std::io::Result<Range<PageId>>;
+
+    // Allocates a page in the tablespace |file_id|.
+    fn allocate_page_in(&mut self, file_id: FileId) -> std::io::Result<PageId> {
+        self.allocate_pages_in(file_id, 1)
+            .map(|page_ids| page_ids.start)
+    }
+
+    // Marks |page_id| as allocated, e.g. when recovery replays allocations.
+    fn reserve_page(&mut self, page_id: PageId) -> std::io::Result<()>;
+
+    fn deallocate_page(&mut self, page_id: PageId) -> std::io::Result<()>;
+
+    fn is_allocated(&self, page_id: PageId) -> bool;
+
+    // Persists the pages and the allocations.
+    fn sync(&mut self) -> std::io::Result<()>;
+}
+
+impl StorageBackend for DiskManager {
+    fn is_read_only(&self) -> bool {
+        DiskManager::is_read_only(self)
+    }
+
+    fn write_page(&mut self, page_id: PageId, data: &mut [u8]) -> std::io::Result<()> {
+        DiskManager::write_page(self, page_id, data)
+    }
+
+    fn write_pages(
+        &mut self,
+        first_page_id: PageId,
+        pages: &mut [&mut [u8]],
+    ) -> std::io::Result<()> {
+        DiskManager::write_pages(self, first_page_id, pages)
+    }
+
+    fn sync_data(&mut self) -> std::io::Result<()> {
+        DiskManager::sync_data(self)
+    }
+
+    fn read_page(&mut self, page_id: PageId, data: &mut [u8]) -> std::io::Result<()> {
+        DiskManager::read_page(self, page_id, data)
+    }
+
+    fn read_pages(&mut self, first_page_id: PageId, data: &mut [u8]) -> std::io::Result<usize> {
+        DiskManager::read_pages(self, first_page_id, data)
+    }
+
+    fn allocate_pages_in(
+        &mut self,
+        file_id: FileId,
+        count: usize,
+    ) -> std::io::Result<Range<PageId>> {
+        DiskManager::allocate_pages_in(self, file_id, count)
+    }
+
+    fn reserve_page(&mut self, page_id: PageId) -> std::io::Result<()> {
+        DiskManager::reserve_page(self, page_id)
+    }
+
+    fn deallocate_page(&mut self, page_id: PageId) -> std::io::Result<()> {
+        DiskManager::deallocate_page(self, page_id)
+    }
+
+    fn is_allocated(&self, page_id: PageId) -> bool {
+        DiskManager::is_allocated(self, page_id)
+    }
+
+    fn sync(&mut self) -> std::io::Result<()> {
+        DiskManager::sync(self)
+    }
+}
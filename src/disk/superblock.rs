@@ -0,0 +1,159 @@
+// Functionality: The superblock, stored in the first |PAGE_SIZE| bytes of
+// every file of pages, before page 0. It identifies the file as a database
+// file, and records the parameters the file was written with, so that opening
+// another file, or a file of an incompatible version, fails with a clear error
+// rather than with checksum errors on every page:
+//  ------------------------------------------------------------------------
+// | Magic (8) | Version (4) | Page Size (4) | Features (4) | CRC-32C (4) | ...
+//  ------------------------------------------------------------------------
+// The rest of the superblock is zeros. The CRC-32C covers the fields before
+// it.
+
+use crate::common::config::PAGE_SIZE;
+use crate::common::crc32c::crc32c;
+use crate::common::error::*;
+use crate::common::reinterpret;
+
+pub const MAGIC: &[u8; 8] = b"RSDBFILE";
+
+// The version of the file layout.
+pub const FORMAT_VERSION: u32 = 1;
+
+// Pages may be compressed, see |DiskManager::set_compression|.
+pub const FEATURE_COMPRESSION: u32 = 1;
+
+// The features this version reads. Opening a file with other features fails.
+pub const SUPPORTED_FEATURES: u32 = FEATURE_COMPRESSION;
+
+const VERSION_OFFSET: usize = 8;
+const PAGE_SIZE_OFFSET: usize = 12;
+const FEATURES_OFFSET: usize = 16;
+const CRC_OFFSET: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Superblock {
+    pub version: u32,
+    pub page_size: u32,
+    pub features: u32,
+}
+
+// The superblock of a new file.
+impl Default for Superblock {
+    fn default() -> Self {
+        Superblock {
+            version: FORMAT_VERSION,
+            page_size: PAGE_SIZE as u32,
+            features: 0,
+        }
+    }
+}
+
+impl Superblock {
+    // The caller needs to ensure that |dst| has |PAGE_SIZE| bytes.
+    pub fn serialize_to(&self, dst: &mut [u8]) {
+        dst.iter_mut().for_each(|byte| *byte = 0);
+        dst[..VERSION_OFFSET].copy_from_slice(MAGIC);
+        reinterpret::write_u32(&mut dst[VERSION_OFFSET..], self.version);
+        reinterpret::write_u32(&mut dst[PAGE_SIZE_OFFSET..], self.page_size);
+        reinterpret::write_u32(&mut dst[FEATURES_OFFSET..], self.features);
+        let crc = crc32c(&dst[..CRC_OFFSET]);
+        reinterpret::write_u32(&mut dst[CRC_OFFSET..], crc);
+    }
+
+    // Reads the superblock of the file at |path| from |src|, and checks that
+    // this version can open the file. Returns |InvalidData| otherwise.
+    // The caller needs to ensure that |src| has |PAGE_SIZE| bytes.
+    pub fn deserialize_from(src: &[u8], path: &str) -> std::io::Result<Self> {
+        if &src[..VERSION_OFFSET] != MAGIC {
+            return Err(invalid_data(&format!(
+                "Not a database file; path = {}",
+                path
+            )));
+        }
+        if reinterpret::read_u32(&src[CRC_OFFSET..]) != crc32c(&src[..CRC_OFFSET]) {
+            return Err(invalid_data(&format!(
+                "Superblock corrupted; path = {}",
+                path
+            )));
+        }
+        let superblock = Superblock {
+            version: reinterpret::read_u32(&src[VERSION_OFFSET..]),
+            page_size: reinterpret::read_u32(&src[PAGE_SIZE_OFFSET..]),
+            features: reinterpret::read_u32(&src[FEATURES_OFFSET..]),
+        };
+        if superblock.version != FORMAT_VERSION {
+            return Err(invalid_data(&format!(
+                "Unsupported format version; path = {}, version = {}, supported = {}",
+                path, superblock.version, FORMAT_VERSION
+            )));
+        }
+        if superblock.page_size != PAGE_SIZE as u32 {
+            return Err(invalid_data(&format!(
+                "Unsupported page size; path = {}, page size = {}, supported = {}",
+                path, superblock.page_size, PAGE_SIZE
+            )));
+        }
+        let unsupported = superblock.features & !SUPPORTED_FEATURES;
+        if unsupported != 0 {
+            return Err(invalid_data(&format!(
+                "Unsupported features; path = {}, features = {:#x}",
+                path, unsupported
+            )));
+        }
+        Ok(superblock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(src: &[u8]) -> String {
+        Superblock::deserialize_from(src, "db")
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn superblock() {
+        let mut data = vec![0xff; PAGE_SIZE];
+        let superblock = Superblock {
+            features: FEATURE_COMPRESSION,
+            ..Superblock::default()
+        };
+        superblock.serialize_to(&mut data);
+        assert_eq!(0, data[PAGE_SIZE - 1]);
+        assert_eq!(
+            superblock,
+            Superblock::deserialize_from(&data, "db").unwrap()
+        );
+
+        assert_eq!("Not a database file; path = db", error(&[0; PAGE_SIZE]));
+        let mut corrupted = data.clone();
+        corrupted[PAGE_SIZE_OFFSET] ^= 1;
+        assert_eq!("Superblock corrupted; path = db", error(&corrupted));
+
+        let mut error_of = |superblock: Superblock| {
+            superblock.serialize_to(&mut data);
+            error(&data)
+        };
+        let version = error_of(Superblock {
+            version: FORMAT_VERSION + 1,
+            ..Superblock::default()
+        });
+        assert!(version.starts_with("Unsupported format version"));
+        let page_size = error_of(Superblock {
+            page_size: 2 * PAGE_SIZE as u32,
+            ..Superblock::default()
+        });
+        assert!(page_size.starts_with("Unsupported page size"));
+        let features = error_of(Superblock {
+            features: 1 << 31,
+            ..Superblock::default()
+        });
+        assert_eq!(
+            "Unsupported features; path = db, features = 0x80000000",
+            features
+        );
+    }
+}
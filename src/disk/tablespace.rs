@@ -0,0 +1,87 @@
+// Functionality: Tablespaces spread the pages of a database over several
+// files, so that tables and indexes can live in separate files and a database
+// is not limited by the size of one file.
+//
+// A page ID encodes a (file ID, page number) pair: the high bits select the
+// tablespace, and the low |PAGE_NO_BITS| bits the page within its file:
+//  -------------------------------------
+// | Sign (1) | FileId (7) | PageNo (24) |
+//  -------------------------------------
+// File 0 is the database file itself, so the page IDs of a single file
+// database are its page numbers. The paths of the other tablespaces are
+// listed, one per line in file ID order, in a registry file next to the
+// database file (see |TABLESPACES_FILE_SUFFIX|).
+
+use crate::common::config::PageId;
+use crate::common::error::*;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::io::Write;
+
+pub type FileId = u8;
+
+pub const TABLESPACES_FILE_SUFFIX: &str = ".ts";
+
+pub const PAGE_NO_BITS: u32 = 24;
+pub const MAX_PAGE_NO: u32 = (1 << PAGE_NO_BITS) - 1;
+pub const MAX_FILE_ID: FileId = 127;
+
+// The database file.
+pub const PRIMARY_FILE_ID: FileId = 0;
+
+// The caller needs to ensure that |page_no| <= |MAX_PAGE_NO| and |file_id| <=
+// |MAX_FILE_ID|.
+pub fn make_page_id(file_id: FileId, page_no: u32) -> PageId {
+    ((file_id as u32) << PAGE_NO_BITS | page_no) as PageId
+}
+
+// The caller needs to ensure that |page_id| is not negative.
+pub fn file_id(page_id: PageId) -> FileId {
+    (page_id as u32 >> PAGE_NO_BITS) as FileId
+}
+
+pub fn page_no(page_id: PageId) -> u32 {
+    page_id as u32 & MAX_PAGE_NO
+}
+
+// Returns the paths of the tablespaces of |db_file| other than the database
+// file, in file ID order.
+pub(crate) fn read_registry(db_file: &str) -> std::io::Result<Vec<String>> {
+    match fs::read_to_string(db_file.to_string() + TABLESPACES_FILE_SUFFIX) {
+        Ok(content) => Ok(content.lines().map(|line| line.to_string()).collect()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+// Records the tablespace at |path| as the next one of |db_file|.
+pub(crate) fn append_registry(db_file: &str, path: &str) -> std::io::Result<()> {
+    if path.contains('\n') {
+        return Err(invalid_input("Tablespace path contains a line break"));
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(db_file.to_string() + TABLESPACES_FILE_SUFFIX)?;
+    writeln!(file, "{}", path)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_id_encoding() {
+        assert_eq!(5, make_page_id(PRIMARY_FILE_ID, 5));
+        let page_id = make_page_id(3, 7);
+        assert_eq!((3, 7), (file_id(page_id), page_no(page_id)));
+        let page_id = make_page_id(MAX_FILE_ID, MAX_PAGE_NO);
+        assert_eq!(PageId::MAX, page_id);
+        assert_eq!(
+            (MAX_FILE_ID, MAX_PAGE_NO),
+            (file_id(page_id), page_no(page_id))
+        );
+    }
+}
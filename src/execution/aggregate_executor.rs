@@ -0,0 +1,73 @@
+// Functionality: Produces a single tuple with the aggregates of all tuples of
+// its child, e.g. for SELECT APPROX_COUNT_DISTINCT(...) FROM ... . Distinct
+// counts are estimated by one HyperLogLog sketch per aggregate, so they take
+// a fixed amount of memory however many tuples the child produces.
+
+use crate::catalog::schema::Schema;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::Executor;
+use crate::plan::expression::Expression;
+use crate::plan::plan_node::AggregateFunction;
+use crate::table::tuple::Tuple;
+use crate::types::hyperloglog::HyperLogLog;
+use crate::types::types::Types;
+use crate::types::value::Value;
+
+pub struct AggregateExecutor<S: StorageBackend = DiskManager> {
+    aggregates: Vec<(AggregateFunction, Expression)>,
+    schema: Schema<'static>,
+    child: Box<dyn Executor<S>>,
+    // The tuple to produce, until it was produced.
+    tuple: Option<Tuple>,
+}
+
+impl<S: StorageBackend> AggregateExecutor<S> {
+    pub fn new(
+        aggregates: Vec<(AggregateFunction, Expression)>,
+        schema: Schema<'static>,
+        child: Box<dyn Executor<S>>,
+    ) -> Self {
+        AggregateExecutor {
+            aggregates,
+            schema,
+            child,
+            tuple: None,
+        }
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for AggregateExecutor<S> {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.child.init(ctx)?;
+        let mut sketches = vec![HyperLogLog::default(); self.aggregates.len()];
+        while let Some((_, tuple)) = self.child.next(ctx)? {
+            let input = self.child.output_schema();
+            for ((_, expression), sketch) in self.aggregates.iter().zip(sketches.iter_mut()) {
+                sketch.add_value(&expression.evaluate(&tuple, input)?);
+            }
+        }
+        let values: Vec<_> = self
+            .aggregates
+            .iter()
+            .zip(sketches.iter())
+            .map(|((function, _), sketch)| match function {
+                AggregateFunction::ApproxCountDistinct => {
+                    Value::new(Types::BigInt(sketch.estimate() as i64))
+                }
+            })
+            .collect();
+        self.tuple = Some(Tuple::new(&values, &self.schema));
+        Ok(())
+    }
+
+    fn next(&mut self, _: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        Ok(self.tuple.take().map(|tuple| (Rid::default(), tuple)))
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        &self.schema
+    }
+}
@@ -0,0 +1,58 @@
+// Functionality: Deletes the tuples of its child, which reads them from a
+// table, and produces the number of tuples deleted.
+
+use crate::catalog::catalog::Oid;
+use crate::catalog::schema::Schema;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::count_tuple;
+use crate::execution::executor::drain;
+use crate::execution::executor::Executor;
+use crate::table::tuple::Tuple;
+
+pub struct DeleteExecutor<S: StorageBackend = DiskManager> {
+    table_oid: Oid,
+    schema: Schema<'static>,
+    child: Box<dyn Executor<S>>,
+    done: bool,
+}
+
+impl<S: StorageBackend> DeleteExecutor<S> {
+    pub fn new(table_oid: Oid, schema: Schema<'static>, child: Box<dyn Executor<S>>) -> Self {
+        DeleteExecutor {
+            table_oid,
+            schema,
+            child,
+            done: false,
+        }
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for DeleteExecutor<S> {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.done = false;
+        self.child.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        if self.done {
+            return Ok(None);
+        }
+        self.done = true;
+        let table = ctx.table(self.table_oid)?;
+        let items = drain(self.child.as_mut(), ctx)?;
+        for (rid, tuple) in items.iter() {
+            ctx.delete_tuple(table, rid, tuple)?;
+        }
+        Ok(Some((
+            Rid::default(),
+            count_tuple(&self.schema, items.len()),
+        )))
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        &self.schema
+    }
+}
@@ -0,0 +1,368 @@
+// Functionality: The state shared by the executors of a statement: the buffer
+// pool of the tables and indexes, the catalog, and the transaction the
+// statement runs in.
+//
+// Executors write tables through the context, which keeps the indexes of a
+// table in sync with its tuples.
+//
+// The transaction is logged if the pool has a log manager: |begin|,
+// |commit| and |abort| write its begin, commit and abort records. Aborting
+// rolls back its table changes from the log, see |RecoveryManager::roll_back|,
+// or from the undo log of a pool without a log manager, and its index changes
+// from a list the context keeps, since indexes are not logged. The sketches
+// of the tables written are updated once the transaction commits, from
+// another list. With a lock
+// manager set, the transaction holds an exclusive lock on every tuple it
+// writes until it ends.
+
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::catalog::catalog::Catalog;
+use crate::catalog::catalog::IndexInfo;
+use crate::catalog::catalog::Oid;
+use crate::catalog::catalog::TableInfo;
+use crate::catalog::column::Column;
+use crate::catalog::schema::Schema;
+use crate::common::config::TransactionId;
+use crate::common::error::*;
+use crate::common::rid::Rid;
+use crate::concurrency::lock_manager::LockManager;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::page::table_page::TablePage;
+use crate::recovery::log_record::LogRecord;
+use crate::recovery::log_record::LogRecordBody;
+use crate::recovery::recovery_manager::RecoveryManager;
+use crate::table::tuple::Tuple;
+use crate::types::types::Operation;
+use crate::types::types::Types;
+use crate::types::value::Value;
+
+pub struct ExecutionContext<'a, S: StorageBackend = DiskManager> {
+    pub(crate) bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+    pub(crate) catalog: &'a Catalog,
+    pub(crate) txn_id: TransactionId,
+    lock_mgr: Option<&'a LockManager>,
+    // The index changes of the transaction, oldest first: the index, the key,
+    // and the RID of a removed key. Aborting reverts them.
+    index_changes: Vec<(IndexInfo, i64, Option<Rid>)>,
+    // The tuples the transaction inserted or deleted, oldest first, with the
+    // OID of their table and whether they were inserted; see |TableSketches|.
+    sketch_changes: Vec<(Oid, Tuple, bool)>,
+}
+
+impl<'a, S: StorageBackend> ExecutionContext<'a, S> {
+    pub fn new(
+        bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+        catalog: &'a Catalog,
+        txn_id: TransactionId,
+    ) -> Self {
+        ExecutionContext {
+            bpm,
+            catalog,
+            txn_id,
+            lock_mgr: None,
+            index_changes: Vec::new(),
+            sketch_changes: Vec::new(),
+        }
+    }
+
+    // Locks the tuples the transaction writes with |lock_mgr|.
+    pub fn set_lock_manager(&mut self, lock_mgr: &'a LockManager) {
+        self.lock_mgr = Some(lock_mgr);
+    }
+
+    // Logs the begin record of the transaction.
+    pub fn begin(&mut self) -> std::io::Result<()> {
+        begin(self.bpm, self.txn_id)
+    }
+
+    // Logs the commit record of the transaction and makes it durable, then
+    // updates the sketches of the tables it wrote and releases its locks.
+    pub fn commit(&mut self) -> std::io::Result<()> {
+        commit(self.bpm, self.txn_id)?;
+        self.index_changes.clear();
+        for (table_oid, tuple, inserted) in self.sketch_changes.drain(..) {
+            if let Some(table) = self.catalog.table_by_oid(table_oid) {
+                let mut sketches = table.sketches_mut();
+                match inserted {
+                    true => sketches.on_insert(table.schema(), &tuple),
+                    false => sketches.on_delete(table.schema(), &tuple),
+                }
+            }
+        }
+        if let Some(lock_mgr) = self.lock_mgr {
+            lock_mgr.commit(self.txn_id);
+        }
+        Ok(())
+    }
+
+    // Rolls back the index and table changes of the transaction, logs its
+    // abort record, then releases its locks.
+    pub fn abort(&mut self) -> std::io::Result<()> {
+        while let Some((index, key, rid)) = self.index_changes.pop() {
+            let mut tree = index.open(self.bpm)?;
+            match rid {
+                Some(rid) => tree.insert(self.bpm, key, rid)?,
+                None => tree.remove(self.bpm, key)?,
+            };
+        }
+        self.sketch_changes.clear();
+        abort(self.bpm, self.txn_id)?;
+        if let Some(lock_mgr) = self.lock_mgr {
+            lock_mgr.abort(self.txn_id);
+        }
+        Ok(())
+    }
+
+    pub fn catalog(&self) -> &Catalog {
+        self.catalog
+    }
+
+    pub fn txn_id(&self) -> TransactionId {
+        self.txn_id
+    }
+
+    // Returns |NotFound| if there is no table with OID |table_oid|, and
+    // |InvalidData| if the table is invalid; see |TableInfo::check_valid|.
+    pub(crate) fn table(&self, table_oid: Oid) -> std::io::Result<&'a TableInfo> {
+        let table = self
+            .catalog
+            .table_by_oid(table_oid)
+            .ok_or_else(|| not_found(&format!("No such table; oid = {}", table_oid)))?;
+        table.check_valid()?;
+        Ok(table)
+    }
+
+    // Inserts |tuple| into |table| and its indexes, with the current schema
+    // version. Returns |InvalidInput| if an index already has its key, in
+    // which case nothing is inserted.
+    pub(crate) fn insert_tuple(
+        &mut self,
+        table: &TableInfo,
+        mut tuple: Tuple,
+    ) -> std::io::Result<Rid> {
+        tuple.set_version(table.version());
+        let keys = self.index_keys(table, &tuple)?;
+        self.check_unique(&keys, None)?;
+        let mut heap = table.heap();
+        let rid = heap.insert_tuple(self.bpm, self.txn_id, tuple.clone())?;
+        table.set_free_space_hint(&heap);
+        self.lock(&rid)?;
+        self.insert_keys(&keys, &rid)?;
+        self.sketch_changes.push((table.oid(), tuple, true));
+        Ok(rid)
+    }
+
+    // Deletes the tuple at |rid| from |table| and its indexes.
+    pub(crate) fn delete_tuple(
+        &mut self,
+        table: &TableInfo,
+        rid: &Rid,
+        tuple: &Tuple,
+    ) -> std::io::Result<()> {
+        self.lock(rid)?;
+        let keys = self.index_keys(table, tuple)?;
+        let mut heap = table.heap();
+        if heap.mark_delete(self.bpm, self.txn_id, rid)? {
+            heap.apply_delete(self.bpm, self.txn_id, rid)?;
+            table.set_free_space_hint(&heap);
+            self.sketch_changes
+                .push((table.oid(), tuple.clone(), false));
+        }
+        self.remove_keys(&keys, rid)
+    }
+
+    // Replaces the tuple |old_tuple| at |rid| of |table| with |new_tuple|, with
+    // the current schema version, moving it if its page has no space left, and
+    // returns its new RID.
+    // Returns |InvalidInput| if an index already has the new key for another
+    // tuple, in which case nothing is changed.
+    pub(crate) fn update_tuple(
+        &mut self,
+        table: &TableInfo,
+        rid: &Rid,
+        old_tuple: &Tuple,
+        mut new_tuple: Tuple,
+    ) -> std::io::Result<Rid> {
+        self.lock(rid)?;
+        new_tuple.set_version(table.version());
+        let old_keys = self.index_keys(table, old_tuple)?;
+        let new_keys = self.index_keys(table, &new_tuple)?;
+        self.check_unique(&new_keys, Some(rid))?;
+        self.remove_keys(&old_keys, rid)?;
+        let mut heap = table.heap();
+        let new_rid = match heap.update_tuple(self.bpm, self.txn_id, rid, new_tuple.clone())? {
+            true => rid.clone(),
+            false => {
+                heap.mark_delete(self.bpm, self.txn_id, rid)?;
+                heap.apply_delete(self.bpm, self.txn_id, rid)?;
+                let new_rid = heap.insert_tuple(self.bpm, self.txn_id, new_tuple.clone())?;
+                table.set_free_space_hint(&heap);
+                self.lock(&new_rid)?;
+                new_rid
+            }
+        };
+        self.insert_keys(&new_keys, &new_rid)?;
+        self.sketch_changes
+            .push((table.oid(), old_tuple.clone(), false));
+        self.sketch_changes.push((table.oid(), new_tuple, true));
+        Ok(new_rid)
+    }
+
+    // Returns the indexes of |table| with the key of |tuple| in each of them,
+    // if it is not null.
+    fn index_keys(
+        &self,
+        table: &TableInfo,
+        tuple: &Tuple,
+    ) -> std::io::Result<Vec<(IndexInfo, Option<i64>)>> {
+        let mut keys = Vec::new();
+        for index in self.catalog.table_indexes(table.oid()) {
+            let value = tuple.nth_value(table.schema(), index.key_column());
+            let key = match value.is_null() {
+                true => None,
+                false => Some(
+                    value
+                        .borrow()
+                        .get_as_i64()
+                        .map_err(|_| invalid_data("Index key is not an integer"))?,
+                ),
+            };
+            keys.push((index.clone(), key));
+        }
+        Ok(keys)
+    }
+
+    // Returns |InvalidInput| if a key is taken by a tuple other than |rid|.
+    fn check_unique(
+        &mut self,
+        keys: &[(IndexInfo, Option<i64>)],
+        rid: Option<&Rid>,
+    ) -> std::io::Result<()> {
+        for (index, key) in keys.iter() {
+            if let Some(key) = key {
+                let tree = index.open(self.bpm)?;
+                match tree.get_value(self.bpm, *key)? {
+                    Some(found) if Some(&found) != rid => {
+                        return Err(invalid_input(&format!(
+                            "Duplicate key; index = {}, key = {}",
+                            index.name(),
+                            key
+                        )));
+                    }
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_keys(&mut self, keys: &[(IndexInfo, Option<i64>)], rid: &Rid) -> std::io::Result<()> {
+        for (index, key) in keys.iter() {
+            if let Some(key) = key {
+                if index.open(self.bpm)?.insert(self.bpm, *key, rid.clone())? {
+                    self.index_changes.push((index.clone(), *key, None));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Removes the keys of the tuple at |rid|.
+    fn remove_keys(&mut self, keys: &[(IndexInfo, Option<i64>)], rid: &Rid) -> std::io::Result<()> {
+        for (index, key) in keys.iter() {
+            if let Some(key) = key {
+                if index.open(self.bpm)?.remove(self.bpm, *key)? {
+                    self.index_changes
+                        .push((index.clone(), *key, Some(rid.clone())));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Takes an exclusive lock on the tuple at |rid| for the transaction.
+    fn lock(&self, rid: &Rid) -> std::io::Result<()> {
+        match self.lock_mgr {
+            Some(lock_mgr) => lock_mgr.lock_exclusive(self.txn_id, rid),
+            None => Ok(()),
+        }
+    }
+}
+
+// Logs the begin record of |txn_id|, if |bpm| has a log manager, and charges
+// the pages pinned from now on to it; see |BufferPoolManager::set_pin_quota|.
+pub(crate) fn begin<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    txn_id: TransactionId,
+) -> std::io::Result<()> {
+    bpm.set_pin_owner(txn_id);
+    if let Some(log_mgr) = bpm.log_manager() {
+        log_mgr.append(LogRecord::new(txn_id, LogRecordBody::Begin))?;
+    }
+    Ok(())
+}
+
+// Logs the commit record of |txn_id| and makes it durable, if |bpm| has a log
+// manager. Otherwise, drops its undo log.
+pub(crate) fn commit<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    txn_id: TransactionId,
+) -> std::io::Result<()> {
+    bpm.release_pins(txn_id);
+    match bpm.log_manager() {
+        Some(log_mgr) => {
+            let lsn = log_mgr.append(LogRecord::new(txn_id, LogRecordBody::Commit))?;
+            log_mgr.flush_until(lsn)?;
+        }
+        None => {
+            bpm.take_undo_log(txn_id);
+        }
+    }
+    Ok(())
+}
+
+// Rolls back the table changes of |txn_id| and logs its abort record, if |bpm|
+// has a log manager. Otherwise, rolls them back from its undo log, which keeps
+// them if it is not enabled. The rollback is not subject to the pin quota of
+// the transaction, which may be what made it fail.
+pub(crate) fn abort<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    txn_id: TransactionId,
+) -> std::io::Result<()> {
+    bpm.release_pins(txn_id);
+    match bpm.log_manager() {
+        Some(_) => RecoveryManager::roll_back(bpm, txn_id).map(|_| ()),
+        None => RecoveryManager::roll_back_unlogged(bpm, txn_id).map(|_| ()),
+    }
+}
+
+// Converts |values| to the column types of |schema|, and builds a tuple out of
+// them. Returns |InvalidInput| if a value cannot be converted.
+pub(crate) fn conform(values: Vec<Value>, schema: &Schema) -> std::io::Result<Tuple> {
+    let mut converted = Vec::new();
+    for (value, column) in values.into_iter().zip(schema.columns().iter()) {
+        converted.push(conform_value(value, column)?);
+    }
+    Ok(Tuple::new(&converted, schema))
+}
+
+// Converts |value| to the type of |column|. Returns |InvalidInput| if it cannot
+// be converted.
+pub(crate) fn conform_value<'a>(value: Value<'a>, column: &Column) -> std::io::Result<Value<'a>> {
+    let id = column.types().id();
+    if value.borrow().id() == id {
+        return Ok(value);
+    }
+    let cannot_convert =
+        || invalid_input(&format!("Cannot convert value; column = {}", column.name()));
+    let types = Types::from_id(id).unwrap();
+    if value.is_null() {
+        types.clone().null_val().map_err(|_| cannot_convert())?;
+        return Ok(Value::null(types));
+    }
+    let mut dst = Value::new(types);
+    value.cast_to(&mut dst).map_err(|_| cannot_convert())?;
+    // The size of |dst| is the one of the zero value; recompute it.
+    Ok(Value::new(dst.borrow().to_static()))
+}
@@ -0,0 +1,402 @@
+// Functionality: Executors evaluate plans in the Volcano (iterator) model:
+// every executor pulls the tuples of its children one at a time via |next|,
+// and produces its own tuples the same way.
+//
+// Executors borrow the buffer pools and the catalog from the execution context
+// passed to every call, so that a tree of executors can share them.
+
+use crate::catalog::schema::Schema;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::aggregate_executor::AggregateExecutor;
+use crate::execution::delete_executor::DeleteExecutor;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::filter_executor::FilterExecutor;
+use crate::execution::hash_join_executor::HashJoinExecutor;
+use crate::execution::index_join_executor::IndexJoinExecutor;
+use crate::execution::index_scan_executor::IndexScanExecutor;
+use crate::execution::insert_executor::InsertExecutor;
+use crate::execution::limit_executor::LimitExecutor;
+use crate::execution::nested_loop_join_executor::NestedLoopJoinExecutor;
+use crate::execution::projection_executor::ProjectionExecutor;
+use crate::execution::seq_scan_executor::SeqScanExecutor;
+use crate::execution::sort_executor::SortExecutor;
+use crate::execution::top_n_executor::TopNExecutor;
+use crate::execution::update_executor::UpdateExecutor;
+use crate::execution::values_executor::ValuesExecutor;
+use crate::plan::plan_node::PlanNode;
+use crate::table::tuple::Tuple;
+use crate::types::types::Types;
+use crate::types::value::Value;
+
+pub trait Executor<S: StorageBackend = DiskManager> {
+    // Prepares the executor and its children to produce tuples from the
+    // start.
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()>;
+
+    // Returns the next tuple with its RID, or None once all tuples were
+    // produced. Tuples not stored in a table come with an invalid RID.
+    fn next(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>>;
+
+    fn output_schema(&self) -> &Schema<'static>;
+}
+
+// Builds the executor tree evaluating |plan|.
+pub fn create_executor<S: StorageBackend + 'static>(plan: &PlanNode) -> Box<dyn Executor<S>> {
+    let child = |child: &PlanNode| create_executor(child);
+    match plan {
+        PlanNode::SeqScan { table_oid, schema } => {
+            Box::new(SeqScanExecutor::new(*table_oid, schema.clone()))
+        }
+        PlanNode::IndexScan {
+            table_oid,
+            index_name,
+            key,
+            schema,
+        } => Box::new(IndexScanExecutor::new(
+            *table_oid,
+            index_name.clone(),
+            *key,
+            schema.clone(),
+        )),
+        PlanNode::Values { schema, rows } => {
+            Box::new(ValuesExecutor::new(schema.clone(), rows.clone()))
+        }
+        PlanNode::Filter {
+            predicate,
+            child: c,
+        } => Box::new(FilterExecutor::new(predicate.clone(), child(c))),
+        PlanNode::Projection {
+            expressions,
+            schema,
+            child: c,
+        } => Box::new(ProjectionExecutor::new(
+            expressions.clone(),
+            schema.clone(),
+            child(c),
+        )),
+        PlanNode::Aggregate {
+            aggregates,
+            schema,
+            child: c,
+        } => Box::new(AggregateExecutor::new(
+            aggregates.clone(),
+            schema.clone(),
+            child(c),
+        )),
+        PlanNode::Sort {
+            order_bys,
+            child: c,
+        } => Box::new(SortExecutor::new(order_bys.clone(), child(c))),
+        PlanNode::Limit { limit, child: c } => Box::new(LimitExecutor::new(*limit, child(c))),
+        PlanNode::TopN {
+            order_bys,
+            limit,
+            child: c,
+        } => Box::new(TopNExecutor::new(order_bys.clone(), *limit, child(c))),
+        PlanNode::HashJoin {
+            left_key,
+            right_key,
+            schema,
+            left,
+            right,
+        } => Box::new(HashJoinExecutor::new(
+            left_key.clone(),
+            right_key.clone(),
+            schema.clone(),
+            child(left),
+            child(right),
+        )),
+        PlanNode::NestedLoopJoin {
+            join_type,
+            predicate,
+            schema,
+            left,
+            right,
+        } => Box::new(NestedLoopJoinExecutor::new(
+            *join_type,
+            predicate.clone(),
+            schema.clone(),
+            child(left),
+            child(right),
+        )),
+        PlanNode::IndexJoin {
+            join_type,
+            left_key,
+            index_name,
+            table_oid,
+            schema,
+            left,
+        } => Box::new(IndexJoinExecutor::new(
+            *join_type,
+            left_key.clone(),
+            index_name.clone(),
+            *table_oid,
+            schema.clone(),
+            child(left),
+        )),
+        PlanNode::Insert {
+            table_oid,
+            on_conflict,
+            child: c,
+        } => Box::new(InsertExecutor::new(
+            *table_oid,
+            on_conflict.clone(),
+            plan.output_schema(),
+            child(c),
+        )),
+        PlanNode::Delete {
+            table_oid,
+            child: c,
+        } => Box::new(DeleteExecutor::new(
+            *table_oid,
+            plan.output_schema(),
+            child(c),
+        )),
+        PlanNode::Update {
+            table_oid,
+            assignments,
+            child: c,
+        } => Box::new(UpdateExecutor::new(
+            *table_oid,
+            assignments.clone(),
+            plan.output_schema(),
+            child(c),
+        )),
+    }
+}
+
+// Evaluates |plan| to completion, and returns the tuples it produced.
+pub fn execute<S: StorageBackend + 'static>(
+    plan: &PlanNode,
+    ctx: &mut ExecutionContext<S>,
+) -> std::io::Result<Vec<Tuple>> {
+    let mut executor = create_executor(plan);
+    executor.init(ctx)?;
+    let mut tuples = Vec::new();
+    while let Some((_, tuple)) = executor.next(ctx)? {
+        tuples.push(tuple);
+    }
+    Ok(tuples)
+}
+
+// Returns all tuples |child| produces.
+pub(crate) fn drain<S: StorageBackend>(
+    child: &mut dyn Executor<S>,
+    ctx: &mut ExecutionContext<S>,
+) -> std::io::Result<Vec<(Rid, Tuple)>> {
+    let mut items = Vec::new();
+    while let Some(item) = child.next(ctx)? {
+        items.push(item);
+    }
+    Ok(items)
+}
+
+// Returns the tuple counting the tuples changed by a statement.
+pub(crate) fn count_tuple(schema: &Schema, count: usize) -> Tuple {
+    Tuple::new(&vec![Value::new(Types::BigInt(count as i64))], schema)
+}
+
+// Returns the tuple with the values of |left| followed by those of |right|,
+// or nulls if there is no right tuple.
+pub(crate) fn join_tuples(
+    left: &Tuple,
+    left_schema: &Schema,
+    right: Option<&Tuple>,
+    right_schema: &Schema,
+    schema: &Schema,
+) -> Tuple {
+    let mut values = Vec::new();
+    for idx in 0..left_schema.columns().len() {
+        values.push(left.nth_value(left_schema, idx));
+    }
+    for idx in 0..right_schema.columns().len() {
+        values.push(match right {
+            Some(right) => right.nth_value(right_schema, idx),
+            // Plans only pad columns that have a null value.
+            None => {
+                let types = right_schema.nth_types(idx).unwrap().clone();
+                Value::null(types)
+            }
+        });
+    }
+    Tuple::new(&values, schema)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::catalog::catalog::Catalog;
+    use crate::catalog::column::Column;
+    use crate::disk::memory_storage::MemoryStorage;
+    use crate::page::table_page::TablePage;
+    use crate::plan::expression::ArithmeticOp;
+    use crate::plan::expression::ComparisonOp;
+    use crate::plan::expression::Expression;
+    use crate::plan::plan_node::AggregateFunction;
+    use crate::types::types::Str;
+    use crate::types::types::Varlen;
+
+    fn create_schema() -> Schema<'static> {
+        Schema::new(vec![
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("Name".to_string(), Types::owned(), 10),
+        ])
+    }
+
+    fn name(s: &str) -> Expression {
+        Expression::constant(Types::Varchar(Varlen::Owned(Str::Val(s.to_string()))))
+    }
+
+    fn row(id: i32, s: &str) -> Vec<Expression> {
+        vec![Expression::constant(Types::Integer(id)), name(s)]
+    }
+
+    fn id_less_than(schema: &Schema, id: i64) -> Expression {
+        Expression::Comparison(
+            ComparisonOp::Lt,
+            Box::new(Expression::column(schema, "Id").unwrap()),
+            Box::new(Expression::constant(Types::BigInt(id))),
+        )
+    }
+
+    fn count(tuples: &[Tuple], schema: &Schema) -> i64 {
+        assert_eq!(1, tuples.len());
+        let value = tuples[0].nth_value(schema, 0);
+        value.borrow().get_as_i64().unwrap()
+    }
+
+    // Runs |f| with an execution context over a fresh in-memory pool, whose
+    // catalog was filled in by |setup|.
+    pub(crate) fn with_context<S, F>(setup: S, f: F)
+    where
+        S: FnOnce(&mut Catalog, &mut MemoryBufferPoolManager<TablePage>),
+        F: FnOnce(&mut ExecutionContext<MemoryStorage>),
+    {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(8);
+        let mut catalog = Catalog::create(&mut bpm).unwrap();
+        setup(&mut catalog, &mut bpm);
+        let mut ctx = ExecutionContext::new(&mut bpm, &catalog, 1);
+        f(&mut ctx);
+    }
+
+    #[test]
+    fn execute_statements() {
+        let setup = |catalog: &mut Catalog, bpm: &mut _| {
+            catalog
+                .create_table(bpm, 1, "users", create_schema())
+                .unwrap();
+            catalog
+                .create_index(bpm, 1, "users_id", "users", "Id")
+                .unwrap();
+        };
+        with_context(setup, |ctx| {
+            // INSERT INTO users VALUES (0, 'user0'), ..., (99, 'user99');
+            let rows = (0..100).map(|i| row(i, &format!("user{}", i))).collect();
+            let input = Schema::new(vec![
+                Column::new("Id".to_string(), Types::integer(), 4),
+                Column::new("Name".to_string(), Types::owned(), 10),
+            ]);
+            let values = PlanNode::values(input, rows).unwrap();
+            let insert = PlanNode::insert(ctx.catalog, "users", values).unwrap();
+            let output = insert.output_schema();
+            assert_eq!(100, count(&execute(&insert, ctx).unwrap(), &output));
+            let values = PlanNode::values(create_schema(), vec![row(5, "again")]).unwrap();
+            let insert = PlanNode::insert(ctx.catalog, "users", values).unwrap();
+            assert!(execute(&insert, ctx).is_err());
+
+            // SELECT Id * 2 AS Double, Name FROM users WHERE Id < 10 LIMIT 3;
+            let scan = PlanNode::seq_scan(ctx.catalog, "users").unwrap();
+            let schema = scan.output_schema();
+            let double = Expression::Arithmetic(
+                ArithmeticOp::Multiply,
+                Box::new(Expression::column(&schema, "Id").unwrap()),
+                Box::new(Expression::constant(Types::Integer(2))),
+            );
+            let select = scan
+                .filter(id_less_than(&schema, 10))
+                .project(
+                    vec![double, Expression::column(&schema, "Name").unwrap()],
+                    &["Double", "Name"],
+                )
+                .unwrap()
+                .limit(3);
+            let output = select.output_schema();
+            let tuples = execute(&select, ctx).unwrap();
+            let strings: Vec<String> = tuples.iter().map(|t| t.to_string(&output)).collect();
+            assert_eq!(3, strings.len());
+            assert!(strings[2].contains("4") && strings[2].contains("user2"));
+
+            // SELECT APPROX_COUNT_DISTINCT(Id), APPROX_COUNT_DISTINCT(7) FROM users;
+            let scan = PlanNode::seq_scan(ctx.catalog, "users").unwrap();
+            let aggregates = vec![
+                (
+                    AggregateFunction::ApproxCountDistinct,
+                    Expression::column(&schema, "Id").unwrap(),
+                ),
+                (
+                    AggregateFunction::ApproxCountDistinct,
+                    Expression::constant(Types::Integer(7)),
+                ),
+            ];
+            let aggregate = scan.aggregate(aggregates, &["Ids", "Sevens"]).unwrap();
+            let output = aggregate.output_schema();
+            let tuples = execute(&aggregate, ctx).unwrap();
+            assert!((97..=103).contains(&count(&tuples, &output)));
+            let sevens = tuples[0].nth_value(&output, 1);
+            assert_eq!(1, sevens.borrow().get_as_i64().unwrap());
+
+            // UPDATE users SET Id = Id + 1000, Name = 'renamed' WHERE Id < 10;
+            let scan = PlanNode::seq_scan(ctx.catalog, "users").unwrap();
+            let plus = Expression::Arithmetic(
+                ArithmeticOp::Add,
+                Box::new(Expression::column(&schema, "Id").unwrap()),
+                Box::new(Expression::constant(Types::Integer(1000))),
+            );
+            let assignments = vec![("Id", plus), ("Name", name("renamed"))];
+            let update = PlanNode::update(
+                ctx.catalog,
+                "users",
+                assignments,
+                scan.filter(id_less_than(&schema, 10)),
+            )
+            .unwrap();
+            let output = update.output_schema();
+            assert_eq!(10, count(&execute(&update, ctx).unwrap(), &output));
+
+            // DELETE FROM users WHERE Id < 50;
+            let scan = PlanNode::seq_scan(ctx.catalog, "users").unwrap();
+            let delete =
+                PlanNode::delete(ctx.catalog, "users", scan.filter(id_less_than(&schema, 50)))
+                    .unwrap();
+            assert_eq!(40, count(&execute(&delete, ctx).unwrap(), &output));
+
+            let scan = PlanNode::seq_scan(ctx.catalog, "users").unwrap();
+            let tuples = execute(&scan, ctx).unwrap();
+            assert_eq!(60, tuples.len());
+            let renamed = tuples
+                .iter()
+                .filter(|t| t.to_string(&schema).contains("renamed"))
+                .count();
+            assert_eq!(10, renamed);
+
+            // The index follows the table.
+            let index = ctx.catalog.index("users_id").unwrap();
+            let tree = index.open(ctx.bpm).unwrap();
+            assert_eq!(60, tree.iter(ctx.bpm).unwrap().count());
+            assert!(tree.get_value(ctx.bpm, 3).unwrap().is_none());
+            let rid = tree.get_value(ctx.bpm, 1003).unwrap().unwrap();
+            let tuple = ctx
+                .catalog
+                .table("users")
+                .unwrap()
+                .heap()
+                .get_tuple(ctx.bpm, &rid)
+                .unwrap()
+                .unwrap();
+            assert!(tuple.to_string(&schema).contains("1003"));
+        });
+    }
+}
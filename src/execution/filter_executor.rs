@@ -0,0 +1,44 @@
+// Functionality: Produces the tuples of its child for which the predicate
+// holds.
+
+use crate::catalog::schema::Schema;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::Executor;
+use crate::plan::expression::Expression;
+use crate::table::tuple::Tuple;
+
+pub struct FilterExecutor<S: StorageBackend = DiskManager> {
+    predicate: Expression,
+    child: Box<dyn Executor<S>>,
+}
+
+impl<S: StorageBackend> FilterExecutor<S> {
+    pub fn new(predicate: Expression, child: Box<dyn Executor<S>>) -> Self {
+        FilterExecutor { predicate, child }
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for FilterExecutor<S> {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.child.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        while let Some((rid, tuple)) = self.child.next(ctx)? {
+            if self
+                .predicate
+                .evaluate_predicate(&tuple, self.child.output_schema())?
+            {
+                return Ok(Some((rid, tuple)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        self.child.output_schema()
+    }
+}
@@ -0,0 +1,208 @@
+// Functionality: Joins the tuples of two children on equal keys. The executor
+// first builds a hash table of the tuples of the inner (right) child, keyed on
+// the hash of their key, then probes it with the key of every tuple of the
+// outer (left) child.
+//
+// Tuples sharing a bucket are matched with |Value::eq|, so that hash
+// collisions and keys of different numeric types are handled correctly. Null
+// keys are never inserted nor probed, as null is not equal to anything.
+
+use crate::catalog::schema::Schema;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::join_tuples;
+use crate::execution::executor::Executor;
+use crate::plan::expression::Expression;
+use crate::table::tuple::Tuple;
+use crate::types::types::Operation;
+use crate::types::value::Value;
+
+use std::collections::HashMap;
+
+pub struct HashJoinExecutor<S: StorageBackend = DiskManager> {
+    left_key: Expression,
+    right_key: Expression,
+    schema: Schema<'static>,
+    left: Box<dyn Executor<S>>,
+    right: Box<dyn Executor<S>>,
+    // The tuples of |right| with their keys, by the hash of their keys.
+    table: HashMap<u64, Vec<(Value<'static>, Tuple)>>,
+    // The left tuple being probed, with the right tuples matching it that
+    // were not joined yet.
+    left_tuple: Option<Tuple>,
+    matches: Vec<Tuple>,
+}
+
+impl<S: StorageBackend> HashJoinExecutor<S> {
+    pub fn new(
+        left_key: Expression,
+        right_key: Expression,
+        schema: Schema<'static>,
+        left: Box<dyn Executor<S>>,
+        right: Box<dyn Executor<S>>,
+    ) -> Self {
+        HashJoinExecutor {
+            left_key,
+            right_key,
+            schema,
+            left,
+            right,
+            table: HashMap::new(),
+            left_tuple: None,
+            matches: Vec::new(),
+        }
+    }
+
+    // Returns the right tuples whose key equals |key|.
+    fn probe(&self, key: &Value) -> Vec<Tuple> {
+        let bucket = match hash_key(key).and_then(|hash| self.table.get(&hash)) {
+            Some(bucket) => bucket,
+            None => return Vec::new(),
+        };
+        // Matches are popped from the back; reverse them to keep the order of
+        // the right child.
+        bucket
+            .iter()
+            .rev()
+            .filter(|(right_key, _)| key.eq(right_key) == Some(true))
+            .map(|(_, tuple)| tuple.clone())
+            .collect()
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for HashJoinExecutor<S> {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.left.init(ctx)?;
+        self.right.init(ctx)?;
+        self.table.clear();
+        self.left_tuple = None;
+        self.matches.clear();
+        while let Some((_, tuple)) = self.right.next(ctx)? {
+            let key = self
+                .right_key
+                .evaluate(&tuple, self.right.output_schema())?;
+            if let Some(hash) = hash_key(&key) {
+                let key = key.to_static();
+                self.table.entry(hash).or_default().push((key, tuple));
+            }
+        }
+        Ok(())
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        loop {
+            if let (Some(left_tuple), Some(right_tuple)) = (&self.left_tuple, self.matches.pop()) {
+                let tuple = join_tuples(
+                    left_tuple,
+                    self.left.output_schema(),
+                    Some(&right_tuple),
+                    self.right.output_schema(),
+                    &self.schema,
+                );
+                return Ok(Some((Rid::default(), tuple)));
+            }
+            let tuple = match self.left.next(ctx)? {
+                Some((_, tuple)) => tuple,
+                None => return Ok(None),
+            };
+            let key = self.left_key.evaluate(&tuple, self.left.output_schema())?;
+            self.matches = self.probe(&key);
+            self.left_tuple = Some(tuple);
+        }
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        &self.schema
+    }
+}
+
+// Returns the hash of |key|, or None if it is null. Equal keys of different
+// numeric types hash alike.
+fn hash_key(key: &Value) -> Option<u64> {
+    if key.is_null() {
+        return None;
+    }
+    Some(key.hash())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::column::Column;
+    use crate::execution::executor::execute;
+    use crate::execution::executor::tests::with_context;
+    use crate::plan::plan_node::PlanNode;
+    use crate::types::types::Str;
+    use crate::types::types::Types;
+    use crate::types::types::Varlen;
+
+    fn name(s: &str) -> Expression {
+        Expression::constant(Types::Varchar(Varlen::Owned(Str::Val(s.to_string()))))
+    }
+
+    #[test]
+    fn hash_join() {
+        let setup = |_: &mut _, _: &mut _| ();
+        with_context(setup, |ctx| {
+            // Users (Id Integer, Name Varchar), some with a null id.
+            let users = Schema::new(vec![
+                Column::new("Id".to_string(), Types::integer(), 4),
+                Column::new("Name".to_string(), Types::owned(), 10),
+            ]);
+            let null_id = Expression::null(Types::integer());
+            let users = PlanNode::values(
+                users,
+                vec![
+                    vec![Expression::constant(Types::Integer(1)), name("alice")],
+                    vec![Expression::constant(Types::Integer(2)), name("bob")],
+                    vec![null_id.clone(), name("nobody")],
+                ],
+            )
+            .unwrap();
+            // Orders (UserId BigInt, Item Varchar), keyed on a wider type.
+            let orders = Schema::new(vec![
+                Column::new("UserId".to_string(), Types::bigint(), 8),
+                Column::new("Item".to_string(), Types::owned(), 10),
+            ]);
+            let orders = PlanNode::values(
+                orders,
+                vec![
+                    vec![Expression::constant(Types::BigInt(1)), name("apple")],
+                    vec![Expression::constant(Types::BigInt(3)), name("cherry")],
+                    vec![Expression::constant(Types::BigInt(1)), name("banana")],
+                    vec![null_id, name("durian")],
+                ],
+            )
+            .unwrap();
+
+            let join = users
+                .clone()
+                .hash_join(orders.clone(), Expression::Column(0), Expression::Column(0))
+                .unwrap();
+            let schema = join.output_schema();
+            assert_eq!(4, schema.columns().len());
+            assert_eq!(Some(3), schema.column_idx("Item"));
+            let rows: Vec<String> = execute(&join, ctx)
+                .unwrap()
+                .iter()
+                .map(|tuple| {
+                    let user = tuple.nth_value(&schema, 1).to_string();
+                    let item = tuple.nth_value(&schema, 3).to_string();
+                    format!("{}:{}", user, item)
+                })
+                .collect();
+            assert_eq!(vec!["alice:apple", "alice:banana"], rows);
+
+            // Keys have to be comparable.
+            assert!(users
+                .clone()
+                .hash_join(orders.clone(), Expression::Column(1), Expression::Column(0))
+                .is_err());
+            assert!(users
+                .hash_join(orders, Expression::Column(0), Expression::Column(2))
+                .is_err());
+        });
+    }
+}
@@ -0,0 +1,207 @@
+// Functionality: Joins the tuples of a child with the tuples of a table, by
+// looking up the key of every child tuple in an index of the table. Indexes
+// are unique, so a child tuple matches at most one tuple of the table.
+
+use crate::catalog::catalog::Oid;
+use crate::catalog::schema::Schema;
+use crate::common::error::*;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::join_tuples;
+use crate::execution::executor::Executor;
+use crate::plan::expression::Expression;
+use crate::plan::plan_node::JoinType;
+use crate::table::tuple::Tuple;
+
+pub struct IndexJoinExecutor<S: StorageBackend = DiskManager> {
+    join_type: JoinType,
+    left_key: Expression,
+    index_name: String,
+    table_oid: Oid,
+    schema: Schema<'static>,
+    left: Box<dyn Executor<S>>,
+}
+
+impl<S: StorageBackend> IndexJoinExecutor<S> {
+    pub fn new(
+        join_type: JoinType,
+        left_key: Expression,
+        index_name: String,
+        table_oid: Oid,
+        schema: Schema<'static>,
+        left: Box<dyn Executor<S>>,
+    ) -> Self {
+        IndexJoinExecutor {
+            join_type,
+            left_key,
+            index_name,
+            table_oid,
+            schema,
+            left,
+        }
+    }
+
+    // Returns the tuple of the table whose key equals the one of |tuple|.
+    fn lookup(
+        &self,
+        ctx: &mut ExecutionContext<S>,
+        tuple: &Tuple,
+    ) -> std::io::Result<Option<Tuple>> {
+        let key = self.left_key.evaluate(tuple, self.left.output_schema())?;
+        if key.is_null() {
+            return Ok(None);
+        }
+        let key = key
+            .borrow()
+            .get_as_i64()
+            .map_err(|_| invalid_data("Index key is not an integer"))?;
+        let index = ctx
+            .catalog
+            .index(&self.index_name)
+            .ok_or_else(|| not_found(&format!("No such index; name = {}", self.index_name)))?;
+        let rid = match index.open(ctx.bpm)?.get_value(ctx.bpm, key)? {
+            Some(rid) => rid,
+            None => return Ok(None),
+        };
+        let table = ctx.table(self.table_oid)?;
+        let tuple = table.heap().get_tuple(ctx.bpm, &rid)?;
+        tuple.map(|tuple| table.upgrade(tuple)).transpose()
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for IndexJoinExecutor<S> {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.left.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        while let Some((_, tuple)) = self.left.next(ctx)? {
+            let right_tuple = self.lookup(ctx, &tuple)?;
+            if right_tuple.is_none() && self.join_type == JoinType::Inner {
+                continue;
+            }
+            let table = ctx.table(self.table_oid)?;
+            let tuple = join_tuples(
+                &tuple,
+                self.left.output_schema(),
+                right_tuple.as_ref(),
+                table.schema(),
+                &self.schema,
+            );
+            return Ok(Some((Rid::default(), tuple)));
+        }
+        Ok(None)
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        &self.schema
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::catalog::Catalog;
+    use crate::catalog::column::Column;
+    use crate::execution::executor::execute;
+    use crate::execution::executor::tests::with_context;
+    use crate::plan::plan_node::PlanNode;
+    use crate::types::types::Str;
+    use crate::types::types::Operation;
+    use crate::types::types::Types;
+    use crate::types::types::Varlen;
+
+    fn orders() -> PlanNode {
+        let schema = Schema::new(vec![
+            Column::new("UserId".to_string(), Types::integer(), 4),
+            Column::new("Item".to_string(), Types::owned(), 10),
+        ]);
+        let row = |id: Option<i32>, item: &str| {
+            let id = match id {
+                Some(id) => Expression::constant(Types::Integer(id)),
+                None => Expression::null(Types::integer()),
+            };
+            let item = Types::Varchar(Varlen::Owned(Str::Val(item.to_string())));
+            vec![id, Expression::constant(item)]
+        };
+        let rows = vec![
+            row(Some(2), "apple"),
+            row(Some(7), "banana"),
+            row(None, "cherry"),
+            row(Some(1), "durian"),
+        ];
+        PlanNode::values(schema, rows).unwrap()
+    }
+
+    #[test]
+    fn index_join() {
+        let setup = |catalog: &mut Catalog, bpm: &mut _| {
+            let schema = Schema::new(vec![
+                Column::new("Id".to_string(), Types::bigint(), 8),
+                Column::new("Score".to_string(), Types::decimal(), 8),
+            ]);
+            catalog.create_table(bpm, 1, "users", schema).unwrap();
+            catalog
+                .create_index(bpm, 1, "users_id", "users", "Id")
+                .unwrap();
+        };
+        with_context(setup, |ctx| {
+            // INSERT INTO users VALUES (1, 0.5), (2, 1.0), (3, 1.5);
+            let schema = ctx.catalog.table("users").unwrap().schema().clone();
+            let rows = (1..4)
+                .map(|id| {
+                    vec![
+                        Expression::constant(Types::BigInt(id)),
+                        Expression::constant(Types::Decimal(id as f64 / 2.0)),
+                    ]
+                })
+                .collect();
+            let values = PlanNode::values(schema, rows).unwrap();
+            let insert = PlanNode::insert(ctx.catalog, "users", values).unwrap();
+            execute(&insert, ctx).unwrap();
+
+            let items = |plan: &PlanNode, ctx: &mut ExecutionContext<_>| -> Vec<String> {
+                let schema = plan.output_schema();
+                execute(plan, ctx)
+                    .unwrap()
+                    .iter()
+                    .map(|tuple| {
+                        let item = tuple.nth_value(&schema, 1).to_string();
+                        match tuple.nth_is_null(&schema, 3) {
+                            true => format!("{}:-", item),
+                            false => {
+                                format!("{}:{}", item, tuple.nth_value(&schema, 3).to_string())
+                            }
+                        }
+                    })
+                    .collect()
+            };
+            let key = Expression::Column(0);
+            let inner = orders()
+                .index_join(ctx.catalog, "users_id", key.clone(), JoinType::Inner)
+                .unwrap();
+            assert_eq!(vec!["apple:1", "durian:0.5"], items(&inner, ctx));
+            let outer = orders()
+                .index_join(ctx.catalog, "users_id", key.clone(), JoinType::LeftOuter)
+                .unwrap();
+            assert_eq!(
+                vec!["apple:1", "banana:-", "cherry:-", "durian:0.5"],
+                items(&outer, ctx)
+            );
+
+            assert!(orders()
+                .index_join(ctx.catalog, "items_id", key, JoinType::Inner)
+                .is_err());
+            assert!(orders()
+                .index_join(
+                    ctx.catalog,
+                    "users_id",
+                    Expression::Column(1),
+                    JoinType::Inner
+                )
+                .is_err());
+        });
+    }
+}
@@ -0,0 +1,63 @@
+// Functionality: Produces the tuple of a table with a given key, by looking it
+// up in an index of the table.
+
+use crate::catalog::catalog::Oid;
+use crate::catalog::schema::Schema;
+use crate::common::error::*;
+use crate::common::rid::Rid;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::Executor;
+use crate::table::tuple::Tuple;
+
+pub struct IndexScanExecutor {
+    table_oid: Oid,
+    index_name: String,
+    key: i64,
+    schema: Schema<'static>,
+    // Whether the key was looked up already.
+    done: bool,
+}
+
+impl IndexScanExecutor {
+    pub fn new(table_oid: Oid, index_name: String, key: i64, schema: Schema<'static>) -> Self {
+        IndexScanExecutor {
+            table_oid,
+            index_name,
+            key,
+            schema,
+            done: false,
+        }
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for IndexScanExecutor {
+    fn init(&mut self, _ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.done = false;
+        Ok(())
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        if self.done {
+            return Ok(None);
+        }
+        self.done = true;
+        let index = ctx
+            .catalog
+            .index(&self.index_name)
+            .ok_or_else(|| not_found(&format!("No such index; name = {}", self.index_name)))?;
+        let rid = match index.open(ctx.bpm)?.get_value(ctx.bpm, self.key)? {
+            Some(rid) => rid,
+            None => return Ok(None),
+        };
+        let table = ctx.table(self.table_oid)?;
+        let tuple = table.heap().get_tuple(ctx.bpm, &rid)?;
+        tuple
+            .map(|tuple| Ok((rid, table.upgrade(tuple)?)))
+            .transpose()
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        &self.schema
+    }
+}
@@ -0,0 +1,138 @@
+// Functionality: Inserts the tuples of its child into a table, converting
+// their values to the column types of the table, and produces the number of
+// tuples inserted. A tuple violating a check constraint of the table fails
+// the insert; see |TableInfo::check_constraints|.
+//
+// With |OnConflict| set, the index it names is probed for the key of every
+// tuple first, and a tuple whose key is taken updates the existing tuple
+// instead, in the same transaction; updated tuples are counted as well. A
+// tuple may thus update one inserted earlier by the same statement.
+//
+// All tuples of the child are read before the first one is inserted, so that
+// a child reading the same table does not see the new tuples.
+
+use crate::catalog::catalog::Oid;
+use crate::catalog::catalog::TableInfo;
+use crate::catalog::schema::Schema;
+use crate::common::error::*;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::conform;
+use crate::execution::execution_context::conform_value;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::count_tuple;
+use crate::execution::executor::drain;
+use crate::execution::executor::join_tuples;
+use crate::execution::executor::Executor;
+use crate::plan::plan_node::conflict_schema;
+use crate::plan::plan_node::OnConflict;
+use crate::table::tuple::Tuple;
+
+pub struct InsertExecutor<S: StorageBackend = DiskManager> {
+    table_oid: Oid,
+    on_conflict: Option<OnConflict>,
+    schema: Schema<'static>,
+    child: Box<dyn Executor<S>>,
+    done: bool,
+}
+
+impl<S: StorageBackend> InsertExecutor<S> {
+    pub fn new(
+        table_oid: Oid,
+        on_conflict: Option<OnConflict>,
+        schema: Schema<'static>,
+        child: Box<dyn Executor<S>>,
+    ) -> Self {
+        InsertExecutor {
+            table_oid,
+            on_conflict,
+            schema,
+            child,
+            done: false,
+        }
+    }
+
+    // Returns the RID and the tuple of |table| |tuple| conflicts with, if any.
+    fn find_conflict(
+        &self,
+        ctx: &mut ExecutionContext<S>,
+        table: &TableInfo,
+        tuple: &Tuple,
+    ) -> std::io::Result<Option<(Rid, Tuple)>> {
+        let on_conflict = match &self.on_conflict {
+            Some(on_conflict) => on_conflict,
+            None => return Ok(None),
+        };
+        let index = ctx.catalog.index(&on_conflict.index_name).ok_or_else(|| {
+            not_found(&format!("No such index; name = {}", on_conflict.index_name))
+        })?;
+        let value = tuple.nth_value(table.schema(), index.key_column());
+        if value.is_null() {
+            return Ok(None);
+        }
+        let key = value
+            .borrow()
+            .get_as_i64()
+            .map_err(|_| invalid_data("Index key is not an integer"))?;
+        let rid = match index.open(ctx.bpm)?.get_value(ctx.bpm, key)? {
+            Some(rid) => rid,
+            None => return Ok(None),
+        };
+        match table.heap().get_tuple(ctx.bpm, &rid)? {
+            Some(existing) => Ok(Some((rid, table.upgrade(existing)?))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for InsertExecutor<S> {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.done = false;
+        self.child.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        if self.done {
+            return Ok(None);
+        }
+        self.done = true;
+        let table = ctx.table(self.table_oid)?;
+        let schema = table.schema();
+        let conflict_schema = conflict_schema(schema);
+        let items = drain(self.child.as_mut(), ctx)?;
+        let input = self.child.output_schema();
+        for (_, tuple) in items.iter() {
+            let values = (0..input.columns().len())
+                .map(|idx| tuple.nth_value(input, idx))
+                .collect();
+            let tuple = conform(values, schema)?;
+            let (rid, existing) = match self.find_conflict(ctx, table, &tuple)? {
+                Some(conflict) => conflict,
+                None => {
+                    table.check_constraints(&tuple)?;
+                    ctx.insert_tuple(table, tuple)?;
+                    continue;
+                }
+            };
+            // Assignments see the existing tuple followed by the proposed one.
+            let joined = join_tuples(&existing, schema, Some(&tuple), schema, &conflict_schema);
+            let mut new_tuple = existing.clone();
+            for (idx, expression) in self.on_conflict.as_ref().unwrap().assignments.iter() {
+                let value = expression.evaluate(&joined, &conflict_schema)?;
+                let value = conform_value(value, schema.nth_column(*idx).unwrap())?;
+                new_tuple.set_nth_value(schema, *idx, &value)?;
+            }
+            table.check_constraints(&new_tuple)?;
+            ctx.update_tuple(table, &rid, &existing, new_tuple)?;
+        }
+        Ok(Some((
+            Rid::default(),
+            count_tuple(&self.schema, items.len()),
+        )))
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        &self.schema
+    }
+}
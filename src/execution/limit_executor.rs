@@ -0,0 +1,48 @@
+// Functionality: Produces at most a given number of tuples of its child.
+
+use crate::catalog::schema::Schema;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::Executor;
+use crate::table::tuple::Tuple;
+
+pub struct LimitExecutor<S: StorageBackend = DiskManager> {
+    limit: usize,
+    // The number of tuples produced so far.
+    produced: usize,
+    child: Box<dyn Executor<S>>,
+}
+
+impl<S: StorageBackend> LimitExecutor<S> {
+    pub fn new(limit: usize, child: Box<dyn Executor<S>>) -> Self {
+        LimitExecutor {
+            limit,
+            produced: 0,
+            child,
+        }
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for LimitExecutor<S> {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.produced = 0;
+        self.child.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        if self.produced >= self.limit {
+            return Ok(None);
+        }
+        let item = self.child.next(ctx)?;
+        if item.is_some() {
+            self.produced += 1;
+        }
+        Ok(item)
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        self.child.output_schema()
+    }
+}
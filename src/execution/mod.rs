@@ -0,0 +1,17 @@
+pub mod aggregate_executor;
+pub mod delete_executor;
+pub mod execution_context;
+pub mod executor;
+pub mod filter_executor;
+pub mod hash_join_executor;
+pub mod index_join_executor;
+pub mod index_scan_executor;
+pub mod insert_executor;
+pub mod limit_executor;
+pub mod nested_loop_join_executor;
+pub mod projection_executor;
+pub mod seq_scan_executor;
+pub mod sort_executor;
+pub mod top_n_executor;
+pub mod update_executor;
+pub mod values_executor;
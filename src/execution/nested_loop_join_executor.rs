@@ -0,0 +1,174 @@
+// Functionality: Joins the tuples of two children on an arbitrary predicate,
+// by testing every pair of tuples. The tuples of the inner (right) child are
+// read once and kept in memory, so it is meant for small inner inputs.
+
+use crate::catalog::schema::Schema;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::drain;
+use crate::execution::executor::join_tuples;
+use crate::execution::executor::Executor;
+use crate::plan::expression::Expression;
+use crate::plan::plan_node::JoinType;
+use crate::table::tuple::Tuple;
+
+pub struct NestedLoopJoinExecutor<S: StorageBackend = DiskManager> {
+    join_type: JoinType,
+    predicate: Expression,
+    schema: Schema<'static>,
+    left: Box<dyn Executor<S>>,
+    right: Box<dyn Executor<S>>,
+    right_tuples: Vec<Tuple>,
+    // The left tuple being joined, the index of the next right tuple to test
+    // it with, and whether it matched any right tuple so far.
+    left_tuple: Option<Tuple>,
+    right_idx: usize,
+    matched: bool,
+}
+
+impl<S: StorageBackend> NestedLoopJoinExecutor<S> {
+    pub fn new(
+        join_type: JoinType,
+        predicate: Expression,
+        schema: Schema<'static>,
+        left: Box<dyn Executor<S>>,
+        right: Box<dyn Executor<S>>,
+    ) -> Self {
+        NestedLoopJoinExecutor {
+            join_type,
+            predicate,
+            schema,
+            left,
+            right,
+            right_tuples: Vec::new(),
+            left_tuple: None,
+            right_idx: 0,
+            matched: false,
+        }
+    }
+
+    fn join(&self, left_tuple: &Tuple, right_tuple: Option<&Tuple>) -> Tuple {
+        join_tuples(
+            left_tuple,
+            self.left.output_schema(),
+            right_tuple,
+            self.right.output_schema(),
+            &self.schema,
+        )
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for NestedLoopJoinExecutor<S> {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.left.init(ctx)?;
+        self.right.init(ctx)?;
+        self.right_tuples = drain(self.right.as_mut(), ctx)?
+            .into_iter()
+            .map(|(_, tuple)| tuple)
+            .collect();
+        self.left_tuple = None;
+        Ok(())
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        loop {
+            if let Some(left_tuple) = &self.left_tuple {
+                while self.right_idx < self.right_tuples.len() {
+                    let right_tuple = &self.right_tuples[self.right_idx];
+                    self.right_idx += 1;
+                    let tuple = self.join(left_tuple, Some(right_tuple));
+                    if self.predicate.evaluate_predicate(&tuple, &self.schema)? {
+                        self.matched = true;
+                        return Ok(Some((Rid::default(), tuple)));
+                    }
+                }
+                let unmatched = !self.matched && self.join_type == JoinType::LeftOuter;
+                let tuple = self.left_tuple.take().unwrap();
+                if unmatched {
+                    return Ok(Some((Rid::default(), self.join(&tuple, None))));
+                }
+            }
+            match self.left.next(ctx)? {
+                Some((_, tuple)) => self.left_tuple = Some(tuple),
+                None => return Ok(None),
+            }
+            self.right_idx = 0;
+            self.matched = false;
+        }
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        &self.schema
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::column::Column;
+    use crate::disk::memory_storage::MemoryStorage;
+    use crate::execution::executor::execute;
+    use crate::execution::executor::tests::with_context;
+    use crate::plan::expression::ComparisonOp;
+    use crate::plan::plan_node::PlanNode;
+    use crate::types::types::Operation;
+    use crate::types::types::Types;
+
+    fn values(name: &str, ids: &[i32]) -> PlanNode {
+        let schema = Schema::new(vec![Column::new(name.to_string(), Types::integer(), 4)]);
+        let rows = ids
+            .iter()
+            .map(|id| vec![Expression::constant(Types::Integer(*id))])
+            .collect();
+        PlanNode::values(schema, rows).unwrap()
+    }
+
+    fn pairs(plan: &PlanNode, ctx: &mut ExecutionContext<MemoryStorage>) -> Vec<String> {
+        let schema = plan.output_schema();
+        execute(plan, ctx)
+            .unwrap()
+            .iter()
+            .map(|tuple| {
+                let left = tuple.nth_value(&schema, 0).to_string();
+                match tuple.nth_is_null(&schema, 1) {
+                    true => format!("{}:-", left),
+                    false => format!("{}:{}", left, tuple.nth_value(&schema, 1).to_string()),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested_loop_join() {
+        let setup = |_: &mut _, _: &mut _| ();
+        with_context(setup, |ctx| {
+            // Joins on L < R.
+            let less = Expression::Comparison(
+                ComparisonOp::Lt,
+                Box::new(Expression::Column(0)),
+                Box::new(Expression::Column(1)),
+            );
+            let left = values("L", &[1, 2, 3]);
+            let right = values("R", &[3, 2]);
+
+            let inner = left
+                .clone()
+                .nested_loop_join(right.clone(), less.clone(), JoinType::Inner)
+                .unwrap();
+            assert_eq!(vec!["1:3", "1:2", "2:3"], pairs(&inner, ctx));
+
+            let outer = left
+                .clone()
+                .nested_loop_join(right.clone(), less, JoinType::LeftOuter)
+                .unwrap();
+            assert_eq!(vec!["1:3", "1:2", "2:3", "3:-"], pairs(&outer, ctx));
+
+            // The predicate has to reference columns of either side.
+            assert!(left
+                .nested_loop_join(right, Expression::Column(2), JoinType::Inner)
+                .is_err());
+        });
+    }
+}
@@ -0,0 +1,55 @@
+// Functionality: Computes the output columns of every tuple of its child.
+
+use crate::catalog::schema::Schema;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::conform;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::Executor;
+use crate::plan::expression::Expression;
+use crate::table::tuple::Tuple;
+
+pub struct ProjectionExecutor<S: StorageBackend = DiskManager> {
+    expressions: Vec<Expression>,
+    schema: Schema<'static>,
+    child: Box<dyn Executor<S>>,
+}
+
+impl<S: StorageBackend> ProjectionExecutor<S> {
+    pub fn new(
+        expressions: Vec<Expression>,
+        schema: Schema<'static>,
+        child: Box<dyn Executor<S>>,
+    ) -> Self {
+        ProjectionExecutor {
+            expressions,
+            schema,
+            child,
+        }
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for ProjectionExecutor<S> {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.child.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        let tuple = match self.child.next(ctx)? {
+            Some((_, tuple)) => tuple,
+            None => return Ok(None),
+        };
+        let input = self.child.output_schema();
+        let values = self
+            .expressions
+            .iter()
+            .map(|expression| expression.evaluate(&tuple, input))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(Some((Rid::default(), conform(values, &self.schema)?)))
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        &self.schema
+    }
+}
@@ -0,0 +1,52 @@
+// Functionality: Produces every live tuple of a table, in storage order.
+
+use crate::catalog::catalog::Oid;
+use crate::catalog::schema::Schema;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::rid::Rid;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::Executor;
+use crate::table::table_iterator::ScanPosition;
+use crate::table::table_iterator::TableIterator;
+use crate::table::tuple::Tuple;
+
+pub struct SeqScanExecutor {
+    table_oid: Oid,
+    schema: Schema<'static>,
+    // Where the scan continues from.
+    position: ScanPosition,
+}
+
+impl SeqScanExecutor {
+    pub fn new(table_oid: Oid, schema: Schema<'static>) -> Self {
+        SeqScanExecutor {
+            table_oid,
+            schema,
+            position: ScanPosition::new(INVALID_PAGE_ID),
+        }
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for SeqScanExecutor {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.position = ScanPosition::new(ctx.table(self.table_oid)?.first_page_id());
+        Ok(())
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        let mut iter = TableIterator::resume(ctx.bpm, self.position);
+        let item = iter.next();
+        self.position = iter.position();
+        let table = ctx.table(self.table_oid)?;
+        item.map(|item| {
+            let (rid, tuple) = item?;
+            Ok((rid, table.upgrade(tuple)?))
+        })
+        .transpose()
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        &self.schema
+    }
+}
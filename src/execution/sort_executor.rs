@@ -0,0 +1,88 @@
+// Functionality: Produces the tuples of its child in order. All tuples are
+// read and sorted by |init|.
+
+use crate::catalog::schema::Schema;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::Executor;
+use crate::index::key::compare_values;
+use crate::plan::expression::Expression;
+use crate::plan::plan_node::OrderDirection;
+use crate::table::tuple::Tuple;
+use crate::types::value::Value;
+
+use std::cmp::Ordering;
+
+pub struct SortExecutor<S: StorageBackend = DiskManager> {
+    order_bys: Vec<(Expression, OrderDirection)>,
+    child: Box<dyn Executor<S>>,
+    // The sorted tuples, in reverse order so that they are popped in order.
+    tuples: Vec<Tuple>,
+}
+
+impl<S: StorageBackend> SortExecutor<S> {
+    pub fn new(order_bys: Vec<(Expression, OrderDirection)>, child: Box<dyn Executor<S>>) -> Self {
+        SortExecutor {
+            order_bys,
+            child,
+            tuples: Vec::new(),
+        }
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for SortExecutor<S> {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.child.init(ctx)?;
+        let mut keyed = Vec::new();
+        while let Some((_, tuple)) = self.child.next(ctx)? {
+            let keys = sort_keys(&self.order_bys, &tuple, self.child.output_schema())?;
+            keyed.push((keys, tuple));
+        }
+        // The sort is stable, so that equal tuples keep the order of the child.
+        keyed.sort_by(|(lhs, _), (rhs, _)| compare_keys(lhs, rhs, &self.order_bys));
+        self.tuples = keyed.into_iter().rev().map(|(_, tuple)| tuple).collect();
+        Ok(())
+    }
+
+    fn next(&mut self, _ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        Ok(self.tuples.pop().map(|tuple| (Rid::default(), tuple)))
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        self.child.output_schema()
+    }
+}
+
+// Returns the values of the order-by expressions for |tuple|.
+pub(crate) fn sort_keys(
+    order_bys: &[(Expression, OrderDirection)],
+    tuple: &Tuple,
+    schema: &Schema,
+) -> std::io::Result<Vec<Value<'static>>> {
+    let mut keys = Vec::new();
+    for (expression, _) in order_bys.iter() {
+        let key = expression.evaluate(tuple, schema)?;
+        keys.push(key.to_static());
+    }
+    Ok(keys)
+}
+
+// Compares the keys of two tuples in the order given by |order_bys|.
+pub(crate) fn compare_keys(
+    lhs: &[Value],
+    rhs: &[Value],
+    order_bys: &[(Expression, OrderDirection)],
+) -> Ordering {
+    for ((lhs, rhs), (_, direction)) in lhs.iter().zip(rhs.iter()).zip(order_bys.iter()) {
+        let ordering = match direction {
+            OrderDirection::Asc => compare_values(lhs, rhs),
+            OrderDirection::Desc => compare_values(rhs, lhs),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
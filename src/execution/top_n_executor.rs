@@ -0,0 +1,180 @@
+// Functionality: Produces the first N tuples of its child in order, e.g. for
+// ORDER BY ... LIMIT N. Instead of sorting all tuples, it keeps the N smallest
+// ones seen so far in a max-heap, and evicts the largest when it overflows.
+
+use crate::catalog::schema::Schema;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::Executor;
+use crate::execution::sort_executor::compare_keys;
+use crate::execution::sort_executor::sort_keys;
+use crate::plan::expression::Expression;
+use crate::plan::plan_node::OrderDirection;
+use crate::table::tuple::Tuple;
+use crate::types::value::Value;
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::rc::Rc;
+
+pub struct TopNExecutor<S: StorageBackend = DiskManager> {
+    order_bys: Rc<Vec<(Expression, OrderDirection)>>,
+    limit: usize,
+    child: Box<dyn Executor<S>>,
+    // The first tuples in reverse order, so that they are popped in order.
+    tuples: Vec<Tuple>,
+}
+
+// A tuple in the heap, ordered by its keys, then by its position in the child
+// so that equal tuples keep the order of the child.
+struct Entry {
+    keys: Vec<Value<'static>>,
+    seq: usize,
+    tuple: Tuple,
+    order_bys: Rc<Vec<(Expression, OrderDirection)>>,
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_keys(&self.keys, &other.keys, &self.order_bys).then(self.seq.cmp(&other.seq))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl<S: StorageBackend> TopNExecutor<S> {
+    pub fn new(
+        order_bys: Vec<(Expression, OrderDirection)>,
+        limit: usize,
+        child: Box<dyn Executor<S>>,
+    ) -> Self {
+        TopNExecutor {
+            order_bys: Rc::new(order_bys),
+            limit,
+            child,
+            tuples: Vec::new(),
+        }
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for TopNExecutor<S> {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.child.init(ctx)?;
+        let mut heap = BinaryHeap::with_capacity(self.limit + 1);
+        let mut seq = 0;
+        while let Some((_, tuple)) = self.child.next(ctx)? {
+            heap.push(Entry {
+                keys: sort_keys(&self.order_bys, &tuple, self.child.output_schema())?,
+                seq,
+                tuple,
+                order_bys: self.order_bys.clone(),
+            });
+            if heap.len() > self.limit {
+                heap.pop();
+            }
+            seq += 1;
+        }
+        self.tuples = heap
+            .into_sorted_vec()
+            .into_iter()
+            .rev()
+            .map(|entry| entry.tuple)
+            .collect();
+        Ok(())
+    }
+
+    fn next(&mut self, _ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        Ok(self.tuples.pop().map(|tuple| (Rid::default(), tuple)))
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        self.child.output_schema()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::column::Column;
+    use crate::disk::memory_storage::MemoryStorage;
+    use crate::execution::executor::execute;
+    use crate::execution::executor::tests::with_context;
+    use crate::plan::plan_node::PlanNode;
+    use crate::types::types::Operation;
+    use crate::types::types::Types;
+
+    fn scores() -> PlanNode {
+        let schema = Schema::new(vec![
+            Column::new("Id".to_string(), Types::integer(), 4),
+            Column::new("Score".to_string(), Types::decimal(), 8),
+        ]);
+        let scores = [Some(2.5), None, Some(1.0), Some(2.5), Some(4.0), Some(1.0)];
+        let rows = scores
+            .iter()
+            .enumerate()
+            .map(|(id, score)| {
+                let score = match score {
+                    Some(score) => Expression::constant(Types::Decimal(*score)),
+                    None => Expression::null(Types::decimal()),
+                };
+                vec![Expression::constant(Types::Integer(id as i32)), score]
+            })
+            .collect();
+        PlanNode::values(schema, rows).unwrap()
+    }
+
+    fn ids(plan: &PlanNode, ctx: &mut ExecutionContext<MemoryStorage>) -> Vec<String> {
+        let schema = plan.output_schema();
+        execute(plan, ctx)
+            .unwrap()
+            .iter()
+            .map(|tuple| tuple.nth_value(&schema, 0).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn top_n() {
+        let setup = |_: &mut _, _: &mut _| ();
+        with_context(setup, |ctx| {
+            let score = Expression::Column(1);
+            let id = Expression::Column(0);
+
+            // ORDER BY Score DESC, Id ASC;
+            let order_bys = vec![
+                (score.clone(), OrderDirection::Desc),
+                (id, OrderDirection::Asc),
+            ];
+            let sort = scores().sort(order_bys).unwrap();
+            assert_eq!(vec!["4", "0", "3", "2", "5", "1"], ids(&sort, ctx));
+
+            // ORDER BY Score DESC, Id ASC LIMIT 3;
+            let top_n = sort.clone().limit(3);
+            assert!(matches!(top_n, PlanNode::TopN { limit: 3, .. }));
+            assert_eq!(vec!["4", "0", "3"], ids(&top_n, ctx));
+            assert!(ids(&sort.clone().limit(0), ctx).is_empty());
+            assert_eq!(6, ids(&sort.limit(10), ctx).len());
+
+            // ORDER BY Score LIMIT 4; ties keep the order of the child.
+            let sort = scores().sort(vec![(score, OrderDirection::Asc)]).unwrap();
+            assert_eq!(vec!["1", "2", "5", "0"], ids(&sort.limit(4), ctx));
+
+            assert!(scores()
+                .sort(vec![(Expression::Column(2), OrderDirection::Asc)])
+                .is_err());
+        });
+    }
+}
@@ -0,0 +1,82 @@
+// Functionality: Sets columns of the tuples of its child, which reads them
+// from a table, and produces the number of tuples updated. A tuple violating
+// a check constraint of the table once updated fails the update; see
+// |TableInfo::check_constraints|.
+//
+// All tuples of the child are read before the first one is updated, so that
+// an updated tuple moved further down the table is not updated again.
+
+use crate::catalog::catalog::Oid;
+use crate::catalog::schema::Schema;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::conform_value;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::count_tuple;
+use crate::execution::executor::drain;
+use crate::execution::executor::Executor;
+use crate::plan::expression::Expression;
+use crate::table::tuple::Tuple;
+
+pub struct UpdateExecutor<S: StorageBackend = DiskManager> {
+    table_oid: Oid,
+    // Column indices with the expressions computing their new values.
+    assignments: Vec<(usize, Expression)>,
+    schema: Schema<'static>,
+    child: Box<dyn Executor<S>>,
+    done: bool,
+}
+
+impl<S: StorageBackend> UpdateExecutor<S> {
+    pub fn new(
+        table_oid: Oid,
+        assignments: Vec<(usize, Expression)>,
+        schema: Schema<'static>,
+        child: Box<dyn Executor<S>>,
+    ) -> Self {
+        UpdateExecutor {
+            table_oid,
+            assignments,
+            schema,
+            child,
+            done: false,
+        }
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for UpdateExecutor<S> {
+    fn init(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.done = false;
+        self.child.init(ctx)
+    }
+
+    fn next(&mut self, ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        if self.done {
+            return Ok(None);
+        }
+        self.done = true;
+        let table = ctx.table(self.table_oid)?;
+        let schema = table.schema();
+        let items = drain(self.child.as_mut(), ctx)?;
+        for (rid, tuple) in items.iter() {
+            // Assignments see the values before the update.
+            let mut new_tuple = tuple.clone();
+            for (idx, expression) in self.assignments.iter() {
+                let value = expression.evaluate(tuple, schema)?;
+                let value = conform_value(value, schema.nth_column(*idx).unwrap())?;
+                new_tuple.set_nth_value(schema, *idx, &value)?;
+            }
+            table.check_constraints(&new_tuple)?;
+            ctx.update_tuple(table, rid, tuple, new_tuple)?;
+        }
+        Ok(Some((
+            Rid::default(),
+            count_tuple(&self.schema, items.len()),
+        )))
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        &self.schema
+    }
+}
@@ -0,0 +1,53 @@
+// Functionality: Produces the given rows of constant expressions.
+
+use crate::catalog::schema::Schema;
+use crate::common::rid::Rid;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::conform;
+use crate::execution::execution_context::ExecutionContext;
+use crate::execution::executor::Executor;
+use crate::plan::expression::Expression;
+use crate::table::tuple::Tuple;
+
+pub struct ValuesExecutor {
+    schema: Schema<'static>,
+    rows: Vec<Vec<Expression>>,
+    // The index of the next row.
+    cursor: usize,
+}
+
+impl ValuesExecutor {
+    pub fn new(schema: Schema<'static>, rows: Vec<Vec<Expression>>) -> Self {
+        ValuesExecutor {
+            schema,
+            rows,
+            cursor: 0,
+        }
+    }
+}
+
+impl<S: StorageBackend> Executor<S> for ValuesExecutor {
+    fn init(&mut self, _ctx: &mut ExecutionContext<S>) -> std::io::Result<()> {
+        self.cursor = 0;
+        Ok(())
+    }
+
+    fn next(&mut self, _ctx: &mut ExecutionContext<S>) -> std::io::Result<Option<(Rid, Tuple)>> {
+        let row = match self.rows.get(self.cursor) {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        self.cursor += 1;
+        // The expressions do not reference columns.
+        let (empty_tuple, empty_schema) = (Tuple::default(), Schema::new(Vec::new()));
+        let values = row
+            .iter()
+            .map(|expression| expression.evaluate(&empty_tuple, &empty_schema))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(Some((Rid::default(), conform(values, &self.schema)?)))
+    }
+
+    fn output_schema(&self) -> &Schema<'static> {
+        &self.schema
+    }
+}
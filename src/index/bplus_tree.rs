@@ -0,0 +1,1304 @@
+// Functionality: A B+ tree index with unique keys, mapping keys to RIDs.
+// Pages live in the buffer pool as |BPlusTreePage| frames, viewed as
+// |BPlusTreeInternalPage| or |BPlusTreeLeafPage| depending on their type.
+//
+// The root page ID is tracked in the header page under the index name, so that
+// the index can be reopened. Pages are pinned only while being read or
+// written; structural changes (splits and merges) work on copies of the
+// entries, and walk back up along the path recorded while descending.
+//
+// Like in a B-link tree, every page links to its right sibling and records
+// the high key bounding its keys. A split first writes the new right page and
+// then the left one, which links to it, before updating the parent. Until the
+// parent is updated (e.g. forever, if a crash gets in between), a descent
+// reaching the left page for a key >= its high key moves right. |repair|
+// completes such half-finished splits.
+
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::config::PageId;
+use crate::common::error::*;
+use crate::common::rid::Rid;
+use crate::disk::storage_backend::StorageBackend;
+use crate::disk::tablespace::PRIMARY_FILE_ID;
+use crate::index::index_iterator::IndexIterator;
+use crate::index::index_util::*;
+use crate::index::key::IndexKey;
+use crate::page::bplus_tree_internal_page::internal_page_capacity;
+use crate::page::bplus_tree_internal_page::BPlusTreeInternalPage;
+use crate::page::bplus_tree_leaf_page::leaf_page_capacity;
+use crate::page::bplus_tree_leaf_page::BPlusTreeLeafPage;
+use crate::page::bplus_tree_page::BPlusTreePage;
+use crate::page::page::Page;
+use crate::page::table_page::TablePage;
+
+pub struct BPlusTree<K: IndexKey = i64> {
+    index_name: String,
+    root_page_id: PageId,
+    key_schema: K::KeySchema,
+    // The maximum number of entries of leaf pages.
+    leaf_max_size: usize,
+    // The maximum number of children of internal pages.
+    internal_max_size: usize,
+}
+
+// Integer keys.
+impl BPlusTree {
+    // Opens the index named |index_name|, creating it if the header page has
+    // no record for it.
+    pub fn new<S: StorageBackend>(
+        index_name: &str,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<Self> {
+        Self::with_key_schema(index_name, bpm, ())
+    }
+
+    // Same as |new|, with custom page fan-outs (e.g. small ones for testing).
+    pub fn with_max_sizes<S: StorageBackend>(
+        index_name: &str,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        leaf_max_size: usize,
+        internal_max_size: usize,
+    ) -> std::io::Result<Self> {
+        Self::open(index_name, bpm, (), leaf_max_size, internal_max_size)
+    }
+}
+
+impl<K: IndexKey> BPlusTree<K> {
+    // Opens the index named |index_name| with keys of |key_schema|, creating
+    // it if the header page has no record for it. Returns |InvalidInput| if
+    // the keys are too large for the pages, and |InvalidData| if the index
+    // has keys of another size.
+    pub fn with_key_schema<S: StorageBackend>(
+        index_name: &str,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        key_schema: K::KeySchema,
+    ) -> std::io::Result<Self> {
+        let key_size = K::serialized_len(&key_schema);
+        let leaf_max_size = leaf_page_capacity(key_size);
+        let internal_max_size = internal_page_capacity(key_size);
+        Self::open(index_name, bpm, key_schema, leaf_max_size, internal_max_size)
+    }
+
+    fn open<S: StorageBackend>(
+        index_name: &str,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        key_schema: K::KeySchema,
+        leaf_max_size: usize,
+        internal_max_size: usize,
+    ) -> std::io::Result<Self> {
+        let key_size = K::serialized_len(&key_schema);
+        if !(2..=leaf_page_capacity(key_size)).contains(&leaf_max_size) {
+            return Err(invalid_input("Leaf max size is out of range"));
+        }
+        if !(3..=internal_page_capacity(key_size)).contains(&internal_max_size) {
+            return Err(invalid_input("Internal max size is out of range"));
+        }
+        let root_page_id = find_or_insert_root(bpm, index_name, INVALID_PAGE_ID)?;
+        if root_page_id != INVALID_PAGE_ID
+            && with_page(bpm, root_page_id, false, |page| page.key_size())? != key_size
+        {
+            return Err(invalid_data("Index key size does not match"));
+        }
+        Ok(BPlusTree {
+            index_name: index_name.to_string(),
+            root_page_id,
+            key_schema,
+            leaf_max_size,
+            internal_max_size,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root_page_id == INVALID_PAGE_ID
+    }
+
+    pub fn index_name(&self) -> &str {
+        &self.index_name
+    }
+
+    pub fn key_schema(&self) -> &K::KeySchema {
+        &self.key_schema
+    }
+
+    // Renames the index, i.e. its record in the header page. Returns
+    // |AlreadyExists| if another index or table has |new_name|.
+    pub fn rename<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        new_name: &str,
+    ) -> std::io::Result<()> {
+        rename_root(bpm, &self.index_name, new_name)?;
+        self.index_name = new_name.to_string();
+        Ok(())
+    }
+
+    pub fn root_page_id(&self) -> PageId {
+        self.root_page_id
+    }
+
+    // Returns the RID stored under |key|, if any.
+    pub fn get_value<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        key: K,
+    ) -> std::io::Result<Option<Rid>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let leaf_id = *self.find_leaf_path(bpm, &key)?.last().unwrap();
+        with_page(bpm, leaf_id, false, |page| {
+            BPlusTreeLeafPage::cast(page).lookup(&self.key_schema, &key)
+        })
+    }
+
+    // Inserts |key| with its |rid|. Returns false if the key already exists,
+    // and |InvalidInput| if it does not match the key schema.
+    pub fn insert<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        key: K,
+        rid: Rid,
+    ) -> std::io::Result<bool> {
+        self.check_key(&key)?;
+        if self.is_empty() {
+            let page = new_page(bpm)?;
+            let root_id = page.page_id();
+            let leaf = BPlusTreeLeafPage::cast_mut(page);
+            leaf.init(self.key_size());
+            let result = leaf.set_entries(&self.key_schema, &[(key, rid)]);
+            bpm.unpin_page(root_id, /*is_dirty=*/ true)?;
+            result?;
+            self.set_root_page_id(bpm, root_id)?;
+            return Ok(true);
+        }
+
+        let path = self.find_leaf_path(bpm, &key)?;
+        let leaf_id = *path.last().unwrap();
+        let mut entries = self.read_leaf(bpm, leaf_id)?;
+        match entries.binary_search_by(|entry| entry.0.cmp(&key)) {
+            Ok(_) => return Ok(false),
+            Err(idx) => entries.insert(idx, (key, rid)),
+        }
+        if entries.len() <= self.leaf_max_size {
+            self.write_leaf(bpm, leaf_id, &entries)?;
+            return Ok(true);
+        }
+
+        let (sep, right_id) = self.split_leaf(bpm, leaf_id, entries)?;
+        let ancestors = &path[..(path.len() - 1)];
+        self.insert_into_parent(bpm, ancestors, leaf_id, sep, right_id)?;
+        Ok(true)
+    }
+
+    // Removes |key|. Returns false if the key does not exist.
+    pub fn remove<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        key: K,
+    ) -> std::io::Result<bool> {
+        if self.is_empty() {
+            return Ok(false);
+        }
+        let path = self.find_leaf_path(bpm, &key)?;
+        let leaf_id = *path.last().unwrap();
+        let mut entries = self.read_leaf(bpm, leaf_id)?;
+        match entries.binary_search_by(|entry| entry.0.cmp(&key)) {
+            Ok(idx) => entries.remove(idx),
+            Err(_) => return Ok(false),
+        };
+        self.write_leaf(bpm, leaf_id, &entries)?;
+
+        if path.len() == 1 {
+            // The root leaf may shrink to empty, which empties the tree.
+            if entries.is_empty() {
+                bpm.delete_page(leaf_id)?;
+                self.set_root_page_id(bpm, INVALID_PAGE_ID)?;
+            }
+        } else if entries.len() < min_size(self.leaf_max_size) {
+            self.rebalance(bpm, &path)?;
+        }
+        Ok(true)
+    }
+
+    // Fills the empty index with |entries|, whose keys need to be strictly
+    // ascending, bottom-up: the leaves are filled evenly up to the maximum
+    // size, then each level of internal pages above them, so that no page is
+    // ever split. The pages are written straight to disk, bypassing the pool;
+    // see |BufferPoolManager::write_pages|. Returns |InvalidInput| if the
+    // index is not empty, the keys are not ascending, or a key does not match
+    // the key schema.
+    pub fn bulk_load<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        entries: &[(K, Rid)],
+    ) -> std::io::Result<()> {
+        if !self.is_empty() {
+            return Err(invalid_input("Index is not empty"));
+        }
+        let root_id = self.write_tree(bpm, entries)?;
+        self.set_root_page_id(bpm, root_id)
+    }
+
+    // Replaces the content of the index with |entries|, bulk-loaded like by
+    // |bulk_load| next to the current tree, which serves reads until the
+    // header page records the new root in one step. Returns the page IDs of
+    // the old tree, for the caller to free once no reader uses them.
+    pub fn rebuild<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        entries: &[(K, Rid)],
+    ) -> std::io::Result<Vec<PageId>> {
+        let old_page_ids = self.page_ids(bpm)?;
+        let root_id = self.write_tree(bpm, entries)?;
+        self.set_root_page_id(bpm, root_id)?;
+        Ok(old_page_ids)
+    }
+
+    // Writes the pages of a tree holding |entries|, and returns its root page
+    // ID, or |INVALID_PAGE_ID| if there are no entries. The tree is not
+    // recorded in the header page.
+    fn write_tree<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        entries: &[(K, Rid)],
+    ) -> std::io::Result<PageId> {
+        if entries.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(invalid_input("Keys are not strictly ascending"));
+        }
+        for (key, _) in entries.iter() {
+            self.check_key(key)?;
+        }
+        if entries.is_empty() {
+            return Ok(INVALID_PAGE_ID);
+        }
+        let (key_schema, key_size) = (&self.key_schema, self.key_size());
+        let mut level = write_level(bpm, key_schema, entries, self.leaf_max_size, |page, entries| {
+            let leaf = BPlusTreeLeafPage::cast_mut(page);
+            leaf.init(key_size);
+            leaf.set_entries(key_schema, entries)
+        })?;
+        while level.len() > 1 {
+            let max_size = self.internal_max_size;
+            level = write_level(bpm, key_schema, &level, max_size, |page, entries| {
+                let internal = BPlusTreeInternalPage::cast_mut(page);
+                internal.init(key_size);
+                internal.set_entries(key_schema, entries)
+            })?;
+        }
+        Ok(level[0].1)
+    }
+
+    // Returns an iterator over all entries in key order.
+    pub fn iter<'a, S: StorageBackend>(
+        &self,
+        bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<IndexIterator<'a, K, S>> {
+        if self.is_empty() {
+            return Ok(IndexIterator::empty(bpm, self.key_schema.clone()));
+        }
+        let leaf_id = self.find_edge_leaf(bpm, /*rightmost=*/ false)?;
+        Ok(IndexIterator::new(
+            bpm,
+            self.key_schema.clone(),
+            leaf_id,
+            0,
+            /*reverse=*/ false,
+        ))
+    }
+
+    // Returns an iterator over the entries with keys >= |start|, in key order.
+    pub fn iter_from<'a, S: StorageBackend>(
+        &self,
+        bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+        start: K,
+    ) -> std::io::Result<IndexIterator<'a, K, S>> {
+        if self.is_empty() {
+            return Ok(IndexIterator::empty(bpm, self.key_schema.clone()));
+        }
+        let leaf_id = *self.find_leaf_path(bpm, &start)?.last().unwrap();
+        let idx = with_page(bpm, leaf_id, false, |page| {
+            BPlusTreeLeafPage::cast(page).key_index(&self.key_schema, &start)
+        })?;
+        Ok(IndexIterator::new(
+            bpm,
+            self.key_schema.clone(),
+            leaf_id,
+            idx,
+            /*reverse=*/ false,
+        ))
+    }
+
+    // Returns an iterator over all entries in reverse key order.
+    pub fn iter_rev<'a, S: StorageBackend>(
+        &self,
+        bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<IndexIterator<'a, K, S>> {
+        if self.is_empty() {
+            return Ok(IndexIterator::empty(bpm, self.key_schema.clone()));
+        }
+        let leaf_id = self.find_edge_leaf(bpm, /*rightmost=*/ true)?;
+        Ok(IndexIterator::new(
+            bpm,
+            self.key_schema.clone(),
+            leaf_id,
+            usize::MAX,
+            /*reverse=*/ true,
+        ))
+    }
+
+    // Returns an iterator over the entries with keys <= |start|, in reverse key
+    // order.
+    pub fn iter_rev_from<'a, S: StorageBackend>(
+        &self,
+        bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+        start: K,
+    ) -> std::io::Result<IndexIterator<'a, K, S>> {
+        if self.is_empty() {
+            return Ok(IndexIterator::empty(bpm, self.key_schema.clone()));
+        }
+        let leaf_id = *self.find_leaf_path(bpm, &start)?.last().unwrap();
+        // One past the last key <= |start|.
+        let end = with_page(bpm, leaf_id, false, |page| {
+            let leaf = BPlusTreeLeafPage::cast(page);
+            let idx = leaf.key_index(&self.key_schema, &start);
+            match idx < leaf.size() && leaf.key_at::<K>(&self.key_schema, idx) == start {
+                true => idx + 1,
+                false => idx,
+            }
+        })?;
+        Ok(IndexIterator::new(
+            bpm,
+            self.key_schema.clone(),
+            leaf_id,
+            end,
+            /*reverse=*/ true,
+        ))
+    }
+
+    // Returns the IDs of the pages of the tree, level by level from the root,
+    // moving right past half-finished splits.
+    pub fn page_ids<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<Vec<PageId>> {
+        let mut page_ids = Vec::new();
+        let mut level_id = self.root_page_id;
+        while level_id != INVALID_PAGE_ID {
+            let mut page_id = level_id;
+            level_id = with_page(bpm, page_id, false, |page| match page.is_leaf() {
+                true => INVALID_PAGE_ID,
+                false => BPlusTreeInternalPage::cast(page).child_at(0),
+            })?;
+            while page_id != INVALID_PAGE_ID {
+                page_ids.push(page_id);
+                page_id = with_page(bpm, page_id, false, |page| match page.is_leaf() {
+                    true => BPlusTreeLeafPage::cast(page).next_page_id(),
+                    false => BPlusTreeInternalPage::cast(page).right_page_id(),
+                })?;
+            }
+        }
+        Ok(page_ids)
+    }
+
+    // Completes the splits whose new right page was never linked into the
+    // parent (e.g. because of a crash), and fixes the |prev_page_id| links of
+    // the leaves. Returns the number of completed splits. Readers tolerate
+    // half-finished splits, but inserts and removes expect complete ones.
+    pub fn repair<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<usize> {
+        if self.is_empty() {
+            return Ok(0);
+        }
+        let mut count = 0;
+        while let Some((depth, left_id, sep, right_id)) = self.find_unlinked_page(bpm)? {
+            // The descent for |sep| passes the parent of |left_id| at |depth| - 1.
+            let path = self.find_leaf_path(bpm, &sep)?;
+            self.insert_into_parent(bpm, &path[..depth], left_id, sep, right_id)?;
+            count += 1;
+        }
+
+        let mut prev_id = INVALID_PAGE_ID;
+        let mut leaf_id = self.find_edge_leaf(bpm, /*rightmost=*/ false)?;
+        while leaf_id != INVALID_PAGE_ID {
+            let (leaf_prev_id, next_id) = with_page(bpm, leaf_id, false, |page| {
+                let leaf = BPlusTreeLeafPage::cast(page);
+                (leaf.prev_page_id(), leaf.next_page_id())
+            })?;
+            if leaf_prev_id != prev_id {
+                with_page(bpm, leaf_id, true, |page| {
+                    BPlusTreeLeafPage::cast_mut(page).set_prev_page_id(prev_id)
+                })?;
+            }
+            prev_id = leaf_id;
+            leaf_id = next_id;
+        }
+        Ok(count)
+    }
+
+    // Returns the page IDs from the root down to the leaf that may contain
+    // |key|, moving right past half-finished splits. The caller needs to
+    // ensure that the tree is not empty.
+    pub(crate) fn find_leaf_path<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        key: &K,
+    ) -> std::io::Result<Vec<PageId>> {
+        let mut path = vec![self.root_page_id];
+        loop {
+            let page_id = *path.last().unwrap();
+            let step = with_page(bpm, page_id, false, |page| {
+                match right_link::<K>(page, &self.key_schema) {
+                    Some((right_id, high_key)) if *key >= high_key => Step::Right(right_id),
+                    _ if page.is_leaf() => Step::Done,
+                    _ => {
+                        let internal = BPlusTreeInternalPage::cast(page);
+                        Step::Down(internal.lookup(&self.key_schema, key))
+                    }
+                }
+            })?;
+            match step {
+                Step::Right(right_id) => *path.last_mut().unwrap() = right_id,
+                Step::Down(child_id) => path.push(child_id),
+                Step::Done => return Ok(path),
+            }
+        }
+    }
+
+    // Returns the leftmost leaf, or the rightmost one, moving right past
+    // half-finished splits. The caller needs to ensure that the tree is not
+    // empty.
+    fn find_edge_leaf<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        rightmost: bool,
+    ) -> std::io::Result<PageId> {
+        let mut page_id = self.root_page_id;
+        loop {
+            let step = with_page(bpm, page_id, false, |page| {
+                let right_id = match page.is_leaf() {
+                    true => BPlusTreeLeafPage::cast(page).next_page_id(),
+                    false => BPlusTreeInternalPage::cast(page).right_page_id(),
+                };
+                if rightmost && right_id != INVALID_PAGE_ID {
+                    Step::Right(right_id)
+                } else if page.is_leaf() {
+                    Step::Done
+                } else {
+                    let internal = BPlusTreeInternalPage::cast(page);
+                    let idx = if rightmost { internal.size() - 1 } else { 0 };
+                    Step::Down(internal.child_at(idx))
+                }
+            })?;
+            match step {
+                Step::Right(next_id) | Step::Down(next_id) => page_id = next_id,
+                Step::Done => return Ok(page_id),
+            }
+        }
+    }
+
+    // Walks the tree level by level, and returns the first page reachable only
+    // through the right link of its left sibling, as (depth, left sibling,
+    // separator, page).
+    fn find_unlinked_page<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<Option<(usize, PageId, K, PageId)>> {
+        let link = with_page(bpm, self.root_page_id, false, |page| {
+            right_link::<K>(page, &self.key_schema)
+        })?;
+        if let Some((right_id, high_key)) = link {
+            return Ok(Some((0, self.root_page_id, high_key, right_id)));
+        }
+        let mut level = vec![self.root_page_id];
+        let mut depth = 0;
+        loop {
+            let mut children = vec![];
+            for &page_id in level.iter() {
+                let child_ids = with_page(bpm, page_id, false, |page| match page.is_leaf() {
+                    true => None,
+                    false => {
+                        let internal = BPlusTreeInternalPage::cast(page);
+                        Some((0..internal.size()).map(|idx| internal.child_at(idx)).collect())
+                    }
+                })?;
+                match child_ids {
+                    Some(child_ids) => children.extend::<Vec<PageId>>(child_ids),
+                    None => return Ok(None),
+                }
+            }
+            depth += 1;
+            for (idx, &child_id) in children.iter().enumerate() {
+                let expected_id = children.get(idx + 1).copied().unwrap_or(INVALID_PAGE_ID);
+                let link = with_page(bpm, child_id, false, |page| {
+                    right_link::<K>(page, &self.key_schema)
+                })?;
+                match link {
+                    Some((right_id, high_key)) if right_id != expected_id => {
+                        return Ok(Some((depth, child_id, high_key, right_id)))
+                    }
+                    None if expected_id != INVALID_PAGE_ID => {
+                        return Err(invalid_data("Sibling link is missing"))
+                    }
+                    _ => {}
+                }
+            }
+            level = children;
+        }
+    }
+
+    // Moves the upper half of |entries| into a new right sibling of the leaf,
+    // and keeps the lower half. Returns the separator and the new page ID; the
+    // caller needs to insert them into the parent.
+    fn split_leaf<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        leaf_id: PageId,
+        mut entries: Vec<(K, Rid)>,
+    ) -> std::io::Result<(K, PageId)> {
+        let link = with_page(bpm, leaf_id, false, |page| {
+            right_link::<K>(page, &self.key_schema)
+        })?;
+        let right_entries = entries.split_off(entries.len() / 2);
+        let sep = right_entries[0].0.clone();
+        let page = new_page(bpm)?;
+        let right_id = page.page_id();
+        let right = BPlusTreeLeafPage::cast_mut(page);
+        right.init(self.key_size());
+        right.set_prev_page_id(leaf_id);
+        let result = set_right_link(right, &self.key_schema, &link)
+            .and_then(|_| right.set_entries(&self.key_schema, &right_entries));
+        bpm.unpin_page(right_id, /*is_dirty=*/ true)?;
+        result?;
+        with_page(bpm, leaf_id, true, |page| {
+            let leaf = BPlusTreeLeafPage::cast_mut(page);
+            leaf.set_next_page_id(right_id);
+            leaf.set_high_key(&self.key_schema, &sep)?;
+            leaf.set_entries(&self.key_schema, &entries)
+        })??;
+        if let Some((next_id, _)) = link {
+            with_page(bpm, next_id, true, |page| {
+                BPlusTreeLeafPage::cast_mut(page).set_prev_page_id(right_id);
+            })?;
+        }
+        Ok((sep, right_id))
+    }
+
+    // Inserts the separator |key| and the new page |right_id| right after
+    // |left_id| into their parent, which is the last of |ancestors|. Splits
+    // the parent recursively if it overflows, or grows a new root.
+    fn insert_into_parent<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        ancestors: &[PageId],
+        left_id: PageId,
+        key: K,
+        right_id: PageId,
+    ) -> std::io::Result<()> {
+        let parent_id = match ancestors.last() {
+            Some(&parent_id) => parent_id,
+            None => {
+                let page = new_page(bpm)?;
+                let root_id = page.page_id();
+                let root = BPlusTreeInternalPage::cast_mut(page);
+                root.init(self.key_size());
+                let entries = [(key.clone(), left_id), (key, right_id)];
+                let result = root.set_entries(&self.key_schema, &entries);
+                bpm.unpin_page(root_id, /*is_dirty=*/ true)?;
+                result?;
+                return self.set_root_page_id(bpm, root_id);
+            }
+        };
+        let mut entries = self.read_internal(bpm, parent_id)?;
+        let idx = child_position(&entries, left_id)?;
+        entries.insert(idx + 1, (key, right_id));
+        if entries.len() <= self.internal_max_size {
+            return self.write_internal(bpm, parent_id, &entries);
+        }
+
+        // Splits the parent; the first key of the new right sibling moves up.
+        let link = with_page(bpm, parent_id, false, |page| {
+            right_link::<K>(page, &self.key_schema)
+        })?;
+        let right_entries = entries.split_off(entries.len() / 2);
+        let sep = right_entries[0].0.clone();
+        let page = new_page(bpm)?;
+        let new_id = page.page_id();
+        let right = BPlusTreeInternalPage::cast_mut(page);
+        right.init(self.key_size());
+        let result = set_right_link(right, &self.key_schema, &link)
+            .and_then(|_| right.set_entries(&self.key_schema, &right_entries));
+        bpm.unpin_page(new_id, /*is_dirty=*/ true)?;
+        result?;
+        with_page(bpm, parent_id, true, |page| {
+            let parent = BPlusTreeInternalPage::cast_mut(page);
+            parent.set_right_page_id(new_id);
+            parent.set_high_key(&self.key_schema, &sep)?;
+            parent.set_entries(&self.key_schema, &entries)
+        })??;
+        let ancestors = &ancestors[..(ancestors.len() - 1)];
+        self.insert_into_parent(bpm, ancestors, parent_id, sep, new_id)
+    }
+
+    // Fixes the underflowing non-root page at the end of |path|, by borrowing
+    // an entry from a sibling or merging with it.
+    fn rebalance<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        path: &[PageId],
+    ) -> std::io::Result<()> {
+        let page_id = path[path.len() - 1];
+        let parent_id = path[path.len() - 2];
+        let mut parent_entries = self.read_internal(bpm, parent_id)?;
+        let idx = child_position(&parent_entries, page_id)?;
+        // Prefers the left sibling. |sep| indexes the separator between the
+        // left and the right page in the parent.
+        let sep = if idx > 0 { idx } else { 1 };
+        let left_id = parent_entries[sep - 1].1;
+        let right_id = parent_entries[sep].1;
+        let page_is_left = idx == 0;
+
+        let is_leaf = with_page(bpm, page_id, false, |page| page.is_leaf())?;
+        let merged = if is_leaf {
+            self.rebalance_leaves(
+                bpm,
+                &mut parent_entries,
+                sep,
+                left_id,
+                right_id,
+                page_is_left,
+            )?
+        } else {
+            self.rebalance_internals(
+                bpm,
+                &mut parent_entries,
+                sep,
+                left_id,
+                right_id,
+                page_is_left,
+            )?
+        };
+        self.write_internal(bpm, parent_id, &parent_entries)?;
+        if !merged {
+            return Ok(());
+        }
+        bpm.delete_page(right_id)?;
+
+        if path.len() == 2 {
+            // The root is left with a single child, which becomes the root.
+            if parent_entries.len() == 1 {
+                bpm.delete_page(parent_id)?;
+                self.set_root_page_id(bpm, parent_entries[0].1)?;
+            }
+        } else if parent_entries.len() < min_size(self.internal_max_size) {
+            self.rebalance(bpm, &path[..(path.len() - 1)])?;
+        }
+        Ok(())
+    }
+
+    // Merges the right leaf into the left one if they fit into one page, or
+    // moves one entry to the underflowing leaf. Returns whether they merged.
+    fn rebalance_leaves<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        parent_entries: &mut Vec<(K, PageId)>,
+        sep: usize,
+        left_id: PageId,
+        right_id: PageId,
+        page_is_left: bool,
+    ) -> std::io::Result<bool> {
+        let mut left = self.read_leaf(bpm, left_id)?;
+        let mut right = self.read_leaf(bpm, right_id)?;
+        let link = with_page(bpm, right_id, false, |page| {
+            right_link::<K>(page, &self.key_schema)
+        })?;
+
+        if left.len() + right.len() <= self.leaf_max_size {
+            left.append(&mut right);
+            with_page(bpm, left_id, true, |page| {
+                let leaf = BPlusTreeLeafPage::cast_mut(page);
+                set_right_link(leaf, &self.key_schema, &link)?;
+                leaf.set_entries(&self.key_schema, &left)
+            })??;
+            if let Some((next_id, _)) = link {
+                with_page(bpm, next_id, true, |page| {
+                    BPlusTreeLeafPage::cast_mut(page).set_prev_page_id(left_id);
+                })?;
+            }
+            parent_entries.remove(sep);
+            return Ok(true);
+        }
+
+        if page_is_left {
+            left.push(right.remove(0));
+        } else {
+            right.insert(0, left.pop().unwrap());
+        }
+        parent_entries[sep].0 = right[0].0.clone();
+        with_page(bpm, left_id, true, |page| {
+            let leaf = BPlusTreeLeafPage::cast_mut(page);
+            leaf.set_high_key(&self.key_schema, &right[0].0)?;
+            leaf.set_entries(&self.key_schema, &left)
+        })??;
+        self.write_leaf(bpm, right_id, &right)?;
+        Ok(false)
+    }
+
+    // Same as |rebalance_leaves|, for internal pages. The separator in the
+    // parent moves down into the pages, and a key of the sibling moves up.
+    fn rebalance_internals<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        parent_entries: &mut Vec<(K, PageId)>,
+        sep: usize,
+        left_id: PageId,
+        right_id: PageId,
+        page_is_left: bool,
+    ) -> std::io::Result<bool> {
+        let mut left = self.read_internal(bpm, left_id)?;
+        let mut right = self.read_internal(bpm, right_id)?;
+        // The unused first key of the right page takes the separator.
+        right[0].0 = parent_entries[sep].0.clone();
+
+        if left.len() + right.len() <= self.internal_max_size {
+            left.append(&mut right);
+            let link = with_page(bpm, right_id, false, |page| {
+                right_link::<K>(page, &self.key_schema)
+            })?;
+            with_page(bpm, left_id, true, |page| {
+                let page = BPlusTreeInternalPage::cast_mut(page);
+                set_right_link(page, &self.key_schema, &link)?;
+                page.set_entries(&self.key_schema, &left)
+            })??;
+            parent_entries.remove(sep);
+            return Ok(true);
+        }
+
+        if page_is_left {
+            left.push(right.remove(0));
+        } else {
+            right.insert(0, left.pop().unwrap());
+        }
+        parent_entries[sep].0 = right[0].0.clone();
+        with_page(bpm, left_id, true, |page| {
+            let page = BPlusTreeInternalPage::cast_mut(page);
+            page.set_high_key(&self.key_schema, &right[0].0)?;
+            page.set_entries(&self.key_schema, &left)
+        })??;
+        self.write_internal(bpm, right_id, &right)?;
+        Ok(false)
+    }
+
+    fn set_root_page_id<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        root_page_id: PageId,
+    ) -> std::io::Result<()> {
+        self.root_page_id = root_page_id;
+        update_root(bpm, &self.index_name, root_page_id)
+    }
+
+    fn key_size(&self) -> usize {
+        K::serialized_len(&self.key_schema)
+    }
+
+    // Returns |InvalidInput| if |key| does not match the key schema, before
+    // any page is changed.
+    fn check_key(&self, key: &K) -> std::io::Result<()> {
+        key.serialize_to(&self.key_schema, &mut vec![0; self.key_size()])
+    }
+
+    fn read_leaf<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        page_id: PageId,
+    ) -> std::io::Result<Vec<(K, Rid)>> {
+        with_page(bpm, page_id, false, |page| {
+            BPlusTreeLeafPage::cast(page).entries(&self.key_schema)
+        })
+    }
+
+    fn write_leaf<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        page_id: PageId,
+        entries: &[(K, Rid)],
+    ) -> std::io::Result<()> {
+        with_page(bpm, page_id, true, |page| {
+            BPlusTreeLeafPage::cast_mut(page).set_entries(&self.key_schema, entries)
+        })?
+    }
+
+    fn read_internal<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        page_id: PageId,
+    ) -> std::io::Result<Vec<(K, PageId)>> {
+        with_page(bpm, page_id, false, |page| {
+            BPlusTreeInternalPage::cast(page).entries(&self.key_schema)
+        })
+    }
+
+    fn write_internal<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        page_id: PageId,
+        entries: &[(K, PageId)],
+    ) -> std::io::Result<()> {
+        with_page(bpm, page_id, true, |page| {
+            BPlusTreeInternalPage::cast_mut(page).set_entries(&self.key_schema, entries)
+        })?
+    }
+}
+
+// A step of the descent in |BPlusTree::find_leaf_path|.
+enum Step {
+    Right(PageId),
+    Down(PageId),
+    Done,
+}
+
+// Returns the right sibling and the high key of a leaf or internal page, if
+// it has a right sibling.
+fn right_link<K: IndexKey>(
+    page: &BPlusTreePage,
+    key_schema: &K::KeySchema,
+) -> Option<(PageId, K)> {
+    match page.is_leaf() {
+        true => {
+            let leaf = BPlusTreeLeafPage::cast(page);
+            let right_id = leaf.next_page_id();
+            (right_id != INVALID_PAGE_ID).then(|| (right_id, leaf.high_key(key_schema)))
+        }
+        false => {
+            let internal = BPlusTreeInternalPage::cast(page);
+            let right_id = internal.right_page_id();
+            (right_id != INVALID_PAGE_ID).then(|| (right_id, internal.high_key(key_schema)))
+        }
+    }
+}
+
+// Links a leaf or internal page to the right sibling |link| returned by
+// |right_link|, or makes it the rightmost page of its level.
+fn set_right_link<K: IndexKey>(
+    page: &mut BPlusTreePage,
+    key_schema: &K::KeySchema,
+    link: &Option<(PageId, K)>,
+) -> std::io::Result<()> {
+    let right_id = link.as_ref().map_or(INVALID_PAGE_ID, |link| link.0);
+    match page.is_leaf() {
+        true => BPlusTreeLeafPage::cast_mut(page).set_next_page_id(right_id),
+        false => BPlusTreeInternalPage::cast_mut(page).set_right_page_id(right_id),
+    }
+    match link {
+        Some((_, high_key)) if page.is_leaf() => {
+            BPlusTreeLeafPage::cast_mut(page).set_high_key(key_schema, high_key)
+        }
+        Some((_, high_key)) => {
+            BPlusTreeInternalPage::cast_mut(page).set_high_key(key_schema, high_key)
+        }
+        None => Ok(()),
+    }
+}
+
+// Writes a level of new pages holding |entries|, split evenly into pages of
+// at most |max_size| entries, each initialized by |init| from its entries.
+// The pages are contiguous and linked to their right siblings. Returns the
+// entries of the level above, i.e. the first key and the ID of every page.
+fn write_level<K, E, F, S>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    key_schema: &K::KeySchema,
+    entries: &[(K, E)],
+    max_size: usize,
+    init: F,
+) -> std::io::Result<Vec<(K, PageId)>>
+where
+    K: IndexKey,
+    S: StorageBackend,
+    F: Fn(&mut BPlusTreePage, &[(K, E)]) -> std::io::Result<()>,
+{
+    let count = entries.len().div_ceil(max_size);
+    let page_ids = bpm.allocate_pages_in(PRIMARY_FILE_ID, count)?;
+    let mut pages = Vec::with_capacity(count);
+    let mut parent_entries = Vec::with_capacity(count);
+    let mut start = 0;
+    for (idx, page_id) in page_ids.clone().enumerate() {
+        // The first |entries.len() % count| pages take an extra entry.
+        let end = start + entries.len() / count + (idx < entries.len() % count) as usize;
+        let mut page = BPlusTreePage::default();
+        page.reset();
+        init(&mut page, &entries[start..end])?;
+        if end < entries.len() {
+            let link = Some((page_id + 1, entries[end].0.clone()));
+            set_right_link(&mut page, key_schema, &link)?;
+        }
+        if idx > 0 && page.is_leaf() {
+            BPlusTreeLeafPage::cast_mut(&mut page).set_prev_page_id(page_id - 1);
+        }
+        parent_entries.push((entries[start].0.clone(), page_id));
+        pages.push(page);
+        start = end;
+    }
+    let mut data: Vec<&mut [u8]> = pages
+        .iter_mut()
+        .map(|page| &mut page.data_mut()[..])
+        .collect();
+    bpm.write_pages(page_ids.start, &mut data)?;
+    Ok(parent_entries)
+}
+
+// The minimum number of entries of a non-root page.
+fn min_size(max_size: usize) -> usize {
+    max_size.div_ceil(2)
+}
+
+fn child_position<K>(entries: &[(K, PageId)], child_id: PageId) -> std::io::Result<usize> {
+    entries
+        .iter()
+        .position(|entry| entry.1 == child_id)
+        .ok_or_else(|| invalid_data("Child not found in parent page"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::catalog::column::Column;
+    use crate::catalog::schema::Schema;
+    use crate::index::index_util::tests::create_header;
+    use crate::index::key::Key;
+    use crate::types::types::Str;
+    use crate::types::types::Types;
+    use crate::types::types::Varlen;
+    use crate::types::value::Value;
+
+    // A fixed permutation of 0..n.
+    fn shuffled(n: i64) -> Vec<i64> {
+        (0..n).map(|i| (i * 37) % n).collect()
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(10);
+        create_header(&mut bpm);
+        let mut tree = BPlusTree::with_max_sizes("index", &mut bpm, 3, 3).unwrap();
+        assert!(tree.is_empty());
+        assert_eq!(None, tree.get_value(&mut bpm, 1).unwrap());
+
+        for key in shuffled(200) {
+            let rid = Rid::new(key as PageId, key as usize);
+            assert!(tree.insert(&mut bpm, key, rid).unwrap());
+        }
+        assert!(!tree.insert(&mut bpm, 7, Rid::default()).unwrap());
+        for key in 0..200 {
+            let rid = Rid::new(key as PageId, key as usize);
+            assert_eq!(Some(rid), tree.get_value(&mut bpm, key).unwrap());
+        }
+        assert_eq!(None, tree.get_value(&mut bpm, 200).unwrap());
+        assert_eq!(None, tree.get_value(&mut bpm, -1).unwrap());
+
+        // The root is tracked in the header page.
+        let reopened = BPlusTree::new("index", &mut bpm).unwrap();
+        assert_eq!(tree.root_page_id(), reopened.root_page_id());
+        assert!(BPlusTree::with_max_sizes("index", &mut bpm, 1, 3).is_err());
+    }
+
+    #[test]
+    fn iterate() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(10);
+        create_header(&mut bpm);
+        let mut tree = BPlusTree::with_max_sizes("index", &mut bpm, 3, 3).unwrap();
+        assert_eq!(0, tree.iter(&mut bpm).unwrap().count());
+        assert_eq!(0, tree.iter_rev(&mut bpm).unwrap().count());
+
+        // Even keys only.
+        for key in shuffled(100) {
+            assert!(tree
+                .insert(&mut bpm, key * 2, Rid::new(0, key as usize))
+                .unwrap());
+        }
+        let keys = |iter: IndexIterator<i64, _>| {
+            iter.map(|entry| entry.unwrap().0)
+                .collect::<Vec<i64>>()
+        };
+        assert_eq!(
+            (0..100).map(|key| key * 2).collect::<Vec<i64>>(),
+            keys(tree.iter(&mut bpm).unwrap())
+        );
+        assert_eq!(
+            (0..100).rev().map(|key| key * 2).collect::<Vec<i64>>(),
+            keys(tree.iter_rev(&mut bpm).unwrap())
+        );
+
+        // Seeks to an existing and a missing start key.
+        assert_eq!(
+            vec![190, 192, 194, 196, 198],
+            keys(tree.iter_from(&mut bpm, 190).unwrap())
+        );
+        assert_eq!(vec![196, 198], keys(tree.iter_from(&mut bpm, 195).unwrap()));
+        assert_eq!(0, tree.iter_from(&mut bpm, 199).unwrap().count());
+        assert_eq!(
+            vec![4, 2, 0],
+            keys(tree.iter_rev_from(&mut bpm, 4).unwrap())
+        );
+        assert_eq!(
+            vec![4, 2, 0],
+            keys(tree.iter_rev_from(&mut bpm, 5).unwrap())
+        );
+        assert_eq!(0, tree.iter_rev_from(&mut bpm, -1).unwrap().count());
+
+        let (key, rid) = tree.iter_from(&mut bpm, 51).unwrap().next().unwrap().unwrap();
+        assert_eq!((52, Rid::new(0, 26)), (key, rid));
+
+        // No page is left pinned.
+        let pinned: Vec<PageId> = (0..10)
+            .map(|_| bpm.new_page().unwrap().page_id())
+            .collect();
+
+        // With every frame pinned, no leaf can be fetched. The error is
+        // yielded, and ends the scan.
+        let mut iter = IndexIterator::<i64, _>::new(&mut bpm, (), tree.root_page_id(), 0, false);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+        for page_id in pinned.iter() {
+            bpm.unpin_page(*page_id, /*is_dirty=*/ false).unwrap();
+        }
+        assert_eq!(100, tree.iter(&mut bpm).unwrap().count());
+    }
+
+    #[test]
+    fn remove() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(10);
+        create_header(&mut bpm);
+        let mut tree = BPlusTree::with_max_sizes("index", &mut bpm, 4, 3).unwrap();
+        for key in shuffled(200) {
+            assert!(tree
+                .insert(&mut bpm, key, Rid::new(0, key as usize))
+                .unwrap());
+        }
+
+        // Removes the odd keys.
+        for key in shuffled(200).into_iter().filter(|key| key % 2 == 1) {
+            assert!(tree.remove(&mut bpm, key).unwrap());
+        }
+        assert!(!tree.remove(&mut bpm, 1).unwrap());
+        for key in 0..200 {
+            let expected = match key % 2 {
+                0 => Some(Rid::new(0, key as usize)),
+                _ => None,
+            };
+            assert_eq!(expected, tree.get_value(&mut bpm, key).unwrap());
+        }
+
+        // Removing everything empties the tree and frees the root.
+        for key in (0..200).filter(|key| key % 2 == 0) {
+            assert!(tree.remove(&mut bpm, key).unwrap());
+        }
+        assert!(tree.is_empty());
+        assert_eq!(
+            INVALID_PAGE_ID,
+            BPlusTree::new("index", &mut bpm).unwrap().root_page_id()
+        );
+
+        // The tree can grow again.
+        assert!(tree.insert(&mut bpm, 5, Rid::new(0, 5)).unwrap());
+        assert_eq!(Some(Rid::new(0, 5)), tree.get_value(&mut bpm, 5).unwrap());
+    }
+
+    #[test]
+    fn rename() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(10);
+        create_header(&mut bpm);
+        let mut tree = BPlusTree::new("index", &mut bpm).unwrap();
+        assert!(tree.insert(&mut bpm, 1, Rid::new(0, 1)).unwrap());
+        BPlusTree::new("other", &mut bpm).unwrap();
+        assert!(tree.rename(&mut bpm, "other").is_err());
+        assert!(tree.rename(&mut bpm, "renamed").is_ok());
+        assert_eq!("renamed", tree.index_name());
+
+        // Root changes are recorded under the new name.
+        for key in 2..1000 {
+            assert!(tree.insert(&mut bpm, key, Rid::new(0, key as usize)).unwrap());
+        }
+        let reopened = BPlusTree::new("renamed", &mut bpm).unwrap();
+        assert_eq!(tree.root_page_id(), reopened.root_page_id());
+        assert_eq!(Some(Rid::new(0, 1)), reopened.get_value(&mut bpm, 1).unwrap());
+        assert!(BPlusTree::new("index", &mut bpm).unwrap().is_empty());
+    }
+
+    #[test]
+    fn repair_half_finished_splits() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(10);
+        create_header(&mut bpm);
+        let mut tree = BPlusTree::with_max_sizes("index", &mut bpm, 3, 3).unwrap();
+        for key in shuffled(60) {
+            assert!(tree.insert(&mut bpm, key, Rid::new(0, key as usize)).unwrap());
+        }
+        assert_eq!(0, tree.repair(&mut bpm).unwrap());
+
+        // Splits leaves as if a crash hit before the parents were updated.
+        for key in [0, 30, 59] {
+            let leaf_id = *tree.find_leaf_path(&mut bpm, &key).unwrap().last().unwrap();
+            let entries = with_page(&mut bpm, leaf_id, false, |page| {
+                BPlusTreeLeafPage::cast(page).entries(&())
+            })
+            .unwrap();
+            assert!(entries.len() >= 2);
+            tree.split_leaf(&mut bpm, leaf_id, entries).unwrap();
+        }
+
+        // Readers move right past the half-finished splits.
+        for key in 0..60 {
+            let rid = Rid::new(0, key as usize);
+            assert_eq!(Some(rid), tree.get_value(&mut bpm, key).unwrap());
+        }
+        let keys: Vec<i64> = tree.iter(&mut bpm).unwrap().map(|entry| entry.unwrap().0).collect();
+        assert_eq!((0..60).collect::<Vec<i64>>(), keys);
+
+        assert_eq!(3, tree.repair(&mut bpm).unwrap());
+        assert_eq!(0, tree.repair(&mut bpm).unwrap());
+        let keys: Vec<i64> = tree
+            .iter_rev(&mut bpm)
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!((0..60).rev().collect::<Vec<i64>>(), keys);
+
+        // Writers work again.
+        for key in shuffled(60) {
+            assert!(tree.remove(&mut bpm, key).unwrap());
+        }
+        assert!(tree.is_empty());
+
+        // A root leaf split without a new root.
+        for key in 0..3 {
+            assert!(tree.insert(&mut bpm, key, Rid::new(0, key as usize)).unwrap());
+        }
+        let root_id = tree.root_page_id();
+        let entries = with_page(&mut bpm, root_id, false, |page| {
+            BPlusTreeLeafPage::cast(page).entries(&())
+        })
+        .unwrap();
+        tree.split_leaf(&mut bpm, root_id, entries).unwrap();
+        assert_eq!(Some(Rid::new(0, 2)), tree.get_value(&mut bpm, 2).unwrap());
+        assert_eq!(1, tree.repair(&mut bpm).unwrap());
+        assert_ne!(root_id, tree.root_page_id());
+        assert!(tree.insert(&mut bpm, 3, Rid::new(0, 3)).unwrap());
+        assert_eq!(4, tree.iter(&mut bpm).unwrap().count());
+    }
+
+    #[test]
+    fn bulk_load() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(10);
+        create_header(&mut bpm);
+        let mut tree = BPlusTree::with_max_sizes("index", &mut bpm, 3, 3).unwrap();
+        let unsorted = [(2, Rid::default()), (1, Rid::default())];
+        assert!(tree.bulk_load(&mut bpm, &unsorted).is_err());
+        assert!(tree.bulk_load(&mut bpm, &[]).is_ok());
+        assert!(tree.is_empty());
+
+        // Even keys only.
+        let entries: Vec<(i64, Rid)> = (0..100)
+            .map(|key| (key * 2, Rid::new(0, key as usize)))
+            .collect();
+        assert!(tree.bulk_load(&mut bpm, &entries).is_ok());
+        assert!(tree.bulk_load(&mut bpm, &entries).is_err());
+        for (key, rid) in entries.iter() {
+            assert_eq!(Some(rid.clone()), tree.get_value(&mut bpm, *key).unwrap());
+        }
+        assert_eq!(None, tree.get_value(&mut bpm, 1).unwrap());
+        let keys: Vec<i64> = tree
+            .iter_rev(&mut bpm)
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(
+            (0..100).rev().map(|key| key * 2).collect::<Vec<i64>>(),
+            keys
+        );
+        // The levels are linked like after splits.
+        assert_eq!(0, tree.repair(&mut bpm).unwrap());
+
+        // The root is tracked in the header page.
+        let reopened = BPlusTree::new("index", &mut bpm).unwrap();
+        assert_eq!(tree.root_page_id(), reopened.root_page_id());
+
+        // Writers work on the loaded tree.
+        for key in shuffled(100) {
+            assert!(tree.insert(&mut bpm, key * 2 + 1, Rid::default()).unwrap());
+        }
+        let keys: Vec<i64> = tree.iter(&mut bpm).unwrap().map(|entry| entry.unwrap().0).collect();
+        assert_eq!((0..200).collect::<Vec<i64>>(), keys);
+
+        // Rebuilding replaces the tree, and hands back its pages.
+        let old_page_ids = tree.page_ids(&mut bpm).unwrap();
+        assert_eq!(old_page_ids, tree.rebuild(&mut bpm, &entries).unwrap());
+        assert!(!old_page_ids.contains(&tree.root_page_id()));
+        for page_id in old_page_ids {
+            bpm.delete_page(page_id).unwrap();
+        }
+        assert_eq!(100, tree.iter(&mut bpm).unwrap().count());
+        let reopened = BPlusTree::new("index", &mut bpm).unwrap();
+        assert_eq!(tree.root_page_id(), reopened.root_page_id());
+
+        for key in shuffled(100) {
+            assert!(tree.remove(&mut bpm, key * 2).unwrap());
+        }
+        assert!(tree.is_empty());
+    }
+
+    // A key of (Name, Id).
+    fn composite(name: &str, id: i32) -> Key<'static> {
+        let name = Types::Varchar(Varlen::Owned(Str::Val(name.to_string())));
+        Key::new(vec![Value::new(name), Value::new(Types::Integer(id))])
+    }
+
+    #[test]
+    fn composite_keys() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(10);
+        create_header(&mut bpm);
+        let key_schema = Schema::new(vec![
+            Column::new("Name".to_string(), Types::owned(), 8),
+            Column::new("Id".to_string(), Types::integer(), 4),
+        ]);
+        let mut tree = BPlusTree::open("index", &mut bpm, key_schema.clone(), 3, 3).unwrap();
+        let names = ["carol", "alice", "bob"];
+        for id in shuffled(100) {
+            let key = composite(names[id as usize % 3], id as i32);
+            assert!(tree.insert(&mut bpm, key, Rid::new(0, id as usize)).unwrap());
+        }
+        assert!(!tree.insert(&mut bpm, composite("bob", 2), Rid::default()).unwrap());
+        let long = composite("a long name", 0);
+        assert!(tree.insert(&mut bpm, long, Rid::default()).is_err());
+        let rid = tree.get_value(&mut bpm, composite("carol", 42)).unwrap();
+        assert_eq!(Some(Rid::new(0, 42)), rid);
+        assert_eq!(None, tree.get_value(&mut bpm, composite("alice", 42)).unwrap());
+
+        // Ordered by name, then by id.
+        let keys: Vec<Key> = tree.iter(&mut bpm).unwrap().map(|entry| entry.unwrap().0).collect();
+        let mut expected: Vec<Key> = (0..100)
+            .map(|id| composite(names[id as usize % 3], id))
+            .collect();
+        expected.sort();
+        assert_eq!(expected, keys);
+        let first = tree.iter_from(&mut bpm, composite("bob", 0)).unwrap().next();
+        assert_eq!(composite("bob", 2), first.unwrap().unwrap().0);
+        let last = tree.iter_rev(&mut bpm).unwrap().next();
+        assert_eq!(composite("carol", 99), last.unwrap().unwrap().0);
+
+        for key in expected.iter().step_by(2) {
+            assert!(tree.remove(&mut bpm, key.clone()).unwrap());
+        }
+        assert_eq!(50, tree.iter_rev(&mut bpm).unwrap().count());
+        assert_eq!(0, tree.repair(&mut bpm).unwrap());
+
+        // The tree is reopened with its key schema, and keys of another size
+        // are rejected.
+        let reopened = BPlusTree::<Key>::with_key_schema("index", &mut bpm, key_schema);
+        let reopened = reopened.unwrap();
+        assert_eq!(tree.root_page_id(), reopened.root_page_id());
+        assert!(BPlusTree::new("index", &mut bpm).is_err());
+    }
+}
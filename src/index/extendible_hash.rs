@@ -0,0 +1,418 @@
+// Functionality: An extendible hash index with unique keys, mapping keys to
+// RIDs. Pages live in the buffer pool as |BPlusTreePage| frames, viewed as
+// |HashTableDirectoryPage| or |HashTableBucketPage| depending on their type.
+//
+// The directory page ID is tracked in the header page under the index name, so
+// that the index can be reopened. A key goes to the bucket in the directory
+// slot selected by the lowest |GlobalDepth| bits of its hash. A full bucket is
+// split in two, doubling the directory if needed; an empty bucket is merged
+// into its split image, shrinking the directory if possible.
+
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::common::config::PageId;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::error::*;
+use crate::common::rid::Rid;
+use crate::disk::storage_backend::StorageBackend;
+use crate::index::index_util::*;
+use crate::index::key::IndexKey;
+use crate::page::hash_table_bucket_page::bucket_page_capacity;
+use crate::page::hash_table_bucket_page::HashTableBucketPage;
+use crate::page::hash_table_directory_page::HashTableDirectoryPage;
+use crate::page::hash_table_directory_page::MAX_GLOBAL_DEPTH;
+use crate::page::page::Page;
+use crate::page::table_page::TablePage;
+
+pub struct ExtendibleHash<K: IndexKey = i64> {
+    index_name: String,
+    directory_page_id: PageId,
+    key_schema: K::KeySchema,
+    // The maximum number of entries of bucket pages.
+    bucket_max_size: usize,
+}
+
+// Integer keys.
+impl ExtendibleHash {
+    // Opens the index named |index_name|, creating it if the header page has
+    // no record for it.
+    pub fn new<S: StorageBackend>(
+        index_name: &str,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<Self> {
+        Self::with_key_schema(index_name, bpm, ())
+    }
+
+    // Same as |new|, with a custom bucket size (e.g. a small one for testing).
+    pub fn with_bucket_max_size<S: StorageBackend>(
+        index_name: &str,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        bucket_max_size: usize,
+    ) -> std::io::Result<Self> {
+        Self::open(index_name, bpm, (), bucket_max_size)
+    }
+}
+
+impl<K: IndexKey> ExtendibleHash<K> {
+    // Opens the index named |index_name| with keys of |key_schema|, creating
+    // it if the header page has no record for it. Returns |InvalidInput| if
+    // the keys are too large for the pages.
+    pub fn with_key_schema<S: StorageBackend>(
+        index_name: &str,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        key_schema: K::KeySchema,
+    ) -> std::io::Result<Self> {
+        let bucket_max_size = bucket_page_capacity(K::serialized_len(&key_schema));
+        Self::open(index_name, bpm, key_schema, bucket_max_size)
+    }
+
+    fn open<S: StorageBackend>(
+        index_name: &str,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        key_schema: K::KeySchema,
+        bucket_max_size: usize,
+    ) -> std::io::Result<Self> {
+        let key_size = K::serialized_len(&key_schema);
+        if !(1..=bucket_page_capacity(key_size)).contains(&bucket_max_size) {
+            return Err(invalid_input("Bucket max size is out of range"));
+        }
+        let mut directory_page_id = find_or_insert_root(bpm, index_name, INVALID_PAGE_ID)?;
+        if directory_page_id == INVALID_PAGE_ID {
+            let page = new_page(bpm)?;
+            let bucket_id = page.page_id();
+            HashTableBucketPage::cast_mut(page).init(key_size);
+            bpm.unpin_page(bucket_id, /*is_dirty=*/ true)?;
+
+            let page = new_page(bpm)?;
+            directory_page_id = page.page_id();
+            HashTableDirectoryPage::cast_mut(page).init(bucket_id);
+            bpm.unpin_page(directory_page_id, /*is_dirty=*/ true)?;
+            update_root(bpm, index_name, directory_page_id)?;
+        }
+        Ok(ExtendibleHash {
+            index_name: index_name.to_string(),
+            directory_page_id,
+            key_schema,
+            bucket_max_size,
+        })
+    }
+
+    pub fn index_name(&self) -> &str {
+        &self.index_name
+    }
+
+    pub fn key_schema(&self) -> &K::KeySchema {
+        &self.key_schema
+    }
+
+    // Renames the index, i.e. its record in the header page. Returns
+    // |AlreadyExists| if another index or table has |new_name|.
+    pub fn rename<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        new_name: &str,
+    ) -> std::io::Result<()> {
+        rename_root(bpm, &self.index_name, new_name)?;
+        self.index_name = new_name.to_string();
+        Ok(())
+    }
+
+    pub fn directory_page_id(&self) -> PageId {
+        self.directory_page_id
+    }
+
+    pub fn global_depth<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<u32> {
+        with_page(bpm, self.directory_page_id, false, |page| {
+            HashTableDirectoryPage::cast(page).global_depth()
+        })
+    }
+
+    // Returns the RID stored under |key|, if any.
+    pub fn get_value<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        key: K,
+    ) -> std::io::Result<Option<Rid>> {
+        let (_, bucket_id, _) = self.find_bucket(bpm, &key)?;
+        with_page(bpm, bucket_id, false, |page| {
+            HashTableBucketPage::cast(page).lookup(&self.key_schema, &key)
+        })
+    }
+
+    // Inserts |key| with its |rid|. Returns false if the key already exists,
+    // and |InvalidInput| if it does not match the key schema.
+    pub fn insert<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        key: K,
+        rid: Rid,
+    ) -> std::io::Result<bool> {
+        // Checks the key before any page is changed.
+        let key_size = K::serialized_len(&self.key_schema);
+        key.serialize_to(&self.key_schema, &mut vec![0; key_size])?;
+        loop {
+            let (idx, bucket_id, local_depth) = self.find_bucket(bpm, &key)?;
+            let entries = with_page(bpm, bucket_id, false, |page| {
+                HashTableBucketPage::cast(page).entries::<K>(&self.key_schema)
+            })?;
+            if entries.iter().any(|entry| entry.0 == key) {
+                return Ok(false);
+            }
+            if entries.len() < self.bucket_max_size {
+                with_page(bpm, bucket_id, true, |page| {
+                    HashTableBucketPage::cast_mut(page).insert(&self.key_schema, &key, &rid)
+                })??;
+                return Ok(true);
+            }
+            self.split_bucket(bpm, idx, bucket_id, local_depth, &entries)?;
+        }
+    }
+
+    // Removes |key|. Returns false if there is no such key.
+    pub fn remove<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        key: K,
+    ) -> std::io::Result<bool> {
+        let (idx, bucket_id, _) = self.find_bucket(bpm, &key)?;
+        let (removed, is_empty) = with_page(bpm, bucket_id, true, |page| {
+            let bucket = HashTableBucketPage::cast_mut(page);
+            (bucket.remove(&self.key_schema, &key), bucket.size() == 0)
+        })?;
+        if removed && is_empty {
+            self.merge_bucket(bpm, idx, bucket_id)?;
+        }
+        Ok(removed)
+    }
+
+    // Returns the directory slot, the bucket page ID and the local depth of the
+    // bucket that |key| goes to.
+    fn find_bucket<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        key: &K,
+    ) -> std::io::Result<(usize, PageId, u32)> {
+        with_page(bpm, self.directory_page_id, false, |page| {
+            let directory = HashTableDirectoryPage::cast(page);
+            let idx = (key.hash() as u32 & directory.global_depth_mask()) as usize;
+            (
+                idx,
+                directory.bucket_page_id(idx),
+                directory.local_depth(idx),
+            )
+        })
+    }
+
+    // Splits the full bucket in slot |idx|, moving the entries whose hash has
+    // bit |local_depth| set into a new bucket.
+    fn split_bucket<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        idx: usize,
+        bucket_id: PageId,
+        local_depth: u32,
+        entries: &[(K, Rid)],
+    ) -> std::io::Result<()> {
+        if local_depth == MAX_GLOBAL_DEPTH {
+            return Err(invalid_data("Hash directory cannot grow any further"));
+        }
+        let high_bit = 1 << local_depth;
+        let (image_entries, entries): (Vec<_>, Vec<_>) = entries
+            .iter()
+            .cloned()
+            .partition(|entry| entry.0.hash() as u32 & high_bit != 0);
+
+        let page = new_page(bpm)?;
+        let image_id = page.page_id();
+        let image = HashTableBucketPage::cast_mut(page);
+        image.init(K::serialized_len(&self.key_schema));
+        let result = image.set_entries(&self.key_schema, &image_entries);
+        bpm.unpin_page(image_id, /*is_dirty=*/ true)?;
+        result?;
+        with_page(bpm, bucket_id, true, |page| {
+            HashTableBucketPage::cast_mut(page).set_entries(&self.key_schema, &entries)
+        })??;
+
+        with_page(bpm, self.directory_page_id, true, |page| {
+            let directory = HashTableDirectoryPage::cast_mut(page);
+            if local_depth == directory.global_depth() {
+                directory.grow();
+            }
+            // All slots sharing the low |local_depth| bits of |idx| point to
+            // the split bucket.
+            let low_mask = (high_bit - 1) as usize;
+            for slot in 0..directory.len() {
+                if slot & low_mask == idx & low_mask {
+                    if slot & high_bit as usize != 0 {
+                        directory.set_bucket_page_id(slot, image_id);
+                    }
+                    directory.set_local_depth(slot, local_depth + 1);
+                }
+            }
+        })
+    }
+
+    // Merges the empty bucket in slot |idx| into its split image, as long as
+    // both have the same local depth, then shrinks the directory. Repeats
+    // while the merged bucket is empty.
+    fn merge_bucket<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        mut idx: usize,
+        mut bucket_id: PageId,
+    ) -> std::io::Result<()> {
+        loop {
+            let merged = with_page(bpm, self.directory_page_id, true, |page| {
+                let directory = HashTableDirectoryPage::cast_mut(page);
+                let local_depth = directory.local_depth(idx);
+                if local_depth == 0 {
+                    return None;
+                }
+                let image_idx = idx ^ (1 << (local_depth - 1));
+                if directory.local_depth(image_idx) != local_depth {
+                    return None;
+                }
+                let image_id = directory.bucket_page_id(image_idx);
+                for slot in 0..directory.len() {
+                    let slot_id = directory.bucket_page_id(slot);
+                    if slot_id == bucket_id || slot_id == image_id {
+                        directory.set_bucket_page_id(slot, image_id);
+                        directory.set_local_depth(slot, local_depth - 1);
+                    }
+                }
+                directory.shrink();
+                Some((idx & directory.global_depth_mask() as usize, image_id))
+            })?;
+            let (image_idx, image_id) = match merged {
+                Some(merged) => merged,
+                None => return Ok(()),
+            };
+            bpm.delete_page(bucket_id)?;
+            let is_empty = with_page(bpm, image_id, false, |page| {
+                HashTableBucketPage::cast(page).size() == 0
+            })?;
+            if !is_empty {
+                return Ok(());
+            }
+            idx = image_idx;
+            bucket_id = image_id;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::catalog::column::Column;
+    use crate::catalog::schema::Schema;
+    use crate::index::index_util::tests::create_header;
+    use crate::index::key::Key;
+    use crate::types::types::Types;
+    use crate::types::value::Value;
+
+    #[test]
+    fn insert_and_get() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(10);
+        create_header(&mut bpm);
+        let mut index = ExtendibleHash::with_bucket_max_size("index", &mut bpm, 4).unwrap();
+        assert_eq!(0, index.global_depth(&mut bpm).unwrap());
+        assert_eq!(None, index.get_value(&mut bpm, 1).unwrap());
+
+        for key in 0..200 {
+            let rid = Rid::new(key as PageId, key as usize);
+            assert!(index.insert(&mut bpm, key, rid).unwrap());
+        }
+        assert!(!index.insert(&mut bpm, 7, Rid::default()).unwrap());
+        assert!(index.global_depth(&mut bpm).unwrap() >= 6);
+        for key in 0..200 {
+            let rid = Rid::new(key as PageId, key as usize);
+            assert_eq!(Some(rid), index.get_value(&mut bpm, key).unwrap());
+        }
+        assert_eq!(None, index.get_value(&mut bpm, 200).unwrap());
+        assert_eq!(None, index.get_value(&mut bpm, -1).unwrap());
+
+        // The directory is tracked in the header page.
+        let reopened = ExtendibleHash::new("index", &mut bpm).unwrap();
+        assert_eq!(index.directory_page_id(), reopened.directory_page_id());
+        assert_eq!(
+            Some(Rid::new(42, 42)),
+            reopened.get_value(&mut bpm, 42).unwrap()
+        );
+        assert!(ExtendibleHash::with_bucket_max_size("index", &mut bpm, 0).is_err());
+    }
+
+    #[test]
+    fn remove_and_shrink() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(10);
+        create_header(&mut bpm);
+        let mut index = ExtendibleHash::with_bucket_max_size("index", &mut bpm, 2).unwrap();
+        for key in 0..100 {
+            assert!(index
+                .insert(&mut bpm, key, Rid::new(0, key as usize))
+                .unwrap());
+        }
+        assert!(!index.remove(&mut bpm, 100).unwrap());
+        for key in (0..100).step_by(2) {
+            assert!(index.remove(&mut bpm, key).unwrap());
+        }
+        assert!(!index.remove(&mut bpm, 0).unwrap());
+        for key in 0..100 {
+            let expected = if key % 2 == 1 {
+                Some(Rid::new(0, key as usize))
+            } else {
+                None
+            };
+            assert_eq!(expected, index.get_value(&mut bpm, key).unwrap());
+        }
+
+        // Once all keys are gone, the directory collapses into one bucket.
+        for key in (1..100).step_by(2) {
+            assert!(index.remove(&mut bpm, key).unwrap());
+        }
+        assert_eq!(0, index.global_depth(&mut bpm).unwrap());
+        assert!(index.insert(&mut bpm, 5, Rid::new(0, 5)).unwrap());
+        assert_eq!(Some(Rid::new(0, 5)), index.get_value(&mut bpm, 5).unwrap());
+    }
+
+    #[test]
+    fn composite_keys() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(10);
+        create_header(&mut bpm);
+        let key_schema = Schema::new(vec![
+            Column::new("A".to_string(), Types::integer(), 4),
+            Column::new("B".to_string(), Types::bigint(), 8),
+        ]);
+        let key = |a: i32, b: i64| {
+            Key::new(vec![
+                Value::new(Types::Integer(a)),
+                Value::new(Types::BigInt(b)),
+            ])
+        };
+        let mut index = ExtendibleHash::open("index", &mut bpm, key_schema, 4).unwrap();
+        for a in 0..20 {
+            for b in 0..10 {
+                let rid = Rid::new(a, b as usize);
+                assert!(index.insert(&mut bpm, key(a, b), rid).unwrap());
+            }
+        }
+        assert!(!index.insert(&mut bpm, key(3, 3), Rid::default()).unwrap());
+        let short = Key::new(vec![Value::new(Types::Integer(0))]);
+        assert!(index.insert(&mut bpm, short, Rid::default()).is_err());
+        assert!(index.global_depth(&mut bpm).unwrap() >= 5);
+        assert_eq!(
+            Some(Rid::new(7, 3)),
+            index.get_value(&mut bpm, key(7, 3)).unwrap()
+        );
+        assert_eq!(None, index.get_value(&mut bpm, key(3, 10)).unwrap());
+
+        for a in 0..20 {
+            for b in 0..10 {
+                assert!(index.remove(&mut bpm, key(a, b)).unwrap());
+            }
+        }
+        assert_eq!(0, index.global_depth(&mut bpm).unwrap());
+    }
+}
@@ -0,0 +1,115 @@
+// Functionality: Range scan over a B+ tree. Walks the leaf pages in key order
+// via their sibling links, forward or in reverse, and yields (key, Rid) pairs.
+// Each leaf is pinned only while it is being read. An I/O error is yielded, and
+// ends the scan.
+
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::common::config::PageId;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::index::key::IndexKey;
+use crate::page::bplus_tree_leaf_page::BPlusTreeLeafPage;
+use crate::page::page::view;
+use crate::page::table_page::TablePage;
+
+pub struct IndexIterator<'a, K: IndexKey = i64, S: StorageBackend = DiskManager> {
+    bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+    key_schema: K::KeySchema,
+    page_id: PageId,
+    // Forward: the index of the next entry. Reverse: one past it, where
+    // |usize::MAX| stands for the end of the page.
+    idx: usize,
+    reverse: bool,
+}
+
+impl<'a, K: IndexKey, S: StorageBackend> IndexIterator<'a, K, S> {
+    // Starts at entry |idx| of the leaf with specified |page_id|. See |idx|
+    // above for its meaning in reverse.
+    pub(crate) fn new(
+        bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+        key_schema: K::KeySchema,
+        page_id: PageId,
+        idx: usize,
+        reverse: bool,
+    ) -> Self {
+        IndexIterator {
+            bpm,
+            key_schema,
+            page_id,
+            idx,
+            reverse,
+        }
+    }
+
+    // Returns an iterator yielding nothing.
+    pub(crate) fn empty(
+        bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+        key_schema: K::KeySchema,
+    ) -> Self {
+        Self::new(bpm, key_schema, INVALID_PAGE_ID, 0, false)
+    }
+}
+
+impl<'a, K: IndexKey, S: StorageBackend> Iterator for IndexIterator<'a, K, S> {
+    type Item = std::io::Result<(K, Rid)>;
+
+    // An I/O error is yielded, and ends the iteration.
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.next_entry().transpose();
+        if let Some(Err(_)) = result {
+            self.page_id = INVALID_PAGE_ID;
+        }
+        result
+    }
+}
+
+impl<'a, K: IndexKey, S: StorageBackend> IndexIterator<'a, K, S> {
+    fn next_entry(&mut self) -> std::io::Result<Option<(K, Rid)>> {
+        while self.page_id != INVALID_PAGE_ID {
+            let page_id = self.page_id;
+            let page = self.bpm.fetch_page(page_id)?;
+            let leaf = BPlusTreeLeafPage::cast(view(page));
+            let item = step(
+                leaf,
+                &self.key_schema,
+                &mut self.page_id,
+                &mut self.idx,
+                self.reverse,
+            );
+            self.bpm.unpin_page(page_id, /*is_dirty=*/ false)?;
+            if item.is_some() {
+                return Ok(item);
+            }
+        }
+        Ok(None)
+    }
+}
+
+// Reads the next entry from |leaf|, or moves |page_id| on to the next leaf.
+fn step<K: IndexKey>(
+    leaf: &BPlusTreeLeafPage,
+    key_schema: &K::KeySchema,
+    page_id: &mut PageId,
+    idx: &mut usize,
+    reverse: bool,
+) -> Option<(K, Rid)> {
+    if reverse {
+        let end = (*idx).min(leaf.size());
+        if end > 0 {
+            *idx = end - 1;
+            return Some((leaf.key_at(key_schema, end - 1), leaf.rid_at(end - 1)));
+        }
+        *page_id = leaf.prev_page_id();
+        *idx = usize::MAX;
+    } else {
+        if *idx < leaf.size() {
+            *idx += 1;
+            return Some((leaf.key_at(key_schema, *idx - 1), leaf.rid_at(*idx - 1)));
+        }
+        *page_id = leaf.next_page_id();
+        *idx = 0;
+    }
+    None
+}
@@ -0,0 +1,157 @@
+// Helpers shared by the indexes, whose pages live in the buffer pool of the
+// database file next to its table pages, viewed as |BPlusTreePage|s, and
+// whose roots are tracked in the header page.
+
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::common::config::PageId;
+use crate::common::config::HEADER_PAGE_ID;
+use crate::common::error::*;
+use crate::disk::storage_backend::StorageBackend;
+use crate::page::bplus_tree_page::BPlusTreePage;
+use crate::page::header_page::HeaderPage;
+use crate::page::page::view_mut;
+use crate::page::page::Page;
+use crate::page::table_page::TablePage;
+use std::io::ErrorKind;
+
+// Runs |f| on the pinned page with specified |page_id|, then unpins it.
+pub(crate) fn with_page<F, V, S>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    page_id: PageId,
+    is_dirty: bool,
+    f: F,
+) -> std::io::Result<V>
+where
+    S: StorageBackend,
+    F: FnOnce(&mut BPlusTreePage) -> V,
+{
+    let result = f(view_mut(bpm.fetch_page(page_id)?));
+    bpm.unpin_page(page_id, is_dirty)?;
+    Ok(result)
+}
+
+// Creates and pins an empty index page.
+pub(crate) fn new_page<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+) -> std::io::Result<&mut BPlusTreePage> {
+    let page: &mut BPlusTreePage = view_mut(bpm.new_page_uninit()?);
+    page.reset();
+    Ok(page)
+}
+
+// Returns the root page ID recorded for |index_name| in the header page,
+// recording |root_id| first if there is no record yet.
+pub(crate) fn find_or_insert_root<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    index_name: &str,
+    root_id: PageId,
+) -> std::io::Result<PageId> {
+    with_header(bpm, |header| match header.root_id(index_name) {
+        Ok(root_id) => Ok((root_id, false)),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            header.insert_record(index_name, root_id)?;
+            Ok((root_id, true))
+        }
+        Err(e) => Err(e),
+    })
+}
+
+// Records |root_id| as the root page ID of |index_name| in the header page.
+pub(crate) fn update_root<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    index_name: &str,
+    root_id: PageId,
+) -> std::io::Result<()> {
+    with_header(bpm, |header| {
+        header
+            .update_record(index_name, root_id)
+            .map(|_| ((), true))
+    })
+}
+
+// Renames the header page record of |index_name| to |new_name|.
+pub(crate) fn rename_root<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    index_name: &str,
+    new_name: &str,
+) -> std::io::Result<()> {
+    with_header(bpm, |header| {
+        header
+            .rename_record(index_name, new_name)
+            .map(|_| ((), true))
+    })
+}
+
+// Deletes the header page record of |index_name|.
+pub(crate) fn delete_root<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    index_name: &str,
+) -> std::io::Result<()> {
+    with_header(bpm, |header| {
+        header.delete_record(index_name).map(|_| ((), true))
+    })
+}
+
+// Allocates and initializes the header page of a new database. Returns
+// |AlreadyExists| if the database already has pages.
+pub fn create_header<S>(bpm: &mut DefaultBufferPoolManager<TablePage, S>) -> std::io::Result<()>
+where
+    S: StorageBackend,
+{
+    let page_id = {
+        let mut header = bpm.new_page_as::<HeaderPage>()?;
+        header.init();
+        header.page_id()
+    };
+    if page_id != HEADER_PAGE_ID {
+        bpm.delete_page(page_id)?;
+        return Err(already_exists("Database is not empty"));
+    }
+    Ok(())
+}
+
+// Empties the header page, e.g. to rebuild the indexes after a crash lost
+// index pages, which are not logged. The header page is allocated anew if the
+// crash also lost its allocation.
+pub fn clear_header<S>(bpm: &mut DefaultBufferPoolManager<TablePage, S>) -> std::io::Result<()>
+where
+    S: StorageBackend,
+{
+    if !bpm.is_allocated(HEADER_PAGE_ID) {
+        bpm.reserve_page(HEADER_PAGE_ID)?;
+    }
+    let mut header = bpm.fetch_page_as_mut::<HeaderPage>(HEADER_PAGE_ID)?;
+    header.reset();
+    header.init();
+    Ok(())
+}
+
+// Runs |f| on the header page, upgraded to the current layout if needed. |f|
+// returns its result and whether it modified the header.
+fn with_header<F, V, S>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    f: F,
+) -> std::io::Result<V>
+where
+    S: StorageBackend,
+    F: FnOnce(&mut HeaderPage) -> std::io::Result<(V, bool)>,
+{
+    let header: &mut HeaderPage = view_mut(bpm.fetch_page(HEADER_PAGE_ID)?);
+    let (result, upgraded) = match header.upgrade() {
+        Ok(upgraded) => (f(header), upgraded),
+        Err(e) => (Err(e), false),
+    };
+    let is_dirty = upgraded || matches!(result, Ok((_, true)));
+    bpm.unpin_page(HEADER_PAGE_ID, is_dirty)?;
+    result.map(|(value, _)| value)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // Allocates and initializes the header page.
+    pub fn create_header<S: StorageBackend>(bpm: &mut DefaultBufferPoolManager<TablePage, S>) {
+        assert!(super::create_header(bpm).is_ok());
+    }
+}
@@ -0,0 +1,366 @@
+// Functionality: The composite key of a multi-column index, i.e. the values of
+// some columns of a tuple, in the order of the key schema of the index (see
+// |key_schema|).
+//
+// Keys are ordered lexicographically, with null smaller than any value, and
+// serialize to a fixed size given by the key schema, so that they fit in fixed
+// size slots of index pages:
+//  ------------------------------------------------------------------------
+// | NullBitmap ((ColumnCount + 7) / 8) | Value_1 (Slot_1) | Value_2 (Slot_2) | ...
+//  ------------------------------------------------------------------------
+// As in tuples, bit |idx % 8| of byte |idx / 8| of the bitmap is set if value
+// |idx| is null. The slot of an inlined column is its fixed length. The slot
+// of a Varchar column has a flag byte and the column length, and that of a
+// Varbinary column has a 4 byte length and the column length.
+//
+// The B+ tree and hash indexes are generic over |IndexKey|, which both these
+// keys and plain integer keys implement.
+
+use crate::catalog::column::Column;
+use crate::catalog::schema::Schema;
+use crate::common::crc32c::crc32c;
+use crate::common::error::*;
+use crate::common::reinterpret;
+use crate::table::tuple::Tuple;
+use crate::types::types::Operation;
+use crate::types::types::Types;
+use crate::types::value::Value;
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+// The keys of an index. All keys of an index serialize to the same size,
+// given by its key schema, so that they fit in fixed size slots of index pages.
+pub trait IndexKey: Clone + Debug + Ord {
+    type KeySchema: Clone;
+
+    fn serialized_len(key_schema: &Self::KeySchema) -> usize;
+
+    // Returns |InvalidInput| if the key does not match |key_schema|.
+    // The caller needs to ensure that |dst| has |serialized_len| bytes.
+    fn serialize_to(&self, key_schema: &Self::KeySchema, dst: &mut [u8]) -> std::io::Result<()>;
+
+    // The caller needs to make sure that |src| holds a key of |key_schema|.
+    fn deserialize_from(key_schema: &Self::KeySchema, src: &[u8]) -> Self;
+
+    // Equal keys have equal hashes.
+    fn hash(&self) -> u64;
+}
+
+// Integer keys, e.g. those of single integer column indexes.
+impl IndexKey for i64 {
+    type KeySchema = ();
+
+    fn serialized_len(_: &()) -> usize {
+        8
+    }
+
+    fn serialize_to(&self, _: &(), dst: &mut [u8]) -> std::io::Result<()> {
+        reinterpret::write_i64(dst, *self);
+        Ok(())
+    }
+
+    fn deserialize_from(_: &(), src: &[u8]) -> Self {
+        reinterpret::read_i64(src)
+    }
+
+    fn hash(&self) -> u64 {
+        crc32c(&self.to_le_bytes()) as u64
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Key<'a> {
+    values: Vec<Value<'a>>,
+}
+
+impl<'a> Key<'a> {
+    pub fn new(values: Vec<Value<'a>>) -> Self {
+        Key { values }
+    }
+
+    // Projects |tuple| onto its key, where the |idx|-th key column is the
+    // column |key_attrs[idx]| of |schema|.
+    // The caller needs to ensure that |key_attrs| are in range of |schema|.
+    pub fn from_tuple(tuple: &Tuple, schema: &'a Schema, key_attrs: &[usize]) -> Self {
+        let values = key_attrs
+            .iter()
+            .map(|&idx| tuple.nth_value(schema, idx))
+            .collect();
+        Key { values }
+    }
+
+    // Returns a copy owning its values; see |Value::to_static|.
+    pub fn to_static(&self) -> Key<'static> {
+        Key {
+            values: self.values.iter().map(Value::to_static).collect(),
+        }
+    }
+
+    pub fn values(&self) -> &[Value<'a>] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // Whether any key column is null.
+    pub fn has_null(&self) -> bool {
+        self.values.iter().any(|value| value.is_null())
+    }
+
+    // Combines the hashes of the values, see |Value::hash|.
+    pub fn hash(&self) -> u64 {
+        self.values.iter().fold(0, |hash, value| {
+            (hash ^ value.hash()).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+    }
+
+    pub fn compare(&self, other: &Self) -> Ordering {
+        for (lhs, rhs) in self.values.iter().zip(other.values.iter()) {
+            let ordering = compare_values(lhs, rhs);
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        self.values.len().cmp(&other.values.len())
+    }
+
+    // The size of serialized keys of |key_schema|.
+    pub fn serialized_len(key_schema: &Schema) -> usize {
+        bitmap_len(key_schema) + key_schema.columns().iter().map(slot_len).sum::<usize>()
+    }
+
+    // Returns |InvalidInput| if the key does not match |key_schema|, or a
+    // value is longer than its column.
+    // The caller needs to ensure that |dst| has |serialized_len| bytes.
+    pub fn serialize_to(&self, key_schema: &Schema, dst: &mut [u8]) -> std::io::Result<()> {
+        if self.values.len() != key_schema.columns().len() {
+            return Err(invalid_input("Key does not match the key schema"));
+        }
+        let mut offset = bitmap_len(key_schema);
+        dst[..offset].iter_mut().for_each(|byte| *byte = 0);
+        for (idx, (value, column)) in self.values.iter().zip(key_schema.columns()).enumerate() {
+            if value.is_null() {
+                dst[idx / 8] |= 1 << (idx % 8);
+            }
+            if value.borrow().id() != column.types().id() {
+                return Err(invalid_input("Key type does not match the key schema"));
+            }
+            let slot = slot_len(column);
+            if !column.is_inlined() && value.len() > column.variable_len() {
+                return Err(invalid_input(&format!(
+                    "Key value is too long; column = {}",
+                    column.name()
+                )));
+            }
+            let dst = &mut dst[offset..(offset + slot)];
+            dst.iter_mut().for_each(|byte| *byte = 0);
+            value.serialize_to(dst);
+            offset += slot;
+        }
+        Ok(())
+    }
+
+    // The caller needs to make sure that |src| holds a key of |key_schema|.
+    pub fn deserialize_from(key_schema: &'a Schema, src: &[u8]) -> Self {
+        let mut values = Vec::new();
+        let mut offset = bitmap_len(key_schema);
+        for (idx, column) in key_schema.columns().iter().enumerate() {
+            let slot = slot_len(column);
+            let mut value = Value::new(column.types().clone());
+            match src[idx / 8] & (1 << (idx % 8)) != 0 {
+                true => value = Value::null(column.types().clone()),
+                false => value.deserialize_from(&src[offset..(offset + slot)]),
+            }
+            values.push(value);
+            offset += slot;
+        }
+        Key { values }
+    }
+}
+
+impl PartialEq for Key<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.compare(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Key<'_> {}
+
+impl PartialOrd for Key<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Key<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.compare(other)
+    }
+}
+
+impl IndexKey for Key<'static> {
+    type KeySchema = Schema<'static>;
+
+    fn serialized_len(key_schema: &Schema<'static>) -> usize {
+        Key::serialized_len(key_schema)
+    }
+
+    fn serialize_to(&self, key_schema: &Schema<'static>, dst: &mut [u8]) -> std::io::Result<()> {
+        Key::serialize_to(self, key_schema, dst)
+    }
+
+    fn deserialize_from(key_schema: &Schema<'static>, src: &[u8]) -> Self {
+        Key::deserialize_from(key_schema, src).to_static()
+    }
+
+    fn hash(&self) -> u64 {
+        Key::hash(self)
+    }
+}
+
+// Returns the schema of the keys made of the columns |key_attrs| of |schema|.
+// The caller needs to ensure that |key_attrs| are in range of |schema|.
+pub fn key_schema(schema: &Schema, key_attrs: &[usize]) -> Schema<'static> {
+    let columns = key_attrs
+        .iter()
+        .map(|&idx| {
+            let column = schema.nth_column(idx).unwrap();
+            Column::new(
+                column.name().to_string(),
+                column.types().to_static(),
+                column.len(),
+            )
+        })
+        .collect();
+    Schema::new(columns)
+}
+
+// Null is smaller than any value. Values that cannot be compared are equal.
+pub(crate) fn compare_values(lhs: &Value, rhs: &Value) -> Ordering {
+    match (lhs.is_null(), rhs.is_null()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) if lhs.lt(rhs) == Some(true) => Ordering::Less,
+        (false, false) if lhs.gt(rhs) == Some(true) => Ordering::Greater,
+        (false, false) => Ordering::Equal,
+    }
+}
+
+fn bitmap_len(key_schema: &Schema) -> usize {
+    key_schema.columns().len().div_ceil(8)
+}
+
+fn slot_len(column: &Column) -> usize {
+    match column.types() {
+        _ if column.is_inlined() => column.fixed_len(),
+        Types::Varbinary(_) => 4 + column.variable_len(),
+        _ => 1 + column.variable_len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::types::Str;
+    use crate::types::types::Varlen;
+
+    fn name(s: &str) -> Value<'static> {
+        Value::new(Types::Varchar(Varlen::Owned(Str::Val(s.to_string()))))
+    }
+
+    #[test]
+    fn key_from_tuple() {
+        let schema = Schema::new(vec![
+            Column::new("Id".to_string(), Types::integer(), 4),
+            Column::new("Name".to_string(), Types::owned(), 8),
+            Column::new("Score".to_string(), Types::decimal(), 8),
+        ]);
+        let tuple = |id: i32, s: &str| {
+            let values = vec![
+                Value::new(Types::Integer(id)),
+                name(s),
+                Value::new(Types::Decimal(1.5)),
+            ];
+            Tuple::new(&values, &schema)
+        };
+        let key_attrs = [1, 0];
+        let key_schema = key_schema(&schema, &key_attrs);
+        assert_eq!(2, key_schema.columns().len());
+        assert_eq!("Name", key_schema.nth_column(0).unwrap().name());
+        assert_eq!(1 + 4 + 1 + 8, Key::serialized_len(&key_schema));
+
+        // Ordered by name, then by id.
+        let key1 = Key::from_tuple(&tuple(2, "alice"), &schema, &key_attrs);
+        let key2 = Key::from_tuple(&tuple(1, "bob"), &schema, &key_attrs);
+        let key3 = Key::from_tuple(&tuple(3, "bob"), &schema, &key_attrs);
+        assert_eq!("alice", key1.values()[0].to_string());
+        assert_eq!(Ordering::Less, key1.compare(&key2));
+        assert_eq!(Ordering::Less, key2.compare(&key3));
+        assert_eq!(Ordering::Equal, key3.compare(&key3.clone()));
+        assert_ne!(key2.hash(), key3.hash());
+
+        // Round trips through fixed size slots, whatever the string length.
+        for key in [&key1, &key2, &key3].iter() {
+            let mut bytes = vec![0xff; Key::serialized_len(&key_schema)];
+            key.serialize_to(&key_schema, &mut bytes).unwrap();
+            let decoded = Key::deserialize_from(&key_schema, &bytes);
+            assert_eq!(Ordering::Equal, key.compare(&decoded));
+            assert_eq!(key.hash(), decoded.hash());
+        }
+
+        let mut bytes = vec![0; Key::serialized_len(&key_schema)];
+        let long = Key::new(vec![name("a long name"), Value::new(Types::Integer(1))]);
+        assert!(long.serialize_to(&key_schema, &mut bytes).is_err());
+        let short = Key::new(vec![name("a")]);
+        assert!(short.serialize_to(&key_schema, &mut bytes).is_err());
+    }
+
+    #[test]
+    fn nulls_first() {
+        let null = Value::null(Types::integer());
+        let null_key = Key::new(vec![Value::new(Types::Integer(1)), null.clone()]);
+        let key1 = Key::new(vec![
+            Value::new(Types::Integer(1)),
+            Value::new(Types::Integer(0)),
+        ]);
+        assert!(null_key.has_null());
+        assert!(!key1.has_null());
+        assert_eq!(Ordering::Less, null_key.compare(&key1));
+
+        // Equal values of different numeric types are equal keys.
+        let key2 = Key::new(vec![
+            Value::new(Types::BigInt(1)),
+            Value::new(Types::TinyInt(0)),
+        ]);
+        assert_eq!(Ordering::Equal, key1.compare(&key2));
+        assert_eq!(key1.hash(), key2.hash());
+
+        let schema = Schema::new(vec![
+            Column::new("A".to_string(), Types::integer(), 4),
+            Column::new("B".to_string(), Types::integer(), 4),
+        ]);
+        let mut bytes = vec![0; Key::serialized_len(&schema)];
+        null_key.serialize_to(&schema, &mut bytes).unwrap();
+        assert!(Key::deserialize_from(&schema, &bytes).values()[1].is_null());
+
+        // The null value of a type is a legal non-null value.
+        let min_key = Key::new(vec![
+            Value::new(Types::Integer(i32::MIN)),
+            Value::new(Types::Integer(0)),
+        ]);
+        min_key.serialize_to(&schema, &mut bytes).unwrap();
+        let decoded = Key::deserialize_from(&schema, &bytes);
+        assert!(!decoded.has_null());
+        assert_eq!(Ordering::Equal, min_key.compare(&decoded));
+        let null_first = Key::new(vec![null, Value::new(Types::Integer(0))]);
+        assert_eq!(Ordering::Greater, min_key.compare(&null_first));
+        assert_eq!(Ordering::Less, min_key.compare(&key1));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod bplus_tree;
+pub mod extendible_hash;
+pub mod index_iterator;
+pub mod index_util;
+pub mod key;
@@ -1,9 +1,16 @@
 pub mod buffer;
 pub mod catalog;
 pub mod common;
+pub mod concurrency;
+pub mod database;
 pub mod disk;
+pub mod execution;
+pub mod index;
 pub mod logging;
 pub mod page;
+pub mod plan;
+pub mod recovery;
 pub mod table;
 pub mod testing;
+pub mod tools;
 pub mod types;
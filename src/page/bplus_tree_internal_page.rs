@@ -0,0 +1,130 @@
+// Internal page of a B+ tree. Stores n sorted keys and n child page IDs; the
+// first key is unused. Child i holds the keys in [Key_i, Key_{i+1}).
+//
+// Format (size in byte):
+//  ------------------------------------------------------------
+// | HEADER (24) | RightPageId (4) | HighKey | Key_1 | Child_1 (4) | ...
+//  ------------------------------------------------------------
+//
+// See |BPlusTreePage| for the common header; keys take |KeySize| bytes. Like
+// in a B-link tree, |RightPageId| links to the right sibling on the same
+// level, and |HighKey| bounds the keys of the subtree from above (exclusive)
+// unless the page is the rightmost one on its level.
+
+use crate::common::config::PageId;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::config::PAGE_SIZE;
+use crate::common::reinterpret;
+use crate::index::key::IndexKey;
+use crate::page::bplus_tree_page::BPlusTreePage;
+use crate::page::bplus_tree_page::IndexPageType;
+use crate::page::bplus_tree_page::BPLUS_TREE_HEADER_SIZE;
+use crate::page::page::Page;
+
+const RIGHT_PAGE_ID_OFFSET: usize = BPLUS_TREE_HEADER_SIZE;
+const HIGH_KEY_OFFSET: usize = BPLUS_TREE_HEADER_SIZE + 4;
+
+// The maximum number of children an internal page with keys of |key_size|
+// bytes can hold.
+pub fn internal_page_capacity(key_size: usize) -> usize {
+    (PAGE_SIZE - HIGH_KEY_OFFSET).saturating_sub(key_size) / (key_size + 4)
+}
+
+#[derive(Clone, Default)]
+#[repr(transparent)]
+pub struct BPlusTreeInternalPage(BPlusTreePage);
+
+bplus_tree_page_view!(BPlusTreeInternalPage);
+
+impl BPlusTreeInternalPage {
+    // Turns the page into an empty internal page for keys of |key_size| bytes.
+    pub fn init(&mut self, key_size: usize) {
+        self.set_page_type(IndexPageType::Internal);
+        self.set_size(0);
+        self.set_key_size(key_size);
+        self.set_right_page_id(INVALID_PAGE_ID);
+    }
+
+    pub fn right_page_id(&self) -> PageId {
+        reinterpret::read_i32(&self.data()[RIGHT_PAGE_ID_OFFSET..])
+    }
+
+    pub fn set_right_page_id(&mut self, page_id: PageId) {
+        reinterpret::write_i32(&mut self.data_mut()[RIGHT_PAGE_ID_OFFSET..], page_id);
+    }
+
+    // Only meaningful if the page has a right sibling.
+    pub fn high_key<K: IndexKey>(&self, key_schema: &K::KeySchema) -> K {
+        K::deserialize_from(key_schema, &self.data()[HIGH_KEY_OFFSET..])
+    }
+
+    pub fn set_high_key<K: IndexKey>(
+        &mut self,
+        key_schema: &K::KeySchema,
+        key: &K,
+    ) -> std::io::Result<()> {
+        let end = HIGH_KEY_OFFSET + self.key_size();
+        key.serialize_to(key_schema, &mut self.data_mut()[HIGH_KEY_OFFSET..end])
+    }
+
+    // The caller needs to ensure that |idx| < |self.size()|.
+    pub fn key_at<K: IndexKey>(&self, key_schema: &K::KeySchema, idx: usize) -> K {
+        K::deserialize_from(key_schema, &self.data()[self.entry_offset(idx)..])
+    }
+
+    // The caller needs to ensure that |idx| < |self.size()|.
+    pub fn child_at(&self, idx: usize) -> PageId {
+        reinterpret::read_i32(&self.data()[(self.entry_offset(idx) + self.key_size())..])
+    }
+
+    // Returns the index of the child whose subtree may contain |key|.
+    pub fn child_index<K: IndexKey>(&self, key_schema: &K::KeySchema, key: &K) -> usize {
+        // Finds the last key <= |key|, skipping the unused first key.
+        let (mut lo, mut hi) = (1, self.size());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self.key_at::<K>(key_schema, mid) <= *key {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo - 1
+    }
+
+    // Returns the child whose subtree may contain |key|.
+    pub fn lookup<K: IndexKey>(&self, key_schema: &K::KeySchema, key: &K) -> PageId {
+        self.child_at(self.child_index(key_schema, key))
+    }
+
+    pub fn entries<K: IndexKey>(&self, key_schema: &K::KeySchema) -> Vec<(K, PageId)> {
+        (0..self.size())
+            .map(|idx| (self.key_at(key_schema, idx), self.child_at(idx)))
+            .collect()
+    }
+
+    // Replaces all entries. Returns |InvalidInput| if a key does not match
+    // |key_schema|. The caller needs to ensure that |entries| are sorted
+    // (except for the first key) and fit into the page.
+    pub fn set_entries<K: IndexKey>(
+        &mut self,
+        key_schema: &K::KeySchema,
+        entries: &[(K, PageId)],
+    ) -> std::io::Result<()> {
+        let key_size = self.key_size();
+        for (idx, (key, child)) in entries.iter().enumerate() {
+            let offset = self.entry_offset(idx);
+            let data = self.data_mut();
+            key.serialize_to(key_schema, &mut data[offset..(offset + key_size)])?;
+            reinterpret::write_i32(&mut data[(offset + key_size)..], *child);
+        }
+        self.set_size(entries.len());
+        Ok(())
+    }
+
+    // Entries follow the high key.
+    fn entry_offset(&self, idx: usize) -> usize {
+        let key_size = self.key_size();
+        HIGH_KEY_OFFSET + key_size + idx * (key_size + 4)
+    }
+}
@@ -0,0 +1,181 @@
+// Leaf page of a B+ tree. Stores sorted keys with the RIDs of their tuples,
+// and links to its neighbouring leaves for range scans in both directions.
+//
+// Format (size in byte):
+//  ----------------------------------------------------------------------------
+// | HEADER (24) | PrevPageId (4) | NextPageId (4) | HighKey | Key_1 | Rid_1 (8) | ...
+//  ----------------------------------------------------------------------------
+//
+// See |BPlusTreePage| for the common header; keys take |KeySize| bytes. A RID
+// is stored as its page ID (4) followed by its slot number (4). Like in a
+// B-link tree, |NextPageId| is the right link, and |HighKey| bounds the keys
+// of the leaf from above (exclusive) unless it is the rightmost leaf.
+
+use crate::common::config::PageId;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::config::PAGE_SIZE;
+use crate::common::reinterpret;
+use crate::common::rid::Rid;
+use crate::index::key::IndexKey;
+use crate::page::bplus_tree_page::BPlusTreePage;
+use crate::page::bplus_tree_page::IndexPageType;
+use crate::page::bplus_tree_page::BPLUS_TREE_HEADER_SIZE;
+use crate::page::page::Page;
+
+const PREV_PAGE_ID_OFFSET: usize = BPLUS_TREE_HEADER_SIZE;
+const NEXT_PAGE_ID_OFFSET: usize = BPLUS_TREE_HEADER_SIZE + 4;
+const HIGH_KEY_OFFSET: usize = BPLUS_TREE_HEADER_SIZE + 8;
+
+// The maximum number of entries a leaf page with keys of |key_size| bytes can
+// hold.
+pub fn leaf_page_capacity(key_size: usize) -> usize {
+    (PAGE_SIZE - HIGH_KEY_OFFSET).saturating_sub(key_size) / (key_size + 8)
+}
+
+#[derive(Clone, Default)]
+#[repr(transparent)]
+pub struct BPlusTreeLeafPage(BPlusTreePage);
+
+bplus_tree_page_view!(BPlusTreeLeafPage);
+
+impl BPlusTreeLeafPage {
+    // Turns the page into an empty leaf page for keys of |key_size| bytes.
+    pub fn init(&mut self, key_size: usize) {
+        self.set_page_type(IndexPageType::Leaf);
+        self.set_size(0);
+        self.set_key_size(key_size);
+        self.set_prev_page_id(INVALID_PAGE_ID);
+        self.set_next_page_id(INVALID_PAGE_ID);
+    }
+
+    pub fn prev_page_id(&self) -> PageId {
+        reinterpret::read_i32(&self.data()[PREV_PAGE_ID_OFFSET..])
+    }
+
+    pub fn next_page_id(&self) -> PageId {
+        reinterpret::read_i32(&self.data()[NEXT_PAGE_ID_OFFSET..])
+    }
+
+    pub fn set_prev_page_id(&mut self, page_id: PageId) {
+        reinterpret::write_i32(&mut self.data_mut()[PREV_PAGE_ID_OFFSET..], page_id);
+    }
+
+    pub fn set_next_page_id(&mut self, page_id: PageId) {
+        reinterpret::write_i32(&mut self.data_mut()[NEXT_PAGE_ID_OFFSET..], page_id);
+    }
+
+    // Only meaningful if the leaf has a right sibling.
+    pub fn high_key<K: IndexKey>(&self, key_schema: &K::KeySchema) -> K {
+        K::deserialize_from(key_schema, &self.data()[HIGH_KEY_OFFSET..])
+    }
+
+    pub fn set_high_key<K: IndexKey>(
+        &mut self,
+        key_schema: &K::KeySchema,
+        key: &K,
+    ) -> std::io::Result<()> {
+        let end = HIGH_KEY_OFFSET + self.key_size();
+        key.serialize_to(key_schema, &mut self.data_mut()[HIGH_KEY_OFFSET..end])
+    }
+
+    // The caller needs to ensure that |idx| < |self.size()|.
+    pub fn key_at<K: IndexKey>(&self, key_schema: &K::KeySchema, idx: usize) -> K {
+        K::deserialize_from(key_schema, &self.data()[self.entry_offset(idx)..])
+    }
+
+    // The caller needs to ensure that |idx| < |self.size()|.
+    pub fn rid_at(&self, idx: usize) -> Rid {
+        let offset = self.entry_offset(idx) + self.key_size();
+        Rid::new(
+            reinterpret::read_i32(&self.data()[offset..]),
+            reinterpret::read_u32(&self.data()[(offset + 4)..]) as usize,
+        )
+    }
+
+    // Returns the index of the first key >= |key|, which is |self.size()| if
+    // there is none.
+    pub fn key_index<K: IndexKey>(&self, key_schema: &K::KeySchema, key: &K) -> usize {
+        let (mut lo, mut hi) = (0, self.size());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self.key_at::<K>(key_schema, mid) < *key {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    pub fn lookup<K: IndexKey>(&self, key_schema: &K::KeySchema, key: &K) -> Option<Rid> {
+        let idx = self.key_index(key_schema, key);
+        if idx < self.size() && self.key_at::<K>(key_schema, idx) == *key {
+            Some(self.rid_at(idx))
+        } else {
+            None
+        }
+    }
+
+    pub fn entries<K: IndexKey>(&self, key_schema: &K::KeySchema) -> Vec<(K, Rid)> {
+        (0..self.size())
+            .map(|idx| (self.key_at(key_schema, idx), self.rid_at(idx)))
+            .collect()
+    }
+
+    // Replaces all entries. Returns |InvalidInput| if a key does not match
+    // |key_schema|. The caller needs to ensure that |entries| are sorted and
+    // fit into the page.
+    pub fn set_entries<K: IndexKey>(
+        &mut self,
+        key_schema: &K::KeySchema,
+        entries: &[(K, Rid)],
+    ) -> std::io::Result<()> {
+        let key_size = self.key_size();
+        for (idx, (key, rid)) in entries.iter().enumerate() {
+            let offset = self.entry_offset(idx);
+            let data = self.data_mut();
+            key.serialize_to(key_schema, &mut data[offset..(offset + key_size)])?;
+            reinterpret::write_i32(&mut data[(offset + key_size)..], rid.page_id());
+            reinterpret::write_u32(&mut data[(offset + key_size + 4)..], rid.slot_num() as u32);
+        }
+        self.set_size(entries.len());
+        Ok(())
+    }
+
+    // Entries follow the high key.
+    fn entry_offset(&self, idx: usize) -> usize {
+        let key_size = self.key_size();
+        HIGH_KEY_OFFSET + key_size + idx * (key_size + 8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_and_lookup() {
+        let mut page = BPlusTreeLeafPage::default();
+        page.init(8);
+        assert!(page.is_leaf());
+        assert_eq!(INVALID_PAGE_ID, page.next_page_id());
+
+        let capacity = leaf_page_capacity(8);
+        let entries: Vec<(i64, Rid)> = (0..capacity)
+            .map(|i| (i as i64 * 2, Rid::new(i as PageId, i)))
+            .collect();
+        page.set_entries(&(), &entries).unwrap();
+        page.set_high_key(&(), &i64::MAX).unwrap();
+        assert_eq!(capacity, page.size());
+        assert_eq!(entries, page.entries(&()));
+        assert_eq!(i64::MAX, page.high_key::<i64>(&()));
+
+        assert_eq!(Some(Rid::new(5, 5)), page.lookup(&(), &10));
+        assert_eq!(None, page.lookup(&(), &11));
+        assert_eq!(6, page.key_index(&(), &11));
+        assert_eq!(0, page.key_index(&(), &-1));
+        assert_eq!(capacity, page.key_index(&(), &i64::MAX));
+        // The last entry ends within the page.
+        assert!(page.entry_offset(capacity) <= PAGE_SIZE);
+    }
+}
@@ -0,0 +1,242 @@
+// Common header shared by B+ tree internal and leaf pages. The buffer pool
+// holds the pages in frames of any |PageFrame| type; |BPlusTreePage| is a view
+// over a frame, and |BPlusTreeInternalPage| and |BPlusTreeLeafPage| are views
+// over a |BPlusTreePage|, selected by its page type. The extendible hash index
+// uses the same views for its directory and bucket pages.
+//
+// Header format (size in byte):
+//  -------------------------------------------------------------
+// | Checksum (8) | PageType (4) | LSN (4) | Size (4) | KeySize (4) |
+//  -------------------------------------------------------------
+//
+// |Size| is the number of entries (key/value pairs) stored in the page, and
+// |KeySize| the size of their keys, which is fixed per index; see |IndexKey|.
+// Like |HeaderPage|, the page ID is not part of the data. Index pages are not
+// logged, so the LSN stays invalid; it is where |TablePage| keeps its LSN, so
+// that index pages in the frames of table pages never wait for the log.
+
+use crate::common::config::Lsn;
+use crate::common::config::PageId;
+use crate::common::config::CHECKSUM_SIZE;
+use crate::common::config::INVALID_LSN;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::config::PAGE_SIZE;
+use crate::common::reinterpret;
+use crate::page::page::Page;
+use crate::page::page::PageFrame;
+use crate::page::page_latch::PageLatch;
+use std::clone::Clone;
+use std::default::Default;
+
+const PAGE_TYPE_OFFSET: usize = CHECKSUM_SIZE;
+const LSN_OFFSET: usize = CHECKSUM_SIZE + 4;
+const SIZE_OFFSET: usize = CHECKSUM_SIZE + 8;
+const KEY_SIZE_OFFSET: usize = CHECKSUM_SIZE + 12;
+
+pub const BPLUS_TREE_HEADER_SIZE: usize = CHECKSUM_SIZE + 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndexPageType {
+    Invalid = 0,
+    Internal = 1,
+    Leaf = 2,
+    HashDirectory = 3,
+    HashBucket = 4,
+}
+
+#[derive(Clone)]
+#[repr(C)]
+pub struct BPlusTreePage {
+    data: [u8; PAGE_SIZE],
+    page_id: PageId,
+    pin_count: i32,
+    is_dirty: bool,
+    latch: PageLatch,
+}
+
+impl BPlusTreePage {
+    pub fn page_type(&self) -> IndexPageType {
+        match reinterpret::read_u32(&self.data[PAGE_TYPE_OFFSET..]) {
+            1 => IndexPageType::Internal,
+            2 => IndexPageType::Leaf,
+            3 => IndexPageType::HashDirectory,
+            4 => IndexPageType::HashBucket,
+            _ => IndexPageType::Invalid,
+        }
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.page_type() == IndexPageType::Leaf
+    }
+
+    pub fn size(&self) -> usize {
+        reinterpret::read_u32(&self.data[SIZE_OFFSET..]) as usize
+    }
+
+    pub fn key_size(&self) -> usize {
+        reinterpret::read_u32(&self.data[KEY_SIZE_OFFSET..]) as usize
+    }
+
+    pub(crate) fn set_page_type(&mut self, page_type: IndexPageType) {
+        reinterpret::write_u32(&mut self.data[PAGE_TYPE_OFFSET..], page_type as u32);
+    }
+
+    pub(crate) fn set_size(&mut self, size: usize) {
+        reinterpret::write_u32(&mut self.data[SIZE_OFFSET..], size as u32);
+    }
+
+    pub(crate) fn set_key_size(&mut self, key_size: usize) {
+        reinterpret::write_u32(&mut self.data[KEY_SIZE_OFFSET..], key_size as u32);
+    }
+}
+
+impl Default for BPlusTreePage {
+    fn default() -> Self {
+        BPlusTreePage {
+            data: [0; PAGE_SIZE],
+            page_id: INVALID_PAGE_ID,
+            pin_count: 0,
+            is_dirty: false,
+            latch: PageLatch::new(),
+        }
+    }
+}
+
+impl Page for BPlusTreePage {
+    fn reset(&mut self) {
+        for byte in self.data[CHECKSUM_SIZE..BPLUS_TREE_HEADER_SIZE].iter_mut() {
+            *byte = 0;
+        }
+        self.set_lsn(INVALID_LSN);
+    }
+
+    fn page_id(&self) -> PageId {
+        self.page_id
+    }
+
+    fn set_page_id(&mut self, page_id: PageId) {
+        self.page_id = page_id;
+    }
+
+    fn data(&self) -> &[u8; PAGE_SIZE] {
+        &self.data
+    }
+
+    fn data_mut(&mut self) -> &mut [u8; PAGE_SIZE] {
+        &mut self.data
+    }
+
+    fn pin_count(&self) -> i32 {
+        self.pin_count
+    }
+
+    fn pin_count_mut(&mut self) -> &mut i32 {
+        &mut self.pin_count
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+
+    fn is_dirty_mut(&mut self) -> &mut bool {
+        &mut self.is_dirty
+    }
+
+    fn latch(&self) -> &PageLatch {
+        &self.latch
+    }
+
+    fn lsn(&self) -> Lsn {
+        reinterpret::read_i32(&self.data[LSN_OFFSET..])
+    }
+
+    fn set_lsn(&mut self, lsn: Lsn) {
+        reinterpret::write_i32(&mut self.data[LSN_OFFSET..], lsn);
+    }
+}
+
+impl PageFrame for BPlusTreePage {}
+
+// Implements |Page|, |Deref| and the casts from a |BPlusTreePage| frame for a
+// view type, which needs to be a |#[repr(transparent)]| wrapper of the frame.
+macro_rules! bplus_tree_page_view {
+    ($view:ident) => {
+        impl $view {
+            // Views |page| as this page type. The caller needs to ensure that
+            // the page type matches.
+            pub fn cast(page: &BPlusTreePage) -> &Self {
+                // Safe, since |Self| is a transparent wrapper of the frame.
+                unsafe { &*(page as *const BPlusTreePage as *const Self) }
+            }
+
+            pub fn cast_mut(page: &mut BPlusTreePage) -> &mut Self {
+                // Safe, since |Self| is a transparent wrapper of the frame.
+                unsafe { &mut *(page as *mut BPlusTreePage as *mut Self) }
+            }
+        }
+
+        impl std::ops::Deref for $view {
+            type Target = BPlusTreePage;
+
+            fn deref(&self) -> &BPlusTreePage {
+                &self.0
+            }
+        }
+
+        impl std::ops::DerefMut for $view {
+            fn deref_mut(&mut self) -> &mut BPlusTreePage {
+                &mut self.0
+            }
+        }
+
+        impl Page for $view {
+            fn reset(&mut self) {
+                self.0.reset();
+            }
+
+            fn page_id(&self) -> PageId {
+                self.0.page_id()
+            }
+
+            fn set_page_id(&mut self, page_id: PageId) {
+                self.0.set_page_id(page_id);
+            }
+
+            fn data(&self) -> &[u8; PAGE_SIZE] {
+                self.0.data()
+            }
+
+            fn data_mut(&mut self) -> &mut [u8; PAGE_SIZE] {
+                self.0.data_mut()
+            }
+
+            fn pin_count(&self) -> i32 {
+                self.0.pin_count()
+            }
+
+            fn pin_count_mut(&mut self) -> &mut i32 {
+                self.0.pin_count_mut()
+            }
+
+            fn is_dirty(&self) -> bool {
+                self.0.is_dirty()
+            }
+
+            fn is_dirty_mut(&mut self) -> &mut bool {
+                self.0.is_dirty_mut()
+            }
+
+            fn latch(&self) -> &$crate::page::page_latch::PageLatch {
+                self.0.latch()
+            }
+
+            fn lsn(&self) -> $crate::common::config::Lsn {
+                self.0.lsn()
+            }
+
+            fn set_lsn(&mut self, lsn: $crate::common::config::Lsn) {
+                self.0.set_lsn(lsn);
+            }
+        }
+    };
+}
@@ -0,0 +1,235 @@
+// Functionality: The allocation page of a table heap growing by extents, i.e.
+// runs of |EXTENT_SIZE| contiguous pages, so that sequential scans of the
+// table read mostly contiguous regions of the file. The allocation page is the
+// first page of the first extent, right before the first page of the heap
+// (its owner), and lists the extents of the heap:
+//
+// Format (size in byte):
+//  ------------------------------------------------------------------
+// | Checksum (8) | PageId (4) | LSN (4) | Magic (4) | OwnerPageId (4) |
+//  ------------------------------------------------------------------
+//  ---------------------------------------------------------------------
+// | ExtentCount (4) | Used (4) | Extent_1 first page_id (4) | Extent_2 ...
+//  ---------------------------------------------------------------------
+//
+// The header starts like that of a |TablePage|, so that the page lives in the
+// frames of table pages. The page is not logged, so its LSN stays invalid.
+// |Used| is the number of pages of the last extent already handed out. Pages
+// are handed out in order, so the pages of the other extents are all in use.
+
+use crate::common::config::Lsn;
+use crate::common::config::PageId;
+use crate::common::config::CHECKSUM_SIZE;
+use crate::common::config::INVALID_LSN;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::config::PAGE_SIZE;
+use crate::common::error::*;
+use crate::common::reinterpret;
+use crate::page::page::Page;
+use crate::page::page::PageFrame;
+use crate::page::page_latch::PageLatch;
+use std::clone::Clone;
+use std::default::Default;
+
+// The number of pages of an extent.
+pub const EXTENT_SIZE: usize = 64;
+
+const MAGIC: u32 = 0x6578_746e;
+const PAGE_ID_OFFSET: usize = CHECKSUM_SIZE;
+const LSN_OFFSET: usize = CHECKSUM_SIZE + 4;
+const MAGIC_OFFSET: usize = CHECKSUM_SIZE + 8;
+const OWNER_OFFSET: usize = CHECKSUM_SIZE + 12;
+const EXTENT_COUNT_OFFSET: usize = CHECKSUM_SIZE + 16;
+const USED_OFFSET: usize = CHECKSUM_SIZE + 20;
+const EXTENTS_OFFSET: usize = CHECKSUM_SIZE + 24;
+
+// The maximum number of extents an allocation page can hold.
+pub const MAX_EXTENT_COUNT: usize = (PAGE_SIZE - EXTENTS_OFFSET) / 4;
+
+#[derive(Clone)]
+#[repr(C)]
+pub struct ExtentPage {
+    data: [u8; PAGE_SIZE],
+    page_id: PageId,
+    pin_count: i32,
+    is_dirty: bool,
+    latch: PageLatch,
+}
+
+impl ExtentPage {
+    pub fn new() -> Self {
+        let mut page = Self::default();
+        page.reset();
+        page
+    }
+
+    // Makes the page the allocation page of the heap starting at |owner|, with
+    // the extent starting at |first_page_id|, whose first |used| pages are in
+    // use.
+    pub fn init(&mut self, owner: PageId, first_page_id: PageId, used: usize) {
+        reinterpret::write_u32(&mut self.data[MAGIC_OFFSET..], MAGIC);
+        reinterpret::write_i32(&mut self.data[OWNER_OFFSET..], owner);
+        self.set_extent_count(1);
+        reinterpret::write_i32(&mut self.data[EXTENTS_OFFSET..], first_page_id);
+        self.set_used(used);
+    }
+
+    // Whether the page is the allocation page of the heap starting at |owner|.
+    pub fn is_owned_by(&self, owner: PageId) -> bool {
+        reinterpret::read_u32(&self.data[MAGIC_OFFSET..]) == MAGIC
+            && reinterpret::read_i32(&self.data[OWNER_OFFSET..]) == owner
+    }
+
+    pub fn extent_count(&self) -> usize {
+        reinterpret::read_u32(&self.data[EXTENT_COUNT_OFFSET..]) as usize
+    }
+
+    // The first page ID of the |idx|-th extent.
+    // The caller needs to ensure that |idx| < |extent_count|.
+    pub fn extent(&self, idx: usize) -> PageId {
+        reinterpret::read_i32(&self.data[(EXTENTS_OFFSET + idx * 4)..])
+    }
+
+    // Hands out the next page of the last extent. Returns None if the last
+    // extent is used up; see |push_extent|.
+    pub fn next_page_id(&mut self) -> Option<PageId> {
+        let used = self.used();
+        if used >= EXTENT_SIZE {
+            return None;
+        }
+        self.set_used(used + 1);
+        Some(self.extent(self.extent_count() - 1) + used as PageId)
+    }
+
+    // Whether the page cannot hold another extent.
+    pub fn is_full(&self) -> bool {
+        self.extent_count() >= MAX_EXTENT_COUNT
+    }
+
+    // Appends the extent starting at |first_page_id|, from which the next pages
+    // are handed out. Returns |InvalidInput| if the page is full.
+    pub fn push_extent(&mut self, first_page_id: PageId) -> std::io::Result<()> {
+        let count = self.extent_count();
+        if self.is_full() {
+            return Err(invalid_input("Allocation page is full"));
+        }
+        let offset = EXTENTS_OFFSET + count * 4;
+        reinterpret::write_i32(&mut self.data[offset..], first_page_id);
+        self.set_extent_count(count + 1);
+        self.set_used(0);
+        Ok(())
+    }
+
+    fn used(&self) -> usize {
+        reinterpret::read_u32(&self.data[USED_OFFSET..]) as usize
+    }
+
+    fn set_used(&mut self, used: usize) {
+        reinterpret::write_u32(&mut self.data[USED_OFFSET..], used as u32);
+    }
+
+    fn set_extent_count(&mut self, count: usize) {
+        reinterpret::write_u32(&mut self.data[EXTENT_COUNT_OFFSET..], count as u32);
+    }
+}
+
+impl Default for ExtentPage {
+    fn default() -> Self {
+        ExtentPage {
+            data: [0; PAGE_SIZE],
+            page_id: INVALID_PAGE_ID,
+            pin_count: 0,
+            is_dirty: false,
+            latch: PageLatch::new(),
+        }
+    }
+}
+
+impl Page for ExtentPage {
+    fn reset(&mut self) {
+        for byte in self.data.iter_mut().skip(LSN_OFFSET) {
+            *byte = 0;
+        }
+        self.set_lsn(INVALID_LSN);
+        self.set_page_id(self.page_id);
+    }
+
+    fn page_id(&self) -> PageId {
+        self.page_id
+    }
+
+    // Also stores the page ID in the data, like |reset|; see
+    // |TablePage::set_page_id|.
+    fn set_page_id(&mut self, page_id: PageId) {
+        self.page_id = page_id;
+        reinterpret::write_i32(&mut self.data[PAGE_ID_OFFSET..], page_id);
+    }
+
+    fn data(&self) -> &[u8; PAGE_SIZE] {
+        &self.data
+    }
+
+    fn data_mut(&mut self) -> &mut [u8; PAGE_SIZE] {
+        &mut self.data
+    }
+
+    fn pin_count(&self) -> i32 {
+        self.pin_count
+    }
+
+    fn pin_count_mut(&mut self) -> &mut i32 {
+        &mut self.pin_count
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+
+    fn is_dirty_mut(&mut self) -> &mut bool {
+        &mut self.is_dirty
+    }
+
+    fn latch(&self) -> &PageLatch {
+        &self.latch
+    }
+
+    fn lsn(&self) -> Lsn {
+        reinterpret::read_i32(&self.data[LSN_OFFSET..])
+    }
+
+    fn set_lsn(&mut self, lsn: Lsn) {
+        reinterpret::write_i32(&mut self.data[LSN_OFFSET..], lsn);
+    }
+}
+
+impl PageFrame for ExtentPage {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extent_page() {
+        let mut page = ExtentPage::new();
+        assert!(!page.is_owned_by(0));
+        page.init(/*owner=*/ 11, /*first_page_id=*/ 10, 2);
+        assert!(page.is_owned_by(11));
+        assert!(!page.is_owned_by(10));
+        assert_eq!(1, page.extent_count());
+
+        for i in 2..EXTENT_SIZE {
+            assert_eq!(Some(10 + i as PageId), page.next_page_id());
+        }
+        assert_eq!(None, page.next_page_id());
+        assert!(page.push_extent(200).is_ok());
+        assert_eq!(Some(200), page.next_page_id());
+        assert_eq!(2, page.extent_count());
+        assert_eq!(200, page.extent(1));
+
+        while page.extent_count() < MAX_EXTENT_COUNT {
+            assert!(page.push_extent(0).is_ok());
+        }
+        assert!(page.is_full());
+        assert!(page.push_extent(0).is_err());
+    }
+}
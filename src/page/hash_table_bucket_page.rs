@@ -0,0 +1,136 @@
+// Bucket page of an extendible hash index. Stores unsorted, unique keys with
+// the RIDs of their tuples.
+//
+// Format (size in byte):
+//  ------------------------------------------
+// | HEADER (24) | Key_1 | Rid_1 (8) | ... |
+//  ------------------------------------------
+//
+// See |BPlusTreePage| for the common header; keys take |KeySize| bytes. A RID
+// is stored as its page ID (4) followed by its slot number (4).
+
+use crate::common::config::PageId;
+use crate::common::config::PAGE_SIZE;
+use crate::common::reinterpret;
+use crate::common::rid::Rid;
+use crate::index::key::IndexKey;
+use crate::page::bplus_tree_page::BPlusTreePage;
+use crate::page::bplus_tree_page::IndexPageType;
+use crate::page::bplus_tree_page::BPLUS_TREE_HEADER_SIZE;
+use crate::page::page::Page;
+
+const ENTRIES_OFFSET: usize = BPLUS_TREE_HEADER_SIZE;
+
+// The maximum number of entries a bucket page with keys of |key_size| bytes
+// can hold.
+pub fn bucket_page_capacity(key_size: usize) -> usize {
+    (PAGE_SIZE - ENTRIES_OFFSET) / (key_size + 8)
+}
+
+#[derive(Clone, Default)]
+#[repr(transparent)]
+pub struct HashTableBucketPage(BPlusTreePage);
+
+bplus_tree_page_view!(HashTableBucketPage);
+
+impl HashTableBucketPage {
+    // Turns the page into an empty bucket page for keys of |key_size| bytes.
+    pub fn init(&mut self, key_size: usize) {
+        self.set_page_type(IndexPageType::HashBucket);
+        self.set_size(0);
+        self.set_key_size(key_size);
+    }
+
+    pub fn lookup<K: IndexKey>(&self, key_schema: &K::KeySchema, key: &K) -> Option<Rid> {
+        self.position(key_schema, key)
+            .map(|idx| self.entry_at::<K>(key_schema, idx).1)
+    }
+
+    // Appends the entry. Returns |InvalidInput| if the key does not match
+    // |key_schema|. The caller needs to ensure that the key is not in the
+    // bucket and that the bucket is not full.
+    pub fn insert<K: IndexKey>(
+        &mut self,
+        key_schema: &K::KeySchema,
+        key: &K,
+        rid: &Rid,
+    ) -> std::io::Result<()> {
+        let idx = self.size();
+        self.set_entry(key_schema, idx, key, rid)?;
+        self.set_size(idx + 1);
+        Ok(())
+    }
+
+    // Removes the entry of |key|, moving the last entry into its place.
+    // Returns false if there is no such key.
+    pub fn remove<K: IndexKey>(&mut self, key_schema: &K::KeySchema, key: &K) -> bool {
+        let idx = match self.position(key_schema, key) {
+            Some(idx) => idx,
+            None => return false,
+        };
+        // Moves the raw bytes, as they are known to be a valid entry.
+        let last = self.size() - 1;
+        let entry_size = self.key_size() + 8;
+        let (src, dst) = (self.entry_offset(last), self.entry_offset(idx));
+        self.data_mut().copy_within(src..(src + entry_size), dst);
+        self.set_size(last);
+        true
+    }
+
+    pub fn entries<K: IndexKey>(&self, key_schema: &K::KeySchema) -> Vec<(K, Rid)> {
+        (0..self.size())
+            .map(|idx| self.entry_at(key_schema, idx))
+            .collect()
+    }
+
+    // Replaces all entries. Returns |InvalidInput| if a key does not match
+    // |key_schema|. The caller needs to ensure that they fit.
+    pub fn set_entries<K: IndexKey>(
+        &mut self,
+        key_schema: &K::KeySchema,
+        entries: &[(K, Rid)],
+    ) -> std::io::Result<()> {
+        for (idx, (key, rid)) in entries.iter().enumerate() {
+            self.set_entry(key_schema, idx, key, rid)?;
+        }
+        self.set_size(entries.len());
+        Ok(())
+    }
+
+    fn position<K: IndexKey>(&self, key_schema: &K::KeySchema, key: &K) -> Option<usize> {
+        (0..self.size()).find(|&idx| self.key_at::<K>(key_schema, idx) == *key)
+    }
+
+    fn key_at<K: IndexKey>(&self, key_schema: &K::KeySchema, idx: usize) -> K {
+        K::deserialize_from(key_schema, &self.data()[self.entry_offset(idx)..])
+    }
+
+    fn entry_at<K: IndexKey>(&self, key_schema: &K::KeySchema, idx: usize) -> (K, Rid) {
+        let offset = self.entry_offset(idx) + self.key_size();
+        let rid = Rid::new(
+            reinterpret::read_i32(&self.data()[offset..]),
+            reinterpret::read_u32(&self.data()[(offset + 4)..]) as usize,
+        );
+        (self.key_at(key_schema, idx), rid)
+    }
+
+    fn set_entry<K: IndexKey>(
+        &mut self,
+        key_schema: &K::KeySchema,
+        idx: usize,
+        key: &K,
+        rid: &Rid,
+    ) -> std::io::Result<()> {
+        let key_size = self.key_size();
+        let offset = self.entry_offset(idx);
+        let data = self.data_mut();
+        key.serialize_to(key_schema, &mut data[offset..(offset + key_size)])?;
+        reinterpret::write_i32(&mut data[(offset + key_size)..], rid.page_id());
+        reinterpret::write_u32(&mut data[(offset + key_size + 4)..], rid.slot_num() as u32);
+        Ok(())
+    }
+
+    fn entry_offset(&self, idx: usize) -> usize {
+        ENTRIES_OFFSET + idx * (self.key_size() + 8)
+    }
+}
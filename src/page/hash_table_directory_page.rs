@@ -0,0 +1,110 @@
+// Directory page of an extendible hash index. Maps the lowest |GlobalDepth|
+// bits of a key hash to a bucket page, along with the local depth of the
+// bucket.
+//
+// Format (size in byte):
+//  ------------------------------------------------------------------------
+// | HEADER (20) | GlobalDepth (4) | BucketPageIds (4 * 512) | LocalDepths (512) |
+//  ------------------------------------------------------------------------
+//
+// See |BPlusTreePage| for the common header.
+
+use crate::common::config::PageId;
+use crate::common::config::PAGE_SIZE;
+use crate::common::reinterpret;
+use crate::page::bplus_tree_page::BPlusTreePage;
+use crate::page::bplus_tree_page::IndexPageType;
+use crate::page::bplus_tree_page::BPLUS_TREE_HEADER_SIZE;
+use crate::page::page::Page;
+
+// The directory holds at most 2^MAX_GLOBAL_DEPTH slots.
+pub const MAX_GLOBAL_DEPTH: u32 = 9;
+pub const DIRECTORY_CAPACITY: usize = 1 << MAX_GLOBAL_DEPTH;
+
+const GLOBAL_DEPTH_OFFSET: usize = BPLUS_TREE_HEADER_SIZE;
+const BUCKET_PAGE_IDS_OFFSET: usize = BPLUS_TREE_HEADER_SIZE + 4;
+const LOCAL_DEPTHS_OFFSET: usize = BUCKET_PAGE_IDS_OFFSET + 4 * DIRECTORY_CAPACITY;
+
+#[derive(Clone, Default)]
+#[repr(transparent)]
+pub struct HashTableDirectoryPage(BPlusTreePage);
+
+bplus_tree_page_view!(HashTableDirectoryPage);
+
+impl HashTableDirectoryPage {
+    // Turns the page into a directory of global depth 0, whose only slot
+    // points to |bucket_page_id|.
+    pub fn init(&mut self, bucket_page_id: PageId) {
+        self.set_page_type(IndexPageType::HashDirectory);
+        self.set_global_depth(0);
+        self.set_bucket_page_id(0, bucket_page_id);
+        self.set_local_depth(0, 0);
+    }
+
+    pub fn global_depth(&self) -> u32 {
+        reinterpret::read_u32(&self.data()[GLOBAL_DEPTH_OFFSET..])
+    }
+
+    // The number of slots in use.
+    pub fn len(&self) -> usize {
+        1 << self.global_depth()
+    }
+
+    // The directory always has at least one slot.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    // The mask selecting the slot of a hash.
+    pub fn global_depth_mask(&self) -> u32 {
+        (1 << self.global_depth()) - 1
+    }
+
+    // The caller needs to ensure that |idx| < |self.len()|.
+    pub fn bucket_page_id(&self, idx: usize) -> PageId {
+        reinterpret::read_i32(&self.data()[(BUCKET_PAGE_IDS_OFFSET + 4 * idx)..])
+    }
+
+    pub fn set_bucket_page_id(&mut self, idx: usize, page_id: PageId) {
+        reinterpret::write_i32(
+            &mut self.data_mut()[(BUCKET_PAGE_IDS_OFFSET + 4 * idx)..],
+            page_id,
+        );
+    }
+
+    // The caller needs to ensure that |idx| < |self.len()|.
+    pub fn local_depth(&self, idx: usize) -> u32 {
+        self.data()[LOCAL_DEPTHS_OFFSET + idx] as u32
+    }
+
+    pub fn set_local_depth(&mut self, idx: usize, depth: u32) {
+        self.data_mut()[LOCAL_DEPTHS_OFFSET + idx] = depth as u8;
+    }
+
+    // Doubles the directory; each new slot mirrors its split image. The caller
+    // needs to ensure that the global depth is below |MAX_GLOBAL_DEPTH|.
+    pub fn grow(&mut self) {
+        let len = self.len();
+        for idx in 0..len {
+            self.set_bucket_page_id(idx + len, self.bucket_page_id(idx));
+            self.set_local_depth(idx + len, self.local_depth(idx));
+        }
+        self.set_global_depth(self.global_depth() + 1);
+    }
+
+    // Halves the directory while no bucket needs all global depth bits.
+    pub fn shrink(&mut self) {
+        while self.global_depth() > 0
+            && (0..self.len()).all(|idx| self.local_depth(idx) < self.global_depth())
+        {
+            self.set_global_depth(self.global_depth() - 1);
+        }
+    }
+
+    fn set_global_depth(&mut self, depth: u32) {
+        reinterpret::write_u32(&mut self.data_mut()[GLOBAL_DEPTH_OFFSET..], depth);
+    }
+}
+
+// The directory has to fit into a page.
+const _: () = assert!(LOCAL_DEPTHS_OFFSET + DIRECTORY_CAPACITY <= PAGE_SIZE);
@@ -1,30 +1,81 @@
-// Database use the first page (page_id = 1) as header page to store metadata, in
-// our case, we will contain information about table/index name (length less than
-// 32 bytes) and their corresponding root_id
+// Database use the first page (page_id = 0) as header page to store metadata, in
+// our case, we will contain information about index name (length less than 32
+// bytes) and their corresponding root_id
 //
 // Format (size in byte):
-//  --------------------------------------------------------------------------------
-// | Checksum (8) | RecordCount (4) | Entry_1 name (32) | Entry_1 root_id (4) | ... |
-//  --------------------------------------------------------------------------------
+//  ----------------------------------------------------------------------------------
+// | Checksum (8) | RecordCount (2) | Version (2) | LSN (4) | Generation (4) | ...
+//  ----------------------------------------------------------------------------------
+//  ----------------------
+// | HeaderCrc (4) | ... |
+//  ----------------------
+//  ------------------------------------------------------------------
+// | Entry_1 name (32) | Entry_1 root_id (4) | Entry_1 crc (4) | ... |
+//  ------------------------------------------------------------------
+//
+// |Generation| is bumped on every mutation, and |HeaderCrc| covers the fields
+// before it. The page is not logged, so |LSN| stays invalid; it is where
+// |TablePage| keeps its LSN, so that the page lives in the frames of table
+// pages. Each entry carries a CRC over its name and
+// root_id. Together they make a torn catalog mutation (e.g. a crash in the
+// middle of |delete_record| shifting entries) detectable on reopen; see
+// |validate| and |repair|.
+//
+// The legacy layout, version 0, had a 4-byte record count followed by 36-byte
+// entries without CRCs. Its record count never exceeds 16 bits, so its
+// version always reads as 0. Version 1 had no |LSN|. Pages of these versions
+// must be converted by |upgrade| before use; pages of an unknown version are
+// rejected.
 
 use crate::common::config::PageId;
 use crate::common::config::CHECKSUM_SIZE;
+use crate::common::config::INVALID_LSN;
 use crate::common::config::INVALID_PAGE_ID;
 use crate::common::config::PAGE_SIZE;
+use crate::common::crc32c::crc32c;
 use crate::common::error::*;
 use crate::common::reinterpret;
 use crate::page::page::Page;
+use crate::page::page::PageFrame;
+use crate::page::page_latch::PageLatch;
+use log::warn;
 use std::clone::Clone;
 use std::default::Default;
 
 const DATA_OFFSET: usize = CHECKSUM_SIZE;
+const RECORD_COUNT_OFFSET: usize = CHECKSUM_SIZE;
+const VERSION_OFFSET: usize = CHECKSUM_SIZE + 2;
+const LSN_OFFSET: usize = CHECKSUM_SIZE + 4;
+const GENERATION_OFFSET: usize = CHECKSUM_SIZE + 8;
+const HEADER_CRC_OFFSET: usize = CHECKSUM_SIZE + 12;
+const RECORDS_OFFSET: usize = CHECKSUM_SIZE + 16;
+
+const NAME_SIZE: usize = 32;
+const ROOT_ID_OFFSET: usize = NAME_SIZE;
+const RECORD_CRC_OFFSET: usize = NAME_SIZE + 4;
+const RECORD_SIZE: usize = NAME_SIZE + 8;
+
+const LEGACY_VERSION: u16 = 0;
+const LEGACY_RECORDS_OFFSET: usize = CHECKSUM_SIZE + 4;
+const LEGACY_RECORD_SIZE: usize = NAME_SIZE + 4;
+
+const V1_VERSION: u16 = 1;
+const V1_RECORDS_OFFSET: usize = CHECKSUM_SIZE + 12;
+
+// The version of the layout written by this build.
+pub const HEADER_PAGE_VERSION: u16 = 2;
+
+// The maximum number of records a header page can hold.
+pub const MAX_RECORD_COUNT: usize = (PAGE_SIZE - RECORDS_OFFSET) / RECORD_SIZE;
 
 #[derive(Clone)]
+#[repr(C)]
 pub struct HeaderPage {
     data: [u8; PAGE_SIZE],
     page_id: PageId,
     pin_count: i32,
     is_dirty: bool,
+    latch: PageLatch,
 }
 
 impl HeaderPage {
@@ -33,62 +84,208 @@ impl HeaderPage {
     }
 
     pub fn init(&mut self) {
-        self.set_record_count(0);
+        reinterpret::write_u16(&mut self.data[RECORD_COUNT_OFFSET..], 0);
+        self.set_generation(0);
     }
 
     pub fn insert_record(&mut self, name: &str, root_id: PageId) -> std::io::Result<()> {
+        self.check_version()?;
         Self::validate_name(name)?;
         if self.find_record(name).is_ok() {
             return Err(already_exists(&format!("Record exists; name = {}", name)));
         }
         let count = self.record_count();
-        let offset = 12 + count * 36;
-        reinterpret::write_str(&mut self.data[offset..], name);
-        reinterpret::write_i32(&mut self.data[(offset + 32)..], root_id);
+        if count >= MAX_RECORD_COUNT {
+            return Err(invalid_input("Header page is full"));
+        }
+        self.write_record(count, name, root_id);
         self.set_record_count(count + 1);
         Ok(())
     }
 
     pub fn delete_record(&mut self, name: &str) -> std::io::Result<()> {
+        self.check_version()?;
         Self::validate_name(name)?;
         let idx = self.find_record(name)?;
         let count = self.record_count();
-        let offset = 12 + idx * 36;
-        let n = (count - idx - 1) * 36;
-        unsafe {
-            let ptr = self.data.as_mut_ptr().add(offset);
-            for i in 0..n {
-                *ptr.add(i) = *ptr.add(i + 36);
-            }
-        }
+        let offset = Self::record_offset(idx);
+        let end = Self::record_offset(count);
+        self.data.copy_within((offset + RECORD_SIZE)..end, offset);
+        self.clear_records(count - 1, count);
         self.set_record_count(count - 1);
         Ok(())
     }
 
     pub fn update_record(&mut self, name: &str, root_id: PageId) -> std::io::Result<()> {
+        self.check_version()?;
+        Self::validate_name(name)?;
+        let idx = self.find_record(name)?;
+        self.write_record(idx, name, root_id);
+        self.set_record_count(self.record_count());
+        Ok(())
+    }
+
+    // Renames the record in place. Only the record itself is rewritten, so a
+    // torn rename is detected (see |validate|) like any other mutation.
+    pub fn rename_record(&mut self, name: &str, new_name: &str) -> std::io::Result<()> {
+        self.check_version()?;
         Self::validate_name(name)?;
+        Self::validate_name(new_name)?;
         let idx = self.find_record(name)?;
-        let offset = 12 + idx * 36;
-        reinterpret::write_i32(&mut self.data[(offset + 32)..], root_id);
+        if self.find_record(new_name).is_ok() {
+            return Err(already_exists(&format!("Record exists; name = {}", new_name)));
+        }
+        let root_id = self.root_id(name)?;
+        self.write_record(idx, new_name, root_id);
+        self.set_record_count(self.record_count());
         Ok(())
     }
 
     pub fn root_id(&self, name: &str) -> std::io::Result<i32> {
+        self.check_version()?;
         Self::validate_name(name)?;
         let idx = self.find_record(name)?;
-        let offset = 8 + (idx + 1) * 36;
+        let offset = Self::record_offset(idx) + ROOT_ID_OFFSET;
         let root_id = reinterpret::read_i32(&self.data[offset..]);
         Ok(root_id)
     }
 
     pub fn record_count(&self) -> usize {
-        reinterpret::read_u32(&self.data[8..]) as usize
+        reinterpret::read_u16(&self.data[RECORD_COUNT_OFFSET..]) as usize
+    }
+
+    pub fn version(&self) -> u16 {
+        reinterpret::read_u16(&self.data[VERSION_OFFSET..])
+    }
+
+    // Returns the name, root ID and CRC status of every record, including torn
+    // ones. Meant for inspection tooling, so records of the old layouts are
+    // decoded too.
+    pub fn records(&self) -> Vec<(String, PageId, bool)> {
+        if let LEGACY_VERSION | V1_VERSION = self.version() {
+            let mut upgraded = self.clone();
+            if let Ok(true) = upgraded.upgrade() {
+                return upgraded.records();
+            }
+        }
+        (0..self.record_count().min(MAX_RECORD_COUNT))
+            .map(|idx| {
+                let offset = Self::record_offset(idx);
+                let name = reinterpret::read_str(&self.data[offset..(offset + NAME_SIZE)]);
+                let root_id = reinterpret::read_i32(&self.data[(offset + ROOT_ID_OFFSET)..]);
+                (name.to_string(), root_id, self.is_record_valid(idx))
+            })
+            .collect()
+    }
+
+    // The number of mutations applied to this page.
+    pub fn generation(&self) -> u32 {
+        reinterpret::read_u32(&self.data[GENERATION_OFFSET..])
+    }
+
+    // Checks the header and record CRCs. Returns |InvalidData| if a catalog
+    // mutation was only partially applied.
+    pub fn validate(&self) -> std::io::Result<()> {
+        self.check_version()?;
+        if !self.is_header_valid() {
+            return Err(invalid_data(&format!(
+                "Header page record count is torn; generation = {}",
+                self.generation()
+            )));
+        }
+        for idx in 0..self.record_count() {
+            if !self.is_record_valid(idx) {
+                return Err(invalid_data(&format!(
+                    "Header page record is torn; idx = {}",
+                    idx
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Drops records whose CRC does not match, compacts the remaining records
+    // and rewrites the header. Returns the number of records dropped.
+    pub fn repair(&mut self) -> usize {
+        let count = self.record_count().min(MAX_RECORD_COUNT);
+        let mut kept = 0;
+        for idx in 0..count {
+            if !self.is_record_valid(idx) {
+                let name = reinterpret::read_str(&self.data[Self::record_offset(idx)..]);
+                warn!("Dropping torn header page record; idx = {}, name = {:?}", idx, name);
+                continue;
+            }
+            if kept != idx {
+                let offset = Self::record_offset(idx);
+                self.data
+                    .copy_within(offset..(offset + RECORD_SIZE), Self::record_offset(kept));
+            }
+            kept += 1;
+        }
+        self.clear_records(kept, count);
+        self.set_record_count(kept);
+        count - kept
+    }
+
+    // Rewrites a page of an old layout in the current one. Returns whether the
+    // page was rewritten, and |InvalidData| if its version is unknown or its
+    // records do not fit in the current layout.
+    pub fn upgrade(&mut self) -> std::io::Result<bool> {
+        let (records_offset, record_size) = match self.version() {
+            HEADER_PAGE_VERSION => return Ok(false),
+            LEGACY_VERSION => (LEGACY_RECORDS_OFFSET, LEGACY_RECORD_SIZE),
+            V1_VERSION => (V1_RECORDS_OFFSET, RECORD_SIZE),
+            version => return Err(Self::unsupported_version(version)),
+        };
+        let count = self.record_count();
+        if count > MAX_RECORD_COUNT {
+            return Err(invalid_data(&format!(
+                "Too many old header page records to upgrade; count = {}",
+                count
+            )));
+        }
+        let records: Vec<(String, PageId)> = (0..count)
+            .map(|idx| {
+                let offset = records_offset + idx * record_size;
+                let name = reinterpret::read_str(&self.data[offset..(offset + NAME_SIZE)]);
+                let root_id = reinterpret::read_i32(&self.data[(offset + NAME_SIZE)..]);
+                (name.to_string(), root_id)
+            })
+            .collect();
+        for byte in self.data[RECORD_COUNT_OFFSET..].iter_mut() {
+            *byte = 0;
+        }
+        for (idx, (name, root_id)) in records.iter().enumerate() {
+            self.write_record(idx, name, *root_id);
+        }
+        self.set_record_count(count);
+        Ok(true)
+    }
+
+    // A page is usable if it has the current layout, or if it has no records
+    // yet, e.g. a page never written, which has version 0.
+    fn check_version(&self) -> std::io::Result<()> {
+        match self.version() {
+            HEADER_PAGE_VERSION => Ok(()),
+            LEGACY_VERSION if self.record_count() == 0 => Ok(()),
+            LEGACY_VERSION | V1_VERSION => Err(invalid_data(
+                "Header page has an old layout; upgrade it first",
+            )),
+            version => Err(Self::unsupported_version(version)),
+        }
+    }
+
+    fn unsupported_version(version: u16) -> std::io::Error {
+        invalid_data(&format!(
+            "Unsupported header page version; version = {}",
+            version
+        ))
     }
 
     fn find_record(&self, name: &str) -> std::io::Result<usize> {
         for i in 0..self.record_count() {
-            let offset = 12 + i * 36;
-            let raw_name = reinterpret::read_str(&self.data[offset..]);
+            let offset = Self::record_offset(i);
+            let raw_name = reinterpret::read_str(&self.data[offset..(offset + NAME_SIZE)]);
             if raw_name == name {
                 return Ok(i);
             }
@@ -96,13 +293,62 @@ impl HeaderPage {
         Err(not_found("Record not found"))
     }
 
+    fn write_record(&mut self, idx: usize, name: &str, root_id: PageId) {
+        let offset = Self::record_offset(idx);
+        let record = &mut self.data[offset..(offset + RECORD_SIZE)];
+        for byte in record[..NAME_SIZE].iter_mut() {
+            *byte = 0;
+        }
+        reinterpret::write_str(&mut record[..NAME_SIZE], name);
+        reinterpret::write_i32(&mut record[ROOT_ID_OFFSET..], root_id);
+        let crc = crc32c(&record[..RECORD_CRC_OFFSET]);
+        reinterpret::write_u32(&mut record[RECORD_CRC_OFFSET..], crc);
+    }
+
+    // Zeroes the records in [|from|, |to|) so that no stale copy survives.
+    fn clear_records(&mut self, from: usize, to: usize) {
+        let range = Self::record_offset(from)..Self::record_offset(to);
+        for byte in self.data[range].iter_mut() {
+            *byte = 0;
+        }
+    }
+
+    fn is_record_valid(&self, idx: usize) -> bool {
+        let offset = Self::record_offset(idx);
+        let record = &self.data[offset..(offset + RECORD_SIZE)];
+        reinterpret::read_u32(&record[RECORD_CRC_OFFSET..]) == crc32c(&record[..RECORD_CRC_OFFSET])
+    }
+
+    fn is_header_valid(&self) -> bool {
+        let header = &self.data[RECORD_COUNT_OFFSET..HEADER_CRC_OFFSET];
+        let crc = reinterpret::read_u32(&self.data[HEADER_CRC_OFFSET..]);
+        // A page that has never been written is all zeros.
+        (crc == 0 && header.iter().all(|&byte| byte == 0)) || crc == crc32c(header)
+    }
+
+    // Sets the record count, bumps the generation and reseals the header. This
+    // is the last step of every mutation.
     fn set_record_count(&mut self, record_count: usize) {
-        // Assuming |record_count| fits in u32.
-        reinterpret::write_u32(&mut self.data[8..], record_count as u32);
+        // |record_count| is at most |MAX_RECORD_COUNT|, so it fits in u16.
+        reinterpret::write_u16(&mut self.data[RECORD_COUNT_OFFSET..], record_count as u16);
+        self.set_generation(self.generation().wrapping_add(1));
+    }
+
+    // Sets the generation, stamps the current version and reseals the header.
+    fn set_generation(&mut self, generation: u32) {
+        reinterpret::write_u16(&mut self.data[VERSION_OFFSET..], HEADER_PAGE_VERSION);
+        reinterpret::write_i32(&mut self.data[LSN_OFFSET..], INVALID_LSN);
+        reinterpret::write_u32(&mut self.data[GENERATION_OFFSET..], generation);
+        let crc = crc32c(&self.data[RECORD_COUNT_OFFSET..HEADER_CRC_OFFSET]);
+        reinterpret::write_u32(&mut self.data[HEADER_CRC_OFFSET..], crc);
+    }
+
+    fn record_offset(idx: usize) -> usize {
+        RECORDS_OFFSET + idx * RECORD_SIZE
     }
 
     fn validate_name(name: &str) -> std::io::Result<()> {
-        if name.len() > 32 {
+        if name.len() > NAME_SIZE {
             Err(invalid_input("Name length should be <= 32"))
         } else {
             Ok(())
@@ -117,6 +363,7 @@ impl Default for HeaderPage {
             page_id: INVALID_PAGE_ID,
             pin_count: 0,
             is_dirty: false,
+            latch: PageLatch::new(),
         }
     }
 }
@@ -126,6 +373,7 @@ impl Page for HeaderPage {
         for byte in self.data.iter_mut().skip(DATA_OFFSET) {
             *byte = 0;
         }
+        reinterpret::write_i32(&mut self.data[LSN_OFFSET..], INVALID_LSN);
     }
 
     fn page_id(&self) -> PageId {
@@ -159,8 +407,14 @@ impl Page for HeaderPage {
     fn is_dirty_mut(&mut self) -> &mut bool {
         &mut self.is_dirty
     }
+
+    fn latch(&self) -> &PageLatch {
+        &self.latch
+    }
 }
 
+impl PageFrame for HeaderPage {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -201,5 +455,123 @@ mod tests {
         assert!(header_page.insert_record("Table A", 64).is_ok());
         assert_eq!(64, header_page.root_id("Table A").unwrap());
         assert_eq!(2, header_page.record_count());
+        assert!(header_page.validate().is_ok());
+
+        assert!(header_page.rename_record("Table A", "Table B").is_ok());
+        assert!(header_page.rename_record("Table A", "Table D").is_err());
+        assert!(header_page.rename_record("Table B", "Table C").is_err());
+        assert!(header_page.root_id("Table A").is_err());
+        assert_eq!(64, header_page.root_id("Table B").unwrap());
+        assert_eq!(2, header_page.record_count());
+        assert!(header_page.validate().is_ok());
+    }
+
+    #[test]
+    fn generation_and_capacity() {
+        let mut header_page = HeaderPage::new();
+        assert!(header_page.validate().is_ok());
+        header_page.init();
+        assert_eq!(0, header_page.generation());
+
+        assert!(header_page.insert_record("Table A", 1).is_ok());
+        assert!(header_page.update_record("Table A", 2).is_ok());
+        assert!(header_page.delete_record("Table A").is_ok());
+        assert_eq!(3, header_page.generation());
+
+        // Failed mutations leave the generation untouched.
+        assert!(header_page.delete_record("Table A").is_err());
+        assert_eq!(3, header_page.generation());
+
+        for i in 0..MAX_RECORD_COUNT {
+            assert!(header_page.insert_record(&format!("{}", i), 0).is_ok());
+        }
+        assert!(header_page.insert_record("Overflow", 0).is_err());
+        assert!(header_page.validate().is_ok());
+    }
+
+    #[test]
+    fn detect_and_repair_torn_records() {
+        let mut header_page = HeaderPage::new();
+        header_page.init();
+        assert!(header_page.insert_record("Table A", 1).is_ok());
+        assert!(header_page.insert_record("Table B", 2).is_ok());
+        assert!(header_page.insert_record("Table C", 3).is_ok());
+
+        // Simulate a crash between writing the name and the root_id.
+        let offset = HeaderPage::record_offset(1);
+        reinterpret::write_str(&mut header_page.data_mut()[offset..], "Table D");
+        assert!(header_page.validate().is_err());
+
+        assert_eq!(1, header_page.repair());
+        assert!(header_page.validate().is_ok());
+        assert_eq!(2, header_page.record_count());
+        assert_eq!(1, header_page.root_id("Table A").unwrap());
+        assert!(header_page.root_id("Table D").is_err());
+        assert_eq!(3, header_page.root_id("Table C").unwrap());
+
+        // Simulate a torn record count.
+        reinterpret::write_u16(&mut header_page.data_mut()[RECORD_COUNT_OFFSET..], 3);
+        assert!(header_page.validate().is_err());
+        assert_eq!(1, header_page.repair());
+        assert!(header_page.validate().is_ok());
+        assert_eq!(2, header_page.record_count());
+    }
+
+    #[test]
+    fn versions() {
+        let mut header_page = HeaderPage::new();
+        header_page.init();
+        assert_eq!(HEADER_PAGE_VERSION, header_page.version());
+        assert!(!header_page.upgrade().unwrap());
+
+        // A page of the legacy layout, with 4-byte record count and 36-byte
+        // records.
+        let mut legacy = HeaderPage::new();
+        reinterpret::write_u32(&mut legacy.data_mut()[RECORD_COUNT_OFFSET..], 2);
+        for (idx, (name, root_id)) in [("Table A", 3), ("Table B", 7)].iter().enumerate() {
+            let offset = LEGACY_RECORDS_OFFSET + idx * LEGACY_RECORD_SIZE;
+            reinterpret::write_str(&mut legacy.data_mut()[offset..], name);
+            reinterpret::write_i32(&mut legacy.data_mut()[(offset + NAME_SIZE)..], *root_id);
+        }
+        assert_eq!(LEGACY_VERSION, legacy.version());
+        let error = legacy.root_id("Table A").unwrap_err();
+        assert_eq!(
+            "Header page has an old layout; upgrade it first",
+            error.to_string()
+        );
+        assert!(legacy.validate().is_err());
+        assert!(legacy.insert_record("Table C", 9).is_err());
+        let records = legacy.records();
+        assert_eq!(("Table B".to_string(), 7, true), records[1]);
+
+        assert!(legacy.upgrade().unwrap());
+        assert_eq!(HEADER_PAGE_VERSION, legacy.version());
+        assert!(legacy.validate().is_ok());
+        assert_eq!(2, legacy.record_count());
+        assert_eq!(3, legacy.root_id("Table A").unwrap());
+        assert_eq!(7, legacy.root_id("Table B").unwrap());
+        assert!(legacy.insert_record("Table C", 9).is_ok());
+
+        // A page of version 1, without LSN.
+        let mut v1 = HeaderPage::new();
+        reinterpret::write_u16(&mut v1.data_mut()[RECORD_COUNT_OFFSET..], 1);
+        reinterpret::write_u16(&mut v1.data_mut()[VERSION_OFFSET..], V1_VERSION);
+        reinterpret::write_str(&mut v1.data_mut()[V1_RECORDS_OFFSET..], "Table A");
+        reinterpret::write_i32(&mut v1.data_mut()[(V1_RECORDS_OFFSET + NAME_SIZE)..], 5);
+        assert!(v1.root_id("Table A").is_err());
+        assert!(v1.upgrade().unwrap());
+        assert!(v1.validate().is_ok());
+        assert_eq!(5, v1.root_id("Table A").unwrap());
+        assert_eq!(INVALID_LSN, reinterpret::read_i32(&v1.data()[LSN_OFFSET..]));
+
+        // An unknown version is rejected.
+        reinterpret::write_u16(&mut legacy.data_mut()[VERSION_OFFSET..], 9);
+        let error = legacy.validate().unwrap_err();
+        assert_eq!(
+            "Unsupported header page version; version = 9",
+            error.to_string()
+        );
+        assert!(legacy.upgrade().is_err());
+        assert!(legacy.root_id("Table A").is_err());
     }
 }
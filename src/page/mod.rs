@@ -1,3 +1,13 @@
+#[macro_use]
+pub mod bplus_tree_page;
+
+pub mod bplus_tree_internal_page;
+pub mod bplus_tree_leaf_page;
+pub mod extent_page;
+pub mod hash_table_bucket_page;
+pub mod hash_table_directory_page;
 pub mod header_page;
 pub mod page;
+pub mod page_latch;
+pub mod raw_page;
 pub mod table_page;
@@ -3,12 +3,21 @@
 // Wrapper around actual data page in main memory and also contains bookkeeping
 // information used by buffer pool manager like pin_count/dirty_flag/page_id.
 // Use page as a basic unit within the database system.
+//
+// The pages of a database file share the frames of one buffer pool, whatever
+// their types; see |PageFrame|.
 
+use crate::common::config::Lsn;
 use crate::common::config::PageId;
+use crate::common::config::INVALID_LSN;
 use crate::common::config::PAGE_SIZE;
+use crate::page::page_latch::PageLatch;
 use std::default::Default;
 
 pub trait Page: Default {
+    // Turns the page into an empty page of its type. Only the regions the page
+    // interprets (e.g. its header) need to be cleared; the rest may keep the
+    // bytes of the page previously held by the frame.
     fn reset(&mut self);
     fn page_id(&self) -> PageId;
     fn set_page_id(&mut self, page_id: PageId);
@@ -18,6 +27,16 @@ pub trait Page: Default {
     fn pin_count_mut(&mut self) -> &mut i32;
     fn is_dirty(&self) -> bool;
     fn is_dirty_mut(&mut self) -> &mut bool;
+    // The reader-writer latch of the frame holding the page.
+    fn latch(&self) -> &PageLatch;
+
+    // The LSN of the latest log record describing a change to the page. Pages
+    // without an LSN field (e.g. the header page) are never logged.
+    fn lsn(&self) -> Lsn {
+        INVALID_LSN
+    }
+
+    fn set_lsn(&mut self, _lsn: Lsn) {}
 
     // Pins the page, increment the pin count by 1.
     fn pin(&mut self) {
@@ -39,4 +58,64 @@ pub trait Page: Default {
     fn set_is_dirty(&mut self, is_dirty: bool) {
         *self.is_dirty_mut() = is_dirty;
     }
+
+    // Latches the data of the page in shared mode, blocking while another
+    // thread holds the write latch. Release with |runlatch|.
+    fn rlatch(&self) {
+        self.latch().rlatch();
+    }
+
+    fn runlatch(&self) {
+        self.latch().runlatch();
+    }
+
+    // Latches the data of the page in exclusive mode, blocking while another
+    // thread holds the latch. Release with |wunlatch|.
+    fn wlatch(&self) {
+        self.latch().wlatch();
+    }
+
+    fn wunlatch(&self) {
+        self.latch().wunlatch();
+    }
+}
+
+// Page types laid out alike, so that a frame of one type can be viewed as a
+// page of another in place; see |view| and |BufferPoolManager::fetch_page_as|.
+// Every page type keeps its LSN, if any, at the same offset as |TablePage|,
+// right after the checksum and a 4-byte field, so that the pool holds a
+// dirty page back until its log is durable whatever the type of the frame.
+//
+// Implementors need to be |#[repr(C)]| structs with the fields |data: [u8;
+// PAGE_SIZE]|, |page_id: PageId|, |pin_count: i32|, |is_dirty: bool| and
+// |latch: PageLatch|, in this order. The trait is sealed, so only the page
+// types listed in |sealed| can implement it.
+pub trait PageFrame: Page + sealed::Sealed {}
+
+mod sealed {
+    use crate::page::bplus_tree_page::BPlusTreePage;
+    use crate::page::extent_page::ExtentPage;
+    use crate::page::header_page::HeaderPage;
+    use crate::page::raw_page::RawPage;
+    use crate::page::table_page::TablePage;
+
+    pub trait Sealed {}
+
+    impl Sealed for BPlusTreePage {}
+    impl Sealed for ExtentPage {}
+    impl Sealed for HeaderPage {}
+    impl Sealed for RawPage {}
+    impl Sealed for TablePage {}
+}
+
+// Views the frame |frame| as a page of type |V|. The caller needs to know the
+// type of the page; the bytes are reinterpreted as is.
+pub fn view<V: PageFrame, T: PageFrame>(frame: &T) -> &V {
+    // Safe, since |T| and |V| have the same layout.
+    unsafe { &*(frame as *const T as *const V) }
+}
+
+pub fn view_mut<V: PageFrame, T: PageFrame>(frame: &mut T) -> &mut V {
+    // Safe, since |T| and |V| have the same layout.
+    unsafe { &mut *(frame as *mut T as *mut V) }
 }
@@ -0,0 +1,153 @@
+// Functionality: A reader-writer latch protecting the data of a page frame,
+// see |Page::rlatch| and |Page::wlatch|. Any number of readers, or a single
+// writer, hold the latch at a time.
+//
+// Unlike |std::sync::RwLock|, whose lock is released by dropping its guard,
+// the latch is released explicitly, so that it can be released in another
+// scope than it was taken in, e.g. by the latch crabbing protocol of the B+
+// tree, which releases the latch of a parent once its child is safe. Waiting
+// writers do not block new readers, so writers may starve under a steady
+// stream of readers.
+
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+#[derive(Default)]
+pub struct PageLatch {
+    // The number of readers holding the latch, or |WRITER| if a writer does.
+    state: Mutex<i32>,
+    // Notified whenever the latch becomes free.
+    released: Condvar,
+}
+
+const WRITER: i32 = -1;
+
+impl PageLatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Blocks until the latch is acquired in shared mode.
+    pub fn rlatch(&self) {
+        let mut state = self.state();
+        while *state == WRITER {
+            state = self.released.wait(state).unwrap();
+        }
+        *state += 1;
+    }
+
+    // Acquires the latch in shared mode if no writer holds it.
+    pub fn try_rlatch(&self) -> bool {
+        let mut state = self.state();
+        match *state {
+            WRITER => false,
+            _ => {
+                *state += 1;
+                true
+            }
+        }
+    }
+
+    // The caller needs to hold the latch in shared mode.
+    pub fn runlatch(&self) {
+        let mut state = self.state();
+        debug_assert!(*state > 0, "Latch is not held in shared mode");
+        *state -= 1;
+        if *state == 0 {
+            self.released.notify_all();
+        }
+    }
+
+    // Blocks until the latch is acquired in exclusive mode.
+    pub fn wlatch(&self) {
+        let mut state = self.state();
+        while *state != 0 {
+            state = self.released.wait(state).unwrap();
+        }
+        *state = WRITER;
+    }
+
+    // Acquires the latch in exclusive mode if nobody holds it.
+    pub fn try_wlatch(&self) -> bool {
+        let mut state = self.state();
+        match *state {
+            0 => {
+                *state = WRITER;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // The caller needs to hold the latch in exclusive mode.
+    pub fn wunlatch(&self) {
+        let mut state = self.state();
+        debug_assert!(*state == WRITER, "Latch is not held in exclusive mode");
+        *state = 0;
+        self.released.notify_all();
+    }
+
+    fn state(&self) -> MutexGuard<'_, i32> {
+        self.state.lock().unwrap()
+    }
+}
+
+// A copy of a page is a different frame, so it gets a free latch.
+impl Clone for PageLatch {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn shared_and_exclusive() {
+        let latch = PageLatch::new();
+        latch.rlatch();
+        assert!(latch.try_rlatch());
+        assert!(!latch.try_wlatch());
+        latch.runlatch();
+        latch.runlatch();
+        assert!(latch.try_wlatch());
+        assert!(!latch.try_rlatch());
+        assert!(latch.clone().try_wlatch());
+        latch.wunlatch();
+        assert!(latch.try_rlatch());
+        latch.runlatch();
+    }
+
+    #[test]
+    fn writers_exclude_each_other() {
+        let latch = Arc::new(PageLatch::new());
+        let holders = Arc::new(AtomicUsize::new(0));
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let latch = latch.clone();
+                let holders = holders.clone();
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        latch.wlatch();
+                        assert_eq!(0, holders.fetch_add(1, Ordering::SeqCst));
+                        holders.fetch_sub(1, Ordering::SeqCst);
+                        latch.wunlatch();
+                        latch.rlatch();
+                        assert_eq!(0, holders.load(Ordering::SeqCst));
+                        latch.runlatch();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert!(latch.try_wlatch());
+    }
+}
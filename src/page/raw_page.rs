@@ -0,0 +1,108 @@
+// Functionality: A page frame of uninterpreted bytes. A buffer pool of raw
+// pages holds pages of every type at once; view them as typed pages in place
+// with |BufferPoolManager::fetch_page_as| and |fetch_page_as_mut|, e.g. the
+// header page, table pages and B+ tree pages from the pool of a single file.
+//
+// Like every |PageFrame|, a raw page reads the LSN of the page it holds where
+// all page types keep it, so a pool of raw pages holds back logged pages until
+// the log is durable too.
+
+use crate::common::config::Lsn;
+use crate::common::config::PageId;
+use crate::common::config::CHECKSUM_SIZE;
+use crate::common::config::INVALID_LSN;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::config::PAGE_SIZE;
+use crate::common::reinterpret;
+use crate::page::page::Page;
+use crate::page::page::PageFrame;
+use crate::page::page_latch::PageLatch;
+use std::clone::Clone;
+use std::default::Default;
+
+const LSN_OFFSET: usize = CHECKSUM_SIZE + 4;
+
+#[derive(Clone)]
+#[repr(C)]
+pub struct RawPage {
+    data: [u8; PAGE_SIZE],
+    page_id: PageId,
+    pin_count: i32,
+    is_dirty: bool,
+    latch: PageLatch,
+}
+
+impl RawPage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for RawPage {
+    fn default() -> Self {
+        RawPage {
+            data: [0; PAGE_SIZE],
+            page_id: INVALID_PAGE_ID,
+            pin_count: 0,
+            is_dirty: false,
+            latch: PageLatch::new(),
+        }
+    }
+}
+
+impl Page for RawPage {
+    // A raw page does not know which regions its type interprets, so it clears
+    // all of them.
+    fn reset(&mut self) {
+        for byte in self.data.iter_mut().skip(CHECKSUM_SIZE) {
+            *byte = 0;
+        }
+        self.set_lsn(INVALID_LSN);
+    }
+
+    fn page_id(&self) -> PageId {
+        self.page_id
+    }
+
+    fn set_page_id(&mut self, page_id: PageId) {
+        self.page_id = page_id;
+    }
+
+    fn data(&self) -> &[u8; PAGE_SIZE] {
+        &self.data
+    }
+
+    fn data_mut(&mut self) -> &mut [u8; PAGE_SIZE] {
+        &mut self.data
+    }
+
+    fn pin_count(&self) -> i32 {
+        self.pin_count
+    }
+
+    fn pin_count_mut(&mut self) -> &mut i32 {
+        &mut self.pin_count
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+
+    fn is_dirty_mut(&mut self) -> &mut bool {
+        &mut self.is_dirty
+    }
+
+    fn latch(&self) -> &PageLatch {
+        &self.latch
+    }
+
+    fn lsn(&self) -> Lsn {
+        reinterpret::read_i32(&self.data[LSN_OFFSET..])
+    }
+
+    fn set_lsn(&mut self, lsn: Lsn) {
+        reinterpret::write_i32(&mut self.data[LSN_OFFSET..], lsn);
+    }
+}
+
+impl PageFrame for RawPage {}
@@ -12,30 +12,118 @@
 //  --------------------------------------------------------------
 // | TupleCount (8) | Tuple_1 offset (8) | Tuple_1 size (8) | ... |
 //  --------------------------------------------------------------
+//
+// Tuples are stored serialized (see |Tuple::serialize_to|) and grow from the
+// end of the page towards the slot array. A slot of size 0 is free and may be
+// reused by the next insertion. The highest bit of the size marks a tuple as
+// deleted until the deletion is applied or rolled back.
+//
+// A tuple updated by |update_tuple_delta| is stored as a chain of versions: its
+// slot points to a delta record describing the latest version as changes to
+// the previous one, which is a delta record or, at the end of the chain, the
+// serialized base version. The previous versions have no slots; they are
+// referred to by their offsets and sizes:
+//  ----------------------------------------------------------------------------
+// | PrevOffset (4) | PrevSize (4) | ChainLen (4) | LenPrefix (8) | Run_1 | ... |
+//  ----------------------------------------------------------------------------
+//  ------------------------------------
+// | Run offset (4) | Run length (4) | Bytes |
+//  ------------------------------------
+// |ChainLen| is the number of delta records in the chain, this one included,
+// and |LenPrefix| the length prefix of the serialized version (see
+// |Tuple::serialize_to|). Each run overwrites bytes of the previous version,
+// resized to the new length, at an offset relative to the end of the prefix.
+// Replacing or deleting the tuple frees the whole chain.
+//
+// Overflow page format (size in byte):
+//  -----------------------------------------------------------------------------------------------
+// | Checksum (8) | PageId (4) | LSN (4) | PrevPageId (4) | NextPageId (4) | ChunkSize (8) | Chunk |
+//  -----------------------------------------------------------------------------------------------
+// Instead of slots, an overflow page holds a chunk of a tuple too large for a
+// page. The chunks of one tuple are chained via |next_page_id|; see
+// |Tuple::overflow|.
 
+use crate::common::config::Lsn;
 use crate::common::config::PageId;
 use crate::common::config::CHECKSUM_SIZE;
+use crate::common::config::INVALID_LSN;
 use crate::common::config::INVALID_PAGE_ID;
 use crate::common::config::PAGE_SIZE;
 use crate::common::reinterpret;
 use crate::common::rid::Rid;
 use crate::page::page::Page;
+use crate::page::page::PageFrame;
+use crate::page::page_latch::PageLatch;
 use crate::table::tuple::Tuple;
+use crate::table::tuple::LEN_FLAGS_MASK;
 use std::clone::Clone;
 use std::default::Default;
+use std::fmt;
+use std::mem;
 
 const PAGE_ID_OFFSET: usize = CHECKSUM_SIZE;
+const LSN_OFFSET: usize = CHECKSUM_SIZE + 4;
 const PREV_PAGE_ID_OFFSET: usize = CHECKSUM_SIZE + 8;
 const NEXT_PAGE_ID_OFFSET: usize = CHECKSUM_SIZE + 12;
 const FREE_SPACE_PTR_OFFSET: usize = CHECKSUM_SIZE + 16;
 const TUPLE_COUNT_OFFSET: usize = CHECKSUM_SIZE + 24;
 const DATA_OFFSET: usize = CHECKSUM_SIZE + 32;
+const CHUNK_SIZE_OFFSET: usize = CHECKSUM_SIZE + 16;
+const CHUNK_OFFSET: usize = CHECKSUM_SIZE + 24;
+const SLOT_SIZE: usize = 16;
+const DELETE_MASK: u64 = 1 << 63;
+// Marks the size of a slot pointing to a delta record.
+const DELTA_MASK: u64 = 1 << 62;
+const SLOT_FLAGS_MASK: u64 = DELETE_MASK | DELTA_MASK;
+const PREV_SIZE_OFFSET: usize = 4;
+const CHAIN_LEN_OFFSET: usize = 8;
+const LEN_PREFIX_OFFSET: usize = 12;
+const DELTA_HEADER_SIZE: usize = 20;
+const RUN_HEADER_SIZE: usize = 8;
+
+// The longest chain of delta records of a tuple. The next update rewrites the
+// tuple in full, so that reads apply a bounded number of deltas.
+pub const MAX_DELTA_CHAIN: usize = 4;
+
+// The largest tuple (see |Tuple::len|) that fits into an empty page.
+pub const MAX_TUPLE_SIZE: usize = PAGE_SIZE - DATA_OFFSET - SLOT_SIZE - mem::size_of::<u64>();
+
+// The largest chunk of a tuple an overflow page holds.
+pub const OVERFLOW_CHUNK_SIZE: usize = PAGE_SIZE - CHUNK_OFFSET;
+
+// The number of slots that fit into a page.
+pub const MAX_SLOT_COUNT: usize = (PAGE_SIZE - DATA_OFFSET) / SLOT_SIZE;
+
+// Returned by |TablePage::insert_tuple| when the page cannot hold the tuple.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageFull {
+    // The bytes the tuple needs, including its length prefix and a new slot
+    // if no free slot can be reused.
+    pub needed: usize,
+    // The bytes left on the page; see |TablePage::free_space|.
+    pub available: usize,
+}
+
+impl fmt::Display for PageFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Page is full; needed = {}, available = {}",
+            self.needed, self.available
+        )
+    }
+}
+
+impl std::error::Error for PageFull {}
 
 #[derive(Clone)]
+#[repr(C)]
 pub struct TablePage {
     data: [u8; PAGE_SIZE],
+    page_id: PageId,
     pin_count: i32,
     is_dirty: bool,
+    latch: PageLatch,
 }
 
 impl TablePage {
@@ -43,6 +131,12 @@ impl TablePage {
         Self::default()
     }
 
+    // The page ID stored in the data, i.e. that of the page written to disk,
+    // which is 0 for a page never written; see |set_page_id|.
+    pub fn stored_page_id(&self) -> PageId {
+        reinterpret::read_i32(&self.data[PAGE_ID_OFFSET..])
+    }
+
     pub fn prev_page_id(&self) -> PageId {
         reinterpret::read_i32(&self.data[PREV_PAGE_ID_OFFSET..])
     }
@@ -59,30 +153,400 @@ impl TablePage {
         reinterpret::write_i32(&mut self.data[NEXT_PAGE_ID_OFFSET..], page_id);
     }
 
-    // TODO: Implement this.
-    pub fn insert_tuple(&mut self, tuple: Tuple) -> Option<Rid> {
-        None
+    // Inserts the tuple into the page, reusing a free slot if any. Returns the
+    // RID of the new tuple, or |PageFull| if the page does not have enough
+    // space.
+    pub fn insert_tuple(&mut self, tuple: Tuple) -> Result<Rid, PageFull> {
+        let needed = self.space_needed(tuple.len());
+        if self.free_space() < needed {
+            return Err(PageFull {
+                needed,
+                available: self.free_space(),
+            });
+        }
+        let slot_num = self.free_slot().unwrap_or(self.tuple_count());
+        self.place_tuple(slot_num, tuple);
+        Ok(Rid::new(self.page_id(), slot_num))
+    }
+
+    // Inserts the tuple into the slot of |rid|, which needs to be free or the
+    // next new slot. Recovery uses this to replay insertions and to undo
+    // |apply_delete|. Returns false if the slot is taken, or the page does not
+    // have enough space.
+    pub fn insert_tuple_at(&mut self, rid: &Rid, tuple: Tuple) -> bool {
+        let slot_num = rid.slot_num();
+        let count = self.tuple_count();
+        let slot_overhead = match slot_num == count {
+            true => SLOT_SIZE,
+            false => 0,
+        };
+        let is_free = slot_num == count || (slot_num < count && self.tuple_size(slot_num) == 0);
+        let needed = tuple.len() + mem::size_of::<u64>() + slot_overhead;
+        if rid.page_id() != self.page_id() || !is_free || self.free_space() < needed {
+            return false;
+        }
+        self.place_tuple(slot_num, tuple);
+        true
     }
 
-    // TODO: Implement this.
+    // Marks the tuple as deleted, so that it is invisible until the deletion is
+    // applied or rolled back. Returns false if there is no such tuple.
     pub fn mark_delete(&mut self, rid: &Rid) -> bool {
-        false
+        match self.live_slot(rid) {
+            Some(slot) => {
+                let size = self.raw_tuple_size(slot);
+                self.set_slot(slot, self.tuple_offset(slot), size | DELETE_MASK);
+                true
+            }
+            None => false,
+        }
     }
 
-    // TODO: Implement this.
+    // Replaces the tuple with |tuple| and returns the old one. Returns None if
+    // there is no such tuple, or the page does not have enough space.
     pub fn replace_tuple(&mut self, rid: &Rid, tuple: Tuple) -> Option<Tuple> {
-        None
+        let slot = self.live_slot(rid)?;
+        if self.raw_tuple_size(slot) & DELTA_MASK != 0 {
+            let chain_size: usize = self.versions(slot).iter().map(|&(_, size)| size).sum();
+            if tuple.len() + mem::size_of::<u64>() > self.free_space() + chain_size {
+                return None;
+            }
+            let old_tuple = self.read_tuple(slot);
+            self.free_versions(slot);
+            self.place_tuple(slot, tuple);
+            return Some(old_tuple);
+        }
+        let old_size = self.tuple_size(slot);
+        let new_size = tuple.len() + mem::size_of::<u64>();
+        if new_size > old_size && self.free_space() < new_size - old_size {
+            return None;
+        }
+        let old_tuple = self.read_tuple(slot);
+        // Moves the tuples stored before this one, so that the new tuple ends
+        // where the old one did.
+        let offset = self.tuple_offset(slot);
+        let free_space_ptr = self.free_space_ptr();
+        let new_free_space_ptr = free_space_ptr + old_size - new_size;
+        self.data
+            .copy_within(free_space_ptr..offset, new_free_space_ptr);
+        self.shift_offsets(offset, new_free_space_ptr as i64 - free_space_ptr as i64);
+        let new_offset = offset + old_size - new_size;
+        tuple.serialize_to(&mut self.data[new_offset..]);
+        self.set_free_space_ptr(new_free_space_ptr);
+        self.set_slot(slot, new_offset, new_size as u64);
+        Some(old_tuple)
+    }
+
+    // Same as |replace_tuple|, but stores only the bytes that changed, as a
+    // delta record chained to the current version, which reduces the bytes
+    // written for small changes to wide tuples. Falls back to |replace_tuple|
+    // if the chain is |MAX_DELTA_CHAIN| long, the delta is not smaller than
+    // the tuple, or the page does not have enough space for it.
+    pub fn update_tuple_delta(&mut self, rid: &Rid, tuple: Tuple) -> Option<Tuple> {
+        let slot = self.live_slot(rid)?;
+        let old_tuple = self.read_tuple(slot);
+        let old_bytes = serialize(&old_tuple);
+        let new_bytes = serialize(&tuple);
+        let prefix = mem::size_of::<u64>();
+        let runs = diff(&old_bytes[prefix..], &new_bytes[prefix..]);
+        let size = DELTA_HEADER_SIZE
+            + runs
+                .iter()
+                .map(|(_, bytes)| RUN_HEADER_SIZE + bytes.len())
+                .sum::<usize>();
+        let chain_len = self.chain_len(slot);
+        if chain_len >= MAX_DELTA_CHAIN || size >= new_bytes.len() || size > self.free_space() {
+            return self.replace_tuple(rid, tuple);
+        }
+
+        let prev_offset = self.tuple_offset(slot) as u32;
+        let prev_size = self.tuple_size(slot) as u32;
+        let offset = self.free_space_ptr() - size;
+        let record = &mut self.data[offset..(offset + size)];
+        reinterpret::write_u32(record, prev_offset);
+        reinterpret::write_u32(&mut record[PREV_SIZE_OFFSET..], prev_size);
+        reinterpret::write_u32(&mut record[CHAIN_LEN_OFFSET..], chain_len as u32 + 1);
+        record[LEN_PREFIX_OFFSET..DELTA_HEADER_SIZE].copy_from_slice(&new_bytes[..prefix]);
+        let mut at = DELTA_HEADER_SIZE;
+        for (run_offset, bytes) in runs {
+            reinterpret::write_u32(&mut record[at..], run_offset as u32);
+            reinterpret::write_u32(&mut record[(at + 4)..], bytes.len() as u32);
+            at += RUN_HEADER_SIZE;
+            record[at..(at + bytes.len())].copy_from_slice(bytes);
+            at += bytes.len();
+        }
+        self.set_free_space_ptr(offset);
+        self.set_slot(slot, offset, size as u64 | DELTA_MASK);
+        Some(old_tuple)
     }
 
-    // TODO: Implement this.
-    pub fn apply_delete(&mut self, rid: &Rid) {}
+    // Removes the tuple and compacts the tuple area, freeing its slot. Works
+    // on both live and deleted tuples. Returns the removed tuple, or None if
+    // there is no such tuple.
+    pub fn apply_delete(&mut self, rid: &Rid) -> Option<Tuple> {
+        if !self.has_slot(rid) || self.tuple_size(rid.slot_num()) == 0 {
+            return None;
+        }
+        let slot = rid.slot_num();
+        let tuple = self.read_tuple(slot);
+        self.free_versions(slot);
+        Some(tuple)
+    }
 
-    // TODO: Implement this.
-    pub fn rollback_delete(&mut self, rid: &Rid) {}
+    // Undoes |mark_delete|, making the tuple visible again.
+    pub fn rollback_delete(&mut self, rid: &Rid) {
+        if !self.has_slot(rid) {
+            return;
+        }
+        let slot = rid.slot_num();
+        let size = self.raw_tuple_size(slot);
+        self.set_slot(slot, self.tuple_offset(slot), size & !DELETE_MASK);
+    }
+
+    // Rewrites the live tuples stored as chains of delta records in full,
+    // which frees their previous versions. Returns the number of versions
+    // freed. Tuples marked as deleted keep their chains until the deletion is
+    // applied.
+    pub fn collapse_versions(&mut self) -> usize {
+        let mut freed = 0;
+        for slot in 0..self.tuple_count() {
+            let rid = Rid::new(self.page_id(), slot);
+            let chain_len = self.chain_len(slot);
+            if chain_len == 0 || self.live_slot(&rid).is_none() {
+                continue;
+            }
+            // Rewriting a tuple in full always fits, since it takes no more
+            // bytes than its chain.
+            let tuple = self.read_tuple(slot);
+            if self.replace_tuple(&rid, tuple).is_some() {
+                freed += chain_len;
+            }
+        }
+        freed
+    }
 
-    // TODO: Implement this.
+    // Whether all slots are free, i.e. the page holds no tuple, not even one
+    // marked as deleted.
+    pub fn is_empty(&self) -> bool {
+        (0..self.tuple_count()).all(|slot| self.tuple_size(slot) == 0)
+    }
+
+    // Returns the tuple, or None if there is no such tuple or it is deleted.
     pub fn get_tuple(&self, rid: &Rid) -> Option<Tuple> {
-        None
+        self.live_slot(rid).map(|slot| self.read_tuple(slot))
+    }
+
+    // The number of bytes left for new tuples and slots.
+    pub fn free_space(&self) -> usize {
+        self.free_space_ptr() - DATA_OFFSET - self.tuple_count() * SLOT_SIZE
+    }
+
+    // Whether a tuple of |tuple_len| bytes (see |Tuple::len|) can be inserted,
+    // accounting for its length prefix and the slot it may add.
+    pub fn can_fit(&self, tuple_len: usize) -> bool {
+        self.space_needed(tuple_len) <= self.free_space()
+    }
+
+    pub fn tuple_count(&self) -> usize {
+        reinterpret::read_u64(&self.data[TUPLE_COUNT_OFFSET..]) as usize
+    }
+
+    // Where the tuple area starts; tuples grow downwards from the page end.
+    pub fn free_space_ptr(&self) -> usize {
+        reinterpret::read_u64(&self.data[FREE_SPACE_PTR_OFFSET..]) as usize
+    }
+
+    // Turns the page into an overflow page holding |chunk|, which needs to
+    // have at most |OVERFLOW_CHUNK_SIZE| bytes.
+    pub fn set_overflow_chunk(&mut self, chunk: &[u8]) {
+        reinterpret::write_u64(&mut self.data[CHUNK_SIZE_OFFSET..], chunk.len() as u64);
+        self.data[CHUNK_OFFSET..(CHUNK_OFFSET + chunk.len())].copy_from_slice(chunk);
+    }
+
+    // Returns the chunk of an overflow page; see |set_overflow_chunk|.
+    pub fn overflow_chunk(&self) -> &[u8] {
+        let len = reinterpret::read_u64(&self.data[CHUNK_SIZE_OFFSET..]) as usize;
+        &self.data[CHUNK_OFFSET..(CHUNK_OFFSET + len.min(OVERFLOW_CHUNK_SIZE))]
+    }
+
+    // Returns the tuple offset, the tuple size and the delete mark stored in
+    // |slot_num|, or None if there is no such slot. Meant for inspection
+    // tooling, so the values are returned as stored, even if corrupted.
+    pub fn slot(&self, slot_num: usize) -> Option<(usize, usize, bool)> {
+        if slot_num >= self.tuple_count().min(MAX_SLOT_COUNT) {
+            return None;
+        }
+        let deleted = self.raw_tuple_size(slot_num) & DELETE_MASK != 0;
+        Some((self.tuple_offset(slot_num), self.tuple_size(slot_num), deleted))
+    }
+
+    // The number of delta records in the chain of the tuple in |slot|.
+    fn chain_len(&self, slot: usize) -> usize {
+        match self.raw_tuple_size(slot) & DELTA_MASK {
+            0 => 0,
+            _ => reinterpret::read_u32(&self.data[(self.tuple_offset(slot) + CHAIN_LEN_OFFSET)..])
+                as usize,
+        }
+    }
+
+    // Returns the offsets and sizes of the versions of the tuple in |slot|,
+    // from the latest one to the base version.
+    fn versions(&self, slot: usize) -> Vec<(usize, usize)> {
+        let mut versions = vec![(self.tuple_offset(slot), self.tuple_size(slot))];
+        let mut chain_len = self.chain_len(slot);
+        while chain_len > 0 && versions.len() <= MAX_DELTA_CHAIN {
+            let (offset, _) = versions[versions.len() - 1];
+            let prev_offset = reinterpret::read_u32(&self.data[offset..]) as usize;
+            let prev_size = reinterpret::read_u32(&self.data[(offset + PREV_SIZE_OFFSET)..]);
+            versions.push((prev_offset, prev_size as usize));
+            chain_len -= 1;
+        }
+        versions
+    }
+
+    // Removes all versions of the tuple in |slot| and frees the slot.
+    fn free_versions(&mut self, slot: usize) {
+        let mut versions = self.versions(slot);
+        self.set_slot(slot, 0, 0);
+        // Removing a version moves the ones stored before it, so they are
+        // removed from the lowest offset up.
+        versions.sort_unstable();
+        for (offset, size) in versions {
+            self.remove_bytes(offset, size);
+        }
+    }
+
+    // Removes |size| bytes at |offset| from the tuple area and compacts it.
+    fn remove_bytes(&mut self, offset: usize, size: usize) {
+        let free_space_ptr = self.free_space_ptr();
+        self.data
+            .copy_within(free_space_ptr..offset, free_space_ptr + size);
+        for byte in self.data[free_space_ptr..(free_space_ptr + size)].iter_mut() {
+            *byte = 0;
+        }
+        self.shift_offsets(offset, size as i64);
+        self.set_free_space_ptr(free_space_ptr + size);
+    }
+
+    // The bytes an insertion of |tuple_len| bytes takes from |free_space|.
+    fn space_needed(&self, tuple_len: usize) -> usize {
+        let slot_overhead = match self.free_slot() {
+            Some(_) => 0,
+            None => SLOT_SIZE,
+        };
+        tuple_len + mem::size_of::<u64>() + slot_overhead
+    }
+
+    // Returns the first slot freed by |apply_delete|, if any.
+    fn free_slot(&self) -> Option<usize> {
+        (0..self.tuple_count()).find(|&slot| self.tuple_size(slot) == 0)
+    }
+
+    // Stores the tuple in front of the tuple area and points |slot_num| to it.
+    // The caller needs to ensure that the slot is free and the page has space.
+    fn place_tuple(&mut self, slot_num: usize, tuple: Tuple) {
+        let size = tuple.len() + mem::size_of::<u64>();
+        let count = self.tuple_count();
+        let offset = self.free_space_ptr() - size;
+        tuple.serialize_to(&mut self.data[offset..]);
+        self.set_free_space_ptr(offset);
+        if slot_num == count {
+            self.set_tuple_count(count + 1);
+        }
+        self.set_slot(slot_num, offset, size as u64);
+    }
+
+    fn has_slot(&self, rid: &Rid) -> bool {
+        rid.page_id() == self.page_id() && rid.slot_num() < self.tuple_count()
+    }
+
+    // Returns the slot of |rid| iff it holds a tuple not marked as deleted.
+    fn live_slot(&self, rid: &Rid) -> Option<usize> {
+        if !self.has_slot(rid) {
+            return None;
+        }
+        let size = self.raw_tuple_size(rid.slot_num());
+        match size == 0 || size & DELETE_MASK != 0 {
+            true => None,
+            false => Some(rid.slot_num()),
+        }
+    }
+
+    // Reads the tuple in |slot|, applying the delta records of its chain to
+    // the base version.
+    fn read_tuple(&self, slot: usize) -> Tuple {
+        let mut versions = self.versions(slot);
+        // It is safe to unwrap here, because there is at least one version.
+        let (offset, _) = versions.pop().unwrap();
+        let mut tuple = Tuple::default();
+        tuple.deserialize_from(&self.data[offset..]);
+        if versions.is_empty() {
+            return tuple;
+        }
+        let prefix = mem::size_of::<u64>();
+        let mut bytes = serialize(&tuple);
+        for &(offset, size) in versions.iter().rev() {
+            let record = &self.data[offset..(offset + size)];
+            let len_prefix = reinterpret::read_u64(&record[LEN_PREFIX_OFFSET..]);
+            bytes.resize(prefix + (len_prefix & !LEN_FLAGS_MASK) as usize, 0);
+            reinterpret::write_u64(&mut bytes, len_prefix);
+            let mut at = DELTA_HEADER_SIZE;
+            while at < size {
+                let run_offset = prefix + reinterpret::read_u32(&record[at..]) as usize;
+                let run_len = reinterpret::read_u32(&record[(at + 4)..]) as usize;
+                at += RUN_HEADER_SIZE;
+                bytes[run_offset..(run_offset + run_len)]
+                    .copy_from_slice(&record[at..(at + run_len)]);
+                at += run_len;
+            }
+        }
+        tuple.deserialize_from(&bytes);
+        tuple
+    }
+
+    // Adds |delta| to the offsets of all tuples and previous versions stored
+    // before |offset|.
+    fn shift_offsets(&mut self, offset: usize, delta: i64) {
+        let shift = |at: usize| match at < offset {
+            true => (at as i64 + delta) as usize,
+            false => at,
+        };
+        for slot in 0..self.tuple_count() {
+            let slot_offset = self.tuple_offset(slot);
+            if self.tuple_size(slot) != 0 && slot_offset < offset {
+                let size = self.raw_tuple_size(slot);
+                self.set_slot(slot, shift(slot_offset), size);
+            }
+        }
+        // The delta records have moved already, so their links are followed
+        // from the shifted offsets.
+        for slot in 0..self.tuple_count() {
+            let mut record = self.tuple_offset(slot);
+            for _ in 0..self.chain_len(slot).min(MAX_DELTA_CHAIN) {
+                let prev_offset = shift(reinterpret::read_u32(&self.data[record..]) as usize);
+                reinterpret::write_u32(&mut self.data[record..], prev_offset as u32);
+                record = prev_offset;
+            }
+        }
+    }
+
+    fn tuple_offset(&self, slot: usize) -> usize {
+        reinterpret::read_u64(&self.data[(DATA_OFFSET + slot * SLOT_SIZE)..]) as usize
+    }
+
+    // The size of the tuple, including the delete mark and the delta mark.
+    fn raw_tuple_size(&self, slot: usize) -> u64 {
+        reinterpret::read_u64(&self.data[(DATA_OFFSET + slot * SLOT_SIZE + 8)..])
+    }
+
+    fn tuple_size(&self, slot: usize) -> usize {
+        (self.raw_tuple_size(slot) & !SLOT_FLAGS_MASK) as usize
+    }
+
+    fn set_slot(&mut self, slot: usize, offset: usize, size: u64) {
+        let slot_offset = DATA_OFFSET + slot * SLOT_SIZE;
+        reinterpret::write_u64(&mut self.data[slot_offset..], offset as u64);
+        reinterpret::write_u64(&mut self.data[(slot_offset + 8)..], size);
     }
 
     fn set_free_space_ptr(&mut self, ptr: usize) {
@@ -94,13 +558,42 @@ impl TablePage {
     }
 }
 
+// Returns |tuple| serialized, length prefix included.
+fn serialize(tuple: &Tuple) -> Vec<u8> {
+    let mut bytes = vec![0; tuple.len() + mem::size_of::<u64>()];
+    tuple.serialize_to(&mut bytes);
+    bytes
+}
+
+// Returns the runs of bytes of |new| differing from |old|, with their offsets.
+// Runs less than a run header apart are merged, since the header would cost
+// more than the bytes in between.
+fn diff<'a>(old: &[u8], new: &'a [u8]) -> Vec<(usize, &'a [u8])> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for idx in 0..new.len() {
+        if idx < old.len() && old[idx] == new[idx] {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((_, end)) if idx - *end < RUN_HEADER_SIZE => *end = idx + 1,
+            _ => runs.push((idx, idx + 1)),
+        }
+    }
+    runs.into_iter()
+        .map(|(start, end)| (start, &new[start..end]))
+        .collect()
+}
+
 impl Default for TablePage {
     fn default() -> Self {
         let mut page = TablePage {
             data: [0 as u8; PAGE_SIZE],
+            page_id: INVALID_PAGE_ID,
             pin_count: 0,
             is_dirty: false,
+            latch: PageLatch::new(),
         };
+        page.reset();
         page.set_page_id(INVALID_PAGE_ID);
         page
     }
@@ -108,20 +601,23 @@ impl Default for TablePage {
 
 impl Page for TablePage {
     fn reset(&mut self) {
+        self.set_page_id(self.page_id);
+        self.set_lsn(INVALID_LSN);
         self.set_prev_page_id(INVALID_PAGE_ID);
         self.set_next_page_id(INVALID_PAGE_ID);
         self.set_free_space_ptr(PAGE_SIZE);
         self.set_tuple_count(0);
-        for byte in self.data.iter_mut().skip(DATA_OFFSET) {
-            *byte = 0;
-        }
     }
 
     fn page_id(&self) -> PageId {
-        reinterpret::read_i32(&self.data[PAGE_ID_OFFSET..])
+        self.page_id
     }
 
+    // Also stores the page ID in the data, like |reset|. The buffer pool sets
+    // it before the page is loaded or reset, so that it never overwrites the
+    // data of a page of another type held by the frame.
     fn set_page_id(&mut self, page_id: PageId) {
+        self.page_id = page_id;
         reinterpret::write_i32(&mut self.data[PAGE_ID_OFFSET..], page_id);
     }
 
@@ -148,7 +644,211 @@ impl Page for TablePage {
     fn is_dirty_mut(&mut self) -> &mut bool {
         &mut self.is_dirty
     }
+
+    fn latch(&self) -> &PageLatch {
+        &self.latch
+    }
+
+    fn lsn(&self) -> Lsn {
+        reinterpret::read_i32(&self.data[LSN_OFFSET..])
+    }
+
+    fn set_lsn(&mut self, lsn: Lsn) {
+        reinterpret::write_i32(&mut self.data[LSN_OFFSET..], lsn);
+    }
 }
 
+impl PageFrame for TablePage {}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    fn create_tuple(content: &str) -> Tuple {
+        let mut buffer = vec![0; content.len() + mem::size_of::<u64>()];
+        reinterpret::write_u64(&mut buffer, content.len() as u64);
+        buffer[mem::size_of::<u64>()..].copy_from_slice(content.as_bytes());
+        let mut tuple = Tuple::default();
+        tuple.deserialize_from(&buffer);
+        tuple
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let mut page = TablePage::new();
+        page.set_page_id(3);
+        let rid1 = page.insert_tuple(create_tuple("hello")).unwrap();
+        let rid2 = page.insert_tuple(create_tuple("world!")).unwrap();
+        assert_eq!(Rid::new(3, 0), rid1);
+        assert_eq!(Rid::new(3, 1), rid2);
+        assert_eq!(Some(create_tuple("hello")), page.get_tuple(&rid1));
+        assert_eq!(Some(create_tuple("world!")), page.get_tuple(&rid2));
+        assert_eq!(None, page.get_tuple(&Rid::new(3, 2)));
+        assert_eq!(None, page.get_tuple(&Rid::new(4, 0)));
+
+        // Fill the page up.
+        let big = "x".repeat(1000);
+        let mut count = 0;
+        while page.insert_tuple(create_tuple(&big)).is_ok() {
+            count += 1;
+        }
+        assert_eq!(3, count);
+        assert!(page.free_space() < 1000 + 8 + SLOT_SIZE);
+        assert!(!page.can_fit(1000));
+        assert_eq!(
+            Err(PageFull {
+                needed: 1000 + 8 + SLOT_SIZE,
+                available: page.free_space(),
+            }),
+            page.insert_tuple(create_tuple(&big))
+        );
+
+        // The remaining space fits a tuple exactly, slot included.
+        let len = page.free_space() - 8 - SLOT_SIZE;
+        assert!(page.can_fit(len));
+        assert!(!page.can_fit(len + 1));
+        assert!(page.insert_tuple(create_tuple(&"y".repeat(len))).is_ok());
+        assert_eq!(0, page.free_space());
+    }
+
+    #[test]
+    fn delete_and_rollback() {
+        let mut page = TablePage::new();
+        page.set_page_id(3);
+        assert!(page.is_empty());
+        let rid1 = page.insert_tuple(create_tuple("hello")).unwrap();
+        let rid2 = page.insert_tuple(create_tuple("world!")).unwrap();
+        let free_space = page.free_space();
+
+        assert!(page.mark_delete(&rid1));
+        assert!(!page.mark_delete(&rid1));
+        assert_eq!(None, page.get_tuple(&rid1));
+        page.rollback_delete(&rid1);
+        assert_eq!(Some(create_tuple("hello")), page.get_tuple(&rid1));
+
+        // Applying the deletion compacts the page and frees the slot.
+        assert!(page.mark_delete(&rid1));
+        assert_eq!(Some(create_tuple("hello")), page.apply_delete(&rid1));
+        assert_eq!(None, page.apply_delete(&rid1));
+        assert_eq!(free_space + 13, page.free_space());
+        assert_eq!(Some(create_tuple("world!")), page.get_tuple(&rid2));
+
+        // The freed slot can be refilled in place, e.g. to undo the deletion.
+        assert!(!page.insert_tuple_at(&rid2, create_tuple("taken")));
+        assert!(!page.insert_tuple_at(&Rid::new(3, 3), create_tuple("skipped")));
+        assert!(page.insert_tuple_at(&rid1, create_tuple("hello")));
+        assert_eq!(Some(create_tuple("hello")), page.get_tuple(&rid1));
+        assert_eq!(free_space, page.free_space());
+        page.apply_delete(&rid1);
+
+        let rid3 = page.insert_tuple(create_tuple("again")).unwrap();
+        assert_eq!(rid1, rid3);
+        assert_eq!(2, page.tuple_count());
+        assert_eq!(Some(create_tuple("again")), page.get_tuple(&rid3));
+        assert_eq!(Some(create_tuple("world!")), page.get_tuple(&rid2));
+
+        // Tuples marked as deleted still take their slots.
+        assert!(page.mark_delete(&rid2));
+        page.apply_delete(&rid3);
+        assert!(!page.is_empty());
+        page.apply_delete(&rid2);
+        assert!(page.is_empty());
+    }
+
+    #[test]
+    fn replace() {
+        let mut page = TablePage::new();
+        page.set_page_id(3);
+        let rid1 = page.insert_tuple(create_tuple("hello")).unwrap();
+        let rid2 = page.insert_tuple(create_tuple("world!")).unwrap();
+        let rid3 = page.insert_tuple(create_tuple("foo")).unwrap();
+
+        let old = page.replace_tuple(&rid2, create_tuple("a much longer tuple"));
+        assert_eq!(Some(create_tuple("world!")), old);
+        let old = page.replace_tuple(&rid1, create_tuple("hi"));
+        assert_eq!(Some(create_tuple("hello")), old);
+        assert_eq!(Some(create_tuple("hi")), page.get_tuple(&rid1));
+        assert_eq!(
+            Some(create_tuple("a much longer tuple")),
+            page.get_tuple(&rid2)
+        );
+        assert_eq!(Some(create_tuple("foo")), page.get_tuple(&rid3));
+
+        // Too large to fit.
+        let huge = "x".repeat(PAGE_SIZE);
+        assert_eq!(None, page.replace_tuple(&rid3, create_tuple(&huge)));
+        assert!(page.mark_delete(&rid3));
+        assert_eq!(None, page.replace_tuple(&rid3, create_tuple("bar")));
+    }
+
+    #[test]
+    fn delta_updates() {
+        let mut page = TablePage::new();
+        page.set_page_id(3);
+        let wide = "a wide tuple, of which updates change a few bytes";
+        let rid1 = page.insert_tuple(create_tuple(wide)).unwrap();
+        let rid2 = page.insert_tuple(create_tuple(wide)).unwrap();
+        let rid3 = page.insert_tuple(create_tuple("foo")).unwrap();
+        let free_space = page.free_space();
+
+        // Each update takes the bytes of its delta record only.
+        let versions = [
+            "a WIDE tuple, of which updates change a few bytes",
+            "a WIDE tuple, of which updates change a few",
+            "a WIDE tuple, of which updates change a few bytes!",
+            "a wide tuple, of which updates change a few bytes",
+        ];
+        let mut previous = wide;
+        for (idx, &version) in versions.iter().enumerate() {
+            let old = page.update_tuple_delta(&rid2, create_tuple(version));
+            assert_eq!(Some(create_tuple(previous)), old);
+            assert_eq!(Some(create_tuple(version)), page.get_tuple(&rid2));
+            assert_eq!(idx + 1, page.chain_len(rid2.slot_num()));
+            previous = version;
+        }
+        assert!(free_space - page.free_space() < 4 * (DELTA_HEADER_SIZE + 2 * RUN_HEADER_SIZE));
+        assert_eq!(Some(create_tuple(wide)), page.get_tuple(&rid1));
+        assert_eq!(Some(create_tuple("foo")), page.get_tuple(&rid3));
+
+        // Compacting the page keeps the chain intact.
+        assert!(page.mark_delete(&rid1));
+        assert_eq!(Some(create_tuple(wide)), page.apply_delete(&rid1));
+        assert_eq!(Some(create_tuple(wide)), page.get_tuple(&rid2));
+        assert!(page.mark_delete(&rid2));
+        page.rollback_delete(&rid2);
+        assert_eq!(Some(create_tuple(wide)), page.get_tuple(&rid2));
+
+        // The chain is full, so the next update rewrites the tuple in full,
+        // which frees the chain.
+        let old = page.update_tuple_delta(&rid2, create_tuple("bar"));
+        assert_eq!(Some(create_tuple(wide)), old);
+        assert_eq!(0, page.chain_len(rid2.slot_num()));
+        assert_eq!(Some(create_tuple("bar")), page.get_tuple(&rid2));
+        assert_eq!(free_space + 2 * wide.len() + 8 - 3, page.free_space());
+
+        // Deltas of small tuples are not smaller than the tuples.
+        assert!(page.update_tuple_delta(&rid3, create_tuple("fox")).is_some());
+        assert_eq!(0, page.chain_len(rid3.slot_num()));
+
+        // Deleting a tuple frees its chain too.
+        let free_space = page.free_space();
+        let rid4 = page.insert_tuple(create_tuple(wide)).unwrap();
+        assert!(page.update_tuple_delta(&rid4, create_tuple(versions[0])).is_some());
+        assert_eq!(1, page.chain_len(rid4.slot_num()));
+        assert_eq!(Some(create_tuple(versions[0])), page.apply_delete(&rid4));
+        assert_eq!(Some(create_tuple("bar")), page.get_tuple(&rid2));
+        assert_eq!(Some(create_tuple("fox")), page.get_tuple(&rid3));
+        assert_eq!(free_space, page.free_space());
+
+        // Collapsing the chains frees the previous versions.
+        let rid4 = page.insert_tuple(create_tuple(wide)).unwrap();
+        let free_space = page.free_space();
+        assert!(page.update_tuple_delta(&rid4, create_tuple(versions[0])).is_some());
+        assert!(page.update_tuple_delta(&rid4, create_tuple(versions[3])).is_some());
+        assert_eq!(2, page.collapse_versions());
+        assert_eq!(0, page.chain_len(rid4.slot_num()));
+        assert_eq!(Some(create_tuple(versions[3])), page.get_tuple(&rid4));
+        assert_eq!(free_space, page.free_space());
+        assert_eq!(0, page.collapse_versions());
+    }
+}
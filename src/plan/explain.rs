@@ -0,0 +1,430 @@
+// Functionality: Renders plans as indented trees, one node per line with its
+// children below it, e.g. for an EXPLAIN statement:
+//
+//   Projection: Name
+//     Filter: (Id < 10)
+//       SeqScan: users
+//
+// |explain| renders a plan as it was built, then as the optimizer rewrote it,
+// which shows whether an index scan was chosen and where filters run.
+//
+// Once the tables a node reads are analyzed, the node ends with the number of
+// tuples it is estimated to produce, e.g. "SeqScan: users (rows = 1000)":
+// the row counts of the tables, scaled by the selectivities of the filters
+// and joins; see |selectivity|. Inserts, deletes and updates have none.
+
+use crate::catalog::catalog::Catalog;
+use crate::catalog::catalog::Oid;
+use crate::catalog::schema::Schema;
+use crate::plan::expression::Expression;
+use crate::plan::optimizer::Optimizer;
+use crate::plan::plan_node::conflict_schema;
+use crate::plan::plan_node::AggregateFunction;
+use crate::plan::plan_node::JoinType;
+use crate::plan::plan_node::OrderDirection;
+use crate::plan::plan_node::PlanNode;
+use crate::plan::selectivity::join_selectivity;
+use crate::plan::selectivity::selectivity;
+
+pub fn explain(catalog: &Catalog, plan: &PlanNode) -> String {
+    let optimized = Optimizer::new(catalog).optimize(plan.clone());
+    format!(
+        "Logical plan:\n{}Physical plan:\n{}",
+        render(catalog, plan),
+        render(catalog, &optimized)
+    )
+}
+
+// Renders |plan| as an indented tree, ending with a newline.
+pub fn render(catalog: &Catalog, plan: &PlanNode) -> String {
+    let mut s = String::new();
+    render_node(catalog, plan, 0, &mut s);
+    s
+}
+
+fn render_node(catalog: &Catalog, plan: &PlanNode, depth: usize, s: &mut String) {
+    s.push_str(&"  ".repeat(depth));
+    s.push_str(&describe(catalog, plan));
+    if let Some(rows) = estimate_rows(catalog, plan) {
+        s.push_str(&format!(" (rows = {})", rows.round()));
+    }
+    s.push('\n');
+    for child in plan.children() {
+        render_node(catalog, child, depth + 1, s);
+    }
+}
+
+// Describes |plan| without its children.
+fn describe(catalog: &Catalog, plan: &PlanNode) -> String {
+    let list = |expressions: Vec<String>| expressions.join(", ");
+    match plan {
+        PlanNode::SeqScan { table_oid, .. } => format!("SeqScan: {}", table(catalog, *table_oid)),
+        PlanNode::IndexScan {
+            table_oid,
+            index_name,
+            key,
+            ..
+        } => format!(
+            "IndexScan: {} using {}, key = {}",
+            table(catalog, *table_oid),
+            index_name,
+            key
+        ),
+        PlanNode::Values { rows, .. } => format!("Values: {} rows", rows.len()),
+        PlanNode::Filter { predicate, child } => {
+            format!("Filter: {}", predicate.to_string(&child.output_schema()))
+        }
+        PlanNode::Projection {
+            expressions,
+            schema,
+            child,
+        } => {
+            let input = child.output_schema();
+            let columns = expressions
+                .iter()
+                .zip(schema.columns().iter())
+                .map(|(expression, column)| {
+                    let expression = expression.to_string(&input);
+                    match expression == column.name() {
+                        true => expression,
+                        false => format!("{} AS {}", expression, column.name()),
+                    }
+                })
+                .collect();
+            format!("Projection: {}", list(columns))
+        }
+        PlanNode::Aggregate {
+            aggregates,
+            schema,
+            child,
+        } => {
+            let input = child.output_schema();
+            let columns = aggregates
+                .iter()
+                .zip(schema.columns().iter())
+                .map(|((function, expression), column)| {
+                    let function = match function {
+                        AggregateFunction::ApproxCountDistinct => "APPROX_COUNT_DISTINCT",
+                    };
+                    let expression = expression.to_string(&input);
+                    format!("{}({}) AS {}", function, expression, column.name())
+                })
+                .collect();
+            format!("Aggregate: {}", list(columns))
+        }
+        PlanNode::Sort { order_bys, child } => {
+            format!("Sort: {}", order_by(order_bys, &child.output_schema()))
+        }
+        PlanNode::Limit { limit, .. } => format!("Limit: {}", limit),
+        PlanNode::TopN {
+            order_bys,
+            limit,
+            child,
+        } => format!(
+            "TopN: {} by {}",
+            limit,
+            order_by(order_bys, &child.output_schema())
+        ),
+        PlanNode::HashJoin {
+            left_key,
+            right_key,
+            left,
+            right,
+            ..
+        } => format!(
+            "HashJoin: {} = {}",
+            left_key.to_string(&left.output_schema()),
+            right_key.to_string(&right.output_schema())
+        ),
+        PlanNode::NestedLoopJoin {
+            join_type,
+            predicate,
+            schema,
+            ..
+        } => format!(
+            "NestedLoopJoin ({:?}): {}",
+            join_type,
+            predicate.to_string(schema)
+        ),
+        PlanNode::IndexJoin {
+            join_type,
+            left_key,
+            index_name,
+            table_oid,
+            left,
+            ..
+        } => format!(
+            "IndexJoin ({:?}): {} using {}, key = {}",
+            join_type,
+            table(catalog, *table_oid),
+            index_name,
+            left_key.to_string(&left.output_schema())
+        ),
+        PlanNode::Insert {
+            table_oid,
+            on_conflict,
+            ..
+        } => match (on_conflict, catalog.table_by_oid(*table_oid)) {
+            (Some(on_conflict), Some(table)) => {
+                let schema = table.schema();
+                let input = conflict_schema(schema);
+                let assignments = on_conflict
+                    .assignments
+                    .iter()
+                    .map(|(idx, expression)| {
+                        format!(
+                            "{} = {}",
+                            schema.nth_column(*idx).unwrap().name(),
+                            expression.to_string(&input)
+                        )
+                    })
+                    .collect();
+                format!(
+                    "Insert: {} on conflict {} set {}",
+                    table.name(),
+                    on_conflict.index_name,
+                    list(assignments)
+                )
+            }
+            _ => format!("Insert: {}", table(catalog, *table_oid)),
+        },
+        PlanNode::Delete { table_oid, .. } => format!("Delete: {}", table(catalog, *table_oid)),
+        PlanNode::Update {
+            table_oid,
+            assignments,
+            ..
+        } => {
+            let schema = catalog.table_by_oid(*table_oid).map(|table| table.schema());
+            let assignments = assignments
+                .iter()
+                .map(|(idx, expression)| match schema {
+                    Some(schema) => format!(
+                        "{} = {}",
+                        schema.nth_column(*idx).unwrap().name(),
+                        expression.to_string(schema)
+                    ),
+                    None => format!("#{} = {}", idx, expression.to_string(&Schema::new(vec![]))),
+                })
+                .collect();
+            format!(
+                "Update: {} set {}",
+                table(catalog, *table_oid),
+                list(assignments)
+            )
+        }
+    }
+}
+
+// Estimates the number of tuples |plan| produces, if every table it reads was
+// analyzed.
+fn estimate_rows(catalog: &Catalog, plan: &PlanNode) -> Option<f64> {
+    let rows = |plan: &PlanNode| estimate_rows(catalog, plan);
+    // The equality selectivity of a key of the table |plan| reads.
+    let key = |plan: &PlanNode, key: &Expression| match key {
+        Expression::Column(idx) => catalog
+            .statistics(plan.source_table()?)?
+            .equality_selectivity(*idx),
+        _ => None,
+    };
+    match plan {
+        PlanNode::SeqScan { table_oid, .. } => {
+            Some(catalog.statistics(*table_oid)?.row_count() as f64)
+        }
+        PlanNode::IndexScan { table_oid, .. } => {
+            let rows = catalog.statistics(*table_oid)?.row_count() as f64;
+            Some(rows.min(1.0))
+        }
+        PlanNode::Values { rows, .. } => Some(rows.len() as f64),
+        PlanNode::Filter { predicate, child } => {
+            let statistics = child
+                .source_table()
+                .and_then(|table_oid| catalog.statistics(table_oid));
+            Some(rows(child)? * selectivity(statistics, predicate))
+        }
+        PlanNode::Projection { child, .. } | PlanNode::Sort { child, .. } => rows(child),
+        PlanNode::Aggregate { child, .. } => rows(child).map(|_| 1.0),
+        PlanNode::Limit { limit, child } | PlanNode::TopN { limit, child, .. } => {
+            Some(rows(child)?.min(*limit as f64))
+        }
+        PlanNode::HashJoin {
+            left_key,
+            right_key,
+            left,
+            right,
+            ..
+        } => {
+            let selectivity = join_selectivity(key(left, left_key), key(right, right_key));
+            Some(rows(left)? * rows(right)? * selectivity)
+        }
+        PlanNode::NestedLoopJoin {
+            join_type,
+            predicate,
+            left,
+            right,
+            ..
+        } => {
+            let left = rows(left)?;
+            let joined = left * rows(right)? * selectivity(None, predicate);
+            Some(match join_type {
+                JoinType::Inner => joined,
+                JoinType::LeftOuter => joined.max(left),
+            })
+        }
+        // Every left tuple matches at most one tuple of the table.
+        PlanNode::IndexJoin {
+            join_type,
+            table_oid,
+            left,
+            ..
+        } => {
+            let left = rows(left)?;
+            let table = catalog.statistics(*table_oid)?.row_count() as f64;
+            Some(match join_type {
+                JoinType::Inner => left.min(table),
+                JoinType::LeftOuter => left,
+            })
+        }
+        PlanNode::Insert { .. } | PlanNode::Delete { .. } | PlanNode::Update { .. } => None,
+    }
+}
+
+fn table(catalog: &Catalog, table_oid: Oid) -> String {
+    match catalog.table_by_oid(table_oid) {
+        Some(table) => table.name().to_string(),
+        None => format!("<oid {}>", table_oid),
+    }
+}
+
+fn order_by(order_bys: &[(Expression, OrderDirection)], schema: &Schema) -> String {
+    order_bys
+        .iter()
+        .map(|(expression, direction)| {
+            let direction = match direction {
+                OrderDirection::Asc => "ASC",
+                OrderDirection::Desc => "DESC",
+            };
+            format!("{} {}", expression.to_string(schema), direction)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::catalog::column::Column;
+    use crate::page::table_page::TablePage;
+    use crate::plan::expression::ComparisonOp;
+    use crate::table::tuple::Tuple;
+    use crate::types::types::Types;
+    use crate::types::value::Value;
+
+    #[test]
+    fn explain_plans() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(4);
+        let mut catalog = Catalog::create(&mut bpm).unwrap();
+        let schema = Schema::new(vec![
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("Name".to_string(), Types::owned(), 10),
+        ]);
+        catalog.create_table(&mut bpm, 1, "users", schema).unwrap();
+        catalog
+            .create_index(&mut bpm, 1, "users_id", "users", "Id")
+            .unwrap();
+
+        // SELECT u.Name FROM users u JOIN users v ON u.Id < v.Id
+        // WHERE u.Id = 3 ORDER BY v.Name DESC LIMIT 2;
+        let scan = PlanNode::seq_scan(&catalog, "users").unwrap();
+        let less = Expression::Comparison(
+            ComparisonOp::Lt,
+            Box::new(Expression::Column(0)),
+            Box::new(Expression::Column(2)),
+        );
+        let three = Expression::Comparison(
+            ComparisonOp::Eq,
+            Box::new(Expression::Column(0)),
+            Box::new(Expression::constant(Types::BigInt(3))),
+        );
+        let plan = scan
+            .clone()
+            .nested_loop_join(scan, less, JoinType::Inner)
+            .unwrap()
+            .filter(three)
+            .sort(vec![(Expression::Column(3), OrderDirection::Desc)])
+            .unwrap()
+            .limit(2)
+            .project(vec![Expression::Column(1)], &["Name"])
+            .unwrap();
+        let expected = "\
+Logical plan:
+Projection: Name
+  TopN: 2 by Name DESC
+    Filter: (Id = 3)
+      NestedLoopJoin (Inner): (Id < Id)
+        SeqScan: users
+        SeqScan: users
+Physical plan:
+Projection: Name
+  TopN: 2 by Name DESC
+    NestedLoopJoin (Inner): (Id < Id)
+      IndexScan: users using users_id, key = 3
+      SeqScan: users
+";
+        assert_eq!(expected, explain(&catalog, &plan));
+
+        // SELECT APPROX_COUNT_DISTINCT(Name) AS Names FROM users;
+        let aggregates = vec![(
+            AggregateFunction::ApproxCountDistinct,
+            Expression::Column(1),
+        )];
+        let plan = PlanNode::seq_scan(&catalog, "users")
+            .unwrap()
+            .aggregate(aggregates, &["Names"])
+            .unwrap();
+        let expected = "\
+Logical plan:
+Aggregate: APPROX_COUNT_DISTINCT(Name) AS Names
+  SeqScan: users
+Physical plan:
+Aggregate: APPROX_COUNT_DISTINCT(Name) AS Names
+  SeqScan: users
+";
+        assert_eq!(expected, explain(&catalog, &plan));
+
+        // Once analyzed, nodes show their estimated row counts.
+        let table = catalog.table("users").unwrap();
+        for id in 0..100 {
+            let values = vec![Value::new(Types::BigInt(id)), Value::null(Types::owned())];
+            let tuple = Tuple::new(&values, table.schema());
+            table.heap().insert_tuple(&mut bpm, 1, tuple).unwrap();
+        }
+        catalog.analyze(&mut bpm, 1, "users").unwrap();
+        let expected = "\
+Aggregate: APPROX_COUNT_DISTINCT(Name) AS Names (rows = 1)
+  SeqScan: users (rows = 100)
+";
+        assert_eq!(expected, render(&catalog, &plan));
+
+        // SELECT * FROM users u JOIN users v ON u.Id = v.Id WHERE u.Id < 25;
+        let scan = PlanNode::seq_scan(&catalog, "users").unwrap();
+        let below = Expression::Comparison(
+            ComparisonOp::Lt,
+            Box::new(Expression::Column(0)),
+            Box::new(Expression::constant(Types::BigInt(25))),
+        );
+        let plan = scan
+            .clone()
+            .filter(below)
+            .hash_join(scan, Expression::Column(0), Expression::Column(0))
+            .unwrap();
+        // Every Id matches one row of the other side.
+        let expected = "\
+HashJoin: Id = Id (rows = 26)
+  Filter: (Id < 25) (rows = 26)
+    SeqScan: users (rows = 100)
+  SeqScan: users (rows = 100)
+";
+        assert_eq!(expected, render(&catalog, &plan));
+    }
+}
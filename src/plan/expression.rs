@@ -0,0 +1,603 @@
+// Functionality: Expression trees evaluated against a tuple and its schema,
+// e.g. the predicate of a filter or the outputs of a projection. Columns are
+// referenced by their index in the schema of the input tuples.
+//
+// Comparisons and boolean operators follow SQL three-valued logic: they
+// evaluate to null if the outcome depends on a null operand, and a predicate
+// holds only if it evaluates to true.
+//
+// Serialized format (size in byte), e.g. for the check constraints in the
+// catalog:
+//  -----------------------------
+// | Kind (1) | Body (variable) |
+//  -----------------------------
+// A constant has TypeId (1), ValueLen (4) and Value (ValueLen), where a null
+// has no value; a column has its index (4); a comparison or an arithmetic
+// has Op (1), then its operands; the other kinds have their operands only.
+
+use crate::catalog::column::Column;
+use crate::catalog::schema::Schema;
+use crate::common::error::*;
+use crate::common::reinterpret;
+use crate::table::tuple::Tuple;
+use crate::types::types::Operation;
+use crate::types::types::Types;
+use crate::types::value::Value;
+use std::mem;
+
+const CONSTANT: u8 = 1;
+const COLUMN: u8 = 2;
+const COMPARISON: u8 = 3;
+const ARITHMETIC: u8 = 4;
+const AND: u8 = 5;
+const OR: u8 = 6;
+const NOT: u8 = 7;
+const IS_NULL: u8 = 8;
+
+// The operators in the order of their serialized codes.
+const COMPARISON_OPS: [ComparisonOp; 6] = [
+    ComparisonOp::Eq,
+    ComparisonOp::Ne,
+    ComparisonOp::Lt,
+    ComparisonOp::Le,
+    ComparisonOp::Gt,
+    ComparisonOp::Ge,
+];
+const ARITHMETIC_OPS: [ArithmeticOp; 5] = [
+    ArithmeticOp::Add,
+    ArithmeticOp::Subtract,
+    ArithmeticOp::Multiply,
+    ArithmeticOp::Divide,
+    ArithmeticOp::Modulo,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComparisonOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArithmeticOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+}
+
+#[derive(Clone, Debug)]
+pub enum Expression {
+    Constant(Value<'static>),
+    // The index of the column in the input schema.
+    Column(usize),
+    Comparison(ComparisonOp, Box<Expression>, Box<Expression>),
+    Arithmetic(ArithmeticOp, Box<Expression>, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    IsNull(Box<Expression>),
+}
+
+impl Expression {
+    pub fn constant(content: Types<'static>) -> Self {
+        Expression::Constant(Value::new(content))
+    }
+
+    // The null value of the type of |types|.
+    pub fn null(types: Types<'static>) -> Self {
+        Expression::Constant(Value::null(types))
+    }
+
+    // References the column |name| of |schema|. Returns |NotFound| if there is
+    // no such column.
+    pub fn column(schema: &Schema, name: &str) -> std::io::Result<Self> {
+        match schema.column_idx(name) {
+            Some(idx) => Ok(Expression::Column(idx)),
+            None => Err(not_found(&format!("Column not found; name = {}", name))),
+        }
+    }
+
+    pub fn evaluate<'a>(&self, tuple: &Tuple, schema: &'a Schema) -> std::io::Result<Value<'a>> {
+        match self {
+            Expression::Constant(value) => Ok(value.clone()),
+            Expression::Column(idx) => match *idx < schema.columns().len() {
+                true => Ok(tuple.nth_value(schema, *idx)),
+                false => Err(invalid_input("Column index is out of range")),
+            },
+            Expression::Comparison(op, lhs, rhs) => {
+                let lhs = lhs.evaluate(tuple, schema)?;
+                let rhs = rhs.evaluate(tuple, schema)?;
+                Ok(boolean(compare(*op, &lhs, &rhs)))
+            }
+            Expression::Arithmetic(op, lhs, rhs) => {
+                let lhs = lhs.evaluate(tuple, schema)?;
+                let rhs = rhs.evaluate(tuple, schema)?;
+                let result = match op {
+                    ArithmeticOp::Add => lhs.add(&rhs),
+                    ArithmeticOp::Subtract => lhs.subtract(&rhs),
+                    ArithmeticOp::Multiply => lhs.multiply(&rhs),
+                    ArithmeticOp::Divide => lhs.divide(&rhs),
+                    ArithmeticOp::Modulo => lhs.modulo(&rhs),
+                };
+                result.map_err(|_| invalid_input(&format!("Cannot evaluate {:?}", op)))
+            }
+            // Short-circuits, i.e. |rhs| is not evaluated if |lhs| decides.
+            Expression::And(lhs, rhs) => {
+                let lhs = lhs.evaluate(tuple, schema)?;
+                if to_bool(&lhs)? == Some(false) {
+                    return Ok(lhs);
+                }
+                logic(lhs.and(&rhs.evaluate(tuple, schema)?))
+            }
+            Expression::Or(lhs, rhs) => {
+                let lhs = lhs.evaluate(tuple, schema)?;
+                if to_bool(&lhs)? == Some(true) {
+                    return Ok(lhs);
+                }
+                logic(lhs.or(&rhs.evaluate(tuple, schema)?))
+            }
+            Expression::Not(child) => logic(child.evaluate(tuple, schema)?.not()),
+            Expression::IsNull(child) => {
+                let value = child.evaluate(tuple, schema)?;
+                Ok(boolean(Some(value.is_null())))
+            }
+        }
+    }
+
+    // Returns whether the predicate holds for |tuple|, i.e. evaluates to
+    // true. Returns |InvalidInput| if the expression is not a boolean one.
+    pub fn evaluate_predicate(&self, tuple: &Tuple, schema: &Schema) -> std::io::Result<bool> {
+        Ok(to_bool(&self.evaluate(tuple, schema)?)? == Some(true))
+    }
+
+    // Describes the values of the expression as a column named |name|, for
+    // the output schema of a projection. Returns |InvalidInput| if a column
+    // index is out of range for |schema|.
+    pub fn output_column(&self, schema: &Schema, name: &str) -> std::io::Result<Column<'static>> {
+        let types = self.output_types(schema)?;
+        let length = match self {
+            Expression::Column(idx) => schema.nth_column(*idx).unwrap().len(),
+            Expression::Constant(value) if !types.is_inlined() => value.len(),
+            _ => types.size(),
+        };
+        Ok(Column::new(name.to_string(), types, length))
+    }
+
+    // Renders the expression, naming columns after |schema|.
+    pub fn to_string(&self, schema: &Schema) -> String {
+        let binary = |op: &str, lhs: &Expression, rhs: &Expression| {
+            format!("({} {} {})", lhs.to_string(schema), op, rhs.to_string(schema))
+        };
+        match self {
+            Expression::Constant(value) if value.is_null() => "NULL".to_string(),
+            Expression::Constant(value) => match value.borrow() {
+                Types::Varchar(_) => format!("'{}'", value.to_string()),
+                _ => value.to_string(),
+            },
+            Expression::Column(idx) => match schema.nth_column(*idx) {
+                Some(column) => column.name().to_string(),
+                None => format!("#{}", idx),
+            },
+            Expression::Comparison(op, lhs, rhs) => {
+                let op = match op {
+                    ComparisonOp::Eq => "=",
+                    ComparisonOp::Ne => "<>",
+                    ComparisonOp::Lt => "<",
+                    ComparisonOp::Le => "<=",
+                    ComparisonOp::Gt => ">",
+                    ComparisonOp::Ge => ">=",
+                };
+                binary(op, lhs, rhs)
+            }
+            Expression::Arithmetic(op, lhs, rhs) => {
+                let op = match op {
+                    ArithmeticOp::Add => "+",
+                    ArithmeticOp::Subtract => "-",
+                    ArithmeticOp::Multiply => "*",
+                    ArithmeticOp::Divide => "/",
+                    ArithmeticOp::Modulo => "%",
+                };
+                binary(op, lhs, rhs)
+            }
+            Expression::And(lhs, rhs) => binary("AND", lhs, rhs),
+            Expression::Or(lhs, rhs) => binary("OR", lhs, rhs),
+            Expression::Not(child) => format!("(NOT {})", child.to_string(schema)),
+            Expression::IsNull(child) => format!("({} IS NULL)", child.to_string(schema)),
+        }
+    }
+
+    // Returns the indices of the columns the expression references, in order
+    // of appearance.
+    pub fn column_indices(&self) -> Vec<usize> {
+        match self {
+            Expression::Constant(_) => vec![],
+            Expression::Column(idx) => vec![*idx],
+            Expression::Comparison(_, lhs, rhs)
+            | Expression::Arithmetic(_, lhs, rhs)
+            | Expression::And(lhs, rhs)
+            | Expression::Or(lhs, rhs) => {
+                let mut indices = lhs.column_indices();
+                indices.extend(rhs.column_indices());
+                indices
+            }
+            Expression::Not(child) | Expression::IsNull(child) => child.column_indices(),
+        }
+    }
+
+    // Replaces every column index |idx| the expression references with
+    // |map(idx)|, e.g. after a column before it was dropped.
+    pub fn remap_columns(&mut self, map: &dyn Fn(usize) -> usize) {
+        match self {
+            Expression::Constant(_) => (),
+            Expression::Column(idx) => *idx = map(*idx),
+            Expression::Comparison(_, lhs, rhs)
+            | Expression::Arithmetic(_, lhs, rhs)
+            | Expression::And(lhs, rhs)
+            | Expression::Or(lhs, rhs) => {
+                lhs.remap_columns(map);
+                rhs.remap_columns(map);
+            }
+            Expression::Not(child) | Expression::IsNull(child) => child.remap_columns(map),
+        }
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        1 + match self {
+            Expression::Constant(value) => 5 + constant_len(value),
+            Expression::Column(_) => 4,
+            Expression::Comparison(_, lhs, rhs) | Expression::Arithmetic(_, lhs, rhs) => {
+                1 + lhs.serialized_len() + rhs.serialized_len()
+            }
+            Expression::And(lhs, rhs) | Expression::Or(lhs, rhs) => {
+                lhs.serialized_len() + rhs.serialized_len()
+            }
+            Expression::Not(child) | Expression::IsNull(child) => child.serialized_len(),
+        }
+    }
+
+    // The caller needs to ensure that |dst| has |self.serialized_len()| bytes.
+    pub fn serialize_to(&self, dst: &mut [u8]) {
+        let binary = |dst: &mut [u8], lhs: &Expression, rhs: &Expression| {
+            lhs.serialize_to(dst);
+            rhs.serialize_to(&mut dst[lhs.serialized_len()..]);
+        };
+        match self {
+            Expression::Constant(value) => {
+                dst[0] = CONSTANT;
+                dst[1] = value.borrow().id();
+                let len = constant_len(value);
+                reinterpret::write_u32(&mut dst[2..], len as u32);
+                if len > 0 {
+                    value.serialize_to(&mut dst[6..(6 + len)]);
+                }
+            }
+            Expression::Column(idx) => {
+                dst[0] = COLUMN;
+                reinterpret::write_u32(&mut dst[1..], *idx as u32);
+            }
+            Expression::Comparison(op, lhs, rhs) => {
+                dst[0] = COMPARISON;
+                dst[1] = COMPARISON_OPS.iter().position(|other| other == op).unwrap() as u8;
+                binary(&mut dst[2..], lhs, rhs);
+            }
+            Expression::Arithmetic(op, lhs, rhs) => {
+                dst[0] = ARITHMETIC;
+                dst[1] = ARITHMETIC_OPS.iter().position(|other| other == op).unwrap() as u8;
+                binary(&mut dst[2..], lhs, rhs);
+            }
+            Expression::And(lhs, rhs) => {
+                dst[0] = AND;
+                binary(&mut dst[1..], lhs, rhs);
+            }
+            Expression::Or(lhs, rhs) => {
+                dst[0] = OR;
+                binary(&mut dst[1..], lhs, rhs);
+            }
+            Expression::Not(child) => {
+                dst[0] = NOT;
+                child.serialize_to(&mut dst[1..]);
+            }
+            Expression::IsNull(child) => {
+                dst[0] = IS_NULL;
+                child.serialize_to(&mut dst[1..]);
+            }
+        }
+    }
+
+    // Reads an expression from the start of |src|; it takes
+    // |serialized_len()| bytes of it. Returns |InvalidData| if |src| does not
+    // hold one.
+    pub fn deserialize_from(src: &[u8]) -> std::io::Result<Self> {
+        let truncated = || invalid_data("Expression is truncated");
+        let invalid = || invalid_data("Expression is invalid");
+        let kind = *src.first().ok_or_else(truncated)?;
+        let byte = |idx: usize| src.get(idx).copied().ok_or_else(truncated);
+        let binary = |src: &[u8]| -> std::io::Result<(Box<Expression>, Box<Expression>)> {
+            let lhs = Expression::deserialize_from(src)?;
+            let rhs = Expression::deserialize_from(&src[lhs.serialized_len()..])?;
+            Ok((Box::new(lhs), Box::new(rhs)))
+        };
+        match kind {
+            CONSTANT => {
+                let types = Types::from_id(byte(1)?).ok_or_else(invalid)?;
+                if src.len() < 6 {
+                    return Err(truncated());
+                }
+                let len = reinterpret::read_u32(&src[2..]) as usize;
+                if len == 0 {
+                    return Ok(Expression::Constant(Value::null(types)));
+                }
+                if src.len() < 6 + len {
+                    return Err(truncated());
+                }
+                let mut value = Value::new(types);
+                value.deserialize_from(&src[6..(6 + len)]);
+                Ok(Expression::Constant(value))
+            }
+            COLUMN => match src.len() < 5 {
+                true => Err(truncated()),
+                false => Ok(Expression::Column(reinterpret::read_u32(&src[1..]) as usize)),
+            },
+            COMPARISON => {
+                let op = *COMPARISON_OPS.get(byte(1)? as usize).ok_or_else(invalid)?;
+                let (lhs, rhs) = binary(&src[2..])?;
+                Ok(Expression::Comparison(op, lhs, rhs))
+            }
+            ARITHMETIC => {
+                let op = *ARITHMETIC_OPS.get(byte(1)? as usize).ok_or_else(invalid)?;
+                let (lhs, rhs) = binary(&src[2..])?;
+                Ok(Expression::Arithmetic(op, lhs, rhs))
+            }
+            AND => binary(&src[1..]).map(|(lhs, rhs)| Expression::And(lhs, rhs)),
+            OR => binary(&src[1..]).map(|(lhs, rhs)| Expression::Or(lhs, rhs)),
+            NOT => Ok(Expression::Not(Box::new(Expression::deserialize_from(
+                &src[1..],
+            )?))),
+            IS_NULL => Ok(Expression::IsNull(Box::new(Expression::deserialize_from(
+                &src[1..],
+            )?))),
+            _ => Err(invalid()),
+        }
+    }
+
+    fn output_types(&self, schema: &Schema) -> std::io::Result<Types<'static>> {
+        let from_id = |id| Types::from_id(id).unwrap();
+        match self {
+            Expression::Constant(value) => Ok(from_id(value.borrow().id())),
+            Expression::Column(idx) => match schema.nth_types(*idx) {
+                Some(types) => Ok(from_id(types.id())),
+                None => Err(invalid_input("Column index is out of range")),
+            },
+            // Arithmetic promotes numeric operands to the wider type, in the
+            // order of type IDs, from TinyInt to Decimal.
+            Expression::Arithmetic(_, lhs, rhs) => {
+                let lhs = lhs.output_types(schema)?;
+                let rhs = rhs.output_types(schema)?;
+                let numeric = |types: &Types| (2..=6).contains(&types.id());
+                match numeric(&lhs) && numeric(&rhs) && rhs.id() > lhs.id() {
+                    true => Ok(rhs),
+                    false => Ok(lhs),
+                }
+            }
+            Expression::Comparison(_, lhs, rhs)
+            | Expression::And(lhs, rhs)
+            | Expression::Or(lhs, rhs) => {
+                lhs.output_types(schema)?;
+                rhs.output_types(schema)?;
+                Ok(Types::boolean())
+            }
+            Expression::Not(child) | Expression::IsNull(child) => {
+                child.output_types(schema)?;
+                Ok(Types::boolean())
+            }
+        }
+    }
+}
+
+// Room for a constant, as in a tuple; none for null.
+fn constant_len(value: &Value) -> usize {
+    match value.is_null() {
+        true => 0,
+        false => value.len() + mem::size_of::<u64>(),
+    }
+}
+
+fn compare(op: ComparisonOp, lhs: &Value, rhs: &Value) -> Option<bool> {
+    if lhs.is_null() || rhs.is_null() {
+        return None;
+    }
+    match op {
+        ComparisonOp::Eq => lhs.eq(rhs),
+        ComparisonOp::Ne => lhs.ne(rhs),
+        ComparisonOp::Lt => lhs.lt(rhs),
+        ComparisonOp::Le => lhs.le(rhs),
+        ComparisonOp::Gt => lhs.gt(rhs),
+        ComparisonOp::Ge => lhs.ge(rhs),
+    }
+}
+
+fn boolean(value: Option<bool>) -> Value<'static> {
+    match value {
+        Some(value) => Value::new(Types::Boolean(value as i8)),
+        None => Value::null(Types::boolean()),
+    }
+}
+
+// Maps the failure of a boolean connective to |InvalidInput|.
+fn logic<T, E>(result: Result<T, E>) -> std::io::Result<T> {
+    result.map_err(|_| invalid_input("Expression is not a boolean"))
+}
+
+// Returns None for null. Returns |InvalidInput| if |value| is not a boolean.
+fn to_bool(value: &Value) -> std::io::Result<Option<bool>> {
+    match value.borrow() {
+        Types::Boolean(_) if value.is_null() => Ok(None),
+        Types::Boolean(value) => Ok(Some(*value != 0)),
+        _ => Err(invalid_input("Expression is not a boolean")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::types::Str;
+    use crate::types::types::Varlen;
+
+    fn create_schema() -> Schema<'static> {
+        Schema::new(vec![
+            Column::new("Id".to_string(), Types::integer(), 4),
+            Column::new("Name".to_string(), Types::owned(), 10),
+            Column::new("Score".to_string(), Types::decimal(), 8),
+        ])
+    }
+
+    fn create_tuple(schema: &Schema, id: i32, score: Option<f64>) -> Tuple {
+        let values = vec![
+            Value::new(Types::Integer(id)),
+            Value::new(Types::Varchar(Varlen::Owned(Str::Val("name".to_string())))),
+            match score {
+                Some(score) => Value::new(Types::Decimal(score)),
+                None => Value::null(Types::decimal()),
+            },
+        ];
+        Tuple::new(&values, schema)
+    }
+
+    fn name_constant(s: &str) -> Expression {
+        Expression::constant(Types::Varchar(Varlen::Owned(Str::Val(s.to_string()))))
+    }
+
+    fn binary(
+        f: fn(Box<Expression>, Box<Expression>) -> Expression,
+        lhs: Expression,
+        rhs: Expression,
+    ) -> Expression {
+        f(Box::new(lhs), Box::new(rhs))
+    }
+
+    #[test]
+    fn evaluate() {
+        let schema = create_schema();
+        let tuple = create_tuple(&schema, 7, Some(1.5));
+        let id = Expression::column(&schema, "Id").unwrap();
+        assert!(Expression::column(&schema, "Age").is_err());
+
+        let sum = Expression::Arithmetic(
+            ArithmeticOp::Add,
+            Box::new(id.clone()),
+            Box::new(Expression::constant(Types::BigInt(3))),
+        );
+        let value = sum.evaluate(&tuple, &schema).unwrap();
+        assert_eq!(10, value.borrow().get_as_i64().unwrap());
+        assert_eq!(5, sum.output_column(&schema, "Sum").unwrap().types().id());
+
+        let gt = Expression::Comparison(
+            ComparisonOp::Gt,
+            Box::new(sum),
+            Box::new(Expression::constant(Types::Integer(9))),
+        );
+        assert!(gt.evaluate_predicate(&tuple, &schema).unwrap());
+        let not = Expression::Not(Box::new(gt.clone()));
+        assert!(!not.evaluate_predicate(&tuple, &schema).unwrap());
+
+        let divide = Expression::Arithmetic(
+            ArithmeticOp::Divide,
+            Box::new(id),
+            Box::new(Expression::constant(Types::Integer(0))),
+        );
+        assert!(divide.evaluate(&tuple, &schema).is_err());
+        assert!(Expression::Column(3).evaluate(&tuple, &schema).is_err());
+        assert!(Expression::Column(3).output_column(&schema, "X").is_err());
+        assert!(Expression::Column(0)
+            .evaluate_predicate(&tuple, &schema)
+            .is_err());
+
+        let is_named = binary(
+            Expression::And,
+            Expression::Not(Box::new(gt)),
+            Expression::Comparison(
+                ComparisonOp::Ne,
+                Box::new(Expression::Column(1)),
+                Box::new(name_constant("x")),
+            ),
+        );
+        assert_eq!(
+            "((NOT ((Id + 3) > 9)) AND (Name <> 'x'))",
+            is_named.to_string(&schema)
+        );
+        assert_eq!("#3", Expression::Column(3).to_string(&schema));
+    }
+
+    #[test]
+    fn three_valued_logic() {
+        let schema = create_schema();
+        let tuple = create_tuple(&schema, 7, None);
+        let score = Expression::column(&schema, "Score").unwrap();
+        let unknown = Expression::Comparison(
+            ComparisonOp::Eq,
+            Box::new(score.clone()),
+            Box::new(Expression::constant(Types::Decimal(1.0))),
+        );
+        let yes = Expression::constant(Types::Boolean(1));
+        let no = Expression::constant(Types::Boolean(0));
+        let is_null = |expr: Expression| expr.evaluate(&tuple, &schema).unwrap().is_null();
+
+        assert!(is_null(unknown.clone()));
+        assert!(is_null(Expression::Not(Box::new(unknown.clone()))));
+        assert!(is_null(binary(
+            Expression::And,
+            unknown.clone(),
+            yes.clone()
+        )));
+        assert!(is_null(binary(Expression::Or, no.clone(), unknown.clone())));
+        let and = binary(Expression::And, unknown.clone(), no);
+        assert!(!and.evaluate_predicate(&tuple, &schema).unwrap());
+        assert!(!is_null(and));
+        let or = binary(Expression::Or, unknown.clone(), yes);
+        assert!(or.evaluate_predicate(&tuple, &schema).unwrap());
+        assert!(!unknown.evaluate_predicate(&tuple, &schema).unwrap());
+        let score_is_null = Expression::IsNull(Box::new(score));
+        assert!(score_is_null.evaluate_predicate(&tuple, &schema).unwrap());
+    }
+
+    #[test]
+    fn serialize_and_remap() {
+        let schema = create_schema();
+        let id = Expression::column(&schema, "Id").unwrap();
+        let score = Expression::column(&schema, "Score").unwrap();
+        let expression = binary(
+            Expression::Or,
+            Expression::Comparison(
+                ComparisonOp::Ge,
+                Box::new(Expression::Arithmetic(
+                    ArithmeticOp::Modulo,
+                    Box::new(id),
+                    Box::new(Expression::constant(Types::Integer(2))),
+                )),
+                Box::new(name_constant("it's")),
+            ),
+            Expression::Not(Box::new(Expression::IsNull(Box::new(binary(
+                Expression::And,
+                score,
+                Expression::null(Types::decimal()),
+            ))))),
+        );
+        let mut data = vec![0; expression.serialized_len()];
+        expression.serialize_to(&mut data);
+        let decoded = Expression::deserialize_from(&data).unwrap();
+        assert_eq!(expression.to_string(&schema), decoded.to_string(&schema));
+        assert_eq!(data.len(), decoded.serialized_len());
+        assert!(Expression::deserialize_from(&data[..(data.len() - 1)]).is_err());
+        assert!(Expression::deserialize_from(&[COMPARISON, 9]).is_err());
+
+        let mut remapped = decoded;
+        assert_eq!(vec![0, 2], remapped.column_indices());
+        remapped.remap_columns(&|idx| idx - (idx > 1) as usize);
+        assert_eq!(vec![0, 1], remapped.column_indices());
+    }
+}
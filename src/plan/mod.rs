@@ -0,0 +1,5 @@
+pub mod explain;
+pub mod expression;
+pub mod optimizer;
+pub mod plan_node;
+pub mod selectivity;
@@ -0,0 +1,723 @@
+// Functionality: A rule-based optimizer, rewriting plans into equivalent ones
+// that are cheaper to execute. Plans are rewritten bottom-up, with the rules:
+//   - Constant folding: expressions that do not reference any column are
+//     replaced with their values, and filters that always hold are removed.
+//   - Predicate pushdown: the conjuncts of a filter above a join that only
+//     reference the columns of one side are moved below the join, where they
+//     discard tuples before they are joined.
+//   - Projection pruning: a projection of a projection is merged into one,
+//     and a projection reproducing its input is removed.
+//   - Index scan: a filter on a sequential scan testing an indexed column for
+//     equality with a constant becomes an index scan. If the table was
+//     analyzed, the conjuncts of the filter are ordered by their selectivity
+//     estimated from its statistics, most selective first.
+
+use crate::catalog::catalog::Catalog;
+use crate::catalog::catalog::Oid;
+use crate::catalog::schema::Schema;
+use crate::plan::expression::ComparisonOp;
+use crate::plan::expression::Expression;
+use crate::plan::plan_node::JoinType;
+use crate::plan::plan_node::OnConflict;
+use crate::plan::plan_node::PlanNode;
+use crate::plan::selectivity::selectivity;
+use crate::table::tuple::Tuple;
+use crate::types::types::Types;
+
+pub struct Optimizer<'a> {
+    catalog: &'a Catalog,
+}
+
+impl<'a> Optimizer<'a> {
+    pub fn new(catalog: &'a Catalog) -> Self {
+        Optimizer { catalog }
+    }
+
+    pub fn optimize(&self, plan: PlanNode) -> PlanNode {
+        match self.optimize_children(plan) {
+            PlanNode::Filter { predicate, child } => self.optimize_filter(predicate, *child),
+            PlanNode::Projection {
+                expressions,
+                schema,
+                child,
+            } => prune_projection(expressions, schema, *child),
+            plan => plan,
+        }
+    }
+
+    // Optimizes the children of |plan|, and folds its expressions.
+    fn optimize_children(&self, plan: PlanNode) -> PlanNode {
+        let optimize = |child: Box<PlanNode>| Box::new(self.optimize(*child));
+        let fold_all = |expressions: Vec<Expression>| expressions.into_iter().map(fold).collect();
+        let fold_keys = |order_bys: Vec<(Expression, _)>| {
+            order_bys
+                .into_iter()
+                .map(|(expression, direction)| (fold(expression), direction))
+                .collect()
+        };
+        match plan {
+            PlanNode::SeqScan { .. } | PlanNode::IndexScan { .. } => plan,
+            PlanNode::Values { schema, rows } => PlanNode::Values {
+                schema,
+                rows: rows.into_iter().map(fold_all).collect(),
+            },
+            PlanNode::Filter { predicate, child } => PlanNode::Filter {
+                predicate: fold(predicate),
+                child: optimize(child),
+            },
+            PlanNode::Projection {
+                expressions,
+                schema,
+                child,
+            } => PlanNode::Projection {
+                expressions: fold_all(expressions),
+                schema,
+                child: optimize(child),
+            },
+            PlanNode::Aggregate {
+                aggregates,
+                schema,
+                child,
+            } => PlanNode::Aggregate {
+                aggregates: aggregates
+                    .into_iter()
+                    .map(|(function, expression)| (function, fold(expression)))
+                    .collect(),
+                schema,
+                child: optimize(child),
+            },
+            PlanNode::Sort { order_bys, child } => PlanNode::Sort {
+                order_bys: fold_keys(order_bys),
+                child: optimize(child),
+            },
+            PlanNode::Limit { limit, child } => PlanNode::Limit {
+                limit,
+                child: optimize(child),
+            },
+            PlanNode::TopN {
+                order_bys,
+                limit,
+                child,
+            } => PlanNode::TopN {
+                order_bys: fold_keys(order_bys),
+                limit,
+                child: optimize(child),
+            },
+            PlanNode::HashJoin {
+                left_key,
+                right_key,
+                schema,
+                left,
+                right,
+            } => PlanNode::HashJoin {
+                left_key: fold(left_key),
+                right_key: fold(right_key),
+                schema,
+                left: optimize(left),
+                right: optimize(right),
+            },
+            PlanNode::NestedLoopJoin {
+                join_type,
+                predicate,
+                schema,
+                left,
+                right,
+            } => PlanNode::NestedLoopJoin {
+                join_type,
+                predicate: fold(predicate),
+                schema,
+                left: optimize(left),
+                right: optimize(right),
+            },
+            PlanNode::IndexJoin {
+                join_type,
+                left_key,
+                index_name,
+                table_oid,
+                schema,
+                left,
+            } => PlanNode::IndexJoin {
+                join_type,
+                left_key: fold(left_key),
+                index_name,
+                table_oid,
+                schema,
+                left: optimize(left),
+            },
+            PlanNode::Insert {
+                table_oid,
+                on_conflict,
+                child,
+            } => PlanNode::Insert {
+                table_oid,
+                on_conflict: on_conflict.map(|on_conflict| OnConflict {
+                    index_name: on_conflict.index_name,
+                    assignments: on_conflict
+                        .assignments
+                        .into_iter()
+                        .map(|(idx, expression)| (idx, fold(expression)))
+                        .collect(),
+                }),
+                child: optimize(child),
+            },
+            PlanNode::Delete { table_oid, child } => PlanNode::Delete {
+                table_oid,
+                child: optimize(child),
+            },
+            PlanNode::Update {
+                table_oid,
+                assignments,
+                child,
+            } => PlanNode::Update {
+                table_oid,
+                assignments: assignments
+                    .into_iter()
+                    .map(|(idx, expression)| (idx, fold(expression)))
+                    .collect(),
+                child: optimize(child),
+            },
+        }
+    }
+
+    // Rewrites a filter of |child|, which is optimized already.
+    fn optimize_filter(&self, predicate: Expression, child: PlanNode) -> PlanNode {
+        if let Expression::Constant(value) = &predicate {
+            if let (false, Types::Boolean(1)) = (value.is_null(), value.borrow()) {
+                return child;
+            }
+        }
+        match child {
+            PlanNode::HashJoin { .. }
+            | PlanNode::NestedLoopJoin { .. }
+            | PlanNode::IndexJoin { .. } => self.push_down(predicate, child),
+            PlanNode::SeqScan { table_oid, schema } => {
+                self.index_scan(predicate, table_oid, schema)
+            }
+            child => PlanNode::Filter {
+                predicate,
+                child: Box::new(child),
+            },
+        }
+    }
+
+    // Moves the conjuncts of |predicate| that only reference one side of
+    // |join| below it. Conjuncts on the right side are only moved below inner
+    // joins, as they would drop the padded tuples of outer joins otherwise.
+    fn push_down(&self, predicate: Expression, join: PlanNode) -> PlanNode {
+        let (left_len, right_pushable) = match &join {
+            PlanNode::HashJoin { left, .. } => (left.output_schema().columns().len(), true),
+            PlanNode::NestedLoopJoin {
+                join_type, left, ..
+            } => (
+                left.output_schema().columns().len(),
+                *join_type == JoinType::Inner,
+            ),
+            // The right side is a table read through its index.
+            PlanNode::IndexJoin { left, .. } => (left.output_schema().columns().len(), false),
+            _ => unreachable!(),
+        };
+        let mut lefts = Vec::new();
+        let mut rights = Vec::new();
+        let mut rest = Vec::new();
+        for conjunct in conjuncts(predicate) {
+            let columns = columns(&conjunct);
+            if columns.iter().all(|idx| *idx < left_len) {
+                lefts.push(conjunct);
+            } else if right_pushable && columns.iter().all(|idx| *idx >= left_len) {
+                rights.push(map_columns(conjunct, &|idx| {
+                    Expression::Column(idx - left_len)
+                }));
+            } else {
+                rest.push(conjunct);
+            }
+        }
+        let join = match join {
+            PlanNode::HashJoin {
+                left_key,
+                right_key,
+                schema,
+                left,
+                right,
+            } => PlanNode::HashJoin {
+                left_key,
+                right_key,
+                schema,
+                left: self.filter(lefts, left),
+                right: self.filter(rights, right),
+            },
+            PlanNode::NestedLoopJoin {
+                join_type,
+                predicate,
+                schema,
+                left,
+                right,
+            } => PlanNode::NestedLoopJoin {
+                join_type,
+                predicate,
+                schema,
+                left: self.filter(lefts, left),
+                right: self.filter(rights, right),
+            },
+            PlanNode::IndexJoin {
+                join_type,
+                left_key,
+                index_name,
+                table_oid,
+                schema,
+                left,
+            } => PlanNode::IndexJoin {
+                join_type,
+                left_key,
+                index_name,
+                table_oid,
+                schema,
+                left: self.filter(lefts, left),
+            },
+            _ => unreachable!(),
+        };
+        match conjoin(rest) {
+            Some(predicate) => PlanNode::Filter {
+                predicate,
+                child: Box::new(join),
+            },
+            None => join,
+        }
+    }
+
+    // Filters |child| on all of |conjuncts|, if any.
+    fn filter(&self, conjuncts: Vec<Expression>, child: Box<PlanNode>) -> Box<PlanNode> {
+        match conjoin(conjuncts) {
+            Some(predicate) => Box::new(self.optimize_filter(predicate, *child)),
+            None => child,
+        }
+    }
+
+    // Replaces a scan of the table |table_oid| filtered on |predicate| with an
+    // index scan, if a conjunct tests an indexed column for equality with a
+    // constant.
+    fn index_scan(
+        &self,
+        predicate: Expression,
+        table_oid: Oid,
+        schema: Schema<'static>,
+    ) -> PlanNode {
+        let indexes = self.catalog.table_indexes(table_oid);
+        let statistics = self.catalog.statistics(table_oid);
+        let mut conjuncts = conjuncts(predicate);
+        // With statistics, the most selective conjuncts go first, so that the
+        // index is the one on the most selective column, and the filter
+        // evaluates the rest in the order most likely to reject tuples early.
+        if statistics.is_some() {
+            let estimate = |conjunct: &Expression| selectivity(statistics, conjunct);
+            conjuncts.sort_by(|lhs, rhs| estimate(lhs).total_cmp(&estimate(rhs)));
+        }
+        let found = conjuncts.iter().enumerate().find_map(|(idx, conjunct)| {
+            let (column, key) = equality_key(conjunct)?;
+            let index = indexes.iter().find(|index| index.key_column() == column)?;
+            Some((idx, index.name().to_string(), key))
+        });
+        let scan = match found {
+            Some((idx, index_name, key)) => {
+                conjuncts.remove(idx);
+                PlanNode::IndexScan {
+                    table_oid,
+                    index_name,
+                    key,
+                    schema,
+                }
+            }
+            None => PlanNode::SeqScan { table_oid, schema },
+        };
+        match conjoin(conjuncts) {
+            Some(predicate) => PlanNode::Filter {
+                predicate,
+                child: Box::new(scan),
+            },
+            None => scan,
+        }
+    }
+}
+
+// Folds the subexpressions of |expression| that do not reference any column.
+// Expressions that fail to evaluate are left as is, so that they fail when the
+// plan is executed.
+fn fold(expression: Expression) -> Expression {
+    let boxed = |expression: Box<Expression>| Box::new(fold(*expression));
+    let expression = match expression {
+        Expression::Comparison(op, lhs, rhs) => Expression::Comparison(op, boxed(lhs), boxed(rhs)),
+        Expression::Arithmetic(op, lhs, rhs) => Expression::Arithmetic(op, boxed(lhs), boxed(rhs)),
+        Expression::And(lhs, rhs) => Expression::And(boxed(lhs), boxed(rhs)),
+        Expression::Or(lhs, rhs) => Expression::Or(boxed(lhs), boxed(rhs)),
+        Expression::Not(child) => Expression::Not(boxed(child)),
+        Expression::IsNull(child) => Expression::IsNull(boxed(child)),
+        expression => return expression,
+    };
+    let constant = |expression: &Expression| matches!(expression, Expression::Constant(_));
+    if !operands(&expression).into_iter().all(constant) {
+        return expression;
+    }
+    let schema = Schema::new(Vec::new());
+    match expression.evaluate(&Tuple::default(), &schema) {
+        Ok(value) => Expression::Constant(value.to_static()),
+        Err(_) => expression,
+    }
+}
+
+fn operands(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::Constant(_) | Expression::Column(_) => Vec::new(),
+        Expression::Comparison(_, lhs, rhs)
+        | Expression::Arithmetic(_, lhs, rhs)
+        | Expression::And(lhs, rhs)
+        | Expression::Or(lhs, rhs) => vec![lhs.as_ref(), rhs.as_ref()],
+        Expression::Not(child) | Expression::IsNull(child) => vec![child.as_ref()],
+    }
+}
+
+// Returns the indexes of the columns |expression| references.
+fn columns(expression: &Expression) -> Vec<usize> {
+    match expression {
+        Expression::Column(idx) => vec![*idx],
+        expression => operands(expression).into_iter().flat_map(columns).collect(),
+    }
+}
+
+// Replaces every column reference of |expression| with |f| of its index.
+fn map_columns(expression: Expression, f: &dyn Fn(usize) -> Expression) -> Expression {
+    let boxed = |expression: Box<Expression>| Box::new(map_columns(*expression, f));
+    match expression {
+        Expression::Column(idx) => f(idx),
+        Expression::Constant(_) => expression,
+        Expression::Comparison(op, lhs, rhs) => Expression::Comparison(op, boxed(lhs), boxed(rhs)),
+        Expression::Arithmetic(op, lhs, rhs) => Expression::Arithmetic(op, boxed(lhs), boxed(rhs)),
+        Expression::And(lhs, rhs) => Expression::And(boxed(lhs), boxed(rhs)),
+        Expression::Or(lhs, rhs) => Expression::Or(boxed(lhs), boxed(rhs)),
+        Expression::Not(child) => Expression::Not(boxed(child)),
+        Expression::IsNull(child) => Expression::IsNull(boxed(child)),
+    }
+}
+
+// Splits |expression| into the expressions it is the conjunction of.
+fn conjuncts(expression: Expression) -> Vec<Expression> {
+    match expression {
+        Expression::And(lhs, rhs) => {
+            let mut result = conjuncts(*lhs);
+            result.extend(conjuncts(*rhs));
+            result
+        }
+        expression => vec![expression],
+    }
+}
+
+// Returns the conjunction of |conjuncts|, or None if there is none.
+fn conjoin(conjuncts: Vec<Expression>) -> Option<Expression> {
+    conjuncts.into_iter().fold(None, |acc, conjunct| match acc {
+        Some(acc) => Some(Expression::And(Box::new(acc), Box::new(conjunct))),
+        None => Some(conjunct),
+    })
+}
+
+// Returns the column and the key if |expression| tests a column for equality
+// with a non-null integer constant.
+fn equality_key(expression: &Expression) -> Option<(usize, i64)> {
+    let (column, value) = match expression {
+        Expression::Comparison(ComparisonOp::Eq, lhs, rhs) => match (lhs.as_ref(), rhs.as_ref()) {
+            (Expression::Column(column), Expression::Constant(value))
+            | (Expression::Constant(value), Expression::Column(column)) => (*column, value),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    if value.is_null() {
+        return None;
+    }
+    value.borrow().get_as_i64().ok().map(|key| (column, key))
+}
+
+// Merges a projection of a projection, and removes a projection whose output
+// is its input.
+fn prune_projection(
+    expressions: Vec<Expression>,
+    schema: Schema<'static>,
+    child: PlanNode,
+) -> PlanNode {
+    match child {
+        PlanNode::Projection {
+            expressions: inner,
+            child: grandchild,
+            ..
+        } => {
+            let merged = expressions
+                .into_iter()
+                .map(|expression| fold(map_columns(expression, &|idx| inner[idx].clone())))
+                .collect();
+            prune_projection(merged, schema, *grandchild)
+        }
+        child => {
+            let input = child.output_schema();
+            let identity = expressions.len() == input.columns().len()
+                && expressions.iter().enumerate().all(
+                    |(idx, expression)| matches!(expression, Expression::Column(i) if *i == idx),
+                )
+                && schema == input;
+            match identity {
+                true => child,
+                false => PlanNode::Projection {
+                    expressions,
+                    schema,
+                    child: Box::new(child),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::catalog::column::Column;
+    use crate::disk::memory_storage::MemoryStorage;
+    use crate::execution::execution_context::ExecutionContext;
+    use crate::execution::executor::execute;
+    use crate::execution::executor::tests::with_context;
+    use crate::page::table_page::TablePage;
+    use crate::plan::expression::ArithmeticOp;
+    use crate::types::types::Str;
+    use crate::types::types::Varlen;
+    use crate::types::value::Value;
+
+    fn constant(content: Types<'static>) -> Box<Expression> {
+        Box::new(Expression::constant(content))
+    }
+
+    fn equals(lhs: Expression, rhs: Box<Expression>) -> Expression {
+        Expression::Comparison(ComparisonOp::Eq, Box::new(lhs), rhs)
+    }
+
+    fn strings(plan: &PlanNode, ctx: &mut ExecutionContext<MemoryStorage>) -> Vec<String> {
+        let schema = plan.output_schema();
+        let mut strings: Vec<String> = execute(plan, ctx)
+            .unwrap()
+            .iter()
+            .map(|tuple| tuple.to_string(&schema))
+            .collect();
+        strings.sort();
+        strings
+    }
+
+    // Returns the plan of the orders of users, with (UserId, Amount) rows.
+    fn orders() -> PlanNode {
+        let schema = Schema::new(vec![
+            Column::new("UserId".to_string(), Types::bigint(), 8),
+            Column::new("Amount".to_string(), Types::decimal(), 8),
+        ]);
+        let rows = (0..10)
+            .map(|i| {
+                vec![
+                    Expression::constant(Types::BigInt(i % 5)),
+                    Expression::constant(Types::Decimal(i as f64)),
+                ]
+            })
+            .collect();
+        PlanNode::values(schema, rows).unwrap()
+    }
+
+    #[test]
+    fn optimize() {
+        let setup = |catalog: &mut Catalog, bpm: &mut _| {
+            let schema = Schema::new(vec![
+                Column::new("Id".to_string(), Types::bigint(), 8),
+                Column::new("Name".to_string(), Types::owned(), 10),
+            ]);
+            catalog.create_table(bpm, 1, "users", schema).unwrap();
+            catalog
+                .create_index(bpm, 1, "users_id", "users", "Id")
+                .unwrap();
+        };
+        with_context(setup, |ctx| {
+            let catalog = ctx.catalog;
+            let optimizer = Optimizer::new(catalog);
+            let rows = (0..20)
+                .map(|i| {
+                    let name = Varlen::Owned(Str::Val(format!("user{}", i)));
+                    vec![
+                        Expression::constant(Types::BigInt(i)),
+                        Expression::constant(Types::Varchar(name)),
+                    ]
+                })
+                .collect();
+            let schema = catalog.table("users").unwrap().schema().clone();
+            let values = PlanNode::values(schema, rows).unwrap();
+            execute(&PlanNode::insert(catalog, "users", values).unwrap(), ctx).unwrap();
+            let scan = PlanNode::seq_scan(catalog, "users").unwrap();
+
+            // WHERE Id = 1 + 2 reads the index.
+            let sum = Expression::Arithmetic(
+                ArithmeticOp::Add,
+                constant(Types::Integer(1)),
+                constant(Types::Integer(2)),
+            );
+            let plan = scan
+                .clone()
+                .filter(equals(Expression::Column(0), Box::new(sum)));
+            let optimized = optimizer.optimize(plan.clone());
+            assert!(matches!(optimized, PlanNode::IndexScan { key: 3, .. }));
+            assert_eq!(strings(&plan, ctx), strings(&optimized, ctx));
+
+            // WHERE 1 < 2 is dropped, and WHERE 1 / 0 = 1 is kept as is.
+            let less = Expression::Comparison(
+                ComparisonOp::Lt,
+                constant(Types::Integer(1)),
+                constant(Types::Integer(2)),
+            );
+            let plan = optimizer.optimize(scan.clone().filter(less));
+            assert!(matches!(plan, PlanNode::SeqScan { .. }));
+            let quotient = Expression::Arithmetic(
+                ArithmeticOp::Divide,
+                constant(Types::Integer(1)),
+                constant(Types::Integer(0)),
+            );
+            let plan = scan
+                .clone()
+                .filter(equals(quotient, constant(Types::Integer(1))));
+            let plan = optimizer.optimize(plan);
+            assert!(execute(&plan, ctx).is_err());
+
+            // WHERE Id = 3 AND Amount > 2.0 AND Id < UserId + 1 on a join.
+            let join = scan
+                .clone()
+                .hash_join(orders(), Expression::Column(0), Expression::Column(0))
+                .unwrap();
+            let amount = Expression::Comparison(
+                ComparisonOp::Gt,
+                Box::new(Expression::Column(3)),
+                constant(Types::Decimal(2.0)),
+            );
+            let next = Expression::Arithmetic(
+                ArithmeticOp::Add,
+                Box::new(Expression::Column(2)),
+                constant(Types::Integer(1)),
+            );
+            let across = Expression::Comparison(
+                ComparisonOp::Lt,
+                Box::new(Expression::Column(0)),
+                Box::new(next),
+            );
+            let predicate = Expression::And(
+                Box::new(Expression::And(
+                    Box::new(equals(Expression::Column(0), constant(Types::BigInt(3)))),
+                    Box::new(amount),
+                )),
+                Box::new(across),
+            );
+            let plan = join.filter(predicate);
+            let optimized = optimizer.optimize(plan.clone());
+            match &optimized {
+                PlanNode::Filter { child, .. } => match child.as_ref() {
+                    PlanNode::HashJoin { left, right, .. } => {
+                        assert!(matches!(left.as_ref(), PlanNode::IndexScan { .. }));
+                        assert!(matches!(right.as_ref(), PlanNode::Filter { .. }));
+                    }
+                    _ => panic!("Filter should be above the join"),
+                },
+                _ => panic!("Conjunct across the join should stay above it"),
+            }
+            let expected = strings(&plan, ctx);
+            assert_eq!(2, expected.len());
+            assert_eq!(expected, strings(&optimized, ctx));
+
+            // Projections of projections are merged, and identities dropped.
+            let double = Expression::Arithmetic(
+                ArithmeticOp::Multiply,
+                Box::new(Expression::Column(0)),
+                constant(Types::Integer(2)),
+            );
+            let identity = vec![Expression::Column(0), Expression::Column(1)];
+            let plan = scan
+                .clone()
+                .project(identity, &["Id", "Name"])
+                .unwrap()
+                .project(vec![double, Expression::Column(1)], &["Double", "Name"])
+                .unwrap()
+                .project(vec![Expression::Column(0)], &["Double"])
+                .unwrap();
+            let optimized = optimizer.optimize(plan.clone());
+            match &optimized {
+                PlanNode::Projection { child, .. } => {
+                    assert!(matches!(child.as_ref(), PlanNode::SeqScan { .. }));
+                }
+                _ => panic!("Projections should be merged"),
+            }
+            assert_eq!(strings(&plan, ctx), strings(&optimized, ctx));
+            let renamed = scan.project(vec![Expression::Column(0)], &["Id"]).unwrap();
+            assert!(matches!(
+                optimizer.optimize(renamed),
+                PlanNode::Projection { .. }
+            ));
+        });
+    }
+
+    #[test]
+    fn order_by_selectivity() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(8);
+        let mut catalog = Catalog::create(&mut bpm).unwrap();
+        let schema = Schema::new(vec![
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("Group".to_string(), Types::bigint(), 8),
+        ]);
+        catalog.create_table(&mut bpm, 1, "users", schema).unwrap();
+        let table = catalog.table("users").unwrap();
+        let mut heap = table.heap();
+        for id in 0..100 {
+            let values = vec![
+                Value::new(Types::BigInt(id)),
+                Value::new(Types::BigInt(id % 2)),
+            ];
+            let tuple = Tuple::new(&values, table.schema());
+            heap.insert_tuple(&mut bpm, 1, tuple).unwrap();
+        }
+        catalog
+            .create_index(&mut bpm, 1, "users_id", "users", "Id")
+            .unwrap();
+
+        // WHERE Group = 1 AND Id > 90 AND Id = 7.
+        let compare = |op, idx, val| {
+            Expression::Comparison(
+                op,
+                Box::new(Expression::Column(idx)),
+                constant(Types::BigInt(val)),
+            )
+        };
+        let predicate = Expression::And(
+            Box::new(Expression::And(
+                Box::new(compare(ComparisonOp::Eq, 1, 1)),
+                Box::new(compare(ComparisonOp::Gt, 0, 90)),
+            )),
+            Box::new(compare(ComparisonOp::Eq, 0, 7)),
+        );
+        let plan = PlanNode::seq_scan(&catalog, "users")
+            .unwrap()
+            .filter(predicate);
+        // Returns the column the filter above the index scan tests first.
+        let first = |plan: &PlanNode| match plan {
+            PlanNode::Filter { predicate, child } => {
+                assert!(matches!(child.as_ref(), PlanNode::IndexScan { key: 7, .. }));
+                match predicate {
+                    Expression::And(lhs, _) => columns(lhs),
+                    _ => panic!("Filter should have two conjuncts"),
+                }
+            }
+            _ => panic!("Filter should be above the index scan"),
+        };
+        // Without statistics, the conjuncts keep their order.
+        assert_eq!(
+            vec![1],
+            first(&Optimizer::new(&catalog).optimize(plan.clone()))
+        );
+
+        // Id > 90 holds for a tenth of the rows, and Group = 1 for half.
+        catalog.analyze(&mut bpm, 1, "users").unwrap();
+        assert_eq!(vec![0], first(&Optimizer::new(&catalog).optimize(plan)));
+    }
+}
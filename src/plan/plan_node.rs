@@ -0,0 +1,721 @@
+// Functionality: Logical plan nodes, i.e. trees describing what a statement
+// computes, leaving how to the executors. Every node produces tuples under its
+// output schema, reading the tuples its children produce.
+//
+// Plans are built bottom-up, and each step is checked against the catalog and
+// the output schema of the children, e.g. a projection only references
+// existing columns, and an insert supplies values for every column of the
+// table. Insert, Delete and Update produce a single tuple counting the tuples
+// they changed.
+
+use crate::catalog::catalog::Catalog;
+use crate::catalog::catalog::Oid;
+use crate::catalog::catalog::TableInfo;
+use crate::catalog::column::Column;
+use crate::catalog::schema::Schema;
+use crate::common::error::*;
+use crate::plan::expression::Expression;
+use crate::types::types::Types;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinType {
+    Inner,
+    // Also produces the left tuples matching nothing, padded with nulls.
+    LeftOuter,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderDirection {
+    Asc,
+    Desc,
+}
+
+// Aggregates of all input tuples. Nulls are skipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AggregateFunction {
+    // The number of distinct values, estimated by a |HyperLogLog| sketch.
+    ApproxCountDistinct,
+}
+
+// How an insert handles a tuple whose key in the index |index_name| is taken
+// by an existing tuple: it sets the columns of the existing tuple instead, to
+// the values of the assigned expressions. They are evaluated against the
+// existing tuple followed by the one proposed, under |conflict_schema|.
+#[derive(Clone, Debug)]
+pub struct OnConflict {
+    pub index_name: String,
+    pub assignments: Vec<(usize, Expression)>,
+}
+
+#[derive(Clone, Debug)]
+pub enum PlanNode {
+    // Reads all tuples of a table.
+    SeqScan {
+        table_oid: Oid,
+        schema: Schema<'static>,
+    },
+    // Reads the tuple of a table whose key in the index |index_name| is
+    // |key|, if any.
+    IndexScan {
+        table_oid: Oid,
+        index_name: String,
+        key: i64,
+        schema: Schema<'static>,
+    },
+    // Produces the given rows, e.g. those of an INSERT ... VALUES.
+    Values {
+        schema: Schema<'static>,
+        rows: Vec<Vec<Expression>>,
+    },
+    Filter {
+        predicate: Expression,
+        child: Box<PlanNode>,
+    },
+    Projection {
+        expressions: Vec<Expression>,
+        schema: Schema<'static>,
+        child: Box<PlanNode>,
+    },
+    // Produces a single tuple with the aggregates of all tuples of |child|.
+    Aggregate {
+        aggregates: Vec<(AggregateFunction, Expression)>,
+        schema: Schema<'static>,
+        child: Box<PlanNode>,
+    },
+    // Produces the tuples of |child| ordered by the values of the order-by
+    // expressions, the first one being the most significant. Nulls come
+    // first in ascending order.
+    Sort {
+        order_bys: Vec<(Expression, OrderDirection)>,
+        child: Box<PlanNode>,
+    },
+    // Produces at most |limit| tuples of |child|.
+    Limit {
+        limit: usize,
+        child: Box<PlanNode>,
+    },
+    // Produces the first |limit| tuples of a Sort, without sorting all of
+    // them.
+    TopN {
+        order_bys: Vec<(Expression, OrderDirection)>,
+        limit: usize,
+        child: Box<PlanNode>,
+    },
+    // Joins the tuples of |left| and |right| whose keys are equal, producing
+    // the columns of the left tuple followed by those of the right one. Null
+    // keys do not match anything.
+    HashJoin {
+        left_key: Expression,
+        right_key: Expression,
+        schema: Schema<'static>,
+        left: Box<PlanNode>,
+        right: Box<PlanNode>,
+    },
+    // Joins every tuple of |left| with the tuples of |right| for which the
+    // predicate holds. The predicate is evaluated against the joined tuple.
+    NestedLoopJoin {
+        join_type: JoinType,
+        predicate: Expression,
+        schema: Schema<'static>,
+        left: Box<PlanNode>,
+        right: Box<PlanNode>,
+    },
+    // Joins every tuple of |left| with the tuple of the table whose key in
+    // the index |index_name| equals |left_key|.
+    IndexJoin {
+        join_type: JoinType,
+        left_key: Expression,
+        index_name: String,
+        table_oid: Oid,
+        schema: Schema<'static>,
+        left: Box<PlanNode>,
+    },
+    // Inserts the tuples produced by |child| into the table, or updates the
+    // ones they conflict with, if |on_conflict| is set.
+    Insert {
+        table_oid: Oid,
+        on_conflict: Option<OnConflict>,
+        child: Box<PlanNode>,
+    },
+    // Deletes the tuples produced by |child|, which reads them from the table.
+    Delete {
+        table_oid: Oid,
+        child: Box<PlanNode>,
+    },
+    // Sets the columns of the tuples produced by |child|, which reads them
+    // from the table, to the values of the assigned expressions.
+    Update {
+        table_oid: Oid,
+        assignments: Vec<(usize, Expression)>,
+        child: Box<PlanNode>,
+    },
+}
+
+impl PlanNode {
+    // Returns |NotFound| if there is no table named |table_name|.
+    pub fn seq_scan(catalog: &Catalog, table_name: &str) -> std::io::Result<Self> {
+        let table = find_table(catalog, table_name)?;
+        Ok(PlanNode::SeqScan {
+            table_oid: table.oid(),
+            schema: table.schema().clone(),
+        })
+    }
+
+    // Returns |NotFound| if there is no index named |index_name|.
+    pub fn index_scan(catalog: &Catalog, index_name: &str, key: i64) -> std::io::Result<Self> {
+        let index = catalog
+            .index(index_name)
+            .ok_or_else(|| not_found(&format!("No such index; name = {}", index_name)))?;
+        let table = catalog.table_by_oid(index.table_oid()).unwrap();
+        Ok(PlanNode::IndexScan {
+            table_oid: table.oid(),
+            index_name: index_name.to_string(),
+            key,
+            schema: table.schema().clone(),
+        })
+    }
+
+    // Returns |InvalidInput| if a row does not have one expression per column.
+    pub fn values(schema: Schema<'static>, rows: Vec<Vec<Expression>>) -> std::io::Result<Self> {
+        if rows.iter().any(|row| row.len() != schema.columns().len()) {
+            return Err(invalid_input("Row does not match the schema"));
+        }
+        Ok(PlanNode::Values { schema, rows })
+    }
+
+    pub fn filter(self, predicate: Expression) -> Self {
+        PlanNode::Filter {
+            predicate,
+            child: Box::new(self),
+        }
+    }
+
+    // Computes |expressions| for every tuple, as the columns named |names|.
+    // Returns |InvalidInput| if the names do not match the expressions, or an
+    // expression references a column that does not exist.
+    pub fn project(self, expressions: Vec<Expression>, names: &[&str]) -> std::io::Result<Self> {
+        if expressions.len() != names.len() {
+            return Err(invalid_input("Projection needs one name per expression"));
+        }
+        let input = self.output_schema();
+        let columns = expressions
+            .iter()
+            .zip(names.iter())
+            .map(|(expression, name)| expression.output_column(&input, name))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(PlanNode::Projection {
+            expressions,
+            schema: Schema::new(columns),
+            child: Box::new(self),
+        })
+    }
+
+    // Computes |aggregates| over all tuples, as the columns named |names|.
+    // Returns |InvalidInput| if the names do not match the aggregates, or an
+    // expression references a column that does not exist.
+    pub fn aggregate(
+        self,
+        aggregates: Vec<(AggregateFunction, Expression)>,
+        names: &[&str],
+    ) -> std::io::Result<Self> {
+        if aggregates.len() != names.len() {
+            return Err(invalid_input("Aggregate needs one name per aggregate"));
+        }
+        let input = self.output_schema();
+        for (_, expression) in aggregates.iter() {
+            expression.output_column(&input, "Aggregate")?;
+        }
+        let columns = names
+            .iter()
+            .map(|name| Column::new(name.to_string(), Types::bigint(), 8))
+            .collect();
+        Ok(PlanNode::Aggregate {
+            aggregates,
+            schema: Schema::new(columns),
+            child: Box::new(self),
+        })
+    }
+
+    // Returns |InvalidInput| if an expression references a column that does
+    // not exist.
+    pub fn sort(self, order_bys: Vec<(Expression, OrderDirection)>) -> std::io::Result<Self> {
+        let input = self.output_schema();
+        for (expression, _) in order_bys.iter() {
+            expression.output_column(&input, "Key")?;
+        }
+        Ok(PlanNode::Sort {
+            order_bys,
+            child: Box::new(self),
+        })
+    }
+
+    // Limiting a Sort makes a TopN.
+    pub fn limit(self, limit: usize) -> Self {
+        match self {
+            PlanNode::Sort { order_bys, child } => PlanNode::TopN {
+                order_bys,
+                limit,
+                child,
+            },
+            _ => PlanNode::Limit {
+                limit,
+                child: Box::new(self),
+            },
+        }
+    }
+
+    // Joins with |right| on |left_key| = |right_key|, which are evaluated
+    // against the tuples of |self| and |right| respectively. Returns
+    // |InvalidInput| if a key references a column that does not exist, or the
+    // keys cannot be compared.
+    pub fn hash_join(
+        self,
+        right: PlanNode,
+        left_key: Expression,
+        right_key: Expression,
+    ) -> std::io::Result<Self> {
+        let left_schema = self.output_schema();
+        let right_schema = right.output_schema();
+        let lhs = left_key.output_column(&left_schema, "Key")?;
+        let rhs = right_key.output_column(&right_schema, "Key")?;
+        if !comparable(lhs.types(), rhs.types()) {
+            return Err(invalid_input("Join keys cannot be compared"));
+        }
+        Ok(PlanNode::HashJoin {
+            left_key,
+            right_key,
+            schema: join_schema(&left_schema, &right_schema),
+            left: Box::new(self),
+            right: Box::new(right),
+        })
+    }
+
+    // Joins with |right| on |predicate|, which is evaluated against the
+    // columns of |self| followed by those of |right|. Returns |InvalidInput|
+    // if the predicate references a column that does not exist, or |right|
+    // has a column that cannot be padded with null for a left outer join.
+    pub fn nested_loop_join(
+        self,
+        right: PlanNode,
+        predicate: Expression,
+        join_type: JoinType,
+    ) -> std::io::Result<Self> {
+        let right_schema = right.output_schema();
+        check_padding(join_type, &right_schema)?;
+        let schema = join_schema(&self.output_schema(), &right_schema);
+        predicate.output_column(&schema, "Predicate")?;
+        Ok(PlanNode::NestedLoopJoin {
+            join_type,
+            predicate,
+            schema,
+            left: Box::new(self),
+            right: Box::new(right),
+        })
+    }
+
+    // Joins with the table of the index |index_name|, looking up |left_key|,
+    // which is evaluated against the tuples of |self|, in the index. Returns
+    // |NotFound| if there is no such index, and |InvalidInput| if the key is
+    // not an integer, or the table has a column that cannot be padded with
+    // null for a left outer join.
+    pub fn index_join(
+        self,
+        catalog: &Catalog,
+        index_name: &str,
+        left_key: Expression,
+        join_type: JoinType,
+    ) -> std::io::Result<Self> {
+        let index = catalog
+            .index(index_name)
+            .ok_or_else(|| not_found(&format!("No such index; name = {}", index_name)))?;
+        let table = catalog.table_by_oid(index.table_oid()).unwrap();
+        check_padding(join_type, table.schema())?;
+        let left_schema = self.output_schema();
+        let key = left_key.output_column(&left_schema, "Key")?;
+        if !(2..=5).contains(&key.types().id()) {
+            return Err(invalid_input("Index key is not an integer"));
+        }
+        Ok(PlanNode::IndexJoin {
+            join_type,
+            left_key,
+            index_name: index_name.to_string(),
+            table_oid: table.oid(),
+            schema: join_schema(&left_schema, table.schema()),
+            left: Box::new(self),
+        })
+    }
+
+    // Inserts the tuples produced by |child| into the table |table_name|.
+    // Returns |InvalidInput| if they do not have a value of a compatible type
+    // for every column of the table.
+    pub fn insert(catalog: &Catalog, table_name: &str, child: PlanNode) -> std::io::Result<Self> {
+        let table = find_table(catalog, table_name)?;
+        let input = child.output_schema();
+        let columns = table.schema().columns();
+        if input.columns().len() != columns.len() {
+            return Err(invalid_input("Insert does not supply every column"));
+        }
+        for (input, column) in input.columns().iter().zip(columns.iter()) {
+            if !input.types().is_coercable_to(column.types()) {
+                return Err(invalid_input(&format!(
+                    "Value type does not match; column = {}",
+                    column.name()
+                )));
+            }
+        }
+        Ok(PlanNode::Insert {
+            table_oid: table.oid(),
+            on_conflict: None,
+            child: Box::new(child),
+        })
+    }
+
+    // Inserts the tuples produced by |child| into the table |table_name|, and
+    // updates the tuple a tuple conflicts with on the column |column_name|
+    // instead; see |OnConflict|. |assignments| map column names to the
+    // expressions computing their new values. Returns |InvalidInput| if the
+    // insert is invalid, the column has no index, or an assignment does not
+    // match a column.
+    pub fn upsert(
+        catalog: &Catalog,
+        table_name: &str,
+        column_name: &str,
+        assignments: Vec<(&str, Expression)>,
+        child: PlanNode,
+    ) -> std::io::Result<Self> {
+        let insert = PlanNode::insert(catalog, table_name, child)?;
+        let table = find_table(catalog, table_name)?;
+        let schema = table.schema();
+        let index = schema
+            .column_idx(column_name)
+            .and_then(|idx| {
+                catalog
+                    .table_indexes(table.oid())
+                    .into_iter()
+                    .find(|index| index.key_column() == idx)
+            })
+            .ok_or_else(|| {
+                invalid_input(&format!("Column has no index; name = {}", column_name))
+            })?;
+        let input = conflict_schema(schema);
+        let on_conflict = OnConflict {
+            index_name: index.name().to_string(),
+            assignments: resolve_assignments(schema, &input, assignments)?,
+        };
+        match insert {
+            PlanNode::Insert {
+                table_oid, child, ..
+            } => Ok(PlanNode::Insert {
+                table_oid,
+                on_conflict: Some(on_conflict),
+                child,
+            }),
+            _ => unreachable!(),
+        }
+    }
+
+    // Returns |InvalidInput| if |child| does not read from the table.
+    pub fn delete(catalog: &Catalog, table_name: &str, child: PlanNode) -> std::io::Result<Self> {
+        let table = find_table(catalog, table_name)?;
+        if child.source_table() != Some(table.oid()) {
+            return Err(invalid_input("Delete needs to read from the table"));
+        }
+        Ok(PlanNode::Delete {
+            table_oid: table.oid(),
+            child: Box::new(child),
+        })
+    }
+
+    // |assignments| map column names to the expressions computing their new
+    // values from the old tuple. Returns |InvalidInput| if |child| does not
+    // read from the table, or an assignment does not match a column.
+    pub fn update(
+        catalog: &Catalog,
+        table_name: &str,
+        assignments: Vec<(&str, Expression)>,
+        child: PlanNode,
+    ) -> std::io::Result<Self> {
+        let table = find_table(catalog, table_name)?;
+        if child.source_table() != Some(table.oid()) {
+            return Err(invalid_input("Update needs to read from the table"));
+        }
+        let schema = table.schema();
+        Ok(PlanNode::Update {
+            table_oid: table.oid(),
+            assignments: resolve_assignments(schema, schema, assignments)?,
+            child: Box::new(child),
+        })
+    }
+
+    pub fn output_schema(&self) -> Schema<'static> {
+        match self {
+            PlanNode::SeqScan { schema, .. }
+            | PlanNode::IndexScan { schema, .. }
+            | PlanNode::Values { schema, .. }
+            | PlanNode::Projection { schema, .. }
+            | PlanNode::Aggregate { schema, .. }
+            | PlanNode::HashJoin { schema, .. }
+            | PlanNode::NestedLoopJoin { schema, .. }
+            | PlanNode::IndexJoin { schema, .. } => schema.clone(),
+            PlanNode::Filter { child, .. }
+            | PlanNode::Sort { child, .. }
+            | PlanNode::Limit { child, .. }
+            | PlanNode::TopN { child, .. } => child.output_schema(),
+            PlanNode::Insert { .. } | PlanNode::Delete { .. } | PlanNode::Update { .. } => {
+                Schema::new(vec![Column::new("Count".to_string(), Types::bigint(), 8)])
+            }
+        }
+    }
+
+    pub fn children(&self) -> Vec<&PlanNode> {
+        match self {
+            PlanNode::SeqScan { .. } | PlanNode::IndexScan { .. } | PlanNode::Values { .. } => {
+                Vec::new()
+            }
+            PlanNode::Filter { child, .. }
+            | PlanNode::Projection { child, .. }
+            | PlanNode::Aggregate { child, .. }
+            | PlanNode::Sort { child, .. }
+            | PlanNode::Limit { child, .. }
+            | PlanNode::TopN { child, .. }
+            | PlanNode::IndexJoin { left: child, .. }
+            | PlanNode::Insert { child, .. }
+            | PlanNode::Delete { child, .. }
+            | PlanNode::Update { child, .. } => vec![child.as_ref()],
+            PlanNode::HashJoin { left, right, .. }
+            | PlanNode::NestedLoopJoin { left, right, .. } => vec![left.as_ref(), right.as_ref()],
+        }
+    }
+
+    // Returns the table whose tuples this node produces unchanged (possibly
+    // filtered), if any.
+    pub(crate) fn source_table(&self) -> Option<Oid> {
+        match self {
+            PlanNode::SeqScan { table_oid, .. } | PlanNode::IndexScan { table_oid, .. } => {
+                Some(*table_oid)
+            }
+            PlanNode::Filter { child, .. } | PlanNode::Limit { child, .. } => child.source_table(),
+            _ => None,
+        }
+    }
+}
+
+fn find_table<'a>(catalog: &'a Catalog, table_name: &str) -> std::io::Result<&'a TableInfo> {
+    catalog
+        .table(table_name)
+        .ok_or_else(|| not_found(&format!("No such table; name = {}", table_name)))
+}
+
+// Returns the schema |OnConflict| assignments are evaluated against: the
+// columns of |schema| followed by those of the proposed tuple, whose names
+// are prefixed with "excluded.".
+pub fn conflict_schema(schema: &Schema) -> Schema<'static> {
+    let excluded = schema
+        .columns()
+        .iter()
+        .map(|column| {
+            let name = format!("excluded.{}", column.name());
+            Column::new(name, column.types().to_static(), column.len())
+        })
+        .collect();
+    join_schema(schema, &Schema::new(excluded))
+}
+
+// Returns the indices of the columns of |schema| |assignments| set, with the
+// expressions computing their values from tuples of |input|. Returns
+// |InvalidInput| if an assignment does not match a column.
+fn resolve_assignments(
+    schema: &Schema,
+    input: &Schema,
+    assignments: Vec<(&str, Expression)>,
+) -> std::io::Result<Vec<(usize, Expression)>> {
+    let mut resolved = Vec::new();
+    for (name, expression) in assignments {
+        let idx = schema
+            .column_idx(name)
+            .ok_or_else(|| invalid_input(&format!("Column not found; name = {}", name)))?;
+        let column = expression.output_column(input, name)?;
+        if !column
+            .types()
+            .is_coercable_to(schema.nth_types(idx).unwrap())
+        {
+            return Err(invalid_input(&format!(
+                "Value type does not match; column = {}",
+                name
+            )));
+        }
+        resolved.push((idx, expression));
+    }
+    Ok(resolved)
+}
+
+// Returns whether values of |lhs| and |rhs| can be tested for equality, i.e.
+// both are numeric or they have the same type.
+fn comparable(lhs: &Types, rhs: &Types) -> bool {
+    let numeric = |types: &Types| (2..=6).contains(&types.id());
+    (numeric(lhs) && numeric(rhs)) || lhs.id() == rhs.id()
+}
+
+// Returns |InvalidInput| if a left outer join cannot pad the columns of
+// |right| with null, as Varchar has no null value.
+fn check_padding(join_type: JoinType, right: &Schema) -> std::io::Result<()> {
+    if join_type == JoinType::Inner {
+        return Ok(());
+    }
+    for column in right.columns().iter() {
+        if column.types().clone().null_val().is_err() {
+            return Err(invalid_input(&format!(
+                "Column cannot be null; column = {}",
+                column.name()
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Returns the schema of the columns of |left| followed by those of |right|.
+fn join_schema(left: &Schema, right: &Schema) -> Schema<'static> {
+    let columns = left
+        .columns()
+        .iter()
+        .chain(right.columns().iter())
+        .map(|column| {
+            let name = column.name().to_string();
+            Column::new(name, column.types().to_static(), column.len())
+        })
+        .collect();
+    Schema::new(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::page::table_page::TablePage;
+    use crate::plan::expression::ComparisonOp;
+    use crate::types::types::Str;
+    use crate::types::types::Varlen;
+
+    fn create_schema() -> Schema<'static> {
+        Schema::new(vec![
+            Column::new("Id".to_string(), Types::integer(), 4),
+            Column::new("Name".to_string(), Types::owned(), 10),
+        ])
+    }
+
+    fn name(s: &str) -> Expression {
+        Expression::constant(Types::Varchar(Varlen::Owned(Str::Val(s.to_string()))))
+    }
+
+    #[test]
+    fn build_plans() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(4);
+        let mut catalog = Catalog::create(&mut bpm).unwrap();
+        let oid = catalog
+            .create_table(&mut bpm, 1, "users", create_schema())
+            .unwrap();
+        assert!(PlanNode::seq_scan(&catalog, "items").is_err());
+
+        let scan = PlanNode::seq_scan(&catalog, "users").unwrap();
+        let schema = scan.output_schema();
+        let id = Expression::column(&schema, "Id").unwrap();
+        let predicate = Expression::Comparison(
+            ComparisonOp::Lt,
+            Box::new(id.clone()),
+            Box::new(Expression::constant(Types::Integer(10))),
+        );
+        let filter = scan.filter(predicate);
+        assert_eq!(1, filter.children().len());
+        let projection = filter
+            .clone()
+            .project(vec![id.clone(), predicate_of(&filter)], &["Id", "Small"])
+            .unwrap();
+        let output = projection.output_schema();
+        assert_eq!("Small", output.nth_column(1).unwrap().name());
+        assert_eq!(1, output.nth_types(1).unwrap().id());
+        assert!(filter
+            .clone()
+            .project(vec![Expression::Column(2)], &["X"])
+            .is_err());
+        assert!(filter.clone().project(vec![id.clone()], &[]).is_err());
+
+        let count = vec![(AggregateFunction::ApproxCountDistinct, id.clone())];
+        let aggregate = filter.clone().aggregate(count.clone(), &["Ids"]).unwrap();
+        let output = aggregate.output_schema();
+        assert_eq!("Ids", output.nth_column(0).unwrap().name());
+        assert_eq!(Types::bigint().id(), output.nth_types(0).unwrap().id());
+        assert!(filter.clone().aggregate(count, &[]).is_err());
+        let unknown = vec![(AggregateFunction::ApproxCountDistinct, Expression::Column(2))];
+        assert!(filter.clone().aggregate(unknown, &["X"]).is_err());
+
+        let values = PlanNode::values(
+            create_schema(),
+            vec![vec![Expression::constant(Types::Integer(1)), name("a")]],
+        )
+        .unwrap();
+        let insert = PlanNode::insert(&catalog, "users", values).unwrap();
+        assert_eq!(1, insert.output_schema().columns().len());
+        assert!(PlanNode::values(create_schema(), vec![vec![name("a")]]).is_err());
+        let names = PlanNode::values(
+            Schema::new(vec![Column::new("Id".to_string(), Types::point(), 16)]),
+            vec![],
+        )
+        .unwrap();
+        assert!(PlanNode::insert(&catalog, "users", names).is_err());
+
+        // Upserts need an index on the conflict column.
+        let values = insert.children()[0].clone();
+        let excluded = Expression::column(&conflict_schema(&create_schema()), "excluded.Name");
+        let assignments = vec![("Name", excluded.unwrap())];
+        let upsert = PlanNode::upsert(&catalog, "users", "Id", assignments.clone(), values.clone());
+        assert!(upsert.is_err());
+        catalog
+            .create_index(&mut bpm, 1, "users_id", "users", "Id")
+            .unwrap();
+        let upsert = PlanNode::upsert(&catalog, "users", "Id", assignments, values.clone());
+        match upsert.unwrap() {
+            PlanNode::Insert { on_conflict, .. } => {
+                let on_conflict = on_conflict.unwrap();
+                assert_eq!("users_id", on_conflict.index_name);
+                assert_eq!(1, on_conflict.assignments[0].0);
+                assert!(matches!(on_conflict.assignments[0].1, Expression::Column(3)));
+            }
+            plan => panic!("Unexpected plan: {:?}", plan),
+        }
+        let upsert = |column, assignments| {
+            PlanNode::upsert(&catalog, "users", column, assignments, values.clone())
+        };
+        assert!(upsert("Name", vec![]).is_err());
+        let point = Expression::constant(Types::Point(1.0, 2.0));
+        assert!(upsert("Id", vec![("Id", point)]).is_err());
+        assert!(upsert("Id", vec![("Id", Expression::Column(4))]).is_err());
+
+        let delete = PlanNode::delete(&catalog, "users", filter.clone()).unwrap();
+        assert!(matches!(delete, PlanNode::Delete { table_oid, .. } if table_oid == oid));
+        assert!(PlanNode::delete(&catalog, "users", projection).is_err());
+
+        let update = PlanNode::update(&catalog, "users", vec![("Name", name("b"))], filter.clone());
+        assert!(update.is_ok());
+        let update = PlanNode::update(&catalog, "users", vec![("Age", id.clone())], filter.clone());
+        assert!(update.is_err());
+        let point = Expression::constant(Types::Point(1.0, 2.0));
+        let update = PlanNode::update(&catalog, "users", vec![("Id", point)], filter.clone());
+        assert!(update.is_err());
+
+        // Varchar columns cannot be padded with null.
+        let scan = PlanNode::seq_scan(&catalog, "users").unwrap();
+        let yes = Expression::constant(Types::Boolean(1));
+        let join = filter.clone().nested_loop_join(scan.clone(), yes.clone(), JoinType::Inner);
+        assert_eq!(4, join.unwrap().output_schema().columns().len());
+        assert!(filter
+            .nested_loop_join(scan, yes, JoinType::LeftOuter)
+            .is_err());
+    }
+
+    fn predicate_of(plan: &PlanNode) -> Expression {
+        match plan {
+            PlanNode::Filter { predicate, .. } => predicate.clone(),
+            _ => panic!("Not a filter"),
+        }
+    }
+}
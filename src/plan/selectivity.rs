@@ -0,0 +1,214 @@
+// Functionality: Estimates the selectivity of predicates, i.e. the fraction of
+// the rows of a table they hold for, from the statistics ANALYZE computed for
+// it. Comparisons of a column with a constant are estimated by the histogram
+// of the column, or its number of distinct values for equality, and boolean
+// operators combine the estimates of their operands, assuming they are
+// independent. Predicates the statistics cannot estimate, or on tables never
+// analyzed, get fixed guesses.
+
+use crate::catalog::table_statistics::TableStatistics;
+use crate::plan::expression::ComparisonOp;
+use crate::plan::expression::Expression;
+use crate::types::types::Types;
+use crate::types::value::Value;
+use std::ops::Bound;
+
+// The guesses for predicates without estimates.
+const EQUALITY_GUESS: f64 = 0.1;
+const RANGE_GUESS: f64 = 1.0 / 3.0;
+const NULL_GUESS: f64 = 0.1;
+const DEFAULT_GUESS: f64 = 0.5;
+
+// Estimates the fraction of the rows of a table with |statistics| that hold
+// for |predicate|, whose columns are those of the table.
+pub fn selectivity(statistics: Option<&TableStatistics>, predicate: &Expression) -> f64 {
+    let estimate = match predicate {
+        Expression::Constant(value) => match (value.is_null(), value.borrow()) {
+            (false, Types::Boolean(1)) => 1.0,
+            _ => 0.0,
+        },
+        Expression::And(lhs, rhs) => selectivity(statistics, lhs) * selectivity(statistics, rhs),
+        Expression::Or(lhs, rhs) => {
+            let lhs = selectivity(statistics, lhs);
+            let rhs = selectivity(statistics, rhs);
+            lhs + rhs - lhs * rhs
+        }
+        Expression::Not(child) => 1.0 - selectivity(statistics, child),
+        Expression::IsNull(child) => match (statistics, child.as_ref()) {
+            (Some(statistics), Expression::Column(idx)) => {
+                statistics.null_fraction(*idx).unwrap_or(NULL_GUESS)
+            }
+            _ => NULL_GUESS,
+        },
+        Expression::Comparison(op, lhs, rhs) => comparison(statistics, *op, lhs, rhs),
+        _ => DEFAULT_GUESS,
+    };
+    estimate.clamp(0.0, 1.0)
+}
+
+// Estimates the fraction of the pairs of rows of two tables whose keys are
+// equal, from the equality selectivities of the keys, if known: every key
+// matches the rows of the other table with its value.
+pub fn join_selectivity(left: Option<f64>, right: Option<f64>) -> f64 {
+    match (left, right) {
+        (Some(left), Some(right)) => left.min(right),
+        (Some(known), None) | (None, Some(known)) => known,
+        (None, None) => EQUALITY_GUESS,
+    }
+}
+
+fn comparison(
+    statistics: Option<&TableStatistics>,
+    op: ComparisonOp,
+    lhs: &Expression,
+    rhs: &Expression,
+) -> f64 {
+    // The column goes on the left.
+    let (op, idx, value) = match (lhs, rhs) {
+        (Expression::Column(idx), Expression::Constant(value)) => (op, *idx, value),
+        (Expression::Constant(value), Expression::Column(idx)) => (mirror(op), *idx, value),
+        _ => return guess(op),
+    };
+    // Comparisons with null never hold.
+    if value.is_null() {
+        return 0.0;
+    }
+    let statistics = match statistics {
+        Some(statistics) => statistics,
+        None => return guess(op),
+    };
+    let range =
+        |low: Bound<&Value>, high: Bound<&Value>| statistics.range_selectivity(idx, low, high);
+    let estimate = match op {
+        ComparisonOp::Eq => statistics.equality_selectivity(idx),
+        ComparisonOp::Ne => statistics
+            .equality_selectivity(idx)
+            .zip(statistics.null_fraction(idx))
+            .map(|(equal, null)| 1.0 - null - equal),
+        ComparisonOp::Lt => range(Bound::Unbounded, Bound::Excluded(value)),
+        ComparisonOp::Le => range(Bound::Unbounded, Bound::Included(value)),
+        ComparisonOp::Gt => range(Bound::Excluded(value), Bound::Unbounded),
+        ComparisonOp::Ge => range(Bound::Included(value), Bound::Unbounded),
+    };
+    estimate.unwrap_or_else(|| guess(op))
+}
+
+// Returns the operator that holds for (rhs, lhs) when |op| holds for (lhs, rhs).
+fn mirror(op: ComparisonOp) -> ComparisonOp {
+    match op {
+        ComparisonOp::Lt => ComparisonOp::Gt,
+        ComparisonOp::Le => ComparisonOp::Ge,
+        ComparisonOp::Gt => ComparisonOp::Lt,
+        ComparisonOp::Ge => ComparisonOp::Le,
+        op => op,
+    }
+}
+
+fn guess(op: ComparisonOp) -> f64 {
+    match op {
+        ComparisonOp::Eq => EQUALITY_GUESS,
+        ComparisonOp::Ne => 1.0 - EQUALITY_GUESS,
+        _ => RANGE_GUESS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::catalog::catalog::Catalog;
+    use crate::catalog::column::Column;
+    use crate::catalog::schema::Schema;
+    use crate::page::table_page::TablePage;
+    use crate::table::tuple::Tuple;
+
+    fn constant(val: i64) -> Box<Expression> {
+        Box::new(Expression::Constant(Value::new(Types::BigInt(val))))
+    }
+
+    fn compare(op: ComparisonOp, idx: usize, val: i64) -> Expression {
+        Expression::Comparison(op, Box::new(Expression::Column(idx)), constant(val))
+    }
+
+    fn assert_near(expected: f64, actual: f64) {
+        assert!(
+            (expected - actual).abs() < 0.03,
+            "{} != {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn estimate() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(8);
+        let mut catalog = Catalog::create(&mut bpm).unwrap();
+        let schema = Schema::new(vec![
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("Group".to_string(), Types::bigint(), 8),
+        ]);
+        catalog.create_table(&mut bpm, 1, "t", schema).unwrap();
+        let table = catalog.table("t").unwrap();
+        let mut heap = table.heap();
+        // Ids 0..1000; groups 0..10, null for every other row.
+        for id in 0..1000 {
+            let group = match id % 2 {
+                0 => Value::new(Types::BigInt(id % 20 / 2)),
+                _ => Value::null(Types::bigint()),
+            };
+            let tuple = Tuple::new(&vec![Value::new(Types::BigInt(id)), group], table.schema());
+            heap.insert_tuple(&mut bpm, 1, tuple).unwrap();
+        }
+
+        // Without statistics, predicates get guesses.
+        assert_eq!(
+            EQUALITY_GUESS,
+            selectivity(None, &compare(ComparisonOp::Eq, 0, 5))
+        );
+        assert_eq!(
+            RANGE_GUESS,
+            selectivity(None, &compare(ComparisonOp::Lt, 0, 5))
+        );
+
+        let statistics = Some(catalog.analyze(&mut bpm, 1, "t").unwrap());
+        let estimate = |predicate: &Expression| selectivity(statistics, predicate);
+        assert_near(0.25, estimate(&compare(ComparisonOp::Lt, 0, 250)));
+        assert_near(0.75, estimate(&compare(ComparisonOp::Ge, 0, 250)));
+        assert_eq!(1.0, estimate(&compare(ComparisonOp::Le, 0, 5000)));
+        assert_eq!(0.0, estimate(&compare(ComparisonOp::Lt, 0, -1)));
+        // The constant on the left.
+        let mirrored = Expression::Comparison(
+            ComparisonOp::Gt,
+            constant(250),
+            Box::new(Expression::Column(0)),
+        );
+        assert_near(0.25, estimate(&mirrored));
+        // Nulls hold for no comparison.
+        assert_near(0.25, estimate(&compare(ComparisonOp::Lt, 1, 5)));
+        assert_near(0.05, estimate(&compare(ComparisonOp::Eq, 1, 5)));
+        assert_near(0.45, estimate(&compare(ComparisonOp::Ne, 1, 5)));
+        assert_eq!(
+            0.5,
+            estimate(&Expression::IsNull(Box::new(Expression::Column(1))))
+        );
+
+        let and = Expression::And(
+            Box::new(compare(ComparisonOp::Ge, 0, 100)),
+            Box::new(compare(ComparisonOp::Lt, 0, 300)),
+        );
+        assert_near(0.9 * 0.3, estimate(&and));
+        let or = Expression::Or(
+            Box::new(compare(ComparisonOp::Lt, 0, 100)),
+            Box::new(compare(ComparisonOp::Ge, 0, 900)),
+        );
+        assert_near(0.19, estimate(&or));
+        assert_near(
+            0.9,
+            estimate(&Expression::Not(Box::new(compare(
+                ComparisonOp::Lt,
+                0,
+                100,
+            )))),
+        );
+    }
+}
@@ -0,0 +1,488 @@
+// Functionality: The log manager assigns LSNs to log records and appends them
+// to the write-ahead log. Records are collected in an in-memory buffer and
+// written to the log file in groups, i.e. one write and sync covers all the
+// records buffered since the last flush.
+//
+// The write-ahead rule is enforced by the buffer pool manager: a dirty page is
+// only written to disk once the log is durable up to the page's LSN (see
+// |BufferPoolManager::set_log_manager|). A transaction is durable once the log
+// is flushed up to its commit record.
+//
+// The log is read back as a stream of records, see |LogReader|, rather than
+// loaded into memory at once. Once every change it describes is on disk, e.g.
+// when the buffer pool is closed, the log is truncated; see |truncate|.
+
+use crate::common::config::Lsn;
+use crate::common::config::TransactionId;
+use crate::common::config::INVALID_LSN;
+use crate::common::config::INVALID_TRANSACTION_ID;
+use crate::common::config::LOG_BUFFER_SIZE;
+use crate::common::reinterpret;
+use crate::logging::error_logging::ErrorLogging;
+use crate::recovery::log_record::LogRecord;
+use crate::recovery::log_record::LogRecordBody;
+use crate::recovery::log_record::LOG_RECORD_HEADER_SIZE;
+use log::info;
+use log::warn;
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::BufReader;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::ops::Drop;
+use std::path::PathBuf;
+
+// LSNs start from 1, so that a page that has never been written (i.e. reads
+// back all zeros) is older than every log record.
+pub const FIRST_LSN: Lsn = 1;
+
+// Appended to the path of the log to get the path of the log replacing it
+// during |LogManager::truncate|.
+const TRUNCATE_FILE_SUFFIX: &str = ".truncate";
+
+pub struct LogManager {
+    path: PathBuf,
+    log_io: File,
+    // Serialized records not yet written to |log_io|.
+    buffer: Vec<u8>,
+    buffer_size: usize,
+    next_lsn: Lsn,
+    // The LSN of the latest record known to be durable.
+    persistent_lsn: Lsn,
+    // The LSN of the latest record of every running transaction, which becomes
+    // the PrevLSN of its next record.
+    last_lsns: HashMap<TransactionId, Lsn>,
+}
+
+impl Drop for LogManager {
+    fn drop(&mut self) {
+        // Unable to handle I/O errors on destruction.
+        self.flush().log();
+    }
+}
+
+impl LogManager {
+    pub fn new(log_file: &str) -> std::io::Result<Self> {
+        Self::with_buffer_size(log_file, LOG_BUFFER_SIZE)
+    }
+
+    // Opens or creates the log file. LSNs continue after the last intact
+    // record; a torn tail left by a crash is truncated.
+    pub fn with_buffer_size(log_file: &str, buffer_size: usize) -> std::io::Result<Self> {
+        let log_io = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(log_file)?;
+        let mut reader = LogReader::new(log_io.try_clone()?);
+        let mut persistent_lsn = INVALID_LSN;
+        for record in reader.by_ref() {
+            persistent_lsn = record?.lsn();
+        }
+        let len = reader.offset();
+        if len < log_io.metadata()?.len() {
+            warn!(
+                "Truncating torn log tail; log_file = {}, len = {}",
+                log_file, len
+            );
+            log_io.set_len(len)?;
+        }
+        Ok(LogManager {
+            path: PathBuf::from(log_file),
+            log_io,
+            buffer: Vec::with_capacity(buffer_size),
+            buffer_size,
+            next_lsn: persistent_lsn.max(FIRST_LSN - 1) + 1,
+            persistent_lsn,
+            last_lsns: HashMap::new(),
+        })
+    }
+
+    // The LSN the next appended record will get.
+    pub fn next_lsn(&self) -> Lsn {
+        self.next_lsn
+    }
+
+    pub fn persistent_lsn(&self) -> Lsn {
+        self.persistent_lsn
+    }
+
+    // Assigns the next LSN to |record|, links it to the previous record of its
+    // transaction and appends it to the buffer. Flushes the buffer first if it
+    // cannot hold the record. Returns the LSN of the record.
+    pub fn append(&mut self, mut record: LogRecord) -> std::io::Result<Lsn> {
+        let lsn = self.next_lsn;
+        let txn_id = record.txn_id();
+        let prev_lsn = match record.body() {
+            LogRecordBody::Begin => INVALID_LSN,
+            _ => *self.last_lsns.get(&txn_id).unwrap_or(&INVALID_LSN),
+        };
+        record.set_lsn(lsn, prev_lsn);
+        match record.body() {
+            LogRecordBody::Commit | LogRecordBody::Abort => {
+                self.last_lsns.remove(&txn_id);
+            }
+            _ if txn_id != INVALID_TRANSACTION_ID => {
+                self.last_lsns.insert(txn_id, lsn);
+            }
+            _ => (),
+        }
+
+        let len = record.serialized_len();
+        if !self.buffer.is_empty() && self.buffer.len() + len > self.buffer_size {
+            self.flush()?;
+        }
+        let offset = self.buffer.len();
+        self.buffer.resize(offset + len, 0);
+        record.serialize_to(&mut self.buffer[offset..]);
+        self.next_lsn += 1;
+        Ok(lsn)
+    }
+
+    // Continues the PrevLSN chain of |txn_id| from |last_lsn|, e.g. for a
+    // transaction rolled back during recovery.
+    pub(crate) fn resume_txn(&mut self, txn_id: TransactionId, last_lsn: Lsn) {
+        self.last_lsns.insert(txn_id, last_lsn);
+    }
+
+    // Writes all buffered records to the log file and syncs it.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        info!("Flush log; next_lsn = {}", self.next_lsn);
+        // Readers share the file, and may have moved its cursor.
+        self.log_io.seek(SeekFrom::End(0))?;
+        self.log_io.write_all(&self.buffer)?;
+        self.log_io.sync_data()?;
+        self.buffer.clear();
+        self.persistent_lsn = self.next_lsn - 1;
+        Ok(())
+    }
+
+    // Makes the log durable up to |lsn|. This is a no-op if it already is, so
+    // that concurrent committers share one flush.
+    pub fn flush_until(&mut self, lsn: Lsn) -> std::io::Result<()> {
+        match lsn > self.persistent_lsn {
+            true => self.flush(),
+            false => Ok(()),
+        }
+    }
+
+    // Returns a reader of the durable records from the start of the log. It
+    // has a file handle of its own, so the log can be appended to meanwhile.
+    pub fn reader(&self) -> std::io::Result<LogReader> {
+        Ok(LogReader::new(File::open(&self.path)?))
+    }
+
+    // Returns all durable records in LSN order. Loads the whole log into
+    // memory; see |reader| to stream it.
+    pub fn records(&mut self) -> std::io::Result<Vec<LogRecord>> {
+        self.reader()?.collect()
+    }
+
+    // Replaces the log with a single checkpoint record, so that it does not
+    // grow without bound. The caller needs to ensure that every change logged
+    // is on disk, e.g. by flushing all dirty pages. Returns false, keeping the
+    // log, if a transaction is still running, since its changes may need to
+    // be undone.
+    //
+    // The checkpoint record carries the next LSN, so that LSNs keep increasing
+    // past those of the pages. It is written to a new file which replaces the
+    // log atomically, so that a crash leaves either log intact.
+    pub fn truncate(&mut self) -> std::io::Result<bool> {
+        self.flush()?;
+        if !self.last_lsns.is_empty() {
+            info!(
+                "Not truncating log; running transactions = {}",
+                self.last_lsns.len()
+            );
+            return Ok(false);
+        }
+        let mut record = LogRecord::new(INVALID_TRANSACTION_ID, LogRecordBody::Checkpoint);
+        record.set_lsn(self.next_lsn, INVALID_LSN);
+        let mut data = vec![0; record.serialized_len()];
+        record.serialize_to(&mut data);
+
+        let mut path = self.path.clone().into_os_string();
+        path.push(TRUNCATE_FILE_SUFFIX);
+        let mut log_io = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        log_io.write_all(&data)?;
+        log_io.sync_data()?;
+        fs::rename(&path, &self.path)?;
+        info!("Truncated log; lsn = {}", self.next_lsn);
+        self.log_io = log_io;
+        self.persistent_lsn = self.next_lsn;
+        self.next_lsn += 1;
+        Ok(true)
+    }
+}
+
+// Reads the intact records of a log one at a time, in LSN order. The records
+// end at the torn tail, if any, left by a crash.
+pub struct LogReader {
+    reader: BufReader<File>,
+    // The offset of the next record.
+    offset: u64,
+}
+
+impl LogReader {
+    fn new(log_io: File) -> Self {
+        LogReader {
+            reader: BufReader::new(log_io),
+            offset: 0,
+        }
+    }
+
+    // Opens the log file for reading only, e.g. to check a database opened
+    // read-only.
+    pub fn open(log_file: &str) -> std::io::Result<Self> {
+        Ok(Self::new(File::open(log_file)?))
+    }
+
+    // Whether the log describes no changes, i.e. holds nothing but a
+    // checkpoint, as after a clean close; see |LogManager::truncate|. Reads up
+    // to the first change.
+    pub fn is_clean(&mut self) -> std::io::Result<bool> {
+        for record in self.by_ref() {
+            if record?.body() != &LogRecordBody::Checkpoint {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // The offset of the next record, i.e. the length of the intact records
+    // read so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // Continues reading from |offset|, which needs to be the offset of a
+    // record; see |offset|.
+    pub fn seek(&mut self, offset: u64) -> std::io::Result<()> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.offset = offset;
+        Ok(())
+    }
+
+    fn read_record(&mut self) -> std::io::Result<Option<LogRecord>> {
+        let mut prefix = [0; 4];
+        if !read_fully(&mut self.reader, &mut prefix)? {
+            return Ok(None);
+        }
+        let size = reinterpret::read_u32(&prefix) as usize;
+        if size < LOG_RECORD_HEADER_SIZE {
+            return Ok(None);
+        }
+        let mut data = vec![0; size];
+        data[..4].copy_from_slice(&prefix);
+        if !read_fully(&mut self.reader, &mut data[4..])? {
+            return Ok(None);
+        }
+        match LogRecord::deserialize_from(&data) {
+            Ok(record) => {
+                self.offset += size as u64;
+                Ok(Some(record))
+            }
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+impl Iterator for LogReader {
+    type Item = std::io::Result<LogRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+// Fills |buffer|. Returns false if the log ends first.
+fn read_fully(reader: &mut BufReader<File>, buffer: &mut [u8]) -> std::io::Result<bool> {
+    match reader.read_exact(buffer) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::reinterpret;
+    use crate::common::rid::Rid;
+    use crate::table::tuple::Tuple;
+    use crate::testing::file_deleter::FileDeleter;
+    use std::fs;
+    use std::path::Path;
+
+    fn create_tuple(size: usize) -> Tuple {
+        let mut buffer = vec![0; size + 8];
+        reinterpret::write_u64(&mut buffer, size as u64);
+        let mut tuple = Tuple::default();
+        tuple.deserialize_from(&buffer);
+        tuple
+    }
+
+    fn insert(txn_id: TransactionId, slot_num: usize) -> LogRecord {
+        let body = LogRecordBody::Insert {
+            rid: Rid::new(1, slot_num),
+            tuple: create_tuple(100),
+        };
+        LogRecord::new(txn_id, body)
+    }
+
+    #[test]
+    fn append_and_flush() {
+        let file_path = "/tmp/testfile.log_manager.1.log";
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+
+        let mut log_mgr = LogManager::new(file_path).unwrap();
+        assert_eq!(1, log_mgr.next_lsn());
+        assert_eq!(INVALID_LSN, log_mgr.persistent_lsn());
+
+        // Records of one transaction are chained via PrevLSN.
+        let begin = LogRecord::new(1, LogRecordBody::Begin);
+        assert_eq!(1, log_mgr.append(begin).unwrap());
+        let begin = LogRecord::new(2, LogRecordBody::Begin);
+        assert_eq!(2, log_mgr.append(begin).unwrap());
+        assert_eq!(3, log_mgr.append(insert(1, 0)).unwrap());
+        assert_eq!(4, log_mgr.append(insert(2, 1)).unwrap());
+        let commit = LogRecord::new(1, LogRecordBody::Commit);
+        assert_eq!(5, log_mgr.append(commit).unwrap());
+
+        // Nothing is durable until the log is flushed, and one flush covers
+        // all buffered records.
+        assert_eq!(INVALID_LSN, log_mgr.persistent_lsn());
+        assert!(log_mgr.records().unwrap().is_empty());
+        log_mgr.flush_until(5).unwrap();
+        assert_eq!(5, log_mgr.persistent_lsn());
+        let records = log_mgr.records().unwrap();
+        let lsns: Vec<(Lsn, Lsn)> = records.iter().map(|r| (r.lsn(), r.prev_lsn())).collect();
+        assert_eq!(vec![(1, -1), (2, -1), (3, 1), (4, 2), (5, 3)], lsns);
+        assert_eq!(insert(2, 1).body(), records[3].body());
+
+        // Already durable.
+        log_mgr.flush_until(4).unwrap();
+        assert_eq!(5, log_mgr.records().unwrap().len());
+
+        // A full buffer is flushed on append.
+        drop(log_mgr);
+        let mut log_mgr = LogManager::with_buffer_size(file_path, 300).unwrap();
+        assert_eq!(6, log_mgr.next_lsn());
+        assert_eq!(6, log_mgr.append(insert(2, 2)).unwrap());
+        assert_eq!(7, log_mgr.append(insert(2, 3)).unwrap());
+        assert_eq!(5, log_mgr.persistent_lsn());
+        assert_eq!(8, log_mgr.append(insert(2, 4)).unwrap());
+        assert_eq!(7, log_mgr.persistent_lsn());
+    }
+
+    #[test]
+    fn truncate_torn_tail() {
+        let file_path = "/tmp/testfile.log_manager.2.log";
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+
+        let mut log_mgr = LogManager::new(file_path).unwrap();
+        for slot_num in 0..3 {
+            log_mgr.append(insert(1, slot_num)).unwrap();
+        }
+        drop(log_mgr);
+
+        // Simulates a crash in the middle of writing the last record.
+        let len = fs::metadata(file_path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(file_path)
+            .unwrap()
+            .set_len(len - 10)
+            .unwrap();
+
+        let mut log_mgr = LogManager::new(file_path).unwrap();
+        assert_eq!(2, log_mgr.persistent_lsn());
+        assert_eq!(3, log_mgr.append(insert(1, 3)).unwrap());
+        log_mgr.flush().unwrap();
+        let records = log_mgr.records().unwrap();
+        assert_eq!(3, records.len());
+        assert_eq!(insert(1, 3).body(), records[2].body());
+    }
+
+    #[test]
+    fn stream_and_truncate() {
+        let file_path = "/tmp/testfile.log_manager.3.log";
+        let truncate_path = file_path.to_string() + TRUNCATE_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&truncate_path);
+
+        let mut log_mgr = LogManager::new(file_path).unwrap();
+        log_mgr
+            .append(LogRecord::new(1, LogRecordBody::Begin))
+            .unwrap();
+        for slot_num in 0..3 {
+            log_mgr.append(insert(1, slot_num)).unwrap();
+        }
+        log_mgr.flush().unwrap();
+
+        // The reader streams the records, and can go back to any of them.
+        let mut reader = log_mgr.reader().unwrap();
+        let mut offsets = Vec::new();
+        loop {
+            let offset = reader.offset();
+            match reader.next() {
+                Some(record) => offsets.push((offset, record.unwrap().lsn())),
+                None => break,
+            }
+        }
+        assert_eq!(
+            vec![1, 2, 3, 4],
+            offsets.iter().map(|o| o.1).collect::<Vec<_>>()
+        );
+        assert_eq!(fs::metadata(file_path).unwrap().len(), reader.offset());
+        reader.seek(offsets[2].0).unwrap();
+        assert_eq!(3, reader.next().unwrap().unwrap().lsn());
+
+        // Transaction 1 is still running.
+        assert!(!log_mgr.reader().unwrap().is_clean().unwrap());
+        assert!(!log_mgr.truncate().unwrap());
+        assert_eq!(4, log_mgr.records().unwrap().len());
+
+        let commit = LogRecord::new(1, LogRecordBody::Commit);
+        assert_eq!(5, log_mgr.append(commit).unwrap());
+        assert!(log_mgr.truncate().unwrap());
+        assert!(!Path::new(&truncate_path).exists());
+        let records = log_mgr.records().unwrap();
+        assert_eq!(1, records.len());
+        assert_eq!(&LogRecordBody::Checkpoint, records[0].body());
+        assert_eq!(6, records[0].lsn());
+        assert_eq!(6, log_mgr.persistent_lsn());
+        assert!(LogReader::open(file_path).unwrap().is_clean().unwrap());
+
+        // LSNs keep increasing, also after reopening the log.
+        assert_eq!(7, log_mgr.append(insert(2, 0)).unwrap());
+        drop(log_mgr);
+        let mut log_mgr = LogManager::new(file_path).unwrap();
+        assert_eq!(8, log_mgr.next_lsn());
+        let lsns: Vec<Lsn> = log_mgr.records().unwrap().iter().map(|r| r.lsn()).collect();
+        assert_eq!(vec![6, 7], lsns);
+    }
+}
@@ -0,0 +1,431 @@
+// Functionality: Records of the write-ahead log. Every change to a table page
+// is described by a log record before the page may be written to disk; see
+// |LogManager|.
+//
+// Format (size in byte):
+//  -------------------------------------------------------------------------
+// | Size (4) | Checksum (4) | LSN (4) | TxnId (4) | PrevLSN (4) | Type (4) | ...
+//  -------------------------------------------------------------------------
+//
+// |Size| is the length of the whole record, and |Checksum| the CRC-32C over
+// everything after it, so that a record torn by a crash is detected when the
+// log is read back. |PrevLSN| links the records of one transaction backwards.
+// The payload depends on the record type:
+//
+//  Begin, Commit, Abort, Checkpoint: (empty)
+//  Insert, MarkDelete, ApplyDelete, RollbackDelete:
+//      | PageId (4) | SlotNum (4) | Tuple |
+//  Update: | PageId (4) | SlotNum (4) | OldTuple | NewTuple |
+//  NewPage: | PrevPageId (4) | PageId (4) |
+//  FreePage: | PrevPageId (4) | PageId (4) | NextPageId (4) |
+//  Compensation: | UndoNextLSN (4) | ActionType (4) | Action payload |
+//
+// Tuples are stored serialized (see |Tuple::serialize_to|).
+
+use crate::common::config::Lsn;
+use crate::common::config::PageId;
+use crate::common::config::TransactionId;
+use crate::common::config::INVALID_LSN;
+use crate::common::crc32c::crc32c;
+use crate::common::error::*;
+use crate::common::reinterpret;
+use crate::common::rid::Rid;
+use crate::table::tuple::Tuple;
+use crate::table::tuple::LEN_FLAGS_MASK;
+use std::mem;
+
+const CHECKSUM_OFFSET: usize = 4;
+const LSN_OFFSET: usize = 8;
+const TXN_ID_OFFSET: usize = 12;
+const PREV_LSN_OFFSET: usize = 16;
+const TYPE_OFFSET: usize = 20;
+
+pub const LOG_RECORD_HEADER_SIZE: usize = 24;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LogRecordBody {
+    Begin,
+    Commit,
+    Abort,
+    Insert {
+        rid: Rid,
+        tuple: Tuple,
+    },
+    // The tuple is marked as deleted; see |TablePage::mark_delete|.
+    MarkDelete {
+        rid: Rid,
+        tuple: Tuple,
+    },
+    // The tuple is removed for good; see |TablePage::apply_delete|.
+    ApplyDelete {
+        rid: Rid,
+        tuple: Tuple,
+    },
+    // The deletion mark is cleared; see |TablePage::rollback_delete|.
+    RollbackDelete {
+        rid: Rid,
+        tuple: Tuple,
+    },
+    Update {
+        rid: Rid,
+        old_tuple: Tuple,
+        new_tuple: Tuple,
+    },
+    // |page_id| is allocated and linked after |prev_page_id|.
+    NewPage {
+        prev_page_id: PageId,
+        page_id: PageId,
+    },
+    // |page_id| is unlinked from between |prev_page_id| and |next_page_id|,
+    // either of which may be invalid, and deleted; see |TableHeap::vacuum|.
+    FreePage {
+        prev_page_id: PageId,
+        page_id: PageId,
+        next_page_id: PageId,
+    },
+    // The first record of a log truncated after a clean shutdown, when no
+    // transaction was running and every change was on disk. Its LSN keeps the
+    // LSNs of the log increasing past those of the pages; see
+    // |LogManager::truncate|.
+    Checkpoint,
+    // A compensation log record (CLR), written when |action| undoes a change
+    // of the transaction. Undo continues at |undo_next_lsn|, so that a change
+    // is never undone twice, even if recovery itself crashes.
+    Compensation {
+        undo_next_lsn: Lsn,
+        action: Box<LogRecordBody>,
+    },
+}
+
+impl LogRecordBody {
+    fn type_id(&self) -> u32 {
+        match self {
+            LogRecordBody::Begin => 1,
+            LogRecordBody::Commit => 2,
+            LogRecordBody::Abort => 3,
+            LogRecordBody::Insert { .. } => 4,
+            LogRecordBody::MarkDelete { .. } => 5,
+            LogRecordBody::ApplyDelete { .. } => 6,
+            LogRecordBody::Update { .. } => 7,
+            LogRecordBody::NewPage { .. } => 8,
+            LogRecordBody::RollbackDelete { .. } => 9,
+            LogRecordBody::Compensation { .. } => 10,
+            LogRecordBody::Checkpoint => 11,
+            LogRecordBody::FreePage { .. } => 12,
+        }
+    }
+
+    fn payload_len(&self) -> usize {
+        let tuple_len = |tuple: &Tuple| tuple.len() + mem::size_of::<u64>();
+        match self {
+            LogRecordBody::Begin
+            | LogRecordBody::Commit
+            | LogRecordBody::Abort
+            | LogRecordBody::Checkpoint => 0,
+            LogRecordBody::Insert { tuple, .. }
+            | LogRecordBody::MarkDelete { tuple, .. }
+            | LogRecordBody::ApplyDelete { tuple, .. }
+            | LogRecordBody::RollbackDelete { tuple, .. } => 8 + tuple_len(tuple),
+            LogRecordBody::Update {
+                old_tuple,
+                new_tuple,
+                ..
+            } => 8 + tuple_len(old_tuple) + tuple_len(new_tuple),
+            LogRecordBody::NewPage { .. } => 8,
+            LogRecordBody::FreePage { .. } => 12,
+            LogRecordBody::Compensation { action, .. } => 8 + action.payload_len(),
+        }
+    }
+
+    fn serialize_payload(&self, dst: &mut [u8]) {
+        match self {
+            LogRecordBody::Begin
+            | LogRecordBody::Commit
+            | LogRecordBody::Abort
+            | LogRecordBody::Checkpoint => (),
+            LogRecordBody::Insert { rid, tuple }
+            | LogRecordBody::MarkDelete { rid, tuple }
+            | LogRecordBody::ApplyDelete { rid, tuple }
+            | LogRecordBody::RollbackDelete { rid, tuple } => {
+                write_rid(dst, rid);
+                tuple.serialize_to(&mut dst[8..]);
+            }
+            LogRecordBody::Update {
+                rid,
+                old_tuple,
+                new_tuple,
+            } => {
+                write_rid(dst, rid);
+                old_tuple.serialize_to(&mut dst[8..]);
+                new_tuple.serialize_to(&mut dst[(16 + old_tuple.len())..]);
+            }
+            LogRecordBody::NewPage {
+                prev_page_id,
+                page_id,
+            } => {
+                reinterpret::write_i32(dst, *prev_page_id);
+                reinterpret::write_i32(&mut dst[4..], *page_id);
+            }
+            LogRecordBody::FreePage {
+                prev_page_id,
+                page_id,
+                next_page_id,
+            } => {
+                reinterpret::write_i32(dst, *prev_page_id);
+                reinterpret::write_i32(&mut dst[4..], *page_id);
+                reinterpret::write_i32(&mut dst[8..], *next_page_id);
+            }
+            LogRecordBody::Compensation {
+                undo_next_lsn,
+                action,
+            } => {
+                reinterpret::write_i32(dst, *undo_next_lsn);
+                reinterpret::write_u32(&mut dst[4..], action.type_id());
+                action.serialize_payload(&mut dst[8..]);
+            }
+        }
+    }
+
+    fn deserialize_payload(type_id: u32, src: &[u8]) -> std::io::Result<Self> {
+        let body = match type_id {
+            1 => LogRecordBody::Begin,
+            2 => LogRecordBody::Commit,
+            3 => LogRecordBody::Abort,
+            4 => LogRecordBody::Insert {
+                rid: read_rid(src)?,
+                tuple: read_tuple(&src[8..])?,
+            },
+            5 => LogRecordBody::MarkDelete {
+                rid: read_rid(src)?,
+                tuple: read_tuple(&src[8..])?,
+            },
+            6 => LogRecordBody::ApplyDelete {
+                rid: read_rid(src)?,
+                tuple: read_tuple(&src[8..])?,
+            },
+            7 => {
+                let rid = read_rid(src)?;
+                let old_tuple = read_tuple(&src[8..])?;
+                let new_tuple = read_tuple(&src[(16 + old_tuple.len())..])?;
+                LogRecordBody::Update {
+                    rid,
+                    old_tuple,
+                    new_tuple,
+                }
+            }
+            8 if src.len() >= 8 => LogRecordBody::NewPage {
+                prev_page_id: reinterpret::read_i32(src),
+                page_id: reinterpret::read_i32(&src[4..]),
+            },
+            9 => LogRecordBody::RollbackDelete {
+                rid: read_rid(src)?,
+                tuple: read_tuple(&src[8..])?,
+            },
+            // A CLR never compensates another CLR.
+            10 if src.len() >= 8 && reinterpret::read_u32(&src[4..]) != 10 => {
+                let action_type_id = reinterpret::read_u32(&src[4..]);
+                LogRecordBody::Compensation {
+                    undo_next_lsn: reinterpret::read_i32(src),
+                    action: Box::new(Self::deserialize_payload(action_type_id, &src[8..])?),
+                }
+            }
+            11 => LogRecordBody::Checkpoint,
+            12 if src.len() >= 12 => LogRecordBody::FreePage {
+                prev_page_id: reinterpret::read_i32(src),
+                page_id: reinterpret::read_i32(&src[4..]),
+                next_page_id: reinterpret::read_i32(&src[8..]),
+            },
+            _ => return Err(invalid_data("Log record type is invalid")),
+        };
+        Ok(body)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRecord {
+    lsn: Lsn,
+    prev_lsn: Lsn,
+    txn_id: TransactionId,
+    body: LogRecordBody,
+}
+
+impl LogRecord {
+    // Creates a record of |txn_id|. Its LSN and PrevLSN are assigned when the
+    // record is appended to the log; see |LogManager::append|.
+    pub fn new(txn_id: TransactionId, body: LogRecordBody) -> Self {
+        LogRecord {
+            lsn: INVALID_LSN,
+            prev_lsn: INVALID_LSN,
+            txn_id,
+            body,
+        }
+    }
+
+    pub fn lsn(&self) -> Lsn {
+        self.lsn
+    }
+
+    pub fn prev_lsn(&self) -> Lsn {
+        self.prev_lsn
+    }
+
+    pub fn txn_id(&self) -> TransactionId {
+        self.txn_id
+    }
+
+    pub fn body(&self) -> &LogRecordBody {
+        &self.body
+    }
+
+    pub(crate) fn set_lsn(&mut self, lsn: Lsn, prev_lsn: Lsn) {
+        self.lsn = lsn;
+        self.prev_lsn = prev_lsn;
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        LOG_RECORD_HEADER_SIZE + self.body.payload_len()
+    }
+
+    // The caller needs to ensure that |dst| has |self.serialized_len()| bytes.
+    pub fn serialize_to(&self, dst: &mut [u8]) {
+        let size = self.serialized_len();
+        reinterpret::write_u32(dst, size as u32);
+        reinterpret::write_i32(&mut dst[LSN_OFFSET..], self.lsn);
+        reinterpret::write_i32(&mut dst[TXN_ID_OFFSET..], self.txn_id);
+        reinterpret::write_i32(&mut dst[PREV_LSN_OFFSET..], self.prev_lsn);
+        reinterpret::write_u32(&mut dst[TYPE_OFFSET..], self.body.type_id());
+        self.body
+            .serialize_payload(&mut dst[LOG_RECORD_HEADER_SIZE..]);
+        let checksum = crc32c(&dst[LSN_OFFSET..size]);
+        reinterpret::write_u32(&mut dst[CHECKSUM_OFFSET..], checksum);
+    }
+
+    // Deserializes the record at the start of |src|. Returns |InvalidData| if
+    // |src| does not start with a complete, intact record, e.g. at the torn
+    // tail of a log.
+    pub fn deserialize_from(src: &[u8]) -> std::io::Result<Self> {
+        if src.len() < LOG_RECORD_HEADER_SIZE {
+            return Err(invalid_data("Log record is truncated"));
+        }
+        let size = reinterpret::read_u32(src) as usize;
+        if size < LOG_RECORD_HEADER_SIZE || size > src.len() {
+            return Err(invalid_data("Log record is truncated"));
+        }
+        if reinterpret::read_u32(&src[CHECKSUM_OFFSET..]) != crc32c(&src[LSN_OFFSET..size]) {
+            return Err(invalid_data("Log record checksum mismatch"));
+        }
+        let type_id = reinterpret::read_u32(&src[TYPE_OFFSET..]);
+        let body = LogRecordBody::deserialize_payload(type_id, &src[LOG_RECORD_HEADER_SIZE..size])?;
+        Ok(LogRecord {
+            lsn: reinterpret::read_i32(&src[LSN_OFFSET..]),
+            prev_lsn: reinterpret::read_i32(&src[PREV_LSN_OFFSET..]),
+            txn_id: reinterpret::read_i32(&src[TXN_ID_OFFSET..]),
+            body,
+        })
+    }
+}
+
+fn write_rid(dst: &mut [u8], rid: &Rid) {
+    reinterpret::write_i32(dst, rid.page_id());
+    reinterpret::write_u32(&mut dst[4..], rid.slot_num() as u32);
+}
+
+fn read_rid(src: &[u8]) -> std::io::Result<Rid> {
+    if src.len() < 8 {
+        return Err(invalid_data("Log record is truncated"));
+    }
+    Ok(Rid::new(
+        reinterpret::read_i32(src),
+        reinterpret::read_u32(&src[4..]) as usize,
+    ))
+}
+
+fn read_tuple(src: &[u8]) -> std::io::Result<Tuple> {
+    let prefix = mem::size_of::<u64>();
+    if src.len() < prefix
+        || src.len() - prefix < (reinterpret::read_u64(src) & !LEN_FLAGS_MASK) as usize
+    {
+        return Err(invalid_data("Log record is truncated"));
+    }
+    let mut tuple = Tuple::default();
+    tuple.deserialize_from(src);
+    Ok(tuple)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::config::INVALID_PAGE_ID;
+
+    fn create_tuple(content: &str) -> Tuple {
+        let mut buffer = vec![0; content.len() + mem::size_of::<u64>()];
+        reinterpret::write_u64(&mut buffer, content.len() as u64);
+        buffer[mem::size_of::<u64>()..].copy_from_slice(content.as_bytes());
+        let mut tuple = Tuple::default();
+        tuple.deserialize_from(&buffer);
+        tuple
+    }
+
+    #[test]
+    fn serialize_and_deserialize() {
+        let bodies = vec![
+            LogRecordBody::Begin,
+            LogRecordBody::Commit,
+            LogRecordBody::Abort,
+            LogRecordBody::Checkpoint,
+            LogRecordBody::Insert {
+                rid: Rid::new(3, 1),
+                tuple: create_tuple("hello"),
+            },
+            LogRecordBody::MarkDelete {
+                rid: Rid::new(3, 1),
+                tuple: create_tuple("hello"),
+            },
+            LogRecordBody::ApplyDelete {
+                rid: Rid::new(3, 1),
+                tuple: create_tuple(""),
+            },
+            LogRecordBody::Update {
+                rid: Rid::new(4, 7),
+                old_tuple: create_tuple("hello"),
+                new_tuple: create_tuple("world!"),
+            },
+            LogRecordBody::NewPage {
+                prev_page_id: 2,
+                page_id: 5,
+            },
+            LogRecordBody::RollbackDelete {
+                rid: Rid::new(3, 1),
+                tuple: create_tuple("hello"),
+            },
+            LogRecordBody::FreePage {
+                prev_page_id: 2,
+                page_id: 5,
+                next_page_id: INVALID_PAGE_ID,
+            },
+            LogRecordBody::Compensation {
+                undo_next_lsn: 4,
+                action: Box::new(LogRecordBody::Update {
+                    rid: Rid::new(4, 7),
+                    old_tuple: create_tuple("world!"),
+                    new_tuple: create_tuple("hello"),
+                }),
+            },
+        ];
+        for (i, body) in bodies.into_iter().enumerate() {
+            let mut record = LogRecord::new(7, body);
+            record.set_lsn(i as Lsn + 10, i as Lsn + 9);
+            let mut buffer = vec![0; record.serialized_len() + 3];
+            record.serialize_to(&mut buffer);
+            assert_eq!(record, LogRecord::deserialize_from(&buffer).unwrap());
+
+            // A torn or corrupted record is rejected.
+            let len = record.serialized_len();
+            assert!(LogRecord::deserialize_from(&buffer[..(len - 1)]).is_err());
+            buffer[len - 1] ^= 1;
+            assert!(LogRecord::deserialize_from(&buffer).is_err());
+        }
+        assert!(LogRecord::deserialize_from(&[0; 8]).is_err());
+        assert!(LogRecord::deserialize_from(&[0; 64]).is_err());
+    }
+}
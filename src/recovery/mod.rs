@@ -0,0 +1,3 @@
+pub mod log_manager;
+pub mod log_record;
+pub mod recovery_manager;
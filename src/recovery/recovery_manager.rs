@@ -0,0 +1,659 @@
+// Functionality: ARIES-style crash recovery. Before the database serves any
+// request, the recovery manager brings the table pages back to a consistent
+// state from the write-ahead log, in three passes:
+//
+//  1. Analysis scans the log and rebuilds the active transaction table (the
+//     transactions without a commit or abort record, with their latest LSN)
+//     and the dirty page table (every page changed by the log, with the first
+//     LSN that may be missing on disk, i.e. its recLSN).
+//  2. Redo repeats history: every change newer than the LSN of its page is
+//     applied again, including the changes of uncommitted transactions and
+//     compensation log records (CLRs).
+//  3. Undo rolls back the losers, i.e. the transactions still active, newest
+//     change first. Every undone change is logged as a CLR pointing at the
+//     next change to undo, so that a crash during recovery never undoes a
+//     change twice. Each loser ends with an abort record.
+//
+// Pages deleted by a table heap are logged as freed, see |TableHeap::vacuum|.
+// Redo deallocates them again, and skips the changes of a page older than its
+// latest free, since the page may have been reused as an overflow page, which
+// is not logged.
+//
+// The log is streamed by every pass rather than loaded into memory; only the
+// offset of every record is kept, for undo to read the records it needs.
+// Checkpoints are only written when the log is truncated, see
+// |LogManager::truncate|, so analysis always starts at the beginning of the
+// log.
+
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::common::config::Lsn;
+use crate::common::config::PageId;
+use crate::common::config::TransactionId;
+use crate::common::config::INVALID_LSN;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::config::INVALID_TRANSACTION_ID;
+use crate::common::error::*;
+use crate::disk::storage_backend::StorageBackend;
+use crate::page::page::Page;
+use crate::page::table_page::TablePage;
+use crate::recovery::log_manager::LogManager;
+use crate::recovery::log_manager::LogReader;
+use crate::recovery::log_record::LogRecord;
+use crate::recovery::log_record::LogRecordBody;
+use log::info;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct RecoveryStats {
+    // The number of page changes redone.
+    pub redone: usize,
+    // The number of changes undone, i.e. CLRs written.
+    pub undone: usize,
+    // The transactions rolled back, in ascending order.
+    pub losers: Vec<TransactionId>,
+}
+
+pub struct RecoveryManager {
+    reader: LogReader,
+    // The offset of every record in the log by LSN.
+    offsets: HashMap<Lsn, u64>,
+    // The latest LSN of every transaction without a commit or abort record.
+    active_txns: HashMap<TransactionId, Lsn>,
+    // The recLSN of every page changed by the log.
+    dirty_pages: HashMap<PageId, Lsn>,
+    // The LSN of the latest free of every page freed by the log.
+    freed_pages: HashMap<PageId, Lsn>,
+}
+
+impl RecoveryManager {
+    // Reads the durable log of the log manager attached to |bpm| and runs the
+    // analysis pass. Returns |InvalidInput| if there is no log manager.
+    pub fn new<S: StorageBackend>(
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<Self> {
+        let reader = log_manager(bpm)?.reader()?;
+        let mut recovery_mgr = RecoveryManager {
+            reader,
+            offsets: HashMap::new(),
+            active_txns: HashMap::new(),
+            dirty_pages: HashMap::new(),
+            freed_pages: HashMap::new(),
+        };
+        recovery_mgr.analyze()?;
+        Ok(recovery_mgr)
+    }
+
+    // Rolls back the running transaction |txn_id| the way recovery rolls back
+    // a loser, from the log of the log manager attached to |bpm|, and logs its
+    // abort. Returns the number of changes undone.
+    pub fn roll_back<S: StorageBackend>(
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+    ) -> std::io::Result<usize> {
+        log_manager(bpm)?.flush()?;
+        let mut recovery_mgr = Self::new(bpm)?;
+        recovery_mgr.active_txns.retain(|&id, _| id == txn_id);
+        let (undone, _) = recovery_mgr.undo(bpm)?;
+        log_manager(bpm)?.flush()?;
+        info!("Rolled back; txn_id = {}, undone = {}", txn_id, undone);
+        Ok(undone)
+    }
+
+    // Rolls back the running transaction |txn_id| from the undo log of |bpm|,
+    // which has no log manager; see |BufferPoolManager::enable_undo_log|.
+    // Returns the number of changes undone.
+    pub fn roll_back_unlogged<S: StorageBackend>(
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+    ) -> std::io::Result<usize> {
+        let mut undone = 0;
+        for body in bpm.take_undo_log(txn_id).iter().rev() {
+            if let Some(action) = undo_of(body) {
+                let page_id = pages_of(&action)[0];
+                let result = apply(bpm.fetch_page(page_id)?, &action);
+                bpm.unpin_page(page_id, /*is_dirty=*/ true)?;
+                result?;
+                undone += 1;
+            }
+        }
+        info!("Rolled back; txn_id = {}, undone = {}", txn_id, undone);
+        Ok(undone)
+    }
+
+    pub fn active_txns(&self) -> &HashMap<TransactionId, Lsn> {
+        &self.active_txns
+    }
+
+    pub fn dirty_pages(&self) -> &HashMap<PageId, Lsn> {
+        &self.dirty_pages
+    }
+
+    // Runs the redo and undo passes, and makes the log durable.
+    pub fn recover<S: StorageBackend>(
+        mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<RecoveryStats> {
+        let redone = self.redo(bpm)?;
+        let (undone, losers) = self.undo(bpm)?;
+        log_manager(bpm)?.flush()?;
+        info!(
+            "Recovered; redone = {}, undone = {}, losers = {:?}",
+            redone, undone, losers
+        );
+        Ok(RecoveryStats {
+            redone,
+            undone,
+            losers,
+        })
+    }
+
+    fn analyze(&mut self) -> std::io::Result<()> {
+        loop {
+            let offset = self.reader.offset();
+            let record = match self.reader.next() {
+                Some(record) => record?,
+                None => return Ok(()),
+            };
+            let lsn = record.lsn();
+            self.offsets.insert(lsn, offset);
+            match record.body() {
+                LogRecordBody::Commit | LogRecordBody::Abort => {
+                    self.active_txns.remove(&record.txn_id());
+                }
+                _ if record.txn_id() != INVALID_TRANSACTION_ID => {
+                    self.active_txns.insert(record.txn_id(), lsn);
+                }
+                _ => (),
+            }
+            if let LogRecordBody::FreePage { page_id, .. } = record.body() {
+                self.freed_pages.insert(*page_id, lsn);
+            }
+            for page_id in pages_of(record.body()) {
+                self.dirty_pages.entry(page_id).or_insert(lsn);
+            }
+        }
+    }
+
+    // Returns the number of page changes redone.
+    fn redo<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<usize> {
+        let start = match self.dirty_pages.values().min() {
+            Some(&lsn) => lsn,
+            None => return Ok(0),
+        };
+        let mut redone = 0;
+        self.reader.seek(self.offsets[&start])?;
+        for record in self.reader.by_ref() {
+            let record = record?;
+            let lsn = record.lsn();
+            for page_id in pages_of(record.body()) {
+                if self.dirty_pages[&page_id] > lsn
+                    || matches!(self.freed_pages.get(&page_id), Some(&freed) if freed > lsn)
+                {
+                    continue;
+                }
+                if let LogRecordBody::NewPage { .. } = record.body() {
+                    // The allocation may not have reached the bitmap.
+                    bpm.reserve_page(page_id)?;
+                }
+                let page = bpm.fetch_page(page_id)?;
+                let is_dirty = page.lsn() < lsn;
+                let result = match is_dirty {
+                    true => apply(page, record.body()).map(|_| page.set_lsn(lsn)),
+                    false => Ok(()),
+                };
+                bpm.unpin_page(page_id, is_dirty)?;
+                result?;
+                if is_dirty {
+                    redone += 1;
+                }
+            }
+            if let LogRecordBody::FreePage { page_id, .. } = record.body() {
+                // The deallocation may not have reached the bitmap.
+                if bpm.is_allocated(*page_id) {
+                    bpm.delete_page(*page_id)?;
+                }
+            }
+        }
+        Ok(redone)
+    }
+
+    // Returns the number of changes undone and the losers.
+    fn undo<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<(usize, Vec<TransactionId>)> {
+        let mut losers: Vec<TransactionId> = self.active_txns.keys().cloned().collect();
+        losers.sort_unstable();
+        let mut to_undo = BinaryHeap::new();
+        for &txn_id in losers.iter() {
+            let last_lsn = self.active_txns[&txn_id];
+            log_manager(bpm)?.resume_txn(txn_id, last_lsn);
+            to_undo.push((last_lsn, txn_id));
+        }
+
+        let mut undone = 0;
+        while let Some((lsn, txn_id)) = to_undo.pop() {
+            let record = self.read_record(lsn)?;
+            let next_lsn = match (record.body(), undo_of(record.body())) {
+                (LogRecordBody::Compensation { undo_next_lsn, .. }, _) => *undo_next_lsn,
+                (_, Some(action)) => {
+                    let page_id = pages_of(&action)[0];
+                    let page = bpm.fetch_page(page_id)?;
+                    let result = apply(page, &action).and_then(|_| {
+                        let body = LogRecordBody::Compensation {
+                            undo_next_lsn: record.prev_lsn(),
+                            action: Box::new(action),
+                        };
+                        bpm.log_page(page_id, LogRecord::new(txn_id, body))
+                    });
+                    bpm.unpin_page(page_id, /*is_dirty=*/ true)?;
+                    result?;
+                    undone += 1;
+                    record.prev_lsn()
+                }
+                (_, None) => record.prev_lsn(),
+            };
+            match next_lsn {
+                INVALID_LSN => {
+                    let abort = LogRecord::new(txn_id, LogRecordBody::Abort);
+                    log_manager(bpm)?.append(abort)?;
+                }
+                _ => to_undo.push((next_lsn, txn_id)),
+            }
+        }
+        Ok((undone, losers))
+    }
+}
+
+impl RecoveryManager {
+    // Reads the record with |lsn| back from the log.
+    fn read_record(&mut self, lsn: Lsn) -> std::io::Result<LogRecord> {
+        let not_found = || invalid_data(&format!("Log record not found; lsn = {}", lsn));
+        let offset = *self.offsets.get(&lsn).ok_or_else(not_found)?;
+        self.reader.seek(offset)?;
+        self.reader.next().ok_or_else(not_found)?
+    }
+}
+
+fn log_manager<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+) -> std::io::Result<&mut LogManager> {
+    bpm.log_manager()
+        .ok_or_else(|| invalid_input("Buffer pool has no log manager"))
+}
+
+// Returns the pages changed by a record with |body|.
+fn pages_of(body: &LogRecordBody) -> Vec<PageId> {
+    match body {
+        LogRecordBody::Begin
+        | LogRecordBody::Commit
+        | LogRecordBody::Abort
+        | LogRecordBody::Checkpoint => vec![],
+        LogRecordBody::Insert { rid, .. }
+        | LogRecordBody::MarkDelete { rid, .. }
+        | LogRecordBody::ApplyDelete { rid, .. }
+        | LogRecordBody::RollbackDelete { rid, .. }
+        | LogRecordBody::Update { rid, .. } => vec![rid.page_id()],
+        LogRecordBody::NewPage {
+            prev_page_id,
+            page_id,
+        } => match *prev_page_id {
+            INVALID_PAGE_ID => vec![*page_id],
+            _ => vec![*page_id, *prev_page_id],
+        },
+        // The freed page itself is not changed, but deleted.
+        LogRecordBody::FreePage {
+            prev_page_id,
+            next_page_id,
+            ..
+        } => [*prev_page_id, *next_page_id]
+            .iter()
+            .filter(|&&page_id| page_id != INVALID_PAGE_ID)
+            .cloned()
+            .collect(),
+        LogRecordBody::Compensation { action, .. } => pages_of(action),
+    }
+}
+
+// Returns the change undoing a change with |body|, if it needs undoing. A new
+// page stays linked into its table heap, and a freed page is not brought back,
+// since it is only freed outside of transactions.
+fn undo_of(body: &LogRecordBody) -> Option<LogRecordBody> {
+    let action = match body.clone() {
+        LogRecordBody::Insert { rid, tuple } => LogRecordBody::ApplyDelete { rid, tuple },
+        LogRecordBody::MarkDelete { rid, tuple } => LogRecordBody::RollbackDelete { rid, tuple },
+        LogRecordBody::ApplyDelete { rid, tuple } => LogRecordBody::Insert { rid, tuple },
+        LogRecordBody::RollbackDelete { rid, tuple } => LogRecordBody::MarkDelete { rid, tuple },
+        LogRecordBody::Update {
+            rid,
+            old_tuple,
+            new_tuple,
+        } => LogRecordBody::Update {
+            rid,
+            old_tuple: new_tuple,
+            new_tuple: old_tuple,
+        },
+        _ => return None,
+    };
+    Some(action)
+}
+
+// Applies the change described by |body| to |page|, which is one of the pages
+// it changes.
+fn apply(page: &mut TablePage, body: &LogRecordBody) -> std::io::Result<()> {
+    let page_id = page.page_id();
+    let failed = |what: &str| {
+        invalid_data(&format!(
+            "Cannot apply log record; page_id = {}, change = {}",
+            page_id, what
+        ))
+    };
+    match body {
+        LogRecordBody::Insert { rid, tuple } => {
+            if !page.insert_tuple_at(rid, tuple.clone()) {
+                return Err(failed("insert"));
+            }
+        }
+        LogRecordBody::MarkDelete { rid, .. } => {
+            page.mark_delete(rid);
+        }
+        LogRecordBody::ApplyDelete { rid, .. } => {
+            page.apply_delete(rid);
+        }
+        LogRecordBody::RollbackDelete { rid, .. } => page.rollback_delete(rid),
+        LogRecordBody::Update { rid, new_tuple, .. } => {
+            if page.replace_tuple(rid, new_tuple.clone()).is_none() {
+                return Err(failed("update"));
+            }
+        }
+        LogRecordBody::NewPage {
+            prev_page_id,
+            page_id,
+        } => {
+            if page.page_id() == *page_id {
+                page.reset();
+                page.set_prev_page_id(*prev_page_id);
+            } else {
+                page.set_next_page_id(*page_id);
+            }
+        }
+        LogRecordBody::FreePage {
+            prev_page_id,
+            next_page_id,
+            ..
+        } => {
+            if page.page_id() == *prev_page_id {
+                page.set_next_page_id(*next_page_id);
+            } else {
+                page.set_prev_page_id(*prev_page_id);
+            }
+        }
+        LogRecordBody::Compensation { action, .. } => apply(page, action)?,
+        LogRecordBody::Begin
+        | LogRecordBody::Commit
+        | LogRecordBody::Abort
+        | LogRecordBody::Checkpoint => (),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::reinterpret;
+    use crate::common::rid::Rid;
+    use crate::disk::disk_manager::BITMAP_FILE_SUFFIX;
+    use crate::table::table_heap::TableHeap;
+    use crate::table::tuple::Tuple;
+    use crate::testing::file_deleter::FileDeleter;
+    use std::fs;
+    use std::path::Path;
+
+    fn create_tuple(id: u64) -> Tuple {
+        // A serialized tuple of 1000 bytes starting with |id|.
+        let mut buffer = vec![0; 1008];
+        reinterpret::write_u64(&mut buffer, 1000);
+        reinterpret::write_u64(&mut buffer[8..], id);
+        let mut tuple = Tuple::default();
+        tuple.deserialize_from(&buffer);
+        tuple
+    }
+
+    fn commit(bpm: &mut DefaultBufferPoolManager<TablePage>, txn_id: TransactionId) {
+        let log_mgr = bpm.log_manager().unwrap();
+        let lsn = log_mgr
+            .append(LogRecord::new(txn_id, LogRecordBody::Commit))
+            .unwrap();
+        log_mgr.flush_until(lsn).unwrap();
+    }
+
+    fn open(file_path: &str, log_path: &str) -> DefaultBufferPoolManager<TablePage> {
+        let mut bpm = DefaultBufferPoolManager::<TablePage>::new(2, file_path).unwrap();
+        bpm.set_log_manager(LogManager::new(log_path).unwrap());
+        bpm
+    }
+
+    #[test]
+    fn redo_and_undo() {
+        let file_path = "/tmp/testfile.recovery_manager.1.db";
+        let log_path = "/tmp/testfile.recovery_manager.1.log";
+        let crash_file_path = "/tmp/testfile.recovery_manager.2.db";
+        let crash_log_path = "/tmp/testfile.recovery_manager.2.log";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let crash_bitmap_path = crash_file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(log_path);
+        file_deleter.push(crash_file_path);
+        file_deleter.push(&crash_bitmap_path);
+        file_deleter.push(crash_log_path);
+
+        let mut bpm = open(file_path, log_path);
+        let mut heap = TableHeap::new(&mut bpm).unwrap();
+
+        // Transaction 1 commits.
+        let mut rids = Vec::new();
+        for id in 0..2 {
+            rids.push(heap.insert_tuple(&mut bpm, 1, create_tuple(id)).unwrap());
+        }
+        commit(&mut bpm, 1);
+
+        // Transaction 2 does not commit, though its changes reach the disk. 3
+        // tuples fit into a page, so the 4th one goes to a new page.
+        for id in 2..4 {
+            rids.push(heap.insert_tuple(&mut bpm, 2, create_tuple(id)).unwrap());
+        }
+        assert!(heap.mark_delete(&mut bpm, 2, &rids[0]).unwrap());
+        assert!(heap
+            .update_tuple(&mut bpm, 2, &rids[1], create_tuple(9))
+            .unwrap());
+        bpm.flush_all_pages().unwrap();
+
+        // Transaction 3 commits, though only its log records reach the disk.
+        heap.insert_tuple(&mut bpm, 3, create_tuple(4)).unwrap();
+        commit(&mut bpm, 3);
+
+        // Simulates a crash by copying the files while |bpm| is alive.
+        fs::copy(file_path, crash_file_path).unwrap();
+        if Path::new(&bitmap_path).exists() {
+            fs::copy(&bitmap_path, &crash_bitmap_path).unwrap();
+        }
+        fs::copy(log_path, crash_log_path).unwrap();
+        drop(bpm);
+
+        let mut bpm = open(crash_file_path, crash_log_path);
+        let recovery_mgr = RecoveryManager::new(&mut bpm).unwrap();
+        assert_eq!(
+            vec![&2],
+            recovery_mgr.active_txns().keys().collect::<Vec<_>>()
+        );
+        assert_eq!(2, recovery_mgr.dirty_pages().len());
+        let stats = recovery_mgr.recover(&mut bpm).unwrap();
+        assert_eq!(4, stats.undone);
+        assert_eq!(vec![2], stats.losers);
+
+        let tuples: Vec<Tuple> = TableHeap::open(heap.first_page_id())
+            .iter(&mut bpm)
+            .map(|item| item.unwrap().1)
+            .collect();
+        let expected: Vec<Tuple> = [0, 1, 4].iter().map(|&id| create_tuple(id)).collect();
+        assert_eq!(expected, tuples);
+        // Unlike closing, dropping keeps the log.
+        drop(bpm);
+
+        // Recovery is idempotent.
+        let mut bpm = open(crash_file_path, crash_log_path);
+        let stats = RecoveryManager::new(&mut bpm)
+            .unwrap()
+            .recover(&mut bpm)
+            .unwrap();
+        assert_eq!(RecoveryStats::default(), stats);
+        let log_mgr = bpm.log_manager().unwrap();
+        let next_lsn = log_mgr.next_lsn();
+        let records = log_mgr.records().unwrap();
+        let abort = LogRecord::new(2, LogRecordBody::Abort);
+        assert_eq!(abort.body(), records.last().unwrap().body());
+        assert!(bpm.close().is_ok());
+
+        // Closing truncates the log, and LSNs continue past the checkpoint.
+        let mut bpm = open(crash_file_path, crash_log_path);
+        let log_mgr = bpm.log_manager().unwrap();
+        let records = log_mgr.records().unwrap();
+        assert_eq!(1, records.len());
+        assert_eq!(&LogRecordBody::Checkpoint, records[0].body());
+        assert_eq!(next_lsn, records[0].lsn());
+        assert_eq!(next_lsn + 1, log_mgr.next_lsn());
+        let stats = RecoveryManager::new(&mut bpm)
+            .unwrap()
+            .recover(&mut bpm)
+            .unwrap();
+        assert_eq!(RecoveryStats::default(), stats);
+        let tuples: Vec<Tuple> = TableHeap::open(heap.first_page_id())
+            .iter(&mut bpm)
+            .map(|item| item.unwrap().1)
+            .collect();
+        assert_eq!(expected, tuples);
+    }
+
+    #[test]
+    fn roll_back() {
+        let file_path = "/tmp/testfile.recovery_manager.4.db";
+        let log_path = "/tmp/testfile.recovery_manager.4.log";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(log_path);
+
+        let mut bpm = open(file_path, log_path);
+        let mut heap = TableHeap::new(&mut bpm).unwrap();
+        let rid = heap.insert_tuple(&mut bpm, 1, create_tuple(0)).unwrap();
+        commit(&mut bpm, 1);
+
+        // Transaction 2 is rolled back while transaction 3 keeps running.
+        let begin = LogRecord::new(2, LogRecordBody::Begin);
+        bpm.log_manager().unwrap().append(begin).unwrap();
+        heap.insert_tuple(&mut bpm, 2, create_tuple(1)).unwrap();
+        assert!(heap.mark_delete(&mut bpm, 2, &rid).unwrap());
+        heap.insert_tuple(&mut bpm, 3, create_tuple(2)).unwrap();
+        assert_eq!(2, RecoveryManager::roll_back(&mut bpm, 2).unwrap());
+
+        let tuples: Vec<Tuple> = heap.iter(&mut bpm).map(|item| item.unwrap().1).collect();
+        assert_eq!(vec![create_tuple(0), create_tuple(2)], tuples);
+        let recovery_mgr = RecoveryManager::new(&mut bpm).unwrap();
+        assert_eq!(
+            vec![&3],
+            recovery_mgr.active_txns().keys().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn redo_vacuum() {
+        let file_path = "/tmp/testfile.recovery_manager.5.db";
+        let log_path = "/tmp/testfile.recovery_manager.5.log";
+        let crash_file_path = "/tmp/testfile.recovery_manager.6.db";
+        let crash_log_path = "/tmp/testfile.recovery_manager.6.log";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let crash_bitmap_path = crash_file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(log_path);
+        file_deleter.push(crash_file_path);
+        file_deleter.push(&crash_bitmap_path);
+        file_deleter.push(crash_log_path);
+
+        // 3 tuples fit into a page, so the tuples take 3 pages, and the middle
+        // one is emptied.
+        let mut bpm = open(file_path, log_path);
+        let mut heap = TableHeap::new(&mut bpm).unwrap();
+        let rids: Vec<Rid> = (0..9)
+            .map(|id| heap.insert_tuple(&mut bpm, 1, create_tuple(id)).unwrap())
+            .collect();
+        commit(&mut bpm, 1);
+        for rid in rids[3..6].iter() {
+            assert!(heap.mark_delete(&mut bpm, 2, rid).unwrap());
+        }
+        commit(&mut bpm, 2);
+        bpm.close().unwrap();
+
+        let mut bpm = open(file_path, log_path);
+        let mut heap = TableHeap::open(heap.first_page_id());
+        assert_eq!(1, heap.vacuum(&mut bpm).unwrap().pages_freed);
+        let freed_page_id = rids[3].page_id();
+
+        // Simulates a crash by copying the files while |bpm| is alive. The
+        // allocation bitmap on disk still has the freed page.
+        fs::copy(file_path, crash_file_path).unwrap();
+        fs::copy(&bitmap_path, &crash_bitmap_path).unwrap();
+        fs::copy(log_path, crash_log_path).unwrap();
+        drop(bpm);
+
+        let mut bpm = open(crash_file_path, crash_log_path);
+        assert!(bpm.is_allocated(freed_page_id));
+        let stats = RecoveryManager::new(&mut bpm)
+            .unwrap()
+            .recover(&mut bpm)
+            .unwrap();
+        assert!(stats.losers.is_empty());
+        assert!(!bpm.is_allocated(freed_page_id));
+        let tuples: Vec<Tuple> = TableHeap::open(heap.first_page_id())
+            .iter(&mut bpm)
+            .map(|item| item.unwrap().1)
+            .collect();
+        let expected: Vec<Tuple> = [0, 1, 2, 6, 7, 8]
+            .iter()
+            .map(|&id| create_tuple(id))
+            .collect();
+        assert_eq!(expected, tuples);
+        let last_page = bpm.fetch_page(rids[6].page_id()).unwrap();
+        assert_eq!(rids[0].page_id(), last_page.prev_page_id());
+        bpm.unpin_page(rids[6].page_id(), /*is_dirty=*/ false)
+            .unwrap();
+    }
+
+    #[test]
+    fn no_log_manager() {
+        let file_path = "/tmp/testfile.recovery_manager.3.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut bpm = DefaultBufferPoolManager::<TablePage>::new(2, file_path).unwrap();
+        assert!(RecoveryManager::new(&mut bpm).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+// Functionality: Loads tuples into a new table heap without going through the
+// buffer pool, e.g. for the initial load of a table. Tuples are packed into
+// table pages in memory, and the pages are written to disk sequentially, a
+// batch of contiguous pages at a time; see |BufferPoolManager::write_pages|.
+//
+// The loaded pages are not logged. They are synced once written, so that the
+// heap survives a crash as soon as |BulkLoader::load| returns. Tuples too large
+// for a page still go to overflow pages through the buffer pool, like with
+// |TableHeap::insert_tuple|. Indexes over the loaded tuples are built from the
+// returned RIDs; see |BPlusTree::bulk_load|.
+
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::common::config::PageId;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::error::*;
+use crate::common::rid::Rid;
+use crate::disk::storage_backend::StorageBackend;
+use crate::disk::tablespace::FileId;
+use crate::disk::tablespace::PRIMARY_FILE_ID;
+use crate::page::extent_page::EXTENT_SIZE;
+use crate::page::page::Page;
+use crate::page::table_page::TablePage;
+use crate::table::table_heap::write_overflow;
+use crate::table::table_heap::TableHeap;
+use crate::table::tuple::Tuple;
+use log::info;
+use std::ops::Range;
+
+pub struct BulkLoader {
+    file_id: FileId,
+    // The number of contiguous pages allocated and written at once.
+    batch_size: usize,
+}
+
+impl BulkLoader {
+    // Loads into the primary tablespace, a batch of |EXTENT_SIZE| pages at a
+    // time.
+    pub fn new() -> Self {
+        Self::with_batch_size(PRIMARY_FILE_ID, EXTENT_SIZE)
+    }
+
+    // Same as |new|, but loads into the tablespace |file_id|, |batch_size|
+    // pages at a time.
+    pub fn with_batch_size(file_id: FileId, batch_size: usize) -> Self {
+        BulkLoader {
+            file_id,
+            batch_size: batch_size.max(1),
+        }
+    }
+
+    // Creates a table heap holding |tuples|, in order. Returns the heap and
+    // the RIDs of the tuples. The pages of the last batch left unused are
+    // given back to the disk manager.
+    pub fn load<I, S>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        tuples: I,
+    ) -> std::io::Result<(TableHeap, Vec<Rid>)>
+    where
+        I: IntoIterator<Item = Tuple>,
+        S: StorageBackend,
+    {
+        let mut batch = bpm.allocate_pages_in(self.file_id, self.batch_size)?;
+        info!("Bulk load; first_page_id = {}", batch.start);
+        let heap = TableHeap::open(batch.start);
+        let mut pages = vec![empty_page(batch.start, INVALID_PAGE_ID)];
+        let mut rids = Vec::new();
+        for tuple in tuples {
+            let tuple = write_overflow(bpm, &heap, tuple)?;
+            // It is safe to unwrap here, because there is always a page.
+            let last = pages.last_mut().unwrap();
+            if !last.can_fit(tuple.len()) {
+                let prev_page_id = last.page_id();
+                let mut page_id = prev_page_id + 1;
+                if page_id == batch.end {
+                    let next_batch = bpm.allocate_pages_in(self.file_id, self.batch_size)?;
+                    page_id = next_batch.start;
+                    last.set_next_page_id(page_id);
+                    write_batch(bpm, &batch, &mut pages)?;
+                    pages.clear();
+                    batch = next_batch;
+                } else {
+                    last.set_next_page_id(page_id);
+                }
+                pages.push(empty_page(page_id, prev_page_id));
+            }
+            let rid = pages.last_mut().unwrap().insert_tuple(tuple);
+            rids.push(rid.map_err(|e| invalid_data(&e.to_string()))?);
+        }
+
+        let used = batch.start + pages.len() as PageId;
+        write_batch(bpm, &batch, &mut pages)?;
+        for page_id in used..batch.end {
+            bpm.delete_page(page_id)?;
+        }
+        info!("Bulk loaded; tuples = {}", rids.len());
+        Ok((heap, rids))
+    }
+}
+
+impl Default for BulkLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Returns an empty table page with the specified |page_id|, linked to the
+// page |prev_page_id|.
+fn empty_page(page_id: PageId, prev_page_id: PageId) -> TablePage {
+    let mut page = TablePage::new();
+    page.set_page_id(page_id);
+    page.set_prev_page_id(prev_page_id);
+    page
+}
+
+// Writes |pages|, the first pages of |batch|, to disk.
+fn write_batch<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    batch: &Range<PageId>,
+    pages: &mut [TablePage],
+) -> std::io::Result<()> {
+    let mut data: Vec<&mut [u8]> = pages
+        .iter_mut()
+        .map(|page| &mut page.data_mut()[..])
+        .collect();
+    bpm.write_pages(batch.start, &mut data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::common::reinterpret;
+    use crate::page::table_page::MAX_TUPLE_SIZE;
+
+    fn create_tuple(id: u64, len: usize) -> Tuple {
+        let mut buffer = vec![0; len + 8];
+        reinterpret::write_u64(&mut buffer, len as u64);
+        reinterpret::write_u64(&mut buffer[8..], id);
+        let mut tuple = Tuple::default();
+        tuple.deserialize_from(&buffer);
+        tuple
+    }
+
+    #[test]
+    fn load() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(2);
+
+        // 3 tuples fit into a page, so the tuples span 3 batches of 3 pages.
+        // The last one is too large for a page, so its slot holds a stub.
+        let mut tuples: Vec<Tuple> = (0..19).map(|id| create_tuple(id, 1000)).collect();
+        tuples.push(create_tuple(19, MAX_TUPLE_SIZE + 1));
+        let first_page_id;
+        {
+            let loader = BulkLoader::with_batch_size(PRIMARY_FILE_ID, 3);
+            let (mut heap, rids) = loader.load(&mut bpm, tuples.clone()).unwrap();
+            first_page_id = heap.first_page_id();
+            assert_eq!(0, first_page_id);
+            assert_eq!(20, rids.len());
+            assert_eq!(Rid::new(0, 2), rids[2]);
+            assert_eq!(Rid::new(1, 0), rids[3]);
+            assert_eq!(Rid::new(6, 1), rids[19]);
+            for (rid, tuple) in rids.iter().zip(tuples.iter()) {
+                assert_eq!(Some(tuple), heap.get_tuple(&mut bpm, rid).unwrap().as_ref());
+            }
+            // The unused pages of the last batch are given back, unlike the
+            // overflow page allocated after the batch.
+            assert!(!bpm.is_allocated(7));
+            assert!(!bpm.is_allocated(8));
+            assert!(bpm.is_allocated(9));
+
+            // The heap grows like any other.
+            let rid = heap
+                .insert_tuple(&mut bpm, 1, create_tuple(20, 1000))
+                .unwrap();
+            assert_eq!(Rid::new(6, 2), rid);
+            assert!(bpm.flush_all_pages().is_ok());
+        }
+
+        let heap = TableHeap::open(first_page_id);
+        tuples.push(create_tuple(20, 1000));
+        let scanned: Vec<Tuple> = heap.iter(&mut bpm).map(|item| item.unwrap().1).collect();
+        assert_eq!(tuples, scanned);
+
+        // Nothing to load still makes a heap.
+        let (heap, rids) = BulkLoader::new().load(&mut bpm, vec![]).unwrap();
+        assert!(rids.is_empty());
+        assert_eq!(0, heap.iter(&mut bpm).count());
+    }
+}
@@ -0,0 +1,397 @@
+// Functionality: Copies the tuples of a table heap from and to CSV, e.g. to
+// get data in and out of the engine. Fields are read as strings and converted
+// to the column types with the cast rules of |Value::cast_to|, like values
+// inserted into a table; values are written with |Value::to_string|.
+//
+// Fields containing the delimiter, a quote or a line break are quoted, with
+// quotes doubled inside, and may span lines. An unquoted field equal to the
+// NULL representation is null, so that a quoted one, e.g. "" with the default
+// representation, is the string itself. Columns whose types have no null
+// value, i.e. varchars and varbinaries, cannot be null.
+//
+// Copying into a table does not maintain its indexes; see
+// |ExecutionContext::insert_tuple| for that.
+
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::catalog::column::Column;
+use crate::catalog::schema::Schema;
+use crate::common::config::TransactionId;
+use crate::common::error::*;
+use crate::disk::storage_backend::StorageBackend;
+use crate::execution::execution_context::conform_value;
+use crate::page::table_page::TablePage;
+use crate::table::table_heap::TableHeap;
+use crate::table::tuple::Tuple;
+use crate::types::types::Operation;
+use crate::types::types::Str;
+use crate::types::types::Types;
+use crate::types::types::Varlen;
+use crate::types::value::Value;
+use std::io::BufRead;
+use std::io::Write;
+use std::mem;
+
+pub struct CsvOptions {
+    // The character between fields.
+    pub delimiter: char,
+    // Whether the first record holds the column names. When reading, the
+    // fields are matched to the columns by name, in any order.
+    pub header: bool,
+    // The representation of nulls.
+    pub null: String,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            header: true,
+            null: String::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Field {
+    text: String,
+    quoted: bool,
+}
+
+impl TableHeap {
+    // Inserts a tuple of |schema| for each record read from |reader|, as the
+    // transaction |txn_id|. Returns the number of tuples inserted. Returns
+    // |InvalidData| if the CSV is malformed, and |InvalidInput| if a field
+    // cannot be converted to the type of its column; the tuples of the records
+    // before stay inserted.
+    pub fn copy_from_csv<R: BufRead, S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        mut reader: R,
+        schema: &Schema,
+        options: &CsvOptions,
+    ) -> std::io::Result<usize> {
+        let mut line = 0;
+        // The index of the field holding each column.
+        let mut positions: Vec<usize> = (0..schema.columns().len()).collect();
+        if options.header {
+            if let Some(names) = read_record(&mut reader, options.delimiter, &mut line)? {
+                positions = header_positions(&names, schema)?;
+            }
+        }
+
+        let mut count = 0;
+        loop {
+            let first_line = line + 1;
+            let fields = match read_record(&mut reader, options.delimiter, &mut line)? {
+                Some(fields) => fields,
+                None => return Ok(count),
+            };
+            if fields.len() != schema.columns().len() {
+                return Err(invalid_data(&format!(
+                    "Wrong number of fields; line = {}, expected = {}, found = {}",
+                    first_line,
+                    schema.columns().len(),
+                    fields.len()
+                )));
+            }
+            let mut values = Vec::new();
+            for (column, &position) in schema.columns().iter().zip(positions.iter()) {
+                let value = parse_value(&fields[position], column, options).map_err(|e| {
+                    std::io::Error::new(e.kind(), format!("{}, line = {}", e, first_line))
+                })?;
+                values.push(value);
+            }
+            self.insert_tuple(bpm, txn_id, Tuple::new(&values, schema))?;
+            count += 1;
+        }
+    }
+
+    // Writes the live tuples of the heap, read with |schema|, to |writer|, one
+    // record per tuple. Returns the number of tuples written.
+    pub fn copy_to_csv<W: Write, S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        mut writer: W,
+        schema: &Schema,
+        options: &CsvOptions,
+    ) -> std::io::Result<usize> {
+        if options.header {
+            let names = schema
+                .columns()
+                .iter()
+                .map(|column| Some(column.name().to_string()));
+            write_record(&mut writer, names, options)?;
+        }
+        let mut count = 0;
+        for item in self.iter(bpm) {
+            let (_, tuple) = item?;
+            // Nulls are None.
+            let fields =
+                (0..schema.columns().len()).map(|idx| match tuple.nth_is_null(schema, idx) {
+                    true => None,
+                    false => Some(tuple.nth_value(schema, idx).to_string()),
+                });
+            write_record(&mut writer, fields, options)?;
+            count += 1;
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+}
+
+// Maps each column of |schema| to the index of the field named after it.
+fn header_positions(names: &[Field], schema: &Schema) -> std::io::Result<Vec<usize>> {
+    if names.len() != schema.columns().len() {
+        return Err(invalid_data(&format!(
+            "Wrong number of columns in header; expected = {}, found = {}",
+            schema.columns().len(),
+            names.len()
+        )));
+    }
+    let mut positions = vec![usize::MAX; schema.columns().len()];
+    for (position, name) in names.iter().enumerate() {
+        let idx = schema.column_idx(&name.text).ok_or_else(|| {
+            invalid_data(&format!("Unknown column in header; name = {}", name.text))
+        })?;
+        if positions[idx] != usize::MAX {
+            return Err(invalid_data(&format!(
+                "Duplicate column in header; name = {}",
+                name.text
+            )));
+        }
+        positions[idx] = position;
+    }
+    Ok(positions)
+}
+
+fn parse_value<'a>(
+    field: &Field,
+    column: &Column,
+    options: &CsvOptions,
+) -> std::io::Result<Value<'a>> {
+    if !field.quoted && field.text == options.null {
+        let types = Types::from_id(column.types().id()).unwrap();
+        return match types.clone().null_val() {
+            Ok(_) => Ok(Value::null(types)),
+            Err(_) => Err(invalid_input(&format!(
+                "Column cannot be null; column = {}",
+                column.name()
+            ))),
+        };
+    }
+    let text = Types::Varchar(Varlen::Owned(Str::Val(field.text.clone())));
+    conform_value(Value::new(text), column)
+}
+
+// Reads the next record, skipping empty lines, and counts the lines read in
+// |line|. Returns None at the end of |reader|.
+fn read_record<R: BufRead>(
+    reader: &mut R,
+    delimiter: char,
+    line: &mut usize,
+) -> std::io::Result<Option<Vec<Field>>> {
+    let mut buffer = String::new();
+    loop {
+        buffer.clear();
+        if reader.read_line(&mut buffer)? == 0 {
+            return Ok(None);
+        }
+        *line += 1;
+        if !buffer.trim_end_matches(&['\r', '\n'][..]).is_empty() {
+            break;
+        }
+    }
+
+    let first_line = *line;
+    let mut fields = Vec::new();
+    let mut field = Field::default();
+    let mut in_quotes = false;
+    loop {
+        let mut chars = buffer.chars().peekable();
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                match c {
+                    '"' if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.text.push('"');
+                    }
+                    '"' => in_quotes = false,
+                    _ => field.text.push(c),
+                }
+            } else if c == '"' && !field.quoted && field.text.is_empty() {
+                field.quoted = true;
+                in_quotes = true;
+            } else if c == delimiter {
+                fields.push(mem::take(&mut field));
+            } else if c != '\r' && c != '\n' {
+                field.text.push(c);
+            }
+        }
+        if !in_quotes {
+            break;
+        }
+        // The line break belongs to the quoted field, which goes on.
+        buffer.clear();
+        if reader.read_line(&mut buffer)? == 0 {
+            return Err(invalid_data(&format!(
+                "Unterminated quoted field; line = {}",
+                first_line
+            )));
+        }
+        *line += 1;
+    }
+    fields.push(field);
+    Ok(Some(fields))
+}
+
+fn write_record<W, I>(writer: &mut W, fields: I, options: &CsvOptions) -> std::io::Result<()>
+where
+    W: Write,
+    I: Iterator<Item = Option<String>>,
+{
+    let mut record = String::new();
+    for (idx, field) in fields.enumerate() {
+        if idx > 0 {
+            record.push(options.delimiter);
+        }
+        match field {
+            Some(field) => record.push_str(&quote(field, options)),
+            None => record.push_str(&options.null),
+        }
+    }
+    record.push('\n');
+    writer.write_all(record.as_bytes())
+}
+
+// Quotes |field| if it would not read back as itself, e.g. if it is equal to
+// the NULL representation.
+fn quote(field: String, options: &CsvOptions) -> String {
+    let needs_quotes = field == options.null
+        || field.starts_with('"')
+        || field
+            .chars()
+            .any(|c| c == options.delimiter || c == '"' || c == '\r' || c == '\n');
+    match needs_quotes {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use std::io::ErrorKind;
+
+    fn create_schema() -> Schema<'static> {
+        Schema::new(vec![
+            Column::new("Name".to_string(), Types::owned(), 20),
+            Column::new("Count".to_string(), Types::integer(), 4),
+            Column::new("Score".to_string(), Types::decimal(), 8),
+        ])
+    }
+
+    #[test]
+    fn copy_from_and_to_csv() {
+        let schema = create_schema();
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(4);
+        let mut heap = TableHeap::new(&mut bpm).unwrap();
+        let options = CsvOptions::default();
+
+        // The header lists the columns in another order. Empty lines are
+        // skipped, and quoted fields may span lines.
+        let csv = "Score,Name,Count\n\
+                   1.5,apple,3\r\n\
+                   \n\
+                   ,\"\",\n\
+                   -2,\"multi\nline, \"\"quoted\"\"\",7\n";
+        let count = heap
+            .copy_from_csv(&mut bpm, 1, csv.as_bytes(), &schema, &options)
+            .unwrap();
+        assert_eq!(3, count);
+        let rows: Vec<String> = heap
+            .iter(&mut bpm)
+            .map(|item| item.unwrap().1.to_string(&schema))
+            .collect();
+        assert!(rows[0].starts_with("(apple, 3, 1.5)"));
+        assert!(rows[1].starts_with("(, <NULL>, <NULL>)"));
+        assert!(rows[2].starts_with("(multi\nline, \"quoted\", 7, -2)"));
+
+        let mut output = Vec::new();
+        let count = heap
+            .copy_to_csv(&mut bpm, &mut output, &schema, &options)
+            .unwrap();
+        assert_eq!(3, count);
+        let expected = "Name,Count,Score\n\
+                        apple,3,1.5\n\
+                        \"\",,\n\
+                        \"multi\nline, \"\"quoted\"\"\",7,-2\n";
+        assert_eq!(expected, String::from_utf8(output.clone()).unwrap());
+
+        // What is written reads back the same, with other options too.
+        let options = CsvOptions {
+            delimiter: '|',
+            header: false,
+            null: "NULL".to_string(),
+        };
+        let mut output = Vec::new();
+        heap.copy_to_csv(&mut bpm, &mut output, &schema, &options)
+            .unwrap();
+        assert_eq!(
+            "apple|3|1.5\n|NULL|NULL\n\"multi\nline, \"\"quoted\"\"\"|7|-2\n",
+            String::from_utf8(output.clone()).unwrap()
+        );
+        let mut copy = TableHeap::new(&mut bpm).unwrap();
+        let count = copy
+            .copy_from_csv(&mut bpm, 1, &output[..], &schema, &options)
+            .unwrap();
+        assert_eq!(3, count);
+        let tuples: Vec<Tuple> = heap.iter(&mut bpm).map(|item| item.unwrap().1).collect();
+        let copied: Vec<Tuple> = copy.iter(&mut bpm).map(|item| item.unwrap().1).collect();
+        assert_eq!(tuples, copied);
+    }
+
+    #[test]
+    fn malformed_csv() {
+        let schema = create_schema();
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(4);
+        let mut heap = TableHeap::new(&mut bpm).unwrap();
+        let options = CsvOptions::default();
+        let mut copy = |csv: &str| {
+            heap.copy_from_csv(&mut bpm, 1, csv.as_bytes(), &schema, &options)
+                .unwrap_err()
+        };
+
+        let error = copy("Name,Count\n");
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+        assert!(copy("Name,Count,Weight\n")
+            .to_string()
+            .starts_with("Unknown column in header"));
+        assert!(copy("Name,Count,Name\n")
+            .to_string()
+            .starts_with("Duplicate column in header"));
+        assert_eq!(
+            "Wrong number of fields; line = 3, expected = 3, found = 2",
+            copy("Name,Count,Score\na,1,2\nb,1\n").to_string()
+        );
+        assert_eq!(
+            "Unterminated quoted field; line = 2",
+            copy("Name,Count,Score\n\"a,1,2\nb,1,2\n").to_string()
+        );
+
+        let error = copy("Name,Count,Score\na,one,2\n");
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert_eq!(
+            "Cannot convert value; column = Count, line = 2",
+            error.to_string()
+        );
+        assert_eq!(
+            "Column cannot be null; column = Name, line = 2",
+            copy("Name,Count,Score\n,1,2\n").to_string()
+        );
+
+        // The records before the error stay inserted.
+        assert_eq!(1, heap.iter(&mut bpm).count());
+    }
+}
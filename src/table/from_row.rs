@@ -0,0 +1,267 @@
+// Functionality: Typed access to tuples. |FromRow| maps a tuple into a user
+// struct, reading each field with |Row::get|, which checks the column type
+// against the Rust type of the field instead of callers hand-indexing
+// |Tuple::nth_value|.
+//
+// struct Visit {
+//     name: String,
+//     count: Option<i32>,
+// }
+//
+// impl FromRow for Visit {
+//     fn from_row(row: &Row) -> std::io::Result<Self> {
+//         Ok(Visit {
+//             name: row.get("Name")?,
+//             count: row.get("Count")?,
+//         })
+//     }
+// }
+
+use crate::catalog::schema::Schema;
+use crate::common::error::*;
+use crate::table::tuple::Tuple;
+use crate::types::types::Str;
+use crate::types::types::Types;
+use crate::types::types::Varlen;
+use crate::types::value::Value;
+use std::any::type_name;
+
+pub trait FromRow: Sized {
+    fn from_row(row: &Row) -> std::io::Result<Self>;
+}
+
+// A Rust type a column value can be read as.
+pub trait FromValue: Sized {
+    // Whether columns of type |types| can be read as this type.
+    fn accepts(types: &Types) -> bool;
+
+    // Converts a non-null value, or returns None if the type does not match.
+    fn from_value(value: &Value) -> Option<Self>;
+
+    // The result for a null value; only |Option| accepts nulls.
+    fn from_null() -> Option<Self> {
+        None
+    }
+}
+
+// A tuple together with the schema describing it.
+pub struct Row<'a> {
+    tuple: &'a Tuple,
+    schema: &'a Schema<'a>,
+}
+
+impl<'a> Row<'a> {
+    pub fn new(tuple: &'a Tuple, schema: &'a Schema<'a>) -> Self {
+        Row { tuple, schema }
+    }
+
+    // Reads the column named |name| as |T|. Returns |NotFound| if there is no
+    // such column, and |InvalidData| if its type does not match |T| or it is
+    // null while |T| is not an |Option|.
+    pub fn get<T: FromValue>(&self, name: &str) -> std::io::Result<T> {
+        match self.schema.column_idx(name) {
+            Some(idx) => self.get_nth(idx),
+            None => Err(not_found(&format!("Column not found; name = {}", name))),
+        }
+    }
+
+    // Same as |get|, by column index.
+    pub fn get_nth<T: FromValue>(&self, idx: usize) -> std::io::Result<T> {
+        let column = self
+            .schema
+            .nth_column(idx)
+            .ok_or_else(|| not_found(&format!("Column not found; idx = {}", idx)))?;
+        if !T::accepts(column.types()) {
+            return Err(invalid_data(&format!(
+                "Column {} of type {} cannot be read as {}",
+                column.name(),
+                column.types().name(),
+                type_name::<T>()
+            )));
+        }
+        let value = self.tuple.nth_value(self.schema, idx);
+        let result = match value.is_null() {
+            true => T::from_null(),
+            false => T::from_value(&value),
+        };
+        result.ok_or_else(|| {
+            invalid_data(&format!(
+                "Column {} cannot be read as {}",
+                column.name(),
+                type_name::<T>()
+            ))
+        })
+    }
+
+    // Maps the row into |T|.
+    pub fn to<T: FromRow>(&self) -> std::io::Result<T> {
+        T::from_row(self)
+    }
+}
+
+macro_rules! from_value {
+    ($type:ty, $variant:ident) => {
+        impl FromValue for $type {
+            fn accepts(types: &Types) -> bool {
+                matches!(types, Types::$variant(..))
+            }
+
+            fn from_value(value: &Value) -> Option<Self> {
+                match value.borrow() {
+                    Types::$variant(val) => Some(*val),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+from_value!(i8, TinyInt);
+from_value!(i16, SmallInt);
+from_value!(i32, Integer);
+from_value!(i64, BigInt);
+from_value!(f64, Decimal);
+from_value!(u64, Timestamp);
+
+impl FromValue for bool {
+    fn accepts(types: &Types) -> bool {
+        matches!(types, Types::Boolean(_))
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value.borrow() {
+            Types::Boolean(val) => Some(*val != 0),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    fn accepts(types: &Types) -> bool {
+        matches!(types, Types::Varchar(_))
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value.borrow() {
+            Types::Varchar(Varlen::Owned(Str::Val(val))) => Some(val.clone()),
+            Types::Varchar(Varlen::Borrowed(Str::Val(val))) => Some(val.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for Vec<u8> {
+    fn accepts(types: &Types) -> bool {
+        matches!(types, Types::Varbinary(_))
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value.borrow() {
+            Types::Varbinary(val) => Some(val.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for (f64, f64) {
+    fn accepts(types: &Types) -> bool {
+        matches!(types, Types::Point(..))
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value.borrow() {
+            Types::Point(x, y) => Some((*x, *y)),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn accepts(types: &Types) -> bool {
+        T::accepts(types)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        T::from_value(value).map(Some)
+    }
+
+    fn from_null() -> Option<Self> {
+        Some(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::column::Column;
+    use std::io::ErrorKind;
+
+    #[derive(Debug, PartialEq)]
+    struct Visit {
+        name: String,
+        count: Option<i32>,
+    }
+
+    impl FromRow for Visit {
+        fn from_row(row: &Row) -> std::io::Result<Self> {
+            Ok(Visit {
+                name: row.get("Name")?,
+                count: row.get("Count")?,
+            })
+        }
+    }
+
+    fn create_schema() -> Schema<'static> {
+        Schema::new(vec![
+            Column::new("Name".to_string(), Types::owned(), 10),
+            Column::new("Count".to_string(), Types::integer(), 4),
+        ])
+    }
+
+    fn create_tuple(schema: &Schema, name: &str, count: Option<i32>) -> Tuple {
+        let values = vec![
+            Value::new(Types::Varchar(Varlen::Owned(Str::Val(name.to_string())))),
+            match count {
+                Some(count) => Value::new(Types::Integer(count)),
+                None => Value::null(Types::integer()),
+            },
+        ];
+        Tuple::new(&values, schema)
+    }
+
+    #[test]
+    fn from_row() {
+        let schema = create_schema();
+        let tuple = create_tuple(&schema, "Instagram", Some(42));
+        let row = Row::new(&tuple, &schema);
+        let expected = Visit {
+            name: "Instagram".to_string(),
+            count: Some(42),
+        };
+        assert_eq!(expected, row.to::<Visit>().unwrap());
+        assert_eq!(42, row.get::<i32>("Count").unwrap());
+        assert_eq!("Instagram", row.get_nth::<String>(0).unwrap());
+
+        // Type mismatches and missing columns are reported.
+        let err = row.get::<i64>("Count").unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        assert!(err.to_string().contains("INTEGER"));
+        assert_eq!(
+            ErrorKind::NotFound,
+            row.get::<i32>("Age").unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::NotFound,
+            row.get_nth::<i32>(2).unwrap_err().kind()
+        );
+
+        // Nulls only map into options.
+        let tuple = create_tuple(&schema, "Instagram", None);
+        let row = Row::new(&tuple, &schema);
+        assert_eq!(None, row.to::<Visit>().unwrap().count);
+        assert!(row.get::<i32>("Count").is_err());
+        let tuple = create_tuple(&schema, "Instagram", Some(i32::MIN));
+        let row = Row::new(&tuple, &schema);
+        assert_eq!(Some(i32::MIN), row.to::<Visit>().unwrap().count);
+    }
+}
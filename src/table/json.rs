@@ -0,0 +1,455 @@
+// Functionality: Converts tuples from and to JSON objects keyed by column
+// name, e.g. for debug dumps and for the expected rows of tests:
+//
+// {"Name":"apple","Count":3,"Ripe":true,"Picked":"2021-06-01 12:00:00.000000+00",
+//  "Where":[1.5,-2],"Tag":"0x1f00","Score":null}
+//
+// Booleans and numbers map to JSON booleans and numbers, timestamps to strings
+// formatted like |Value::to_string|, points to [x, y] arrays, varbinaries to
+// hex strings, and nulls to null. When reading, a JSON string is also accepted
+// for any scalar column and converted with the cast rules of |Value::cast_to|,
+// and a missing key is null.
+
+use crate::catalog::column::Column;
+use crate::catalog::schema::Schema;
+use crate::common::error::*;
+use crate::execution::execution_context::conform_value;
+use crate::table::tuple::Tuple;
+use crate::types::timestamp::format_timestamp;
+use crate::types::types::bytes_to_hex;
+use crate::types::types::Str;
+use crate::types::types::Types;
+use crate::types::types::Varlen;
+use crate::types::value::Value;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// A parsed JSON value. Numbers keep their text, so that they are converted
+// to the column type without going through a float.
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Tuple {
+    // Formats the tuple, read with |schema|, as a JSON object.
+    pub fn to_json(&self, schema: &Schema) -> String {
+        let mut json = String::from("{");
+        for (idx, column) in schema.columns().iter().enumerate() {
+            if idx > 0 {
+                json.push(',');
+            }
+            push_string(&mut json, column.name());
+            json.push(':');
+            match self.nth_is_null(schema, idx) {
+                true => json.push_str("null"),
+                false => push_value(&mut json, &self.nth_value(schema, idx)),
+            }
+        }
+        json.push('}');
+        json
+    }
+
+    // Creates a tuple of |schema| from the JSON object |json|. Returns
+    // |InvalidData| if |json| is not a JSON object, and |InvalidInput| if a key
+    // is not a column or a value cannot be converted to the type of its
+    // column.
+    pub fn from_json(json: &str, schema: &Schema) -> std::io::Result<Tuple> {
+        let mut parser = Parser {
+            chars: json.chars().peekable(),
+        };
+        let members = match parser.parse()? {
+            Json::Object(members) => members,
+            _ => return Err(invalid_data("JSON is not an object")),
+        };
+        let mut fields: Vec<Option<Json>> = schema.columns().iter().map(|_| None).collect();
+        for (name, json) in members {
+            let idx = schema
+                .column_idx(&name)
+                .ok_or_else(|| invalid_input(&format!("Unknown column; name = {}", name)))?;
+            if fields[idx].is_some() {
+                return Err(invalid_data(&format!("Duplicate key; name = {}", name)));
+            }
+            fields[idx] = Some(json);
+        }
+        let mut values = Vec::new();
+        for (field, column) in fields.into_iter().zip(schema.columns().iter()) {
+            values.push(parse_value(field.unwrap_or(Json::Null), column)?);
+        }
+        Ok(Tuple::new(&values, schema))
+    }
+}
+
+fn push_value(json: &mut String, value: &Value) {
+    match value.borrow() {
+        Types::Boolean(val) => json.push_str(if *val == 0 { "false" } else { "true" }),
+        Types::TinyInt(val) => json.push_str(&val.to_string()),
+        Types::SmallInt(val) => json.push_str(&val.to_string()),
+        Types::Integer(val) => json.push_str(&val.to_string()),
+        Types::BigInt(val) => json.push_str(&val.to_string()),
+        Types::Decimal(val) => push_number(json, *val),
+        Types::Timestamp(val) => push_string(json, &format_timestamp(*val)),
+        Types::Varchar(varlen) => push_string(json, varlen.borrow().unwrap_or_default()),
+        Types::Point(x, y) => {
+            json.push('[');
+            push_number(json, *x);
+            json.push(',');
+            push_number(json, *y);
+            json.push(']');
+        }
+        Types::Varbinary(bytes) => push_string(json, &bytes_to_hex(bytes)),
+    }
+}
+
+// JSON has no infinities or NaN, so they are written as strings, which read
+// back through the cast rules.
+fn push_number(json: &mut String, val: f64) {
+    match val.is_finite() {
+        true => json.push_str(&val.to_string()),
+        false => push_string(json, &val.to_string()),
+    }
+}
+
+fn push_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+fn parse_value<'a>(json: Json, column: &Column) -> std::io::Result<Value<'a>> {
+    let cannot_convert =
+        || invalid_input(&format!("Cannot convert value; column = {}", column.name()));
+    let types = Types::from_id(column.types().id()).unwrap();
+    let text = match (json, types) {
+        (Json::Null, types) => {
+            return match types.clone().null_val() {
+                Ok(_) => Ok(Value::null(types)),
+                Err(_) => Err(invalid_input(&format!(
+                    "Column cannot be null; column = {}",
+                    column.name()
+                ))),
+            };
+        }
+        (Json::Array(coords), Types::Point(_, _)) => {
+            return match coords.as_slice() {
+                [Json::Number(x), Json::Number(y)] => {
+                    let x = x.parse().map_err(|_| cannot_convert())?;
+                    let y = y.parse().map_err(|_| cannot_convert())?;
+                    Ok(Value::new(Types::Point(x, y)))
+                }
+                _ => Err(cannot_convert()),
+            };
+        }
+        (Json::String(hex), Types::Varbinary(_)) => {
+            let bytes = hex_to_bytes(&hex).ok_or_else(cannot_convert)?;
+            return Ok(Value::new(Types::Varbinary(bytes)));
+        }
+        (Json::Bool(val), Types::Boolean(_)) => val.to_string(),
+        (Json::Number(number), Types::TinyInt(_))
+        | (Json::Number(number), Types::SmallInt(_))
+        | (Json::Number(number), Types::Integer(_))
+        | (Json::Number(number), Types::BigInt(_))
+        | (Json::Number(number), Types::Decimal(_)) => number,
+        (Json::String(s), _) => s,
+        _ => return Err(cannot_convert()),
+    };
+    let text = Types::Varchar(Varlen::Owned(Str::Val(text)));
+    conform_value(Value::new(text), column)
+}
+
+// The inverse of |bytes_to_hex|.
+fn hex_to_bytes(hex: &str) -> Option<Vec<u8>> {
+    let digits = hex.strip_prefix("0x")?;
+    if digits.len() % 2 != 0 || !digits.is_ascii() {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&digits[idx..(idx + 2)], 16).ok())
+        .collect()
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    // Parses a single JSON value, surrounded by nothing but whitespace.
+    fn parse(&mut self) -> std::io::Result<Json> {
+        let json = self.parse_value()?;
+        self.skip_whitespace();
+        match self.chars.next() {
+            None => Ok(json),
+            Some(c) => Err(unexpected(c)),
+        }
+    }
+
+    fn parse_value(&mut self) -> std::io::Result<Json> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => self.parse_string().map(Json::String),
+            Some('-') | Some('0'..='9') => self.parse_number(),
+            Some('t') => self.parse_literal("true", Json::Bool(true)),
+            Some('f') => self.parse_literal("false", Json::Bool(false)),
+            Some('n') => self.parse_literal("null", Json::Null),
+            Some(&c) => Err(unexpected(c)),
+            None => Err(end_of_input()),
+        }
+    }
+
+    fn parse_object(&mut self) -> std::io::Result<Json> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.chars.next();
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            members.push((name, self.parse_value()?));
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(members)),
+                Some(c) => return Err(unexpected(c)),
+                None => return Err(end_of_input()),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> std::io::Result<Json> {
+        self.expect('[')?;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.chars.next();
+            return Ok(Json::Array(elements));
+        }
+        loop {
+            elements.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(elements)),
+                Some(c) => return Err(unexpected(c)),
+                None => return Err(end_of_input()),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> std::io::Result<String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next().ok_or_else(end_of_input)? {
+                '"' => return Ok(s),
+                '\\' => match self.chars.next().ok_or_else(end_of_input)? {
+                    '"' => s.push('"'),
+                    '\\' => s.push('\\'),
+                    '/' => s.push('/'),
+                    'b' => s.push('\u{8}'),
+                    'f' => s.push('\u{c}'),
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    'u' => s.push(self.parse_escaped_char()?),
+                    c => return Err(unexpected(c)),
+                },
+                c if (c as u32) < 0x20 => return Err(unexpected(c)),
+                c => s.push(c),
+            }
+        }
+    }
+
+    // Parses the code point of a \u escape, the \u excluded, combining
+    // surrogate pairs.
+    fn parse_escaped_char(&mut self) -> std::io::Result<char> {
+        let high = self.parse_hex4()?;
+        let code = match high {
+            0xd800..=0xdbff => {
+                self.expect('\\')?;
+                self.expect('u')?;
+                let low = self.parse_hex4()?;
+                if !(0xdc00..=0xdfff).contains(&low) {
+                    return Err(invalid_data("Invalid surrogate pair in JSON"));
+                }
+                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+            }
+            _ => high,
+        };
+        std::char::from_u32(code).ok_or_else(|| invalid_data("Invalid code point in JSON"))
+    }
+
+    fn parse_hex4(&mut self) -> std::io::Result<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let c = self.chars.next().ok_or_else(end_of_input)?;
+            code = code * 16 + c.to_digit(16).ok_or_else(|| unexpected(c))?;
+        }
+        Ok(code)
+    }
+
+    fn parse_number(&mut self) -> std::io::Result<Json> {
+        let mut number = String::new();
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                '0'..='9' | '-' | '+' | '.' | 'e' | 'E' => {
+                    number.push(c);
+                    self.chars.next();
+                }
+                _ => break,
+            }
+        }
+        // Checked loosely; the number is parsed again into its column type.
+        match number.parse::<f64>() {
+            Ok(_) => Ok(Json::Number(number)),
+            Err(_) => Err(invalid_data(&format!(
+                "Invalid number in JSON; number = {}",
+                number
+            ))),
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, json: Json) -> std::io::Result<Json> {
+        for expected in literal.chars() {
+            self.expect(expected)?;
+        }
+        Ok(json)
+    }
+
+    fn expect(&mut self, expected: char) -> std::io::Result<()> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(unexpected(c)),
+            None => Err(end_of_input()),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ') | Some('\t') | Some('\n') | Some('\r') = self.chars.peek() {
+            self.chars.next();
+        }
+    }
+}
+
+fn unexpected(c: char) -> std::io::Error {
+    invalid_data(&format!(
+        "Unexpected character in JSON; character = {:?}",
+        c
+    ))
+}
+
+fn end_of_input() -> std::io::Error {
+    invalid_data("Unexpected end of JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::types::Operation;
+    use std::io::ErrorKind;
+
+    fn create_schema() -> Schema<'static> {
+        Schema::new(vec![
+            Column::new("Name".to_string(), Types::owned(), 20),
+            Column::new("Count".to_string(), Types::integer(), 4),
+            Column::new("Ripe".to_string(), Types::boolean(), 1),
+            Column::new("Picked".to_string(), Types::timestamp(), 8),
+            Column::new("Where".to_string(), Types::point(), 16),
+            Column::new("Tag".to_string(), Types::varbinary(), 20),
+            Column::new("Score".to_string(), Types::decimal(), 8),
+        ])
+    }
+
+    #[test]
+    fn to_and_from_json() {
+        let schema = create_schema();
+        let json = "{\"Name\":\"say \\\"hi\\\"\\n\",\"Count\":3,\"Ripe\":true,\
+                    \"Picked\":\"2021-06-01 12:00:00.000000+00\",\"Where\":[1.5,-2],\
+                    \"Tag\":\"0x1f00\",\"Score\":null}";
+        let tuple = Tuple::from_json(json, &schema).unwrap();
+        assert_eq!("say \"hi\"\n", tuple.nth_value(&schema, 0).to_string());
+        assert!(tuple.nth_is_null(&schema, 6));
+        assert_eq!(json, tuple.to_json(&schema));
+
+        // Whitespace, escapes, other key orders, strings for scalars, and
+        // missing keys are accepted.
+        let other = " { \"Score\" : null , \"Tag\":\"0x1f00\", \"Where\": \"(1.5, -2)\",\
+                     \"Picked\":\"2021-06-01 12:00:00\", \"Ripe\": \"true\",\
+                     \"Count\": \"3\", \"Name\":\"say \\u0022hi\\u0022\\n\" } ";
+        assert_eq!(tuple, Tuple::from_json(other, &schema).unwrap());
+        let missing =
+            Tuple::from_json("{\"Name\":\"\\ud83d\\ude00\",\"Tag\":\"0x\"}", &schema).unwrap();
+        assert_eq!(
+            "{\"Name\":\"\u{1f600}\",\"Count\":null,\"Ripe\":null,\"Picked\":null,\
+             \"Where\":null,\"Tag\":\"0x\",\"Score\":null}",
+            missing.to_json(&schema)
+        );
+    }
+
+    #[test]
+    fn invalid_json() {
+        let schema = create_schema();
+        let error = |json: &str| Tuple::from_json(json, &schema).unwrap_err();
+
+        for json in &[
+            "",
+            "[]",
+            "{",
+            "{\"Name\" 1}",
+            "{\"Name\":tru}",
+            "{} {}",
+            "{\"Count\":1-}",
+        ] {
+            assert_eq!(ErrorKind::InvalidData, error(json).kind(), "{}", json);
+        }
+        assert_eq!(
+            "Duplicate key; name = Count",
+            error("{\"Name\":\"a\",\"Tag\":\"0x\",\"Count\":1,\"Count\":2}").to_string()
+        );
+
+        let error = |json: &str| {
+            let error = Tuple::from_json(json, &schema).unwrap_err();
+            assert_eq!(ErrorKind::InvalidInput, error.kind());
+            error.to_string()
+        };
+        assert_eq!("Unknown column; name = Weight", error("{\"Weight\":1}"));
+        assert_eq!(
+            "Column cannot be null; column = Name",
+            error("{\"Tag\":\"0x\"}")
+        );
+        assert_eq!(
+            "Cannot convert value; column = Count",
+            error("{\"Name\":\"a\",\"Tag\":\"0x\",\"Count\":true}")
+        );
+        assert_eq!(
+            "Cannot convert value; column = Tag",
+            error("{\"Name\":\"a\",\"Tag\":\"0x1\"}")
+        );
+        assert_eq!(
+            "Cannot convert value; column = Where",
+            error("{\"Name\":\"a\",\"Tag\":\"0x\",\"Where\":[1]}")
+        );
+    }
+}
@@ -1 +1,7 @@
+pub mod bulk_loader;
+pub mod csv;
+pub mod from_row;
+pub mod json;
+pub mod table_heap;
+pub mod table_iterator;
 pub mod tuple;
@@ -0,0 +1,1010 @@
+// Functionality: A table heap is a doubly linked list of table pages holding
+// the tuples of one table. Pages are linked via |prev_page_id| and
+// |next_page_id|, starting from |first_page_id|.
+//
+// Every change is described by a log record of the transaction |txn_id|, if
+// the buffer pool has a log manager attached; see |BufferPoolManager::log_page|.
+//
+// Inserts look for space from a free-space hint onward rather than from the
+// first page, so that they do not fetch every page of a large table. The hint
+// is the page of the last insert or deletion, and is not persisted; a heap
+// opened by |TableHeap::open| starts from its first page.
+//
+// Tuples too large for a page are written to a chain of overflow pages, and
+// their slots hold stubs referring to the chains; see |Tuple::overflow|. The
+// overflow pages are not logged, but flushed before any log record refers to
+// them. A chain is not reclaimed when its stub is deleted or replaced, since
+// undoing the change brings the stub back; |TableHeap::vacuum| frees the
+// chains of the stubs it removes, and logs the pages it frees.
+//
+// All pages of a heap, overflow pages included, are allocated in the
+// tablespace of its first page; see |TableHeap::new_in|. A heap created by
+// |TableHeap::with_extents| allocates them from extents of contiguous pages,
+// listed by an allocation page right before its first page; see |ExtentPage|.
+//
+// With delta updates on (see |TableHeap::set_delta_updates|), updates store
+// only the changed bytes, chained to the previous version of the tuple; see
+// |TablePage::update_tuple_delta|. The option is not persisted, and a heap
+// reads tuples updated either way. |TableHeap::vacuum| reclaims the space of
+// deleted tuples and of previous versions.
+
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::common::config::PageId;
+use crate::common::config::TransactionId;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::config::INVALID_TRANSACTION_ID;
+use crate::common::error::*;
+use crate::common::rid::Rid;
+use crate::disk::storage_backend::StorageBackend;
+use crate::disk::tablespace::file_id;
+use crate::disk::tablespace::page_no;
+use crate::disk::tablespace::FileId;
+use crate::disk::tablespace::PRIMARY_FILE_ID;
+use crate::page::extent_page::ExtentPage;
+use crate::page::extent_page::EXTENT_SIZE;
+use crate::page::page::Page;
+use crate::page::table_page::TablePage;
+use crate::page::table_page::MAX_TUPLE_SIZE;
+use crate::page::table_page::OVERFLOW_CHUNK_SIZE;
+use crate::recovery::log_record::LogRecord;
+use crate::recovery::log_record::LogRecordBody;
+use crate::table::table_iterator::TableIterator;
+use crate::table::tuple::Tuple;
+use log::info;
+
+// Returned by |TableHeap::vacuum|.
+#[derive(Debug, Default, PartialEq)]
+pub struct VacuumStats {
+    // The tuples marked as deleted whose deletion was applied.
+    pub tuples_removed: usize,
+    // The previous versions of updated tuples freed; see
+    // |TablePage::collapse_versions|.
+    pub versions_removed: usize,
+    // The emptied pages given back to the disk manager.
+    pub pages_freed: usize,
+    // The overflow pages of the removed tuples given back to the disk
+    // manager; see |free_overflow|.
+    pub overflow_pages_freed: usize,
+}
+
+pub struct TableHeap {
+    first_page_id: PageId,
+    // The page |insert_tuple| starts looking for space from.
+    free_space_hint: PageId,
+    delta_updates: bool,
+}
+
+impl TableHeap {
+    // Creates a new table heap with one empty page.
+    pub fn new<S: StorageBackend>(
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<Self> {
+        Self::new_in(bpm, PRIMARY_FILE_ID)
+    }
+
+    // Same as |new|, but the heap lives in the tablespace |file_id|.
+    pub fn new_in<S: StorageBackend>(
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        file_id: FileId,
+    ) -> std::io::Result<Self> {
+        let page_id = bpm.new_page_in(file_id)?.page_id();
+        Self::init(bpm, page_id)
+    }
+
+    // Same as |new_in|, but the heap grows by extents of |EXTENT_SIZE|
+    // contiguous pages, so that its pages are mostly contiguous on disk. The
+    // first extent holds the allocation page, then the first page of the heap.
+    pub fn with_extents<S: StorageBackend>(
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        file_id: FileId,
+    ) -> std::io::Result<Self> {
+        let extent = bpm.allocate_pages_in(file_id, EXTENT_SIZE)?;
+        let alloc_page_id = extent.start;
+        let page_id = extent.start + 1;
+        let mut extents = ExtentPage::new();
+        extents.init(page_id, extent.start, /*used=*/ 2);
+        let page = bpm.new_page_at(alloc_page_id)?;
+        page.data_mut().copy_from_slice(extents.data());
+        page.set_page_id(alloc_page_id);
+        bpm.unpin_page(alloc_page_id, /*is_dirty=*/ true)?;
+        // The allocation page is not logged, like overflow pages.
+        bpm.flush_page(alloc_page_id)?;
+        bpm.new_page_at(page_id)?;
+        Self::init(bpm, page_id)
+    }
+
+    // Logs the creation of the heap starting at the pinned new page |page_id|.
+    fn init<S: StorageBackend>(
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        page_id: PageId,
+    ) -> std::io::Result<Self> {
+        let body = LogRecordBody::NewPage {
+            prev_page_id: INVALID_PAGE_ID,
+            page_id,
+        };
+        let logged = bpm.log_page(page_id, LogRecord::new(INVALID_TRANSACTION_ID, body));
+        bpm.unpin_page(page_id, /*is_dirty=*/ true)?;
+        logged?;
+        Ok(TableHeap {
+            first_page_id: page_id,
+            free_space_hint: page_id,
+            delta_updates: false,
+        })
+    }
+
+    // Opens an existing table heap starting at |first_page_id|.
+    pub fn open(first_page_id: PageId) -> Self {
+        TableHeap {
+            first_page_id,
+            free_space_hint: first_page_id,
+            delta_updates: false,
+        }
+    }
+
+    // The page inserts start looking for space from; see |insert_tuple|.
+    pub fn free_space_hint(&self) -> PageId {
+        self.free_space_hint
+    }
+
+    // Sets the page inserts start looking for space from, e.g. to carry the
+    // hint of a previous handle over to a new one. |page_id| needs to be a
+    // page of the heap.
+    pub fn set_free_space_hint(&mut self, page_id: PageId) {
+        self.free_space_hint = page_id;
+    }
+
+    // Sets whether |update_tuple| stores updates as deltas; off by default.
+    pub fn set_delta_updates(&mut self, enabled: bool) {
+        self.delta_updates = enabled;
+    }
+
+    pub fn first_page_id(&self) -> PageId {
+        self.first_page_id
+    }
+
+    // The tablespace of the heap.
+    pub fn file_id(&self) -> FileId {
+        file_id(self.first_page_id)
+    }
+
+    // Inserts the tuple into the first page with enough space from the
+    // free-space hint onward, appending a new page if there is none. A tuple
+    // that does not fit into an empty page goes to overflow pages.
+    pub fn insert_tuple<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        tuple: Tuple,
+    ) -> std::io::Result<Rid> {
+        let tuple = write_overflow(bpm, self, tuple)?;
+        let mut page_id = self.free_space_hint;
+        loop {
+            let page = bpm.fetch_page(page_id)?;
+            if page.can_fit(tuple.len()) {
+                self.free_space_hint = page_id;
+                let rid = page.insert_tuple(tuple.clone());
+                let rid = rid
+                    .map_err(|e| invalid_data(&e.to_string()))
+                    .and_then(|rid| log_insert(bpm, txn_id, rid, tuple));
+                bpm.unpin_page(page_id, /*is_dirty=*/ true)?;
+                return rid;
+            }
+            let next_page_id = page.next_page_id();
+            bpm.unpin_page(page_id, /*is_dirty=*/ false)?;
+            if next_page_id != INVALID_PAGE_ID {
+                page_id = next_page_id;
+                continue;
+            }
+
+            // The last page is full, append a new one. Both pages carry the
+            // LSN of the new page record, since it describes both links.
+            let new_page = self.new_page(bpm)?;
+            let new_page_id = new_page.page_id();
+            new_page.set_prev_page_id(page_id);
+            let rid = new_page.insert_tuple(tuple.clone());
+            let body = LogRecordBody::NewPage {
+                prev_page_id: page_id,
+                page_id: new_page_id,
+            };
+            let logged = bpm
+                .log_page(new_page_id, LogRecord::new(txn_id, body))
+                .and_then(|lsn| {
+                    let rid = rid.map_err(|e| invalid_data(&e.to_string()))?;
+                    Ok((lsn, log_insert(bpm, txn_id, rid, tuple)?))
+                });
+            bpm.unpin_page(new_page_id, /*is_dirty=*/ true)?;
+            self.free_space_hint = new_page_id;
+            let page = bpm.fetch_page(page_id)?;
+            page.set_next_page_id(new_page_id);
+            if let Ok((lsn, _)) = &logged {
+                page.set_lsn(*lsn);
+            }
+            bpm.unpin_page(page_id, /*is_dirty=*/ true)?;
+            return logged.map(|(_, rid)| rid);
+        }
+    }
+
+    // Marks the tuple as deleted. Returns false if there is no such tuple.
+    pub fn mark_delete<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        rid: &Rid,
+    ) -> std::io::Result<bool> {
+        let page = bpm.fetch_page(rid.page_id())?;
+        let logged = match page.get_tuple(rid) {
+            Some(tuple) => {
+                page.mark_delete(rid);
+                let body = LogRecordBody::MarkDelete {
+                    rid: rid.clone(),
+                    tuple,
+                };
+                bpm.log_page(rid.page_id(), LogRecord::new(txn_id, body))
+                    .map(|_| true)
+            }
+            None => Ok(false),
+        };
+        let deleted = *logged.as_ref().unwrap_or(&true);
+        bpm.unpin_page(rid.page_id(), deleted)?;
+        logged
+    }
+
+    // Removes the tuple from its page for good. The page becomes the
+    // free-space hint, since it has space now.
+    pub fn apply_delete<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        rid: &Rid,
+    ) -> std::io::Result<()> {
+        self.remove_tuple(bpm, txn_id, rid).map(|_| ())
+    }
+
+    // Replaces the tuple in place. Returns false if there is no such tuple, or
+    // its page does not have enough space for the new one.
+    pub fn update_tuple<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        rid: &Rid,
+        tuple: Tuple,
+    ) -> std::io::Result<bool> {
+        let tuple = write_overflow(bpm, self, tuple)?;
+        let page = bpm.fetch_page(rid.page_id())?;
+        let replaced = match self.delta_updates {
+            true => page.update_tuple_delta(rid, tuple.clone()),
+            false => page.replace_tuple(rid, tuple.clone()),
+        };
+        let logged = match replaced {
+            Some(old_tuple) => {
+                let body = LogRecordBody::Update {
+                    rid: rid.clone(),
+                    old_tuple,
+                    new_tuple: tuple,
+                };
+                bpm.log_page(rid.page_id(), LogRecord::new(txn_id, body))
+                    .map(|_| true)
+            }
+            None => Ok(false),
+        };
+        let updated = *logged.as_ref().unwrap_or(&true);
+        bpm.unpin_page(rid.page_id(), updated)?;
+        logged
+    }
+
+    pub fn get_tuple<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        rid: &Rid,
+    ) -> std::io::Result<Option<Tuple>> {
+        let tuple = bpm.fetch_page(rid.page_id())?.get_tuple(rid);
+        bpm.unpin_page(rid.page_id(), /*is_dirty=*/ false)?;
+        tuple.map(|tuple| read_overflow(bpm, tuple)).transpose()
+    }
+
+    // Reclaims the space of dead tuples: applies the deletion of the tuples
+    // marked as deleted, frees the previous versions of updated tuples and the
+    // overflow chains of the removed tuples, and unlinks and deletes the
+    // emptied pages but the first one. The caller needs to ensure that no
+    // transaction is active, e.g. by running it between statements, so that
+    // the tuples marked as deleted stay deleted.
+    //
+    // The changes are logged outside of any transaction and never undone,
+    // since the tuples they remove are deleted for good already. The log is
+    // flushed before a page is deleted, so that recovery never redoes a change
+    // into a freed page, see |RecoveryManager|, nor follows a stub to a freed
+    // chain. The free-space hint is reset to the first page; other handles on
+    // the heap must not insert with the hints they had.
+    pub fn vacuum<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<VacuumStats> {
+        info!("Vacuum table heap; first_page_id = {}", self.first_page_id);
+        let mut stats = VacuumStats::default();
+        let mut page_id = self.first_page_id;
+        while page_id != INVALID_PAGE_ID {
+            let page = bpm.fetch_page(page_id)?;
+            let deleted: Vec<Rid> = (0..page.tuple_count())
+                .filter(|&slot| matches!(page.slot(slot), Some((_, size, true)) if size > 0))
+                .map(|slot| Rid::new(page_id, slot))
+                .collect();
+            bpm.unpin_page(page_id, /*is_dirty=*/ false)?;
+            for rid in deleted.iter() {
+                if let Some(tuple) = self.remove_tuple(bpm, INVALID_TRANSACTION_ID, rid)? {
+                    stats.overflow_pages_freed += free_overflow(bpm, &tuple)?;
+                }
+            }
+            stats.tuples_removed += deleted.len();
+
+            let page = bpm.fetch_page(page_id)?;
+            let versions = page.collapse_versions();
+            let is_empty = page.is_empty();
+            let (prev_page_id, next_page_id) = (page.prev_page_id(), page.next_page_id());
+            bpm.unpin_page(page_id, /*is_dirty=*/ versions > 0)?;
+            stats.versions_removed += versions;
+
+            if is_empty && page_id != self.first_page_id {
+                free_page(bpm, prev_page_id, page_id, next_page_id)?;
+                if self.free_space_hint == page_id {
+                    self.free_space_hint = self.first_page_id;
+                }
+                stats.pages_freed += 1;
+            }
+            page_id = next_page_id;
+        }
+        self.free_space_hint = self.first_page_id;
+        info!("Vacuumed table heap; stats = {:?}", stats);
+        Ok(stats)
+    }
+
+    // Removes the tuple from its page for good, see |apply_delete|, and
+    // returns it as stored, i.e. the stub of an overflow tuple.
+    fn remove_tuple<S: StorageBackend>(
+        &mut self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+        txn_id: TransactionId,
+        rid: &Rid,
+    ) -> std::io::Result<Option<Tuple>> {
+        let logged = match bpm.fetch_page(rid.page_id())?.apply_delete(rid) {
+            Some(tuple) => {
+                self.free_space_hint = rid.page_id();
+                let body = LogRecordBody::ApplyDelete {
+                    rid: rid.clone(),
+                    tuple: tuple.clone(),
+                };
+                bpm.log_page(rid.page_id(), LogRecord::new(txn_id, body))
+                    .map(|_| Some(tuple))
+            }
+            None => Ok(None),
+        };
+        bpm.unpin_page(rid.page_id(), /*is_dirty=*/ true)?;
+        logged
+    }
+
+    // Returns an iterator over all live tuples of the heap.
+    pub fn iter<'a, S: StorageBackend>(
+        &self,
+        bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> TableIterator<'a, S> {
+        TableIterator::new(bpm, self.first_page_id)
+    }
+
+    // The extents of the heap, as their first page IDs, or an empty list if
+    // the heap does not grow by extents.
+    pub fn extents<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<Vec<PageId>> {
+        Ok(match self.allocation_page(bpm)? {
+            Some(extents) => (0..extents.extent_count())
+                .map(|idx| extents.extent(idx))
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+
+    // Returns the IDs of the pages of the heap: its allocation page if it grows
+    // by extents, its table pages, and the overflow pages of its live tuples.
+    // Unused pages of its extents are left out.
+    pub fn page_ids<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<Vec<PageId>> {
+        let mut page_ids = Vec::new();
+        if self.allocation_page(bpm)?.is_some() {
+            page_ids.push(self.first_page_id - 1);
+        }
+        let mut page_id = self.first_page_id;
+        while page_id != INVALID_PAGE_ID {
+            let page = bpm.fetch_page(page_id)?;
+            let chains: Vec<PageId> = (0..page.tuple_count())
+                .filter_map(|slot| page.get_tuple(&Rid::new(page_id, slot)))
+                .filter_map(|tuple| tuple.overflow_ref().map(|(_, first_page_id)| first_page_id))
+                .collect();
+            let next_page_id = page.next_page_id();
+            bpm.unpin_page(page_id, /*is_dirty=*/ false)?;
+            page_ids.push(page_id);
+            for mut chain_page_id in chains {
+                while chain_page_id != INVALID_PAGE_ID {
+                    page_ids.push(chain_page_id);
+                    let next_chain_page_id = bpm.fetch_page(chain_page_id)?.next_page_id();
+                    bpm.unpin_page(chain_page_id, /*is_dirty=*/ false)?;
+                    chain_page_id = next_chain_page_id;
+                }
+            }
+            page_id = next_page_id;
+        }
+        Ok(page_ids)
+    }
+
+    // Creates and pins a new page for the heap, the next page of its last
+    // extent if it grows by extents. A new extent is allocated when the last
+    // one is used up, and single pages once the allocation page is full.
+    fn new_page<'a, S: StorageBackend>(
+        &self,
+        bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<&'a mut TablePage> {
+        let mut extents = match self.allocation_page(bpm)? {
+            Some(extents) => extents,
+            None => return bpm.new_page_in(self.file_id()),
+        };
+        let page_id = match extents.next_page_id() {
+            Some(page_id) => page_id,
+            None if extents.is_full() => return bpm.new_page_in(self.file_id()),
+            None => {
+                let extent = bpm.allocate_pages_in(self.file_id(), EXTENT_SIZE)?;
+                extents.push_extent(extent.start)?;
+                // It is safe to unwrap here, because the new extent is unused.
+                extents.next_page_id().unwrap()
+            }
+        };
+        let alloc_page_id = self.first_page_id - 1;
+        let page = bpm.fetch_page(alloc_page_id)?;
+        page.data_mut().copy_from_slice(extents.data());
+        bpm.unpin_page(alloc_page_id, /*is_dirty=*/ true)?;
+        bpm.flush_page(alloc_page_id)?;
+        bpm.new_page_at(page_id)
+    }
+
+    // Reads the allocation page, right before the first page, if the heap
+    // grows by extents.
+    fn allocation_page<S: StorageBackend>(
+        &self,
+        bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    ) -> std::io::Result<Option<ExtentPage>> {
+        let alloc_page_id = self.first_page_id - 1;
+        if page_no(self.first_page_id) == 0 || !bpm.is_allocated(alloc_page_id) {
+            return Ok(None);
+        }
+        let mut extents = ExtentPage::new();
+        extents
+            .data_mut()
+            .copy_from_slice(bpm.fetch_page(alloc_page_id)?.data());
+        bpm.unpin_page(alloc_page_id, /*is_dirty=*/ false)?;
+        Ok(match extents.is_owned_by(self.first_page_id) {
+            true => Some(extents),
+            false => None,
+        })
+    }
+}
+
+// Writes |tuple| to a chain of new overflow pages and returns the stub
+// referring to it, if it does not fit into a page. Otherwise, returns |tuple|.
+pub(crate) fn write_overflow<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    heap: &TableHeap,
+    tuple: Tuple,
+) -> std::io::Result<Tuple> {
+    if tuple.len() <= MAX_TUPLE_SIZE {
+        return Ok(tuple);
+    }
+    // Chunks are written from the last one, so that each page knows the next.
+    let mut next_page_id = INVALID_PAGE_ID;
+    for chunk in tuple.data().chunks(OVERFLOW_CHUNK_SIZE).rev() {
+        let page = heap.new_page(bpm)?;
+        let page_id = page.page_id();
+        page.set_overflow_chunk(chunk);
+        page.set_next_page_id(next_page_id);
+        bpm.unpin_page(page_id, /*is_dirty=*/ true)?;
+        bpm.flush_page(page_id)?;
+        next_page_id = page_id;
+    }
+    Ok(Tuple::overflow(&tuple, next_page_id))
+}
+
+// Reads the tuple the overflow stub |tuple| refers to. Other tuples are
+// returned as they are. Returns |InvalidData| if the chain is broken.
+pub(crate) fn read_overflow<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    tuple: Tuple,
+) -> std::io::Result<Tuple> {
+    let (len, mut page_id) = match tuple.overflow_ref() {
+        Some(overflow) => overflow,
+        None => return Ok(tuple),
+    };
+    let mut data = Vec::with_capacity(len);
+    while data.len() < len {
+        if page_id == INVALID_PAGE_ID {
+            return Err(invalid_data("Overflow chain is truncated"));
+        }
+        let page = bpm.fetch_page(page_id)?;
+        let chunk = page.overflow_chunk();
+        let next_page_id = page.next_page_id();
+        let empty = chunk.is_empty();
+        data.extend_from_slice(chunk);
+        bpm.unpin_page(page_id, /*is_dirty=*/ false)?;
+        if empty {
+            return Err(invalid_data("Overflow page is empty"));
+        }
+        page_id = next_page_id;
+    }
+    data.truncate(len);
+    Ok(Tuple::from_overflow(&tuple, data))
+}
+
+// Deletes the chain of overflow pages the stub |tuple| refers to. Other tuples
+// have no chain. Returns the number of pages deleted.
+fn free_overflow<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    tuple: &Tuple,
+) -> std::io::Result<usize> {
+    let mut page_id = match tuple.overflow_ref() {
+        Some((_, first_page_id)) => first_page_id,
+        None => return Ok(0),
+    };
+    let mut freed = 0;
+    while page_id != INVALID_PAGE_ID {
+        let next_page_id = bpm.fetch_page(page_id)?.next_page_id();
+        bpm.unpin_page(page_id, /*is_dirty=*/ false)?;
+        free_page(bpm, INVALID_PAGE_ID, page_id, INVALID_PAGE_ID)?;
+        freed += 1;
+        page_id = next_page_id;
+    }
+    Ok(freed)
+}
+
+// Unlinks the page |page_id| from between |prev_page_id| and |next_page_id|,
+// either of which may be invalid, and deletes it. The change is logged outside
+// of any transaction, and the log flushed before the page is deleted.
+fn free_page<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    prev_page_id: PageId,
+    page_id: PageId,
+    next_page_id: PageId,
+) -> std::io::Result<()> {
+    let body = LogRecordBody::FreePage {
+        prev_page_id,
+        page_id,
+        next_page_id,
+    };
+    let record = LogRecord::new(INVALID_TRANSACTION_ID, body);
+    // The record is stamped on the previous page, or on the freed page itself
+    // if there is none.
+    let lsn = match prev_page_id {
+        INVALID_PAGE_ID => {
+            bpm.fetch_page(page_id)?;
+            let logged = bpm.log_page(page_id, record);
+            bpm.unpin_page(page_id, /*is_dirty=*/ false)?;
+            logged?
+        }
+        _ => {
+            bpm.fetch_page(prev_page_id)?
+                .set_next_page_id(next_page_id);
+            let logged = bpm.log_page(prev_page_id, record);
+            bpm.unpin_page(prev_page_id, /*is_dirty=*/ true)?;
+            logged?
+        }
+    };
+    if next_page_id != INVALID_PAGE_ID {
+        let page = bpm.fetch_page(next_page_id)?;
+        page.set_prev_page_id(prev_page_id);
+        page.set_lsn(lsn);
+        bpm.unpin_page(next_page_id, /*is_dirty=*/ true)?;
+    }
+    if let Some(log_mgr) = bpm.log_manager() {
+        log_mgr.flush()?;
+    }
+    bpm.delete_page(page_id)
+}
+
+// Logs the insertion of |tuple| at |rid|, whose page is pinned.
+fn log_insert<S: StorageBackend>(
+    bpm: &mut DefaultBufferPoolManager<TablePage, S>,
+    txn_id: TransactionId,
+    rid: Rid,
+    tuple: Tuple,
+) -> std::io::Result<Rid> {
+    let body = LogRecordBody::Insert {
+        rid: rid.clone(),
+        tuple,
+    };
+    bpm.log_page(rid.page_id(), LogRecord::new(txn_id, body))?;
+    Ok(rid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::catalog::column::Column;
+    use crate::catalog::schema::Schema;
+    use crate::common::config::Lsn;
+    use crate::common::config::INVALID_LSN;
+    use crate::common::config::PAGE_SIZE;
+    use crate::common::reinterpret;
+    use crate::common::config::HEADER_PAGE_ID;
+    use crate::disk::disk_manager::BITMAP_FILE_SUFFIX;
+    use crate::disk::tablespace::TABLESPACES_FILE_SUFFIX;
+    use crate::recovery::log_manager::LogManager;
+    use crate::testing::file_deleter::FileDeleter;
+    use crate::types::types::Types;
+    use crate::types::value::Value;
+
+    fn create_tuple(id: u64) -> Tuple {
+        // A serialized tuple of 1000 bytes starting with |id|.
+        let mut buffer = vec![0; 1008];
+        reinterpret::write_u64(&mut buffer, 1000);
+        reinterpret::write_u64(&mut buffer[8..], id);
+        let mut tuple = Tuple::default();
+        tuple.deserialize_from(&buffer);
+        tuple
+    }
+
+    #[test]
+    fn log_changes() {
+        let file_path = "/tmp/testfile.table_heap.1.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let log_path = "/tmp/testfile.table_heap.1.log";
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(log_path);
+
+        let mut bpm = DefaultBufferPoolManager::<TablePage>::new(2, file_path).unwrap();
+        bpm.set_log_manager(LogManager::new(log_path).unwrap());
+        let mut heap = TableHeap::new(&mut bpm).unwrap();
+
+        // 3 tuples fit into a page, so the 4th one goes to a new page.
+        let mut rids = Vec::new();
+        for id in 0..4 {
+            rids.push(heap.insert_tuple(&mut bpm, 1, create_tuple(id)).unwrap());
+        }
+        assert!(heap.mark_delete(&mut bpm, 1, &rids[0]).unwrap());
+        assert!(!heap.mark_delete(&mut bpm, 1, &rids[0]).unwrap());
+        heap.apply_delete(&mut bpm, 1, &rids[0]).unwrap();
+        assert!(heap.update_tuple(&mut bpm, 1, &rids[1], create_tuple(9)).unwrap());
+        assert_eq!(Some(create_tuple(9)), heap.get_tuple(&mut bpm, &rids[1]).unwrap());
+
+        // Every page carries the LSN of its latest change.
+        let first_page_lsn = bpm.fetch_page(rids[1].page_id()).unwrap().lsn();
+        let last_page_lsn = bpm.fetch_page(rids[3].page_id()).unwrap().lsn();
+        assert_eq!((9, 6), (first_page_lsn, last_page_lsn));
+        bpm.unpin_page(rids[1].page_id(), /*is_dirty=*/ false).unwrap();
+        bpm.unpin_page(rids[3].page_id(), /*is_dirty=*/ false).unwrap();
+        assert!(bpm.close().is_ok());
+
+        let records = LogManager::new(log_path).unwrap().records().unwrap();
+        let new_page = LogRecordBody::NewPage {
+            prev_page_id: rids[0].page_id(),
+            page_id: rids[3].page_id(),
+        };
+        assert_eq!(&new_page, records[4].body());
+        let deleted = LogRecordBody::ApplyDelete {
+            rid: rids[0].clone(),
+            tuple: create_tuple(0),
+        };
+        assert_eq!(&deleted, records[7].body());
+
+        // Records of the transaction are chained, the heap's first page is not
+        // part of it.
+        let lsns: Vec<(Lsn, Lsn)> = records.iter().map(|r| (r.lsn(), r.prev_lsn())).collect();
+        let mut expected = vec![(1, INVALID_LSN)];
+        expected.extend((2..10).map(|lsn| (lsn, lsn - 1)));
+        expected[1].1 = INVALID_LSN;
+        assert_eq!(expected, lsns);
+        assert_eq!(INVALID_TRANSACTION_ID, records[0].txn_id());
+    }
+
+    #[test]
+    fn overflow() {
+        let file_path = "/tmp/testfile.table_heap.2.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let log_path = "/tmp/testfile.table_heap.2.log";
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(log_path);
+
+        let schema = Schema::new(vec![
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("Blob".to_string(), Types::varbinary(), 0),
+        ]);
+        let create_blob_tuple = |id: i64, len: usize| {
+            let bytes = (0..len).map(|i| (i % 251) as u8).collect();
+            let values = vec![
+                Value::new(Types::BigInt(id)),
+                Value::new(Types::Varbinary(bytes)),
+            ];
+            Tuple::new(&values, &schema)
+        };
+
+        let mut bpm = DefaultBufferPoolManager::<TablePage>::new(2, file_path).unwrap();
+        bpm.set_log_manager(LogManager::new(log_path).unwrap());
+        let mut heap = TableHeap::new(&mut bpm).unwrap();
+
+        // Tuples spanning 1, 3 and 2 overflow pages, next to a small one.
+        let tuples = vec![
+            create_blob_tuple(0, MAX_TUPLE_SIZE),
+            create_blob_tuple(1, 3 * OVERFLOW_CHUNK_SIZE - 100),
+            create_tuple(2),
+            create_blob_tuple(3, OVERFLOW_CHUNK_SIZE),
+        ];
+        let mut rids = Vec::new();
+        for tuple in tuples.iter() {
+            rids.push(heap.insert_tuple(&mut bpm, 1, tuple.clone()).unwrap());
+        }
+        for (rid, tuple) in rids.iter().zip(tuples.iter()) {
+            assert_eq!(Some(tuple), heap.get_tuple(&mut bpm, rid).unwrap().as_ref());
+        }
+        let scanned: Vec<Tuple> = heap.iter(&mut bpm).map(|item| item.unwrap().1).collect();
+        assert_eq!(tuples, scanned);
+        let blob = scanned[1].nth_value(&schema, 1);
+        assert_eq!(3 * OVERFLOW_CHUNK_SIZE - 100, blob.len());
+
+        // Only the stub is stored in the slot.
+        let page = bpm.fetch_page(rids[1].page_id()).unwrap();
+        assert!(page.get_tuple(&rids[1]).unwrap().is_overflow());
+        assert!(!page.get_tuple(&rids[2]).unwrap().is_overflow());
+        bpm.unpin_page(rids[1].page_id(), /*is_dirty=*/ false).unwrap();
+
+        // A small tuple replaces a large one in place, and the other way round.
+        assert!(heap.update_tuple(&mut bpm, 1, &rids[1], create_tuple(5)).unwrap());
+        let large = create_blob_tuple(6, 2 * PAGE_SIZE);
+        assert!(heap.update_tuple(&mut bpm, 1, &rids[2], large.clone()).unwrap());
+        assert_eq!(Some(create_tuple(5)), heap.get_tuple(&mut bpm, &rids[1]).unwrap());
+        assert_eq!(Some(large), heap.get_tuple(&mut bpm, &rids[2]).unwrap());
+        assert!(bpm.close().is_ok());
+
+        // Stubs survive in the log.
+        let records = LogManager::new(log_path).unwrap().records().unwrap();
+        let inserted: Vec<bool> = records
+            .iter()
+            .filter_map(|record| match record.body() {
+                LogRecordBody::Insert { tuple, .. } => Some(tuple.is_overflow()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![true, true, false, true], inserted);
+    }
+
+    #[test]
+    fn tablespace() {
+        let file_path = "/tmp/testfile.table_heap.3.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let registry_path = file_path.to_string() + TABLESPACES_FILE_SUFFIX;
+        let space_path = "/tmp/testfile.table_heap.3.space.db";
+        let space_bitmap_path = space_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(&registry_path);
+        file_deleter.push(space_path);
+        file_deleter.push(&space_bitmap_path);
+
+        let large = {
+            let mut buffer = vec![0; 2 * PAGE_SIZE + 8];
+            reinterpret::write_u64(&mut buffer, 2 * PAGE_SIZE as u64);
+            let mut tuple = Tuple::default();
+            tuple.deserialize_from(&buffer);
+            tuple
+        };
+        let first_page_id;
+        {
+            let mut bpm = DefaultBufferPoolManager::<TablePage>::new(2, file_path).unwrap();
+            let space = bpm.add_tablespace(space_path).unwrap();
+            let mut heap = TableHeap::new_in(&mut bpm, space).unwrap();
+            first_page_id = heap.first_page_id();
+            assert_eq!(space, heap.file_id());
+            heap.insert_tuple(&mut bpm, 1, large.clone()).unwrap();
+            for id in 0..4 {
+                let rid = heap.insert_tuple(&mut bpm, 1, create_tuple(id)).unwrap();
+                assert_eq!(space, file_id(rid.page_id()));
+            }
+            // Overflow pages are in the tablespace too.
+            assert_eq!(HEADER_PAGE_ID, bpm.new_page().unwrap().page_id());
+            assert!(bpm.close().is_ok());
+        }
+
+        let mut bpm = DefaultBufferPoolManager::<TablePage>::new(2, file_path).unwrap();
+        let heap = TableHeap::open(first_page_id);
+        let mut expected = vec![large];
+        expected.extend((0..4).map(create_tuple));
+        let scanned: Vec<Tuple> = heap.iter(&mut bpm).map(|item| item.unwrap().1).collect();
+        assert_eq!(expected, scanned);
+    }
+
+    #[test]
+    fn extents() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(4);
+        let mut page_ids = vec![];
+        {
+            let mut heap = TableHeap::with_extents(&mut bpm, PRIMARY_FILE_ID).unwrap();
+            assert_eq!(1, heap.first_page_id());
+            assert_eq!(vec![0], heap.extents(&mut bpm).unwrap());
+
+            // Other allocations go after the extent.
+            let page_id = bpm.new_page().unwrap().page_id();
+            assert_eq!(EXTENT_SIZE as PageId, page_id);
+            assert!(bpm.unpin_page(page_id, /*is_dirty=*/ false).is_ok());
+
+            let mut id = 0;
+            while page_ids.len() < EXTENT_SIZE {
+                let rid = heap.insert_tuple(&mut bpm, 1, create_tuple(id)).unwrap();
+                if page_ids.last() != Some(&rid.page_id()) {
+                    page_ids.push(rid.page_id());
+                }
+                id += 1;
+            }
+            assert!(bpm.flush_all_pages().is_ok());
+        }
+
+        // The heap fills the first extent, then continues in a new one.
+        let mut expected: Vec<PageId> = (1..(EXTENT_SIZE as PageId)).collect();
+        expected.push(EXTENT_SIZE as PageId + 1);
+        assert_eq!(expected, page_ids);
+
+        // A heap opened anew reads its extents from the allocation page.
+        let heap = TableHeap::open(1);
+        let extents = vec![0, EXTENT_SIZE as PageId + 1];
+        assert_eq!(extents, heap.extents(&mut bpm).unwrap());
+        assert_eq!(EXTENT_SIZE as PageId + 2, heap.new_page(&mut bpm).unwrap().page_id());
+        assert_eq!(Vec::<PageId>::new(), TableHeap::open(2).extents(&mut bpm).unwrap());
+    }
+
+    #[test]
+    fn delta_updates() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(2);
+        let first_page_id;
+        let mut rids = Vec::new();
+        {
+            let mut heap = TableHeap::new(&mut bpm).unwrap();
+            heap.set_delta_updates(true);
+            first_page_id = heap.first_page_id();
+            for id in 0..3 {
+                rids.push(heap.insert_tuple(&mut bpm, 1, create_tuple(id)).unwrap());
+            }
+            // The updates change the first bytes only, so they are stored as
+            // deltas, and every fifth one rewrites the tuple in full.
+            for id in 10..20 {
+                assert!(heap.update_tuple(&mut bpm, 1, &rids[1], create_tuple(id)).unwrap());
+                let tuple = heap.get_tuple(&mut bpm, &rids[1]).unwrap();
+                assert_eq!(Some(create_tuple(id)), tuple);
+            }
+            assert!(bpm.flush_all_pages().is_ok());
+        }
+
+        let heap = TableHeap::open(first_page_id);
+        let expected = vec![create_tuple(0), create_tuple(19), create_tuple(2)];
+        let scanned: Vec<Tuple> = heap.iter(&mut bpm).map(|item| item.unwrap().1).collect();
+        assert_eq!(expected, scanned);
+    }
+
+    #[test]
+    fn vacuum() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(2);
+        let mut heap = TableHeap::new(&mut bpm).unwrap();
+        heap.set_delta_updates(true);
+        // 3 tuples fit into a page, so the tuples take 3 pages.
+        let rids: Vec<Rid> = (0..9)
+            .map(|id| heap.insert_tuple(&mut bpm, 1, create_tuple(id)).unwrap())
+            .collect();
+        for rid in [&rids[0], &rids[3], &rids[4], &rids[5]].iter() {
+            assert!(heap.mark_delete(&mut bpm, 1, rid).unwrap());
+        }
+        assert!(heap.update_tuple(&mut bpm, 1, &rids[1], create_tuple(10)).unwrap());
+        assert!(heap.update_tuple(&mut bpm, 1, &rids[1], create_tuple(11)).unwrap());
+
+        let expected = VacuumStats {
+            tuples_removed: 4,
+            versions_removed: 2,
+            pages_freed: 1,
+            overflow_pages_freed: 0,
+        };
+        assert_eq!(expected, heap.vacuum(&mut bpm).unwrap());
+        assert!(!bpm.is_allocated(rids[3].page_id()));
+        let scanned: Vec<Tuple> = heap.iter(&mut bpm).map(|item| item.unwrap().1).collect();
+        let expected: Vec<Tuple> = [11, 2, 6, 7, 8].iter().map(|&id| create_tuple(id)).collect();
+        assert_eq!(expected, scanned);
+        assert_eq!(VacuumStats::default(), heap.vacuum(&mut bpm).unwrap());
+
+        // The heap stays linked both ways.
+        let last_page = bpm.fetch_page(rids[6].page_id()).unwrap();
+        assert_eq!(rids[0].page_id(), last_page.prev_page_id());
+        assert!(bpm.unpin_page(rids[6].page_id(), /*is_dirty=*/ false).is_ok());
+    }
+
+    #[test]
+    fn free_space_hint() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(2);
+        let mut heap = TableHeap::new(&mut bpm).unwrap();
+        // 3 tuples fit into a page, so the tuples take 10 pages.
+        let rids: Vec<Rid> = (0..30)
+            .map(|id| heap.insert_tuple(&mut bpm, 1, create_tuple(id)).unwrap())
+            .collect();
+        assert_eq!(rids[29].page_id(), heap.free_space_hint());
+
+        // Appending a page fetches the last page only, not the whole heap.
+        let fetches = |bpm: &MemoryBufferPoolManager<TablePage>| {
+            let stats = bpm.stats();
+            stats.hits + stats.misses
+        };
+        let before = fetches(&bpm);
+        let rid = heap.insert_tuple(&mut bpm, 1, create_tuple(30)).unwrap();
+        assert_ne!(rids[29].page_id(), rid.page_id());
+        assert_eq!(2, fetches(&bpm) - before);
+
+        // A deletion makes room on a page, which the next insert fills.
+        assert!(heap.mark_delete(&mut bpm, 1, &rids[4]).unwrap());
+        heap.apply_delete(&mut bpm, 1, &rids[4]).unwrap();
+        assert_eq!(rids[4].page_id(), heap.free_space_hint());
+        let rid = heap.insert_tuple(&mut bpm, 1, create_tuple(31)).unwrap();
+        assert_eq!(rids[4].page_id(), rid.page_id());
+
+        // A heap opened anew starts from its first page.
+        let heap = TableHeap::open(heap.first_page_id());
+        assert_eq!(rids[0].page_id(), heap.free_space_hint());
+    }
+
+    #[test]
+    fn vacuum_overflow() {
+        let schema = Schema::new(vec![Column::new(
+            "Blob".to_string(),
+            Types::varbinary(),
+            0,
+        )]);
+        let create_blob_tuple = |len: usize| {
+            let values = vec![Value::new(Types::Varbinary(vec![7; len]))];
+            Tuple::new(&values, &schema)
+        };
+        let allocated = |bpm: &MemoryBufferPoolManager<TablePage>| {
+            (0..64).filter(|&page_id| bpm.is_allocated(page_id)).count()
+        };
+
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(2);
+        let mut heap = TableHeap::new(&mut bpm).unwrap();
+        // Tuples spanning 3 and 2 overflow pages, next to a small one.
+        let rids: Vec<Rid> = vec![
+            create_blob_tuple(3 * OVERFLOW_CHUNK_SIZE - 100),
+            create_blob_tuple(2 * OVERFLOW_CHUNK_SIZE - 100),
+            create_tuple(2),
+        ]
+        .into_iter()
+        .map(|tuple| heap.insert_tuple(&mut bpm, 1, tuple).unwrap())
+        .collect();
+        assert_eq!(6, allocated(&bpm));
+
+        assert!(heap.mark_delete(&mut bpm, 1, &rids[0]).unwrap());
+        assert!(heap.mark_delete(&mut bpm, 1, &rids[2]).unwrap());
+        let expected = VacuumStats {
+            tuples_removed: 2,
+            versions_removed: 0,
+            pages_freed: 0,
+            overflow_pages_freed: 3,
+        };
+        assert_eq!(expected, heap.vacuum(&mut bpm).unwrap());
+        assert_eq!(3, allocated(&bpm));
+
+        // The remaining chain is intact, and freed pages are reused.
+        let expected = create_blob_tuple(2 * OVERFLOW_CHUNK_SIZE - 100);
+        assert_eq!(Some(expected), heap.get_tuple(&mut bpm, &rids[1]).unwrap());
+        heap.insert_tuple(&mut bpm, 1, create_blob_tuple(MAX_TUPLE_SIZE))
+            .unwrap();
+        assert_eq!(4, allocated(&bpm));
+    }
+}
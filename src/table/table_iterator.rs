@@ -0,0 +1,215 @@
+// Functionality: Sequential scan over a table heap. Walks the pages via
+// |next_page_id| and yields every live tuple with its RID. Each page is pinned
+// only while it is being read. Overflow stubs are resolved into their tuples.
+//
+// Once a scan has read more pages than the scan ring threshold of the pool, it
+// reads the rest through the scan ring rather than fetching them, so that a
+// large one-off scan does not evict the working set; see
+// |BufferPoolManager::read_page_for_scan|.
+
+use crate::buffer::buffer_pool_manager::DefaultBufferPoolManager;
+use crate::common::config::PageId;
+use crate::common::config::INVALID_PAGE_ID;
+use crate::common::rid::Rid;
+use crate::disk::disk_manager::DiskManager;
+use crate::disk::storage_backend::StorageBackend;
+use crate::page::table_page::TablePage;
+use crate::table::table_heap::read_overflow;
+use crate::table::tuple::Tuple;
+
+pub struct TableIterator<'a, S: StorageBackend = DiskManager> {
+    bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+    position: ScanPosition,
+}
+
+// Where a scan continues from; see |TableIterator::position|.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScanPosition {
+    pub page_id: PageId,
+    pub slot_num: usize,
+    // The number of pages the scan moved past.
+    pub pages_read: usize,
+}
+
+impl ScanPosition {
+    // The start of a scan from |first_page_id|.
+    pub fn new(first_page_id: PageId) -> Self {
+        ScanPosition {
+            page_id: first_page_id,
+            slot_num: 0,
+            pages_read: 0,
+        }
+    }
+}
+
+impl<'a, S: StorageBackend> TableIterator<'a, S> {
+    pub fn new(bpm: &'a mut DefaultBufferPoolManager<TablePage, S>, first_page_id: PageId) -> Self {
+        TableIterator {
+            bpm,
+            position: ScanPosition::new(first_page_id),
+        }
+    }
+
+    // Resumes a scan from |position|, e.g. one that released the buffer pool
+    // in between; see |position|.
+    pub fn resume(
+        bpm: &'a mut DefaultBufferPoolManager<TablePage, S>,
+        position: ScanPosition,
+    ) -> Self {
+        TableIterator { bpm, position }
+    }
+
+    // Returns where the scan continues from.
+    pub fn position(&self) -> ScanPosition {
+        self.position
+    }
+}
+
+impl<'a, S: StorageBackend> Iterator for TableIterator<'a, S> {
+    type Item = std::io::Result<(Rid, Tuple)>;
+
+    // An I/O error is yielded, and ends the iteration.
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.next_tuple().transpose();
+        if let Some(Err(_)) = result {
+            self.position.page_id = INVALID_PAGE_ID;
+        }
+        result
+    }
+}
+
+impl<'a, S: StorageBackend> TableIterator<'a, S> {
+    fn next_tuple(&mut self) -> std::io::Result<Option<(Rid, Tuple)>> {
+        let threshold = self.bpm.scan_ring_threshold();
+        while self.position.page_id != INVALID_PAGE_ID {
+            let position = &mut self.position;
+            let page_id = position.page_id;
+            let in_ring = threshold > 0 && position.pages_read >= threshold;
+            let page: &TablePage = match in_ring {
+                true => self.bpm.read_page_for_scan(page_id)?,
+                false => self.bpm.fetch_page(page_id)?,
+            };
+            let mut found = None;
+            while found.is_none() && position.slot_num < page.tuple_count() {
+                let rid = Rid::new(page_id, position.slot_num);
+                found = page.get_tuple(&rid).map(|tuple| (rid, tuple));
+                position.slot_num += 1;
+            }
+            if found.is_none() {
+                position.page_id = page.next_page_id();
+                position.slot_num = 0;
+                position.pages_read += 1;
+            }
+            if !in_ring {
+                self.bpm.unpin_page(page_id, /*is_dirty=*/ false)?;
+            }
+            if let Some((rid, tuple)) = found {
+                let tuple = read_overflow(self.bpm, tuple)?;
+                return Ok(Some((rid, tuple)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_pool_manager::MemoryBufferPoolManager;
+    use crate::common::config::INVALID_TRANSACTION_ID;
+    use crate::common::reinterpret;
+    use crate::page::page::Page;
+    use crate::table::table_heap::TableHeap;
+
+    fn create_tuple(id: u64) -> Tuple {
+        // A serialized tuple of 500 bytes starting with |id|.
+        let mut buffer = vec![0; 508];
+        reinterpret::write_u64(&mut buffer, 500);
+        reinterpret::write_u64(&mut buffer[8..], id);
+        let mut tuple = Tuple::default();
+        tuple.deserialize_from(&buffer);
+        tuple
+    }
+
+    #[test]
+    fn scan_table_heap() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(2);
+        let mut heap = TableHeap::new(&mut bpm).unwrap();
+        assert_eq!(0, heap.iter(&mut bpm).count());
+
+        // 7 tuples fit into a page, so the heap spans more pages than the pool.
+        let mut rids = Vec::new();
+        for id in 0..30 {
+            let rid = heap.insert_tuple(&mut bpm, INVALID_TRANSACTION_ID, create_tuple(id));
+            rids.push(rid.unwrap());
+        }
+        assert_ne!(rids[0].page_id(), rids[29].page_id());
+        assert!(heap.mark_delete(&mut bpm, INVALID_TRANSACTION_ID, &rids[3]).unwrap());
+        heap.apply_delete(&mut bpm, INVALID_TRANSACTION_ID, &rids[10]).unwrap();
+
+        let scanned: Vec<(Rid, Tuple)> = heap.iter(&mut bpm).map(Result::unwrap).collect();
+        assert_eq!(28, scanned.len());
+        let expected: Vec<u64> = (0..30).filter(|&id| id != 3 && id != 10).collect();
+        for ((rid, tuple), id) in scanned.iter().zip(expected) {
+            assert_eq!(rids[id as usize], *rid);
+            assert_eq!(create_tuple(id), *tuple);
+        }
+
+        // No page is left pinned.
+        let pinned = [
+            bpm.new_page().unwrap().page_id(),
+            bpm.new_page().unwrap().page_id(),
+        ];
+
+        // With every frame pinned, no heap page can be fetched. The error is
+        // yielded, and ends the scan.
+        let mut iter = heap.iter(&mut bpm);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+        for page_id in pinned.iter() {
+            assert!(bpm.unpin_page(*page_id, /*is_dirty=*/ false).is_ok());
+        }
+        assert_eq!(28, heap.iter(&mut bpm).count());
+    }
+
+    #[test]
+    fn scan_ring() {
+        let mut bpm = MemoryBufferPoolManager::<TablePage>::in_memory(3);
+        let mut heap = TableHeap::new(&mut bpm).unwrap();
+        let mut rids = Vec::new();
+        for id in 0..30 {
+            let rid = heap.insert_tuple(&mut bpm, INVALID_TRANSACTION_ID, create_tuple(id));
+            rids.push(rid.unwrap());
+        }
+        let hot_page_id = bpm.new_page().unwrap().page_id();
+        assert!(bpm.unpin_page(hot_page_id, /*is_dirty=*/ true).is_ok());
+        bpm.flush_all_pages().unwrap();
+
+        // Past the first page, the scan reads through the ring, and the hot
+        // page stays in the pool.
+        bpm.set_scan_ring_threshold(1);
+        assert_eq!(30, heap.iter(&mut bpm).count());
+        assert!(bpm.page_version(hot_page_id).is_some());
+        assert!(bpm.page_version(rids[14].page_id()).is_none());
+        assert_eq!(0, bpm.stats().pinned_pages);
+
+        // Writes go through the pool, and are seen by the next scan.
+        let last_page_id = rids[29].page_id();
+        heap.update_tuple(&mut bpm, INVALID_TRANSACTION_ID, &rids[29], create_tuple(100))
+            .unwrap();
+        let mut position = ScanPosition::new(heap.first_page_id());
+        let mut scanned = Vec::new();
+        loop {
+            let mut iter = TableIterator::resume(&mut bpm, position);
+            match iter.next() {
+                Some(item) => scanned.push(item.unwrap().1),
+                None => break,
+            }
+            position = iter.position();
+        }
+        assert_eq!(30, scanned.len());
+        assert_eq!(create_tuple(100), scanned[29]);
+        assert!(position.pages_read > 1);
+        assert!(bpm.page_version(last_page_id).is_some());
+    }
+}
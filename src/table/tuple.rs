@@ -1,5 +1,36 @@
+// Functionality: A row of a table, serialized as the values of its columns.
+// Inlined values are stored at their column offsets, and the others at the end
+// of the tuple, referred to by their offsets.
+//
+// Tuples start with a null bitmap, in which bit |idx % 8| of byte |idx / 8| is
+// set if column |idx| is null:
+//  ------------------------------------------------------------------------
+// | NullBitmap ((ColumnCount + 7) / 8) | Inlined values | Uninlined values |
+//  ------------------------------------------------------------------------
+// Column offsets are relative to the end of the bitmap. Nullness is read from
+// the bitmap only, so every value of a type can be stored, including its null
+// value (see |Types::null_val|). Tuples written before the bitmap was added
+// have none, and encode nulls as the null values of their types; they are
+// told apart by |NULL_BITMAP_MASK| on the serialized length, and still read as
+// before.
+//
+// The serialized length also carries the version of the table schema the tuple
+// was written with (see |VERSION_MASK|), so that tuples written before columns
+// were added or dropped can be read with the schema of their version.
+//
+// A tuple too large for a page is stored in a chain of overflow pages instead,
+// and its slot holds a stub referring to the chain (see |Tuple::overflow|):
+//  ---------------------------------
+// | TupleLen (8) | FirstPageId (4) |
+//  ---------------------------------
+// Stubs are told apart by |OVERFLOW_MASK| on their serialized length, and keep
+// the |NULL_BITMAP_MASK| of the tuple they refer to on its length.
+
 use crate::catalog::schema::Schema;
+use crate::common::config::PageId;
+use crate::common::error::*;
 use crate::common::reinterpret;
+use crate::types::interner::Interner;
 use crate::types::types::Operation;
 use crate::types::value::Value;
 use std::clone::Clone;
@@ -8,15 +39,36 @@ use std::default::Default;
 use std::fmt::Debug;
 use std::mem;
 
+// Marks the serialized length of an overflow stub.
+pub const OVERFLOW_MASK: u64 = 1 << 63;
+// Marks the serialized length of a tuple with a null bitmap.
+pub const NULL_BITMAP_MASK: u64 = 1 << 62;
+// The schema version on serialized lengths.
+pub const VERSION_MASK: u64 = 0xffff << VERSION_SHIFT;
+const VERSION_SHIFT: u64 = 32;
+// The flags and the schema version on serialized lengths.
+pub const LEN_FLAGS_MASK: u64 = OVERFLOW_MASK | NULL_BITMAP_MASK | VERSION_MASK;
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Tuple {
     // rid: RID,
     data: Vec<u8>,
+    // Whether |data| is an overflow stub rather than the tuple itself.
+    overflow: bool,
+    // Whether |data| starts with a null bitmap.
+    bitmap: bool,
+    // The version of the table schema the tuple was written with.
+    version: u16,
 }
 
 impl Default for Tuple {
     fn default() -> Self {
-        Tuple { data: Vec::new() }
+        Tuple {
+            data: Vec::new(),
+            overflow: false,
+            bitmap: false,
+            version: 0,
+        }
     }
 }
 
@@ -24,19 +76,26 @@ impl Tuple {
     // The caller needs to ensure that |values| and |schema.columns| have the same size.
     pub fn new(values: &Vec<Value>, schema: &Schema) -> Self {
         // Step1: Calculate size of the tuple.
-        let mut size = schema.len();
+        let header_len = bitmap_len(schema);
+        let mut size = header_len + schema.len();
         for &idx in schema.uninlined().iter() {
             size += values[idx].len() + mem::size_of::<u64>();
         }
         let mut tuple = Tuple {
             data: vec![0; size],
+            overflow: false,
+            bitmap: true,
+            version: 0,
         };
         let ptr = tuple.data.as_mut_slice();
 
         // Step2: Serialize each column (attribute) based on input value.
-        let mut str_offset = schema.len();
+        let mut str_offset = header_len + schema.len();
         for idx in 0..schema.columns().len() {
-            let nth_offset = schema.nth_offset(idx).unwrap();
+            if values[idx].is_null() {
+                ptr[idx / 8] |= 1 << (idx % 8);
+            }
+            let nth_offset = header_len + schema.nth_offset(idx).unwrap();
             if !schema.nth_is_inlined(idx).unwrap() {
                 reinterpret::write_u64(&mut ptr[nth_offset..], str_offset as u64);
                 values[idx].serialize_to(&mut ptr[str_offset..]);
@@ -48,14 +107,68 @@ impl Tuple {
         tuple
     }
 
+    // Wraps the serialized values of the tuple the overflow stub |stub| refers
+    // to, read back from overflow pages; see |data|.
+    pub fn from_overflow(stub: &Tuple, data: Vec<u8>) -> Self {
+        let len = reinterpret::read_u64(&stub.data);
+        Tuple {
+            data,
+            overflow: false,
+            bitmap: len & NULL_BITMAP_MASK != 0,
+            version: ((len & VERSION_MASK) >> VERSION_SHIFT) as u16,
+        }
+    }
+
+    // Returns the stub referring to |tuple|, stored in the chain of overflow
+    // pages starting at |first_page_id|.
+    pub fn overflow(tuple: &Tuple, first_page_id: PageId) -> Self {
+        let mut data = vec![0; 12];
+        reinterpret::write_u64(&mut data, tuple.len_with_flags());
+        reinterpret::write_i32(&mut data[8..], first_page_id);
+        Tuple {
+            data,
+            overflow: true,
+            bitmap: false,
+            version: 0,
+        }
+    }
+
+    // Returns the length and the first overflow page of the tuple this stub
+    // refers to, or None if it is not a stub.
+    pub fn overflow_ref(&self) -> Option<(usize, PageId)> {
+        if !self.overflow || self.data.len() < 12 {
+            return None;
+        }
+        let len = reinterpret::read_u64(&self.data) & !LEN_FLAGS_MASK;
+        Some((len as usize, reinterpret::read_i32(&self.data[8..])))
+    }
+
+    pub fn is_overflow(&self) -> bool {
+        self.overflow
+    }
+
     pub fn len(&self) -> usize {
         self.data.len()
     }
 
+    // The version of the table schema the tuple was written with; 0 unless
+    // set otherwise.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: u16) {
+        self.version = version;
+    }
+
+    // The serialized values, without the length prefix.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
     // The caller needs to make sure that |dst| has enough space.
     pub fn serialize_to(&self, dst: &mut [u8]) {
-        let size = self.data.len() as u64;
-        reinterpret::write_u64(dst, size);
+        reinterpret::write_u64(dst, self.len_with_flags());
         for (s, d) in self
             .data
             .iter()
@@ -67,8 +180,11 @@ impl Tuple {
 
     // The caller needs to make sure that |src| is valid.
     pub fn deserialize_from(&mut self, src: &[u8]) {
-        let size = reinterpret::read_u64(src) as usize;
-        self.data = vec![0; size];
+        let size = reinterpret::read_u64(src);
+        self.overflow = size & OVERFLOW_MASK != 0;
+        self.bitmap = size & NULL_BITMAP_MASK != 0;
+        self.version = ((size & VERSION_MASK) >> VERSION_SHIFT) as u16;
+        self.data = vec![0; (size & !LEN_FLAGS_MASK) as usize];
         for (d, s) in self
             .data
             .iter_mut()
@@ -80,14 +196,93 @@ impl Tuple {
 
     // The caller needs to ensure that |idx| won't be out of range.
     pub fn nth_value<'a>(&self, schema: &'a Schema, idx: usize) -> Value<'a> {
+        if self.nth_bit(idx) {
+            return Value::null(schema.nth_types(idx).unwrap().clone());
+        }
+        let value = self.nth_stored_value(schema, idx);
+        match self.bitmap {
+            true => value,
+            false => value.decode_null_sentinel(),
+        }
+    }
+
+    // Same as |nth_value|, but Varchar values borrow from |interner|, so that
+    // identical strings across a batch of tuples share one allocation.
+    // The caller needs to ensure that |idx| won't be out of range.
+    pub fn nth_value_interned<'a>(
+        &self,
+        schema: &'a Schema,
+        idx: usize,
+        interner: &'a Interner,
+    ) -> Value<'a> {
+        if self.nth_bit(idx) {
+            return Value::null(schema.nth_types(idx).unwrap().clone());
+        }
         let mut value = Value::new(schema.nth_types(idx).unwrap().clone());
-        value.deserialize_from(self.nth_data_ptr(schema, idx));
-        value
+        value.deserialize_interned(self.nth_data_ptr(schema, idx), interner);
+        match self.bitmap {
+            true => value,
+            false => value.decode_null_sentinel(),
+        }
+    }
+
+    // Sets the value of column |idx| to |value|, leaving the tuple as |new|
+    // would build it. Inlined values are rewritten in place; an uninlined one
+    // is spliced in, moving the uninlined values after it if its size changes.
+    // Returns |InvalidInput| if |value| is not of the column type, or the tuple
+    // is an overflow stub.
+    // The caller needs to ensure that |idx| won't be out of range.
+    pub fn set_nth_value(
+        &mut self,
+        schema: &Schema,
+        idx: usize,
+        value: &Value,
+    ) -> std::io::Result<()> {
+        if self.overflow {
+            return Err(invalid_input("Cannot set value of an overflow stub"));
+        }
+        if value.borrow().id() != schema.nth_types(idx).unwrap().id() {
+            return Err(invalid_input(&format!(
+                "Value does not match column type; idx = {}",
+                idx
+            )));
+        }
+        if self.bitmap {
+            match value.is_null() {
+                true => self.data[idx / 8] |= 1 << (idx % 8),
+                false => self.data[idx / 8] &= !(1 << (idx % 8)),
+            }
+        }
+        let header_len = self.header_len(schema);
+        let nth_offset = header_len + schema.nth_offset(idx).unwrap();
+        if schema.nth_is_inlined(idx).unwrap() {
+            value.serialize_to(&mut self.data[nth_offset..]);
+            return Ok(());
+        }
+
+        let str_offset = reinterpret::read_u64(&self.data[nth_offset..]) as usize;
+        let old_len = self.nth_stored_value(schema, idx).len() + mem::size_of::<u64>();
+        let new_len = value.len() + mem::size_of::<u64>();
+        let mut bytes = vec![0; new_len];
+        value.serialize_to(&mut bytes);
+        self.data.splice(str_offset..(str_offset + old_len), bytes);
+        // Move the pointers to the values after the new one.
+        for &other in schema.uninlined().iter() {
+            let ptr = &mut self.data[(header_len + schema.nth_offset(other).unwrap())..];
+            let offset = reinterpret::read_u64(ptr) as usize;
+            if offset > str_offset {
+                reinterpret::write_u64(ptr, (offset + new_len - old_len) as u64);
+            }
+        }
+        Ok(())
     }
 
     // The caller needs to ensure that |idx| won't be out of range.
     pub fn nth_is_null(&self, schema: &Schema, idx: usize) -> bool {
-        self.nth_value(schema, idx).is_null()
+        match self.bitmap {
+            true => self.nth_bit(idx),
+            false => self.nth_value(schema, idx).is_null(),
+        }
     }
 
     pub fn to_string(&self, schema: &Schema) -> String {
@@ -110,18 +305,54 @@ impl Tuple {
         s
     }
 
+    // The serialized length, with its flags and the schema version.
+    fn len_with_flags(&self) -> u64 {
+        let mut len = self.data.len() as u64 | (self.version as u64) << VERSION_SHIFT;
+        if self.overflow {
+            len |= OVERFLOW_MASK;
+        }
+        if self.bitmap {
+            len |= NULL_BITMAP_MASK;
+        }
+        len
+    }
+
+    fn header_len(&self, schema: &Schema) -> usize {
+        match self.bitmap {
+            true => bitmap_len(schema),
+            false => 0,
+        }
+    }
+
+    // Whether column |idx| is null in the null bitmap, if there is one.
+    fn nth_bit(&self, idx: usize) -> bool {
+        self.bitmap && self.data[idx / 8] & (1 << (idx % 8)) != 0
+    }
+
+    // The value stored for column |idx|, regardless of its nullness.
+    fn nth_stored_value<'a>(&self, schema: &'a Schema, idx: usize) -> Value<'a> {
+        let mut value = Value::new(schema.nth_types(idx).unwrap().clone());
+        value.deserialize_from(self.nth_data_ptr(schema, idx));
+        value
+    }
+
     fn nth_data_ptr(&self, schema: &Schema, idx: usize) -> &[u8] {
-        let nth_offset = schema.nth_offset(idx).unwrap();
+        let nth_offset = self.header_len(schema) + schema.nth_offset(idx).unwrap();
         let ptr = &self.data.as_slice()[nth_offset..];
         if schema.nth_is_inlined(idx).unwrap() {
             ptr
         } else {
+            // The offset is relative to the start of the tuple, bitmap included.
             let str_offset = reinterpret::read_u64(ptr) as usize;
-            &ptr[str_offset..]
+            &self.data.as_slice()[str_offset..]
         }
     }
 }
 
+fn bitmap_len(schema: &Schema) -> usize {
+    schema.columns().len().div_ceil(8)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -159,14 +390,190 @@ mod tests {
         assert_eq!(Some(true), value2.eq(&tuple.nth_value(&schema, 1)));
     }
 
+    #[test]
+    fn varchar_after_inlined() {
+        let values = vec![
+            Value::new(Types::BigInt(7)),
+            Value::new(Types::Varchar(Varlen::Owned(Str::Val("a".to_string())))),
+            Value::new(Types::Varchar(Varlen::Owned(Str::Val("bc".to_string())))),
+        ];
+        let schema = Schema::new(vec![
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("First".to_string(), Types::owned(), 10),
+            Column::new("Second".to_string(), Types::owned(), 10),
+        ]);
+        let tuple = Tuple::new(&values, &schema);
+        for (idx, value) in values.iter().enumerate() {
+            assert_eq!(Some(true), value.eq(&tuple.nth_value(&schema, idx)));
+        }
+    }
+
+    #[test]
+    fn set_nth_value() {
+        let varchar = |s: &str| Value::new(Types::Varchar(Varlen::Owned(Str::Val(s.to_string()))));
+        let mut values = vec![varchar("a"), Value::new(Types::BigInt(7)), varchar("bc")];
+        let schema = Schema::new(vec![
+            Column::new("First".to_string(), Types::owned(), 10),
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("Second".to_string(), Types::owned(), 10),
+        ]);
+        let mut tuple = Tuple::new(&values, &schema);
+
+        // Inlined, then growing, shrinking and resizing the last value.
+        let updates = vec![
+            (1, Value::new(Types::BigInt(8))),
+            (0, varchar("abcdef")),
+            (0, varchar("")),
+            (2, varchar("bcdefgh")),
+            (1, Value::null(Types::bigint())),
+        ];
+        for (idx, value) in updates.into_iter() {
+            tuple.set_nth_value(&schema, idx, &value).unwrap();
+            values[idx] = value;
+            assert_eq!(Tuple::new(&values, &schema), tuple);
+        }
+        assert_eq!("bcdefgh", tuple.nth_value(&schema, 2).to_string());
+        assert!(tuple.nth_is_null(&schema, 1));
+
+        let integer = Value::new(Types::Integer(8));
+        assert!(tuple.set_nth_value(&schema, 1, &integer).is_err());
+        let mut stub = Tuple::overflow(&tuple, 1);
+        assert!(stub.set_nth_value(&schema, 0, &varchar("a")).is_err());
+    }
+
+    #[test]
+    fn null_bitmap() {
+        let values = vec![
+            Value::null(Types::bigint()),
+            Value::new(Types::Varchar(Varlen::Owned(Str::Val("a".to_string())))),
+            Value::new(Types::Integer(5)),
+        ];
+        let schema = Schema::new(vec![
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("Name".to_string(), Types::owned(), 10),
+            Column::new("Score".to_string(), Types::integer(), 4),
+        ]);
+        let mut tuple = Tuple::new(&values, &schema);
+        assert_eq!(1 + schema.len() + 1 + 8, tuple.len());
+        assert_eq!(&[1], &tuple.data()[..1]);
+        assert!(tuple.nth_is_null(&schema, 0));
+        assert!(tuple.nth_value(&schema, 0).is_null());
+        assert!(!tuple.nth_is_null(&schema, 1));
+        assert!(!tuple.nth_is_null(&schema, 2));
+
+        let null = Value::null(Types::integer());
+        tuple.set_nth_value(&schema, 2, &null).unwrap();
+        assert!(tuple.nth_is_null(&schema, 2));
+        tuple.set_nth_value(&schema, 2, &values[2]).unwrap();
+        assert_eq!(Tuple::new(&values, &schema), tuple);
+
+        let mut buffer = vec![0; 8 + tuple.len()];
+        tuple.serialize_to(&mut buffer);
+        assert_ne!(0, reinterpret::read_u64(&buffer) & NULL_BITMAP_MASK);
+        let mut tuple2 = Tuple::default();
+        tuple2.deserialize_from(&buffer);
+        assert_eq!(tuple, tuple2);
+        assert!(tuple2.nth_is_null(&schema, 0));
+    }
+
+    #[test]
+    fn min_values() {
+        // The values that tuples without a null bitmap took for null.
+        let types = [
+            Types::tinyint(),
+            Types::smallint(),
+            Types::integer(),
+            Types::bigint(),
+            Types::decimal(),
+            Types::timestamp(),
+            Types::point(),
+        ];
+        let schema = Schema::new(
+            types
+                .iter()
+                .enumerate()
+                .map(|(idx, types)| Column::new(format!("C{}", idx), types.clone(), types.size()))
+                .collect(),
+        );
+        let values: Vec<_> = types
+            .iter()
+            .map(|types| Value::new(types.clone().null_val().unwrap()))
+            .collect();
+        let tuple = Tuple::new(&values, &schema);
+        let mut buffer = vec![0; 8 + tuple.len()];
+        tuple.serialize_to(&mut buffer);
+        let mut tuple2 = Tuple::default();
+        tuple2.deserialize_from(&buffer);
+        for (idx, value) in values.iter().enumerate() {
+            assert!(!tuple2.nth_is_null(&schema, idx));
+            let other = tuple2.nth_value(&schema, idx);
+            assert!(!other.is_null());
+            assert_eq!(value.to_string(), other.to_string());
+        }
+    }
+
+    #[test]
+    fn without_null_bitmap() {
+        let schema = Schema::new(vec![
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("Name".to_string(), Types::owned(), 10),
+        ]);
+        // A tuple written before null bitmaps, with a null id.
+        let name = Value::new(Types::Varchar(Varlen::Owned(Str::Val("ab".to_string()))));
+        let len = schema.len() + name.len() + 8;
+        let mut buffer = vec![0; 8 + len];
+        reinterpret::write_u64(&mut buffer, len as u64);
+        Value::null(Types::bigint()).serialize_to(&mut buffer[8..]);
+        reinterpret::write_u64(&mut buffer[16..], schema.len() as u64);
+        name.serialize_to(&mut buffer[(8 + schema.len())..]);
+
+        let mut tuple = Tuple::default();
+        tuple.deserialize_from(&buffer);
+        assert!(tuple.nth_is_null(&schema, 0));
+        assert_eq!(Some(true), name.eq(&tuple.nth_value(&schema, 1)));
+        let longer = Value::new(Types::Varchar(Varlen::Owned(Str::Val("abc".to_string()))));
+        tuple.set_nth_value(&schema, 1, &longer).unwrap();
+        assert_eq!("abc", tuple.nth_value(&schema, 1).to_string());
+
+        // It keeps its format.
+        let mut buffer2 = vec![0; 8 + tuple.len()];
+        tuple.serialize_to(&mut buffer2);
+        assert_eq!((len + 1) as u64, reinterpret::read_u64(&buffer2));
+    }
+
+    #[test]
+    fn nth_interned() {
+        let (schema, tuple) = create_tuple();
+        let (_, another_tuple) = create_tuple();
+        let interner = Interner::new();
+        let value1 = tuple.nth_value_interned(&schema, 0, &interner);
+        let value2 = another_tuple.nth_value_interned(&schema, 0, &interner);
+        assert_eq!(Some(true), value1.eq(&tuple.nth_value(&schema, 0)));
+        match (value1.borrow(), value2.borrow()) {
+            (
+                Types::Varchar(Varlen::Borrowed(Str::Val(s1))),
+                Types::Varchar(Varlen::Borrowed(Str::Val(s2))),
+            ) => assert_eq!(s1.as_ptr(), s2.as_ptr()),
+            _ => panic!("Varchar should be borrowed from the interner"),
+        }
+        assert_eq!(1, interner.len());
+
+        // Non-varchar values are not affected.
+        let value = tuple.nth_value_interned(&schema, 1, &interner);
+        assert_eq!(Some(true), Value::new(Types::Integer(123456789)).eq(&value));
+    }
+
     #[test]
     fn serialize_and_deserialize() {
-        let (_, tuple) = create_tuple();
+        let (_, mut tuple) = create_tuple();
+        tuple.set_version(3);
         let mut buffer: Vec<u8> = vec![0; 100];
         tuple.serialize_to(buffer.as_mut_slice());
 
         let mut tuple2 = Tuple::default();
         tuple2.deserialize_from(buffer.as_slice());
         assert_eq!(tuple, tuple2);
+        assert_eq!(3, tuple2.version());
+        assert_eq!(tuple.len(), tuple2.len());
     }
 }
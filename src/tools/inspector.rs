@@ -0,0 +1,407 @@
+// Functionality: Inspects a database file without going through the buffer
+// pool, for debugging storage bugs. It reports the catalog of the header page,
+// the type, LSN and checksum status of every allocated page, a summary of the
+// allocation bitmap, and the decoded layout and hex dump of selected pages.
+// See the |rsdb-inspect| binary.
+//
+// The file is opened read-only, so it is safe to inspect a database that is
+// not being written to. Pages are not tagged with their type, so the type is
+// inferred from the page content: a page storing its own page ID with a sane
+// free space pointer is a table page, a page with a known index page type is
+// an index page. Corrupted pages may therefore be reported as |Unknown|.
+
+use crate::common::config::Lsn;
+use crate::common::config::PageId;
+use crate::common::config::HEADER_PAGE_ID;
+use crate::common::config::INVALID_LSN;
+use crate::common::config::PAGE_SIZE;
+use crate::common::hexdump::hex_dump;
+use crate::disk::disk_manager::checksum_mismatch;
+use crate::disk::disk_manager::DiskManager;
+use crate::page::bplus_tree_page::BPlusTreePage;
+use crate::page::bplus_tree_page::IndexPageType;
+use crate::page::header_page::HeaderPage;
+use crate::page::page::Page;
+use crate::page::table_page::TablePage;
+use crate::page::table_page::MAX_SLOT_COUNT;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PageKind {
+    Header,
+    Table,
+    Index(IndexPageType),
+    // All zeros, e.g. allocated but never written.
+    Zeroed,
+    Unknown,
+}
+
+impl fmt::Display for PageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageKind::Index(page_type) => write!(f, "Index({:?})", page_type),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChecksumStatus {
+    // No checksum was ever written.
+    Unset,
+    Valid,
+    Mismatch { expected: u64, actual: u64 },
+}
+
+impl fmt::Display for ChecksumStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChecksumStatus::Mismatch { expected, actual } => write!(
+                f,
+                "MISMATCH (expected = {:#018x}, actual = {:#018x})",
+                expected, actual
+            ),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageSummary {
+    pub page_id: PageId,
+    pub kind: PageKind,
+    // INVALID_LSN for pages without an LSN field.
+    pub lsn: Lsn,
+    pub checksum: ChecksumStatus,
+}
+
+impl fmt::Display for PageSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Page {}: kind = {}, lsn = {}, checksum = {}",
+            self.page_id, self.kind, self.lsn, self.checksum
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BitmapSummary {
+    // The number of pages the database file spans.
+    pub file_pages: usize,
+    pub allocated: usize,
+    // Allocated pages past the end of the file, i.e. never written.
+    pub unwritten: usize,
+    // Pages within the file that are not allocated.
+    pub free: usize,
+}
+
+impl fmt::Display for BitmapSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Bitmap: file pages = {}, allocated = {}, unwritten = {}, free = {}",
+            self.file_pages, self.allocated, self.unwritten, self.free
+        )
+    }
+}
+
+pub struct Inspector {
+    disk_mgr: DiskManager,
+}
+
+impl Inspector {
+    // Opens |db_file| read-only. Fails with |WouldBlock| if another process
+    // has it open for writing.
+    pub fn open(db_file: &str) -> std::io::Result<Self> {
+        Ok(Inspector {
+            disk_mgr: DiskManager::new_read_only(db_file)?,
+        })
+    }
+
+    pub fn bitmap_summary(&self) -> std::io::Result<BitmapSummary> {
+        let file_pages = self.disk_mgr.file_page_count()?;
+        let page_count = file_pages.max(self.disk_mgr.bitmap_page_count());
+        let mut summary = BitmapSummary {
+            file_pages,
+            allocated: 0,
+            unwritten: 0,
+            free: 0,
+        };
+        for page_id in 0..page_count {
+            match (
+                self.disk_mgr.is_allocated(page_id as PageId),
+                page_id < file_pages,
+            ) {
+                (true, true) => summary.allocated += 1,
+                (true, false) => {
+                    summary.allocated += 1;
+                    summary.unwritten += 1;
+                }
+                (false, true) => summary.free += 1,
+                (false, false) => (),
+            }
+        }
+        Ok(summary)
+    }
+
+    // Returns the name, root ID and CRC status of every header page record.
+    pub fn catalog(&mut self) -> std::io::Result<Vec<(String, PageId, bool)>> {
+        if !self.disk_mgr.is_allocated(HEADER_PAGE_ID) {
+            return Ok(Vec::new());
+        }
+        let header_page: HeaderPage = self.load(HEADER_PAGE_ID)?;
+        Ok(header_page.records())
+    }
+
+    pub fn summarize_page(&mut self, page_id: PageId) -> std::io::Result<PageSummary> {
+        let mut data = [0; PAGE_SIZE];
+        self.disk_mgr.read_page_unchecked(page_id, &mut data)?;
+        let checksum = match checksum_mismatch(&data)? {
+            Some((expected, actual)) => ChecksumStatus::Mismatch { expected, actual },
+            None if data[..8].iter().all(|&byte| byte == 0) => ChecksumStatus::Unset,
+            None => ChecksumStatus::Valid,
+        };
+        let kind = classify(page_id, &data);
+        let lsn = match kind {
+            PageKind::Table => as_page::<TablePage>(&data).lsn(),
+            PageKind::Index(_) => as_page::<BPlusTreePage>(&data).lsn(),
+            _ => INVALID_LSN,
+        };
+        Ok(PageSummary {
+            page_id,
+            kind,
+            lsn,
+            checksum,
+        })
+    }
+
+    // Summarizes every allocated page within the database file.
+    pub fn summarize_pages(&mut self) -> std::io::Result<Vec<PageSummary>> {
+        let mut summaries = Vec::new();
+        for page_id in 0..self.disk_mgr.file_page_count()? as PageId {
+            if self.disk_mgr.is_allocated(page_id) {
+                summaries.push(self.summarize_page(page_id)?);
+            }
+        }
+        Ok(summaries)
+    }
+
+    // Returns the summary, the decoded layout and the hex dump of a page.
+    pub fn describe_page(&mut self, page_id: PageId) -> std::io::Result<String> {
+        let summary = self.summarize_page(page_id)?;
+        let mut data = [0; PAGE_SIZE];
+        self.disk_mgr.read_page_unchecked(page_id, &mut data)?;
+        let mut s = format!("{}\n", summary);
+        match summary.kind {
+            PageKind::Header => {
+                for (name, root_id, valid) in as_page::<HeaderPage>(&data).records() {
+                    s.push_str(&format!(
+                        "  record: name = {:?}, root_id = {}{}\n",
+                        name,
+                        root_id,
+                        if valid { "" } else { " (TORN)" }
+                    ));
+                }
+            }
+            PageKind::Table => s.push_str(&describe_table_page(&as_page(&data))),
+            PageKind::Index(_) => {
+                let page = as_page::<BPlusTreePage>(&data);
+                s.push_str(&format!("  size = {}\n", page.size()));
+            }
+            PageKind::Zeroed | PageKind::Unknown => (),
+        }
+        s.push_str(&hex_dump(&data));
+        Ok(s)
+    }
+
+    // Renders the catalog, the bitmap summary and the page summaries, followed
+    // by the descriptions of |page_ids|.
+    pub fn report(&mut self, page_ids: &[PageId]) -> std::io::Result<String> {
+        let mut s = String::from("Catalog:\n");
+        for (name, root_id, valid) in self.catalog()? {
+            s.push_str(&format!(
+                "  {:?} -> {}{}\n",
+                name,
+                root_id,
+                if valid { "" } else { " (TORN)" }
+            ));
+        }
+        s.push_str(&format!("{}\n", self.bitmap_summary()?));
+        for summary in self.summarize_pages()? {
+            s.push_str(&format!("{}\n", summary));
+        }
+        for &page_id in page_ids {
+            s.push('\n');
+            s.push_str(&self.describe_page(page_id)?);
+        }
+        Ok(s)
+    }
+
+    fn load<T: Page>(&mut self, page_id: PageId) -> std::io::Result<T> {
+        let mut data = [0; PAGE_SIZE];
+        self.disk_mgr.read_page_unchecked(page_id, &mut data)?;
+        Ok(as_page(&data))
+    }
+}
+
+fn as_page<T: Page>(data: &[u8; PAGE_SIZE]) -> T {
+    let mut page = T::default();
+    page.data_mut().copy_from_slice(data);
+    page
+}
+
+fn classify(page_id: PageId, data: &[u8; PAGE_SIZE]) -> PageKind {
+    if data.iter().all(|&byte| byte == 0) {
+        return PageKind::Zeroed;
+    }
+    let table_page = as_page::<TablePage>(data);
+    if table_page.stored_page_id() == page_id
+        && table_page.free_space_ptr() <= PAGE_SIZE
+        && table_page.tuple_count() <= MAX_SLOT_COUNT
+    {
+        return PageKind::Table;
+    }
+    if page_id == HEADER_PAGE_ID {
+        return PageKind::Header;
+    }
+    match as_page::<BPlusTreePage>(data).page_type() {
+        IndexPageType::Invalid => PageKind::Unknown,
+        page_type => PageKind::Index(page_type),
+    }
+}
+
+fn describe_table_page(page: &TablePage) -> String {
+    let mut s = format!(
+        "  prev_page_id = {}, next_page_id = {}, free_space_ptr = {}, tuple_count = {}\n",
+        page.prev_page_id(),
+        page.next_page_id(),
+        page.free_space_ptr(),
+        page.tuple_count()
+    );
+    let mut slot_num = 0;
+    while let Some((offset, size, deleted)) = page.slot(slot_num) {
+        let status = match (size, deleted) {
+            (0, _) => " (free)",
+            (_, true) => " (deleted)",
+            _ if offset < page.free_space_ptr()
+                || offset.checked_add(size).map_or(true, |end| end > PAGE_SIZE) =>
+            {
+                " (OUT OF BOUNDS)"
+            }
+            _ => "",
+        };
+        s.push_str(&format!(
+            "  slot {}: offset = {}, size = {}{}\n",
+            slot_num, offset, size, status
+        ));
+        slot_num += 1;
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::reinterpret;
+    use crate::disk::disk_manager::offset;
+    use crate::disk::disk_manager::BITMAP_FILE_SUFFIX;
+    use crate::table::tuple::Tuple;
+    use crate::testing::file_deleter::FileDeleter;
+    use std::fs::OpenOptions;
+    use std::io::Seek;
+    use std::io::SeekFrom;
+    use std::io::Write;
+
+    fn create_tuple(content: &str) -> Tuple {
+        let mut buffer = vec![0; content.len() + 8];
+        reinterpret::write_u64(&mut buffer, content.len() as u64);
+        buffer[8..].copy_from_slice(content.as_bytes());
+        let mut tuple = Tuple::default();
+        tuple.deserialize_from(&buffer);
+        tuple
+    }
+
+    #[test]
+    fn inspect_pages() {
+        let file_path = "/tmp/testfile.inspector.1.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+
+        let mut disk_mgr = DiskManager::new(file_path).unwrap();
+        let mut header_page = HeaderPage::new();
+        header_page.init();
+        header_page.insert_record("orders", 1).unwrap();
+        let header_page_id = disk_mgr.allocate_page().unwrap();
+        disk_mgr
+            .write_page(header_page_id, header_page.data_mut())
+            .unwrap();
+
+        let mut table_page = TablePage::new();
+        let table_page_id = disk_mgr.allocate_page().unwrap();
+        table_page.set_page_id(table_page_id);
+        table_page.set_lsn(7);
+        table_page.insert_tuple(create_tuple("hello")).unwrap();
+        table_page.insert_tuple(create_tuple("world")).unwrap();
+        disk_mgr
+            .write_page(table_page_id, table_page.data_mut())
+            .unwrap();
+
+        // A page corrupted after being written, and a page never written.
+        let corrupted_page_id = disk_mgr.allocate_page().unwrap();
+        disk_mgr
+            .write_page(corrupted_page_id, table_page.data_mut())
+            .unwrap();
+        let unwritten_page_id = disk_mgr.allocate_page().unwrap();
+        disk_mgr.sync().unwrap();
+        drop(disk_mgr);
+        let mut file = OpenOptions::new().write(true).open(file_path).unwrap();
+        let offset = offset(corrupted_page_id) + 100;
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.write_all(&[0xff; 4]).unwrap();
+        drop(file);
+
+        let mut inspector = Inspector::open(file_path).unwrap();
+        assert_eq!(
+            vec![("orders".to_string(), 1, true)],
+            inspector.catalog().unwrap()
+        );
+        let bitmap_summary = BitmapSummary {
+            file_pages: 3,
+            allocated: 4,
+            unwritten: 1,
+            free: 0,
+        };
+        assert_eq!(bitmap_summary, inspector.bitmap_summary().unwrap());
+
+        let summaries = inspector.summarize_pages().unwrap();
+        assert_eq!(3, summaries.len());
+        assert_eq!(PageKind::Header, summaries[0].kind);
+        let table_summary = PageSummary {
+            page_id: table_page_id,
+            kind: PageKind::Table,
+            lsn: 7,
+            checksum: ChecksumStatus::Valid,
+        };
+        assert_eq!(table_summary, summaries[1]);
+        match summaries[2].checksum {
+            ChecksumStatus::Mismatch { .. } => (),
+            checksum => panic!("Unexpected checksum status: {}", checksum),
+        }
+        let unwritten = inspector.summarize_page(unwritten_page_id).unwrap();
+        assert_eq!(PageKind::Zeroed, unwritten.kind);
+        assert_eq!(ChecksumStatus::Unset, unwritten.checksum);
+
+        let description = inspector.describe_page(table_page_id).unwrap();
+        assert!(description.contains("tuple_count = 2"));
+        assert!(description.contains("slot 1: offset = 4070, size = 13\n"));
+        assert!(description.contains("\n00000ff0 "));
+        let report = inspector.report(&[header_page_id]).unwrap();
+        assert!(report.contains("\"orders\" -> 1\n"));
+        assert!(report.contains("record: name = \"orders\", root_id = 1\n"));
+    }
+}
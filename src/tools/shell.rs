@@ -0,0 +1,311 @@
+// Functionality: An interactive shell over a |Database|, see the |rsdb|
+// binary. It executes one statement per line, see |Statement|, and prints the
+// tuples it produces as a table aligned in columns. Lines starting with a dot
+// are meta commands: |.tables|, |.schema [table]|, |.stats <table>| and
+// |.help|.
+
+use crate::common::error::*;
+use crate::common::options::DbOptions;
+use crate::database::database::Database;
+use crate::types::types::Operation;
+use crate::types::value::Value;
+
+const HELP: &str = "\
+create table <table> (<column> <type>[(<length>)], ...)
+create index <index> on <table> (<column>)
+insert into <table> values (<value>, ...), ...
+scan <table> [where <column> <op> <value>] [limit <count>]
+explain <scan or insert>
+.tables               List the tables
+.schema [<table>]     Show the statements creating the tables
+.stats <table>        Analyze a table and show its statistics
+.help                 Show this message
+";
+
+pub struct Shell {
+    db: Database,
+}
+
+impl Shell {
+    // Opens the database at |path|, creating it if it does not exist.
+    pub fn open(path: &str) -> std::io::Result<Self> {
+        Ok(Shell {
+            db: Database::open(path, &DbOptions::default())?,
+        })
+    }
+
+    // Executes the statement or meta command |line|, and returns its output.
+    pub fn execute(&mut self, line: &str) -> std::io::Result<String> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(String::new());
+        }
+        if line.starts_with('.') {
+            return self.execute_meta(line);
+        }
+        let rows = self.db.execute(line)?;
+        let schema = rows.schema().clone();
+        if schema.columns().is_empty() {
+            return Ok(String::new());
+        }
+        let mut fields = Vec::new();
+        for tuple in rows {
+            let tuple = tuple?;
+            let row = (0..schema.columns().len())
+                .map(|idx| match tuple.nth_is_null(&schema, idx) {
+                    true => "NULL".to_string(),
+                    false => tuple.nth_value(&schema, idx).to_string(),
+                })
+                .collect();
+            fields.push(row);
+        }
+        let header = schema
+            .columns()
+            .iter()
+            .map(|column| column.name().to_string())
+            .collect();
+        Ok(format_table(header, &fields))
+    }
+
+    // Flushes the database to disk.
+    pub fn close(self) -> std::io::Result<()> {
+        self.db.close()
+    }
+
+    fn execute_meta(&mut self, line: &str) -> std::io::Result<String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [".help"] => Ok(HELP.to_string()),
+            [".tables"] => Ok(self
+                .db
+                .catalog()
+                .tables()
+                .iter()
+                .map(|table| format!("{}\n", table.name()))
+                .collect()),
+            [".schema"] => {
+                let names: Vec<String> = self
+                    .db
+                    .catalog()
+                    .tables()
+                    .iter()
+                    .map(|table| table.name().to_string())
+                    .collect();
+                names.iter().map(|name| self.describe_table(name)).collect()
+            }
+            [".schema", name] => self.describe_table(name),
+            [".stats", name] => self.stats(name),
+            _ => Err(invalid_input(&format!(
+                "Unknown command; command = {}",
+                line
+            ))),
+        }
+    }
+
+    // Returns the statements creating the table |name| and its indexes.
+    fn describe_table(&self, name: &str) -> std::io::Result<String> {
+        let table = self
+            .db
+            .catalog()
+            .table(name)
+            .ok_or_else(|| not_found(&format!("No such table; name = {}", name)))?;
+        let columns: Vec<String> = table
+            .schema()
+            .columns()
+            .iter()
+            .map(|column| match column.is_inlined() {
+                true => format!("{} {}", column.name(), column.types().name()),
+                false => format!(
+                    "{} {}({})",
+                    column.name(),
+                    column.types().name(),
+                    column.len()
+                ),
+            })
+            .collect();
+        let mut s = format!("create table {} ({})\n", name, columns.join(", "));
+        for index in self.db.catalog().table_indexes(table.oid()) {
+            let column = table.schema().nth_column(index.key_column()).unwrap();
+            s.push_str(&format!(
+                "create index {} on {} ({})\n",
+                index.name(),
+                name,
+                column.name()
+            ));
+        }
+        Ok(s)
+    }
+
+    // Analyzes the table |name|, and formats its statistics.
+    fn stats(&mut self, name: &str) -> std::io::Result<String> {
+        self.db.analyze(name)?;
+        let table = self.db.catalog().table(name).unwrap();
+        let statistics = self.db.catalog().statistics(table.oid()).unwrap();
+        let schema = table.schema();
+        let format = |value: Option<&Value>| value.map_or("NULL".to_string(), |v| v.to_string());
+        let rows: Vec<Vec<String>> = schema
+            .columns()
+            .iter()
+            .enumerate()
+            .map(|(idx, column)| {
+                let column_statistics = statistics.column(idx).unwrap();
+                vec![
+                    column.name().to_string(),
+                    column_statistics.null_count().to_string(),
+                    column_statistics.distinct_count().to_string(),
+                    format(column_statistics.min()),
+                    format(column_statistics.max()),
+                ]
+            })
+            .collect();
+        let header = ["Column", "Nulls", "Distinct", "Min", "Max"];
+        Ok(format!(
+            "Rows: {}\n{}",
+            statistics.row_count(),
+            format_table(header.iter().map(|s| s.to_string()).collect(), &rows)
+        ))
+    }
+}
+
+// Formats |rows| under |header| as a table aligned in columns, followed by
+// the number of rows.
+fn format_table(header: Vec<String>, rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|name| name.chars().count()).collect();
+    for row in rows.iter() {
+        for (width, field) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(field.chars().count());
+        }
+    }
+    let separator: String = widths
+        .iter()
+        .map(|width| format!("+{}", "-".repeat(width + 2)))
+        .chain(std::iter::once("+\n".to_string()))
+        .collect();
+    let format_row = |row: &[String]| -> String {
+        row.iter()
+            .zip(widths.iter())
+            .map(|(field, width)| format!("| {:<width$} ", field, width = width))
+            .chain(std::iter::once("|\n".to_string()))
+            .collect()
+    };
+    let mut s = separator.clone();
+    s.push_str(&format_row(&header));
+    s.push_str(&separator);
+    for row in rows.iter() {
+        s.push_str(&format_row(row));
+    }
+    s.push_str(&separator);
+    match rows.len() {
+        1 => s.push_str("(1 row)\n"),
+        count => s.push_str(&format!("({} rows)\n", count)),
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::database::log_path;
+    use crate::disk::disk_manager::BITMAP_FILE_SUFFIX;
+    use crate::testing::file_deleter::FileDeleter;
+
+    #[test]
+    fn statements() {
+        let file_path = "/tmp/testfile.shell.1.db";
+        let bitmap_path = file_path.to_string() + BITMAP_FILE_SUFFIX;
+        let log_path = log_path(file_path);
+
+        // Test file deleter with RAII.
+        let mut file_deleter = FileDeleter::new();
+        file_deleter.push(file_path);
+        file_deleter.push(&bitmap_path);
+        file_deleter.push(&log_path);
+
+        let mut shell = Shell::open(file_path).unwrap();
+        let mut execute = |line: &str| shell.execute(line).unwrap();
+        assert_eq!("", execute(""));
+        assert_eq!(
+            "",
+            execute("CREATE TABLE users (Id bigint, Name varchar(20), Score decimal);")
+        );
+        assert_eq!(
+            "+-------+\n\
+             | Count |\n\
+             +-------+\n\
+             | 3     |\n\
+             +-------+\n\
+             (1 row)\n",
+            execute(
+                "insert into users values (1, 'apple', 1.5), (2, 'it''s', null), (3, 'kiwi', -2)"
+            )
+        );
+        assert_eq!(
+            "+----+-------+-------+\n\
+             | Id | Name  | Score |\n\
+             +----+-------+-------+\n\
+             | 1  | apple | 1.5   |\n\
+             | 2  | it's  | NULL  |\n\
+             | 3  | kiwi  | -2    |\n\
+             +----+-------+-------+\n\
+             (3 rows)\n",
+            execute("scan users")
+        );
+        assert!(execute("scan users where Score < 0").contains("(1 row)"));
+        assert!(execute("scan users where Id >= 2 limit 1").contains("| 2  | it's | NULL  |"));
+        assert!(execute("scan users limit 0").ends_with("(0 rows)\n"));
+
+        assert_eq!("", execute("create index users_id on users (Id)"));
+        let plan = execute("explain scan users where Id = 2");
+        assert!(plan.contains("Filter: (Id = 2)"));
+        assert!(plan.contains("IndexScan: users using users_id, key = 2"));
+        assert!(execute("explain insert into users values (4, 'x', 0)").contains("Insert"));
+
+        assert_eq!("users\n", execute(".tables"));
+        let schema = "create table users (Id BIGINT, Name VARCHAR(20), Score DECIMAL)\n\
+                      create index users_id on users (Id)\n";
+        assert_eq!(schema, execute(".schema"));
+        assert_eq!(schema, execute(".schema users"));
+        assert_eq!(
+            "Rows: 3\n\
+             +--------+-------+----------+-------+------+\n\
+             | Column | Nulls | Distinct | Min   | Max  |\n\
+             +--------+-------+----------+-------+------+\n\
+             | Id     | 0     | 3        | 1     | 3    |\n\
+             | Name   | 0     | 3        | apple | kiwi |\n\
+             | Score  | 1     | 2        | -2    | 1.5  |\n\
+             +--------+-------+----------+-------+------+\n\
+             (3 rows)\n",
+            execute(".stats users")
+        );
+        assert!(execute(".help").contains(".stats <table>"));
+
+        let mut error = |line: &str| shell.execute(line).unwrap_err().to_string();
+        assert_eq!("Unknown command; command = .drop", error(".drop"));
+        assert_eq!("Unexpected token; token = users", error("scan users users"));
+        assert_eq!("Unexpected end of statement", error("scan"));
+        assert_eq!("Unterminated string", error("scan users where Name = 'a"));
+        assert_eq!(
+            "Unknown type; name = UUID",
+            error("create table t (Id uuid)")
+        );
+        assert_eq!(
+            "Column cannot be null; column = Name",
+            error("insert into users values (5, null, 0)")
+        );
+        assert_eq!(
+            "Cannot convert value; column = Id",
+            error("insert into users values ('five', 'x', 0)")
+        );
+        assert!(error("scan nothing").starts_with("No such table"));
+        assert!(shell.close().is_ok());
+
+        // The tables and indexes are persisted.
+        let mut shell = Shell::open(file_path).unwrap();
+        assert_eq!(schema, shell.execute(".schema").unwrap());
+        assert!(shell
+            .execute("scan users where Id = 3")
+            .unwrap()
+            .contains("(1 row)"));
+        assert!(shell.close().is_ok());
+    }
+}
@@ -0,0 +1,311 @@
+// Functionality: A count-min sketch, estimating how often each value occurs
+// in a fixed amount of memory, along with the most frequent values seen so far
+// (the heavy hitters). It backs selectivity estimates of equality predicates.
+//
+// Every value increments one counter in each of |depth| rows of |width|
+// counters; its estimated count is the minimum over its counters, which never
+// underestimates. Deleting a value decrements the same counters, so the sketch
+// tracks a table under inserts and deletes.
+//
+// Serialized format (size in byte):
+//  -----------------------------------------------------------------------
+// | Width (4) | Depth (4) | Total (8) | HitterCount (4) | Counter_1 (8) | ...
+//  -----------------------------------------------------------------------
+//  -------------------------------------------------------------
+// | HitterLen_1 (4) | Hitter_1 (HitterLen_1) | HitterLen_2 (4) | ...
+//  -------------------------------------------------------------
+
+use crate::common::error::*;
+use crate::common::reinterpret;
+use crate::types::hyperloglog::hash;
+use crate::types::value::Value;
+
+pub const DEFAULT_WIDTH: usize = 1024;
+pub const DEFAULT_DEPTH: usize = 4;
+// The number of heavy hitters kept.
+pub const HEAVY_HITTERS: usize = 8;
+
+const HEADER_SIZE: usize = 20;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CountMinSketch {
+    width: usize,
+    depth: usize,
+    // |depth| rows of |width| counters.
+    counters: Vec<u64>,
+    // The number of values added and not removed.
+    total: u64,
+    // Candidates for the most frequent values, serialized.
+    hitters: Vec<Vec<u8>>,
+}
+
+impl Default for CountMinSketch {
+    fn default() -> Self {
+        Self::new(DEFAULT_WIDTH, DEFAULT_DEPTH)
+    }
+}
+
+impl CountMinSketch {
+    // |width| and |depth| are at least 1.
+    pub fn new(width: usize, depth: usize) -> Self {
+        let width = width.max(1);
+        let depth = depth.max(1);
+        CountMinSketch {
+            width,
+            depth,
+            counters: vec![0; width * depth],
+            total: 0,
+            hitters: Vec::new(),
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    // Adds a value. Nulls are not counted.
+    pub fn add_value(&mut self, value: &Value) {
+        if let Some(bytes) = to_bytes(value) {
+            self.add_bytes(&bytes);
+        }
+    }
+
+    // Removes a value added before. Nulls are not counted.
+    pub fn remove_value(&mut self, value: &Value) {
+        if let Some(bytes) = to_bytes(value) {
+            self.remove_bytes(&bytes);
+        }
+    }
+
+    pub fn estimate_value(&self, value: &Value) -> u64 {
+        to_bytes(value).map_or(0, |bytes| self.estimate_bytes(&bytes))
+    }
+
+    pub fn add_bytes(&mut self, bytes: &[u8]) {
+        for idx in self.indices(bytes) {
+            self.counters[idx] += 1;
+        }
+        self.total += 1;
+        self.offer(bytes);
+    }
+
+    pub fn remove_bytes(&mut self, bytes: &[u8]) {
+        for idx in self.indices(bytes) {
+            self.counters[idx] = self.counters[idx].saturating_sub(1);
+        }
+        self.total = self.total.saturating_sub(1);
+        if self.estimate_bytes(bytes) == 0 {
+            self.hitters.retain(|hitter| hitter.as_slice() != bytes);
+        }
+    }
+
+    // Never less than the real count of the value.
+    pub fn estimate_bytes(&self, bytes: &[u8]) -> u64 {
+        self.indices(bytes)
+            .into_iter()
+            .map(|idx| self.counters[idx])
+            .min()
+            .unwrap_or(0)
+    }
+
+    // Returns the most frequent values seen, serialized, with their estimated
+    // counts in descending order.
+    pub fn heavy_hitters(&self) -> Vec<(Vec<u8>, u64)> {
+        let mut hitters: Vec<(Vec<u8>, u64)> = self
+            .hitters
+            .iter()
+            .map(|hitter| (hitter.clone(), self.estimate_bytes(hitter)))
+            .collect();
+        hitters.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        hitters
+    }
+
+    // Folds |other| into this sketch, as if all its changes were applied here.
+    // Returns |InvalidInput| if the dimensions differ.
+    pub fn merge(&mut self, other: &Self) -> std::io::Result<()> {
+        if (self.width, self.depth) != (other.width, other.depth) {
+            return Err(invalid_input(
+                "Cannot merge sketches of different dimensions",
+            ));
+        }
+        for (counter, &other) in self.counters.iter_mut().zip(other.counters.iter()) {
+            *counter += other;
+        }
+        self.total += other.total;
+        for hitter in other.hitters.iter() {
+            self.offer(hitter);
+        }
+        Ok(())
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        HEADER_SIZE
+            + 8 * self.counters.len()
+            + self
+                .hitters
+                .iter()
+                .map(|hitter| 4 + hitter.len())
+                .sum::<usize>()
+    }
+
+    // The caller needs to ensure that |dst| has |self.serialized_len()| bytes.
+    pub fn serialize_to(&self, dst: &mut [u8]) {
+        reinterpret::write_u32(dst, self.width as u32);
+        reinterpret::write_u32(&mut dst[4..], self.depth as u32);
+        reinterpret::write_u64(&mut dst[8..], self.total);
+        reinterpret::write_u32(&mut dst[16..], self.hitters.len() as u32);
+        let mut offset = HEADER_SIZE;
+        for &counter in self.counters.iter() {
+            reinterpret::write_u64(&mut dst[offset..], counter);
+            offset += 8;
+        }
+        for hitter in self.hitters.iter() {
+            reinterpret::write_u32(&mut dst[offset..], hitter.len() as u32);
+            dst[(offset + 4)..(offset + 4 + hitter.len())].copy_from_slice(hitter);
+            offset += 4 + hitter.len();
+        }
+    }
+
+    // Returns |InvalidData| if |src| does not hold a serialized sketch.
+    pub fn deserialize_from(src: &[u8]) -> std::io::Result<Self> {
+        let truncated = || invalid_data("Sketch is truncated");
+        if src.len() < HEADER_SIZE {
+            return Err(truncated());
+        }
+        let width = reinterpret::read_u32(src) as usize;
+        let depth = reinterpret::read_u32(&src[4..]) as usize;
+        let hitter_count = reinterpret::read_u32(&src[16..]) as usize;
+        if width == 0 || depth == 0 || hitter_count > HEAVY_HITTERS {
+            return Err(invalid_data("Sketch dimensions are out of range"));
+        }
+        let mut offset = HEADER_SIZE;
+        if (src.len() - offset) / 8 / width < depth {
+            return Err(truncated());
+        }
+        let mut sketch = CountMinSketch::new(width, depth);
+        sketch.total = reinterpret::read_u64(&src[8..]);
+        for counter in sketch.counters.iter_mut() {
+            *counter = reinterpret::read_u64(&src[offset..]);
+            offset += 8;
+        }
+        for _ in 0..hitter_count {
+            if src.len() < offset + 4 {
+                return Err(truncated());
+            }
+            let len = reinterpret::read_u32(&src[offset..]) as usize;
+            if src.len() < offset + 4 + len {
+                return Err(truncated());
+            }
+            sketch
+                .hitters
+                .push(src[(offset + 4)..(offset + 4 + len)].to_vec());
+            offset += 4 + len;
+        }
+        Ok(sketch)
+    }
+
+    // The counter of |bytes| in every row, derived from one 64-bit hash by
+    // double hashing.
+    fn indices(&self, bytes: &[u8]) -> Vec<usize> {
+        let hash = hash(bytes);
+        let (h1, h2) = (hash as u32 as u64, (hash >> 32) | 1);
+        (0..self.depth)
+            .map(|row| {
+                let col = h1.wrapping_add((row as u64).wrapping_mul(h2)) % self.width as u64;
+                row * self.width + col as usize
+            })
+            .collect()
+    }
+
+    // Keeps |bytes| as a heavy hitter if it is among the most frequent.
+    fn offer(&mut self, bytes: &[u8]) {
+        if self.hitters.iter().any(|hitter| hitter.as_slice() == bytes) {
+            return;
+        }
+        if self.hitters.len() < HEAVY_HITTERS {
+            self.hitters.push(bytes.to_vec());
+            return;
+        }
+        let (idx, min) = self
+            .hitters
+            .iter()
+            .enumerate()
+            .map(|(idx, hitter)| (idx, self.estimate_bytes(hitter)))
+            .min_by_key(|&(_, count)| count)
+            .unwrap();
+        if self.estimate_bytes(bytes) > min {
+            self.hitters[idx] = bytes.to_vec();
+        }
+    }
+}
+
+fn to_bytes(value: &Value) -> Option<Vec<u8>> {
+    if value.is_null() {
+        return None;
+    }
+    Some(value.to_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::types::Types;
+
+    #[test]
+    fn estimate_and_heavy_hitters() {
+        let mut sketch = CountMinSketch::default();
+        for i in 0..1000 {
+            sketch.add_value(&Value::new(Types::Integer(i)));
+        }
+        for _ in 0..100 {
+            sketch.add_value(&Value::new(Types::Integer(7)));
+            sketch.add_value(&Value::new(Types::Integer(42)));
+        }
+        sketch.add_value(&Value::null(Types::integer()));
+        assert_eq!(1200, sketch.total());
+        assert!(sketch.estimate_value(&Value::new(Types::Integer(7))) >= 101);
+        assert!(sketch.estimate_value(&Value::new(Types::Integer(3))) < 10);
+
+        let hitters = sketch.heavy_hitters();
+        assert_eq!(HEAVY_HITTERS, hitters.len());
+        let mut top: Vec<Vec<u8>> = hitters[..2].iter().map(|(b, _)| b.clone()).collect();
+        top.sort();
+        let mut expected = vec![
+            to_bytes(&Value::new(Types::Integer(7))).unwrap(),
+            to_bytes(&Value::new(Types::Integer(42))).unwrap(),
+        ];
+        expected.sort();
+        assert_eq!(expected, top);
+
+        // Deletes are subtracted.
+        for _ in 0..101 {
+            sketch.remove_value(&Value::new(Types::Integer(7)));
+        }
+        assert_eq!(1099, sketch.total());
+        assert!(sketch.estimate_value(&Value::new(Types::Integer(7))) < 10);
+        let seven = to_bytes(&Value::new(Types::Integer(7))).unwrap();
+        assert_ne!(seven, sketch.heavy_hitters()[0].0);
+    }
+
+    #[test]
+    fn merge_and_serialize() {
+        let mut sketch = CountMinSketch::new(64, 2);
+        let mut other = CountMinSketch::new(64, 2);
+        for i in 0..10 {
+            sketch.add_bytes(&[i]);
+            other.add_bytes(&[i]);
+            other.add_bytes(&[0]);
+        }
+        assert!(sketch.merge(&other).is_ok());
+        assert_eq!(30, sketch.total());
+        assert!(sketch.estimate_bytes(&[0]) >= 12);
+        assert_eq!(vec![0], sketch.heavy_hitters()[0].0);
+        assert!(sketch.merge(&CountMinSketch::new(32, 2)).is_err());
+
+        let mut buffer = vec![0; sketch.serialized_len()];
+        sketch.serialize_to(&mut buffer);
+        assert_eq!(sketch, CountMinSketch::deserialize_from(&buffer).unwrap());
+        assert!(CountMinSketch::deserialize_from(&buffer[..10]).is_err());
+        assert!(CountMinSketch::deserialize_from(&buffer[..(buffer.len() - 1)]).is_err());
+    }
+}
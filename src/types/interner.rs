@@ -0,0 +1,69 @@
+// Functionality: An interning arena for strings. Identical strings interned
+// into the same arena share one allocation, which executors can hand out as
+// |Varlen::Borrowed| values instead of allocating a |String| per value.
+//
+// Interned strings are never freed before the arena is cleared or dropped, so
+// an arena should live as long as one batch of tuples (e.g. one group-by or
+// join), and be cleared between batches.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+#[derive(Default)]
+pub struct Interner {
+    strings: RefCell<HashSet<Box<str>>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns the interned copy of |s|, allocating one on first use.
+    pub fn intern(&self, s: &str) -> &str {
+        let mut strings = self.strings.borrow_mut();
+        if !strings.contains(s) {
+            strings.insert(Box::from(s));
+        }
+        let interned: &str = strings.get(s).unwrap();
+        // The boxed string never moves or gets freed while |self| is borrowed,
+        // since strings are only dropped by |clear| (which takes |&mut self|)
+        // or on drop.
+        unsafe { &*(interned as *const str) }
+    }
+
+    // The number of distinct strings interned.
+    pub fn len(&self) -> usize {
+        self.strings.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.borrow().is_empty()
+    }
+
+    // Frees all interned strings.
+    pub fn clear(&mut self) {
+        self.strings.get_mut().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern() {
+        let mut interner = Interner::new();
+        assert!(interner.is_empty());
+        let hello = interner.intern("hello");
+        let world = interner.intern("world");
+        let another_hello = interner.intern(&String::from("hello"));
+        assert_eq!("hello", hello);
+        assert_eq!("world", world);
+        assert_eq!(hello.as_ptr(), another_hello.as_ptr());
+        assert_eq!(2, interner.len());
+
+        interner.clear();
+        assert!(interner.is_empty());
+    }
+}
@@ -5,10 +5,10 @@ pub const DBL_MAX: f64 = std::f64::MAX;
 pub const FLT_MIN: f32 = std::f32::MIN;
 pub const FLT_MAX: f32 = std::f32::MAX;
 
-pub const RSDB_INT8_MIN: i8 = std::i8::MIN + 1;
-pub const RSDB_INT16_MIN: i16 = std::i16::MIN + 1;
-pub const RSDB_INT32_MIN: i32 = std::i32::MIN + 1;
-pub const RSDB_INT64_MIN: i64 = std::i64::MIN + 1;
+pub const RSDB_INT8_MIN: i8 = std::i8::MIN;
+pub const RSDB_INT16_MIN: i16 = std::i16::MIN;
+pub const RSDB_INT32_MIN: i32 = std::i32::MIN;
+pub const RSDB_INT64_MIN: i64 = std::i64::MIN;
 pub const RSDB_DECIMAL_MIN: f64 = FLT_MIN as f64;
 pub const RSDB_TIMESTAMP_MIN: u64 = 0;
 pub const RSDB_DATE_MIN: u32 = 0;
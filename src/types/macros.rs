@@ -204,7 +204,16 @@ macro_rules! compare_bool {
 
 macro_rules! compare_timestamp {
     ($x:ident, $y:ident, $closure:tt) => {{
-        Ok($closure($x, unwrapor!($y.get_as_u64()))) as Result<_, Error>
+        let rhs = match $y.content {
+            Types::Timestamp(rhs) => rhs,
+            _ => {
+                let mut rhs = Value::new(Types::timestamp());
+                unwrapor!($y.cast_to(&mut rhs));
+                unwrapor!(rhs.get_as_u64())
+            }
+        };
+        // The packed values do not sort chronologically.
+        Ok($closure(timestamp_micros($x), timestamp_micros(rhs))) as Result<_, Error>
     }};
 }
 
@@ -224,6 +233,43 @@ macro_rules! compare_varchar {
     }};
 }
 
+macro_rules! compare_point {
+    ($x:expr, $y:expr, $z:ident, $closure:tt) => {{
+        let (rx, ry) = match $z.content {
+            Types::Point(rx, ry) => (rx, ry),
+            _ => {
+                let mut rhs = Value::new(Types::point());
+                unwrapor!($z.cast_to(&mut rhs));
+                unwrapor!(rhs.get_as_point())
+            }
+        };
+        // Points are ordered lexicographically by (x, y).
+        let res = match ($x.partial_cmp(&rx), $y.partial_cmp(&ry)) {
+            (Some(Ordering::Less), _) => -1,
+            (Some(Ordering::Greater), _) => 1,
+            (_, Some(Ordering::Less)) => -1,
+            (_, Some(Ordering::Greater)) => 1,
+            _ => 0,
+        };
+        Ok($closure(res, 0)) as Result<_, Error>
+    }};
+}
+
+macro_rules! compare_varbinary {
+    ($x:ident, $y:ident, $closure:tt) => {{
+        let res = match $y.content {
+            Types::Varbinary(ref rhs) => $x.as_slice().cmp(rhs.as_slice()),
+            _ => {
+                let mut rhs = Value::new(Types::varbinary());
+                unwrapor!($y.cast_to(&mut rhs));
+                $x.as_slice().cmp(rhs.borrow().data().unwrap_or(&[]))
+            }
+        };
+        // Bytes are ordered lexicographically, as by |Ord| on slices.
+        Ok($closure(res as i8, 0)) as Result<_, Error>
+    }};
+}
+
 macro_rules! compare {
     ($x:ident, $y:ident, $closure1:tt, $closure2:tt) => {{
         unwrapor!(assert_comparable($x, $y));
@@ -247,6 +293,8 @@ macro_rules! compare {
                 Types::Timestamp(lhs) => compare_timestamp!(lhs, $y, $closure1).log_and().ok(),
                 Types::Decimal(lhs) => compare_decimal!(lhs, $y, $closure2).log_and().ok(),
                 Types::Varchar(ref lhs) => compare_varchar!(lhs, $y, $closure1).log_and().ok(),
+                Types::Point(lx, ly) => compare_point!(lx, ly, $y, $closure1).log_and().ok(),
+                Types::Varbinary(ref lhs) => compare_varbinary!(lhs, $y, $closure1).log_and().ok(),
             }
         }
     }};
@@ -301,16 +349,16 @@ macro_rules! forward {
 
 macro_rules! nullas {
     ($x:ident) => {{
-        Ok(Value::new($x.content.clone().null_val()?))
+        Ok(Value::null($x.content.clone()))
     }};
 }
 
 macro_rules! string {
-    ($x:ident, $y:expr) => {{
+    ($x:ident, $null:expr, $val:expr) => {{
         if $x.is_null() {
-            $y.to_string()
+            $null.to_string()
         } else {
-            $x.to_string()
+            $val.to_string()
         }
     }};
 }
@@ -371,7 +419,7 @@ macro_rules! arithmetic_impl {
 macro_rules! genmatch {
     ($x:expr, $default:expr, $( { [$( $variant:ident ),*], $val:expr } ),*) => {{
         match $x {
-            $( $( Types::$variant(_) )|* => $val, )*
+            $( $( Types::$variant(..) )|* => $val, )*
             _ => $default,
         }
     }};
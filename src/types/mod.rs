@@ -1,6 +1,14 @@
 #[macro_use]
 mod macros;
 
+pub mod count_min_sketch;
+pub mod hyperloglog;
+pub mod interner;
+pub mod reservoir_sample;
+#[cfg(feature = "serde")]
+pub mod serde_impls;
+pub mod string_functions;
+pub mod timestamp;
 pub mod types;
 pub mod value;
 
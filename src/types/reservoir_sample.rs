@@ -0,0 +1,263 @@
+// Functionality: A reservoir sample, i.e. a uniform random sample of at most
+// |capacity| values out of all values added, maintained in one pass. It backs
+// estimates that need actual values, e.g. range selectivity and histograms.
+//
+// Once the reservoir is full, the n-th value added replaces a random sample
+// with probability capacity / n. Removing a value drops it from the sample if
+// it was sampled, so the sample shrinks under deletes until it is rebuilt from
+// a full scan.
+//
+// Serialized format (size in byte):
+//  ---------------------------------------------------------------------
+// | Capacity (4) | Population (8) | SampleCount (4) | SampleLen_1 (4) | ...
+//  ---------------------------------------------------------------------
+//  ------------------------------------------
+// | Sample_1 (SampleLen_1) | SampleLen_2 (4) | ...
+//  ------------------------------------------
+
+use crate::common::error::*;
+use crate::common::reinterpret;
+use crate::types::value::Value;
+
+pub const DEFAULT_CAPACITY: usize = 256;
+
+const HEADER_SIZE: usize = 16;
+// The pseudo random generator is seeded deterministically, so that samples
+// are reproducible.
+const SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+#[derive(Clone, Debug)]
+pub struct ReservoirSample {
+    capacity: usize,
+    // The number of values added and not removed.
+    population: u64,
+    // Serialized values.
+    samples: Vec<Vec<u8>>,
+    rng: u64,
+}
+
+impl PartialEq for ReservoirSample {
+    fn eq(&self, other: &Self) -> bool {
+        self.capacity == other.capacity
+            && self.population == other.population
+            && self.samples == other.samples
+    }
+}
+
+impl Default for ReservoirSample {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl ReservoirSample {
+    // |capacity| is at least 1.
+    pub fn new(capacity: usize) -> Self {
+        ReservoirSample {
+            capacity: capacity.max(1),
+            population: 0,
+            samples: Vec::new(),
+            rng: SEED,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn population(&self) -> u64 {
+        self.population
+    }
+
+    // The sampled values, serialized.
+    pub fn samples(&self) -> &Vec<Vec<u8>> {
+        &self.samples
+    }
+
+    // Adds a value. Nulls are not sampled.
+    pub fn add_value(&mut self, value: &Value) {
+        if let Some(bytes) = to_bytes(value) {
+            self.add_bytes(&bytes);
+        }
+    }
+
+    // Removes a value added before. Nulls are not sampled.
+    pub fn remove_value(&mut self, value: &Value) {
+        if let Some(bytes) = to_bytes(value) {
+            self.remove_bytes(&bytes);
+        }
+    }
+
+    pub fn add_bytes(&mut self, bytes: &[u8]) {
+        self.population += 1;
+        if self.samples.len() < self.capacity {
+            self.samples.push(bytes.to_vec());
+            return;
+        }
+        let idx = self.next_random() % self.population;
+        if idx < self.capacity as u64 {
+            self.samples[idx as usize] = bytes.to_vec();
+        }
+    }
+
+    pub fn remove_bytes(&mut self, bytes: &[u8]) {
+        self.population = self.population.saturating_sub(1);
+        if let Some(idx) = self.samples.iter().position(|s| s.as_slice() == bytes) {
+            self.samples.swap_remove(idx);
+        }
+    }
+
+    // Folds |other| into this sample, so that it samples the values of both
+    // uniformly. Returns |InvalidInput| if the capacities differ.
+    pub fn merge(&mut self, other: &Self) -> std::io::Result<()> {
+        if self.capacity != other.capacity {
+            return Err(invalid_input(
+                "Cannot merge samples of different capacities",
+            ));
+        }
+        let mut mine = std::mem::take(&mut self.samples);
+        let mut theirs = other.samples.clone();
+        // Every pick comes from either side in proportion to its population.
+        let (mut mine_left, mut theirs_left) = (self.population, other.population);
+        while self.samples.len() < self.capacity && !(mine.is_empty() && theirs.is_empty()) {
+            let pick_mine = match (mine.is_empty(), theirs.is_empty()) {
+                (false, true) => true,
+                (true, false) => false,
+                _ => self.next_random() % (mine_left + theirs_left).max(1) < mine_left,
+            };
+            let random = self.next_random();
+            let (from, left) = match pick_mine {
+                true => (&mut mine, &mut mine_left),
+                false => (&mut theirs, &mut theirs_left),
+            };
+            let idx = (random % from.len() as u64) as usize;
+            self.samples.push(from.swap_remove(idx));
+            *left = left.saturating_sub(1);
+        }
+        self.population += other.population;
+        Ok(())
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        HEADER_SIZE
+            + self
+                .samples
+                .iter()
+                .map(|sample| 4 + sample.len())
+                .sum::<usize>()
+    }
+
+    // The caller needs to ensure that |dst| has |self.serialized_len()| bytes.
+    pub fn serialize_to(&self, dst: &mut [u8]) {
+        reinterpret::write_u32(dst, self.capacity as u32);
+        reinterpret::write_u64(&mut dst[4..], self.population);
+        reinterpret::write_u32(&mut dst[12..], self.samples.len() as u32);
+        let mut offset = HEADER_SIZE;
+        for sample in self.samples.iter() {
+            reinterpret::write_u32(&mut dst[offset..], sample.len() as u32);
+            dst[(offset + 4)..(offset + 4 + sample.len())].copy_from_slice(sample);
+            offset += 4 + sample.len();
+        }
+    }
+
+    // Returns |InvalidData| if |src| does not hold a serialized sample.
+    pub fn deserialize_from(src: &[u8]) -> std::io::Result<Self> {
+        let truncated = || invalid_data("Sample is truncated");
+        if src.len() < HEADER_SIZE {
+            return Err(truncated());
+        }
+        let capacity = reinterpret::read_u32(src) as usize;
+        let sample_count = reinterpret::read_u32(&src[12..]) as usize;
+        if capacity == 0 || sample_count > capacity {
+            return Err(invalid_data("Sample count is out of range"));
+        }
+        let mut sample = ReservoirSample::new(capacity);
+        sample.population = reinterpret::read_u64(&src[4..]);
+        let mut offset = HEADER_SIZE;
+        for _ in 0..sample_count {
+            if src.len() < offset + 4 {
+                return Err(truncated());
+            }
+            let len = reinterpret::read_u32(&src[offset..]) as usize;
+            if src.len() < offset + 4 + len {
+                return Err(truncated());
+            }
+            sample
+                .samples
+                .push(src[(offset + 4)..(offset + 4 + len)].to_vec());
+            offset += 4 + len;
+        }
+        Ok(sample)
+    }
+
+    // xorshift64*.
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+fn to_bytes(value: &Value) -> Option<Vec<u8>> {
+    if value.is_null() {
+        return None;
+    }
+    Some(value.to_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::types::Types;
+
+    #[test]
+    fn add_and_remove() {
+        let mut sample = ReservoirSample::new(100);
+        for i in 0..10_000u32 {
+            sample.add_bytes(&i.to_le_bytes());
+        }
+        sample.add_value(&Value::null(Types::integer()));
+        assert_eq!(10_000, sample.population());
+        let mut bytes = ReservoirSample::new(1);
+        bytes.add_value(&Value::new(Types::Varbinary(vec![1, 2, 3])));
+        assert_eq!(&[3, 0, 0, 0, 1, 2, 3], &bytes.samples()[0][..7]);
+        assert_eq!(100, sample.samples().len());
+
+        // The sample is spread over the whole input.
+        let late = sample
+            .samples()
+            .iter()
+            .filter(|s| reinterpret::read_u32(s) >= 5000)
+            .count();
+        assert!(late > 30 && late < 70, "late = {}", late);
+
+        let removed = sample.samples()[0].clone();
+        sample.remove_bytes(&removed);
+        assert_eq!(9999, sample.population());
+        assert_eq!(99, sample.samples().len());
+        assert!(!sample.samples().contains(&removed));
+    }
+
+    #[test]
+    fn merge_and_serialize() {
+        let mut sample = ReservoirSample::new(10);
+        let mut other = ReservoirSample::new(10);
+        for i in 0..4u8 {
+            sample.add_bytes(&[i]);
+        }
+        for i in 100..200u8 {
+            other.add_bytes(&[i]);
+        }
+        assert!(sample.merge(&other).is_ok());
+        assert_eq!(104, sample.population());
+        assert_eq!(10, sample.samples().len());
+        assert!(sample.merge(&ReservoirSample::new(5)).is_err());
+
+        let mut buffer = vec![0; sample.serialized_len()];
+        sample.serialize_to(&mut buffer);
+        assert_eq!(sample, ReservoirSample::deserialize_from(&buffer).unwrap());
+        assert!(ReservoirSample::deserialize_from(&buffer[..10]).is_err());
+        assert!(ReservoirSample::deserialize_from(&buffer[..(buffer.len() - 1)]).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+// Functionality: |Serialize| and |Deserialize| for values, types, columns and
+// schemas, behind the "serde" feature, so that applications embedding the
+// crate can persist catalogs or exchange rows in any serde format. In JSON:
+//
+// Value:  {"Integer":42}, {"Varchar":"apple"}, {"Point":[1.5,-2.0]}
+// Column: {"name":"Id","type":"BIGINT","length":8}
+// Schema: {"columns":[{"name":"Id","type":"BIGINT","length":8}, ...]}
+//
+// Values are tagged with their type. Null values are null, e.g.
+// {"Integer":null}, and so is the largest varchar, which has no string;
+// timestamps are formatted like |Value::to_string|. Column offsets are not
+// stored, since |Schema::new| computes them again.
+
+use crate::catalog::column::Column;
+use crate::catalog::schema::Schema;
+use crate::types::limits::*;
+use crate::types::timestamp::format_timestamp;
+use crate::types::timestamp::parse_timestamp;
+use crate::types::types::Str;
+use crate::types::types::Types;
+use crate::types::types::Varlen;
+use crate::types::value::is_null_sentinel;
+use crate::types::value::Value;
+use serde::de::Error;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use std::borrow::Cow;
+
+// The serialized form of |Types|. Strings and bytes are borrowed when
+// serializing, and owned when deserialized.
+#[derive(Serialize, Deserialize)]
+enum TypesRepr<'a> {
+    Boolean(Option<bool>),
+    TinyInt(Option<i8>),
+    SmallInt(Option<i16>),
+    Integer(Option<i32>),
+    BigInt(Option<i64>),
+    Decimal(Option<f64>),
+    Timestamp(Option<Cow<'a, str>>),
+    Varchar(Option<Cow<'a, str>>),
+    Point(Option<(f64, f64)>),
+    Varbinary(Cow<'a, [u8]>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct ColumnRepr<'a> {
+    name: Cow<'a, str>,
+    #[serde(rename = "type")]
+    types: Cow<'a, str>,
+    length: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SchemaRepr<'a> {
+    columns: Vec<Column<'a>>,
+}
+
+impl<'a> Serialize for Types<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        repr(self, is_null_sentinel(self)).serialize(serializer)
+    }
+}
+
+impl<'de, 'a> Deserialize<'de> for Types<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        from_repr(TypesRepr::deserialize(deserializer)?).map(|(types, _)| types)
+    }
+}
+
+impl<'a> Serialize for Value<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        repr(self.borrow(), self.is_null()).serialize(serializer)
+    }
+}
+
+impl<'de, 'a> Deserialize<'de> for Value<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (types, null) = from_repr(TypesRepr::deserialize(deserializer)?)?;
+        Ok(match null {
+            true => Value::null(types),
+            false => Value::new(types),
+        })
+    }
+}
+
+impl<'a> Serialize for Column<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = ColumnRepr {
+            name: Cow::Borrowed(self.name()),
+            types: Cow::Owned(self.types().name()),
+            length: self.len(),
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de, 'a> Deserialize<'de> for Column<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = ColumnRepr::deserialize(deserializer)?;
+        // Type IDs are dense from 1; see |Types::from_id|.
+        let types = (1..)
+            .map(Types::from_id)
+            .take_while(Option::is_some)
+            .flatten()
+            .find(|types| types.name() == repr.types)
+            .ok_or_else(|| D::Error::custom(format!("unknown column type `{}`", repr.types)))?;
+        Ok(Column::new(repr.name.into_owned(), types, repr.length))
+    }
+}
+
+impl<'a> Serialize for Schema<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = SchemaRepr {
+            columns: self.columns().clone(),
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de, 'a> Deserialize<'de> for Schema<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SchemaRepr::deserialize(deserializer).map(|repr| Schema::new(repr.columns))
+    }
+}
+
+// The serialized form of |types|, which is null if |null|. Varchars have no
+// null of their own, and the largest varchar is serialized as null.
+fn repr<'a>(types: &'a Types<'a>, null: bool) -> TypesRepr<'a> {
+    match types {
+        Types::Boolean(val) => TypesRepr::Boolean(non_null(null, || *val != 0)),
+        Types::TinyInt(val) => TypesRepr::TinyInt(non_null(null, || *val)),
+        Types::SmallInt(val) => TypesRepr::SmallInt(non_null(null, || *val)),
+        Types::Integer(val) => TypesRepr::Integer(non_null(null, || *val)),
+        Types::BigInt(val) => TypesRepr::BigInt(non_null(null, || *val)),
+        Types::Decimal(val) => TypesRepr::Decimal(non_null(null, || *val)),
+        Types::Timestamp(val) => {
+            TypesRepr::Timestamp(non_null(null, || Cow::Owned(format_timestamp(*val))))
+        }
+        Types::Varchar(varlen) => TypesRepr::Varchar(varlen.borrow().ok().map(Cow::Borrowed)),
+        Types::Point(x, y) => TypesRepr::Point(non_null(null, || (*x, *y))),
+        Types::Varbinary(bytes) => TypesRepr::Varbinary(Cow::Borrowed(bytes)),
+    }
+}
+
+// The types deserialized from |repr|, and whether it is null. Nulls take the
+// null values of their types.
+fn from_repr<'a, E: Error>(repr: TypesRepr) -> Result<(Types<'a>, bool), E> {
+    let null = match &repr {
+        TypesRepr::Boolean(val) => val.is_none(),
+        TypesRepr::TinyInt(val) => val.is_none(),
+        TypesRepr::SmallInt(val) => val.is_none(),
+        TypesRepr::Integer(val) => val.is_none(),
+        TypesRepr::BigInt(val) => val.is_none(),
+        TypesRepr::Decimal(val) => val.is_none(),
+        TypesRepr::Timestamp(val) => val.is_none(),
+        TypesRepr::Point(val) => val.is_none(),
+        TypesRepr::Varchar(_) | TypesRepr::Varbinary(_) => false,
+    };
+    let types = match repr {
+        TypesRepr::Boolean(val) => Types::Boolean(val.map_or(RSDB_BOOLEAN_NULL, |val| val as i8)),
+        TypesRepr::TinyInt(val) => Types::TinyInt(val.unwrap_or(RSDB_INT8_NULL)),
+        TypesRepr::SmallInt(val) => Types::SmallInt(val.unwrap_or(RSDB_INT16_NULL)),
+        TypesRepr::Integer(val) => Types::Integer(val.unwrap_or(RSDB_INT32_NULL)),
+        TypesRepr::BigInt(val) => Types::BigInt(val.unwrap_or(RSDB_INT64_NULL)),
+        TypesRepr::Decimal(val) => Types::Decimal(val.unwrap_or(RSDB_DECIMAL_NULL)),
+        TypesRepr::Timestamp(None) => Types::Timestamp(RSDB_TIMESTAMP_NULL),
+        TypesRepr::Timestamp(Some(s)) => Types::Timestamp(
+            parse_timestamp(&s).map_err(|_| E::custom(format!("invalid timestamp `{}`", s)))?,
+        ),
+        TypesRepr::Varchar(s) => Types::Varchar(Varlen::Owned(match s {
+            Some(s) => Str::Val(s.into_owned()),
+            None => Str::MaxVal,
+        })),
+        TypesRepr::Point(point) => {
+            let (x, y) = point.unwrap_or((RSDB_DECIMAL_NULL, RSDB_DECIMAL_NULL));
+            Types::Point(x, y)
+        }
+        TypesRepr::Varbinary(bytes) => Types::Varbinary(bytes.into_owned()),
+    };
+    Ok((types, null))
+}
+
+// Returns |f()|, unless |null|.
+fn non_null<T, F: FnOnce() -> T>(null: bool, f: F) -> Option<T> {
+    match null {
+        true => None,
+        false => Some(f()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::types::Operation;
+
+    fn round_trip<'a>(value: &Value<'a>) -> Value<'static> {
+        let json = serde_json::to_string(value).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn values() {
+        let apple = Value::new(Types::Varchar(Varlen::Borrowed(Str::Val("apple"))));
+        assert_eq!(
+            "{\"Varchar\":\"apple\"}",
+            serde_json::to_string(&apple).unwrap()
+        );
+        assert_eq!(
+            "{\"Integer\":null}",
+            serde_json::to_string(&Value::null(Types::integer())).unwrap()
+        );
+        assert_eq!(
+            "{\"Point\":[1.5,-2.0]}",
+            serde_json::to_string(&Value::new(Types::Point(1.5, -2.0))).unwrap()
+        );
+
+        let values = vec![
+            Value::new(Types::Boolean(1)),
+            Value::new(Types::TinyInt(-3)),
+            Value::new(Types::SmallInt(300)),
+            Value::new(Types::Integer(70000)),
+            Value::new(Types::BigInt(1 << 40)),
+            Value::new(Types::Decimal(0.25)),
+            Value::new(Types::Timestamp(
+                parse_timestamp("2021-06-01 12:00:00").unwrap(),
+            )),
+            apple,
+            Value::new(Types::owned()),
+            Value::new(Types::Point(1.5, -2.0)),
+            Value::new(Types::Varbinary(vec![0x1f, 0])),
+        ];
+        for value in values.iter() {
+            let other = round_trip(value);
+            assert_eq!(value.borrow().id(), other.borrow().id());
+            assert_eq!(value.to_string(), other.to_string());
+            assert_eq!(value.len(), other.len());
+        }
+        for types in &[
+            Types::boolean(),
+            Types::decimal(),
+            Types::timestamp(),
+            Types::point(),
+        ] {
+            let null = Value::null(types.clone());
+            assert!(round_trip(&null).is_null());
+        }
+        for min in &[
+            Types::TinyInt(i8::MIN),
+            Types::Integer(i32::MIN),
+            Types::BigInt(i64::MIN),
+        ] {
+            let other = round_trip(&Value::new(min.clone()));
+            assert!(!other.is_null());
+            assert_eq!(Value::new(min.clone()).to_string(), other.to_string());
+        }
+
+        let error = serde_json::from_str::<Value>("{\"Timestamp\":\"noon\"}").unwrap_err();
+        assert!(error.to_string().starts_with("invalid timestamp `noon`"));
+        assert!(serde_json::from_str::<Value>("{\"Integer\":\"1\"}").is_err());
+    }
+
+    #[test]
+    fn schemas() {
+        let schema = Schema::new(vec![
+            Column::new("Id".to_string(), Types::bigint(), 8),
+            Column::new("Name".to_string(), Types::owned(), 20),
+            Column::new("Location".to_string(), Types::point(), 16),
+        ]);
+        let json = serde_json::to_string(&schema).unwrap();
+        assert_eq!(
+            "{\"columns\":[{\"name\":\"Id\",\"type\":\"BIGINT\",\"length\":8},\
+             {\"name\":\"Name\",\"type\":\"VARCHAR\",\"length\":20},\
+             {\"name\":\"Location\",\"type\":\"POINT\",\"length\":16}]}",
+            json
+        );
+        let other: Schema = serde_json::from_str(&json).unwrap();
+        assert_eq!(schema, other);
+        assert_eq!(schema.len(), other.len());
+        for (lhs, rhs) in schema.columns().iter().zip(other.columns().iter()) {
+            assert_eq!(lhs.to_string(), rhs.to_string());
+        }
+
+        let error =
+            serde_json::from_str::<Column>("{\"name\":\"Id\",\"type\":\"UUID\",\"length\":16}")
+                .unwrap_err();
+        assert!(error.to_string().starts_with("unknown column type `UUID`"));
+    }
+}
@@ -0,0 +1,254 @@
+// Functionality: SQL string functions on values, for expression evaluation:
+// |concat|, |substring|, |upper|, |lower|, |trim|, |length| and |like|.
+//
+// A null argument makes the result NULL. Varchar has no null value, so every
+// function returns None for NULL. Positions and lengths count characters, not
+// bytes, and positions start at 1, as in SQL.
+
+use crate::types::error::Error;
+use crate::types::error::ErrorKind;
+use crate::types::types::Str;
+use crate::types::types::Types;
+use crate::types::types::Varlen;
+use crate::types::value::Value;
+use std::result::Result;
+
+// Concatenates |values|, casting non-Varchar ones to strings.
+pub fn concat(values: &[Value]) -> Result<Option<Value<'static>>, Error> {
+    let mut result = String::new();
+    for value in values.iter() {
+        if value.is_null() {
+            return Ok(None);
+        }
+        match value.borrow() {
+            Types::Varchar(varlen) => result.push_str(varlen.borrow()?),
+            types => result.push_str(types.to_varlen()?.borrow()?),
+        }
+    }
+    Ok(Some(varchar(result)))
+}
+
+// Returns the |len| characters starting at |start|, or all of them if |len|
+// is None. As in SQL, characters before the first one are counted but not
+// returned, e.g. SUBSTRING('abc' FROM 0 FOR 2) is 'a'. Returns |NotSupported|
+// for a negative length.
+pub fn substring(
+    value: &Value,
+    start: &Value,
+    len: Option<&Value>,
+) -> Result<Option<Value<'static>>, Error> {
+    let s = match string(value)? {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+    let start = match integer(start)? {
+        Some(start) => start,
+        None => return Ok(None),
+    };
+    let end = match len.map(integer).transpose()? {
+        Some(None) => return Ok(None),
+        Some(Some(len)) if len < 0 => {
+            return Err(Error::new(
+                ErrorKind::NotSupported,
+                "Negative substring length",
+            ))
+        }
+        Some(Some(len)) => start.saturating_add(len),
+        None => i64::MAX,
+    };
+    let result = s
+        .chars()
+        .enumerate()
+        .filter(|(idx, _)| (start..end).contains(&(*idx as i64 + 1)))
+        .map(|(_, c)| c)
+        .collect();
+    Ok(Some(varchar(result)))
+}
+
+pub fn upper(value: &Value) -> Result<Option<Value<'static>>, Error> {
+    Ok(string(value)?.map(|s| varchar(s.to_uppercase())))
+}
+
+pub fn lower(value: &Value) -> Result<Option<Value<'static>>, Error> {
+    Ok(string(value)?.map(|s| varchar(s.to_lowercase())))
+}
+
+// Removes leading and trailing whitespace.
+pub fn trim(value: &Value) -> Result<Option<Value<'static>>, Error> {
+    Ok(string(value)?.map(|s| varchar(s.trim().to_string())))
+}
+
+// The number of characters, as a BigInt.
+pub fn length(value: &Value) -> Result<Option<Value<'static>>, Error> {
+    Ok(string(value)?.map(|s| Value::new(Types::BigInt(s.chars().count() as i64))))
+}
+
+// Matches |value| against |pattern|, where '%' matches any sequence of
+// characters, '_' matches one character, and '\' escapes the next character.
+// Returns a Boolean.
+pub fn like(value: &Value, pattern: &Value) -> Result<Option<Value<'static>>, Error> {
+    let (s, pattern) = match (string(value)?, string(pattern)?) {
+        (Some(s), Some(pattern)) => (s, pattern),
+        _ => return Ok(None),
+    };
+    let s: Vec<char> = s.chars().collect();
+    let tokens = tokenize(&pattern)?;
+    let matched = matches(&s, &tokens);
+    Ok(Some(Value::new(Types::Boolean(matched as i8))))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token {
+    Literal(char),
+    // '_'
+    One,
+    // '%'
+    Any,
+}
+
+fn tokenize(pattern: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '%' => Token::Any,
+            '_' => Token::One,
+            '\\' => match chars.next() {
+                Some(c) => Token::Literal(c),
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::CannotParse,
+                        "Pattern ends with an escape",
+                    ))
+                }
+            },
+            c => Token::Literal(c),
+        });
+    }
+    Ok(tokens)
+}
+
+// Matches greedily, backtracking to the position after the last '%' on a
+// mismatch, which takes O(|s| * |tokens|) time at worst.
+fn matches(s: &[char], tokens: &[Token]) -> bool {
+    let (mut i, mut j) = (0, 0);
+    // The token after the last '%', and the character it was tried at.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while i < s.len() {
+        match tokens.get(j) {
+            Some(Token::Any) => {
+                j += 1;
+                backtrack = Some((j, i));
+            }
+            Some(Token::One) => {
+                i += 1;
+                j += 1;
+            }
+            Some(Token::Literal(c)) if *c == s[i] => {
+                i += 1;
+                j += 1;
+            }
+            _ => match backtrack {
+                Some((token, start)) => {
+                    j = token;
+                    i = start + 1;
+                    backtrack = Some((token, start + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    tokens[j..].iter().all(|token| *token == Token::Any)
+}
+
+fn varchar(s: String) -> Value<'static> {
+    Value::new(Types::Varchar(Varlen::Owned(Str::Val(s))))
+}
+
+// Returns None for null. Returns |NotSupported| if |value| is not a Varchar.
+fn string(value: &Value) -> Result<Option<String>, Error> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    match value.borrow() {
+        Types::Varchar(varlen) => Ok(Some(varlen.borrow()?.to_string())),
+        _ => Err(Error::new(ErrorKind::NotSupported, "Non varchar")),
+    }
+}
+
+// Returns None for null. Returns |NotSupported| if |value| is not an integer.
+fn integer(value: &Value) -> Result<Option<i64>, Error> {
+    match value.is_null() {
+        true => Ok(None),
+        false => value.borrow().get_as_i64().map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::types::Operation;
+
+    fn s(s: &str) -> Value<'static> {
+        varchar(s.to_string())
+    }
+
+    fn int(val: i64) -> Value<'static> {
+        Value::new(Types::BigInt(val))
+    }
+
+    fn null() -> Value<'static> {
+        Value::null(Types::bigint())
+    }
+
+    fn text(result: Result<Option<Value>, Error>) -> Option<String> {
+        result.unwrap().map(|value| value.to_string())
+    }
+
+    #[test]
+    fn string_functions() {
+        let values = [s("ab"), int(1), s("ç")];
+        assert_eq!(Some("ab1ç".to_string()), text(concat(&values)));
+        assert_eq!(None, text(concat(&[s("ab"), null()])));
+
+        let hello = s("héllo");
+        let substring =
+            |start, len: Option<Value>| text(substring(&hello, &int(start), len.as_ref()));
+        assert_eq!(Some("éll".to_string()), substring(2, Some(int(3))));
+        assert_eq!(Some("éllo".to_string()), substring(2, None));
+        assert_eq!(Some("h".to_string()), substring(0, Some(int(2))));
+        assert_eq!(Some("".to_string()), substring(9, Some(int(2))));
+        assert_eq!(None, substring(2, Some(null())));
+        assert!(super::substring(&hello, &int(1), Some(&int(-1))).is_err());
+        assert!(super::substring(&hello, &s("1"), None).is_err());
+
+        assert_eq!(Some("HÉLLO".to_string()), text(upper(&hello)));
+        assert_eq!(Some("abc".to_string()), text(lower(&s("AbC"))));
+        assert_eq!(Some("a b".to_string()), text(trim(&s(" \ta b\n"))));
+        let len = length(&hello).unwrap().unwrap();
+        assert_eq!(Some(true), len.eq(&int(5)));
+        assert_eq!(None, text(length(&null())));
+        assert!(upper(&int(1)).is_err());
+    }
+
+    #[test]
+    fn like_patterns() {
+        let like = |value: &str, pattern: &str| text(like(&s(value), &s(pattern)));
+        let yes = Some("true".to_string());
+        let no = Some("false".to_string());
+        assert_eq!(yes, like("hello", "hello"));
+        assert_eq!(yes, like("hello", "h%"));
+        assert_eq!(yes, like("hello", "%llo"));
+        assert_eq!(yes, like("hello", "h_l%o"));
+        assert_eq!(yes, like("hello", "%"));
+        assert_eq!(yes, like("", "%%"));
+        assert_eq!(yes, like("mississippi", "%iss%ppi"));
+        assert_eq!(yes, like("50%", "50\\%"));
+        assert_eq!(no, like("500", "50\\%"));
+        assert_eq!(no, like("hello", "h_llo_"));
+        assert_eq!(no, like("hello", "hell"));
+        assert_eq!(no, like("", "_"));
+        assert_eq!(None, text(super::like(&null(), &s("%"))));
+        assert!(super::like(&s("a"), &s("a\\")).is_err());
+    }
+}
@@ -0,0 +1,350 @@
+// Functionality: Conversions of timestamps between their packed storage in
+// |Types::Timestamp|, their calendar fields, and ISO-8601 strings such as
+// "2024-01-15 10:30:00+02" or "2024-01-15T10:30:00.250Z".
+//
+// Packed format, from the most significant digits:
+//   (((((month * 32 + day) * 27 + (tz + 12)) * 10000 + year) * 100000
+//     + second of day) * 1000000 + microsecond
+// The packed values do not sort chronologically (the month comes before the
+// year), so comparisons go through |DateTime::to_unix_micros|.
+
+use crate::types::error::Error;
+use crate::types::error::ErrorKind;
+use std::fmt;
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+// The calendar fields of a timestamp, in the local time of its offset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub micro: u32,
+    // The offset from UTC in hours, from -12 to +14.
+    pub tz: i8,
+}
+
+impl DateTime {
+    // Returns |CannotParse| if a field is out of range, e.g. February 30.
+    pub fn new(
+        (year, month, day): (u16, u8, u8),
+        (hour, minute, second, micro): (u8, u8, u8, u32),
+        tz: i8,
+    ) -> Result<Self, Error> {
+        let valid = year <= 9999
+            && (1..=12).contains(&month)
+            && day >= 1
+            && day <= days_in_month(year, month)
+            && hour < 24
+            && minute < 60
+            && second < 60
+            && micro < 1_000_000
+            && (-12..=14).contains(&tz);
+        match valid {
+            true => Ok(DateTime {
+                year,
+                month,
+                day,
+                hour,
+                minute,
+                second,
+                micro,
+                tz,
+            }),
+            false => Err(invalid("Timestamp field is out of range")),
+        }
+    }
+
+    // The storage of |Types::Timestamp|.
+    pub fn pack(&self) -> u64 {
+        let seconds = self.hour as u64 * 3600 + self.minute as u64 * 60 + self.second as u64;
+        let mut tm = self.month as u64 * 32 + self.day as u64;
+        tm = tm * 27 + (self.tz + 12) as u64;
+        tm = tm * 10000 + self.year as u64;
+        tm = tm * 100000 + seconds;
+        tm * 1000000 + self.micro as u64
+    }
+
+    // The inverse of |pack|. Fields are not validated.
+    pub fn unpack(mut tm: u64) -> Self {
+        let micro = (tm % 1000000) as u32;
+        tm /= 1000000;
+        let seconds = (tm % 100000) as u32;
+        tm /= 100000;
+        let year = (tm % 10000) as u16;
+        tm /= 10000;
+        let tz = (tm % 27) as i8 - 12;
+        tm /= 27;
+        let day = (tm % 32) as u8;
+        tm /= 32;
+        DateTime {
+            year,
+            month: tm as u8,
+            day,
+            hour: (seconds / 3600) as u8,
+            minute: (seconds / 60 % 60) as u8,
+            second: (seconds % 60) as u8,
+            micro,
+            tz,
+        }
+    }
+
+    // Parses "YYYY-MM-DD[( |T)HH:MM[:SS[.ffffff]]][Z|(+|-)HH[[:]MM]]". A
+    // missing time is midnight, and a missing offset is UTC. Offsets need to
+    // be whole hours.
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let s = s.trim();
+        let (date, rest) = match s.find(&[' ', 'T'][..]) {
+            Some(pos) => (&s[..pos], s[(pos + 1)..].trim_start()),
+            None => (s, ""),
+        };
+        let mut fields = date.split('-');
+        let year = number(fields.next(), 4)?;
+        let month = number(fields.next(), 2)?;
+        let day = number(fields.next(), 2)?;
+        if fields.next().is_some() {
+            return Err(invalid("Timestamp has an invalid date"));
+        }
+
+        // The offset starts at the first sign or 'Z' of the time.
+        let (time, zone) = match rest.find(&['+', '-', 'Z'][..]) {
+            Some(pos) => (&rest[..pos], &rest[pos..]),
+            None => (rest, ""),
+        };
+        let (mut hour, mut minute, mut second, mut micro) = (0, 0, 0, 0);
+        if !time.is_empty() {
+            let (time, fraction) = match time.find('.') {
+                Some(pos) => (&time[..pos], Some(&time[(pos + 1)..])),
+                None => (time, None),
+            };
+            let mut fields = time.split(':');
+            hour = number(fields.next(), 2)?;
+            minute = number(fields.next(), 2)?;
+            if let Some(field) = fields.next() {
+                second = number(Some(field), 2)?;
+            }
+            if fields.next().is_some() || (fraction.is_some() && time.len() != 8) {
+                return Err(invalid("Timestamp has an invalid time"));
+            }
+            if let Some(fraction) = fraction {
+                if fraction.is_empty() || fraction.len() > 6 {
+                    return Err(invalid("Timestamp has an invalid fraction"));
+                }
+                let digits: u32 = number(Some(fraction), fraction.len())?;
+                micro = digits * 10u32.pow(6 - fraction.len() as u32);
+            }
+        }
+        let tz = parse_zone(zone)?;
+        DateTime::new((year, month, day), (hour, minute, second, micro), tz)
+    }
+
+    // The microseconds since 1970-01-01 00:00:00 UTC, negative before.
+    pub fn to_unix_micros(&self) -> i64 {
+        let days = days_from_civil(self.year as i64, self.month as i64, self.day as i64);
+        let seconds = days * SECONDS_PER_DAY
+            + self.hour as i64 * 3600
+            + self.minute as i64 * 60
+            + self.second as i64
+            - self.tz as i64 * 3600;
+        seconds * MICROS_PER_SECOND + self.micro as i64
+    }
+
+    // The inverse of |to_unix_micros|, in the local time of offset |tz|.
+    // Returns |CannotParse| if the year is not in 0..=9999 or |tz| is out of
+    // range.
+    pub fn from_unix_micros(micros: i64, tz: i8) -> Result<Self, Error> {
+        let local = micros + tz as i64 * 3600 * MICROS_PER_SECOND;
+        let seconds = local.div_euclid(MICROS_PER_SECOND);
+        let micro = local.rem_euclid(MICROS_PER_SECOND) as u32;
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let second_of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        if !(0..=9999).contains(&year) {
+            return Err(invalid("Timestamp is out of range"));
+        }
+        DateTime::new(
+            (year as u16, month as u8, day as u8),
+            (
+                (second_of_day / 3600) as u8,
+                (second_of_day / 60 % 60) as u8,
+                (second_of_day % 60) as u8,
+                micro,
+            ),
+            tz,
+        )
+    }
+}
+
+// Formats as "YYYY-MM-DD HH:MM:SS.ffffff+HH", which |DateTime::parse| accepts.
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}{}{:02}",
+            self.year,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+            self.micro,
+            if self.tz < 0 { '-' } else { '+' },
+            self.tz.abs()
+        )
+    }
+}
+
+// Parses an ISO-8601 timestamp into its packed storage; see |DateTime::parse|.
+pub fn parse_timestamp(s: &str) -> Result<u64, Error> {
+    DateTime::parse(s).map(|datetime| datetime.pack())
+}
+
+// Formats a packed timestamp; see |DateTime|'s |Display|.
+pub fn format_timestamp(tm: u64) -> String {
+    DateTime::unpack(tm).to_string()
+}
+
+// Orders packed timestamps chronologically.
+pub fn timestamp_micros(tm: u64) -> i64 {
+    DateTime::unpack(tm).to_unix_micros()
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::CannotParse, msg)
+}
+
+// Parses exactly |len| decimal digits.
+fn number<T: std::str::FromStr>(field: Option<&str>, len: usize) -> Result<T, Error> {
+    match field {
+        Some(field) if field.len() == len && field.bytes().all(|b| b.is_ascii_digit()) => field
+            .parse()
+            .map_err(|_| invalid("Timestamp has an invalid number")),
+        _ => Err(invalid("Timestamp has an invalid number")),
+    }
+}
+
+// Parses "", "Z", "+HH", "+HHMM" or "+HH:MM" (or with '-') into hours.
+fn parse_zone(zone: &str) -> Result<i8, Error> {
+    let (sign, digits) = match zone.chars().next() {
+        None => return Ok(0),
+        Some('Z') if zone.len() == 1 => return Ok(0),
+        Some('+') => (1, &zone[1..]),
+        Some('-') => (-1, &zone[1..]),
+        _ => return Err(invalid("Timestamp has an invalid offset")),
+    };
+    let digits = digits.replacen(':', "", 1);
+    let (hours, minutes) = match digits.len() {
+        2 => (number::<i8>(Some(&digits), 2)?, 0),
+        4 => (
+            number::<i8>(Some(&digits[..2]), 2)?,
+            number::<u8>(Some(&digits[2..]), 2)?,
+        ),
+        _ => return Err(invalid("Timestamp has an invalid offset")),
+    };
+    if minutes != 0 {
+        return Err(invalid("Timestamp offset is not a whole hour"));
+    }
+    Ok(sign * hours)
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        // The days from February 1 to March 1.
+        2 => (days_from_civil(year as i64, 3, 1) - days_from_civil(year as i64, 2, 1)) as u8,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// The days since 1970-01-01 of a date of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// The inverse of |days_from_civil|.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format() {
+        let datetime = DateTime::parse("2024-01-15 10:30:00+02").unwrap();
+        assert_eq!(
+            DateTime::new((2024, 1, 15), (10, 30, 0, 0), 2).unwrap(),
+            datetime
+        );
+        assert_eq!("2024-01-15 10:30:00.000000+02", datetime.to_string());
+        assert_eq!(datetime, DateTime::unpack(datetime.pack()));
+        assert_eq!(datetime, DateTime::parse(&datetime.to_string()).unwrap());
+
+        let datetime = DateTime::parse("1999-12-31T23:59:59.25-05:00").unwrap();
+        assert_eq!(250000, datetime.micro);
+        assert_eq!("1999-12-31 23:59:59.250000-05", datetime.to_string());
+        assert_eq!(
+            "2000-02-29 00:00:00.000000+00",
+            format_timestamp(parse_timestamp("2000-02-29").unwrap())
+        );
+        assert!(DateTime::parse("2024-01-15 10:30Z").is_ok());
+        assert!(DateTime::parse("2024-01-15 10:30:00+0200").is_ok());
+
+        for invalid in [
+            "",
+            "2024-1-15",
+            "2023-02-29",
+            "2024-13-01",
+            "2024-01-15 24:00:00",
+            "2024-01-15 10",
+            "2024-01-15 10:30:00.1234567",
+            "2024-01-15 10:30:00+02:30",
+            "2024-01-15 10:30:00+15",
+            "2024-01-15 10:30:00 UTC",
+        ]
+        .iter()
+        {
+            assert!(DateTime::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn unix_micros() {
+        let epoch = DateTime::parse("1970-01-01 00:00:00Z").unwrap();
+        assert_eq!(0, epoch.to_unix_micros());
+        let datetime = DateTime::parse("2024-01-15 10:30:00.5+02").unwrap();
+        assert_eq!(1705307400500000, datetime.to_unix_micros());
+        let utc = DateTime::from_unix_micros(datetime.to_unix_micros(), 0).unwrap();
+        assert_eq!("2024-01-15 08:30:00.500000+00", utc.to_string());
+        let before = DateTime::from_unix_micros(-1, -12).unwrap();
+        assert_eq!("1969-12-31 11:59:59.999999-12", before.to_string());
+        assert!(DateTime::from_unix_micros(i64::MAX / 2, 0).is_err());
+
+        // Packed values do not sort chronologically.
+        let earlier = parse_timestamp("2023-12-01 00:00:00").unwrap();
+        let later = parse_timestamp("2024-01-01 00:00:00").unwrap();
+        assert!(earlier > later);
+        assert!(timestamp_micros(earlier) < timestamp_micros(later));
+    }
+}
@@ -1,7 +1,10 @@
 use crate::types::error::Error;
 use crate::types::error::ErrorKind;
+use crate::types::hyperloglog::hash as hash_bytes;
 use crate::types::limits::*;
 use crate::types::numeric_util::*;
+use crate::types::timestamp::format_timestamp;
+use crate::types::timestamp::timestamp_micros;
 use std::clone::Clone;
 use std::fmt::Debug;
 use std::result::Result;
@@ -16,6 +19,9 @@ pub enum Types<'a> {
     Decimal(f64),
     Timestamp(u64),
     Varchar(Varlen<'a>),
+    Point(f64, f64),
+    // Arbitrary bytes, e.g. a BLOB.
+    Varbinary(Vec<u8>),
 }
 
 #[derive(Clone, Debug)]
@@ -97,6 +103,7 @@ impl<'a> Types<'a> {
                 Varlen::Owned(s) => s.as_bytes(),
                 Varlen::Borrowed(s) => s.as_bytes(),
             },
+            Self::Varbinary(bytes) => Some(bytes),
             _ => None,
         }
     }
@@ -107,13 +114,14 @@ impl<'a> Types<'a> {
                 Varlen::Owned(s) => s.as_bytes_mut(),
                 Varlen::Borrowed(_) => None,
             },
+            Self::Varbinary(bytes) => Some(bytes),
             _ => None,
         }
     }
 
     pub fn is_inlined(&self) -> bool {
         match self {
-            Types::Varchar(_) => false,
+            Types::Varchar(_) | Types::Varbinary(_) => false,
             _ => true,
         }
     }
@@ -142,6 +150,11 @@ impl<'a> Types<'a> {
                 _ => false,
             },
             Self::Varchar(_) => true,
+            Self::Point(_, _) => match other {
+                Self::Point(_, _) | Self::Varchar(_) => true,
+                _ => false,
+            },
+            Self::Varbinary(_) => matches!(other, Self::Varbinary(_) | Self::Varchar(_)),
         }
     }
 
@@ -155,6 +168,8 @@ impl<'a> Types<'a> {
             Self::Decimal(_) => 8,
             Self::Timestamp(_) => 8,
             Self::Varchar(_) => 0,
+            Self::Point(_, _) => 16,
+            Self::Varbinary(_) => 0,
         }
     }
 
@@ -168,6 +183,81 @@ impl<'a> Types<'a> {
             Self::Decimal(_) => 6,
             Self::Timestamp(_) => 7,
             Self::Varchar(_) => 8,
+            Self::Point(_, _) => 9,
+            Self::Varbinary(_) => 10,
+        }
+    }
+
+    // Copies the value into one that does not borrow, e.g. to keep it beyond
+    // the lifetime of the interner or schema it borrows from.
+    pub fn to_static(&self) -> Types<'static> {
+        match self {
+            Self::Boolean(val) => Types::Boolean(*val),
+            Self::TinyInt(val) => Types::TinyInt(*val),
+            Self::SmallInt(val) => Types::SmallInt(*val),
+            Self::Integer(val) => Types::Integer(*val),
+            Self::BigInt(val) => Types::BigInt(*val),
+            Self::Decimal(val) => Types::Decimal(*val),
+            Self::Timestamp(val) => Types::Timestamp(*val),
+            Self::Varchar(Varlen::Owned(val)) => Types::Varchar(Varlen::Owned(val.clone())),
+            Self::Varchar(Varlen::Borrowed(Str::Val(val))) => {
+                Types::Varchar(Varlen::Owned(Str::Val(val.to_string())))
+            }
+            Self::Varchar(Varlen::Borrowed(Str::MaxVal)) => Types::owned(),
+            Self::Point(x, y) => Types::Point(*x, *y),
+            Self::Varbinary(bytes) => Types::Varbinary(bytes.clone()),
+        }
+    }
+
+    // A 64-bit hash that is stable across runs, for hash joins, hash
+    // aggregation and hash indexes. Values that are |eq| hash alike: numerics
+    // hash by their value as a Decimal, e.g. TinyInt(5) as BigInt(5), and
+    // timestamps by the instant they denote. Decimals that are only almost
+    // equal, or values equal to a Varchar after a cast, may not.
+    pub fn hash(&self) -> u64 {
+        let mut bytes = vec![self.id()];
+        match self {
+            Self::Boolean(val) => bytes.push(*val as u8),
+            Self::TinyInt(_)
+            | Self::SmallInt(_)
+            | Self::Integer(_)
+            | Self::BigInt(_)
+            | Self::Decimal(_) => {
+                // Adding zero turns -0.0 into 0.0, which are equal.
+                let val = self.get_as_f64().unwrap_or_default() + 0.0;
+                bytes[0] = Self::decimal().id();
+                bytes.extend_from_slice(&val.to_bits().to_le_bytes());
+            }
+            Self::Timestamp(val) => {
+                bytes.extend_from_slice(&timestamp_micros(*val).to_le_bytes());
+            }
+            Self::Point(x, y) => {
+                bytes.extend_from_slice(&(x + 0.0).to_bits().to_le_bytes());
+                bytes.extend_from_slice(&(y + 0.0).to_bits().to_le_bytes());
+            }
+            // Strings never contain 0xff, so it stands for the max value.
+            Self::Varchar(_) | Self::Varbinary(_) => {
+                bytes.extend_from_slice(self.data().unwrap_or(&[0xff]))
+            }
+        }
+        hash_bytes(&bytes)
+    }
+
+    // The inverse of |id|, with the zero value of the type. Variable length
+    // types are owned. Returns None for unknown type IDs.
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::boolean()),
+            2 => Some(Self::tinyint()),
+            3 => Some(Self::smallint()),
+            4 => Some(Self::integer()),
+            5 => Some(Self::bigint()),
+            6 => Some(Self::decimal()),
+            7 => Some(Self::timestamp()),
+            8 => Some(Self::owned()),
+            9 => Some(Self::point()),
+            10 => Some(Self::varbinary()),
+            _ => None,
         }
     }
 
@@ -181,6 +271,8 @@ impl<'a> Types<'a> {
             Self::Decimal(_) => "DECIMAL",
             Self::Timestamp(_) => "TIMESTAMP",
             Self::Varchar(_) => "VARCHAR",
+            Self::Point(_, _) => "POINT",
+            Self::Varbinary(_) => "VARBINARY",
         }
         .to_string()
     }
@@ -213,6 +305,14 @@ impl<'a> Types<'a> {
         Self::Timestamp(0)
     }
 
+    pub fn point() -> Self {
+        Self::Point(0.0, 0.0)
+    }
+
+    pub fn varbinary() -> Self {
+        Self::Varbinary(Vec::new())
+    }
+
     pub fn owned() -> Self {
         Self::Varchar(Varlen::Owned(Str::MaxVal))
     }
@@ -234,6 +334,11 @@ impl<'a> Types<'a> {
                 Varlen::Owned(val) => *val = Str::Val("".to_string()),
                 Varlen::Borrowed(val) => *val = Str::Val(""),
             },
+            Self::Point(x, y) => {
+                *x = RSDB_DECIMAL_MIN;
+                *y = RSDB_DECIMAL_MIN;
+            }
+            Self::Varbinary(val) => val.clear(),
         }
         self
     }
@@ -251,6 +356,12 @@ impl<'a> Types<'a> {
                 Varlen::Owned(val) => *val = Str::MaxVal,
                 Varlen::Borrowed(val) => *val = Str::MaxVal,
             },
+            Self::Point(x, y) => {
+                *x = RSDB_DECIMAL_MAX;
+                *y = RSDB_DECIMAL_MAX;
+            }
+            // There is no largest byte string, so the value is kept.
+            Self::Varbinary(_) => (),
         }
         self
     }
@@ -264,6 +375,10 @@ impl<'a> Types<'a> {
             Self::BigInt(val) => *val = RSDB_INT64_NULL,
             Self::Decimal(val) => *val = RSDB_DECIMAL_NULL,
             Self::Timestamp(val) => *val = RSDB_TIMESTAMP_NULL,
+            Self::Point(x, y) => {
+                *x = RSDB_DECIMAL_NULL;
+                *y = RSDB_DECIMAL_NULL;
+            }
             _ => Err(Error::new(
                 ErrorKind::NotSupported,
                 "Invalid type for `null_val`",
@@ -280,7 +395,9 @@ impl<'a> Types<'a> {
             Self::Integer(val) => Varlen::Owned(Str::Val(val.to_string())),
             Self::BigInt(val) => Varlen::Owned(Str::Val(val.to_string())),
             Self::Decimal(val) => Varlen::Owned(Str::Val(val.to_string())),
-            Self::Timestamp(val) => Varlen::Owned(Str::Val(val.to_string())),
+            Self::Timestamp(val) => Varlen::Owned(Str::Val(format_timestamp(*val))),
+            Self::Point(x, y) => Varlen::Owned(Str::Val(point_to_string(*x, *y))),
+            Self::Varbinary(bytes) => Varlen::Owned(Str::Val(bytes_to_string(bytes)?)),
             _ => Err(unsupported!("Type error for to_varlen"))?,
         };
         Ok(varlen)
@@ -357,6 +474,49 @@ impl<'a> Types<'a> {
         };
         Ok(res)
     }
+
+    pub fn get_as_point(&self) -> Result<(f64, f64), Error> {
+        let res = match self {
+            Self::Point(x, y) => (*x, *y),
+            _ => Err(unsupported!("Invalid type for `get_as_point`"))?,
+        };
+        Ok(res)
+    }
+}
+
+// Formats a point as "(x, y)", which is also the format accepted by
+// |parse_point|.
+pub fn point_to_string(x: f64, y: f64) -> String {
+    format!("({}, {})", x, y)
+}
+
+// Reads bytes as a UTF-8 string, the inverse of casting a string to bytes.
+pub fn bytes_to_string(bytes: &[u8]) -> Result<String, Error> {
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| Error::new(ErrorKind::CannotCast, "Bytes are not valid UTF-8"))
+}
+
+// Formats bytes as lowercase hex digits prefixed by "0x", e.g. "0x1f00".
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
+    let mut s = String::from("0x");
+    for byte in bytes {
+        s.push_str(&format!("{:02x}", byte));
+    }
+    s
+}
+
+// Parses a point from "(x, y)"; the parentheses are optional.
+pub fn parse_point(s: &str) -> Result<(f64, f64), Error> {
+    let s = s.trim();
+    let s = s
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .unwrap_or(s);
+    let mut coords = s.split(',');
+    match (coords.next(), coords.next(), coords.next()) {
+        (Some(x), Some(y), None) => Ok((parse(x.trim())?, parse(y.trim())?)),
+        _ => Err(Error::new(ErrorKind::CannotParse, "Parse point failure")),
+    }
 }
 
 pub trait Operation: Sized {
@@ -372,9 +532,15 @@ pub trait Operation: Sized {
     fn divide(&self, other: &Self) -> Result<Self, Error>;
     fn modulo(&self, other: &Self) -> Result<Self, Error>;
     fn sqrt(&self) -> Result<Self, Error>;
+    fn distance(&self, other: &Self) -> Result<Self, Error>;
     fn min(&self, other: &Self) -> Result<Self, Error>;
     fn max(&self, other: &Self) -> Result<Self, Error>;
     fn null(&self, other: &Self) -> Result<Self, Error>;
+    // Boolean connectives with SQL's three-valued logic, where null means
+    // unknown, e.g. NULL AND FALSE is FALSE, but NULL AND TRUE is NULL.
+    fn and(&self, other: &Self) -> Result<Self, Error>;
+    fn or(&self, other: &Self) -> Result<Self, Error>;
+    fn not(&self) -> Result<Self, Error>;
     fn is_zero(&self) -> Result<bool, Error>;
     fn is_inlined(&self) -> bool;
     fn to_string(&self) -> String;
@@ -2,21 +2,32 @@ use crate::common::reinterpret;
 use crate::logging::error_logging::ErrorLogging;
 use crate::types::error::Error;
 use crate::types::error::ErrorKind;
+use crate::types::interner::Interner;
 use crate::types::limits::*;
 use crate::types::numeric_util::*;
+use crate::types::timestamp::format_timestamp;
+use crate::types::timestamp::parse_timestamp;
+use crate::types::timestamp::timestamp_micros;
+use crate::types::types::bytes_to_hex;
+use crate::types::types::bytes_to_string;
+use crate::types::types::parse_point;
+use crate::types::types::point_to_string;
 use crate::types::types::Operation;
 use crate::types::types::Str;
 use crate::types::types::Types;
 use crate::types::types::Varlen;
 use crate::types::varlen_util::*;
-use std::cmp::PartialEq;
+use std::cmp::Ordering;
 use std::fmt::Debug;
 use std::result::Result;
 
+// Nullness is kept apart from the content, so that every value of a type,
+// including the one |Types::null_val| returns, is a legal non-null value.
 #[derive(Clone, Debug)]
 pub struct Value<'a> {
     content: Types<'a>,
     size: usize,
+    null: bool,
 }
 
 impl<'a> Value<'a> {
@@ -24,13 +35,50 @@ impl<'a> Value<'a> {
         Value {
             size: get_size(&content),
             content: content,
+            null: false,
         }
     }
 
+    // Returns the null value of the type of |types|. Its content is the null
+    // value of the type, if it has one, so that it serializes as tuples
+    // without a null bitmap encode nulls.
+    pub fn null(types: Types<'a>) -> Self {
+        let content = types.clone().null_val().unwrap_or(types);
+        Value {
+            size: get_size(&content),
+            content,
+            null: true,
+        }
+    }
+
+    // Makes the value null if its content is the null value of its type, the
+    // way tuples without a null bitmap encode nulls.
+    pub fn decode_null_sentinel(mut self) -> Self {
+        self.null = is_null_sentinel(&self.content);
+        self
+    }
+
     pub fn len(&self) -> usize {
         self.size
     }
 
+    // Serializes the value into bytes of its own, e.g. to hash or sample it.
+    // Like in tuples, variable-length values get room for their length.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0; self.size + std::mem::size_of::<u64>()];
+        self.serialize_to(&mut bytes);
+        bytes
+    }
+
+    // Returns a copy owning its content; see |Types::to_static|.
+    pub fn to_static(&self) -> Value<'static> {
+        Value {
+            content: self.content.to_static(),
+            size: self.size,
+            null: self.null,
+        }
+    }
+
     pub fn borrow(&self) -> &'a Types {
         &self.content
     }
@@ -39,8 +87,22 @@ impl<'a> Value<'a> {
         &mut self.content
     }
 
+    // Same as |deserialize_from|, but a Varchar borrows its content from
+    // |interner| instead of allocating a new string.
+    // The caller needs to make sure that |src| is valid.
+    pub fn deserialize_interned(&mut self, src: &[u8], interner: &'a Interner) {
+        match &mut self.content {
+            Types::Varchar(vc) if reinterpret::read_i8(src) == 0 => {
+                let s = interner.intern(reinterpret::read_str(&src[1..]));
+                *vc = Varlen::Borrowed(Str::Val(s));
+                self.size = get_size(&self.content);
+            }
+            _ => self.deserialize_from(src),
+        }
+    }
+
     pub fn is_null(&self) -> bool {
-        self.size == RSDB_VALUE_NULL as usize
+        self.null
     }
 
     pub fn is_numeric(&self) -> bool {
@@ -80,9 +142,23 @@ impl<'a> Value<'a> {
                 | Types::Varchar(_) => true,
                 _ => false,
             },
+            Types::Timestamp(_) => matches!(other.content, Types::Timestamp(_) | Types::Varchar(_)),
             // Anything can be cast to a string!
             Types::Varchar(_) => true,
-            _ => false,
+            Types::Point(_, _) => match other.content {
+                Types::Point(_, _) | Types::Varchar(_) => true,
+                _ => false,
+            },
+            Types::Varbinary(_) => matches!(other.content, Types::Varbinary(_) | Types::Varchar(_)),
+        }
+    }
+
+    // See |Types::hash|. Nulls all hash alike, e.g. for grouping, although
+    // they are not equal to each other.
+    pub fn hash(&self) -> u64 {
+        match self.is_null() {
+            true => 0,
+            false => self.content.hash(),
         }
     }
 
@@ -93,6 +169,7 @@ impl<'a> Value<'a> {
     forward!(content, get_as_i64, Result<i64, Error>);
     forward!(content, get_as_u64, Result<u64, Error>);
     forward!(content, get_as_f64, Result<f64, Error>);
+    forward!(content, get_as_point, Result<(f64, f64), Error>);
 }
 
 impl<'a> Operation for Value<'a> {
@@ -143,8 +220,7 @@ impl<'a> Operation for Value<'a> {
     fn sqrt(&self) -> Result<Self, Error> {
         assert_numeric(self)?;
         if self.is_null() {
-            let null = Types::decimal().null_val()?;
-            return Ok(Value::new(null));
+            return Ok(Value::null(Types::decimal()));
         }
         let val = match self.content {
             Types::TinyInt(val) => val as f64,
@@ -161,6 +237,18 @@ impl<'a> Operation for Value<'a> {
         }
     }
 
+    // Euclidean distance between two points, as a Decimal. Either operand may
+    // be a string parsed as a point, as long as the other one is a point.
+    fn distance(&self, other: &Self) -> Result<Self, Error> {
+        assert_points(self, other)?;
+        if self.is_null() || other.is_null() {
+            return Ok(Value::null(Types::decimal()));
+        }
+        let (x1, y1) = point_of(self)?;
+        let (x2, y2) = point_of(other)?;
+        Ok(value!((x1 - x2).hypot(y1 - y2), Decimal))
+    }
+
     fn min(&self, other: &Self) -> Result<Self, Error> {
         assert_comparable(self, other)?;
         if self.is_null() || other.is_null() {
@@ -216,10 +304,38 @@ impl<'a> Operation for Value<'a> {
                 Err(unsupported!("Invalid type for `null` on Decimal")),
                 { [TinyInt, SmallInt, Integer, BigInt, Decimal], nullas!(self) }
             ),
+            Types::Point(_, _) => genmatch!(
+                other.content,
+                Err(unsupported!("Invalid type for `null` on Point")),
+                { [Point], nullas!(self) }
+            ),
             _ => Err(unsupported!("Invalid type for `null`")),
         }
     }
 
+    fn and(&self, other: &Self) -> Result<Self, Error> {
+        match (truth(self)?, truth(other)?) {
+            (Some(false), _) | (_, Some(false)) => Ok(value!(0, Boolean)),
+            (Some(true), Some(true)) => Ok(value!(1, Boolean)),
+            _ => nullas!(self),
+        }
+    }
+
+    fn or(&self, other: &Self) -> Result<Self, Error> {
+        match (truth(self)?, truth(other)?) {
+            (Some(true), _) | (_, Some(true)) => Ok(value!(1, Boolean)),
+            (Some(false), Some(false)) => Ok(value!(0, Boolean)),
+            _ => nullas!(self),
+        }
+    }
+
+    fn not(&self) -> Result<Self, Error> {
+        match truth(self)? {
+            Some(val) => Ok(value!(!val as i8, Boolean)),
+            None => nullas!(self),
+        }
+    }
+
     fn is_zero(&self) -> Result<bool, Error> {
         let res = match self.content {
             Types::TinyInt(val) => val == 0,
@@ -241,7 +357,9 @@ impl<'a> Operation for Value<'a> {
     fn to_string(&self) -> String {
         match self.content {
             Types::Boolean(val) => {
-                if val == 0 {
+                if self.is_null() {
+                    "boolean_null".to_string()
+                } else if val == 0 {
                     "false".to_string()
                 } else if val == 1 {
                     "true".to_string()
@@ -249,17 +367,25 @@ impl<'a> Operation for Value<'a> {
                     "boolean_null".to_string()
                 }
             }
-            Types::TinyInt(_) => string!(self, "tinyint"),
-            Types::SmallInt(_) => string!(self, "smallint"),
-            Types::Integer(_) => string!(self, "integer"),
-            Types::BigInt(_) => string!(self, "bigint"),
-            Types::Decimal(_) => string!(self, "decimal"),
-            Types::Timestamp(val) => string!(self, human_readable(val)),
+            Types::TinyInt(val) => string!(self, "tinyint", val),
+            Types::SmallInt(val) => string!(self, "smallint", val),
+            Types::Integer(val) => string!(self, "integer", val),
+            Types::BigInt(val) => string!(self, "bigint", val),
+            Types::Decimal(val) => string!(self, "decimal", val),
+            Types::Timestamp(val) => string!(self, "timestamp", format_timestamp(val)),
             Types::Varchar(ref varlen) => match varlen {
                 Varlen::Owned(Str::Val(val)) => val.clone(),
                 Varlen::Borrowed(Str::Val(val)) => val.to_string(),
                 _ => "varchar_max".to_string(),
             },
+            Types::Point(x, y) => {
+                if self.is_null() {
+                    "point".to_string()
+                } else {
+                    point_to_string(x, y)
+                }
+            }
+            Types::Varbinary(ref bytes) => bytes_to_hex(bytes),
         }
     }
 
@@ -284,6 +410,15 @@ impl<'a> Operation for Value<'a> {
                 }
                 _ => reinterpret::write_i8(dst, 1),
             },
+            Types::Point(x, y) => {
+                reinterpret::write_f64(dst, x);
+                reinterpret::write_f64(&mut dst[8..], y);
+            }
+            // The length, followed by the bytes.
+            Types::Varbinary(ref bytes) => {
+                reinterpret::write_u32(dst, bytes.len() as u32);
+                dst[4..(4 + bytes.len())].copy_from_slice(bytes);
+            }
         }
     }
 
@@ -306,7 +441,19 @@ impl<'a> Operation for Value<'a> {
                     *vc = Varlen::Owned(Str::MaxVal);
                 }
             }
+            Types::Point(x, y) => {
+                *x = reinterpret::read_f64(src);
+                *y = reinterpret::read_f64(&src[8..]);
+            }
+            Types::Varbinary(bytes) => {
+                let len = reinterpret::read_u32(src) as usize;
+                *bytes = src[4..(4 + len)].to_vec();
+            }
         }
+        // Nullness is not serialized; the tuple or key holding the value
+        // records it.
+        self.size = get_size(&self.content);
+        self.null = false;
     }
 
     fn cast_to(&self, dst: &mut Self) -> Result<(), Error> {
@@ -323,8 +470,8 @@ impl<'a> Operation for Value<'a> {
             Types::Decimal(src) => castnum!(dst.content, src, loss_cast, "decimal"),
             Types::Timestamp(src) => match &mut dst.content {
                 Types::Timestamp(val) => *val = src,
-                Types::Varchar(val) => *val = Varlen::Owned(Str::Val(src.to_string())),
-                _ => Err(unsupported!("Cannot cast boolean to given type"))?,
+                Types::Varchar(val) => *val = Varlen::Owned(Str::Val(format_timestamp(src))),
+                _ => Err(unsupported!("Cannot cast timestamp to given type"))?,
             },
             Types::Varchar(ref varlen) => match &mut dst.content {
                 Types::Boolean(val) => *val = parse::<_, bool>(varlen.borrow()?)? as i8,
@@ -333,8 +480,27 @@ impl<'a> Operation for Value<'a> {
                 Types::Integer(val) => *val = parse(varlen.borrow()?)?,
                 Types::BigInt(val) => *val = parse(varlen.borrow()?)?,
                 Types::Decimal(val) => *val = parse(varlen.borrow()?)?,
-                Types::Timestamp(val) => *val = parse(varlen.borrow()?)?,
+                Types::Timestamp(val) => *val = parse_timestamp(varlen.borrow()?)?,
                 Types::Varchar(val) => *val = varlen.clone(),
+                Types::Point(x, y) => {
+                    let (px, py) = parse_point(varlen.borrow()?)?;
+                    *x = px;
+                    *y = py;
+                }
+                Types::Varbinary(val) => *val = varlen.borrow()?.as_bytes().to_vec(),
+            },
+            Types::Point(x, y) => match &mut dst.content {
+                Types::Point(dx, dy) => {
+                    *dx = x;
+                    *dy = y;
+                }
+                Types::Varchar(val) => *val = Varlen::Owned(Str::Val(point_to_string(x, y))),
+                _ => Err(unsupported!("Cannot cast point to given type"))?,
+            },
+            Types::Varbinary(ref bytes) => match &mut dst.content {
+                Types::Varbinary(val) => *val = bytes.clone(),
+                Types::Varchar(val) => *val = Varlen::Owned(Str::Val(bytes_to_string(bytes)?)),
+                _ => Err(unsupported!("Cannot cast varbinary to given type"))?,
             },
         }
         Ok(())
@@ -353,6 +519,15 @@ fn assert_numeric(val: &Value) -> Result<(), Error> {
     }
 }
 
+// Returns None for null. Only booleans have a truth value.
+fn truth(val: &Value) -> Result<Option<bool>, Error> {
+    match val.content {
+        Types::Boolean(_) if val.is_null() => Ok(None),
+        Types::Boolean(b) => Ok(Some(b != 0)),
+        _ => Err(unsupported!("Non boolean")),
+    }
+}
+
 fn assert_comparable(lhs: &Value, rhs: &Value) -> Result<(), Error> {
     if !lhs.is_comparable_to(rhs) {
         Err(unsupported!("Cannot compare"))
@@ -361,6 +536,28 @@ fn assert_comparable(lhs: &Value, rhs: &Value) -> Result<(), Error> {
     }
 }
 
+// Both operands are points, or one is a point and the other a string.
+fn assert_points(lhs: &Value, rhs: &Value) -> Result<(), Error> {
+    match (&lhs.content, &rhs.content) {
+        (Types::Point(_, _), Types::Point(_, _))
+        | (Types::Point(_, _), Types::Varchar(_))
+        | (Types::Varchar(_), Types::Point(_, _)) => Ok(()),
+        _ => Err(unsupported!("Invalid type for `distance`")),
+    }
+}
+
+// Returns the coordinates of |val|, parsing it if it is a string.
+fn point_of(val: &Value) -> Result<(f64, f64), Error> {
+    match val.content {
+        Types::Point(x, y) => Ok((x, y)),
+        _ => {
+            let mut point = Value::new(Types::point());
+            val.cast_to(&mut point)?;
+            point.get_as_point()
+        }
+    }
+}
+
 fn varlen_value_cmp(lhs: &Varlen, rhs: &Value) -> Result<i8, Error> {
     let res = match rhs.content {
         Types::Varchar(ref varlen) => varlen_cmp(lhs, varlen),
@@ -369,59 +566,26 @@ fn varlen_value_cmp(lhs: &Varlen, rhs: &Value) -> Result<i8, Error> {
     Ok(res)
 }
 
-fn human_readable(mut tm: u64) -> String {
-    let micro = (tm % 1000000) as u32;
-    tm /= 1000000;
-    let mut second = (tm % 100000) as u32;
-    let sec = (second % 60) as u16;
-    second /= 60;
-    let min = (second % 60) as u16;
-    second /= 60;
-    let hour = (second % 24) as u16;
-    tm /= 100000;
-    let year = (tm % 10000) as u16;
-    tm /= 10000;
-    let mut tz = (tm % 27) as i32;
-    tz -= 12;
-    tm /= 27;
-    let day = (tm % 32) as u16;
-    tm /= 32;
-    let month = tm as u16;
-    let mut s = format!(
-        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}",
-        year, month, day, hour, min, sec, micro
-    );
-    if tz >= 0 {
-        s.push('+');
-    } else {
-        s.push('-');
-    }
-    if tz < 0 {
-        tz = -tz;
-    }
-    s.push_str(&format!("{:02}", tz));
-    s
-}
-
 fn get_size<'a>(content: &Types<'a>) -> usize {
-    let size = content.size();
     match content {
-        Types::Boolean(val) => choose_size(val, &RSDB_BOOLEAN_NULL, size),
-        Types::TinyInt(val) => choose_size(val, &RSDB_INT8_NULL, size),
-        Types::SmallInt(val) => choose_size(val, &RSDB_INT16_NULL, size),
-        Types::Integer(val) => choose_size(val, &RSDB_INT32_NULL, size),
-        Types::BigInt(val) => choose_size(val, &RSDB_INT64_NULL, size),
-        Types::Timestamp(val) => choose_size(val, &RSDB_TIMESTAMP_NULL, size),
-        Types::Decimal(val) => choose_size(val, &RSDB_DECIMAL_NULL, size),
         Types::Varchar(val) => val.len(),
+        Types::Varbinary(val) => val.len(),
+        _ => content.size(),
     }
 }
 
-fn choose_size<T: PartialEq>(val: &T, null: &T, size: usize) -> usize {
-    if val == null {
-        RSDB_VALUE_NULL as usize
-    } else {
-        size
+// Whether |content| is the null value of its type; see |Types::null_val|.
+pub(crate) fn is_null_sentinel<'a>(content: &Types<'a>) -> bool {
+    match content {
+        Types::Boolean(val) => *val == RSDB_BOOLEAN_NULL,
+        Types::TinyInt(val) => *val == RSDB_INT8_NULL,
+        Types::SmallInt(val) => *val == RSDB_INT16_NULL,
+        Types::Integer(val) => *val == RSDB_INT32_NULL,
+        Types::BigInt(val) => *val == RSDB_INT64_NULL,
+        Types::Timestamp(val) => *val == RSDB_TIMESTAMP_NULL,
+        Types::Decimal(val) => *val == RSDB_DECIMAL_NULL,
+        Types::Point(x, _) => *x == RSDB_DECIMAL_NULL,
+        Types::Varchar(_) | Types::Varbinary(_) => false,
     }
 }
 
@@ -561,8 +725,8 @@ mod tests {
 
     #[test]
     fn null_and_checks() {
-        let nullint = Value::new(Types::integer().null_val().unwrap());
-        let nulldec = Value::new(Types::decimal().null_val().unwrap());
+        let nullint = Value::null(Types::integer());
+        let nulldec = Value::null(Types::decimal());
         assert!(nullint.is_integer());
         assert!(!nulldec.is_integer());
         assert!(nullint.is_numeric());
@@ -595,8 +759,8 @@ mod tests {
         assert_eq!(Some(true), int1.min(&dec1).unwrap().eq(&int1));
         assert_eq!(Some(true), int1.max(&dec1).unwrap().eq(&dec1));
 
-        let nullint = Value::new(Types::integer().null_val().unwrap());
-        let nulldec = Value::new(Types::decimal().null_val().unwrap());
+        let nullint = Value::null(Types::integer());
+        let nulldec = Value::null(Types::decimal());
         assert!(nullint.min(&int1).unwrap().is_null());
         assert!(nullint.max(&int2).unwrap().is_null());
         assert!(int2.min(&nullint).unwrap().is_null());
@@ -641,6 +805,162 @@ mod tests {
         }
     }
 
+    #[test]
+    fn point_test() {
+        let p1 = Value::new(Types::Point(1.0, 2.0));
+        let p2 = Value::new(Types::Point(4.0, 6.0));
+        let p3 = value!(Varlen::Borrowed(Str::Val("(1, 2)")), Varchar);
+        assert_eq!(Some(true), p1.eq(&p3));
+        assert_eq!(Some(false), p1.eq(&p2));
+        assert_eq!(Some(true), p1.lt(&p2));
+        assert_eq!(Some(true), p1.distance(&p2).unwrap().eq(&value!(5.0, Decimal)));
+        assert_eq!(Some(true), p2.distance(&p3).unwrap().eq(&value!(5.0, Decimal)));
+        assert_eq!(Some(true), p3.distance(&p2).unwrap().eq(&value!(5.0, Decimal)));
+        let integer = value!(1, Integer);
+        assert!(p1.distance(&integer).is_err());
+        assert!(integer.distance(&p1).is_err());
+        assert!(p3.distance(&integer).is_err());
+        assert!(integer.distance(&p3).is_err());
+        assert!(p3.distance(&p3).is_err());
+
+        let nullpoint = Value::null(Types::point());
+        assert!(nullpoint.is_null());
+        assert!(p1.distance(&nullpoint).unwrap().is_null());
+        assert!(p1.eq(&nullpoint).is_none());
+
+        let mut buffer = [0; 16];
+        let mut pr = Value::new(Types::point());
+        p2.serialize_to(&mut buffer);
+        pr.deserialize_from(&buffer);
+        assert_eq!((4.0, 6.0), pr.get_as_point().unwrap());
+
+        let mut string = Value::new(Types::owned());
+        assert!(p2.cast_to(&mut string).is_ok());
+        assert_eq!("(4, 6)", string.to_string());
+        assert!(value!(Varlen::Borrowed(Str::Val("(1, 2, 3)")), Varchar)
+            .cast_to(&mut pr)
+            .is_err());
+    }
+
+    #[test]
+    fn varbinary_test() {
+        let b1 = value!(vec![0x00, 0x1f, 0xff], Varbinary);
+        let b2 = value!(vec![0x00, 0x20], Varbinary);
+        assert_eq!(3, b1.len());
+        assert!(!b1.is_inlined());
+        assert_eq!("0x001fff", b1.to_string());
+        assert_eq!(Some(true), b1.lt(&b2));
+        assert_eq!(Some(false), b1.eq(&b2));
+        assert_eq!(Some(true), b1.max(&b2).unwrap().eq(&b2));
+        assert!(b1.eq(&value!(1, Integer)).is_none());
+
+        let mut buffer = [0; 16];
+        let mut br = Value::new(Types::varbinary());
+        b1.serialize_to(&mut buffer);
+        br.deserialize_from(&buffer);
+        assert_eq!(Some(true), b1.eq(&br));
+        assert_eq!(Some(&[0x00, 0x1f, 0xff][..]), br.borrow().data());
+
+        // Strings cast to their UTF-8 bytes, and back.
+        let hello = value!(Varlen::Borrowed(Str::Val("hello")), Varchar);
+        assert!(hello.cast_to(&mut br).is_ok());
+        assert_eq!("0x68656c6c6f", br.to_string());
+        assert_eq!(Some(true), br.eq(&hello));
+        let mut string = Value::new(Types::owned());
+        assert!(br.cast_to(&mut string).is_ok());
+        assert_eq!("hello", string.to_string());
+        assert!(b1.cast_to(&mut string).is_err());
+        assert!(b1.cast_to(&mut Value::new(Types::integer())).is_err());
+    }
+
+    #[test]
+    fn timestamp_test() {
+        let string = value!(Varlen::Borrowed(Str::Val("2024-01-15 10:30:00+02")), Varchar);
+        let mut t1 = Value::new(Types::timestamp());
+        assert!(string.cast_to(&mut t1).is_ok());
+        assert_eq!("2024-01-15 10:30:00.000000+02", t1.to_string());
+        let mut back = Value::new(Types::owned());
+        assert!(t1.cast_to(&mut back).is_ok());
+        assert_eq!(t1.to_string(), back.to_string());
+
+        // Compared chronologically, across offsets.
+        let t2 = value!(parse_timestamp("2023-12-31 23:00:00Z").unwrap(), Timestamp);
+        let t3 = value!(parse_timestamp("2024-01-15 08:30:00Z").unwrap(), Timestamp);
+        assert_eq!(Some(true), t2.lt(&t1));
+        assert_eq!(Some(true), t3.eq(&t1));
+        assert_eq!(Some(true), t1.le(&string));
+        assert!(t1.eq(&value!(1, Integer)).is_none());
+
+        let null = Value::null(Types::timestamp());
+        assert_eq!("timestamp", null.to_string());
+        assert!(t1.lt(&null).is_none());
+        let invalid = value!(Varlen::Borrowed(Str::Val("2024-02-30")), Varchar);
+        assert!(invalid.cast_to(&mut t1).is_err());
+    }
+
+    #[test]
+    fn three_valued_logic() {
+        let t = value!(1, Boolean);
+        let f = value!(0, Boolean);
+        let n = Value::null(Types::boolean());
+        let truth = |val: Result<Value, Error>| {
+            let val = val.unwrap();
+            match val.is_null() {
+                true => None,
+                false => Some(val.get_as_bool().unwrap() != 0),
+            }
+        };
+        assert_eq!(Some(true), truth(t.and(&t)));
+        assert_eq!(Some(false), truth(t.and(&f)));
+        assert_eq!(None, truth(t.and(&n)));
+        assert_eq!(Some(false), truth(n.and(&f)));
+        assert_eq!(None, truth(n.and(&n)));
+        assert_eq!(Some(true), truth(f.or(&t)));
+        assert_eq!(Some(false), truth(f.or(&f)));
+        assert_eq!(None, truth(f.or(&n)));
+        assert_eq!(Some(true), truth(n.or(&t)));
+        assert_eq!(None, truth(n.or(&n)));
+        assert_eq!(Some(false), truth(t.not()));
+        assert_eq!(Some(true), truth(f.not()));
+        assert_eq!(None, truth(n.not()));
+
+        let int = value!(1, Integer);
+        assert!(int.not().is_err());
+        assert!(t.and(&int).is_err());
+        assert!(int.or(&t).is_err());
+    }
+
+    #[test]
+    fn hash_test() {
+        // Equal values of different numeric types hash alike.
+        let five = value!(5, BigInt).hash();
+        assert_eq!(five, value!(5, TinyInt).hash());
+        assert_eq!(five, value!(5, Integer).hash());
+        assert_eq!(five, value!(5.0, Decimal).hash());
+        assert_ne!(five, value!(6, BigInt).hash());
+        assert_ne!(five, value!(5.5, Decimal).hash());
+        assert_eq!(value!(0.0, Decimal).hash(), value!(-0.0, Decimal).hash());
+
+        // Timestamps hash by instant, across offsets.
+        let t1 = value!(parse_timestamp("2024-01-15 10:30:00+02").unwrap(), Timestamp);
+        let t2 = value!(parse_timestamp("2024-01-15 08:30:00Z").unwrap(), Timestamp);
+        assert_eq!(t1.hash(), t2.hash());
+
+        let s1 = value!(Varlen::Borrowed(Str::Val("abc")), Varchar);
+        let s2 = value!(Varlen::Owned(Str::Val("abc".to_string())), Varchar);
+        assert_eq!(s1.hash(), s2.hash());
+        assert_ne!(s1.hash(), value!(b"abc".to_vec(), Varbinary).hash());
+        assert_ne!(value!(1, Boolean).hash(), value!(1, TinyInt).hash());
+
+        // Nulls hash alike, whatever their type.
+        let null = Value::null(Types::integer());
+        assert_eq!(null.hash(), Value::null(Types::decimal()).hash());
+
+        // Stable across runs.
+        assert_eq!(five, value!(5, SmallInt).hash());
+        assert_eq!(0x0d45_9abf_f48a_c59f, five);
+    }
+
     #[test]
     fn cast_test() {
         let integer = value!(66666, Integer);